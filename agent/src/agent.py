@@ -134,6 +134,10 @@ class AgentConfig:
         self.image_model: str = init_data.get("image_model", "") or ""
         self.image_api_key: str = init_data.get("image_api_key", "") or ""
         self.image_endpoint_url: str | None = init_data.get("image_endpoint_url")
+        # Completion tokens between live usage updates (0 disables them)
+        self.token_usage_update_interval_tokens: int = int(
+            init_data.get("token_usage_update_interval_tokens") or 0
+        )
 
 
 def build_message_history(history: list[dict[str, Any]]) -> list[BaseMessage]:
@@ -658,7 +662,7 @@ class ChatAgent:
                              None means use provider defaults.
 
         Yields StreamEvent objects for: assistant_delta, thinking_delta,
-        tool_call, tool_result, complete, error.
+        tool_call, tool_result, token_usage_update, complete, error.
         """
         self._cancelled = False
         self.messages.append(HumanMessage(content=content))
@@ -717,6 +721,11 @@ class ChatAgent:
             logger.info("Deep thinking enabled (provider=%s, thinking_budget=%s), bound kwargs: %s",
                         self.config.provider, effective_budget, getattr(llm, 'kwargs', {}))
 
+        usage_interval = self.config.token_usage_update_interval_tokens
+        usage_total = 0  # Token usage of finished iterations
+        usage_completion = 0
+        last_reported_completion = 0
+
         iteration = 0
         while MAX_ITERATIONS <= 0 or iteration < MAX_ITERATIONS:
             iteration += 1
@@ -796,6 +805,22 @@ class ChatAgent:
                     for tc_chunk in chunk.tool_call_chunks:
                         _accumulate_tool_call(tool_calls, tc_chunk)
 
+                # Report running usage once enough new completion tokens arrive
+                usage = getattr(accumulated_chunk, "usage_metadata", None)
+                if usage_interval > 0 and usage:
+                    completion = usage_completion + usage.get("output_tokens", 0)
+                    if completion - last_reported_completion >= usage_interval:
+                        last_reported_completion = completion
+                        yield StreamEvent("token_usage_update", {
+                            "total_tokens": usage_total + usage.get("total_tokens", 0),
+                            "completion_tokens": completion,
+                        })
+
+            usage = getattr(accumulated_chunk, "usage_metadata", None)
+            if usage:
+                usage_total += usage.get("total_tokens", 0)
+                usage_completion += usage.get("output_tokens", 0)
+
             # Filter out ghost tool call entries (empty name from index gaps)
             if deep_thinking:
                 logger.info("Thinking total chars: %d", thinking_total)
@@ -31,8 +31,12 @@ BACKEND_WS_URL = os.environ.get(
     "BACKEND_WS_URL", "ws://host.docker.internal:3001/internal/ws"
 )
 CONTAINER_TOKEN = os.environ.get("CONTAINER_TOKEN", "")
+CONTAINER_CLAIM_FILE = os.environ.get("CONTAINER_CLAIM_FILE", "")
+CLAIM_POLL_INTERVAL_SECS = 0.2
 MAX_RECONNECT_ATTEMPTS = 5
 DEFAULT_MAX_WS_MESSAGE_BYTES = 8 * 1024 * 1024
+# Init fields an ``update_config`` message may replace (rotated API keys).
+UPDATABLE_CONFIG_KEYS = frozenset({"api_key", "subagent_api_key", "image_api_key"})
 
 
 def _read_max_ws_message_bytes() -> int:
@@ -56,6 +60,25 @@ def _read_max_ws_message_bytes() -> int:
 MAX_WS_MESSAGE_BYTES = _read_max_ws_message_bytes()
 
 
+async def _wait_for_claim(
+    path: str, poll_interval: float = CLAIM_POLL_INTERVAL_SECS
+) -> str:
+    """Wait until the backend assigns this pre-warmed container to a conversation.
+
+    The backend writes the container token followed by a newline; the newline
+    marks the file as complete.
+    """
+    while True:
+        try:
+            with open(path, encoding="utf-8") as f:
+                content = f.read()
+            if content.endswith("\n") and content.strip():
+                return content.strip()
+        except FileNotFoundError:
+            pass
+        await asyncio.sleep(poll_interval)
+
+
 class AgentSession:
     """Manages the WebSocket connection and agent lifecycle."""
 
@@ -64,6 +87,7 @@ class AgentSession:
         self.token = token
         self.agent: ChatAgent | None = None
         self.mcp_manager: McpManager = McpManager()
+        self._init_msg: dict | None = None
         self._current_task: asyncio.Task | None = None
         self._shutdown = False
 
@@ -112,11 +136,14 @@ class AgentSession:
             self._handle_truncate_history(msg)
         elif msg_type == "cancel":
             self._handle_cancel()
+        elif msg_type == "update_config":
+            await self._handle_update_config(msg)
         else:
             logger.warning("Unknown message type: %s", msg_type)
 
     async def _handle_init(self, msg: dict) -> None:
         """Initialize the agent with config from backend."""
+        self._init_msg = msg
         config = AgentConfig(msg)
         logger.info(
             "Initialized for conversation %s (provider=%s, model=%s)",
@@ -163,6 +190,19 @@ class AgentSession:
 
         self.agent = ChatAgent(config, tools=tools)
 
+    async def _handle_update_config(self, msg: dict) -> None:
+        """Rebuild the agent with rotated credentials, keeping its history."""
+        if self.agent is None or self._init_msg is None:
+            logger.warning("update_config received before init")
+            return
+        updates = {k: v for k, v in msg.items() if k in UPDATABLE_CONFIG_KEYS}
+        if not updates:
+            return
+        messages = self.agent.messages
+        await self._handle_init({**self._init_msg, **updates})
+        self.agent.messages = messages
+        logger.info("Applied config update: %s", ", ".join(sorted(updates)))
+
     async def _handle_user_message(self, msg: dict) -> None:
         """Process a user message through the agent."""
         content = msg.get("content", "")
@@ -300,7 +340,12 @@ async def main() -> None:
         stream=sys.stdout,
     )
 
-    session = AgentSession(BACKEND_WS_URL, CONTAINER_TOKEN)
+    token = CONTAINER_TOKEN
+    if not token and CONTAINER_CLAIM_FILE:
+        logger.info("Waiting for claim at %s", CONTAINER_CLAIM_FILE)
+        token = await _wait_for_claim(CONTAINER_CLAIM_FILE)
+
+    session = AgentSession(BACKEND_WS_URL, token)
 
     loop = asyncio.get_running_loop()
     if sys.platform != "win32":
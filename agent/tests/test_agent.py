@@ -852,6 +852,50 @@ class TestChatAgent:
         assert isinstance(agent.messages[1], HumanMessage)
         assert agent.messages[1].content == "test"
 
+    @staticmethod
+    def _usage_stream(chunks: int):
+        from langchain_core.messages import AIMessageChunk
+
+        async def fake_astream(messages):
+            for i in range(chunks):
+                yield AIMessageChunk(
+                    content="x",
+                    tool_call_chunks=[],
+                    usage_metadata={
+                        "input_tokens": 10 if i == 0 else 0,
+                        "output_tokens": 3,
+                        "total_tokens": 13 if i == 0 else 3,
+                    },
+                )
+        return fake_astream
+
+    @patch("src.agent.create_chat_model")
+    async def test_token_usage_updates_follow_interval(self, mock_create):
+        mock_llm = AsyncMock()
+        mock_llm.astream = self._usage_stream(4)
+        mock_create.return_value = mock_llm
+
+        agent = ChatAgent(self._make_config(token_usage_update_interval_tokens=5))
+        events = [e async for e in agent.handle_message("test")]
+
+        updates = [e.data for e in events if e.type == "token_usage_update"]
+        assert updates == [
+            {"total_tokens": 16, "completion_tokens": 6},
+            {"total_tokens": 22, "completion_tokens": 12},
+        ]
+        assert events[-1].type == "complete"
+
+    @patch("src.agent.create_chat_model")
+    async def test_token_usage_updates_disabled_by_default(self, mock_create):
+        mock_llm = AsyncMock()
+        mock_llm.astream = self._usage_stream(4)
+        mock_create.return_value = mock_llm
+
+        agent = ChatAgent(self._make_config())
+        events = [e async for e in agent.handle_message("test")]
+
+        assert not any(e.type == "token_usage_update" for e in events)
+
     @patch("src.agent.create_chat_model")
     async def test_handle_message_error_yields_error_event(self, mock_create):
         mock_llm = AsyncMock()
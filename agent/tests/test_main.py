@@ -14,6 +14,7 @@ from src.main import (
     DEFAULT_MAX_WS_MESSAGE_BYTES,
     MAX_WS_MESSAGE_BYTES,
     _read_max_ws_message_bytes,
+    _wait_for_claim,
 )
 from src.prompts.tools import TOOL_DESCRIPTIONS
 
@@ -27,6 +28,22 @@ class TestAgentSession:
         monkeypatch.setenv("MAX_WS_MESSAGE_BYTES", "invalid-number")
         assert _read_max_ws_message_bytes() == DEFAULT_MAX_WS_MESSAGE_BYTES
 
+    async def test_wait_for_claim_returns_token_once_complete(self, tmp_path):
+        claim = tmp_path / "claim-token"
+
+        async def write_claim():
+            await asyncio.sleep(0.02)
+            claim.write_text("partial")
+            await asyncio.sleep(0.02)
+            claim.write_text("tok-123\n")
+
+        writer = asyncio.create_task(write_claim())
+        token = await asyncio.wait_for(
+            _wait_for_claim(str(claim), poll_interval=0.01), timeout=2
+        )
+        await writer
+        assert token == "tok-123"
+
     def test_init(self):
         session = AgentSession("ws://localhost:3001/internal/ws", "token123")
         assert session.ws_url == "ws://localhost:3001/internal/ws"
@@ -170,6 +187,51 @@ class TestAgentSession:
         await session._handle_message(json.dumps(msg))
         session._handle_truncate_history.assert_called_once()
 
+    async def test_handle_message_dispatches_update_config(self):
+        session = AgentSession("ws://test", "tok")
+        session._handle_update_config = AsyncMock()
+        msg = {"type": "update_config", "api_key": "new"}
+        await session._handle_message(json.dumps(msg))
+        session._handle_update_config.assert_called_once()
+
+    async def test_handle_update_config_rebuilds_agent_with_new_keys(self):
+        session = AgentSession("ws://test", "tok")
+        session.ws = AsyncMock()
+
+        def fake_agent(config, tools=()):
+            return MagicMock(config=config, messages=["system"])
+
+        with patch("src.main.ChatAgent", side_effect=fake_agent):
+            await session._handle_init({
+                "conversation_id": "conv-1",
+                "provider": "openai",
+                "model": "gpt-4o",
+                "api_key": "old-key",
+                "tools_enabled": False,
+            })
+            session.agent.messages.append("earlier turn")
+            old_agent = session.agent
+
+            await session._handle_update_config({
+                "type": "update_config",
+                "api_key": "new-key",
+                "image_api_key": "new-image-key",
+                "model": "ignored",
+            })
+
+        assert session.agent is not old_agent
+        assert session.agent.config.api_key == "new-key"
+        assert session.agent.config.subagent_api_key == "new-key"
+        assert session.agent.config.image_api_key == "new-image-key"
+        assert session.agent.config.model == "gpt-4o"
+        assert session.agent.messages == ["system", "earlier turn"]
+
+    async def test_handle_update_config_before_init(self):
+        session = AgentSession("ws://test", "tok")
+        # Should not raise
+        await session._handle_update_config({"type": "update_config", "api_key": "k"})
+        assert session.agent is None
+
     async def test_handle_truncate_history_calls_agent(self):
         session = AgentSession("ws://test", "tok")
         session.agent = MagicMock()
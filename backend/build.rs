@@ -0,0 +1,5 @@
+// Re-embed migrations when a file under ../migrations is added or changed;
+// `sqlx::migrate!` alone only notices them when Rust sources change.
+fn main() {
+    println!("cargo:rerun-if-changed=../migrations");
+}
@@ -1,16 +1,25 @@
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    routing::get,
+    routing::{delete, get, post, put},
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
 use validator::Validate;
 
-use crate::auth::middleware::{AdminOnly, AppState};
+use crate::auth::middleware::{AdminOnly, AppState, AuthUser};
+use crate::auth::password;
+use crate::config::Config;
 use crate::db;
+use crate::db::audit_log::AuditEvent;
+use crate::db::refresh_tokens::SessionClient;
+use crate::docker::manager::{ActiveContainer, DockerError, ExecResult, exec_command_allowed};
 use crate::error::AppError;
+use crate::mcp::validation::{McpServerConfig, validate_mcp_server_config};
 
 pub fn router() -> Router<Arc<AppState>> {
     Router::new()
@@ -24,6 +33,47 @@ pub fn router() -> Router<Arc<AppState>> {
                 .put(update_mcp_server)
                 .delete(delete_mcp_server),
         )
+        .route("/mcp-servers/{id}/clone", post(clone_mcp_server))
+        .route(
+            "/provider-pricing",
+            get(list_provider_pricing).post(upsert_provider_pricing),
+        )
+        .route(
+            "/provider-pricing/{provider_type}/{model}",
+            delete(delete_provider_pricing),
+        )
+        .route("/broadcast", post(broadcast))
+        .route("/announce", post(announce))
+        .route("/stats", get(system_stats))
+        .route("/audit-log", get(list_audit_log))
+        .route("/ws-metrics", get(ws_metrics))
+        .route("/ws-connections", get(ws_connections))
+        .route(
+            "/maintenance/repair-orphaned-parts",
+            post(repair_orphaned_parts),
+        )
+        .route(
+            "/maintenance/backfill-token-usage",
+            post(backfill_token_usage),
+        )
+        .route("/maintenance/merge-text-parts", post(merge_text_parts))
+        .route("/users", get(list_users).post(create_user))
+        .route("/users/{id}", put(update_user).delete(delete_user))
+        .route("/users/{id}/deactivate", post(deactivate_user))
+        .route("/users/{id}/quota", get(get_user_quota).put(set_user_quota))
+        .route(
+            "/conversations/auto-archive",
+            post(auto_archive_conversations),
+        )
+        .route("/containers", get(list_containers))
+        .route(
+            "/containers/{conversation_id}",
+            delete(force_stop_container),
+        )
+        .route(
+            "/containers/{conversation_id}/exec",
+            post(exec_in_container),
+        )
 }
 
 #[derive(Serialize)]
@@ -67,16 +117,7 @@ async fn list_mcp_servers(
     Ok(Json(servers.into_iter().map(Into::into).collect()))
 }
 
-fn validate_transport(transport: &str) -> Result<(), validator::ValidationError> {
-    if transport == "stdio" || transport == "sse" {
-        Ok(())
-    } else {
-        Err(validator::ValidationError::new("invalid_transport")
-            .with_message("Transport must be 'stdio' or 'sse'".into()))
-    }
-}
-
-fn validate_read_only_overrides(raw: Option<&str>) -> Result<Option<String>, AppError> {
+pub(crate) fn validate_read_only_overrides(raw: Option<&str>) -> Result<Option<String>, AppError> {
     let Some(raw_str) = raw else {
         return Ok(None);
     };
@@ -114,13 +155,13 @@ pub struct CreateMcpServerRequest {
     #[validate(length(min = 1, message = "Name is required"))]
     pub name: String,
     pub description: Option<String>,
-    #[validate(custom(function = "validate_transport"))]
     pub transport: String,
     pub command: Option<String>,
     pub args: Option<String>,
     pub url: Option<String>,
     pub env_vars: Option<String>,
     pub read_only_overrides: Option<String>,
+    pub is_enabled: Option<bool>,
 }
 
 async fn create_mcp_server(
@@ -130,6 +171,14 @@ async fn create_mcp_server(
 ) -> Result<(StatusCode, Json<McpServerDetailResponse>), AppError> {
     req.validate()
         .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    validate_mcp_server_config(&McpServerConfig {
+        transport: &req.transport,
+        command: req.command.as_deref(),
+        args: req.args.as_deref(),
+        url: req.url.as_deref(),
+        env_vars: req.env_vars.as_deref(),
+    })
+    .map_err(AppError::UnprocessableEntity)?;
     let read_only_overrides = validate_read_only_overrides(req.read_only_overrides.as_deref())?;
 
     let server = db::mcp_servers::create_mcp_server_with_overrides(
@@ -142,7 +191,7 @@ async fn create_mcp_server(
         req.url.as_deref(),
         req.env_vars.as_deref(),
         read_only_overrides.as_deref(),
-        true,
+        req.is_enabled.unwrap_or(true),
     )
     .await?;
 
@@ -160,6 +209,34 @@ async fn get_mcp_server(
     Ok(Json(server.into()))
 }
 
+#[derive(Deserialize, Validate)]
+pub struct CloneMcpServerRequest {
+    #[validate(length(min = 1, message = "Name is required"))]
+    pub name: String,
+}
+
+async fn clone_mcp_server(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Path(id): Path<String>,
+    Json(req): Json<CloneMcpServerRequest>,
+) -> Result<(StatusCode, Json<McpServerDetailResponse>), AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let server = db::mcp_servers::clone_mcp_server(&state.db, &id, &req.name)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                AppError::Conflict(format!("MCP server '{}' already exists", req.name))
+            }
+            e => e.into(),
+        })?
+        .ok_or(AppError::NotFound)?;
+
+    Ok((StatusCode::CREATED, Json(server.into())))
+}
+
 #[derive(Deserialize)]
 pub struct UpdateMcpServerRequest {
     pub name: Option<String>,
@@ -190,6 +267,14 @@ async fn update_mcp_server(
 
     let name = req.name.as_deref().unwrap_or(&existing.name);
     let transport = req.transport.as_deref().unwrap_or(&existing.transport);
+    let config = McpServerConfig {
+        transport,
+        command: req.command.as_deref().or(existing.command.as_deref()),
+        args: req.args.as_deref().or(existing.args.as_deref()),
+        url: req.url.as_deref().or(existing.url.as_deref()),
+        env_vars: req.env_vars.as_deref().or(existing.env_vars.as_deref()),
+    };
+    validate_mcp_server_config(&config).map_err(AppError::UnprocessableEntity)?;
     let is_enabled = req.is_enabled.unwrap_or(existing.is_enabled);
 
     let server = db::mcp_servers::update_mcp_server_with_overrides(
@@ -200,10 +285,10 @@ async fn update_mcp_server(
             .as_deref()
             .or(existing.description.as_deref()),
         transport,
-        req.command.as_deref().or(existing.command.as_deref()),
-        req.args.as_deref().or(existing.args.as_deref()),
-        req.url.as_deref().or(existing.url.as_deref()),
-        req.env_vars.as_deref().or(existing.env_vars.as_deref()),
+        config.command,
+        config.args,
+        config.url,
+        config.env_vars,
         read_only_overrides.as_deref(),
         is_enabled,
     )
@@ -224,6 +309,765 @@ async fn delete_mcp_server(
     }
 }
 
+async fn list_provider_pricing(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+) -> Result<Json<Vec<db::pricing::ProviderPricing>>, AppError> {
+    Ok(Json(db::pricing::list_pricing(&state.db).await?))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpsertProviderPricingRequest {
+    #[validate(length(min = 1, message = "provider_type is required"))]
+    pub provider_type: String,
+    #[validate(length(min = 1, message = "model is required"))]
+    pub model: String,
+    #[validate(range(min = 0.0, message = "input_cost_per_1k must not be negative"))]
+    pub input_cost_per_1k: f64,
+    #[validate(range(min = 0.0, message = "output_cost_per_1k must not be negative"))]
+    pub output_cost_per_1k: f64,
+}
+
+/// Create or update the price of a provider type's model. Applies to
+/// messages completed from now on.
+async fn upsert_provider_pricing(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Json(req): Json<UpsertProviderPricingRequest>,
+) -> Result<Json<db::pricing::ProviderPricing>, AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let pricing = db::pricing::upsert_pricing(
+        &state.db,
+        &req.provider_type,
+        &req.model,
+        req.input_cost_per_1k,
+        req.output_cost_per_1k,
+    )
+    .await?;
+    Ok(Json(pricing))
+}
+
+async fn delete_provider_pricing(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Path((provider_type, model)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    if db::pricing::delete_pricing(&state.db, &provider_type, &model).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound)
+    }
+}
+
+fn default_broadcast_type() -> String {
+    "announcement".to_string()
+}
+
+#[derive(Deserialize, Validate)]
+pub struct BroadcastRequest {
+    #[validate(length(min = 1, message = "Message is required"))]
+    pub message: String,
+    #[serde(rename = "type", default = "default_broadcast_type")]
+    pub broadcast_type: String,
+    /// Only reach this user's open conversations instead of every client.
+    pub user_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BroadcastResponse {
+    pub sent_to: usize,
+}
+
+async fn broadcast(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Json(req): Json<BroadcastRequest>,
+) -> Result<Json<BroadcastResponse>, AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let envelope = serde_json::json!({
+        "type": "broadcast",
+        "broadcast_type": req.broadcast_type,
+        "message": req.message,
+    });
+    let sent_to = match req.user_id.as_deref() {
+        Some(user_id) => {
+            state
+                .ws_state
+                .broadcast_to_user(user_id, &envelope.to_string())
+                .await
+        }
+        None => {
+            state
+                .ws_state
+                .broadcast_to_all_clients(&envelope.to_string())
+                .await
+        }
+    };
+    Ok(Json(BroadcastResponse { sent_to }))
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnouncementSeverity {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct AnnounceRequest {
+    #[validate(length(min = 1, message = "Message is required"))]
+    pub message: String,
+    #[serde(default)]
+    pub severity: AnnouncementSeverity,
+}
+
+/// Show a maintenance or outage notice in every open conversation of every
+/// connected user.
+async fn announce(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Json(req): Json<AnnounceRequest>,
+) -> Result<Json<BroadcastResponse>, AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let payload = serde_json::json!({
+        "type": "announcement",
+        "message": req.message,
+        "severity": req.severity,
+    });
+    let sent_to = state
+        .ws_state
+        .broadcast_to_all_clients(&payload.to_string())
+        .await;
+    Ok(Json(BroadcastResponse { sent_to }))
+}
+
+#[derive(Deserialize)]
+pub struct AuditLogParams {
+    pub user_id: Option<String>,
+    pub action: Option<String>,
+    pub limit: Option<i64>,
+    /// Return entries older than the entry with this ID.
+    pub after: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AuditLogResponse {
+    pub entries: Vec<db::audit_log::AuditEntry>,
+    /// ID to pass as `after` for the next page; absent on the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Audit log entries, newest first.
+async fn list_audit_log(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Query(params): Query<AuditLogParams>,
+) -> Result<Json<AuditLogResponse>, AppError> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+    let after = match params.after.as_deref() {
+        Some(id) => Some(
+            db::audit_log::get_entry(&state.db, id)
+                .await?
+                .ok_or_else(|| AppError::BadRequest(format!("Unknown audit log entry: {id}")))?,
+        ),
+        None => None,
+    };
+    let mut entries = db::audit_log::list_entries(
+        &state.db,
+        params.user_id.as_deref(),
+        params.action.as_deref(),
+        after.as_ref(),
+        limit + 1,
+    )
+    .await?;
+    let next_cursor = if entries.len() as i64 > limit {
+        entries.truncate(limit as usize);
+        entries.last().map(|e| e.id.clone())
+    } else {
+        None
+    };
+    Ok(Json(AuditLogResponse {
+        entries,
+        next_cursor,
+    }))
+}
+
+/// How long `GET /stats` serves a cached result before querying again.
+const SYSTEM_STATS_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Serialize)]
+pub struct SystemStatsResponse {
+    pub users: UserCounts,
+    pub conversations: ConversationCounts,
+    pub messages: MessageCounts,
+    pub containers: ContainerCounts,
+    pub db_size_bytes: i64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct UserCounts {
+    pub total: i64,
+    pub active_7d: i64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ConversationCounts {
+    pub total: i64,
+    pub active_today: i64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MessageCounts {
+    pub total: i64,
+    pub today: i64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ContainerCounts {
+    pub active: usize,
+}
+
+/// The last `GET /stats` result and when it was computed.
+pub type SystemStatsCache = Arc<Mutex<Option<(Instant, SystemStatsResponse)>>>;
+
+async fn system_stats(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+) -> Result<Json<SystemStatsResponse>, AppError> {
+    let mut cache = state.system_stats_cache.lock().await;
+    if let Some((computed_at, stats)) = cache.as_ref()
+        && computed_at.elapsed() < SYSTEM_STATS_TTL
+    {
+        return Ok(Json(stats.clone()));
+    }
+
+    let db_stats = db::system_stats::system_stats(&state.db).await?;
+    let ws_metrics = state.ws_state.metrics().await;
+    let stats = SystemStatsResponse {
+        users: UserCounts {
+            total: db_stats.users_total,
+            active_7d: db_stats.users_active_7d,
+        },
+        conversations: ConversationCounts {
+            total: db_stats.conversations_total,
+            active_today: db_stats.conversations_active_today,
+        },
+        messages: MessageCounts {
+            total: db_stats.messages_total,
+            today: db_stats.messages_today,
+        },
+        containers: ContainerCounts {
+            active: ws_metrics.active_container_connections,
+        },
+        db_size_bytes: db_stats.db_size_bytes,
+    };
+    *cache = Some((Instant::now(), stats.clone()));
+    Ok(Json(stats))
+}
+
+async fn ws_metrics(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+) -> Json<crate::ws::WsMetrics> {
+    Json(state.ws_state.metrics().await)
+}
+
+async fn ws_connections(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+) -> Json<Vec<crate::ws::ClientConnInfo>> {
+    Json(state.ws_state.list_connections().await)
+}
+
+#[derive(Serialize)]
+pub struct RepairOrphanedPartsResponse {
+    pub deleted_parts: u64,
+}
+
+async fn repair_orphaned_parts(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+) -> Result<Json<RepairOrphanedPartsResponse>, AppError> {
+    let deleted_parts = db::messages_v2::repair_orphaned_parts(&state.db).await?;
+    Ok(Json(RepairOrphanedPartsResponse { deleted_parts }))
+}
+
+#[derive(Deserialize)]
+pub struct BackfillTokenUsageQuery {
+    pub conversation_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BackfillTokenUsageResponse {
+    pub updated: u64,
+}
+
+async fn backfill_token_usage(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Query(query): Query<BackfillTokenUsageQuery>,
+) -> Result<Json<BackfillTokenUsageResponse>, AppError> {
+    let updated =
+        db::messages_v2::backfill_token_usage(&state.db, query.conversation_id.as_deref()).await?;
+    Ok(Json(BackfillTokenUsageResponse { updated }))
+}
+
+#[derive(Deserialize)]
+pub struct MergeTextPartsQuery {
+    pub conversation_id: String,
+}
+
+#[derive(Serialize)]
+pub struct MergeTextPartsResponse {
+    pub removed_parts: u64,
+}
+
+async fn merge_text_parts(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Query(query): Query<MergeTextPartsQuery>,
+) -> Result<Json<MergeTextPartsResponse>, AppError> {
+    let removed_parts = db::messages_v2::merge_consecutive_text_parts_for_conversation(
+        &state.db,
+        &query.conversation_id,
+    )
+    .await?;
+    Ok(Json(MergeTextPartsResponse { removed_parts }))
+}
+
+const DEFAULT_EXEC_TIMEOUT_SECS: u64 = 10;
+const MAX_EXEC_TIMEOUT_SECS: u64 = 60;
+
+#[derive(Deserialize)]
+pub struct ExecRequest {
+    pub command: Vec<String>,
+    pub timeout_secs: Option<u64>,
+}
+
+async fn list_containers(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+) -> Result<Json<Vec<ActiveContainer>>, AppError> {
+    Ok(Json(state.docker_manager.list_active_containers().await))
+}
+
+/// Stop a conversation's container and close its agent connection.
+async fn force_stop_container(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Path(conversation_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    state
+        .docker_manager
+        .force_stop(&state.ws_state, &conversation_id)
+        .await
+        .map_err(|e| match e {
+            DockerError::NotRunning => AppError::NotFound,
+            e => AppError::Internal(e.to_string()),
+        })?;
+    state
+        .sse_state
+        .close(
+            &conversation_id,
+            &crate::api::conversations::stopped_status(&conversation_id),
+        )
+        .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Run an allowlisted diagnostic command in a conversation's container.
+async fn exec_in_container(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Path(conversation_id): Path<String>,
+    Json(req): Json<ExecRequest>,
+) -> Result<Json<ExecResult>, AppError> {
+    if !exec_command_allowed(&req.command, &state.config.container_exec_allowlist) {
+        return Err(AppError::BadRequest(format!(
+            "Command not allowed; permitted programs: {}",
+            state.config.container_exec_allowlist.join(", ")
+        )));
+    }
+    let timeout_secs = req
+        .timeout_secs
+        .unwrap_or(DEFAULT_EXEC_TIMEOUT_SECS)
+        .clamp(1, MAX_EXEC_TIMEOUT_SECS);
+    let command: Vec<&str> = req.command.iter().map(String::as_str).collect();
+
+    let result = state
+        .docker_manager
+        .exec_in_container(&conversation_id, &command, timeout_secs)
+        .await
+        .map_err(|e| match e {
+            DockerError::NotRunning => AppError::Conflict("Container is not running".into()),
+            DockerError::Timeout(_) => AppError::BadRequest(e.to_string()),
+            e => AppError::Internal(e.to_string()),
+        })?;
+    Ok(Json(result))
+}
+
+#[derive(Serialize)]
+pub struct DeactivateUserResponse {
+    pub closed_connections: usize,
+}
+
+/// Disable an account, revoke its refresh tokens and close its live
+/// WebSocket connections.
+async fn deactivate_user(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    client: SessionClient,
+    Path(id): Path<String>,
+) -> Result<Json<DeactivateUserResponse>, AppError> {
+    auth.require_admin()?;
+    if !db::users::set_user_active(&state.db, &id, false).await? {
+        return Err(AppError::NotFound);
+    }
+    let closed_connections = sign_out_user(&state, &id).await?;
+    db::audit_log::record(
+        &state.db,
+        &client,
+        AuditEvent::new("admin_user_deactivate", Some(&auth.user_id)).target("user", &id),
+    )
+    .await;
+    Ok(Json(DeactivateUserResponse { closed_connections }))
+}
+
+/// Revoke a user's refresh tokens and close their live WebSocket
+/// connections. Returns the number of connections closed.
+async fn sign_out_user(state: &AppState, user_id: &str) -> Result<usize, AppError> {
+    db::refresh_tokens::invalidate_all_for_user(&state.db, user_id).await?;
+    let senders = state.ws_state.remove_all_clients_for_user(user_id).await;
+    for sender in &senders {
+        let _ = sender.try_send(crate::ws::ACCOUNT_DISABLED_MESSAGE.to_string());
+    }
+    Ok(senders.len())
+}
+
+#[derive(Deserialize)]
+pub struct ListUsersParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct AdminUserListResponse {
+    pub users: Vec<db::users::AdminUserSummary>,
+    pub total: i64,
+}
+
+async fn list_users(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Query(params): Query<ListUsersParams>,
+) -> Result<Json<AdminUserListResponse>, AppError> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let (users, total) = db::users::list_users(&state.db, limit, offset).await?;
+    Ok(Json(AdminUserListResponse { users, total }))
+}
+
+#[derive(Serialize)]
+pub struct AdminUserResponse {
+    pub id: String,
+    pub username: String,
+    pub email: String,
+    pub is_admin: bool,
+    pub email_verified: bool,
+    pub is_active: bool,
+    pub created_at: String,
+}
+
+impl From<db::users::User> for AdminUserResponse {
+    fn from(u: db::users::User) -> Self {
+        Self {
+            id: u.id,
+            username: u.username,
+            email: u.email,
+            is_admin: u.is_admin,
+            email_verified: u.email_verified,
+            is_active: u.is_active,
+            created_at: u.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateUserRequest {
+    #[validate(length(min = 3, max = 50, message = "Username must be 3-50 characters"))]
+    pub username: String,
+    #[validate(email(message = "Invalid email address"))]
+    pub email: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub password: String,
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+/// Create an account directly. Unlike registration, the email address is
+/// treated as verified and no session is started.
+async fn create_user(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    client: SessionClient,
+    Json(req): Json<CreateUserRequest>,
+) -> Result<(StatusCode, Json<AdminUserResponse>), AppError> {
+    auth.require_admin()?;
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let pw = req.password.clone();
+    let password_hash = tokio::task::spawn_blocking(move || password::hash_password(&pw))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .map_err(AppError::from)?;
+
+    let mut tx = state.db.begin().await?;
+    let user = db::users::admin_create_user_in_tx(
+        &mut tx,
+        &req.username,
+        &req.email,
+        &password_hash,
+        req.is_admin,
+    )
+    .await
+    .map_err(crate::api::auth::map_user_create_error)?;
+    db::presets::ensure_builtin_presets_for_user_in_tx(&mut tx, &user.id).await?;
+    tx.commit().await?;
+    db::audit_log::record(
+        &state.db,
+        &client,
+        AuditEvent::new("admin_user_create", Some(&auth.user_id))
+            .target("user", &user.id)
+            .details(serde_json::json!({ "is_admin": user.is_admin })),
+    )
+    .await;
+    Ok((StatusCode::CREATED, Json(user.into())))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateUserRequest {
+    #[validate(email(message = "Invalid email address"))]
+    pub email: Option<String>,
+    pub is_admin: Option<bool>,
+    /// `false` suspends the account and signs it out everywhere.
+    pub is_active: Option<bool>,
+}
+
+async fn update_user(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    client: SessionClient,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateUserRequest>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    auth.require_admin()?;
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    if id == auth.user_id && (req.is_admin == Some(false) || req.is_active == Some(false)) {
+        return Err(AppError::BadRequest(
+            "You cannot demote or suspend your own account".into(),
+        ));
+    }
+
+    let user = db::users::admin_update_user(
+        &state.db,
+        &id,
+        req.email.as_deref(),
+        req.is_admin,
+        req.is_active,
+    )
+    .await
+    .map_err(crate::api::auth::map_user_create_error)?
+    .ok_or(AppError::NotFound)?;
+    if req.is_active == Some(false) {
+        sign_out_user(&state, &id).await?;
+    }
+    db::audit_log::record(
+        &state.db,
+        &client,
+        AuditEvent::new("admin_user_update", Some(&auth.user_id))
+            .target("user", &id)
+            .details(serde_json::json!({
+                "email": req.email,
+                "is_admin": req.is_admin,
+                "is_active": req.is_active,
+            })),
+    )
+    .await;
+    Ok(Json(user.into()))
+}
+
+/// Delete an account with all of its conversations, then stop their
+/// containers and remove their workspaces.
+async fn delete_user(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    client: SessionClient,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    auth.require_admin()?;
+    if id == auth.user_id {
+        return Err(AppError::BadRequest(
+            "You cannot delete your own account".into(),
+        ));
+    }
+
+    let conversation_ids = db::users::admin_delete_user(&state.db, &id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    sign_out_user(&state, &id).await?;
+    db::audit_log::record(
+        &state.db,
+        &client,
+        AuditEvent::new("admin_user_delete", Some(&auth.user_id))
+            .target("user", &id)
+            .details(serde_json::json!({ "conversation_count": conversation_ids.len() })),
+    )
+    .await;
+    for conversation_id in &conversation_ids {
+        crate::api::conversations::release_conversation_resources(&state, conversation_id).await;
+        if let Err(e) = crate::api::conversations::remove_workspace(conversation_id).await {
+            tracing::error!(
+                "Failed to remove workspace for conversation {}: {}",
+                conversation_id,
+                e
+            );
+        }
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize)]
+pub struct UserQuotaResponse {
+    pub user_id: String,
+    /// Limits set for this user; `null` falls back to the server default.
+    pub max_conversations: Option<i64>,
+    pub max_messages_per_conversation: Option<i64>,
+    pub max_workspace_bytes: Option<i64>,
+    /// Limits in force once server defaults are applied; `null` is unlimited.
+    pub effective: db::user_quotas::EffectiveQuota,
+}
+
+impl UserQuotaResponse {
+    fn new(user_id: String, quota: Option<db::user_quotas::UserQuota>, config: &Config) -> Self {
+        let effective = db::user_quotas::EffectiveQuota::resolve(quota.as_ref(), config);
+        Self {
+            user_id,
+            max_conversations: quota.as_ref().and_then(|q| q.max_conversations),
+            max_messages_per_conversation: quota
+                .as_ref()
+                .and_then(|q| q.max_messages_per_conversation),
+            max_workspace_bytes: quota.as_ref().and_then(|q| q.max_workspace_bytes),
+            effective,
+        }
+    }
+}
+
+async fn get_user_quota(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Path(id): Path<String>,
+) -> Result<Json<UserQuotaResponse>, AppError> {
+    db::users::get_user_by_id(&state.db, &id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let quota = db::user_quotas::get_user_quota(&state.db, &id).await?;
+    Ok(Json(UserQuotaResponse::new(id, quota, &state.config)))
+}
+
+/// Limits omitted or `null` fall back to the server default.
+#[derive(Deserialize, Validate)]
+pub struct UserQuotaRequest {
+    #[validate(range(min = 0))]
+    pub max_conversations: Option<i64>,
+    #[validate(range(min = 0))]
+    pub max_messages_per_conversation: Option<i64>,
+    #[validate(range(min = 0))]
+    pub max_workspace_bytes: Option<i64>,
+}
+
+/// Replace a user's quota.
+async fn set_user_quota(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    client: SessionClient,
+    Path(id): Path<String>,
+    Json(req): Json<UserQuotaRequest>,
+) -> Result<Json<UserQuotaResponse>, AppError> {
+    auth.require_admin()?;
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    db::users::get_user_by_id(&state.db, &id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let quota = db::user_quotas::set_user_quota(
+        &state.db,
+        &id,
+        req.max_conversations,
+        req.max_messages_per_conversation,
+        req.max_workspace_bytes,
+    )
+    .await?;
+    db::audit_log::record(
+        &state.db,
+        &client,
+        AuditEvent::new("admin_user_quota_update", Some(&auth.user_id))
+            .target("user", &id)
+            .details(serde_json::json!({
+                "max_conversations": req.max_conversations,
+                "max_messages_per_conversation": req.max_messages_per_conversation,
+                "max_workspace_bytes": req.max_workspace_bytes,
+            })),
+    )
+    .await;
+    Ok(Json(UserQuotaResponse::new(id, Some(quota), &state.config)))
+}
+
+fn default_inactive_days() -> i64 {
+    90
+}
+
+#[derive(Deserialize, Validate)]
+pub struct AutoArchiveRequest {
+    #[serde(default = "default_inactive_days")]
+    #[validate(range(min = 1))]
+    pub inactive_days: i64,
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AutoArchiveResponse {
+    pub archived: u64,
+}
+
+async fn auto_archive_conversations(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Json(req): Json<AutoArchiveRequest>,
+) -> Result<Json<AutoArchiveResponse>, AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let archived = db::conversations::archive_all_inactive(
+        &state.db,
+        req.user_id.as_deref(),
+        req.inactive_days,
+    )
+    .await?;
+    Ok(Json(AutoArchiveResponse { archived }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,12 +1,14 @@
 use axum::{
     Json, Router,
-    extract::State,
-    http::{HeaderMap, HeaderValue, header},
+    extract::{ConnectInfo, FromRequestParts, Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header, request::Parts},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, post},
 };
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tower_governor::{
     GovernorLayer, governor::GovernorConfigBuilder, key_extractor::SmartIpKeyExtractor,
@@ -14,11 +16,18 @@ use tower_governor::{
 use validator::Validate;
 
 use crate::auth;
-use crate::auth::middleware::AppState;
+use crate::auth::middleware::{AppState, AuthUser};
+use crate::auth::oauth;
 use crate::auth::password;
+use crate::config::Feature;
 use crate::db;
+use crate::db::audit_log::AuditEvent;
+use crate::db::refresh_tokens::SessionClient;
 use crate::error::AppError;
 
+const EMAIL_VERIFICATION_TTL_HOURS: i64 = 24;
+const PASSWORD_RESET_TTL_HOURS: i64 = 1;
+
 pub fn router() -> Router<Arc<AppState>> {
     let governor_conf = GovernorConfigBuilder::default()
         .per_second(6)
@@ -32,6 +41,13 @@ pub fn router() -> Router<Arc<AppState>> {
         .route("/login", post(login))
         .route("/refresh", post(refresh))
         .route("/logout", post(logout))
+        .route("/sessions", get(list_sessions))
+        .route("/sessions/{session_id}", delete(delete_session))
+        .route("/verify-email", post(verify_email))
+        .route("/password-reset/request", post(request_password_reset))
+        .route("/password-reset/confirm", post(confirm_password_reset))
+        .route("/oauth/init", post(oauth_init))
+        .route("/oauth/callback", get(oauth_callback))
         .layer(GovernorLayer::new(governor_conf))
 }
 
@@ -58,6 +74,7 @@ pub struct UserResponse {
     pub username: String,
     pub email: String,
     pub is_admin: bool,
+    pub email_verified: bool,
 }
 
 #[derive(Serialize)]
@@ -69,6 +86,7 @@ pub struct MessageResponse {
 struct ConsumedRefreshToken {
     user_id: String,
     expires_at: String,
+    family_id: String,
 }
 
 fn append_set_cookie(headers: &mut HeaderMap, cookie: String) -> Result<(), AppError> {
@@ -164,11 +182,12 @@ fn auth_response(
             username: user.username.clone(),
             email: user.email.clone(),
             is_admin: user.is_admin,
+            email_verified: user.email_verified,
         },
     }
 }
 
-fn map_user_create_error(err: sqlx::Error) -> AppError {
+pub(crate) fn map_user_create_error(err: sqlx::Error) -> AppError {
     if let sqlx::Error::Database(db_err) = &err
         && db_err.is_unique_violation()
     {
@@ -184,10 +203,25 @@ fn map_user_create_error(err: sqlx::Error) -> AppError {
     AppError::from(err)
 }
 
+/// Send an email if a mailer is configured. Failures are logged rather than
+/// returned: the account change that triggered the email has already
+/// happened.
+async fn send_email(state: &AppState, to: &str, subject: &str, body: &str) {
+    let Some(mailer) = state.mailer.as_ref() else {
+        tracing::warn!("No mailer configured; not sending \"{subject}\" email");
+        return;
+    };
+    if let Err(e) = mailer.send(to, subject, body).await {
+        tracing::warn!("Failed to send \"{subject}\" email: {e}");
+    }
+}
+
 async fn register(
     State(state): State<Arc<AppState>>,
+    client: SessionClient,
     Json(req): Json<RegisterRequest>,
 ) -> Result<Response, AppError> {
+    state.features.require(Feature::Registration)?;
     req.validate()
         .map_err(|e| AppError::BadRequest(e.to_string()))?;
 
@@ -216,11 +250,22 @@ async fn register(
         .map_err(map_user_create_error)?;
     db::presets::ensure_builtin_presets_for_user_in_tx(&mut tx, &user.id).await?;
 
+    let (verification_token, verification_hash) = generate_refresh_token();
+    let verification_expires_at =
+        chrono::Utc::now() + chrono::Duration::hours(EMAIL_VERIFICATION_TTL_HOURS);
+    db::email_verification::create_verification_token_in_tx(
+        &mut tx,
+        &user.id,
+        &verification_hash,
+        &verification_expires_at.to_rfc3339(),
+    )
+    .await?;
+
     let access_token = auth::create_access_token(
         &user.id,
         &user.username,
         user.is_admin,
-        &state.config.jwt_secret,
+        &state.jwt_keys,
         state.config.access_token_ttl_secs,
     )
     .map_err(|e| AppError::Internal(e.to_string()))?;
@@ -233,16 +278,30 @@ async fn register(
         &user.id,
         &token_hash,
         &expires_at.to_rfc3339(),
+        &client,
     )
     .await?;
     tx.commit().await?;
+    db::audit_log::record(
+        &state.db,
+        &client,
+        AuditEvent::new("register", Some(&user.id)).target("user", &user.id),
+    )
+    .await;
 
-    let mut response = Json(auth_response(
-        &user,
-        access_token.clone(),
-        refresh_token.clone(),
-    ))
-    .into_response();
+    send_email(
+        &state,
+        &user.email,
+        "Verify your email",
+        &format!(
+            "Use this code to verify your email address:\n\n{verification_token}\n\n\
+             It expires in {EMAIL_VERIFICATION_TTL_HOURS} hours."
+        ),
+    )
+    .await;
+
+    let body = auth_response(&user, access_token.clone(), refresh_token.clone());
+    let mut response = Json(body).into_response();
     set_auth_cookies(
         response.headers_mut(),
         &access_token,
@@ -252,6 +311,133 @@ async fn register(
     Ok(response)
 }
 
+#[derive(Deserialize, Validate)]
+pub struct VerifyEmailRequest {
+    #[validate(length(min = 1, message = "Token is required"))]
+    pub token: String,
+}
+
+async fn verify_email(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<VerifyEmailRequest>,
+) -> Result<Json<MessageResponse>, AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let token =
+        db::email_verification::consume_verification_token(&state.db, &hash_token(&req.token))
+            .await?
+            .ok_or_else(|| AppError::BadRequest("Invalid verification token".into()))?;
+
+    let expires_at = chrono::DateTime::parse_from_rfc3339(&token.expires_at)
+        .map_err(|_| AppError::Internal("Invalid token expiry".into()))?;
+    if expires_at < chrono::Utc::now() {
+        return Err(AppError::BadRequest("Verification token expired".into()));
+    }
+
+    db::users::mark_email_verified(&state.db, &token.user_id).await?;
+    Ok(Json(MessageResponse {
+        message: "Email verified".into(),
+    }))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct PasswordResetRequest {
+    #[validate(email(message = "Invalid email address"))]
+    pub email: String,
+}
+
+#[derive(Serialize)]
+pub struct PasswordResetRequestResponse {
+    pub message: String,
+}
+
+/// Start a password reset. The response is the same whether or not the
+/// email is registered, so it cannot be used to discover accounts.
+async fn request_password_reset(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PasswordResetRequest>,
+) -> Result<Json<PasswordResetRequestResponse>, AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    if let Some(user) = db::users::get_user_by_email(&state.db, &req.email).await? {
+        let (token, token_hash) = generate_refresh_token();
+        let expires_at = chrono::Utc::now() + chrono::Duration::hours(PASSWORD_RESET_TTL_HOURS);
+        db::password_reset::create_reset_token(
+            &state.db,
+            &user.id,
+            &token_hash,
+            &expires_at.to_rfc3339(),
+        )
+        .await?;
+        send_email(
+            &state,
+            &user.email,
+            "Reset your password",
+            &format!(
+                "Use this code to reset your password:\n\n{token}\n\n\
+                 It expires in {PASSWORD_RESET_TTL_HOURS} hour. If you did not ask for \
+                 a reset, ignore this email."
+            ),
+        )
+        .await;
+    }
+
+    Ok(Json(PasswordResetRequestResponse {
+        message: "If that email is registered, a reset link has been sent".into(),
+    }))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct PasswordResetConfirmRequest {
+    #[validate(length(min = 1, message = "Token is required"))]
+    pub token: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub new_password: String,
+}
+
+/// Set a new password with a reset token and revoke every refresh token of
+/// the account.
+async fn confirm_password_reset(
+    State(state): State<Arc<AppState>>,
+    client: SessionClient,
+    Json(req): Json<PasswordResetConfirmRequest>,
+) -> Result<Json<MessageResponse>, AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let token = db::password_reset::consume_reset_token(&state.db, &hash_token(&req.token))
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Invalid reset token".into()))?;
+
+    let expires_at = chrono::DateTime::parse_from_rfc3339(&token.expires_at)
+        .map_err(|_| AppError::Internal("Invalid token expiry".into()))?;
+    if expires_at < chrono::Utc::now() {
+        return Err(AppError::BadRequest("Reset token expired".into()));
+    }
+
+    let pw = req.new_password.clone();
+    let new_hash = tokio::task::spawn_blocking(move || password::hash_password(&pw))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .map_err(AppError::from)?;
+    db::users::update_password_hash(&state.db, &token.user_id, &new_hash).await?;
+    db::refresh_tokens::invalidate_all_for_user(&state.db, &token.user_id).await?;
+    db::audit_log::record(
+        &state.db,
+        &client,
+        AuditEvent::new("password_change", Some(&token.user_id))
+            .target("user", &token.user_id)
+            .details(serde_json::json!({ "method": "reset_token" })),
+    )
+    .await;
+
+    Ok(Json(MessageResponse {
+        message: "Password updated".into(),
+    }))
+}
+
 #[derive(Deserialize, Validate)]
 pub struct LoginRequest {
     #[validate(length(min = 1, message = "Username is required"))]
@@ -262,31 +448,66 @@ pub struct LoginRequest {
 
 async fn login(
     State(state): State<Arc<AppState>>,
+    client: SessionClient,
     Json(req): Json<LoginRequest>,
 ) -> Result<Response, AppError> {
     req.validate()
         .map_err(|e| AppError::BadRequest(e.to_string()))?;
 
-    let user = db::users::get_user_by_username(&state.db, &req.username)
-        .await?
-        .ok_or_else(|| AppError::Unauthorized("Invalid credentials".into()))?;
-
-    let pw = req.password.clone();
-    let hash = user.password_hash.clone();
-    let valid = tokio::task::spawn_blocking(move || password::verify_password(&pw, &hash))
-        .await
-        .map_err(|e| AppError::Internal(e.to_string()))?
-        .map_err(AppError::from)?;
+    let ip = client.ip_address.as_deref().unwrap_or_default();
+    // Usernames are case-insensitive, so track failures by the canonical form.
+    let username = req.username.to_lowercase();
+    if let Some(until) = db::login_attempts::locked_until(&state.db, ip, &username).await? {
+        return Err(too_many_login_attempts(until));
+    }
 
-    if !valid {
-        return Err(AppError::Unauthorized("Invalid credentials".into()));
+    let user = match db::users::get_user_by_username(&state.db, &username).await? {
+        Some(user) => {
+            let pw = req.password.clone();
+            let hash = user.password_hash.clone();
+            let valid = tokio::task::spawn_blocking(move || password::verify_password(&pw, &hash))
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?
+                .map_err(AppError::from)?;
+            valid.then_some(user)
+        }
+        None => None,
+    };
+    // Unknown usernames count as failures too, so lockouts do not reveal
+    // which accounts exist.
+    let Some(user) = user else {
+        let locked = db::login_attempts::record_failure(
+            &state.db,
+            ip,
+            &username,
+            state.config.max_login_attempts,
+            state.config.login_lockout_secs,
+        )
+        .await?;
+        return Err(match locked {
+            Some(until) => too_many_login_attempts(until),
+            None => AppError::Unauthorized("Invalid credentials".into()),
+        });
+    };
+    db::login_attempts::reset(&state.db, ip, &username).await?;
+    if !user.is_active {
+        return Err(AppError::Forbidden("Account disabled".into()));
     }
+    db::users::record_login(&state.db, &user.id).await?;
+    db::audit_log::record(
+        &state.db,
+        &client,
+        AuditEvent::new("login", Some(&user.id))
+            .target("user", &user.id)
+            .details(serde_json::json!({ "method": "password" })),
+    )
+    .await;
 
     let access_token = auth::create_access_token(
         &user.id,
         &user.username,
         user.is_admin,
-        &state.config.jwt_secret,
+        &state.jwt_keys,
         state.config.access_token_ttl_secs,
     )
     .map_err(|e| AppError::Internal(e.to_string()))?;
@@ -299,6 +520,7 @@ async fn login(
         &user.id,
         &token_hash,
         &expires_at.to_rfc3339(),
+        &client,
     )
     .await?;
 
@@ -317,6 +539,13 @@ async fn login(
     Ok(response)
 }
 
+fn too_many_login_attempts(until: chrono::DateTime<chrono::Utc>) -> AppError {
+    let remaining = (until - chrono::Utc::now()).num_seconds().max(1);
+    AppError::TooManyRequests {
+        retry_after_secs: remaining as u64,
+    }
+}
+
 #[derive(Deserialize, Default)]
 pub struct RefreshRequest {
     pub refresh_token: Option<String>,
@@ -330,6 +559,7 @@ fn extract_refresh_token(headers: &HeaderMap, req: &RefreshRequest) -> Option<St
 
 async fn refresh(
     State(state): State<Arc<AppState>>,
+    client: SessionClient,
     headers: HeaderMap,
     Json(req): Json<RefreshRequest>,
 ) -> Result<Response, AppError> {
@@ -340,15 +570,31 @@ async fn refresh(
     let mut tx = state.db.begin().await?;
 
     // Consume token in the same transaction to prevent refresh-token replay.
+    // The row is kept (marked used) so a later replay can be recognised.
     let consumed = sqlx::query_as::<_, ConsumedRefreshToken>(
-        "DELETE FROM refresh_tokens
-         WHERE token_hash = ?
-         RETURNING user_id, expires_at",
+        "UPDATE refresh_tokens SET used = 1
+         WHERE token_hash = ? AND used = 0
+         RETURNING user_id, expires_at, family_id",
     )
     .bind(&token_hash)
     .fetch_optional(&mut *tx)
-    .await?
-    .ok_or_else(|| AppError::Unauthorized("Invalid refresh token".into()))?;
+    .await?;
+    let Some(consumed) = consumed else {
+        // A used token coming back means someone else holds a copy of it, so
+        // every token rotated from the same login is revoked.
+        if let Some(family_id) =
+            db::refresh_tokens::used_token_family_in_tx(&mut tx, &token_hash).await?
+        {
+            let revoked = db::refresh_tokens::delete_family_in_tx(&mut tx, &family_id).await?;
+            tx.commit().await?;
+            tracing::warn!(
+                family_id = %family_id,
+                revoked,
+                "Refresh token reuse detected; revoked token family"
+            );
+        }
+        return Err(AppError::Unauthorized("Invalid refresh token".into()));
+    };
 
     let expires_at = chrono::DateTime::parse_from_rfc3339(&consumed.expires_at)
         .map_err(|_| AppError::Internal("Invalid token expiry".into()))?;
@@ -359,7 +605,7 @@ async fn refresh(
     }
 
     let user = sqlx::query_as::<_, db::users::User>(
-        "SELECT id, username, email, password_hash, is_admin, created_at, updated_at
+        "SELECT id, username, email, password_hash, is_admin, email_verified, is_active, created_at, updated_at
          FROM users
          WHERE id = ?",
     )
@@ -367,12 +613,16 @@ async fn refresh(
     .fetch_optional(&mut *tx)
     .await?
     .ok_or_else(|| AppError::Unauthorized("User not found".into()))?;
+    if !user.is_active {
+        tx.commit().await?;
+        return Err(AppError::Forbidden("Account disabled".into()));
+    }
 
     let access_token = auth::create_access_token(
         &user.id,
         &user.username,
         user.is_admin,
-        &state.config.jwt_secret,
+        &state.jwt_keys,
         state.config.access_token_ttl_secs,
     )
     .map_err(|e| AppError::Internal(e.to_string()))?;
@@ -380,13 +630,17 @@ async fn refresh(
     let (new_refresh_token, new_token_hash) = generate_refresh_token();
     let new_expires_at = now + chrono::Duration::days(state.config.refresh_token_ttl_days);
     sqlx::query(
-        "INSERT INTO refresh_tokens (id, user_id, token_hash, expires_at)
-         VALUES (?, ?, ?, ?)",
+        "INSERT INTO refresh_tokens
+         (id, user_id, token_hash, expires_at, family_id, user_agent, ip_address)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
     )
     .bind(uuid::Uuid::new_v4().to_string())
     .bind(&user.id)
     .bind(&new_token_hash)
     .bind(new_expires_at.to_rfc3339())
+    .bind(&consumed.family_id)
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
     .execute(&mut *tx)
     .await?;
     tx.commit().await?;
@@ -413,6 +667,7 @@ pub struct LogoutRequest {
 
 async fn logout(
     State(state): State<Arc<AppState>>,
+    client: SessionClient,
     headers: HeaderMap,
     Json(req): Json<LogoutRequest>,
 ) -> Result<Response, AppError> {
@@ -422,7 +677,16 @@ async fn logout(
 
     if let Some(token) = refresh_token {
         let token_hash = hash_token(&token);
-        db::refresh_tokens::delete_refresh_token_by_hash(&state.db, &token_hash).await?;
+        let session = db::refresh_tokens::get_refresh_token_by_hash(&state.db, &token_hash).await?;
+        db::refresh_tokens::delete_family_by_hash(&state.db, &token_hash).await?;
+        if let Some(session) = session {
+            db::audit_log::record(
+                &state.db,
+                &client,
+                AuditEvent::new("logout", Some(&session.user_id)).target("user", &session.user_id),
+            )
+            .await;
+        }
     }
 
     let mut response = Json(MessageResponse {
@@ -433,6 +697,216 @@ async fn logout(
     Ok(response)
 }
 
+impl FromRequestParts<Arc<AppState>> for SessionClient {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let ip_address = auth::client_ip(&parts.headers, peer, &state.config.trusted_proxies)
+            .map(|ip| ip.to_string());
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.chars().take(512).collect());
+        Ok(SessionClient {
+            user_agent,
+            ip_address,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    pub id: String,
+    pub created_at: String,
+    pub expires_at: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+async fn list_sessions(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<SessionResponse>>, AppError> {
+    let sessions = db::refresh_tokens::list_sessions_for_user(&state.db, &auth_user.user_id)
+        .await?
+        .into_iter()
+        .map(|t| SessionResponse {
+            id: t.id,
+            created_at: t.created_at,
+            expires_at: t.expires_at,
+            user_agent: t.user_agent,
+            ip_address: t.ip_address,
+        })
+        .collect();
+    Ok(Json(sessions))
+}
+
+async fn delete_session(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if !db::refresh_tokens::delete_session(&state.db, &auth_user.user_id, &session_id).await? {
+        return Err(AppError::NotFound);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize)]
+pub struct OAuthInitResponse {
+    pub state: String,
+    pub code_verifier: String,
+    pub code_challenge: String,
+    /// `None` until an OAuth provider is configured.
+    pub auth_url: Option<String>,
+}
+
+async fn oauth_init(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<OAuthInitResponse>, AppError> {
+    let oauth_state = oauth::generate_state();
+    let code_verifier = oauth::generate_code_verifier();
+    let code_challenge = oauth::code_challenge(&code_verifier);
+    let expires_at = chrono::Utc::now() + chrono::Duration::seconds(oauth::OAUTH_STATE_TTL_SECS);
+    db::oauth_states::create_oauth_state(
+        &state.db,
+        &oauth_state,
+        &code_verifier,
+        &expires_at.to_rfc3339(),
+    )
+    .await?;
+
+    let auth_url = state
+        .config
+        .oauth_provider()
+        .map(|provider| oauth::build_authorize_url(&provider, &oauth_state, &code_challenge))
+        .transpose()
+        .map_err(|e| AppError::Internal(format!("Invalid OAuth auth_url: {e}")))?;
+
+    Ok(Json(OAuthInitResponse {
+        state: oauth_state,
+        code_verifier,
+        code_challenge,
+        auth_url,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct OAuthCallbackQuery {
+    pub code: String,
+    pub state: String,
+}
+
+async fn oauth_callback(
+    State(state): State<Arc<AppState>>,
+    client: SessionClient,
+    Query(query): Query<OAuthCallbackQuery>,
+) -> Result<Response, AppError> {
+    // Consume the state up front so a replayed callback can never succeed.
+    let pending = db::oauth_states::consume_oauth_state(&state.db, &query.state)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid OAuth state".into()))?;
+
+    let expires_at = chrono::DateTime::parse_from_rfc3339(&pending.expires_at)
+        .map_err(|_| AppError::Internal("Invalid OAuth state expiry".into()))?;
+    if expires_at < chrono::Utc::now() {
+        return Err(AppError::Unauthorized("OAuth state expired".into()));
+    }
+
+    let provider = state
+        .config
+        .oauth_provider()
+        .ok_or(AppError::NotImplemented)?;
+    let identity = oauth::exchange_code(&provider, &query.code, &pending.code_verifier).await?;
+    let user = find_or_create_oauth_user(&state, &identity).await?;
+    if !user.is_active {
+        return Err(AppError::Forbidden("Account disabled".into()));
+    }
+    db::users::record_login(&state.db, &user.id).await?;
+    db::audit_log::record(
+        &state.db,
+        &client,
+        AuditEvent::new("login", Some(&user.id))
+            .target("user", &user.id)
+            .details(serde_json::json!({ "method": "oauth" })),
+    )
+    .await;
+
+    let access_token = auth::create_access_token(
+        &user.id,
+        &user.username,
+        user.is_admin,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let (refresh_token, token_hash) = generate_refresh_token();
+    let expires_at =
+        chrono::Utc::now() + chrono::Duration::days(state.config.refresh_token_ttl_days);
+    db::refresh_tokens::create_refresh_token(
+        &state.db,
+        &user.id,
+        &token_hash,
+        &expires_at.to_rfc3339(),
+        &client,
+    )
+    .await?;
+
+    let mut response = Json(auth_response(
+        &user,
+        access_token.clone(),
+        refresh_token.clone(),
+    ))
+    .into_response();
+    set_auth_cookies(
+        response.headers_mut(),
+        &access_token,
+        &refresh_token,
+        &state,
+    )?;
+    Ok(response)
+}
+
+/// Match an OAuth identity to an existing account by email, or register a
+/// new one when registration is enabled. New accounts get a random password
+/// so password login stays unusable until the user sets one.
+async fn find_or_create_oauth_user(
+    state: &AppState,
+    identity: &oauth::OAuthIdentity,
+) -> Result<db::users::User, AppError> {
+    if let Some(user) = db::users::get_user_by_email(&state.db, &identity.email).await? {
+        return Ok(user);
+    }
+    state.features.require(Feature::Registration)?;
+
+    let (random_password, _) = generate_refresh_token();
+    let password_hash =
+        tokio::task::spawn_blocking(move || password::hash_password(&random_password))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .map_err(AppError::from)?;
+
+    let mut tx = state.db.begin().await?;
+    let user =
+        db::users::create_user_in_tx(&mut tx, &identity.username, &identity.email, &password_hash)
+            .await
+            .map_err(map_user_create_error)?;
+    db::presets::ensure_builtin_presets_for_user_in_tx(&mut tx, &user.id).await?;
+    tx.commit().await?;
+    Ok(user)
+}
+
 fn generate_refresh_token() -> (String, String) {
     use rand::RngCore;
     let mut bytes = [0u8; 32];
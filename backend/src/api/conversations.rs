@@ -1,19 +1,34 @@
 use axum::{
     Json, Router,
-    extract::{Path, Query, State},
-    http::StatusCode,
-    routing::get,
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{StatusCode, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{delete, get, post, put},
 };
+use futures_util::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::{io::ErrorKind, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::Infallible,
+    io::ErrorKind,
+    sync::Arc,
+};
 
 use crate::auth::middleware::{AppState, AuthUser};
+use crate::config::{Feature, FeatureFlags};
 use crate::db;
+use crate::db::audit_log::AuditEvent;
+use crate::db::refresh_tokens::SessionClient;
+use crate::docker::manager::{DockerError, workspace_relative_path};
 use crate::error::AppError;
 
 const DEFAULT_THINKING_BUDGET: i64 = 128000;
 const MIN_THINKING_BUDGET: i64 = 1024;
 const MAX_THINKING_BUDGET: i64 = 1_000_000;
+const MAX_COPY_FILE_BYTES: usize = 50 * 1024 * 1024;
 
 fn validate_budget(field_name: &str, budget: i64) -> Result<(), AppError> {
     if !(MIN_THINKING_BUDGET..=MAX_THINKING_BUDGET).contains(&budget) {
@@ -31,6 +46,15 @@ fn validate_optional_budget(field_name: &str, budget: Option<i64>) -> Result<(),
     Ok(())
 }
 
+fn validate_idle_timeout(timeout_secs: Option<i64>) -> Result<(), AppError> {
+    if timeout_secs.is_some_and(|t| t < 0) {
+        return Err(AppError::BadRequest(
+            "container_idle_timeout_secs must not be negative".into(),
+        ));
+    }
+    Ok(())
+}
+
 fn normalize_optional_string(value: Option<&str>) -> Option<String> {
     value
         .map(str::trim)
@@ -38,6 +62,22 @@ fn normalize_optional_string(value: Option<&str>) -> Option<String> {
         .map(ToString::to_string)
 }
 
+/// Reject deep thinking (including subagent settings) or image generation
+/// options when those features are switched off.
+fn ensure_model_features(
+    features: &FeatureFlags,
+    wants_deep_thinking: bool,
+    wants_image_generation: bool,
+) -> Result<(), AppError> {
+    if wants_deep_thinking {
+        features.require(Feature::DeepThinking)?;
+    }
+    if wants_image_generation {
+        features.require(Feature::ImageGeneration)?;
+    }
+    Ok(())
+}
+
 fn parse_models_json(json_str: Option<&str>) -> Vec<String> {
     json_str
         .and_then(|s| serde_json::from_str::<Vec<String>>(s).ok())
@@ -75,6 +115,31 @@ fn ensure_provider_has_model(
     )))
 }
 
+/// Provider types the agent's image generation tool can talk to.
+const IMAGE_PROVIDER_TYPES: &[&str] = &["openai", "google"];
+
+async fn ensure_image_provider_type(
+    state: &AppState,
+    user_id: &str,
+    image_provider_id: Option<&str>,
+) -> Result<(), AppError> {
+    let Some(image_provider_id) = image_provider_id else {
+        return Ok(());
+    };
+    for provider_type in IMAGE_PROVIDER_TYPES {
+        if db::providers::list_providers_by_type(&state.db, user_id, provider_type)
+            .await?
+            .iter()
+            .any(|p| p.id == image_provider_id)
+        {
+            return Ok(());
+        }
+    }
+    Err(AppError::BadRequest(format!(
+        "Provider id '{image_provider_id}' does not support image generation"
+    )))
+}
+
 struct ValidatedConversationModels {
     provider_id: String,
     model_name: String,
@@ -128,18 +193,67 @@ fn validate_conversation_models(
 
 pub fn router() -> Router<Arc<AppState>> {
     Router::new()
-        .route("/", get(list_conversations).post(create_conversation))
+        .route(
+            "/",
+            get(list_conversations)
+                .post(create_conversation)
+                .delete(bulk_delete_conversations),
+        )
+        .route("/import", post(import_conversation))
         .route(
             "/{id}",
             get(get_conversation)
                 .put(update_conversation)
                 .delete(delete_conversation),
         )
+        .route(
+            "/{id}/pin",
+            put(pin_conversation).patch(set_conversation_pinned),
+        )
+        .route("/{id}/unpin", put(unpin_conversation))
+        .route("/{id}/tags", post(add_conversation_tag))
+        .route("/{id}/tags/{tag}", delete(remove_conversation_tag))
+        .route("/{id}/stats", get(get_conversation_stats))
+        .route("/{id}/token-usage", get(get_token_usage))
+        .route("/{id}/cost", get(get_conversation_cost))
+        .route("/{id}/export", get(export_conversation))
+        .route("/{id}/fork", post(fork_conversation))
+        .route("/{id}/duplicate", post(duplicate_conversation))
+        .route("/{id}/archive", put(archive_conversation))
+        .route("/{id}/unarchive", put(unarchive_conversation))
         .route("/{id}/messages", get(list_messages))
+        .route("/{id}/messages/around", get(list_messages_around))
+        .route("/{id}/messages/last", get(get_last_message))
+        .route("/{id}/messages/search", get(search_messages))
+        .route("/{id}/messages/stream", post(stream_message))
+        .route("/{id}/messages/{msg_id}/context", get(get_message_context))
+        .route("/{id}/messages/{msg_id}/undelete", post(undelete_message))
+        .route(
+            "/{id}/messages/{msg_id}/bookmark",
+            post(bookmark_message).delete(unbookmark_message),
+        )
+        .route(
+            "/{id}/messages/{msg_id}/reactions",
+            post(add_message_reaction),
+        )
+        .route(
+            "/{id}/messages/{msg_id}/reactions/{emoji}",
+            delete(remove_message_reaction),
+        )
+        .route("/{id}/messages/import", post(import_messages))
+        .route("/{id}/summarize", post(summarize_conversation))
         .route(
             "/{id}/mcp-servers",
             get(get_mcp_servers).put(set_mcp_servers),
         )
+        .route("/{id}/container/update-key", post(update_container_key))
+        .route("/{id}/container-status", get(get_container_status))
+        .route("/{id}/container/logs", get(stream_container_logs))
+        .route("/{id}/container/status", get(get_container_state))
+        .route(
+            "/{id}/container/copy-file",
+            post(copy_file_to_container).layer(DefaultBodyLimit::max(MAX_COPY_FILE_BYTES)),
+        )
 }
 
 #[derive(Serialize)]
@@ -159,6 +273,16 @@ pub struct ConversationResponse {
     pub share_token: Option<String>,
     pub thinking_budget: Option<i64>,
     pub subagent_thinking_budget: Option<i64>,
+    pub pinned: bool,
+    pub last_container_error: Option<String>,
+    pub archived_at: Option<String>,
+    pub container_idle_timeout_secs: Option<i64>,
+    /// Only filled in by the detail and list endpoints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// Only filled in by the detail endpoint; list items carry their own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_count: Option<i64>,
 }
 
 impl From<db::conversations::Conversation> for ConversationResponse {
@@ -179,16 +303,84 @@ impl From<db::conversations::Conversation> for ConversationResponse {
             share_token: c.share_token,
             thinking_budget: c.thinking_budget,
             subagent_thinking_budget: c.subagent_thinking_budget,
+            pinned: c.pinned,
+            last_container_error: c.last_container_error,
+            archived_at: c.archived_at,
+            container_idle_timeout_secs: c.container_idle_timeout_secs,
+            tags: None,
+            message_count: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ConversationListItem {
+    #[serde(flatten)]
+    pub conversation: ConversationResponse,
+    pub last_message_preview: Option<String>,
+    pub last_message_at: Option<String>,
+    pub message_count: i64,
+}
+
+impl From<db::conversations::ConversationWithPreview> for ConversationListItem {
+    fn from(c: db::conversations::ConversationWithPreview) -> Self {
+        Self {
+            conversation: c.conversation.into(),
+            last_message_preview: c.last_message_preview,
+            last_message_at: c.last_message_at,
+            message_count: c.message_count,
         }
     }
 }
 
+const MAX_CONVERSATION_QUERY_CHARS: usize = 256;
+
+#[derive(Deserialize)]
+pub struct ListConversationsQuery {
+    pub pinned: Option<bool>,
+    #[serde(default)]
+    pub include_archived: bool,
+    /// Matches conversation titles and message content.
+    pub q: Option<String>,
+    pub tag: Option<String>,
+}
+
 async fn list_conversations(
     State(state): State<Arc<AppState>>,
     auth: AuthUser,
-) -> Result<Json<Vec<ConversationResponse>>, AppError> {
-    let convos = db::conversations::list_conversations(&state.db, &auth.user_id).await?;
-    Ok(Json(convos.into_iter().map(Into::into).collect()))
+    Query(query): Query<ListConversationsQuery>,
+) -> Result<Json<Vec<ConversationListItem>>, AppError> {
+    if query
+        .q
+        .as_ref()
+        .is_some_and(|q| q.chars().count() > MAX_CONVERSATION_QUERY_CHARS)
+    {
+        return Err(AppError::BadRequest(format!(
+            "q must be at most {MAX_CONVERSATION_QUERY_CHARS} characters"
+        )));
+    }
+    let filter = db::conversations::ConversationFilter {
+        pinned: query.pinned,
+        include_archived: query.include_archived,
+        query: query.q.map(|q| q.trim().to_string()),
+        tag: query.tag,
+    };
+    let convos =
+        db::conversations::list_conversations_with_preview(&state.db, &auth.user_id, &filter)
+            .await?;
+    let ids: Vec<String> = convos.iter().map(|c| c.conversation.id.clone()).collect();
+    let mut tags = db::conversations::list_tags_for_conversations(&state.db, &ids).await?;
+    Ok(Json(
+        convos
+            .into_iter()
+            .map(|c| {
+                let mut item = ConversationListItem::from(c);
+                item.conversation.tags =
+                    Some(tags.remove(&item.conversation.id).unwrap_or_default());
+                item
+            })
+            .collect(),
+    ))
 }
 
 #[derive(Deserialize)]
@@ -204,6 +396,41 @@ pub struct CreateConversationRequest {
     pub image_model: Option<String>,
     pub thinking_budget: Option<i64>,
     pub subagent_thinking_budget: Option<i64>,
+    pub container_idle_timeout_secs: Option<i64>,
+}
+
+/// Fails with 429 once the user owns as many conversations as their quota allows.
+async fn ensure_conversation_quota(state: &AppState, user_id: &str) -> Result<(), AppError> {
+    match db::user_quotas::conversation_limit_reached(&state.db, &state.config, user_id).await? {
+        Some(max) => Err(AppError::QuotaExceeded(format!(
+            "limit of {max} conversations reached"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Fails with 429 when `adding` more messages would exceed the user's
+/// per-conversation message quota.
+async fn ensure_message_quota(
+    state: &AppState,
+    user_id: &str,
+    conversation_id: &str,
+    adding: usize,
+) -> Result<(), AppError> {
+    match db::user_quotas::message_limit_reached(
+        &state.db,
+        &state.config,
+        user_id,
+        conversation_id,
+        adding,
+    )
+    .await?
+    {
+        Some(max) => Err(AppError::QuotaExceeded(format!(
+            "limit of {max} messages per conversation reached"
+        ))),
+        None => Ok(()),
+    }
 }
 
 async fn create_conversation(
@@ -213,18 +440,47 @@ async fn create_conversation(
 ) -> Result<(StatusCode, Json<ConversationResponse>), AppError> {
     validate_optional_budget("thinking_budget", req.thinking_budget)?;
     validate_optional_budget("subagent_thinking_budget", req.subagent_thinking_budget)?;
+    validate_idle_timeout(req.container_idle_timeout_secs)?;
+    let image_provider_id = normalize_optional_string(req.image_provider_id.as_deref());
+    let image_model = normalize_optional_string(req.image_model.as_deref());
+    ensure_model_features(
+        &state.features,
+        req.deep_thinking == Some(true)
+            || req.subagent_provider_id.is_some()
+            || req.subagent_model.is_some()
+            || req.subagent_thinking_budget.is_some(),
+        image_provider_id.is_some() || image_model.is_some(),
+    )?;
+    ensure_conversation_quota(&state, &auth.user_id).await?;
 
     let title = req.title.unwrap_or_else(|| "New Conversation".into());
+    let provider_id = normalize_optional_string(req.provider_id.as_deref());
+    let model_name = normalize_optional_string(req.model_name.as_deref());
+    // Without deep thinking the subagent just mirrors the main model.
+    let (subagent_provider_id, subagent_model) = if state.features.enable_deep_thinking {
+        (
+            normalize_optional_string(req.subagent_provider_id.as_deref()),
+            normalize_optional_string(req.subagent_model.as_deref()),
+        )
+    } else {
+        (provider_id.clone(), model_name.clone())
+    };
     let providers = db::providers::list_providers(&state.db, &auth.user_id).await?;
     let validated_models = validate_conversation_models(
         &providers,
-        normalize_optional_string(req.provider_id.as_deref()),
-        normalize_optional_string(req.model_name.as_deref()),
-        normalize_optional_string(req.subagent_provider_id.as_deref()),
-        normalize_optional_string(req.subagent_model.as_deref()),
-        normalize_optional_string(req.image_provider_id.as_deref()),
-        normalize_optional_string(req.image_model.as_deref()),
+        provider_id,
+        model_name,
+        subagent_provider_id,
+        subagent_model,
+        image_provider_id,
+        image_model,
     )?;
+    ensure_image_provider_type(
+        &state,
+        &auth.user_id,
+        validated_models.image_provider_id.as_deref(),
+    )
+    .await?;
 
     let thinking_budget = req.thinking_budget.unwrap_or(DEFAULT_THINKING_BUDGET);
     let subagent_thinking_budget = req.subagent_thinking_budget.unwrap_or(thinking_budget);
@@ -237,11 +493,13 @@ async fn create_conversation(
         Some(validated_models.model_name.as_str()),
         Some(validated_models.subagent_provider_id.as_str()),
         Some(validated_models.subagent_model.as_str()),
-        req.deep_thinking.unwrap_or(true),
+        req.deep_thinking
+            .unwrap_or(state.features.enable_deep_thinking),
         validated_models.image_provider_id.as_deref(),
         validated_models.image_model.as_deref(),
         Some(thinking_budget),
         Some(subagent_thinking_budget),
+        req.container_idle_timeout_secs,
     )
     .await?;
 
@@ -260,7 +518,63 @@ async fn get_conversation(
     let conv = db::conversations::get_conversation(&state.db, &id, &auth.user_id)
         .await?
         .ok_or(AppError::NotFound)?;
-    Ok(Json(conv.into()))
+    let tags = db::conversations::list_tags(&state.db, &id).await?;
+    let message_count = db::messages::count_messages(&state.db, &id).await?;
+    Ok(Json(ConversationResponse {
+        tags: Some(tags),
+        message_count: Some(message_count),
+        ..conv.into()
+    }))
+}
+
+const MAX_TAG_CHARS: usize = 50;
+
+#[derive(Deserialize)]
+pub struct AddTagRequest {
+    pub tag: String,
+}
+
+#[derive(Serialize)]
+pub struct TagsResponse {
+    pub tags: Vec<String>,
+}
+
+/// Tag a conversation; adding a tag it already has is a no-op.
+async fn add_conversation_tag(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Json(req): Json<AddTagRequest>,
+) -> Result<(StatusCode, Json<TagsResponse>), AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let tag = req.tag.trim();
+    if tag.is_empty() || tag.chars().count() > MAX_TAG_CHARS {
+        return Err(AppError::BadRequest(format!(
+            "Tag must be 1 to {MAX_TAG_CHARS} characters"
+        )));
+    }
+    db::conversations::add_tag(&state.db, &id, tag).await?;
+    let tags = db::conversations::list_tags(&state.db, &id).await?;
+    Ok((StatusCode::CREATED, Json(TagsResponse { tags })))
+}
+
+async fn remove_conversation_tag(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((id, tag)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if db::conversations::remove_tag(&state.db, &id, &tag).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound)
+    }
 }
 
 #[derive(Deserialize)]
@@ -276,6 +590,7 @@ pub struct UpdateConversationRequest {
     pub image_model: Option<String>,
     pub thinking_budget: Option<i64>,
     pub subagent_thinking_budget: Option<i64>,
+    pub container_idle_timeout_secs: Option<i64>,
 }
 
 async fn update_conversation(
@@ -286,6 +601,16 @@ async fn update_conversation(
 ) -> Result<Json<ConversationResponse>, AppError> {
     validate_optional_budget("thinking_budget", req.thinking_budget)?;
     validate_optional_budget("subagent_thinking_budget", req.subagent_thinking_budget)?;
+    validate_idle_timeout(req.container_idle_timeout_secs)?;
+    ensure_model_features(
+        &state.features,
+        req.deep_thinking == Some(true)
+            || req.subagent_provider_id.is_some()
+            || req.subagent_model.is_some()
+            || req.subagent_thinking_budget.is_some(),
+        normalize_optional_string(req.image_provider_id.as_deref()).is_some()
+            || normalize_optional_string(req.image_model.as_deref()).is_some(),
+    )?;
 
     let existing = db::conversations::get_conversation(&state.db, &id, &auth.user_id)
         .await?
@@ -300,6 +625,8 @@ async fn update_conversation(
         Some(value) => normalize_optional_string(Some(value)),
         None => normalize_optional_string(existing.model_name.as_deref()),
     };
+    // With deep thinking off, subagent fields were rejected above, so the
+    // stored values are kept and only ignored when the container starts.
     let subagent_provider_id = match req.subagent_provider_id.as_deref() {
         Some(value) => normalize_optional_string(Some(value)),
         None => normalize_optional_string(existing.subagent_provider_id.as_deref()),
@@ -318,6 +645,9 @@ async fn update_conversation(
     let subagent_thinking_budget = req
         .subagent_thinking_budget
         .or(existing.subagent_thinking_budget);
+    let container_idle_timeout_secs = req
+        .container_idle_timeout_secs
+        .or(existing.container_idle_timeout_secs);
     let image_provider_id = match req.image_provider_id.as_deref() {
         Some(value) => normalize_optional_string(Some(value)),
         None => normalize_optional_string(existing.image_provider_id.as_deref()),
@@ -336,6 +666,12 @@ async fn update_conversation(
         image_provider_id,
         image_model,
     )?;
+    ensure_image_provider_type(
+        &state,
+        &auth.user_id,
+        validated_models.image_provider_id.as_deref(),
+    )
+    .await?;
 
     // If provider_id or model changed, stop the running container so it
     // re-initialises with the new config on the next message.
@@ -358,23 +694,21 @@ async fn update_conversation(
         || image_provider_changed
         || image_model_changed
     {
+        let status = serde_json::json!({
+            "type": "container_status",
+            "conversation_id": &id,
+            "status": "restarting",
+            "reason": "model_switch",
+            "message": "Switching model. Restarting container..."
+        })
+        .to_string();
         state
             .ws_state
-            .send_to_client(
-                &auth.user_id,
-                &id,
-                &serde_json::json!({
-                    "type": "container_status",
-                    "conversation_id": &id,
-                    "status": "restarting",
-                    "reason": "model_switch",
-                    "message": "Switching model. Restarting container..."
-                })
-                .to_string(),
-            )
+            .send_to_client(&auth.user_id, &id, &status)
             .await;
         let _ = state.docker_manager.stop_container(&id).await;
         state.ws_state.remove_container(&id).await;
+        state.sse_state.close(&id, &status).await;
     }
 
     let conv = db::conversations::update_conversation_with_subagent(
@@ -392,163 +726,1242 @@ async fn update_conversation(
         validated_models.image_model.as_deref(),
         thinking_budget,
         subagent_thinking_budget,
+        container_idle_timeout_secs,
     )
     .await?
     .ok_or(AppError::NotFound)?;
     Ok(Json(conv.into()))
 }
 
-async fn delete_conversation(
+#[derive(Serialize)]
+pub struct ContainerStatusResponse {
+    pub status: &'static str,
+    pub pending_message: bool,
+}
+
+/// Polling fallback for clients that cannot keep a WebSocket open.
+async fn get_container_status(
     State(state): State<Arc<AppState>>,
     auth: AuthUser,
     Path(id): Path<String>,
-) -> Result<StatusCode, AppError> {
+) -> Result<Json<ContainerStatusResponse>, AppError> {
     db::conversations::get_conversation(&state.db, &id, &auth.user_id)
         .await?
         .ok_or(AppError::NotFound)?;
 
-    if let Err(e) = state.docker_manager.stop_container(&id).await {
-        tracing::warn!("Failed to stop container for conversation {}: {}", id, e);
-    }
-    state.ws_state.remove_container(&id).await;
-    let _ = state.ws_state.take_pending_message(&id).await;
-
-    let workspace_dir = format!("data/conversations/{}", id);
-    match tokio::fs::remove_dir_all(&workspace_dir).await {
-        Ok(_) => {}
-        Err(e) if e.kind() == ErrorKind::NotFound => {}
-        Err(e) => {
-            tracing::error!(
-                "Failed to remove workspace for conversation {} at {}: {}",
-                id,
-                workspace_dir,
-                e
-            );
-            return Err(AppError::Internal(
-                "failed to delete conversation workspace".into(),
-            ));
-        }
+    let status = if state.ws_state.is_container_connected(&id).await {
+        "connected"
+    } else {
+        "disconnected"
     };
+    Ok(Json(ContainerStatusResponse {
+        status,
+        pending_message: state.ws_state.has_pending_message(&id).await,
+    }))
+}
 
-    if db::conversations::delete_conversation(&state.db, &id, &auth.user_id).await? {
-        Ok(StatusCode::NO_CONTENT)
+/// Follow the conversation container's output as server-sent events, one
+/// `data:` event per log chunk. The stream closes when the container exits.
+async fn stream_container_logs(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let logs = state
+        .docker_manager
+        .stream_container_logs(&id)
+        .await
+        .map_err(|e| match e {
+            DockerError::NotRunning => AppError::Conflict("Container is not running".into()),
+            e => AppError::Internal(e.to_string()),
+        })?;
+    let events = logs
+        .take_while(|chunk| {
+            if let Err(e) = chunk {
+                tracing::warn!(error = %e, "Container log stream failed");
+            }
+            std::future::ready(chunk.is_ok())
+        })
+        .filter_map(|chunk| {
+            std::future::ready(
+                chunk
+                    .ok()
+                    .map(|text| Ok(Event::default().data(text.trim_end_matches('\n')))),
+            )
+        });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Serialize)]
+pub struct ContainerStateResponse {
+    pub status: &'static str,
+    pub container_id: Option<String>,
+    pub uptime_secs: Option<u64>,
+}
+
+/// Container lifecycle state for page reloads and dashboards: `running`
+/// once the agent has connected, `starting` while a container exists but
+/// has not connected yet, `stopped` otherwise.
+async fn get_container_state(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ContainerStateResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let Some(info) = state.docker_manager.inspect_container(&id).await else {
+        return Ok(Json(ContainerStateResponse {
+            status: "stopped",
+            container_id: None,
+            uptime_secs: None,
+        }));
+    };
+    let status = if state.ws_state.is_container_connected(&id).await {
+        "running"
     } else {
-        Err(AppError::NotFound)
-    }
+        "starting"
+    };
+    Ok(Json(ContainerStateResponse {
+        status,
+        container_id: Some(info.container_id),
+        uptime_secs: Some(info.started_at.elapsed().as_secs()),
+    }))
 }
 
 #[derive(Deserialize)]
-pub struct PaginationParams {
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
+pub struct CopyFileQuery {
+    pub dest_path: String,
 }
 
 #[derive(Serialize)]
-pub struct MessagesResponse {
-    pub messages: Vec<MessageResponse>,
-    pub total: i64,
+pub struct CopyFileResponse {
+    pub copied: bool,
 }
 
-#[derive(Serialize)]
-pub struct MessageResponse {
-    pub id: String,
-    pub role: String,
-    pub content: String,
-    pub parts: Vec<MessagePartResponse>,
-    pub tool_calls: Option<String>,
-    pub tool_call_id: Option<String>,
-    pub token_count: Option<i64>,
-    pub created_at: String,
+/// Copy the first file of a multipart upload into the conversation's running
+/// container at `dest_path`, which must lie inside `/workspace`.
+async fn copy_file_to_container(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Query(query): Query<CopyFileQuery>,
+    mut multipart: Multipart,
+) -> Result<Json<CopyFileResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if workspace_relative_path(&query.dest_path).is_none() {
+        return Err(AppError::BadRequest(
+            "dest_path must be a file path inside /workspace".into(),
+        ));
+    }
+
+    let field = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?
+        .ok_or_else(|| AppError::BadRequest("No file provided".into()))?;
+    let contents = field
+        .bytes()
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    state
+        .docker_manager
+        .copy_to_container(&id, &contents, &query.dest_path)
+        .await
+        .map_err(|e| match e {
+            DockerError::NotRunning => AppError::Conflict("Container is not running".into()),
+            e => AppError::Internal(e.to_string()),
+        })?;
+
+    Ok(Json(CopyFileResponse { copied: true }))
 }
 
-#[derive(Serialize, Clone)]
-pub struct MessagePartResponse {
-    #[serde(rename = "type")]
-    pub part_type: String,
-    pub text: Option<String>,
-    pub json_payload: Option<serde_json::Value>,
-    pub tool_call_id: Option<String>,
-    pub seq: Option<i64>,
+/// Push the conversation's current provider API keys to its running
+/// container, e.g. after the user rotated a key.
+async fn update_container_key(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let conv = db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let conv = crate::ws::container::with_feature_flags(conv, &state.features);
+    let providers = db::providers::list_providers(&state.db, &auth.user_id).await?;
+    let resolved = crate::ws::container::resolve_conversation_providers(&conv, &providers)
+        .map_err(AppError::BadRequest)?;
+
+    let decrypt = |provider: &db::providers::UserProvider| {
+        crate::crypto::decrypt(&provider.api_key_encrypted, &state.config.encryption_key).map_err(
+            |e| AppError::Internal(format!("failed to decrypt provider {}: {e}", provider.id)),
+        )
+    };
+
+    let mut env_vars = HashMap::from([
+        ("api_key".to_string(), decrypt(&resolved.chat_provider)?),
+        (
+            "subagent_api_key".to_string(),
+            decrypt(&resolved.subagent_provider)?,
+        ),
+    ]);
+    if let Some(image_provider) = resolved.image_provider.as_ref() {
+        env_vars.insert("image_api_key".to_string(), decrypt(image_provider)?);
+    }
+
+    state
+        .docker_manager
+        .update_container_env(&state.ws_state, &id, env_vars)
+        .await
+        .map_err(|e| match e {
+            DockerError::NotRunning => AppError::Conflict("Container is not running".into()),
+            e => AppError::Internal(e.to_string()),
+        })?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
-fn legacy_parts_from_message(m: &db::messages::Message) -> Vec<MessagePartResponse> {
-    db::messages_v2::legacy_message_to_parts(m)
-        .into_iter()
-        .enumerate()
-        .map(|(idx, p)| MessagePartResponse {
-            part_type: p.part_type,
-            text: p.text,
-            json_payload: p
-                .json_payload
-                .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok()),
-            tool_call_id: p.tool_call_id,
-            seq: Some(idx as i64),
-        })
-        .collect()
+#[derive(Deserialize)]
+pub struct PinConversationRequest {
+    pub pinned: bool,
 }
 
-async fn list_messages(
+async fn set_conversation_pinned(
     State(state): State<Arc<AppState>>,
     auth: AuthUser,
     Path(id): Path<String>,
-    Query(params): Query<PaginationParams>,
-) -> Result<Json<MessagesResponse>, AppError> {
-    // Verify conversation belongs to user
-    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+    Json(req): Json<PinConversationRequest>,
+) -> Result<Json<ConversationResponse>, AppError> {
+    let conv = db::conversations::set_pinned(&state.db, &id, &auth.user_id, req.pinned)
         .await?
         .ok_or(AppError::NotFound)?;
+    Ok(Json(conv.into()))
+}
 
-    let limit = params.limit.unwrap_or(50).min(100);
-    let offset = params.offset.unwrap_or(0);
-
-    let messages = db::messages::list_messages(&state.db, &id, limit, offset).await?;
-    let total = db::messages::count_messages(&state.db, &id).await?;
-    let message_ids = messages.iter().map(|m| m.id.clone()).collect::<Vec<_>>();
-    let existing_v2_ids =
-        db::messages_v2::list_existing_message_v2_ids(&state.db, &message_ids).await?;
-    let parts_by_message_id =
-        db::messages_v2::list_message_parts_for_messages(&state.db, &message_ids).await?;
+async fn pin_conversation(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ConversationResponse>, AppError> {
+    let conv = db::conversations::set_pinned(&state.db, &id, &auth.user_id, true)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    Ok(Json(conv.into()))
+}
 
-    Ok(Json(MessagesResponse {
-        messages: {
-            let mut out: Vec<MessageResponse> = Vec::with_capacity(messages.len());
-            for m in messages {
-                let parts = if existing_v2_ids.contains(&m.id) {
-                    parts_by_message_id
-                        .get(&m.id)
-                        .cloned()
-                        .unwrap_or_default()
-                        .into_iter()
-                        .map(|p| MessagePartResponse {
-                            part_type: p.part_type,
-                            text: p.text,
-                            json_payload: p
-                                .json_payload
-                                .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok()),
-                            tool_call_id: p.tool_call_id,
-                            seq: Some(p.seq),
-                        })
-                        .collect()
-                } else {
-                    legacy_parts_from_message(&m)
-                };
-                out.push(MessageResponse {
-                    id: m.id,
-                    role: m.role,
-                    content: m.content,
-                    parts,
-                    tool_calls: m.tool_calls,
-                    tool_call_id: m.tool_call_id,
-                    token_count: m.token_count,
-                    created_at: m.created_at,
-                });
+async fn unpin_conversation(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ConversationResponse>, AppError> {
+    let conv = db::conversations::set_pinned(&state.db, &id, &auth.user_id, false)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    Ok(Json(conv.into()))
+}
+
+#[derive(Serialize)]
+pub struct ConversationStatsResponse {
+    #[serde(flatten)]
+    pub stats: db::conversations::ConversationStats,
+    pub token_usage: db::messages::TokenUsageSummary,
+}
+
+async fn get_conversation_stats(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ConversationStatsResponse>, AppError> {
+    let stats = db::conversations::conversation_stats(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let token_usage = db::messages::sum_token_usage(&state.db, &id).await?;
+    Ok(Json(ConversationStatsResponse { stats, token_usage }))
+}
+
+#[derive(Deserialize)]
+pub struct TokenUsageParams {
+    pub bucket: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TokenUsageResponse {
+    #[serde(flatten)]
+    pub summary: db::messages::TokenUsageSummary,
+    pub bucket: String,
+    pub buckets: Vec<db::messages::TokenBucket>,
+}
+
+/// Token totals for a conversation plus a per-`bucket` breakdown (`day` by
+/// default) for charting.
+async fn get_token_usage(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Query(params): Query<TokenUsageParams>,
+) -> Result<Json<TokenUsageResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let bucket = params.bucket.unwrap_or_else(|| "day".into());
+    if !db::messages::TOKEN_USAGE_BUCKETS.contains(&bucket.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "bucket must be one of: {}",
+            db::messages::TOKEN_USAGE_BUCKETS.join(", ")
+        )));
+    }
+    let summary = db::messages::sum_token_usage(&state.db, &id).await?;
+    let buckets = db::messages::token_usage_over_time(&state.db, &id, &bucket).await?;
+    Ok(Json(TokenUsageResponse {
+        summary,
+        bucket,
+        buckets,
+    }))
+}
+
+#[derive(Serialize)]
+pub struct ConversationCostResponse {
+    pub conversation_id: String,
+    #[serde(flatten)]
+    pub cost: db::pricing::ConversationCost,
+}
+
+/// Total spend on a conversation, from the costs recorded as each assistant
+/// message completed.
+async fn get_conversation_cost(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ConversationCostResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let cost = db::pricing::get_conversation_cost(&state.db, &id).await?;
+    Ok(Json(ConversationCostResponse {
+        conversation_id: id,
+        cost,
+    }))
+}
+
+async fn archive_conversation(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ConversationResponse>, AppError> {
+    let conv = db::conversations::archive_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    Ok(Json(conv.into()))
+}
+
+async fn unarchive_conversation(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ConversationResponse>, AppError> {
+    let conv = db::conversations::unarchive_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    Ok(Json(conv.into()))
+}
+
+async fn delete_conversation(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    client: SessionClient,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    release_conversation_resources(&state, &id).await;
+    if let Err(e) = remove_workspace(&id).await {
+        tracing::error!("Failed to remove workspace for conversation {}: {}", id, e);
+        return Err(AppError::Internal(
+            "failed to delete conversation workspace".into(),
+        ));
+    }
+
+    if db::conversations::delete_conversation(&state.db, &id, &auth.user_id).await? {
+        db::audit_log::record(
+            &state.db,
+            &client,
+            AuditEvent::new("conversation_delete", Some(&auth.user_id)).target("conversation", &id),
+        )
+        .await;
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound)
+    }
+}
+
+/// Stop the conversation's container and drop its agent connection and any
+/// message still waiting for it.
+pub(crate) async fn release_conversation_resources(state: &AppState, id: &str) {
+    if let Err(e) = state.docker_manager.stop_container(id).await {
+        tracing::warn!("Failed to stop container for conversation {}: {}", id, e);
+    }
+    state.ws_state.remove_container(id).await;
+    let _ = state.ws_state.take_pending_message(id).await;
+    state.sse_state.close(id, &stopped_status(id)).await;
+}
+
+/// Final frame for assistant streams whose container was stopped.
+pub(crate) fn stopped_status(id: &str) -> String {
+    serde_json::json!({
+        "type": "container_status",
+        "conversation_id": id,
+        "status": "stopped",
+        "message": "Container stopped"
+    })
+    .to_string()
+}
+
+/// Remove a conversation's workspace directory; a missing one is fine.
+pub(crate) async fn remove_workspace(id: &str) -> std::io::Result<()> {
+    match tokio::fs::remove_dir_all(format!("data/conversations/{}", id)).await {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+const MAX_BULK_DELETE: usize = 500;
+
+#[derive(Deserialize)]
+pub struct BulkDeleteRequest {
+    pub ids: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct BulkDeleteFailure {
+    pub id: String,
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+pub struct BulkDeleteResponse {
+    pub deleted: u64,
+    pub failed: Vec<BulkDeleteFailure>,
+}
+
+/// Delete several conversations at once. IDs that aren't the caller's, or
+/// whose workspace can't be removed, are reported in `failed` and left alone.
+async fn bulk_delete_conversations(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    client: SessionClient,
+    Json(req): Json<BulkDeleteRequest>,
+) -> Result<Json<BulkDeleteResponse>, AppError> {
+    if req.ids.is_empty() {
+        return Err(AppError::BadRequest("ids must not be empty".into()));
+    }
+    if req.ids.len() > MAX_BULK_DELETE {
+        return Err(AppError::BadRequest(format!(
+            "At most {MAX_BULK_DELETE} conversations can be deleted at once"
+        )));
+    }
+    let mut ids = req.ids;
+    ids.sort();
+    ids.dedup();
+
+    let owned: HashSet<String> =
+        db::conversations::owned_conversation_ids(&state.db, &auth.user_id, &ids)
+            .await?
+            .into_iter()
+            .collect();
+    let mut failed = Vec::new();
+    let mut deletable = Vec::new();
+    for id in ids {
+        if owned.contains(&id) {
+            deletable.push(id);
+        } else {
+            failed.push(BulkDeleteFailure {
+                id,
+                reason: "not found".into(),
+            });
+        }
+    }
+
+    for id in &deletable {
+        release_conversation_resources(&state, id).await;
+    }
+    // Each workspace succeeds or fails on its own, so one bad directory
+    // doesn't block deleting the rest.
+    let removals =
+        futures_util::future::join_all(deletable.iter().map(|id| remove_workspace(id))).await;
+    let mut removed = Vec::with_capacity(deletable.len());
+    for (id, result) in deletable.into_iter().zip(removals) {
+        match result {
+            Ok(()) => removed.push(id),
+            Err(e) => {
+                tracing::error!("Failed to remove workspace for conversation {}: {}", id, e);
+                failed.push(BulkDeleteFailure {
+                    id,
+                    reason: "failed to delete conversation workspace".into(),
+                });
+            }
+        }
+    }
+
+    let deleted =
+        db::conversations::delete_conversations(&state.db, &auth.user_id, &removed).await?;
+    for id in &removed {
+        db::audit_log::record(
+            &state.db,
+            &client,
+            AuditEvent::new("conversation_delete", Some(&auth.user_id))
+                .target("conversation", id)
+                .details(serde_json::json!({ "bulk": true })),
+        )
+        .await;
+    }
+    Ok(Json(BulkDeleteResponse { deleted, failed }))
+}
+
+#[derive(Deserialize)]
+pub struct PaginationParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ListMessagesParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// Return messages after this message ID instead of using `offset`.
+    pub after: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct MessagesResponse {
+    pub messages: Vec<MessageResponse>,
+    pub total: i64,
+    /// ID to pass as `after` for the next page; absent on the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct MessageResponse {
+    pub id: String,
+    pub role: String,
+    pub content: String,
+    pub parts: Vec<MessagePartResponse>,
+    pub tool_calls: Option<String>,
+    pub tool_call_id: Option<String>,
+    pub token_count: Option<i64>,
+    pub created_at: String,
+    /// Reaction count per emoji.
+    pub reactions: BTreeMap<String, i64>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct MessagePartResponse {
+    #[serde(rename = "type")]
+    pub part_type: String,
+    pub text: Option<String>,
+    pub json_payload: Option<serde_json::Value>,
+    pub tool_call_id: Option<String>,
+    pub seq: Option<i64>,
+}
+
+fn legacy_parts_from_message(m: &db::messages::Message) -> Vec<MessagePartResponse> {
+    db::messages_v2::legacy_message_to_parts(m)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, p)| MessagePartResponse {
+            part_type: p.part_type,
+            text: p.text,
+            json_payload: p
+                .json_payload
+                .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok()),
+            tool_call_id: p.tool_call_id,
+            seq: Some(idx as i64),
+        })
+        .collect()
+}
+
+async fn build_message_responses(
+    pool: &sqlx::SqlitePool,
+    messages: Vec<db::messages::Message>,
+) -> Result<Vec<MessageResponse>, AppError> {
+    let message_ids = messages.iter().map(|m| m.id.clone()).collect::<Vec<_>>();
+    let existing_v2_ids = db::messages_v2::list_existing_message_v2_ids(pool, &message_ids).await?;
+    let parts_by_message_id =
+        db::messages_v2::list_message_parts_for_messages(pool, &message_ids).await?;
+    let mut reactions = db::messages::count_reactions_for_messages(pool, &message_ids).await?;
+
+    let mut out: Vec<MessageResponse> = Vec::with_capacity(messages.len());
+    for m in messages {
+        let parts = if existing_v2_ids.contains(&m.id) {
+            parts_by_message_id
+                .get(&m.id)
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|p| MessagePartResponse {
+                    part_type: p.part_type,
+                    text: p.text,
+                    json_payload: p
+                        .json_payload
+                        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok()),
+                    tool_call_id: p.tool_call_id,
+                    seq: Some(p.seq),
+                })
+                .collect()
+        } else {
+            legacy_parts_from_message(&m)
+        };
+        out.push(MessageResponse {
+            reactions: reactions.remove(&m.id).unwrap_or_default(),
+            id: m.id,
+            role: m.role,
+            content: m.content,
+            parts,
+            tool_calls: m.tool_calls,
+            tool_call_id: m.tool_call_id,
+            token_count: m.token_count,
+            created_at: m.created_at,
+        });
+    }
+    Ok(out)
+}
+
+async fn list_messages(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Query(params): Query<ListMessagesParams>,
+) -> Result<Json<MessagesResponse>, AppError> {
+    // Verify conversation belongs to user
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let limit = params.limit.unwrap_or(50).min(100);
+
+    // Fetch one extra row to tell whether another page follows.
+    let mut messages = match &params.after {
+        Some(after) => {
+            let in_conversation = db::messages::get_message(&state.db, after)
+                .await?
+                .is_some_and(|m| m.conversation_id == id);
+            if !in_conversation {
+                return Err(AppError::BadRequest("Invalid cursor".into()));
             }
-            out
-        },
+            db::messages::list_messages_after(&state.db, &id, after, limit + 1).await?
+        }
+        None => {
+            let offset = params.offset.unwrap_or(0);
+            db::messages::list_messages(&state.db, &id, limit + 1, offset).await?
+        }
+    };
+    let has_more = messages.len() as i64 > limit;
+    messages.truncate(limit.max(0) as usize);
+    let next_cursor = has_more
+        .then(|| messages.last().map(|m| m.id.clone()))
+        .flatten();
+    let total = db::messages::count_messages(&state.db, &id).await?;
+
+    Ok(Json(MessagesResponse {
+        messages: build_message_responses(&state.db, messages).await?,
+        total,
+        next_cursor,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct StreamMessageRequest {
+    pub content: String,
+    /// Client-chosen UUID for the saved user message.
+    pub message_id: Option<String>,
+}
+
+/// Send a user message and stream the assistant's reply as server-sent
+/// events: one `data:` event per frame relayed from the container, then
+/// `data: [DONE]` once the turn completes. The stream ends early if the
+/// container reports an error, stops or disconnects, or goes quiet for
+/// [`ASSISTANT_STREAM_IDLE_TIMEOUT`](crate::ws::sse::ASSISTANT_STREAM_IDLE_TIMEOUT).
+///
+/// Starts the container when needed; the message is queued until the
+/// container's init handshake completes, so no events arrive before then.
+async fn stream_message(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Json(req): Json<StreamMessageRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>> + use<>>, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if req.content.is_empty() {
+        return Err(AppError::BadRequest("content must not be empty".into()));
+    }
+    if let Some(message_id) = &req.message_id
+        && uuid::Uuid::parse_str(message_id).is_err()
+    {
+        return Err(AppError::BadRequest("message_id must be a UUID".into()));
+    }
+    ensure_message_quota(
+        &state,
+        &auth.user_id,
+        &id,
+        db::user_quotas::MESSAGES_PER_TURN,
+    )
+    .await?;
+
+    let connected = state.ws_state.is_container_connected(&id).await;
+    if !connected {
+        state
+            .docker_manager
+            .start_container(&id, &auth.user_id)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to start container: {e}")))?;
+    }
+
+    // Subscribe before the message goes out so no frames are missed.
+    let rx = state.sse_state.subscribe(&id).await;
+
+    let (msg, conv) = crate::ws::client::save_user_message(
+        &state.db,
+        &id,
+        &auth.user_id,
+        req.message_id.as_deref(),
+        &req.content,
+    )
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+            AppError::Conflict("message_id already exists".into())
+        }
+        e => e.into(),
+    })?;
+
+    let frame = serde_json::json!({
+        "type": "user_message",
+        "message_id": msg.id,
+        "content": req.content,
+        "deep_thinking": state.features.enable_deep_thinking
+            && conv.as_ref().is_some_and(|c| c.deep_thinking),
+        "thinking_budget": conv.as_ref().and_then(|c| c.thinking_budget),
+        "subagent_thinking_budget": conv.as_ref().and_then(|c| c.subagent_thinking_budget),
+    })
+    .to_string();
+    if state.ws_state.send_to_container(&id, &frame).await {
+        state.docker_manager.touch_activity(&id).await;
+    } else {
+        // The container handler forwards this once the init handshake is done.
+        state.ws_state.set_pending_message(&id, frame).await;
+    }
+
+    Ok(Sse::new(crate::ws::sse::assistant_events(
+        rx,
+        crate::ws::sse::ASSISTANT_STREAM_IDLE_TIMEOUT,
+    ))
+    .keep_alive(KeepAlive::default()))
+}
+
+#[derive(Deserialize)]
+pub struct SearchMessagesParams {
+    pub q: String,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Full-text search within one conversation; `total` counts all matches.
+async fn search_messages(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Query(params): Query<SearchMessagesParams>,
+) -> Result<Json<MessagesResponse>, AppError> {
+    if params.q.trim().is_empty() {
+        return Err(AppError::BadRequest(
+            "Search query must not be empty".into(),
+        ));
+    }
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let messages =
+        db::messages::search_messages(&state.db, &id, &auth.user_id, &params.q, limit, offset)
+            .await?;
+    let total =
+        db::messages::count_search_messages(&state.db, &id, &auth.user_id, &params.q).await?;
+
+    Ok(Json(MessagesResponse {
+        messages: build_message_responses(&state.db, messages).await?,
         total,
+        next_cursor: None,
+    }))
+}
+
+const MAX_CONTEXT_WINDOW: usize = 50;
+
+#[derive(Deserialize)]
+pub struct MessagesAroundParams {
+    pub anchor_id: String,
+    pub before: Option<usize>,
+    pub after: Option<usize>,
+}
+
+async fn list_messages_around(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Query(params): Query<MessagesAroundParams>,
+) -> Result<Json<MessagesResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let before = params.before.unwrap_or(5).min(MAX_CONTEXT_WINDOW);
+    let after = params.after.unwrap_or(5).min(MAX_CONTEXT_WINDOW);
+
+    let messages =
+        db::messages::get_messages_around(&state.db, &id, &params.anchor_id, before, after).await?;
+    if messages.is_empty() {
+        return Err(AppError::NotFound);
+    }
+    let total = db::messages::count_messages(&state.db, &id).await?;
+
+    Ok(Json(MessagesResponse {
+        messages: build_message_responses(&state.db, messages).await?,
+        total,
+        next_cursor: None,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct MessageContextParams {
+    pub n: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct MessageContextResponse {
+    pub anchor: MessageResponse,
+    pub before: Vec<MessageResponse>,
+    pub after: Vec<MessageResponse>,
+}
+
+/// A message plus up to `n` (default 5) messages on either side, e.g. for
+/// showing a search hit in context.
+async fn get_message_context(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((id, msg_id)): Path<(String, String)>,
+    Query(params): Query<MessageContextParams>,
+) -> Result<Json<MessageContextResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let n = params.n.unwrap_or(5).min(MAX_CONTEXT_WINDOW);
+    let messages = db::messages::get_messages_around(&state.db, &id, &msg_id, n, n).await?;
+    let mut before = build_message_responses(&state.db, messages).await?;
+    let anchor_idx = before
+        .iter()
+        .position(|m| m.id == msg_id)
+        .ok_or(AppError::NotFound)?;
+    let after = before.split_off(anchor_idx + 1);
+    let anchor = before.pop().ok_or(AppError::NotFound)?;
+
+    Ok(Json(MessageContextResponse {
+        anchor,
+        before,
+        after,
     }))
 }
 
+const MAX_EMOJI_CHARS: usize = 16;
+
+#[derive(Deserialize)]
+pub struct AddReactionRequest {
+    pub emoji: String,
+}
+
+#[derive(Serialize)]
+pub struct ReactionsResponse {
+    /// Reaction count per emoji.
+    pub reactions: BTreeMap<String, i64>,
+}
+
+/// Check that `msg_id` is a live message in conversation `id` owned by
+/// `user_id`.
+async fn ensure_message_in_conversation(
+    state: &AppState,
+    user_id: &str,
+    id: &str,
+    msg_id: &str,
+) -> Result<(), AppError> {
+    db::conversations::get_conversation(&state.db, id, user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    db::messages::get_message(&state.db, msg_id)
+        .await?
+        .filter(|m| m.conversation_id == id)
+        .ok_or(AppError::NotFound)?;
+    Ok(())
+}
+
+async fn add_message_reaction(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((id, msg_id)): Path<(String, String)>,
+    Json(req): Json<AddReactionRequest>,
+) -> Result<(StatusCode, Json<ReactionsResponse>), AppError> {
+    ensure_message_in_conversation(&state, &auth.user_id, &id, &msg_id).await?;
+
+    let emoji = req.emoji.trim();
+    if emoji.is_empty()
+        || emoji.chars().count() > MAX_EMOJI_CHARS
+        || emoji.chars().any(char::is_whitespace)
+    {
+        return Err(AppError::BadRequest("Invalid emoji".into()));
+    }
+    db::messages::add_reaction(&state.db, &msg_id, &auth.user_id, emoji).await?;
+
+    let mut reactions = BTreeMap::new();
+    for reaction in db::messages::list_reactions_for_message(&state.db, &msg_id).await? {
+        *reactions.entry(reaction.emoji).or_insert(0) += 1;
+    }
+    Ok((StatusCode::CREATED, Json(ReactionsResponse { reactions })))
+}
+
+async fn remove_message_reaction(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((id, msg_id, emoji)): Path<(String, String, String)>,
+) -> Result<StatusCode, AppError> {
+    ensure_message_in_conversation(&state, &auth.user_id, &id, &msg_id).await?;
+
+    if db::messages::remove_reaction(&state.db, &msg_id, &auth.user_id, &emoji).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound)
+    }
+}
+
+/// Bookmark a message; bookmarking it again is a no-op.
+async fn bookmark_message(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((id, msg_id)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    ensure_message_in_conversation(&state, &auth.user_id, &id, &msg_id).await?;
+    db::messages::bookmark_message(&state.db, &auth.user_id, &msg_id, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn unbookmark_message(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((id, msg_id)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    ensure_message_in_conversation(&state, &auth.user_id, &id, &msg_id).await?;
+    if db::messages::unbookmark_message(&state.db, &auth.user_id, &msg_id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound)
+    }
+}
+
+/// Restore a message removed by an edit or regenerate.
+async fn undelete_message(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((id, msg_id)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if !db::messages::undelete_message(&state.db, &id, &msg_id).await? {
+        return Err(AppError::NotFound);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Polling fallback: the most recent message, or `204` if there is none.
+async fn get_last_message(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let Some(message) = db::messages::get_last_message(&state.db, &id, &auth.user_id).await? else {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    };
+    let response = build_message_responses(&state.db, vec![message])
+        .await?
+        .pop()
+        .ok_or_else(|| AppError::Internal("Failed to build message response".into()))?;
+    Ok(Json(response).into_response())
+}
+
+const IMPORTABLE_ROLES: &[&str] = &["user", "assistant", "system", "tool"];
+
+#[derive(Deserialize)]
+pub struct ImportMessage {
+    pub role: String,
+    pub content: String,
+    pub tool_calls: Option<serde_json::Value>,
+    pub tool_call_id: Option<String>,
+    pub token_count: Option<i64>,
+}
+
+async fn import_messages(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Json(req): Json<Vec<ImportMessage>>,
+) -> Result<(StatusCode, Json<MessagesResponse>), AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if req.is_empty() {
+        return Err(AppError::BadRequest("No messages to import".into()));
+    }
+    if req.len() > db::messages::MAX_MESSAGE_BATCH {
+        return Err(AppError::PayloadTooLarge(format!(
+            "At most {} messages can be imported at once",
+            db::messages::MAX_MESSAGE_BATCH
+        )));
+    }
+    if let Some(bad) = req
+        .iter()
+        .find(|m| !IMPORTABLE_ROLES.contains(&m.role.as_str()))
+    {
+        return Err(AppError::BadRequest(format!("Invalid role: {}", bad.role)));
+    }
+    ensure_message_quota(&state, &auth.user_id, &id, req.len()).await?;
+
+    let tool_calls: Vec<Option<String>> = req
+        .iter()
+        .map(|m| {
+            m.tool_calls
+                .as_ref()
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_string())
+        })
+        .collect();
+    let batch: Vec<db::messages::NewMessage<'_>> = req
+        .iter()
+        .zip(&tool_calls)
+        .map(|(m, tool_calls)| db::messages::NewMessage {
+            role: &m.role,
+            content: &m.content,
+            tool_calls: tool_calls.as_deref(),
+            tool_call_id: m.tool_call_id.as_deref(),
+            token_count: m.token_count,
+        })
+        .collect();
+
+    let created = db::messages::create_message_batch(&state.db, &id, &batch).await?;
+    db::conversations::touch_conversation_activity(&state.db, &id, &auth.user_id).await?;
+    let total = db::messages::count_messages(&state.db, &id).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MessagesResponse {
+            messages: build_message_responses(&state.db, created).await?,
+            total,
+            next_cursor: None,
+        }),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    pub format: Option<String>,
+}
+
+fn role_heading(role: &str) -> String {
+    let mut chars = role.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Render an export as a readable Markdown transcript. Text parts are
+/// preferred over the legacy content, which may hold raw content blocks.
+fn export_to_markdown(export: &db::conversations::ConversationExport) -> String {
+    let mut out = format!("# {}\n", export.title);
+    for message in &export.messages {
+        out.push_str(&format!("\n## {}\n\n", role_heading(&message.role)));
+        let texts: Vec<&str> = message
+            .parts
+            .iter()
+            .filter(|p| p.part_type == "text")
+            .filter_map(|p| p.text.as_deref())
+            .collect();
+        if texts.is_empty() {
+            out.push_str(message.content.trim_end());
+        } else {
+            out.push_str(texts.join("\n\n").trim_end());
+        }
+        out.push('\n');
+    }
+    out
+}
+
+async fn export_conversation(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, AppError> {
+    let export = db::conversations::export_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    match params.format.as_deref() {
+        None | Some("json") => Ok(Json(export).into_response()),
+        Some("markdown") => Ok((
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            export_to_markdown(&export),
+        )
+            .into_response()),
+        Some(other) => Err(AppError::BadRequest(format!(
+            "Unsupported export format: {other}"
+        ))),
+    }
+}
+
+async fn import_conversation(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Json(req): Json<db::conversations::ConversationExport>,
+) -> Result<(StatusCode, Json<ConversationResponse>), AppError> {
+    if req.title.trim().is_empty() {
+        return Err(AppError::BadRequest("Title must not be empty".into()));
+    }
+    if let Some(bad) = req
+        .messages
+        .iter()
+        .find(|m| !IMPORTABLE_ROLES.contains(&m.role.as_str()))
+    {
+        return Err(AppError::BadRequest(format!("Invalid role: {}", bad.role)));
+    }
+    validate_optional_budget("thinking_budget", req.thinking_budget)?;
+    validate_optional_budget("subagent_thinking_budget", req.subagent_thinking_budget)?;
+    validate_idle_timeout(req.container_idle_timeout_secs)?;
+    ensure_conversation_quota(&state, &auth.user_id).await?;
+
+    let conv = db::conversations::import_conversation(&state.db, &auth.user_id, req).await?;
+    Ok((StatusCode::CREATED, Json(conv.into())))
+}
+
+#[derive(Deserialize)]
+pub struct ForkConversationRequest {
+    pub from_message_id: String,
+}
+
+/// Branch a conversation into a new one that ends at `from_message_id`.
+async fn fork_conversation(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Json(req): Json<ForkConversationRequest>,
+) -> Result<(StatusCode, Json<ConversationResponse>), AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    ensure_conversation_quota(&state, &auth.user_id).await?;
+
+    let fork = db::conversations::fork_conversation_at_message(
+        &state.db,
+        &id,
+        &auth.user_id,
+        &req.from_message_id,
+    )
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Message not found in this conversation".into()))?;
+    Ok((StatusCode::CREATED, Json(fork.into())))
+}
+
+/// Copy a conversation with its full message history. The share link is not
+/// carried over.
+async fn duplicate_conversation(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<ConversationResponse>), AppError> {
+    ensure_conversation_quota(&state, &auth.user_id).await?;
+    let copy = db::conversations::duplicate_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    Ok((StatusCode::CREATED, Json(copy.into())))
+}
+
+const DEFAULT_SUMMARY_MESSAGES: i64 = 20;
+const MAX_SUMMARY_MESSAGES: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct SummarizeParams {
+    pub count: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct SummaryResponse {
+    pub message_id: String,
+    pub summary: String,
+}
+
+/// Condense the first `count` messages into a `system` message placed at the
+/// start of the conversation.
+async fn summarize_conversation(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Query(params): Query<SummarizeParams>,
+) -> Result<(StatusCode, Json<SummaryResponse>), AppError> {
+    db::conversations::get_conversation(&state.db, &id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let count = params
+        .count
+        .unwrap_or(DEFAULT_SUMMARY_MESSAGES)
+        .clamp(1, MAX_SUMMARY_MESSAGES);
+    let messages = db::messages::list_messages(&state.db, &id, count, 0).await?;
+    if messages.is_empty() {
+        return Err(AppError::BadRequest("No messages to summarize".into()));
+    }
+
+    let summary = crate::prompts::summary_prompt(
+        messages
+            .iter()
+            .map(|m| (m.role.as_str(), m.content.as_str())),
+    );
+    let message = db::messages::create_message_at_start(&state.db, &id, "system", &summary).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SummaryResponse {
+            message_id: message.id,
+            summary,
+        }),
+    ))
+}
+
 #[derive(Serialize)]
 pub struct McpServerResponse {
     pub id: String,
@@ -563,6 +1976,7 @@ async fn get_mcp_servers(
     auth: AuthUser,
     Path(id): Path<String>,
 ) -> Result<Json<Vec<McpServerResponse>>, AppError> {
+    state.features.require(Feature::Mcp)?;
     db::conversations::get_conversation(&state.db, &id, &auth.user_id)
         .await?
         .ok_or(AppError::NotFound)?;
@@ -593,6 +2007,7 @@ async fn set_mcp_servers(
     Path(id): Path<String>,
     Json(req): Json<SetMcpServersRequest>,
 ) -> Result<StatusCode, AppError> {
+    state.features.require(Feature::Mcp)?;
     db::conversations::get_conversation(&state.db, &id, &auth.user_id)
         .await?
         .ok_or(AppError::NotFound)?;
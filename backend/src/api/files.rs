@@ -1,17 +1,21 @@
 use axum::{
     Json, Router,
-    body::Body,
+    body::{Body, Bytes},
     extract::{Multipart, Path, Query, State},
     http::{HeaderMap, StatusCode, header},
     response::Response,
-    routing::{get, post},
+    routing::{get, patch, post},
 };
+use futures_util::TryStreamExt;
 use serde::{Deserialize, Serialize};
-use std::io::{Cursor, Write as _};
+use std::collections::HashMap;
+use std::io::{Cursor, Read as _, Write as _};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio_util::io::ReaderStream;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::RwLock;
+use tokio_util::io::{ReaderStream, StreamReader};
 use zip::write::SimpleFileOptions;
 
 use crate::auth::middleware::{AppState, QueryAuthUser};
@@ -21,14 +25,46 @@ use crate::error::AppError;
 
 const MAX_BATCH_DOWNLOAD_PATHS: usize = 100;
 const MAX_BATCH_DOWNLOAD_BYTES: u64 = 100 * 1024 * 1024;
+const MAX_BATCH_DELETE_PATHS: usize = 100;
+const UPLOAD_URL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+/// Workspace files are untrusted; never let the browser run them as active content.
+const VIEW_CONTENT_SECURITY_POLICY: &str = "default-src 'none'; sandbox";
+const VIEW_CACHE_CONTROL: &str = "private, max-age=3600, immutable";
+const SEARCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+const MAX_SEARCH_RESULTS: usize = 100;
+const MAX_SEARCH_TERM_LEN: usize = 256;
+/// Largest chunk accepted by a resumable upload `PATCH`.
+const MAX_UPLOAD_CHUNK_BYTES: u64 = 32 * 1024 * 1024;
+/// Resumable uploads untouched for this long are discarded.
+const PENDING_UPLOAD_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Leading bytes of an upload inspected to detect its MIME type.
+const MIME_SNIFF_BYTES: usize = 8192;
+/// Files larger than this are skipped by content search.
+const MAX_SEARCH_FILE_BYTES: u64 = 10 * 1024 * 1024;
+/// Matching lines are truncated to this many characters in search results.
+const MAX_SEARCH_LINE_CHARS: usize = 500;
 
 pub fn router() -> Router<Arc<AppState>> {
     Router::new()
-        .route("/", get(list_files))
+        .route("/", get(list_files).delete(delete_path))
         .route("/download", get(download_file))
         .route("/download-batch", post(download_batch))
+        .route("/delete-batch", post(delete_batch))
         .route("/upload", post(upload_files))
+        .route("/upload/init", post(init_upload))
+        .route(
+            "/upload/{upload_id}",
+            patch(append_upload_chunk).delete(abort_upload),
+        )
+        .route("/upload/{upload_id}/complete", post(complete_upload))
+        .route("/upload-url", post(upload_from_url))
+        .route("/rename", post(rename_path))
+        .route("/move", post(rename_path))
+        .route("/mkdir", post(make_directory))
+        .route("/extract", post(extract_archive))
         .route("/view", get(view_file))
+        .route("/search", get(search_files))
+        .route("/disk-usage", get(disk_usage))
 }
 
 #[derive(Debug)]
@@ -41,6 +77,25 @@ enum BatchZipError {
 struct FileQuery {
     path: Option<String>,
     recursive: Option<bool>,
+    sort: Option<SortField>,
+    order: Option<SortOrder>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SortField {
+    Name,
+    Size,
+    Modified,
+    Type,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
 }
 
 #[derive(Serialize)]
@@ -77,6 +132,96 @@ pub(crate) fn resolve_safe_path(
     }
 }
 
+/// Resolve a destination path that may not exist yet. Only plain path
+/// components are accepted, and the deepest existing ancestor must resolve
+/// inside the workspace. Returns the destination and its workspace-relative
+/// display path (`/a/b.txt`).
+pub(crate) fn resolve_safe_destination(
+    workspace_root: &std::path::Path,
+    requested: &str,
+) -> Option<(PathBuf, String)> {
+    let relative = std::path::Path::new(requested.trim_start_matches('/'));
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        match component {
+            std::path::Component::Normal(part) => parts.push(part.to_str()?),
+            std::path::Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+
+    let root_canonical = workspace_root.canonicalize().ok()?;
+    let mut existing = root_canonical.join(parts.join("/"));
+    while !existing.exists() {
+        existing = existing.parent()?.to_path_buf();
+    }
+    if !existing.canonicalize().ok()?.starts_with(&root_canonical) {
+        return None;
+    }
+    Some((
+        root_canonical.join(parts.join("/")),
+        format!("/{}", parts.join("/")),
+    ))
+}
+
+/// Default listing order: directories first, then case-insensitive name.
+fn sort_entries_default(entries: &mut [FileEntry]) {
+    entries.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then(a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+}
+
+fn modified_timestamp(entry: &FileEntry) -> i64 {
+    entry
+        .modified
+        .as_deref()
+        .and_then(|m| chrono::DateTime::parse_from_rfc3339(m).ok())
+        .map(|dt| dt.timestamp())
+        .unwrap_or(0)
+}
+
+fn file_type_key(entry: &FileEntry) -> String {
+    if entry.is_dir {
+        return String::new();
+    }
+    std::path::Path::new(&entry.name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Sort entries (and any nested children) by an explicit field.
+///
+/// Uses a stable sort so entries with equal keys keep their default
+/// (directories first, name asc) relative order.
+fn sort_entries(entries: &mut [FileEntry], field: SortField, order: SortOrder) {
+    entries.sort_by(|a, b| {
+        let ordering = match field {
+            SortField::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortField::Size => a.size.cmp(&b.size),
+            SortField::Modified => modified_timestamp(a).cmp(&modified_timestamp(b)),
+            SortField::Type => b
+                .is_dir
+                .cmp(&a.is_dir)
+                .then_with(|| file_type_key(a).cmp(&file_type_key(b))),
+        };
+        match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
+    for entry in entries.iter_mut() {
+        if let Some(children) = entry.children.as_mut() {
+            sort_entries(children, field, order);
+        }
+    }
+}
+
 async fn read_dir_recursive(dir: &std::path::Path) -> Result<Vec<FileEntry>, AppError> {
     let mut entries = Vec::new();
     let mut read_dir = tokio::fs::read_dir(dir)
@@ -115,11 +260,7 @@ async fn read_dir_recursive(dir: &std::path::Path) -> Result<Vec<FileEntry>, App
         });
     }
 
-    entries.sort_by(|a, b| {
-        b.is_dir
-            .cmp(&a.is_dir)
-            .then(a.name.to_lowercase().cmp(&b.name.to_lowercase()))
-    });
+    sort_entries_default(&mut entries);
 
     Ok(entries)
 }
@@ -159,7 +300,7 @@ async fn list_files(
 
     let recursive = query.recursive.unwrap_or(false);
 
-    let entries = if recursive {
+    let mut entries = if recursive {
         read_dir_recursive(&dir_path).await?
     } else {
         let mut entries = Vec::new();
@@ -192,15 +333,14 @@ async fn list_files(
             });
         }
 
-        // Sort: directories first, then alphabetical
-        entries.sort_by(|a, b| {
-            b.is_dir
-                .cmp(&a.is_dir)
-                .then(a.name.to_lowercase().cmp(&b.name.to_lowercase()))
-        });
+        sort_entries_default(&mut entries);
         entries
     };
 
+    if let Some(field) = query.sort {
+        sort_entries(&mut entries, field, query.order.unwrap_or_default());
+    }
+
     let display_path = if requested.is_empty() {
         "/".into()
     } else {
@@ -396,6 +536,429 @@ async fn download_batch(
         .map_err(|e| AppError::Internal(e.to_string()))
 }
 
+/// Delete a workspace file, or a directory and everything under it. The
+/// workspace root itself cannot be deleted.
+async fn delete_path(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path(conversation_id): Path<String>,
+    Query(query): Query<FileQuery>,
+) -> Result<StatusCode, AppError> {
+    db::conversations::get_conversation(&state.db, &conversation_id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let requested = query
+        .path
+        .ok_or_else(|| AppError::BadRequest("Path required".into()))?;
+    let workspace_root = PathBuf::from(format!("data/conversations/{}", conversation_id));
+    remove_workspace_path(&workspace_root, &requested).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn remove_workspace_path(
+    workspace_root: &std::path::Path,
+    requested: &str,
+) -> Result<(), AppError> {
+    let target = resolve_safe_path(workspace_root, requested)
+        .ok_or_else(|| AppError::Forbidden("Path traversal denied".into()))?;
+    let root_canonical = workspace_root
+        .canonicalize()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    if target == root_canonical {
+        return Err(AppError::BadRequest(
+            "Cannot delete the workspace root".into(),
+        ));
+    }
+
+    let result = if target.is_dir() {
+        tokio::fs::remove_dir_all(&target).await
+    } else {
+        tokio::fs::remove_file(&target).await
+    };
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(AppError::NotFound),
+        Err(e) => Err(AppError::Internal(e.to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+struct BatchDeleteRequest {
+    paths: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BatchDeleteFailure {
+    path: String,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct BatchDeleteResponse {
+    deleted: Vec<String>,
+    failed: Vec<BatchDeleteFailure>,
+}
+
+/// Delete several workspace paths concurrently, reporting each failure
+/// instead of stopping at the first.
+async fn delete_batch(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path(conversation_id): Path<String>,
+    Json(req): Json<BatchDeleteRequest>,
+) -> Result<Json<BatchDeleteResponse>, AppError> {
+    if req.paths.is_empty() {
+        return Err(AppError::BadRequest("No paths provided".into()));
+    }
+    if req.paths.len() > MAX_BATCH_DELETE_PATHS {
+        return Err(AppError::BadRequest(format!(
+            "At most {MAX_BATCH_DELETE_PATHS} paths can be deleted at once"
+        )));
+    }
+
+    db::conversations::get_conversation(&state.db, &conversation_id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let workspace_root = PathBuf::from(format!("data/conversations/{}", conversation_id));
+    let mut seen = std::collections::HashSet::new();
+    let paths: Vec<String> = req
+        .paths
+        .into_iter()
+        .filter(|path| seen.insert(path.clone()))
+        .collect();
+    let results = futures_util::future::join_all(
+        paths
+            .iter()
+            .map(|path| remove_workspace_path(&workspace_root, path)),
+    )
+    .await;
+
+    let mut response = BatchDeleteResponse {
+        deleted: Vec::new(),
+        failed: Vec::new(),
+    };
+    for (path, result) in paths.into_iter().zip(results) {
+        let reason = match result {
+            Ok(()) => {
+                response.deleted.push(path);
+                continue;
+            }
+            Err(AppError::Forbidden(_)) => "not found or outside the workspace",
+            Err(AppError::BadRequest(_)) => "cannot delete the workspace root",
+            Err(AppError::NotFound) => "not found",
+            Err(e) => {
+                tracing::warn!(path = %path, error = %e, "Failed to delete workspace path");
+                "failed to delete"
+            }
+        };
+        response.failed.push(BatchDeleteFailure {
+            path,
+            reason: reason.into(),
+        });
+    }
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+struct RenameRequest {
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize)]
+struct RenameResponse {
+    path: String,
+    name: String,
+}
+
+/// Rename or move a file or directory within the workspace, creating the
+/// destination's parent directories. Also served as `/move`.
+async fn rename_path(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path(conversation_id): Path<String>,
+    Json(req): Json<RenameRequest>,
+) -> Result<Json<RenameResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &conversation_id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let workspace_root = PathBuf::from(format!("data/conversations/{}", conversation_id));
+    let source = resolve_safe_path(&workspace_root, &req.from)
+        .ok_or_else(|| AppError::Forbidden("Path traversal denied".into()))?;
+    let (destination, display_path) = resolve_safe_destination(&workspace_root, &req.to)
+        .ok_or_else(|| AppError::Forbidden("Path traversal denied".into()))?;
+    let root_canonical = workspace_root
+        .canonicalize()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    if source == root_canonical {
+        return Err(AppError::BadRequest(
+            "Cannot move the workspace root".into(),
+        ));
+    }
+    if destination.starts_with(&source) {
+        return Err(AppError::BadRequest(
+            "Cannot move a path into itself".into(),
+        ));
+    }
+    if destination.exists() {
+        return Err(AppError::Conflict("Destination already exists".into()));
+    }
+
+    if let Some(parent) = destination.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+    }
+    tokio::fs::rename(&source, &destination)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let name = destination
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(Json(RenameResponse {
+        path: display_path,
+        name,
+    }))
+}
+
+#[derive(Deserialize)]
+struct MkdirRequest {
+    path: String,
+}
+
+#[derive(Serialize)]
+struct MkdirResponse {
+    path: String,
+    created: bool,
+}
+
+/// Create a directory, and any missing parents, inside the workspace.
+async fn make_directory(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path(conversation_id): Path<String>,
+    Json(req): Json<MkdirRequest>,
+) -> Result<Json<MkdirResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &conversation_id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let workspace_root = PathBuf::from(format!("data/conversations/{}", conversation_id));
+    tokio::fs::create_dir_all(&workspace_root)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    let (target, display_path) = resolve_safe_destination(&workspace_root, &req.path)
+        .ok_or_else(|| AppError::Forbidden("Path traversal denied".into()))?;
+
+    if target.is_dir() {
+        return Ok(Json(MkdirResponse {
+            path: display_path,
+            created: false,
+        }));
+    }
+    if target.exists() {
+        return Err(AppError::Conflict(
+            "A file already exists at this path".into(),
+        ));
+    }
+    tokio::fs::create_dir_all(&target)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    resolve_safe_path(&workspace_root, &display_path)
+        .ok_or_else(|| AppError::Forbidden("Path traversal denied".into()))?;
+
+    Ok(Json(MkdirResponse {
+        path: display_path,
+        created: true,
+    }))
+}
+
+#[derive(Deserialize)]
+struct ExtractQuery {
+    path: String,
+    dest: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ExtractResponse {
+    files_extracted: usize,
+    dest: String,
+}
+
+/// Expand a `.zip` archive in the workspace into `dest` (default: the
+/// workspace root). The whole archive is rejected if any entry would land
+/// outside the workspace or if it would exceed the workspace quota.
+async fn extract_archive(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path(conversation_id): Path<String>,
+    Query(query): Query<ExtractQuery>,
+) -> Result<Json<ExtractResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &conversation_id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let workspace_root = PathBuf::from(format!("data/conversations/{}", conversation_id));
+    let archive_path = resolve_safe_path(&workspace_root, &query.path)
+        .ok_or_else(|| AppError::Forbidden("Path traversal denied".into()))?;
+    let is_zip = archive_path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    if !archive_path.is_file() || !is_zip {
+        return Err(AppError::BadRequest("path must be a .zip file".into()));
+    }
+
+    let root_canonical = workspace_root
+        .canonicalize()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    let dest = query.dest.as_deref().unwrap_or_default();
+    let (dest_dir, display_dest) = if dest.trim_matches('/').is_empty() {
+        (root_canonical.clone(), "/".to_string())
+    } else {
+        let (dir, display) = resolve_safe_destination(&workspace_root, dest)
+            .ok_or_else(|| AppError::Forbidden("Path traversal denied".into()))?;
+        (dir, format!("{display}/"))
+    };
+
+    let remaining = workspace_quota(&state, &auth.user_id)
+        .await?
+        .saturating_sub(db::conversations::get_workspace_usage(&conversation_id).await);
+    let allowed = state.config.allowed_upload_mime_types.clone();
+    let files_extracted = tokio::task::spawn_blocking(move || {
+        extract_zip(
+            &archive_path,
+            &dest_dir,
+            &root_canonical,
+            remaining,
+            allowed.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))??;
+
+    Ok(Json(ExtractResponse {
+        files_extracted,
+        dest: display_dest,
+    }))
+}
+
+/// Extract the regular files and directories in `archive_path` under
+/// `dest_dir`, returning the number of files written. Every entry name, and
+/// with an `allowed` MIME list every file's content type, is checked before
+/// anything is written. Each parent directory must still resolve inside
+/// `root` so symlinks in the workspace cannot redirect writes. Symlink
+/// entries are not supported. Declared sizes can lie, so extraction also
+/// stops once more than `max_bytes` have actually been written.
+fn extract_zip(
+    archive_path: &std::path::Path,
+    dest_dir: &std::path::Path,
+    root: &std::path::Path,
+    max_bytes: u64,
+    allowed: Option<&[String]>,
+) -> Result<usize, AppError> {
+    let file = std::fs::File::open(archive_path).map_err(|e| AppError::Internal(e.to_string()))?;
+    let mut archive = zip::ZipArchive::new(std::io::BufReader::new(file))
+        .map_err(|e| AppError::BadRequest(format!("Invalid zip archive: {e}")))?;
+
+    let mut entries = Vec::with_capacity(archive.len());
+    let mut total_bytes = 0u64;
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| AppError::BadRequest(format!("Invalid zip archive: {e}")))?;
+        let relative = entry.enclosed_name().ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Archive entry escapes the destination: {}",
+                entry.name()
+            ))
+        })?;
+        if entry.is_symlink() {
+            return Err(AppError::BadRequest(format!(
+                "Archive entry is a symlink: {}",
+                entry.name()
+            )));
+        }
+        total_bytes = total_bytes.saturating_add(entry.size());
+        entries.push((relative, entry.is_dir()));
+    }
+    if total_bytes > max_bytes {
+        return Err(AppError::PayloadTooLarge(format!(
+            "Extracted archive would exceed the workspace quota by {} bytes",
+            total_bytes - max_bytes
+        )));
+    }
+    if let Some(allowed) = allowed {
+        for (i, (relative, is_dir)) in entries.iter().enumerate() {
+            if *is_dir {
+                continue;
+            }
+            let entry = archive
+                .by_index(i)
+                .map_err(|e| AppError::BadRequest(format!("Invalid zip archive: {e}")))?;
+            let mut head = Vec::new();
+            entry
+                .take(MIME_SNIFF_BYTES as u64)
+                .read_to_end(&mut head)
+                .map_err(|e| AppError::BadRequest(format!("Invalid zip archive: {e}")))?;
+            check_mime_allowed(
+                allowed,
+                detect_mime_type(&head, &relative.to_string_lossy()),
+            )?;
+        }
+    }
+
+    let io_err = |e: std::io::Error| AppError::Internal(e.to_string());
+    let mut written = 0u64;
+    let mut files_extracted = 0;
+    for (i, (relative, is_dir)) in entries.into_iter().enumerate() {
+        let target = dest_dir.join(&relative);
+        let dir = if is_dir {
+            target.as_path()
+        } else {
+            target.parent().unwrap_or(dest_dir)
+        };
+        std::fs::create_dir_all(dir).map_err(io_err)?;
+        if !dir.canonicalize().map_err(io_err)?.starts_with(root) {
+            return Err(AppError::Forbidden("Path traversal denied".into()));
+        }
+        if is_dir {
+            continue;
+        }
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| AppError::BadRequest(format!("Invalid zip archive: {e}")))?;
+        // Replace rather than follow a symlink already at the target.
+        if target
+            .symlink_metadata()
+            .is_ok_and(|meta| meta.file_type().is_symlink())
+        {
+            std::fs::remove_file(&target).map_err(io_err)?;
+        }
+        let name = entry.name().to_string();
+        let mut out = std::fs::File::create(&target).map_err(io_err)?;
+        // Read one byte past the remaining budget to detect an entry that
+        // inflates beyond its declared size.
+        let budget = max_bytes - written;
+        let copied = std::io::copy(&mut (&mut entry).take(budget.saturating_add(1)), &mut out)
+            .map_err(|e| AppError::BadRequest(format!("Failed to extract {name}: {e}")))?;
+        if copied > budget {
+            drop(out);
+            let _ = std::fs::remove_file(&target);
+            return Err(AppError::PayloadTooLarge(format!(
+                "Extracting {name} would exceed the workspace quota"
+            )));
+        }
+        written += copied;
+        files_extracted += 1;
+    }
+    Ok(files_extracted)
+}
+
 #[derive(Serialize)]
 struct UploadedFileInfo {
     name: String,
@@ -408,6 +971,52 @@ struct UploadResponse {
     uploaded: Vec<UploadedFileInfo>,
 }
 
+/// Content type to serve from `view_file`. HTML is downgraded to plain text so
+/// a workspace file can never render as a page on our origin.
+fn view_content_type(mime: &str) -> &str {
+    match mime {
+        "text/html" | "application/xhtml+xml" => "text/plain; charset=utf-8",
+        other => other,
+    }
+}
+
+/// MIME type of an upload from its magic bytes, falling back to the file
+/// extension for formats without a signature (plain text, source code).
+fn detect_mime_type(head: &[u8], file_name: &str) -> String {
+    match infer::get(head) {
+        Some(kind) => kind.mime_type().to_string(),
+        None => mime_guess::from_path(file_name)
+            .first_or_octet_stream()
+            .essence_str()
+            .to_string(),
+    }
+}
+
+/// Fail with 415 unless `detected` is in the configured upload allowlist.
+fn check_mime_allowed(allowed: &[String], detected: String) -> Result<(), AppError> {
+    if allowed
+        .iter()
+        .any(|m| m.trim().eq_ignore_ascii_case(&detected))
+    {
+        Ok(())
+    } else {
+        Err(AppError::UnsupportedMediaType(detected))
+    }
+}
+
+/// The first [`MIME_SNIFF_BYTES`] of a file, or fewer if it is shorter or
+/// cannot be read.
+async fn read_file_head(path: &std::path::Path) -> Vec<u8> {
+    let mut head = Vec::new();
+    if let Ok(file) = tokio::fs::File::open(path).await {
+        let _ = file
+            .take(MIME_SNIFF_BYTES as u64)
+            .read_to_end(&mut head)
+            .await;
+    }
+    head
+}
+
 /// Returns true if the filename is safe (no path separators or traversal).
 fn is_safe_filename(name: &str) -> bool {
     !name.is_empty()
@@ -459,6 +1068,9 @@ async fn upload_files(
         canonical
     };
 
+    let quota = workspace_quota(&state, &auth.user_id).await?;
+    let mut remaining =
+        quota.saturating_sub(db::conversations::get_workspace_usage(&conversation_id).await);
     let mut uploaded = Vec::new();
 
     while let Some(field) = multipart
@@ -476,22 +1088,53 @@ async fn upload_files(
         }
 
         let dest = target_dir.join(&file_name);
+        let mut field = field;
+
+        // Sniff the content type from the leading bytes before writing anything.
+        let mut head = Vec::new();
+        if let Some(allowed) = &state.config.allowed_upload_mime_types {
+            while head.len() < MIME_SNIFF_BYTES {
+                match field
+                    .chunk()
+                    .await
+                    .map_err(|e| AppError::BadRequest(e.to_string()))?
+                {
+                    Some(chunk) => head.extend_from_slice(&chunk),
+                    None => break,
+                }
+            }
+            check_mime_allowed(allowed, detect_mime_type(&head, &file_name))?;
+        }
+
         let mut file = tokio::fs::File::create(&dest)
             .await
             .map_err(|e| AppError::Internal(e.to_string()))?;
-        let mut field = field;
         let mut file_size = 0u64;
-
-        while let Some(chunk) = field
-            .chunk()
-            .await
-            .map_err(|e| AppError::BadRequest(e.to_string()))?
-        {
+        let mut sniffed = (!head.is_empty()).then(|| Bytes::from(head));
+
+        loop {
+            let chunk = match sniffed.take() {
+                Some(chunk) => chunk,
+                None => match field
+                    .chunk()
+                    .await
+                    .map_err(|e| AppError::BadRequest(e.to_string()))?
+                {
+                    Some(chunk) => chunk,
+                    None => break,
+                },
+            };
+            file_size = file_size.saturating_add(chunk.len() as u64);
+            if file_size > remaining {
+                drop(file);
+                let _ = tokio::fs::remove_file(&dest).await;
+                return Err(workspace_quota_exceeded(quota));
+            }
             file.write_all(&chunk)
                 .await
                 .map_err(|e| AppError::Internal(e.to_string()))?;
-            file_size = file_size.saturating_add(chunk.len() as u64);
         }
+        remaining -= file_size;
         file.flush()
             .await
             .map_err(|e| AppError::Internal(e.to_string()))?;
@@ -516,6 +1159,675 @@ async fn upload_files(
     Ok(Json(UploadResponse { uploaded }))
 }
 
+/// Disk quota for each of the user's conversation workspaces.
+async fn workspace_quota(state: &AppState, user_id: &str) -> Result<u64, AppError> {
+    Ok(
+        db::user_quotas::effective_quota(&state.db, &state.config, user_id)
+            .await?
+            .max_workspace_bytes,
+    )
+}
+
+fn workspace_quota_exceeded(quota: u64) -> AppError {
+    AppError::PayloadTooLarge(format!("Workspace quota of {quota} bytes exceeded"))
+}
+
+#[derive(Serialize)]
+struct DiskUsageResponse {
+    bytes_used: u64,
+    bytes_limit: u64,
+}
+
+async fn disk_usage(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path(conversation_id): Path<String>,
+) -> Result<Json<DiskUsageResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &conversation_id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(DiskUsageResponse {
+        bytes_used: db::conversations::get_workspace_usage(&conversation_id).await,
+        bytes_limit: workspace_quota(&state, &auth.user_id).await?,
+    }))
+}
+
+/// A resumable upload in progress. Chunks are appended to `temp_path`
+/// outside the workspace and moved into place on completion.
+#[derive(Debug)]
+pub struct PendingUpload {
+    conversation_id: String,
+    user_id: String,
+    filename: String,
+    size: u64,
+    chunk_size: u64,
+    /// Bytes received so far; the offset the next chunk must start at.
+    received: u64,
+    temp_path: PathBuf,
+    updated_at: Instant,
+}
+
+/// Pending resumable uploads keyed by upload ID.
+pub type PendingUploads = Arc<RwLock<HashMap<String, PendingUpload>>>;
+
+#[derive(Deserialize)]
+struct InitUploadRequest {
+    filename: String,
+    size: u64,
+    chunk_size: u64,
+}
+
+#[derive(Serialize)]
+struct InitUploadResponse {
+    upload_id: String,
+}
+
+#[derive(Deserialize)]
+struct ChunkQuery {
+    offset: u64,
+}
+
+#[derive(Serialize)]
+struct ChunkResponse {
+    received: u64,
+    size: u64,
+}
+
+/// Start a resumable upload into the workspace root. Chunks are sent with
+/// `PATCH /upload/{upload_id}?offset=N`, then `POST .../complete`.
+async fn init_upload(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path(conversation_id): Path<String>,
+    Json(req): Json<InitUploadRequest>,
+) -> Result<Json<InitUploadResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &conversation_id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if !is_safe_filename(&req.filename) {
+        return Err(AppError::BadRequest(format!(
+            "Invalid filename: {}",
+            req.filename
+        )));
+    }
+    if !(1..=MAX_UPLOAD_CHUNK_BYTES).contains(&req.chunk_size) {
+        return Err(AppError::BadRequest(format!(
+            "chunk_size must be between 1 and {MAX_UPLOAD_CHUNK_BYTES}"
+        )));
+    }
+    let quota = workspace_quota(&state, &auth.user_id).await?;
+    let used = db::conversations::get_workspace_usage(&conversation_id).await;
+
+    expire_pending_uploads(&state.pending_uploads).await;
+
+    let upload_id = uuid::Uuid::new_v4().to_string();
+    let temp_dir = PathBuf::from("data/uploads");
+    let temp_path = temp_dir.join(format!("{upload_id}.part"));
+    {
+        // Uploads still in progress count against the quota, so several
+        // concurrent uploads cannot each claim the same free space.
+        let mut uploads = state.pending_uploads.write().await;
+        let reserved: u64 = uploads
+            .values()
+            .filter(|u| u.conversation_id == conversation_id)
+            .map(|u| u.size)
+            .sum();
+        if used.saturating_add(reserved).saturating_add(req.size) > quota {
+            return Err(workspace_quota_exceeded(quota));
+        }
+        uploads.insert(
+            upload_id.clone(),
+            PendingUpload {
+                conversation_id,
+                user_id: auth.user_id.clone(),
+                filename: req.filename,
+                size: req.size,
+                chunk_size: req.chunk_size,
+                received: 0,
+                temp_path: temp_path.clone(),
+                updated_at: Instant::now(),
+            },
+        );
+    }
+
+    let created = async {
+        tokio::fs::create_dir_all(&temp_dir).await?;
+        tokio::fs::File::create(&temp_path).await.map(|_| ())
+    }
+    .await;
+    if let Err(e) = created {
+        state.pending_uploads.write().await.remove(&upload_id);
+        return Err(AppError::Internal(e.to_string()));
+    }
+
+    Ok(Json(InitUploadResponse { upload_id }))
+}
+
+/// Drop uploads idle for longer than [`PENDING_UPLOAD_TTL`] and delete their
+/// temporary files.
+async fn expire_pending_uploads(pending: &PendingUploads) {
+    let expired: Vec<PathBuf> = {
+        let mut uploads = pending.write().await;
+        let stale: Vec<String> = uploads
+            .iter()
+            .filter(|(_, u)| u.updated_at.elapsed() > PENDING_UPLOAD_TTL)
+            .map(|(id, _)| id.clone())
+            .collect();
+        stale
+            .iter()
+            .filter_map(|id| uploads.remove(id))
+            .map(|u| u.temp_path)
+            .collect()
+    };
+    for path in expired {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+}
+
+fn is_owned_upload(upload: &PendingUpload, conversation_id: &str, user_id: &str) -> bool {
+    upload.conversation_id == conversation_id && upload.user_id == user_id
+}
+
+/// Append one chunk. `offset` must equal the bytes received so far; on a
+/// mismatch the 409 tells the client where to resume.
+async fn append_upload_chunk(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path((conversation_id, upload_id)): Path<(String, String)>,
+    Query(query): Query<ChunkQuery>,
+    body: Bytes,
+) -> Result<Json<ChunkResponse>, AppError> {
+    let len = body.len() as u64;
+    // Reserve the byte range under the lock so concurrent chunks cannot
+    // both claim the same offset.
+    let (temp_path, size) = {
+        let mut uploads = state.pending_uploads.write().await;
+        let upload = uploads
+            .get_mut(&upload_id)
+            .filter(|u| is_owned_upload(u, &conversation_id, &auth.user_id))
+            .ok_or(AppError::NotFound)?;
+        if query.offset != upload.received {
+            return Err(AppError::Conflict(format!(
+                "Expected offset {}",
+                upload.received
+            )));
+        }
+        if len == 0 || len > upload.chunk_size {
+            return Err(AppError::BadRequest(format!(
+                "Chunk must be between 1 and {} bytes",
+                upload.chunk_size
+            )));
+        }
+        if upload.received + len > upload.size {
+            return Err(AppError::BadRequest("Chunk exceeds declared size".into()));
+        }
+        upload.received += len;
+        upload.updated_at = Instant::now();
+        (upload.temp_path.clone(), upload.size)
+    };
+
+    let written = async {
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(&temp_path)
+            .await?;
+        file.seek(std::io::SeekFrom::Start(query.offset)).await?;
+        file.write_all(&body).await?;
+        file.flush().await
+    }
+    .await;
+    if let Err(e) = written {
+        if let Some(upload) = state.pending_uploads.write().await.get_mut(&upload_id)
+            && upload.received == query.offset + len
+        {
+            upload.received = query.offset;
+        }
+        return Err(AppError::Internal(e.to_string()));
+    }
+
+    Ok(Json(ChunkResponse {
+        received: query.offset + len,
+        size,
+    }))
+}
+
+/// Move a fully received upload into the workspace root.
+async fn complete_upload(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path((conversation_id, upload_id)): Path<(String, String)>,
+) -> Result<Json<UploadedFileInfo>, AppError> {
+    let upload = {
+        let mut uploads = state.pending_uploads.write().await;
+        let upload = uploads
+            .get(&upload_id)
+            .filter(|u| is_owned_upload(u, &conversation_id, &auth.user_id))
+            .ok_or(AppError::NotFound)?;
+        if upload.received != upload.size {
+            return Err(AppError::BadRequest(format!(
+                "Upload incomplete: received {} of {} bytes",
+                upload.received, upload.size
+            )));
+        }
+        uploads.remove(&upload_id).ok_or(AppError::NotFound)?
+    };
+    let discard = || async {
+        let _ = tokio::fs::remove_file(&upload.temp_path).await;
+    };
+
+    if let Some(allowed) = &state.config.allowed_upload_mime_types {
+        let head = read_file_head(&upload.temp_path).await;
+        if let Err(e) = check_mime_allowed(allowed, detect_mime_type(&head, &upload.filename)) {
+            discard().await;
+            return Err(e);
+        }
+    }
+    let quota = workspace_quota(&state, &auth.user_id).await?;
+    let used = db::conversations::get_workspace_usage(&conversation_id).await;
+    if used.saturating_add(upload.size) > quota {
+        discard().await;
+        return Err(workspace_quota_exceeded(quota));
+    }
+
+    let workspace_root = PathBuf::from(format!("data/conversations/{}", conversation_id));
+    tokio::fs::create_dir_all(&workspace_root)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    if let Err(e) =
+        tokio::fs::rename(&upload.temp_path, workspace_root.join(&upload.filename)).await
+    {
+        discard().await;
+        return Err(AppError::Internal(e.to_string()));
+    }
+
+    Ok(Json(UploadedFileInfo {
+        path: format!("/{}", upload.filename),
+        name: upload.filename,
+        size: upload.size,
+    }))
+}
+
+async fn abort_upload(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path((conversation_id, upload_id)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    let upload = {
+        let mut uploads = state.pending_uploads.write().await;
+        if !uploads
+            .get(&upload_id)
+            .is_some_and(|u| is_owned_upload(u, &conversation_id, &auth.user_id))
+        {
+            return Err(AppError::NotFound);
+        }
+        uploads.remove(&upload_id).ok_or(AppError::NotFound)?
+    };
+    let _ = tokio::fs::remove_file(&upload.temp_path).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct UploadUrlRequest {
+    url: String,
+    filename: String,
+}
+
+#[derive(Serialize)]
+struct UploadUrlResponse {
+    path: String,
+    size: u64,
+}
+
+/// Download a remote `https` file into the workspace root. The upload MIME
+/// allowlist applies as for multipart uploads.
+async fn upload_from_url(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path(conversation_id): Path<String>,
+    Json(req): Json<UploadUrlRequest>,
+) -> Result<Json<UploadUrlResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &conversation_id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let url =
+        url::Url::parse(&req.url).map_err(|e| AppError::BadRequest(format!("Invalid url: {e}")))?;
+    if url.scheme() != "https" {
+        return Err(AppError::BadRequest("Only https URLs are supported".into()));
+    }
+    if !is_safe_filename(&req.filename) {
+        return Err(AppError::BadRequest(format!(
+            "Invalid filename: {}",
+            req.filename
+        )));
+    }
+    let pinned = resolve_public_host(&url).await?;
+    let client = upload_url_client(pinned.as_ref().map(|(host, addr)| (host.as_str(), *addr)))?;
+
+    let workspace_root = PathBuf::from(format!("data/conversations/{}", conversation_id));
+    tokio::fs::create_dir_all(&workspace_root)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let remaining = workspace_quota(&state, &auth.user_id)
+        .await?
+        .saturating_sub(db::conversations::get_workspace_usage(&conversation_id).await);
+
+    // Download outside the workspace so a rejected file is never visible there.
+    let temp_dir = PathBuf::from("data/uploads");
+    tokio::fs::create_dir_all(&temp_dir)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    let temp_path = temp_dir.join(format!("{}.part", uuid::Uuid::new_v4()));
+    let size = download_to_file(
+        &client,
+        url,
+        &temp_path,
+        state.config.max_file_size_bytes.min(remaining),
+    )
+    .await?;
+
+    if let Some(allowed) = &state.config.allowed_upload_mime_types {
+        let head = read_file_head(&temp_path).await;
+        if let Err(e) = check_mime_allowed(allowed, detect_mime_type(&head, &req.filename)) {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(e);
+        }
+    }
+    if let Err(e) = tokio::fs::rename(&temp_path, workspace_root.join(&req.filename)).await {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return Err(AppError::Internal(e.to_string()));
+    }
+
+    Ok(Json(UploadUrlResponse {
+        path: format!("/{}", req.filename),
+        size,
+    }))
+}
+
+/// Whether `ip` is reachable on the public internet, i.e. not loopback,
+/// private, link-local (cloud metadata), shared, reserved or multicast.
+fn is_public_ip(ip: std::net::IpAddr) -> bool {
+    use std::net::IpAddr;
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_unspecified()
+                || v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (b & 0xc0) == 64)
+                || (a == 198 && (b & 0xfe) == 18))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let [first, second, ..] = v6.segments();
+            // NAT64 addresses embed an IPv4 address in the last 32 bits.
+            if first == 0x64 && second == 0xff9b {
+                let [.., a, b, c, d] = v6.octets();
+                return is_public_ip(IpAddr::V4(std::net::Ipv4Addr::new(a, b, c, d)));
+            }
+            !(v6.is_unspecified()
+                || v6.is_loopback()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && second == 0x0db8))
+        }
+    }
+}
+
+/// Resolve the host of `url` and fail with 400 unless every address it
+/// resolves to is public. Returns the host name and the address to pin the
+/// connection to, or `None` when the host is already an IP literal.
+async fn resolve_public_host(
+    url: &url::Url,
+) -> Result<Option<(String, std::net::SocketAddr)>, AppError> {
+    let not_public = || AppError::BadRequest("URL must point to a public host".into());
+    let port = url.port_or_known_default().unwrap_or(443);
+    match url.host() {
+        Some(url::Host::Ipv4(ip)) if is_public_ip(ip.into()) => Ok(None),
+        Some(url::Host::Ipv6(ip)) if is_public_ip(ip.into()) => Ok(None),
+        Some(url::Host::Domain(domain)) => {
+            let addrs: Vec<_> = tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|e| AppError::BadRequest(format!("Failed to resolve {domain}: {e}")))?
+                .collect();
+            match addrs.first() {
+                Some(addr) if addrs.iter().all(|a| is_public_ip(a.ip())) => {
+                    Ok(Some((domain.to_string(), *addr)))
+                }
+                _ => Err(not_public()),
+            }
+        }
+        _ => Err(not_public()),
+    }
+}
+
+/// Client for `upload-url`. `pinned` fixes the address a host name connects
+/// to, so DNS cannot change between the public-address check and the fetch;
+/// proxies are bypassed for the same reason. Redirects are not followed: a
+/// hop could leave `https` or point at an internal host.
+fn upload_url_client(
+    pinned: Option<(&str, std::net::SocketAddr)>,
+) -> Result<reqwest::Client, AppError> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(UPLOAD_URL_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .no_proxy();
+    if let Some((host, addr)) = pinned {
+        builder = builder.resolve(host, addr);
+    }
+    builder
+        .build()
+        .map_err(|e| AppError::Internal(e.to_string()))
+}
+
+/// Stream `url` into `dest`, failing with 413 once more than `max_bytes`
+/// would be written. A partially written file is removed on failure.
+async fn download_to_file(
+    client: &reqwest::Client,
+    url: url::Url,
+    dest: &std::path::Path,
+    max_bytes: u64,
+) -> Result<u64, AppError> {
+    let too_large = || AppError::PayloadTooLarge(format!("File exceeds {max_bytes} bytes"));
+
+    let resp = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Failed to fetch url: {e}")))?;
+    if resp.status().is_redirection() {
+        return Err(AppError::BadRequest(
+            "Remote server redirected; redirects are not followed".into(),
+        ));
+    }
+    if !resp.status().is_success() {
+        return Err(AppError::BadRequest(format!(
+            "Remote server returned {}",
+            resp.status()
+        )));
+    }
+    if resp.content_length().is_some_and(|len| len > max_bytes) {
+        return Err(too_large());
+    }
+
+    let stream = resp.bytes_stream().map_err(std::io::Error::other);
+    // Read one byte past the limit so an oversized body without a
+    // content-length is still detected.
+    let mut reader = StreamReader::new(stream).take(max_bytes.saturating_add(1));
+    let mut file = tokio::fs::File::create(dest)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let result = match tokio::io::copy(&mut reader, &mut file).await {
+        Ok(n) if n > max_bytes => Err(too_large()),
+        Ok(n) => file
+            .flush()
+            .await
+            .map(|_| n)
+            .map_err(|e| AppError::Internal(e.to_string())),
+        Err(e) => Err(AppError::BadRequest(format!("Failed to download url: {e}"))),
+    };
+    if result.is_err() {
+        drop(file);
+        let _ = tokio::fs::remove_file(dest).await;
+    }
+    result
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    path: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct SearchMatch {
+    /// Workspace-relative path of the file.
+    file: String,
+    /// 1-based line number.
+    line: usize,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct SearchResponse {
+    matches: Vec<SearchMatch>,
+}
+
+/// Lines containing a literal search term in the text files under `path`
+/// (default: the whole workspace).
+async fn search_files(
+    State(state): State<Arc<AppState>>,
+    auth: QueryAuthUser,
+    Path(conversation_id): Path<String>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, AppError> {
+    db::conversations::get_conversation(&state.db, &conversation_id, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if query.q.is_empty() || query.q.len() > MAX_SEARCH_TERM_LEN {
+        return Err(AppError::BadRequest(format!(
+            "Search term must be 1-{MAX_SEARCH_TERM_LEN} bytes"
+        )));
+    }
+    // Matching is line by line, so a multi-line term could never match.
+    if query.q.contains(['\n', '\r', '\0']) {
+        return Err(AppError::BadRequest(
+            "Search term must be a single line".into(),
+        ));
+    }
+
+    let workspace_root = PathBuf::from(format!("data/conversations/{}", conversation_id));
+    if !workspace_root.is_dir() {
+        return Ok(Json(SearchResponse { matches: vec![] }));
+    }
+    let root_canonical = workspace_root
+        .canonicalize()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    let search_root = resolve_safe_path(&workspace_root, query.path.as_deref().unwrap_or(""))
+        .ok_or_else(|| AppError::Forbidden("Path traversal denied".into()))?;
+
+    let term = query.q;
+    let deadline = std::time::Instant::now() + SEARCH_TIMEOUT;
+    let matches = tokio::task::spawn_blocking(move || {
+        search_workspace(&root_canonical, &search_root, &term, deadline)
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+    .ok_or_else(|| AppError::BadRequest("Search timed out".into()))?;
+    Ok(Json(SearchResponse { matches }))
+}
+
+/// Walk `search_root` in name order and collect up to
+/// [`MAX_SEARCH_RESULTS`] lines containing the literal `term`. Symlinks are
+/// not followed; files over [`MAX_SEARCH_FILE_BYTES`] and binary files (a
+/// NUL byte in the first [`MIME_SNIFF_BYTES`]) are skipped. Paths are
+/// reported relative to `workspace_root`. Returns `None` once `deadline`
+/// passes so an abandoned search stops reading the disk.
+fn search_workspace(
+    workspace_root: &std::path::Path,
+    search_root: &std::path::Path,
+    term: &str,
+    deadline: std::time::Instant,
+) -> Option<Vec<SearchMatch>> {
+    let mut matches = Vec::new();
+    search_path(workspace_root, search_root, term, deadline, &mut matches)?;
+    Some(matches)
+}
+
+fn search_path(
+    workspace_root: &std::path::Path,
+    path: &std::path::Path,
+    term: &str,
+    deadline: std::time::Instant,
+    matches: &mut Vec<SearchMatch>,
+) -> Option<()> {
+    if std::time::Instant::now() >= deadline {
+        return None;
+    }
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return Some(());
+    };
+    if meta.is_dir() {
+        let Ok(entries) = std::fs::read_dir(path) else {
+            return Some(());
+        };
+        let mut children: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        children.sort();
+        for child in children {
+            if matches.len() >= MAX_SEARCH_RESULTS {
+                break;
+            }
+            search_path(workspace_root, &child, term, deadline, matches)?;
+        }
+        return Some(());
+    }
+    if !meta.is_file() || meta.len() > MAX_SEARCH_FILE_BYTES {
+        return Some(());
+    }
+    let Ok(bytes) = std::fs::read(path) else {
+        return Some(());
+    };
+    if bytes[..bytes.len().min(MIME_SNIFF_BYTES)].contains(&0) {
+        return Some(());
+    }
+
+    let file = path
+        .strip_prefix(workspace_root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned();
+    for (i, line) in String::from_utf8_lossy(&bytes).lines().enumerate() {
+        if matches.len() >= MAX_SEARCH_RESULTS {
+            break;
+        }
+        if std::time::Instant::now() >= deadline {
+            return None;
+        }
+        if line.contains(term) {
+            matches.push(SearchMatch {
+                file: file.clone(),
+                line: i + 1,
+                text: line.chars().take(MAX_SEARCH_LINE_CHARS).collect(),
+            });
+        }
+    }
+    Some(())
+}
+
 /// Serve a file inline with correct MIME type and optional Range support.
 async fn view_file(
     State(state): State<Arc<AppState>>,
@@ -543,10 +1855,30 @@ async fn view_file(
         .await
         .map_err(|e| AppError::Internal(e.to_string()))?;
     let file_size = metadata.len();
+    let modified_secs = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    let etag = file_etag(file_size, modified_secs);
+    let last_modified = httpdate::fmt_http_date(
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(modified_secs),
+    );
+
+    if is_not_modified(&headers, &etag, modified_secs) {
+        return Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, &etag)
+            .header(header::LAST_MODIFIED, &last_modified)
+            .header(header::CACHE_CONTROL, VIEW_CACHE_CONTROL)
+            .body(Body::empty())
+            .map_err(|e| AppError::Internal(e.to_string()));
+    }
 
     let mime = mime_guess::from_path(&file_path)
         .first_raw()
         .unwrap_or("application/octet-stream");
+    let content_type = view_content_type(mime);
 
     // Check for Range header
     if let Some(range_header) = headers.get(header::RANGE) {
@@ -562,7 +1894,6 @@ async fn view_file(
                 .await
                 .map_err(|e| AppError::Internal(e.to_string()))?;
 
-            use tokio::io::AsyncSeekExt;
             let mut file = file;
             file.seek(std::io::SeekFrom::Start(start))
                 .await
@@ -573,13 +1904,20 @@ async fn view_file(
 
             return Response::builder()
                 .status(StatusCode::PARTIAL_CONTENT)
-                .header(header::CONTENT_TYPE, mime)
+                .header(header::CONTENT_TYPE, content_type)
+                .header(
+                    header::CONTENT_SECURITY_POLICY,
+                    VIEW_CONTENT_SECURITY_POLICY,
+                )
+                .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
                 .header(header::ACCEPT_RANGES, "bytes")
                 .header(
                     header::CONTENT_RANGE,
                     format!("bytes {}-{}/{}", start, end, file_size),
                 )
                 .header(header::CONTENT_LENGTH, length.to_string())
+                .header(header::ETAG, &etag)
+                .header(header::LAST_MODIFIED, &last_modified)
                 .body(body)
                 .map_err(|e| AppError::Internal(e.to_string()));
         }
@@ -593,14 +1931,44 @@ async fn view_file(
     let body = Body::from_stream(stream);
 
     Response::builder()
-        .header(header::CONTENT_TYPE, mime)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(
+            header::CONTENT_SECURITY_POLICY,
+            VIEW_CONTENT_SECURITY_POLICY,
+        )
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
         .header(header::ACCEPT_RANGES, "bytes")
         .header(header::CONTENT_LENGTH, file_size.to_string())
-        .header(header::CACHE_CONTROL, "private, max-age=3600, immutable")
+        .header(header::CACHE_CONTROL, VIEW_CACHE_CONTROL)
+        .header(header::ETAG, &etag)
+        .header(header::LAST_MODIFIED, &last_modified)
         .body(body)
         .map_err(|e| AppError::Internal(e.to_string()))
 }
 
+/// Validator for a workspace file derived from its size and mtime.
+fn file_etag(file_size: u64, modified_secs: u64) -> String {
+    format!("\"{:x}\"", file_size ^ modified_secs)
+}
+
+/// Whether a conditional request's validators still match the file.
+/// `If-None-Match` takes precedence over `If-Modified-Since`.
+fn is_not_modified(headers: &HeaderMap, etag: &str, modified_secs: u64) -> bool {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        return if_none_match.to_str().is_ok_and(|tags| {
+            tags.split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        });
+    }
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| httpdate::parse_http_date(v).ok())
+        .and_then(|since| since.duration_since(std::time::UNIX_EPOCH).ok())
+        .is_some_and(|since| modified_secs <= since.as_secs())
+}
+
 /// Parse a simple "bytes=START-END" or "bytes=START-" range header.
 pub(crate) fn parse_range(range_str: &str, file_size: u64) -> Option<(u64, u64)> {
     if file_size == 0 {
@@ -694,6 +2062,109 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_detect_mime_type_prefers_magic_bytes() {
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F'];
+        assert_eq!(detect_mime_type(&jpeg, "photo.txt"), "image/jpeg");
+        assert_eq!(detect_mime_type(b"hello", "notes.txt"), "text/plain");
+        assert_eq!(
+            detect_mime_type(b"hello", "no-extension"),
+            "application/octet-stream"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_mime_allowed_on_downloaded_file() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("download.part");
+        let mut elf = vec![0x7F, b'E', b'L', b'F', 2, 1, 1, 0];
+        elf.resize(64, 0);
+        elf[16] = 2;
+        fs::write(&path, elf).unwrap();
+        let allowed = vec!["image/jpeg".to_string(), " TEXT/PLAIN ".to_string()];
+
+        let head = read_file_head(&path).await;
+        assert!(matches!(
+            check_mime_allowed(&allowed, detect_mime_type(&head, "tool.txt")),
+            Err(AppError::UnsupportedMediaType(m)) if m == "application/x-executable"
+        ));
+        assert!(check_mime_allowed(&allowed, "text/plain".into()).is_ok());
+    }
+
+    #[test]
+    fn test_resolve_safe_destination() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().join("ws");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        let root_canonical = root.canonicalize().unwrap();
+
+        let (path, display) = resolve_safe_destination(&root, "/sub/./new/file.txt").unwrap();
+        assert_eq!(path, root_canonical.join("sub/new/file.txt"));
+        assert_eq!(display, "/sub/new/file.txt");
+
+        assert!(resolve_safe_destination(&root, "../escape.txt").is_none());
+        assert!(resolve_safe_destination(&root, "sub/../../escape.txt").is_none());
+        assert!(resolve_safe_destination(&root, "/").is_none());
+
+        std::os::unix::fs::symlink(tmp.path(), root.join("link")).unwrap();
+        assert!(resolve_safe_destination(&root, "link/new/file.txt").is_none());
+    }
+
+    fn write_zip(path: &std::path::Path, entries: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (name, content) in entries {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        fs::write(path, zip.finish().unwrap().into_inner()).unwrap();
+    }
+
+    #[test]
+    fn test_extract_zip_stops_when_entry_exceeds_declared_size() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        let archive = root.join("bomb.zip");
+        write_zip(&archive, &[("big.bin", &[0u8; 10_000])]);
+
+        // Understate the uncompressed size in the local and central headers.
+        let mut bytes = fs::read(&archive).unwrap();
+        let central = bytes.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        bytes[22..26].copy_from_slice(&10u32.to_le_bytes());
+        bytes[central + 24..central + 28].copy_from_slice(&10u32.to_le_bytes());
+        fs::write(&archive, bytes).unwrap();
+
+        let err = extract_zip(&archive, &root, &root, 1_000, None).unwrap_err();
+        assert!(matches!(err, AppError::PayloadTooLarge(_)), "{err:?}");
+        assert!(!root.join("big.bin").exists());
+    }
+
+    #[test]
+    fn test_extract_zip_applies_mime_allowlist_to_every_entry() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        let archive = root.join("mixed.zip");
+        let mut elf = vec![0x7F, b'E', b'L', b'F', 2, 1, 1, 0];
+        elf.resize(64, 0);
+        elf[16] = 2;
+        write_zip(&archive, &[("notes.txt", b"hello"), ("tool.txt", &elf)]);
+        let allowed = vec!["text/plain".to_string()];
+
+        let err = extract_zip(&archive, &root, &root, u64::MAX, Some(&allowed)).unwrap_err();
+        assert!(
+            matches!(&err, AppError::UnsupportedMediaType(m) if m == "application/x-executable"),
+            "{err:?}"
+        );
+        assert!(!root.join("notes.txt").exists());
+
+        write_zip(&archive, &[("notes.txt", b"hello")]);
+        assert_eq!(
+            extract_zip(&archive, &root, &root, u64::MAX, Some(&allowed)).unwrap(),
+            1
+        );
+    }
+
     #[test]
     fn test_add_dir_to_zip() {
         let tmp = TempDir::new().unwrap();
@@ -727,6 +2198,100 @@ mod tests {
         assert_eq!(names, vec!["mydir/a.txt", "mydir/nested/b.txt"]);
     }
 
+    fn seed_search_workspace() -> TempDir {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().join("workspace");
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::write(
+            root.join("src/main.rs"),
+            "fn needle() {}\n// needle again\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/nested/app.ts"), "const needle = 1;\n").unwrap();
+        fs::write(root.join("src/lib.py"), "haystack\n").unwrap();
+        fs::write(root.join("notes.txt"), "needle in a text file\n").unwrap();
+        fs::write(tmp.path().join("outside.rs"), "needle outside\n").unwrap();
+        tmp
+    }
+
+    fn search(root: &std::path::Path, term: &str) -> Vec<SearchMatch> {
+        search_workspace(root, root, term, far_deadline()).unwrap()
+    }
+
+    fn far_deadline() -> std::time::Instant {
+        std::time::Instant::now() + std::time::Duration::from_secs(60)
+    }
+
+    #[test]
+    fn test_search_workspace_reports_matching_lines() {
+        let tmp = seed_search_workspace();
+        let root = tmp.path().join("workspace");
+        let matches = search(&root, "needle");
+        let hits: Vec<(&str, usize)> = matches.iter().map(|m| (m.file.as_str(), m.line)).collect();
+        assert_eq!(
+            hits,
+            vec![
+                ("notes.txt", 1),
+                ("src/main.rs", 1),
+                ("src/main.rs", 2),
+                ("src/nested/app.ts", 1),
+            ]
+        );
+        assert_eq!(matches[2].text, "// needle again");
+
+        let scoped =
+            search_workspace(&root, &root.join("src/nested"), "needle", far_deadline()).unwrap();
+        assert_eq!(scoped.len(), 1);
+        assert_eq!(scoped[0].file, "src/nested/app.ts");
+    }
+
+    #[test]
+    fn test_search_workspace_treats_term_literally() {
+        let tmp = seed_search_workspace();
+        let root = tmp.path().join("workspace");
+        fs::write(root.join("flags.js"), "--help $(whoami) a.*b\n").unwrap();
+
+        for term in ["--help", "$(whoami)", "a.*b"] {
+            let matches = search(&root, term);
+            let files: Vec<&str> = matches.iter().map(|m| m.file.as_str()).collect();
+            assert_eq!(files, vec!["flags.js"], "term {term:?}");
+        }
+        assert!(search(&root, "a.b").is_empty());
+    }
+
+    #[test]
+    fn test_search_workspace_skips_binary_and_symlinked_files() {
+        let tmp = seed_search_workspace();
+        let root = tmp.path().join("workspace");
+        fs::write(root.join("blob.bin"), b"needle\0binary").unwrap();
+        std::os::unix::fs::symlink(tmp.path().join("outside.rs"), root.join("link.rs")).unwrap();
+
+        let files: Vec<String> = search(&root, "needle")
+            .into_iter()
+            .map(|m| m.file)
+            .collect();
+        assert!(!files.iter().any(|f| f == "blob.bin" || f == "link.rs"));
+    }
+
+    #[test]
+    fn test_search_workspace_limits_results() {
+        let tmp = TempDir::new().unwrap();
+        for i in 0..(MAX_SEARCH_RESULTS + 5) {
+            fs::write(tmp.path().join(format!("f{i:03}.py")), "needle\n").unwrap();
+        }
+        let matches = search(tmp.path(), "needle");
+        assert_eq!(matches.len(), MAX_SEARCH_RESULTS);
+        assert_eq!(matches[0].file, "f000.py");
+    }
+
+    #[test]
+    fn test_search_workspace_stops_at_deadline() {
+        let tmp = seed_search_workspace();
+        let root = tmp.path().join("workspace");
+        let expired = std::time::Instant::now();
+        assert!(search_workspace(&root, &root, "needle", expired).is_none());
+    }
+
     #[tokio::test]
     async fn test_read_dir_recursive() {
         let tmp = TempDir::new().unwrap();
@@ -759,6 +2324,117 @@ mod tests {
         assert!(b_children[0].children.is_none());
     }
 
+    fn entry(name: &str, is_dir: bool, size: u64, modified: Option<&str>) -> FileEntry {
+        FileEntry {
+            name: name.to_string(),
+            is_dir,
+            size,
+            modified: modified.map(ToString::to_string),
+            children: None,
+        }
+    }
+
+    fn names(entries: &[FileEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    fn sample_entries() -> Vec<FileEntry> {
+        let mut entries = vec![
+            entry("b.txt", false, 30, Some("2024-01-02T00:00:00+00:00")),
+            entry("A.md", false, 10, Some("2024-01-03T00:00:00+00:00")),
+            entry("docs", true, 4096, None),
+            entry("c.rs", false, 20, Some("2024-01-01T00:00:00+00:00")),
+        ];
+        sort_entries_default(&mut entries);
+        entries
+    }
+
+    #[test]
+    fn test_sort_entries_default_dirs_first_then_name() {
+        let entries = sample_entries();
+        assert_eq!(names(&entries), vec!["docs", "A.md", "b.txt", "c.rs"]);
+    }
+
+    #[test]
+    fn test_sort_entries_by_name() {
+        let mut entries = sample_entries();
+        sort_entries(&mut entries, SortField::Name, SortOrder::Asc);
+        assert_eq!(names(&entries), vec!["A.md", "b.txt", "c.rs", "docs"]);
+        sort_entries(&mut entries, SortField::Name, SortOrder::Desc);
+        assert_eq!(names(&entries), vec!["docs", "c.rs", "b.txt", "A.md"]);
+    }
+
+    #[test]
+    fn test_sort_entries_by_size() {
+        let mut entries = sample_entries();
+        sort_entries(&mut entries, SortField::Size, SortOrder::Asc);
+        assert_eq!(names(&entries), vec!["A.md", "c.rs", "b.txt", "docs"]);
+        sort_entries(&mut entries, SortField::Size, SortOrder::Desc);
+        assert_eq!(names(&entries), vec!["docs", "b.txt", "c.rs", "A.md"]);
+    }
+
+    #[test]
+    fn test_sort_entries_by_modified_treats_missing_as_epoch() {
+        let mut entries = sample_entries();
+        sort_entries(&mut entries, SortField::Modified, SortOrder::Asc);
+        assert_eq!(names(&entries), vec!["docs", "c.rs", "b.txt", "A.md"]);
+        sort_entries(&mut entries, SortField::Modified, SortOrder::Desc);
+        assert_eq!(names(&entries), vec!["A.md", "b.txt", "c.rs", "docs"]);
+    }
+
+    #[test]
+    fn test_sort_entries_by_type() {
+        let mut entries = sample_entries();
+        sort_entries(&mut entries, SortField::Type, SortOrder::Asc);
+        assert_eq!(names(&entries), vec!["docs", "A.md", "c.rs", "b.txt"]);
+        sort_entries(&mut entries, SortField::Type, SortOrder::Desc);
+        assert_eq!(names(&entries), vec!["b.txt", "c.rs", "A.md", "docs"]);
+    }
+
+    #[test]
+    fn test_sort_entries_equal_keys_are_stable() {
+        let mut entries = vec![
+            entry("a.txt", false, 5, None),
+            entry("b.txt", false, 5, None),
+            entry("c.txt", false, 5, None),
+        ];
+        sort_entries(&mut entries, SortField::Size, SortOrder::Asc);
+        assert_eq!(names(&entries), vec!["a.txt", "b.txt", "c.txt"]);
+        sort_entries(&mut entries, SortField::Size, SortOrder::Desc);
+        assert_eq!(names(&entries), vec!["a.txt", "b.txt", "c.txt"]);
+        sort_entries(&mut entries, SortField::Modified, SortOrder::Desc);
+        assert_eq!(names(&entries), vec!["a.txt", "b.txt", "c.txt"]);
+    }
+
+    #[test]
+    fn test_sort_entries_applies_to_children() {
+        let mut parent = entry("dir", true, 0, None);
+        parent.children = Some(vec![
+            entry("small.txt", false, 1, None),
+            entry("large.txt", false, 100, None),
+        ]);
+        let mut entries = vec![parent];
+        sort_entries(&mut entries, SortField::Size, SortOrder::Desc);
+        let children = entries[0].children.as_ref().unwrap();
+        assert_eq!(names(children), vec!["large.txt", "small.txt"]);
+    }
+
+    #[test]
+    fn view_content_type_downgrades_html() {
+        assert_eq!(view_content_type("text/html"), "text/plain; charset=utf-8");
+        assert_eq!(
+            view_content_type("application/xhtml+xml"),
+            "text/plain; charset=utf-8"
+        );
+    }
+
+    #[test]
+    fn view_content_type_keeps_other_types() {
+        assert_eq!(view_content_type("text/plain"), "text/plain");
+        assert_eq!(view_content_type("image/png"), "image/png");
+        assert_eq!(view_content_type("text/javascript"), "text/javascript");
+    }
+
     #[test]
     fn test_is_safe_filename_valid() {
         assert!(is_safe_filename("hello.txt"));
@@ -808,4 +2484,233 @@ mod tests {
         assert_eq!(parse_range("bytes=0-0", 0), None);
         assert_eq!(parse_range("bytes=0-", 0), None);
     }
+
+    #[test]
+    fn test_parse_range_last_byte_and_overflow() {
+        assert_eq!(parse_range("bytes=99-", 100), Some((99, 99)));
+        assert_eq!(parse_range("bytes=100-", 100), None);
+        assert_eq!(parse_range("bytes=0-100", 100), None);
+        assert_eq!(parse_range("bytes=-10", 100), None);
+        assert_eq!(parse_range("bytes=0-1-2", 100), None);
+        assert_eq!(parse_range("bytes=0-18446744073709551616", 100), None);
+    }
+
+    #[test]
+    fn test_is_not_modified() {
+        let etag = file_etag(100, 1_700_000_000);
+        let with = |name, value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, value.parse().unwrap());
+            headers
+        };
+
+        assert!(!is_not_modified(&HeaderMap::new(), &etag, 1_700_000_000));
+        assert!(is_not_modified(
+            &with(header::IF_NONE_MATCH, &format!("\"other\", W/{etag}")),
+            &etag,
+            1_700_000_000
+        ));
+        assert!(is_not_modified(&with(header::IF_NONE_MATCH, "*"), &etag, 0));
+        assert!(!is_not_modified(
+            &with(header::IF_NONE_MATCH, "\"stale\""),
+            &etag,
+            1_700_000_000
+        ));
+
+        let since = httpdate::fmt_http_date(
+            std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000),
+        );
+        let headers = with(header::IF_MODIFIED_SINCE, &since);
+        assert!(is_not_modified(&headers, &etag, 1_700_000_000));
+        assert!(!is_not_modified(&headers, &etag, 1_700_000_001));
+        assert!(!is_not_modified(
+            &with(header::IF_MODIFIED_SINCE, "yesterday"),
+            &etag,
+            0
+        ));
+    }
+
+    /// Serve a few fixed responses on a random local port.
+    async fn spawn_mock_server() -> String {
+        let app = Router::new()
+            .route("/small", get(|| async { "hello" }))
+            .route("/big", get(|| async { "x".repeat(64) }))
+            .route(
+                "/chunked",
+                get(|| async {
+                    let chunks = (0..8).map(|_| Ok::<_, std::io::Error>("chunk-data"));
+                    Body::from_stream(futures_util::stream::iter(chunks))
+                }),
+            )
+            .route("/missing", get(|| async { StatusCode::NOT_FOUND }))
+            .route(
+                "/redirect",
+                get(|| async { axum::response::Redirect::temporary("/small") }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn mock_url(base: &str, path: &str) -> url::Url {
+        url::Url::parse(&format!("{base}{path}")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_writes_body() {
+        let base = spawn_mock_server().await;
+        let tmp = TempDir::new().unwrap();
+        let dest = tmp.path().join("out.txt");
+
+        let size = download_to_file(
+            &reqwest::Client::new(),
+            mock_url(&base, "/small"),
+            &dest,
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(size, 5);
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_rejects_large_content_length() {
+        let base = spawn_mock_server().await;
+        let tmp = TempDir::new().unwrap();
+        let dest = tmp.path().join("out.txt");
+
+        let err = download_to_file(&reqwest::Client::new(), mock_url(&base, "/big"), &dest, 10)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::PayloadTooLarge(_)));
+        assert!(!dest.exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_counts_bytes_without_content_length() {
+        let base = spawn_mock_server().await;
+        let tmp = TempDir::new().unwrap();
+        let dest = tmp.path().join("out.txt");
+
+        let err = download_to_file(
+            &reqwest::Client::new(),
+            mock_url(&base, "/chunked"),
+            &dest,
+            20,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AppError::PayloadTooLarge(_)));
+        assert!(!dest.exists());
+
+        let size = download_to_file(
+            &reqwest::Client::new(),
+            mock_url(&base, "/chunked"),
+            &dest,
+            80,
+        )
+        .await
+        .unwrap();
+        assert_eq!(size, 80);
+    }
+
+    #[tokio::test]
+    async fn test_upload_url_client_does_not_follow_redirects() {
+        let base = spawn_mock_server().await;
+        let tmp = TempDir::new().unwrap();
+        let dest = tmp.path().join("out.txt");
+
+        let err = download_to_file(
+            &upload_url_client(None).unwrap(),
+            mock_url(&base, "/redirect"),
+            &dest,
+            1024,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(msg) if msg.contains("redirect")));
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["93.184.216.34", "8.8.8.8", "2606:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_public_host_rejects_internal_hosts() {
+        for url in [
+            "https://localhost/a",
+            "https://127.0.0.1/a",
+            "https://[::1]/a",
+            "https://169.254.169.254/latest/meta-data",
+        ] {
+            let err = resolve_public_host(&url::Url::parse(url).unwrap())
+                .await
+                .unwrap_err();
+            assert!(matches!(err, AppError::BadRequest(_)), "{url}");
+        }
+        let literal = url::Url::parse("https://93.184.216.34/a").unwrap();
+        assert!(resolve_public_host(&literal).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_upload_url_client_connects_to_pinned_address() {
+        let base = spawn_mock_server().await;
+        let addr: std::net::SocketAddr = base.trim_start_matches("http://").parse().unwrap();
+        let tmp = TempDir::new().unwrap();
+        let dest = tmp.path().join("out.txt");
+
+        let size = download_to_file(
+            &upload_url_client(Some(("files.example.invalid", addr))).unwrap(),
+            mock_url(
+                &format!("http://files.example.invalid:{}", addr.port()),
+                "/small",
+            ),
+            &dest,
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(size, 5);
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_rejects_error_status() {
+        let base = spawn_mock_server().await;
+        let tmp = TempDir::new().unwrap();
+        let dest = tmp.path().join("out.txt");
+
+        let err = download_to_file(
+            &reqwest::Client::new(),
+            mock_url(&base, "/missing"),
+            &dest,
+            1024,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)));
+        assert!(!dest.exists());
+    }
 }
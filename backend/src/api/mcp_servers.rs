@@ -0,0 +1,209 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::api::conversations::McpServerResponse;
+use crate::auth::middleware::{AdminOnly, AppState, AuthUser};
+use crate::config::Feature;
+use crate::db;
+use crate::error::AppError;
+
+const HTTP_HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_available_mcp_servers))
+        .route("/{id}/health", get(check_mcp_server_health))
+        .route(
+            "/{id}/override",
+            get(get_mcp_server_override).put(put_mcp_server_override),
+        )
+}
+
+async fn list_available_mcp_servers(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<Json<Vec<McpServerResponse>>, AppError> {
+    state.features.require(Feature::Mcp)?;
+    let servers =
+        db::mcp_servers::list_enabled_mcp_servers_for_user(&state.db, &auth.user_id).await?;
+    Ok(Json(
+        servers
+            .into_iter()
+            .map(|s| McpServerResponse {
+                id: s.id,
+                name: s.name,
+                description: s.description,
+                transport: s.transport,
+                is_enabled: s.is_enabled,
+            })
+            .collect(),
+    ))
+}
+
+/// The caller's override of a server; `null` fields inherit the global
+/// config.
+#[derive(Serialize)]
+pub struct McpServerOverrideResponse {
+    pub mcp_server_id: String,
+    pub is_enabled: Option<bool>,
+    pub read_only_overrides: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+impl From<db::mcp_servers::UserMcpServerOverride> for McpServerOverrideResponse {
+    fn from(o: db::mcp_servers::UserMcpServerOverride) -> Self {
+        Self {
+            mcp_server_id: o.mcp_server_id,
+            is_enabled: o.is_enabled,
+            read_only_overrides: o.read_only_overrides,
+            updated_at: Some(o.updated_at),
+        }
+    }
+}
+
+async fn get_mcp_server_override(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<McpServerOverrideResponse>, AppError> {
+    state.features.require(Feature::Mcp)?;
+    db::mcp_servers::get_mcp_server(&state.db, &id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let response =
+        match db::mcp_servers::get_user_mcp_server_override(&state.db, &auth.user_id, &id).await? {
+            Some(o) => o.into(),
+            None => McpServerOverrideResponse {
+                mcp_server_id: id,
+                is_enabled: None,
+                read_only_overrides: None,
+                updated_at: None,
+            },
+        };
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+pub struct McpServerOverrideRequest {
+    pub is_enabled: Option<bool>,
+    pub read_only_overrides: Option<String>,
+}
+
+/// Replace the caller's override of a server. Omitted fields are cleared and
+/// fall back to the global config.
+async fn put_mcp_server_override(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Json(req): Json<McpServerOverrideRequest>,
+) -> Result<Json<McpServerOverrideResponse>, AppError> {
+    state.features.require(Feature::Mcp)?;
+    db::mcp_servers::get_mcp_server(&state.db, &id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let read_only_overrides =
+        crate::api::admin::validate_read_only_overrides(req.read_only_overrides.as_deref())?;
+    let saved = db::mcp_servers::upsert_user_mcp_server_override(
+        &state.db,
+        &auth.user_id,
+        &id,
+        req.is_enabled,
+        read_only_overrides.as_deref(),
+    )
+    .await?;
+    Ok(Json(saved.into()))
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct HealthResponse {
+    /// `false` when the transport cannot be probed from the backend.
+    pub checked: bool,
+    pub reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl HealthResponse {
+    fn up(started: Instant) -> Self {
+        Self {
+            checked: true,
+            reachable: true,
+            latency_ms: Some(started.elapsed().as_millis() as u64),
+            error: None,
+        }
+    }
+
+    fn down(error: impl Into<String>) -> Self {
+        Self {
+            checked: true,
+            reachable: false,
+            latency_ms: None,
+            error: Some(error.into()),
+        }
+    }
+
+    fn unchecked() -> Self {
+        Self {
+            checked: false,
+            reachable: false,
+            latency_ms: None,
+            error: None,
+        }
+    }
+}
+
+/// Probe an MCP server with `GET {url}/health` (`sse`/`http` transports).
+/// `stdio` servers only ever run inside conversation containers, so they are
+/// reported as unchecked rather than launched on the backend host.
+async fn check_mcp_server_health(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminOnly,
+    Path(id): Path<String>,
+) -> Result<Json<HealthResponse>, AppError> {
+    state.features.require(Feature::Mcp)?;
+    let server = db::mcp_servers::get_mcp_server(&state.db, &id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let health = match server.transport.as_str() {
+        "sse" | "http" => match server.url.as_deref() {
+            Some(url) => check_http_health(url).await,
+            None => HealthResponse::down("No url configured"),
+        },
+        "stdio" => HealthResponse::unchecked(),
+        other => {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported transport: {other}"
+            )));
+        }
+    };
+    Ok(Json(health))
+}
+
+async fn check_http_health(url: &str) -> HealthResponse {
+    let client = match reqwest::Client::builder()
+        .timeout(HTTP_HEALTH_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => return HealthResponse::down(e.to_string()),
+    };
+    let started = Instant::now();
+    match client
+        .get(format!("{}/health", url.trim_end_matches('/')))
+        .send()
+        .await
+    {
+        Ok(resp) if resp.status().is_success() => HealthResponse::up(started),
+        Ok(resp) => HealthResponse::down(format!("Health check returned {}", resp.status())),
+        Err(e) => HealthResponse::down(e.to_string()),
+    }
+}
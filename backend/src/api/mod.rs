@@ -2,6 +2,7 @@ pub mod admin;
 pub mod auth;
 pub mod conversations;
 pub mod files;
+pub mod mcp_servers;
 pub mod presets;
 pub mod sharing;
 pub mod users;
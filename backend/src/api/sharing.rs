@@ -14,6 +14,7 @@ use tokio::io::AsyncReadExt;
 use tokio_util::io::ReaderStream;
 
 use crate::auth::middleware::{AppState, AuthUser};
+use crate::config::Feature;
 use crate::db;
 use crate::error::AppError;
 
@@ -46,6 +47,7 @@ async fn create_share(
     auth: AuthUser,
     Path(id): Path<String>,
 ) -> Result<Json<ShareResponse>, AppError> {
+    state.features.require(Feature::Sharing)?;
     // Check if already shared
     let conv = db::conversations::get_conversation(&state.db, &id, &auth.user_id)
         .await?
@@ -96,6 +98,8 @@ async fn revoke_share(
 struct SharedConversationResponse {
     title: String,
     model_name: Option<String>,
+    provider_display_name: Option<String>,
+    message_count: i64,
     created_at: String,
     updated_at: String,
 }
@@ -104,15 +108,18 @@ async fn get_shared_conversation(
     State(state): State<Arc<AppState>>,
     Path(share_token): Path<String>,
 ) -> Result<Json<SharedConversationResponse>, AppError> {
-    let conv = db::conversations::get_conversation_by_share_token(&state.db, &share_token)
+    state.features.require(Feature::Sharing)?;
+    let view = db::conversations::get_conversation_for_share_link(&state.db, &share_token)
         .await?
         .ok_or(AppError::NotFound)?;
 
     Ok(Json(SharedConversationResponse {
-        title: conv.title,
-        model_name: conv.model_name,
-        created_at: conv.created_at,
-        updated_at: conv.updated_at,
+        title: view.title,
+        model_name: view.model_name,
+        provider_display_name: view.provider_display_name,
+        message_count: view.message_count,
+        created_at: view.created_at,
+        updated_at: view.updated_at,
     }))
 }
 
@@ -137,6 +144,7 @@ async fn get_shared_messages(
     Path(share_token): Path<String>,
     Query(params): Query<PaginationParams>,
 ) -> Result<Json<SharedMessagesResponse>, AppError> {
+    state.features.require(Feature::Sharing)?;
     let conv = db::conversations::get_conversation_by_share_token(&state.db, &share_token)
         .await?
         .ok_or(AppError::NotFound)?;
@@ -174,6 +182,7 @@ async fn view_shared_file(
     Query(query): Query<FileViewQuery>,
     headers: HeaderMap,
 ) -> Result<Response, AppError> {
+    state.features.require(Feature::Sharing)?;
     let conv = db::conversations::get_conversation_by_share_token(&state.db, &share_token)
         .await?
         .ok_or(AppError::NotFound)?;
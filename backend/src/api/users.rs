@@ -1,27 +1,46 @@
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    routing::{delete, get},
+    routing::{delete, get, patch, post, put},
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use validator::Validate;
 
 use crate::auth::middleware::{AppState, AuthUser};
+use crate::auth::password;
 use crate::crypto;
 use crate::db;
+use crate::db::audit_log::AuditEvent;
+use crate::db::refresh_tokens::SessionClient;
 use crate::error::AppError;
+use crate::provider_api;
 
 pub fn router() -> Router<Arc<AppState>> {
     Router::new()
         .route("/me", get(get_profile))
+        .route("/me/password", patch(change_password))
+        .route("/me/stats", get(get_stats))
+        .route("/me/bookmarks", get(list_bookmarks))
         .route("/me/providers", get(list_providers).post(upsert_provider))
+        .route("/me/providers/order", put(reorder_providers))
         .route("/me/providers/{id}", delete(delete_provider))
+        .route("/me/providers/{id}/validate", post(validate_provider_key))
+        .route(
+            "/me/providers/{id}/refresh-models",
+            post(refresh_provider_models),
+        )
+        .route(
+            "/me/providers/{id}/conversations",
+            get(list_provider_conversations),
+        )
         .route(
             "/me/model-defaults",
             get(get_model_defaults).put(update_model_defaults),
         )
+        .route("/api-keys", get(list_api_keys).post(create_api_key))
+        .route("/api-keys/{id}", delete(delete_api_key))
 }
 
 #[derive(Serialize)]
@@ -30,6 +49,7 @@ pub struct ProfileResponse {
     pub username: String,
     pub email: String,
     pub is_admin: bool,
+    pub email_verified: bool,
     pub created_at: String,
 }
 
@@ -46,10 +66,140 @@ async fn get_profile(
         username: user.username,
         email: user.email,
         is_admin: user.is_admin,
+        email_verified: user.email_verified,
         created_at: user.created_at,
     }))
 }
 
+async fn get_stats(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<Json<db::users::UserStats>, AppError> {
+    Ok(Json(db::users::user_stats(&state.db, &auth.user_id).await?))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1, message = "Current password is required"))]
+    pub current_password: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub new_password: String,
+}
+
+/// Change the caller's password and revoke all of their refresh tokens.
+/// Access tokens already issued stay valid until they expire.
+async fn change_password(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    client: SessionClient,
+    Json(req): Json<ChangePasswordRequest>,
+) -> Result<StatusCode, AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let user = db::users::get_user_by_id(&state.db, &auth.user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let pw = req.current_password.clone();
+    let hash = user.password_hash.clone();
+    let valid = tokio::task::spawn_blocking(move || password::verify_password(&pw, &hash))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .map_err(AppError::from)?;
+    if !valid {
+        return Err(AppError::BadRequest("Current password is incorrect".into()));
+    }
+
+    let pw = req.new_password.clone();
+    let new_hash = tokio::task::spawn_blocking(move || password::hash_password(&pw))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .map_err(AppError::from)?;
+    db::users::update_password_hash(&state.db, &user.id, &new_hash).await?;
+    db::refresh_tokens::invalidate_all_for_user(&state.db, &user.id).await?;
+    db::audit_log::record(
+        &state.db,
+        &client,
+        AuditEvent::new("password_change", Some(&user.id)).target("user", &user.id),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 100, message = "Name must be 1-100 characters"))]
+    pub name: String,
+}
+
+#[derive(Serialize)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    /// Raw key; only returned when the key is created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl From<db::api_keys::ApiKey> for ApiKeyResponse {
+    fn from(k: db::api_keys::ApiKey) -> Self {
+        Self {
+            id: k.id,
+            name: k.name,
+            created_at: k.created_at,
+            last_used_at: k.last_used_at,
+            key: None,
+        }
+    }
+}
+
+async fn create_api_key(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<ApiKeyResponse>), AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let (key, key_hash) = crate::auth::generate_api_key();
+    let api_key =
+        db::api_keys::create_api_key(&state.db, &auth.user_id, &key_hash, req.name.trim()).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(ApiKeyResponse {
+            key: Some(key),
+            ..api_key.into()
+        }),
+    ))
+}
+
+async fn list_api_keys(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<Json<Vec<ApiKeyResponse>>, AppError> {
+    let keys = db::api_keys::list_api_keys(&state.db, &auth.user_id)
+        .await?
+        .into_iter()
+        .map(ApiKeyResponse::from)
+        .collect();
+    Ok(Json(keys))
+}
+
+async fn delete_api_key(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if !db::api_keys::delete_api_key(&state.db, &auth.user_id, &id).await? {
+        return Err(AppError::NotFound);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
 #[derive(Serialize)]
 pub struct ProviderResponse {
     pub id: String,
@@ -60,6 +210,7 @@ pub struct ProviderResponse {
     pub image_models: Vec<String>,
     pub is_default: bool,
     pub has_api_key: bool,
+    pub fallback_provider_id: Option<String>,
 }
 
 fn parse_models_json(json_str: Option<&str>) -> Vec<String> {
@@ -68,23 +219,37 @@ fn parse_models_json(json_str: Option<&str>) -> Vec<String> {
         .unwrap_or_default()
 }
 
+#[derive(Serialize)]
+pub struct ProviderListItem {
+    #[serde(flatten)]
+    pub provider: ProviderResponse,
+    pub conversation_count: i64,
+}
+
 async fn list_providers(
     State(state): State<Arc<AppState>>,
     auth: AuthUser,
-) -> Result<Json<Vec<ProviderResponse>>, AppError> {
-    let providers = db::providers::list_providers(&state.db, &auth.user_id).await?;
+) -> Result<Json<Vec<ProviderListItem>>, AppError> {
+    let providers = db::providers::list_providers_with_usage(&state.db, &auth.user_id).await?;
     Ok(Json(
         providers
             .into_iter()
-            .map(|p| ProviderResponse {
-                id: p.id,
-                name: p.name.unwrap_or_else(|| p.provider.clone()),
-                provider: p.provider,
-                endpoint_url: p.endpoint_url,
-                models: parse_models_json(p.models.as_deref()),
-                image_models: parse_models_json(p.image_models.as_deref()),
-                is_default: p.is_default,
-                has_api_key: true,
+            .map(|u| {
+                let p = u.provider;
+                ProviderListItem {
+                    provider: ProviderResponse {
+                        id: p.id,
+                        name: p.name.unwrap_or_else(|| p.provider.clone()),
+                        provider: p.provider,
+                        endpoint_url: p.endpoint_url,
+                        models: parse_models_json(p.models.as_deref()),
+                        image_models: parse_models_json(p.image_models.as_deref()),
+                        is_default: p.is_default,
+                        has_api_key: true,
+                        fallback_provider_id: p.fallback_provider_id,
+                    },
+                    conversation_count: u.conversation_count,
+                }
             })
             .collect(),
     ))
@@ -112,11 +277,15 @@ pub struct UpsertProviderRequest {
     pub models: Option<Vec<String>>,
     pub image_models: Option<Vec<String>>,
     pub is_default: Option<bool>,
+    /// Provider to fail over to when this one can't initialize a container.
+    /// Omit to keep the current fallback; an empty string clears it.
+    pub fallback_provider_id: Option<String>,
 }
 
 async fn upsert_provider(
     State(state): State<Arc<AppState>>,
     auth: AuthUser,
+    client: SessionClient,
     Json(req): Json<UpsertProviderRequest>,
 ) -> Result<Json<ProviderResponse>, AppError> {
     req.validate()
@@ -133,6 +302,10 @@ async fn upsert_provider(
         ));
     }
 
+    if let Some(endpoint_url) = req.endpoint_url.as_deref() {
+        db::providers::validate_endpoint_url(endpoint_url).map_err(AppError::BadRequest)?;
+    }
+
     let provider_id = normalize_optional_string(req.id.as_deref());
     let existing_provider = if let Some(id) = provider_id.as_deref() {
         db::providers::get_provider_by_id(&state.db, &auth.user_id, id).await?
@@ -148,6 +321,28 @@ async fn upsert_provider(
         .is_default
         .unwrap_or_else(|| existing_provider.as_ref().is_some_and(|p| p.is_default));
 
+    let fallback_provider_id = match req.fallback_provider_id.as_deref() {
+        Some(requested) => normalize_optional_string(Some(requested)),
+        None => existing_provider
+            .as_ref()
+            .and_then(|p| p.fallback_provider_id.clone()),
+    };
+    if let Some(fallback_id) = fallback_provider_id.as_deref() {
+        if provider_id.as_deref() == Some(fallback_id) {
+            return Err(AppError::BadRequest(
+                "A provider cannot be its own fallback".into(),
+            ));
+        }
+        if db::providers::get_provider_by_id(&state.db, &auth.user_id, fallback_id)
+            .await?
+            .is_none()
+        {
+            return Err(AppError::BadRequest(
+                "Fallback provider does not exist".into(),
+            ));
+        }
+    }
+
     // If editing and keeping existing key, reuse encrypted key from the existing provider id.
     let encrypted_key = if req.api_key == "__KEEP_EXISTING__" {
         match existing_provider.as_ref() {
@@ -186,7 +381,26 @@ async fn upsert_provider(
         image_models_json.as_deref(),
     )
     .await?;
+    db::providers::set_provider_fallback(
+        &state.db,
+        &auth.user_id,
+        &provider.id,
+        fallback_provider_id.as_deref(),
+    )
+    .await?;
     let _ = db::model_defaults::prune_invalid_provider_references(&state.db, &auth.user_id).await?;
+    db::audit_log::record(
+        &state.db,
+        &client,
+        AuditEvent::new("provider_key_save", Some(&auth.user_id))
+            .target("provider", &provider.id)
+            .details(serde_json::json!({
+                "provider_type": provider.provider,
+                "created": existing_provider.is_none(),
+                "key_changed": req.api_key != "__KEEP_EXISTING__",
+            })),
+    )
+    .await;
 
     Ok(Json(ProviderResponse {
         id: provider.id,
@@ -197,6 +411,7 @@ async fn upsert_provider(
         image_models: parse_models_json(provider.image_models.as_deref()),
         is_default: provider.is_default,
         has_api_key: true,
+        fallback_provider_id,
     }))
 }
 
@@ -214,6 +429,156 @@ async fn delete_provider(
     }
 }
 
+#[derive(Deserialize)]
+pub struct ReorderProvidersRequest {
+    pub provider_ids: Vec<String>,
+}
+
+/// Persist the user's chosen provider order. The request must list each of
+/// the user's providers exactly once.
+async fn reorder_providers(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Json(req): Json<ReorderProvidersRequest>,
+) -> Result<StatusCode, AppError> {
+    if db::providers::reorder_providers(&state.db, &auth.user_id, &req.provider_ids).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::BadRequest(
+            "provider_ids must list each of your providers exactly once".into(),
+        ))
+    }
+}
+
+/// Make a minimal authenticated request to the provider to check its stored
+/// API key.
+async fn validate_provider_key(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<provider_api::KeyValidation>, AppError> {
+    let provider = db::providers::get_provider_by_id(&state.db, &auth.user_id, &id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let api_key = crypto::decrypt(&provider.api_key_encrypted, &state.config.encryption_key)?;
+    let model = provider.model_name.clone().or_else(|| {
+        parse_models_json(provider.models.as_deref())
+            .into_iter()
+            .next()
+    });
+
+    Ok(Json(
+        provider_api::validate_api_key(
+            &provider.provider,
+            provider.endpoint_url.as_deref(),
+            &api_key,
+            model.as_deref(),
+        )
+        .await,
+    ))
+}
+
+/// Replace the provider's chat model list with the models its API currently
+/// offers. The provider's primary model is kept if it is still listed.
+async fn refresh_provider_models(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<String>>, AppError> {
+    let provider = db::providers::get_provider_by_id(&state.db, &auth.user_id, &id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let api_key = crypto::decrypt(&provider.api_key_encrypted, &state.config.encryption_key)?;
+
+    let models = state
+        .provider_api
+        .list_models(
+            &provider.provider,
+            provider.endpoint_url.as_deref(),
+            &api_key,
+        )
+        .await?;
+    if models.is_empty() {
+        return Err(AppError::BadGateway("Provider returned no models".into()));
+    }
+
+    let model_name = provider
+        .model_name
+        .as_ref()
+        .filter(|m| models.contains(m))
+        .unwrap_or(&models[0]);
+    let models_json = serde_json::to_string(&models)
+        .map_err(|e| AppError::Internal(format!("Failed to encode models: {e}")))?;
+    db::providers::upsert_provider(
+        &state.db,
+        Some(&provider.id),
+        &auth.user_id,
+        &provider.provider,
+        &provider.api_key_encrypted,
+        provider.endpoint_url.as_deref(),
+        Some(model_name),
+        provider.is_default,
+        Some(&models_json),
+        provider.name.as_deref(),
+        provider.image_models.as_deref(),
+    )
+    .await?;
+    let _ = db::model_defaults::prune_invalid_provider_references(&state.db, &auth.user_id).await?;
+
+    Ok(Json(models))
+}
+
+#[derive(Serialize)]
+pub struct ConversationRef {
+    pub id: String,
+    pub title: String,
+    pub updated_at: String,
+}
+
+/// List the conversations that depend on a provider, so the user can see
+/// what is affected before deleting or rotating it.
+async fn list_provider_conversations(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<ConversationRef>>, AppError> {
+    db::providers::get_provider_by_id(&state.db, &auth.user_id, &id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let convs =
+        db::conversations::list_conversations_by_provider(&state.db, &auth.user_id, &id).await?;
+    Ok(Json(
+        convs
+            .into_iter()
+            .map(|c| ConversationRef {
+                id: c.id,
+                title: c.title,
+                updated_at: c.updated_at,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct BookmarksParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// The caller's bookmarked messages, most recently bookmarked first.
+async fn list_bookmarks(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Query(params): Query<BookmarksParams>,
+) -> Result<Json<Vec<db::messages::BookmarkedMessage>>, AppError> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let bookmarks =
+        db::messages::list_bookmarks_for_user(&state.db, &auth.user_id, limit, offset).await?;
+    Ok(Json(bookmarks))
+}
+
 #[derive(Serialize)]
 pub struct ModelDefaultsResponse {
     pub chat_provider_id: Option<String>,
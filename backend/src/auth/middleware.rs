@@ -3,30 +3,64 @@ use std::sync::Arc;
 use axum::extract::FromRequestParts;
 use axum::http::request::Parts;
 
+use crate::api::admin::SystemStatsCache;
+use crate::api::files::PendingUploads;
 use crate::config::Config;
+use crate::config::FeatureFlags;
 use crate::docker::manager::DockerManager;
 use crate::error::AppError;
+use crate::mailer::Mailer;
+use crate::provider_api::ProviderApiClient;
 use crate::ws::WsState;
+use crate::ws::sse::SseState;
 
 /// Shared application state, stored as `Router::with_state(Arc<AppState>)`.
 pub struct AppState {
     pub db: sqlx::SqlitePool,
     pub config: Config,
+    /// Keys for signing and verifying access tokens, loaded from `config`.
+    pub jwt_keys: super::JwtKeys,
     pub ws_state: Arc<WsState>,
+    /// Server-sent event streams for `POST /messages/stream`.
+    pub sse_state: Arc<SseState>,
+    /// Resumable file uploads that have not been completed or aborted.
+    pub pending_uploads: PendingUploads,
     pub docker_manager: Arc<DockerManager>,
+    /// Client for provider APIs (model listing); replaced by a mock in tests.
+    pub provider_api: Arc<dyn ProviderApiClient>,
+    /// Outgoing email; `None` when `SMTP_URL` is not configured.
+    pub mailer: Option<Arc<dyn Mailer>>,
+    /// Cached result of `GET /api/admin/stats`.
+    pub system_stats_cache: SystemStatsCache,
+    /// Which optional features are switched on.
+    pub features: FeatureFlags,
 }
 
 /// Extractor that authenticates a request via either:
-/// 1) `Authorization: Bearer <token>`
-/// 2) `access_token` HttpOnly cookie.
+/// 1) `X-API-Key: <key>`
+/// 2) `Authorization: Bearer <token>`
+/// 3) `access_token` HttpOnly cookie.
 ///
-/// and provides the caller's identity.
+/// and provides the caller's identity. Disabled accounts are rejected with
+/// `403`, as are users with an unverified email when
+/// `require_email_verification` is enabled.
 #[derive(Debug, Clone)]
 pub struct AuthUser {
     pub user_id: String,
     pub is_admin: bool,
 }
 
+impl AuthUser {
+    /// Fail with `403` unless the caller is an admin.
+    pub fn require_admin(&self) -> Result<(), AppError> {
+        if self.is_admin {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden("Admin privileges required".into()))
+        }
+    }
+}
+
 /// Route-scoped extractor that also accepts `?token=...` for media/file URLs.
 #[derive(Debug, Clone)]
 pub struct QueryAuthUser(pub AuthUser);
@@ -63,11 +97,57 @@ fn token_from_query(parts: &Parts) -> Option<String> {
     })
 }
 
-fn authenticate(
+/// Load the caller's account and reject it if it has been disabled or, when
+/// `require_email_verification` is on, has an unverified email.
+pub async fn load_active_user(
+    state: &AppState,
+    user_id: &str,
+) -> Result<crate::db::users::User, AppError> {
+    let user = crate::db::users::get_user_by_id(&state.db, user_id)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("User not found".into()))?;
+    if !user.is_active {
+        return Err(AppError::Forbidden("Account disabled".into()));
+    }
+    if state.config.require_email_verification && !user.email_verified {
+        return Err(AppError::EmailNotVerified);
+    }
+    Ok(user)
+}
+
+/// Resolve the owner of an API key and record the key as used.
+async fn authenticate_api_key(key: &str, state: &Arc<AppState>) -> Result<AuthUser, AppError> {
+    let api_key = crate::db::api_keys::get_api_key_by_hash(&state.db, &super::hash_api_key(key))
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid API key".into()))?;
+    let user = load_active_user(state, &api_key.user_id).await?;
+
+    let pool = state.db.clone();
+    tokio::spawn(async move {
+        if let Err(e) = crate::db::api_keys::touch_api_key(&pool, &api_key.id).await {
+            tracing::warn!(api_key_id = %api_key.id, "Failed to record API key use: {e}");
+        }
+    });
+
+    Ok(AuthUser {
+        user_id: user.id,
+        is_admin: user.is_admin,
+    })
+}
+
+async fn authenticate(
     parts: &Parts,
     state: &Arc<AppState>,
     allow_query_token: bool,
 ) -> Result<AuthUser, AppError> {
+    if let Some(key) = parts
+        .headers
+        .get(super::API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+    {
+        return authenticate_api_key(key, state).await;
+    }
+
     let token = if let Some(token) = token_from_header_or_cookie(parts)? {
         token
     } else if allow_query_token {
@@ -77,12 +157,16 @@ fn authenticate(
         return Err(AppError::Unauthorized("Missing authorization".into()));
     };
 
-    let claims = super::verify_access_token(&token, &state.config.jwt_secret)
+    let claims = super::verify_access_token(&token, &state.jwt_keys)
         .map_err(|_| AppError::Unauthorized("Invalid or expired token".into()))?;
 
+    // The admin flag comes from the account, so a demotion applies at once
+    // rather than when the token expires.
+    let user = load_active_user(state, &claims.sub).await?;
+
     Ok(AuthUser {
-        user_id: claims.sub,
-        is_admin: claims.is_admin,
+        user_id: user.id,
+        is_admin: user.is_admin,
     })
 }
 
@@ -93,7 +177,7 @@ impl FromRequestParts<Arc<AppState>> for AuthUser {
         parts: &mut Parts,
         state: &Arc<AppState>,
     ) -> Result<Self, Self::Rejection> {
-        authenticate(parts, state, false)
+        authenticate(parts, state, false).await
     }
 }
 
@@ -104,7 +188,7 @@ impl FromRequestParts<Arc<AppState>> for QueryAuthUser {
         parts: &mut Parts,
         state: &Arc<AppState>,
     ) -> Result<Self, Self::Rejection> {
-        Ok(QueryAuthUser(authenticate(parts, state, true)?))
+        Ok(QueryAuthUser(authenticate(parts, state, true).await?))
     }
 }
 
@@ -121,10 +205,34 @@ impl FromRequestParts<Arc<AppState>> for AdminOnly {
         parts: &mut Parts,
         state: &Arc<AppState>,
     ) -> Result<Self, Self::Rejection> {
-        let user = AuthUser::from_request_parts(parts, state).await?;
-        if !user.is_admin {
-            return Err(AppError::Forbidden("Admin privileges required".into()));
-        }
+        AuthUser::from_request_parts(parts, state)
+            .await?
+            .require_admin()?;
         Ok(AdminOnly)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &str, is_admin: bool) -> AuthUser {
+        AuthUser {
+            user_id: id.to_string(),
+            is_admin,
+        }
+    }
+
+    #[test]
+    fn require_admin_allows_admin() {
+        assert!(user("u1", true).require_admin().is_ok());
+    }
+
+    #[test]
+    fn require_admin_rejects_regular_user() {
+        assert!(matches!(
+            user("u1", false).require_admin(),
+            Err(AppError::Forbidden(_))
+        ));
+    }
+}
@@ -1,13 +1,106 @@
 pub mod middleware;
+pub mod oauth;
 pub mod password;
 
 use axum::http::{HeaderMap, header};
 use chrono::Utc;
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+use crate::config::Config;
 
 pub const ACCESS_COOKIE_NAME: &str = "access_token";
 pub const REFRESH_COOKIE_NAME: &str = "refresh_token";
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Signing algorithm for user access tokens, set with `JWT_ALGORITHM`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum JwtAlgorithm {
+    /// HMAC with `JWT_SECRET`.
+    #[default]
+    Hs256,
+    /// RSA with the PEM key pair at `JWT_PRIVATE_KEY_PATH` / `JWT_PUBLIC_KEY_PATH`.
+    Rs256,
+    /// ECDSA P-256 with the PEM key pair at the same paths.
+    Es256,
+}
+
+impl From<JwtAlgorithm> for Algorithm {
+    fn from(alg: JwtAlgorithm) -> Self {
+        match alg {
+            JwtAlgorithm::Hs256 => Algorithm::HS256,
+            JwtAlgorithm::Rs256 => Algorithm::RS256,
+            JwtAlgorithm::Es256 => Algorithm::ES256,
+        }
+    }
+}
+
+/// Keys used to sign and verify user access tokens.
+///
+/// Container tokens are only ever checked by this server, so they keep
+/// using the HMAC `JWT_SECRET` regardless of the configured algorithm.
+#[derive(Clone)]
+pub struct JwtKeys {
+    algorithm: JwtAlgorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl JwtKeys {
+    pub fn from_secret(secret: &str) -> Self {
+        Self {
+            algorithm: JwtAlgorithm::Hs256,
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+        }
+    }
+
+    /// Build asymmetric keys from PEM-encoded private and public keys.
+    pub fn from_pem(
+        algorithm: JwtAlgorithm,
+        private_pem: &[u8],
+        public_pem: &[u8],
+    ) -> Result<Self, jsonwebtoken::errors::Error> {
+        let (encoding, decoding) = match algorithm {
+            JwtAlgorithm::Hs256 => {
+                return Err(jsonwebtoken::errors::ErrorKind::InvalidAlgorithm.into());
+            }
+            JwtAlgorithm::Rs256 => (
+                EncodingKey::from_rsa_pem(private_pem)?,
+                DecodingKey::from_rsa_pem(public_pem)?,
+            ),
+            JwtAlgorithm::Es256 => (
+                EncodingKey::from_ec_pem(private_pem)?,
+                DecodingKey::from_ec_pem(public_pem)?,
+            ),
+        };
+        Ok(Self {
+            algorithm,
+            encoding,
+            decoding,
+        })
+    }
+
+    /// Load the keys selected by `jwt_algorithm`, reading PEM files for the
+    /// asymmetric algorithms.
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        if config.jwt_algorithm == JwtAlgorithm::Hs256 {
+            return Ok(Self::from_secret(&config.jwt_secret));
+        }
+        let read = |path: &Option<String>, name: &str| {
+            let path = path
+                .as_deref()
+                .ok_or_else(|| format!("{name} is required for {:?}", config.jwt_algorithm))?;
+            std::fs::read(path).map_err(|e| format!("Failed to read {name} {path}: {e}"))
+        };
+        let private_pem = read(&config.jwt_private_key_path, "JWT_PRIVATE_KEY_PATH")?;
+        let public_pem = read(&config.jwt_public_key_path, "JWT_PUBLIC_KEY_PATH")?;
+        Self::from_pem(config.jwt_algorithm, &private_pem, &public_pem)
+            .map_err(|e| format!("Invalid JWT key: {e}"))
+    }
+}
 
 /// Claims embedded in a user-facing JWT access token.
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -35,6 +128,8 @@ pub struct ContainerClaims {
     pub exp: u64,
     /// Issued-at time as a UTC Unix timestamp.
     pub iat: u64,
+    /// Unique token ID, used to reject replayed tokens.
+    pub jti: String,
 }
 
 /// Create an access token for a user with the given TTL in seconds.
@@ -42,7 +137,7 @@ pub fn create_access_token(
     user_id: &str,
     username: &str,
     is_admin: bool,
-    secret: &str,
+    keys: &JwtKeys,
     ttl_secs: u64,
 ) -> Result<String, jsonwebtoken::errors::Error> {
     let now = Utc::now().timestamp() as u64;
@@ -53,11 +148,7 @@ pub fn create_access_token(
         exp: now + ttl_secs,
         iat: now,
     };
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )
+    encode(&Header::new(keys.algorithm.into()), &claims, &keys.encoding)
 }
 
 /// Create a container token scoped to a single conversation with the given TTL in seconds.
@@ -73,6 +164,7 @@ pub fn create_container_token(
         user_id: user_id.to_owned(),
         exp: now + ttl_secs,
         iat: now,
+        jti: uuid::Uuid::new_v4().to_string(),
     };
     encode(
         &Header::default(),
@@ -84,12 +176,12 @@ pub fn create_container_token(
 /// Verify and decode a user access token.
 pub fn verify_access_token(
     token: &str,
-    secret: &str,
+    keys: &JwtKeys,
 ) -> Result<Claims, jsonwebtoken::errors::Error> {
     let token_data = decode::<Claims>(
         token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
+        &keys.decoding,
+        &Validation::new(keys.algorithm.into()),
     )?;
     Ok(token_data.claims)
 }
@@ -119,16 +211,132 @@ pub fn get_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
     None
 }
 
+/// The address of the client behind a request. `X-Forwarded-For` (or
+/// `X-Real-IP`) is only believed when the direct peer is one of
+/// `trusted_proxies`; the client is then the right-most forwarded address
+/// that is not itself a trusted proxy, since anything further left can be
+/// forged by the client.
+pub fn client_ip(
+    headers: &HeaderMap,
+    peer: Option<IpAddr>,
+    trusted_proxies: &[ipnet::IpNet],
+) -> Option<IpAddr> {
+    let trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+    let peer = peer?;
+    if !trusted(&peer) {
+        return Some(peer);
+    }
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(forwarded_for) = header("x-forwarded-for") {
+        let mut hops = forwarded_for
+            .rsplit(',')
+            .map(|hop| hop.trim().parse::<IpAddr>());
+        return match hops.find(|hop| !matches!(hop, Ok(ip) if trusted(ip))) {
+            Some(Ok(ip)) => Some(ip),
+            // An unparseable hop means the chain can't be trusted past it.
+            Some(Err(_)) | None => Some(peer),
+        };
+    }
+    header("x-real-ip")
+        .and_then(|v| v.trim().parse().ok())
+        .or(Some(peer))
+}
+
+/// Create a random API key, returning the raw key and its SHA-256 hash.
+pub fn generate_api_key() -> (String, String) {
+    use rand::RngCore;
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let key = hex::encode(bytes);
+    let hash = hash_api_key(&key);
+    (key, hash)
+}
+
+pub fn hash_api_key(key: &str) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn client_ip_ignores_forwarded_for_from_untrusted_peer() {
+        let peer: IpAddr = "198.51.100.1".parse().unwrap();
+        let headers = forwarded("203.0.113.7");
+        assert_eq!(client_ip(&headers, Some(peer), &[]), Some(peer));
+        assert_eq!(client_ip(&headers, None, &[]), None);
+    }
+
+    #[test]
+    fn client_ip_uses_right_most_untrusted_hop_behind_trusted_proxy() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let trusted = [proxy.into(), "10.0.0.2/32".parse().unwrap()];
+        assert_eq!(
+            client_ip(
+                &forwarded("1.2.3.4, 203.0.113.7, 10.0.0.2"),
+                Some(proxy),
+                &trusted
+            ),
+            Some("203.0.113.7".parse().unwrap())
+        );
+        assert_eq!(
+            client_ip(&forwarded("garbage, 10.0.0.2"), Some(proxy), &trusted),
+            Some(proxy)
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert("x-real-ip", "203.0.113.9".parse().unwrap());
+        assert_eq!(
+            client_ip(&headers, Some(proxy), &trusted),
+            Some("203.0.113.9".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn client_ip_trusts_proxies_in_cidr_range() {
+        let proxy: IpAddr = "172.28.0.10".parse().unwrap();
+        let trusted = ["172.28.0.0/16".parse().unwrap()];
+        assert_eq!(
+            client_ip(
+                &forwarded("203.0.113.7, 172.28.0.11"),
+                Some(proxy),
+                &trusted
+            ),
+            Some("203.0.113.7".parse().unwrap())
+        );
+        let outside: IpAddr = "172.29.0.1".parse().unwrap();
+        assert_eq!(
+            client_ip(&forwarded("203.0.113.7"), Some(outside), &trusted),
+            Some(outside)
+        );
+    }
+
     const SECRET: &str = "test-jwt-secret-that-is-long-enough";
 
+    fn keys() -> JwtKeys {
+        JwtKeys::from_secret(SECRET)
+    }
+
+    fn rs256_keys(private_pem: &str, public_pem: &str) -> JwtKeys {
+        JwtKeys::from_pem(
+            JwtAlgorithm::Rs256,
+            private_pem.as_bytes(),
+            public_pem.as_bytes(),
+        )
+        .unwrap()
+    }
+
     #[test]
     fn access_token_round_trip() {
-        let token = create_access_token("user-1", "alice", false, SECRET, 7200).unwrap();
-        let claims = verify_access_token(&token, SECRET).unwrap();
+        let token = create_access_token("user-1", "alice", false, &keys(), 7200).unwrap();
+        let claims = verify_access_token(&token, &keys()).unwrap();
         assert_eq!(claims.sub, "user-1");
         assert_eq!(claims.username, "alice");
         assert!(!claims.is_admin);
@@ -136,8 +344,8 @@ mod tests {
 
     #[test]
     fn admin_flag_preserved() {
-        let token = create_access_token("user-2", "bob", true, SECRET, 7200).unwrap();
-        let claims = verify_access_token(&token, SECRET).unwrap();
+        let token = create_access_token("user-2", "bob", true, &keys(), 7200).unwrap();
+        let claims = verify_access_token(&token, &keys()).unwrap();
         assert!(claims.is_admin);
     }
 
@@ -147,11 +355,78 @@ mod tests {
         let claims = verify_container_token(&token, SECRET).unwrap();
         assert_eq!(claims.sub, "conv-1");
         assert_eq!(claims.user_id, "user-1");
+        assert!(!claims.jti.is_empty());
+    }
+
+    #[test]
+    fn container_tokens_have_unique_jti() {
+        let a = create_container_token("conv-1", "user-1", SECRET, 3600).unwrap();
+        let b = create_container_token("conv-1", "user-1", SECRET, 3600).unwrap();
+        let a = verify_container_token(&a, SECRET).unwrap();
+        let b = verify_container_token(&b, SECRET).unwrap();
+        assert_ne!(a.jti, b.jti);
     }
 
     #[test]
     fn wrong_secret_fails() {
-        let token = create_access_token("user-1", "alice", false, SECRET, 7200).unwrap();
-        assert!(verify_access_token(&token, "wrong-secret").is_err());
+        let token = create_access_token("user-1", "alice", false, &keys(), 7200).unwrap();
+        assert!(verify_access_token(&token, &JwtKeys::from_secret("wrong-secret")).is_err());
+    }
+
+    #[test]
+    fn rs256_round_trip() {
+        let keys = rs256_keys(
+            include_str!("testdata/rs256_private_1.pem"),
+            include_str!("testdata/rs256_public_1.pem"),
+        );
+        let token = create_access_token("user-1", "alice", true, &keys, 7200).unwrap();
+        let header = jsonwebtoken::decode_header(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::RS256);
+
+        let claims = verify_access_token(&token, &keys).unwrap();
+        assert_eq!(claims.sub, "user-1");
+        assert!(claims.is_admin);
+    }
+
+    #[test]
+    fn rs256_wrong_key_fails() {
+        let signer = rs256_keys(
+            include_str!("testdata/rs256_private_1.pem"),
+            include_str!("testdata/rs256_public_1.pem"),
+        );
+        let other = rs256_keys(
+            include_str!("testdata/rs256_private_2.pem"),
+            include_str!("testdata/rs256_public_2.pem"),
+        );
+        let token = create_access_token("user-1", "alice", false, &signer, 7200).unwrap();
+        assert!(verify_access_token(&token, &other).is_err());
+    }
+
+    #[test]
+    fn hs256_token_rejected_by_rs256_keys() {
+        let keys = rs256_keys(
+            include_str!("testdata/rs256_private_1.pem"),
+            include_str!("testdata/rs256_public_1.pem"),
+        );
+        let token = create_access_token("user-1", "alice", false, &self::keys(), 7200).unwrap();
+        assert!(verify_access_token(&token, &keys).is_err());
+    }
+
+    #[test]
+    fn es256_round_trip() {
+        let keys = JwtKeys::from_pem(
+            JwtAlgorithm::Es256,
+            include_bytes!("testdata/es256_private.pem"),
+            include_bytes!("testdata/es256_public.pem"),
+        )
+        .unwrap();
+        let token = create_access_token("user-1", "alice", false, &keys, 7200).unwrap();
+        assert_eq!(verify_access_token(&token, &keys).unwrap().sub, "user-1");
+    }
+
+    #[test]
+    fn from_pem_rejects_hs256_and_bad_keys() {
+        assert!(JwtKeys::from_pem(JwtAlgorithm::Hs256, b"", b"").is_err());
+        assert!(JwtKeys::from_pem(JwtAlgorithm::Rs256, b"not a key", b"not a key").is_err());
     }
 }
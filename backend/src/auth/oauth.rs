@@ -0,0 +1,129 @@
+//! OAuth2 authorization-code flow helpers (PKCE, RFC 7636).
+//!
+//! Only the client-side scaffolding lives here; the token exchange is a stub
+//! until a concrete provider is wired up.
+
+use base64::Engine as _;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::config::OAuthProvider;
+use crate::error::AppError;
+
+/// How long an authorization request may stay pending before its state expires.
+pub const OAUTH_STATE_TTL_SECS: i64 = 600;
+
+/// Identity returned by the provider after a successful code exchange.
+#[derive(Debug, Clone)]
+pub struct OAuthIdentity {
+    pub username: String,
+    pub email: String,
+}
+
+fn random_url_safe(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Generate an opaque `state` value for CSRF protection.
+pub fn generate_state() -> String {
+    random_url_safe(32)
+}
+
+/// Generate a PKCE code verifier (43 characters from the unreserved set).
+pub fn generate_code_verifier() -> String {
+    random_url_safe(32)
+}
+
+/// Derive the `S256` code challenge for a verifier.
+pub fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+/// Build the provider authorization URL for the given state and challenge.
+pub fn build_authorize_url(
+    provider: &OAuthProvider,
+    state: &str,
+    code_challenge: &str,
+) -> Result<String, url::ParseError> {
+    let mut url = url::Url::parse(&provider.auth_url)?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &provider.client_id)
+        .append_pair("state", state)
+        .append_pair("code_challenge", code_challenge)
+        .append_pair("code_challenge_method", "S256");
+    Ok(url.into())
+}
+
+/// Exchange an authorization code for the user's identity.
+///
+/// Not implemented yet: no provider-specific token or userinfo handling exists.
+pub async fn exchange_code(
+    _provider: &OAuthProvider,
+    _code: &str,
+    _code_verifier: &str,
+) -> Result<OAuthIdentity, AppError> {
+    Err(AppError::NotImplemented)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> OAuthProvider {
+        OAuthProvider {
+            client_id: "client-1".into(),
+            client_secret: "secret".into(),
+            auth_url: "https://idp.example.com/authorize".into(),
+            token_url: "https://idp.example.com/token".into(),
+        }
+    }
+
+    #[test]
+    fn code_verifier_uses_unreserved_characters() {
+        let verifier = generate_code_verifier();
+        assert_eq!(verifier.len(), 43);
+        assert!(
+            verifier
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        );
+    }
+
+    #[test]
+    fn generated_values_are_unique() {
+        assert_ne!(generate_state(), generate_state());
+        assert_ne!(generate_code_verifier(), generate_code_verifier());
+    }
+
+    #[test]
+    fn code_challenge_is_s256_of_verifier() {
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mJ92IFmZtgLLOS7Xc8lqvS57Mnj4ew"),
+            "bPPOsULZrL8t2l3FdwNq4A1YRrpcaPS2FhPI3-Dmd7I"
+        );
+    }
+
+    #[test]
+    fn authorize_url_includes_pkce_params() {
+        let url = build_authorize_url(&provider(), "st", "ch").unwrap();
+        let parsed = url::Url::parse(&url).unwrap();
+        let pairs: std::collections::HashMap<_, _> = parsed.query_pairs().into_owned().collect();
+        assert_eq!(pairs["response_type"], "code");
+        assert_eq!(pairs["client_id"], "client-1");
+        assert_eq!(pairs["state"], "st");
+        assert_eq!(pairs["code_challenge"], "ch");
+        assert_eq!(pairs["code_challenge_method"], "S256");
+    }
+
+    #[tokio::test]
+    async fn exchange_code_is_not_implemented() {
+        let err = exchange_code(&provider(), "code", "verifier")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::NotImplemented));
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use sqlx::prelude::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ApiKey {
+    pub id: String,
+    pub user_id: String,
+    pub key_hash: String,
+    pub name: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
+pub async fn create_api_key(
+    pool: &SqlitePool,
+    user_id: &str,
+    key_hash: &str,
+    name: &str,
+) -> Result<ApiKey, sqlx::Error> {
+    let id = uuid::Uuid::new_v4().to_string();
+
+    sqlx::query_as::<_, ApiKey>(
+        "INSERT INTO api_keys (id, user_id, key_hash, name) \
+         VALUES (?, ?, ?, ?) \
+         RETURNING id, user_id, key_hash, name, created_at, last_used_at",
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(key_hash)
+    .bind(name)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn list_api_keys(pool: &SqlitePool, user_id: &str) -> Result<Vec<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(
+        "SELECT id, user_id, key_hash, name, created_at, last_used_at \
+         FROM api_keys WHERE user_id = ? \
+         ORDER BY created_at DESC, rowid DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_api_key_by_hash(
+    pool: &SqlitePool,
+    key_hash: &str,
+) -> Result<Option<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(
+        "SELECT id, user_id, key_hash, name, created_at, last_used_at \
+         FROM api_keys WHERE key_hash = ?",
+    )
+    .bind(key_hash)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn touch_api_key(pool: &SqlitePool, id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE api_keys SET last_used_at = ? WHERE id = ?")
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Revoke key `id` if it is owned by `user_id`. Returns `false` when there
+/// is no such key.
+pub async fn delete_api_key(
+    pool: &SqlitePool,
+    user_id: &str,
+    id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM api_keys WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::db::users::create_user;
+
+    #[tokio::test]
+    async fn test_api_key_lifecycle() {
+        let pool = init_db("sqlite::memory:").await;
+        let user = create_user(&pool, "keys", "keys@example.com", "hash")
+            .await
+            .unwrap();
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+
+        let key = create_api_key(&pool, &user.id, "hash-1", "ci")
+            .await
+            .unwrap();
+        assert!(key.last_used_at.is_none());
+
+        let fetched = get_api_key_by_hash(&pool, "hash-1").await.unwrap().unwrap();
+        assert_eq!(fetched.id, key.id);
+        touch_api_key(&pool, &key.id).await.unwrap();
+        let listed = list_api_keys(&pool, &user.id).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].last_used_at.is_some());
+        assert!(list_api_keys(&pool, &other.id).await.unwrap().is_empty());
+
+        assert!(!delete_api_key(&pool, &other.id, &key.id).await.unwrap());
+        assert!(delete_api_key(&pool, &user.id, &key.id).await.unwrap());
+        assert!(
+            get_api_key_by_hash(&pool, "hash-1")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use sqlx::prelude::FromRow;
+
+use super::refresh_tokens::SessionClient;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AuditEntry {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: String,
+    pub details_json: Option<String>,
+}
+
+/// A sensitive action to record. `user_id` is the acting user, if known.
+#[derive(Debug, Clone)]
+pub struct AuditEvent<'a> {
+    pub action: &'a str,
+    pub user_id: Option<&'a str>,
+    pub target_type: Option<&'a str>,
+    pub target_id: Option<&'a str>,
+    pub details: Option<serde_json::Value>,
+}
+
+impl<'a> AuditEvent<'a> {
+    pub fn new(action: &'a str, user_id: Option<&'a str>) -> Self {
+        Self {
+            action,
+            user_id,
+            target_type: None,
+            target_id: None,
+            details: None,
+        }
+    }
+
+    pub fn target(mut self, target_type: &'a str, target_id: &'a str) -> Self {
+        self.target_type = Some(target_type);
+        self.target_id = Some(target_id);
+        self
+    }
+
+    pub fn details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// Best effort: the audited action has already been committed by the time
+/// this runs, so a failed insert is logged rather than failing the request.
+pub async fn record(pool: &SqlitePool, client: &SessionClient, event: AuditEvent<'_>) {
+    let result = sqlx::query(
+        "INSERT INTO audit_log \
+         (id, user_id, action, target_type, target_id, ip, user_agent, details_json) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    // v7 ids sort by creation, which orders entries within the same second.
+    .bind(uuid::Uuid::now_v7().to_string())
+    .bind(event.user_id)
+    .bind(event.action)
+    .bind(event.target_type)
+    .bind(event.target_id)
+    .bind(&client.ip_address)
+    .bind(&client.user_agent)
+    .bind(event.details.map(|d| d.to_string()))
+    .execute(pool)
+    .await;
+    if let Err(e) = result {
+        tracing::error!("Failed to record audit event {}: {e}", event.action);
+    }
+}
+
+pub async fn get_entry(pool: &SqlitePool, id: &str) -> Result<Option<AuditEntry>, sqlx::Error> {
+    sqlx::query_as::<_, AuditEntry>(
+        "SELECT id, user_id, action, target_type, target_id, ip, user_agent, created_at, \
+         details_json \
+         FROM audit_log WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Entries newest first, optionally filtered by acting user and action.
+/// `after` is the last entry of the previous page.
+pub async fn list_entries(
+    pool: &SqlitePool,
+    user_id: Option<&str>,
+    action: Option<&str>,
+    after: Option<&AuditEntry>,
+    limit: i64,
+) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    let (after_created_at, after_id) = after
+        .map(|e| (e.created_at.as_str(), e.id.as_str()))
+        .unzip();
+    sqlx::query_as::<_, AuditEntry>(
+        "SELECT id, user_id, action, target_type, target_id, ip, user_agent, created_at, \
+         details_json \
+         FROM audit_log \
+         WHERE (? IS NULL OR user_id = ?) \
+         AND (? IS NULL OR action = ?) \
+         AND (? IS NULL OR created_at < ? OR (created_at = ? AND id < ?)) \
+         ORDER BY created_at DESC, id DESC \
+         LIMIT ?",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .bind(action)
+    .bind(action)
+    .bind(after_created_at)
+    .bind(after_created_at)
+    .bind(after_created_at)
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+
+    #[tokio::test]
+    async fn records_and_pages_entries_newest_first() {
+        let pool = init_db("sqlite::memory:").await;
+        let client = SessionClient {
+            user_agent: Some("test-agent".into()),
+            ip_address: Some("10.0.0.1".into()),
+        };
+        for action in ["login", "logout", "login"] {
+            record(&pool, &client, AuditEvent::new(action, Some("u1"))).await;
+        }
+        record(
+            &pool,
+            &client,
+            AuditEvent::new("provider_key_save", Some("u2"))
+                .target("provider", "p1")
+                .details(serde_json::json!({"provider_type": "openai"})),
+        )
+        .await;
+
+        let all = list_entries(&pool, None, None, None, 10).await.unwrap();
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[0].action, "provider_key_save");
+        assert_eq!(all[0].target_id.as_deref(), Some("p1"));
+        assert_eq!(all[0].ip.as_deref(), Some("10.0.0.1"));
+        assert_eq!(
+            all[0].details_json.as_deref(),
+            Some(r#"{"provider_type":"openai"}"#)
+        );
+
+        let logins = list_entries(&pool, Some("u1"), Some("login"), None, 1)
+            .await
+            .unwrap();
+        assert_eq!(logins.len(), 1);
+        let older = list_entries(&pool, Some("u1"), Some("login"), Some(&logins[0]), 10)
+            .await
+            .unwrap();
+        assert_eq!(older.len(), 1);
+        assert_ne!(older[0].id, logins[0].id);
+    }
+
+    #[tokio::test]
+    async fn pages_cover_every_entry_in_order() {
+        let pool = init_db("sqlite::memory:").await;
+        let client = SessionClient {
+            user_agent: None,
+            ip_address: None,
+        };
+        let actions: Vec<String> = (0..7).map(|i| format!("action-{i}")).collect();
+        for action in &actions {
+            record(&pool, &client, AuditEvent::new(action, None)).await;
+        }
+        // Most entries share a timestamp; the id keeps their order.
+        sqlx::query("UPDATE audit_log SET created_at = '2026-01-01 00:00:00' WHERE action != ?")
+            .bind("action-6")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut seen = Vec::new();
+        let mut after = None;
+        loop {
+            let page = list_entries(&pool, None, None, after.as_ref(), 3)
+                .await
+                .unwrap();
+            seen.extend(page.iter().map(|e| e.action.clone()));
+            match page.last() {
+                Some(last) if page.len() == 3 => after = Some(last.clone()),
+                _ => break,
+            }
+        }
+        let expected: Vec<String> = actions.into_iter().rev().collect();
+        assert_eq!(seen, expected);
+        assert!(get_entry(&pool, "missing").await.unwrap().is_none());
+    }
+}
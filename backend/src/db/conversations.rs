@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
 use sqlx::prelude::FromRow;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Conversation {
@@ -20,6 +22,23 @@ pub struct Conversation {
     pub share_token: Option<String>,
     pub thinking_budget: Option<i64>,
     pub subagent_thinking_budget: Option<i64>,
+    pub pinned: bool,
+    pub last_container_error: Option<String>,
+    /// Set while the conversation is archived and hidden from the default list.
+    pub archived_at: Option<String>,
+    /// Overrides the global container idle timeout; `0` never times out.
+    pub container_idle_timeout_secs: Option<i64>,
+}
+
+/// All conversations the user owns, archived ones included.
+pub async fn count_user_conversations(
+    pool: &SqlitePool,
+    user_id: &str,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM conversations WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -50,6 +69,7 @@ pub async fn create_conversation(
         image_model,
         thinking_budget,
         thinking_budget,
+        None,
     )
     .await
 }
@@ -69,15 +89,17 @@ pub async fn create_conversation_with_subagent(
     image_model: Option<&str>,
     thinking_budget: Option<i64>,
     subagent_thinking_budget: Option<i64>,
+    container_idle_timeout_secs: Option<i64>,
 ) -> Result<Conversation, sqlx::Error> {
     let id = uuid::Uuid::new_v4().to_string();
 
     sqlx::query_as::<_, Conversation>(
-        "INSERT INTO conversations (id, user_id, title, system_prompt_override, provider_id, model_name, subagent_provider_id, subagent_model, deep_thinking, image_provider_id, image_model, thinking_budget, subagent_thinking_budget)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "INSERT INTO conversations (id, user_id, title, system_prompt_override, provider_id, model_name, subagent_provider_id, subagent_model, deep_thinking, image_provider_id, image_model, thinking_budget, subagent_thinking_budget, container_idle_timeout_secs)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
          RETURNING id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
                    system_prompt_override, deep_thinking, created_at, updated_at,
-                   image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget",
+                   image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget, pinned,
+                   last_container_error, archived_at, container_idle_timeout_secs",
     )
     .bind(&id)
     .bind(user_id)
@@ -92,23 +114,148 @@ pub async fn create_conversation_with_subagent(
     .bind(image_model)
     .bind(thinking_budget)
     .bind(subagent_thinking_budget)
+    .bind(container_idle_timeout_secs)
     .fetch_one(pool)
     .await
 }
 
+/// A conversation plus a summary of its most recent message, used by the
+/// conversation list so the client doesn't need a request per conversation.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ConversationWithPreview {
+    #[sqlx(flatten)]
+    pub conversation: Conversation,
+    pub last_message_preview: Option<String>,
+    pub last_message_at: Option<String>,
+    pub message_count: i64,
+}
+
+/// Maximum number of characters returned in `last_message_preview`.
+pub const MESSAGE_PREVIEW_CHARS: i64 = 140;
+
+#[allow(dead_code)]
 pub async fn list_conversations(
     pool: &SqlitePool,
     user_id: &str,
+    include_archived: bool,
+) -> Result<Vec<Conversation>, sqlx::Error> {
+    sqlx::query_as::<_, Conversation>(
+        "SELECT id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
+                system_prompt_override, deep_thinking, created_at, updated_at,
+                image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget, pinned,
+                last_container_error, archived_at, container_idle_timeout_secs
+         FROM conversations
+         WHERE user_id = ? AND (? OR archived_at IS NULL)
+         ORDER BY pinned_at IS NULL ASC, updated_at DESC, created_at DESC, id DESC",
+    )
+    .bind(user_id)
+    .bind(include_archived)
+    .fetch_all(pool)
+    .await
+}
+
+/// Narrows [`list_conversations_with_preview`]. The default lists every
+/// unarchived conversation.
+#[derive(Debug, Clone, Default)]
+pub struct ConversationFilter {
+    /// Only conversations with this pinned state.
+    pub pinned: Option<bool>,
+    pub include_archived: bool,
+    /// Case-insensitive substring of the title or of any live message.
+    pub query: Option<String>,
+    /// Only conversations carrying this tag.
+    pub tag: Option<String>,
+}
+
+/// `%text%` with LIKE wildcards in `text` escaped by `\`.
+fn contains_pattern(text: &str) -> String {
+    let mut pattern = String::with_capacity(text.len() + 2);
+    pattern.push('%');
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// List a user's conversations matching `filter`, pinned ones first.
+pub async fn list_conversations_with_preview(
+    pool: &SqlitePool,
+    user_id: &str,
+    filter: &ConversationFilter,
+) -> Result<Vec<ConversationWithPreview>, sqlx::Error> {
+    let pattern = filter
+        .query
+        .as_deref()
+        .filter(|q| !q.is_empty())
+        .map(contains_pattern);
+    sqlx::query_as::<_, ConversationWithPreview>(
+        "SELECT c.id, c.user_id, c.title, c.provider_id, c.model_name,
+                c.subagent_provider_id, c.subagent_model,
+                c.system_prompt_override, c.deep_thinking, c.created_at, c.updated_at,
+                c.image_provider_id, c.image_model, c.share_token,
+                c.thinking_budget, c.subagent_thinking_budget, c.pinned,
+                c.last_container_error, c.archived_at, c.container_idle_timeout_secs,
+                SUBSTR(lm.content, 1, ?) AS last_message_preview,
+                lm.created_at AS last_message_at,
+                COALESCE(stats.message_count, 0) AS message_count
+         FROM conversations c
+         LEFT JOIN (
+             SELECT conversation_id, COUNT(*) AS message_count, MAX(position) AS last_position
+             FROM messages
+             WHERE deleted_at IS NULL
+             GROUP BY conversation_id
+         ) stats ON stats.conversation_id = c.id
+         LEFT JOIN messages lm
+             ON lm.conversation_id = stats.conversation_id AND lm.position = stats.last_position
+         WHERE c.user_id = ? AND (? IS NULL OR c.pinned = ?)
+           AND (? OR c.archived_at IS NULL)
+           AND (? IS NULL
+                OR c.title LIKE ? ESCAPE '\\'
+                OR c.id IN (SELECT DISTINCT conversation_id FROM messages
+                            WHERE deleted_at IS NULL AND content LIKE ? ESCAPE '\\'))
+           AND (? IS NULL OR EXISTS (SELECT 1 FROM conversation_tags t
+                                     WHERE t.conversation_id = c.id AND t.tag = ?))
+         ORDER BY c.pinned_at IS NULL ASC, c.updated_at DESC, c.created_at DESC, c.id DESC",
+    )
+    .bind(MESSAGE_PREVIEW_CHARS)
+    .bind(user_id)
+    .bind(filter.pinned)
+    .bind(filter.pinned)
+    .bind(filter.include_archived)
+    .bind(&pattern)
+    .bind(&pattern)
+    .bind(&pattern)
+    .bind(&filter.tag)
+    .bind(&filter.tag)
+    .fetch_all(pool)
+    .await
+}
+
+/// List a user's conversations that reference `provider_id` in any role
+/// (chat, subagent, or image).
+pub async fn list_conversations_by_provider(
+    pool: &SqlitePool,
+    user_id: &str,
+    provider_id: &str,
 ) -> Result<Vec<Conversation>, sqlx::Error> {
     sqlx::query_as::<_, Conversation>(
         "SELECT id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
                 system_prompt_override, deep_thinking, created_at, updated_at,
-                image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget
+                image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget, pinned,
+                last_container_error, archived_at, container_idle_timeout_secs
          FROM conversations
          WHERE user_id = ?
+           AND (provider_id = ? OR subagent_provider_id = ? OR image_provider_id = ?)
          ORDER BY updated_at DESC, created_at DESC, id DESC",
     )
     .bind(user_id)
+    .bind(provider_id)
+    .bind(provider_id)
+    .bind(provider_id)
     .fetch_all(pool)
     .await
 }
@@ -121,7 +268,8 @@ pub async fn get_conversation(
     sqlx::query_as::<_, Conversation>(
         "SELECT id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
                 system_prompt_override, deep_thinking, created_at, updated_at,
-                image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget
+                image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget, pinned,
+                last_container_error, archived_at, container_idle_timeout_secs
          FROM conversations
          WHERE id = ? AND user_id = ?",
     )
@@ -161,6 +309,7 @@ pub async fn update_conversation(
         image_model,
         thinking_budget,
         thinking_budget,
+        None,
     )
     .await
 }
@@ -181,6 +330,7 @@ pub async fn update_conversation_with_subagent(
     image_model: Option<&str>,
     thinking_budget: Option<i64>,
     subagent_thinking_budget: Option<i64>,
+    container_idle_timeout_secs: Option<i64>,
 ) -> Result<Option<Conversation>, sqlx::Error> {
     sqlx::query_as::<_, Conversation>(
         "UPDATE conversations
@@ -190,11 +340,13 @@ pub async fn update_conversation_with_subagent(
              image_provider_id = ?, image_model = ?,
              thinking_budget = ?,
              subagent_thinking_budget = ?,
+             container_idle_timeout_secs = ?,
              updated_at = datetime('now')
          WHERE id = ? AND user_id = ?
          RETURNING id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
                    system_prompt_override, deep_thinking, created_at, updated_at,
-                   image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget",
+                   image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget, pinned,
+                   last_container_error, archived_at, container_idle_timeout_secs",
     )
     .bind(title)
     .bind(provider_id)
@@ -207,203 +359,1438 @@ pub async fn update_conversation_with_subagent(
     .bind(image_model)
     .bind(thinking_budget)
     .bind(subagent_thinking_budget)
+    .bind(container_idle_timeout_secs)
     .bind(id)
     .bind(user_id)
     .fetch_optional(pool)
     .await
 }
 
-pub async fn touch_conversation_activity(
+/// Switch a conversation from `from_provider_id` to `to_provider_id` with
+/// `model_name`. The subagent moves too if it used the same provider.
+/// Returns `false` if the conversation no longer uses `from_provider_id`.
+pub async fn fail_over_provider(
     pool: &SqlitePool,
     id: &str,
     user_id: &str,
+    from_provider_id: &str,
+    to_provider_id: &str,
+    model_name: &str,
 ) -> Result<bool, sqlx::Error> {
     let result = sqlx::query(
         "UPDATE conversations
-         SET updated_at = datetime('now')
-         WHERE id = ? AND user_id = ?",
+         SET provider_id = ?, model_name = ?,
+             subagent_model = CASE WHEN subagent_provider_id = ? THEN ? ELSE subagent_model END,
+             subagent_provider_id = CASE WHEN subagent_provider_id = ? THEN ? ELSE subagent_provider_id END,
+             updated_at = datetime('now')
+         WHERE id = ? AND user_id = ? AND provider_id = ?",
     )
+    .bind(to_provider_id)
+    .bind(model_name)
+    .bind(from_provider_id)
+    .bind(model_name)
+    .bind(from_provider_id)
+    .bind(to_provider_id)
     .bind(id)
     .bind(user_id)
+    .bind(from_provider_id)
     .execute(pool)
     .await?;
-
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn delete_conversation(
+/// Pin or unpin a conversation. Does not bump `updated_at`, so unpinning
+/// returns the conversation to its usual position in the list.
+pub async fn set_pinned(
     pool: &SqlitePool,
     id: &str,
     user_id: &str,
-) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM conversations WHERE id = ? AND user_id = ?")
-        .bind(id)
-        .bind(user_id)
+    pinned: bool,
+) -> Result<Option<Conversation>, sqlx::Error> {
+    sqlx::query_as::<_, Conversation>(
+        "UPDATE conversations
+         SET pinned = ?,
+             pinned_at = CASE WHEN ? THEN COALESCE(pinned_at, datetime('now')) END
+         WHERE id = ? AND user_id = ?
+         RETURNING id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
+                   system_prompt_override, deep_thinking, created_at, updated_at,
+                   image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget, pinned,
+                   last_container_error, archived_at, container_idle_timeout_secs",
+    )
+    .bind(pinned)
+    .bind(pinned)
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Record (or clear, with `None`) the last container init failure for a
+/// conversation.
+pub async fn set_last_container_error(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    error: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE conversations SET last_container_error = ? WHERE id = ?")
+        .bind(error)
+        .bind(conversation_id)
         .execute(pool)
         .await?;
+    Ok(())
+}
+
+pub async fn touch_conversation_activity(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE conversations
+         SET updated_at = datetime('now')
+         WHERE id = ? AND user_id = ?",
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
 
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn set_share_token(
+async fn set_archived_at(
     pool: &SqlitePool,
     id: &str,
     user_id: &str,
-    share_token: &str,
+    archived: bool,
 ) -> Result<Option<Conversation>, sqlx::Error> {
-    // Only set if share_token is currently NULL to avoid race conditions
     sqlx::query_as::<_, Conversation>(
         "UPDATE conversations
-         SET share_token = ?, updated_at = datetime('now')
-         WHERE id = ? AND user_id = ? AND share_token IS NULL
+         SET archived_at = CASE WHEN ? THEN COALESCE(archived_at, datetime('now')) END
+         WHERE id = ? AND user_id = ?
          RETURNING id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
                    system_prompt_override, deep_thinking, created_at, updated_at,
-                   image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget",
+                   image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget, pinned,
+                   last_container_error, archived_at, container_idle_timeout_secs",
     )
-    .bind(share_token)
+    .bind(archived)
     .bind(id)
     .bind(user_id)
     .fetch_optional(pool)
     .await
 }
 
-pub async fn remove_share_token(
+/// Hide a conversation from the default list. Archiving an archived
+/// conversation keeps its original `archived_at`.
+pub async fn archive_conversation(
     pool: &SqlitePool,
     id: &str,
     user_id: &str,
-) -> Result<bool, sqlx::Error> {
+) -> Result<Option<Conversation>, sqlx::Error> {
+    set_archived_at(pool, id, user_id, true).await
+}
+
+pub async fn unarchive_conversation(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+) -> Result<Option<Conversation>, sqlx::Error> {
+    set_archived_at(pool, id, user_id, false).await
+}
+
+/// Archive every conversation whose last activity is older than
+/// `inactive_days`. `user_id = None` applies to all users.
+pub async fn archive_all_inactive(
+    pool: &SqlitePool,
+    user_id: Option<&str>,
+    inactive_days: i64,
+) -> Result<u64, sqlx::Error> {
     let result = sqlx::query(
-        "UPDATE conversations SET share_token = NULL, updated_at = datetime('now')
-         WHERE id = ? AND user_id = ? AND share_token IS NOT NULL",
+        "UPDATE conversations
+         SET archived_at = datetime('now')
+         WHERE (? IS NULL OR user_id = ?)
+           AND updated_at < datetime('now', ?)
+           AND archived_at IS NULL",
     )
-    .bind(id)
     .bind(user_id)
+    .bind(user_id)
+    .bind(format!("-{} days", inactive_days))
     .execute(pool)
     .await?;
-    Ok(result.rows_affected() > 0)
+
+    Ok(result.rows_affected())
 }
 
-pub async fn get_conversation_by_share_token(
+/// Message and token totals for one conversation.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ConversationStats {
+    pub message_count: i64,
+    pub user_message_count: i64,
+    pub assistant_message_count: i64,
+    pub total_tokens: i64,
+    pub created_at: String,
+}
+
+/// Totals over the non-deleted messages of a conversation owned by
+/// `user_id`. Returns `None` if there is no such conversation.
+pub async fn conversation_stats(
     pool: &SqlitePool,
-    share_token: &str,
-) -> Result<Option<Conversation>, sqlx::Error> {
-    sqlx::query_as::<_, Conversation>(
-        "SELECT id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
-                system_prompt_override, deep_thinking, created_at, updated_at,
-                image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget
-         FROM conversations
-         WHERE share_token = ?",
+    id: &str,
+    user_id: &str,
+) -> Result<Option<ConversationStats>, sqlx::Error> {
+    sqlx::query_as::<_, ConversationStats>(
+        "SELECT COUNT(m.id) AS message_count,
+                COALESCE(SUM(m.role = 'user'), 0) AS user_message_count,
+                COALESCE(SUM(m.role = 'assistant'), 0) AS assistant_message_count,
+                COALESCE(SUM(m.token_count), 0) AS total_tokens,
+                c.created_at
+         FROM conversations c
+         LEFT JOIN messages m ON m.conversation_id = c.id AND m.deleted_at IS NULL
+         WHERE c.id = ? AND c.user_id = ?
+         GROUP BY c.id",
     )
-    .bind(share_token)
+    .bind(id)
+    .bind(user_id)
     .fetch_optional(pool)
     .await
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::db::init_db;
-    use crate::db::users::create_user;
+/// A self-contained copy of a conversation and its messages, used to move a
+/// conversation between accounts or deployments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationExport {
+    pub title: String,
+    pub provider_id: Option<String>,
+    pub model_name: Option<String>,
+    pub subagent_provider_id: Option<String>,
+    pub subagent_model: Option<String>,
+    pub system_prompt_override: Option<String>,
+    #[serde(default)]
+    pub deep_thinking: bool,
+    pub image_provider_id: Option<String>,
+    pub image_model: Option<String>,
+    pub thinking_budget: Option<i64>,
+    pub subagent_thinking_budget: Option<i64>,
+    pub container_idle_timeout_secs: Option<i64>,
+    pub created_at: Option<String>,
+    pub messages: Vec<ExportedMessage>,
+}
 
-    async fn setup() -> (SqlitePool, String) {
-        let pool = init_db("sqlite::memory:").await;
-        let user = create_user(&pool, "testuser", "test@example.com", "hash")
-            .await
-            .unwrap();
-        (pool, user.id)
-    }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedMessage {
+    pub role: String,
+    pub content: String,
+    pub tool_calls: Option<String>,
+    pub tool_call_id: Option<String>,
+    pub token_count: Option<i64>,
+    pub created_at: Option<String>,
+    /// Structured parts from `messages_v2`; empty for messages that were
+    /// never dual-written.
+    #[serde(default)]
+    pub parts: Vec<ExportedMessagePart>,
+}
 
-    #[tokio::test]
-    async fn test_create_conversation() {
-        let (pool, user_id) = setup().await;
-        let conv = create_conversation(
-            &pool, &user_id, "My Chat", None, None, None, false, None, None, None,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedMessagePart {
+    pub part_type: String,
+    pub text: Option<String>,
+    pub json_payload: Option<String>,
+    pub tool_call_id: Option<String>,
+}
+
+/// Export conversation `id` owned by `user_id` with all of its non-deleted
+/// messages. Returns `None` if there is no such conversation.
+pub async fn export_conversation(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+) -> Result<Option<ConversationExport>, sqlx::Error> {
+    let Some(conv) = get_conversation(pool, id, user_id).await? else {
+        return Ok(None);
+    };
+
+    let messages = sqlx::query_as::<_, crate::db::messages::Message>(
+        "SELECT id, conversation_id, role, content, \
+         tool_calls, tool_call_id, token_count, created_at \
+         FROM messages \
+         WHERE conversation_id = ? AND deleted_at IS NULL \
+         ORDER BY position ASC",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+    let message_ids: Vec<String> = messages.iter().map(|m| m.id.clone()).collect();
+    let mut parts_by_message =
+        crate::db::messages_v2::list_message_parts_for_messages(pool, &message_ids).await?;
+
+    let messages = messages
+        .into_iter()
+        .map(|m| ExportedMessage {
+            parts: parts_by_message
+                .remove(&m.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|p| ExportedMessagePart {
+                    part_type: p.part_type,
+                    text: p.text,
+                    json_payload: p.json_payload,
+                    tool_call_id: p.tool_call_id,
+                })
+                .collect(),
+            role: m.role,
+            content: m.content,
+            tool_calls: m.tool_calls,
+            tool_call_id: m.tool_call_id,
+            token_count: m.token_count,
+            created_at: Some(m.created_at),
+        })
+        .collect();
+
+    Ok(Some(ConversationExport {
+        title: conv.title,
+        provider_id: conv.provider_id,
+        model_name: conv.model_name,
+        subagent_provider_id: conv.subagent_provider_id,
+        subagent_model: conv.subagent_model,
+        system_prompt_override: conv.system_prompt_override,
+        deep_thinking: conv.deep_thinking,
+        image_provider_id: conv.image_provider_id,
+        image_model: conv.image_model,
+        thinking_budget: conv.thinking_budget,
+        subagent_thinking_budget: conv.subagent_thinking_budget,
+        container_idle_timeout_secs: conv.container_idle_timeout_secs,
+        created_at: Some(conv.created_at),
+        messages,
+    }))
+}
+
+/// Keep `(provider_id, model)` only when `user_id` owns that provider, so an
+/// export from another account can't point at someone else's credentials.
+async fn owned_provider_pair(
+    conn: &mut sqlx::SqliteConnection,
+    user_id: &str,
+    provider_id: Option<String>,
+    model: Option<String>,
+) -> Result<(Option<String>, Option<String>), sqlx::Error> {
+    let Some(provider_id) = provider_id else {
+        return Ok((None, None));
+    };
+    let owned: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM user_providers WHERE id = ? AND user_id = ?)",
+    )
+    .bind(&provider_id)
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(if owned {
+        (Some(provider_id), model)
+    } else {
+        (None, None)
+    })
+}
+
+/// Create a new conversation for `user_id` from `export`, inserting it and
+/// all of its messages in one transaction. The conversation and messages get
+/// fresh IDs; provider references the user doesn't own are dropped.
+pub async fn import_conversation(
+    pool: &SqlitePool,
+    user_id: &str,
+    export: ConversationExport,
+) -> Result<Conversation, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let (provider_id, model_name) =
+        owned_provider_pair(&mut tx, user_id, export.provider_id, export.model_name).await?;
+    let (subagent_provider_id, subagent_model) = owned_provider_pair(
+        &mut tx,
+        user_id,
+        export.subagent_provider_id,
+        export.subagent_model,
+    )
+    .await?;
+    let (image_provider_id, image_model) = owned_provider_pair(
+        &mut tx,
+        user_id,
+        export.image_provider_id,
+        export.image_model,
+    )
+    .await?;
+
+    let conv = sqlx::query_as::<_, Conversation>(
+        "INSERT INTO conversations (id, user_id, title, system_prompt_override, provider_id, model_name, subagent_provider_id, subagent_model, deep_thinking, image_provider_id, image_model, thinking_budget, subagent_thinking_budget, container_idle_timeout_secs)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+         RETURNING id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
+                   system_prompt_override, deep_thinking, created_at, updated_at,
+                   image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget, pinned,
+                   last_container_error, archived_at, container_idle_timeout_secs",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(user_id)
+    .bind(&export.title)
+    .bind(&export.system_prompt_override)
+    .bind(provider_id)
+    .bind(model_name)
+    .bind(subagent_provider_id)
+    .bind(subagent_model)
+    .bind(export.deep_thinking)
+    .bind(image_provider_id)
+    .bind(image_model)
+    .bind(export.thinking_budget)
+    .bind(export.subagent_thinking_budget)
+    .bind(export.container_idle_timeout_secs)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    for message in &export.messages {
+        let message_id = uuid::Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO messages (id, conversation_id, role, content, \
+             tool_calls, tool_call_id, token_count, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, COALESCE(?, datetime('now')))",
         )
-        .await
-        .unwrap();
-        assert_eq!(conv.user_id, user_id);
-        assert_eq!(conv.title, "My Chat");
-        assert!(conv.provider_id.is_none());
-        assert!(conv.model_name.is_none());
-        assert!(conv.system_prompt_override.is_none());
-        assert!(!conv.deep_thinking);
-        assert!(!conv.id.is_empty());
-        assert!(conv.image_provider_id.is_none());
-        assert!(conv.image_model.is_none());
-    }
+        .bind(&message_id)
+        .bind(&conv.id)
+        .bind(&message.role)
+        .bind(&message.content)
+        .bind(&message.tool_calls)
+        .bind(&message.tool_call_id)
+        .bind(message.token_count)
+        .bind(&message.created_at)
+        .execute(&mut *tx)
+        .await?;
 
-    #[tokio::test]
-    async fn test_create_conversation_with_system_prompt() {
-        let (pool, user_id) = setup().await;
-        let conv = create_conversation(
-            &pool,
-            &user_id,
-            "Prompted Chat",
-            Some("You are a pirate."),
-            None,
-            None,
-            false,
-            None,
-            None,
-            None,
+        if message.parts.is_empty() {
+            continue;
+        }
+        sqlx::query(
+            "INSERT INTO messages_v2 (id, conversation_id, role, created_at) \
+             VALUES (?, ?, ?, COALESCE(?, datetime('now')))",
         )
-        .await
-        .unwrap();
-        assert_eq!(
-            conv.system_prompt_override.as_deref(),
-            Some("You are a pirate.")
-        );
+        .bind(&message_id)
+        .bind(&conv.id)
+        .bind(&message.role)
+        .bind(&message.created_at)
+        .execute(&mut *tx)
+        .await?;
+        for (seq, part) in message.parts.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO message_parts (id, message_id, seq, part_type, text, json_payload, tool_call_id) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(&message_id)
+            .bind(seq as i64)
+            .bind(&part.part_type)
+            .bind(&part.text)
+            .bind(&part.json_payload)
+            .bind(&part.tool_call_id)
+            .execute(&mut *tx)
+            .await?;
+        }
     }
 
-    #[tokio::test]
-    async fn test_create_conversation_with_provider_and_model() {
-        let (pool, user_id) = setup().await;
-        let conv = create_conversation(
-            &pool,
-            &user_id,
-            "Provider Chat",
-            None,
-            Some("openai"),
-            Some("gpt-4o"),
-            false,
-            None,
-            None,
-            None,
-        )
-        .await
-        .unwrap();
-        assert_eq!(conv.provider_id.as_deref(), Some("openai"));
-        assert_eq!(conv.model_name.as_deref(), Some("gpt-4o"));
-        assert!(conv.system_prompt_override.is_none());
+    tx.commit().await?;
+    Ok(conv)
+}
+
+/// Create a copy of conversation `id` owned by `user_id` with the same model
+/// settings. Sharing, pinning, archiving and container state are not copied.
+async fn insert_conversation_copy(
+    conn: &mut sqlx::SqliteConnection,
+    id: &str,
+    user_id: &str,
+    title: &str,
+) -> Result<Option<Conversation>, sqlx::Error> {
+    sqlx::query_as::<_, Conversation>(
+        "INSERT INTO conversations (id, user_id, title, system_prompt_override, provider_id, model_name, subagent_provider_id, subagent_model, deep_thinking, image_provider_id, image_model, thinking_budget, subagent_thinking_budget, container_idle_timeout_secs)
+         SELECT ?, user_id, ?, system_prompt_override, provider_id, model_name, subagent_provider_id, subagent_model, deep_thinking, image_provider_id, image_model, thinking_budget, subagent_thinking_budget, container_idle_timeout_secs
+         FROM conversations WHERE id = ? AND user_id = ?
+         RETURNING id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
+                   system_prompt_override, deep_thinking, created_at, updated_at,
+                   image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget, pinned,
+                   last_container_error, archived_at, container_idle_timeout_secs",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(title)
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await
+}
+
+/// Copy the live messages of `source_id` into `target_id` under fresh IDs,
+/// keeping their order and timestamps along with their structured parts.
+/// With `through_message_id`, copying stops after that message.
+async fn copy_messages(
+    conn: &mut sqlx::SqliteConnection,
+    source_id: &str,
+    target_id: &str,
+    through_message_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let messages = sqlx::query_as::<_, crate::db::messages::Message>(
+        "SELECT id, conversation_id, role, content, \
+         tool_calls, tool_call_id, token_count, created_at \
+         FROM messages \
+         WHERE conversation_id = ? AND deleted_at IS NULL \
+         AND (? IS NULL OR position <= (SELECT position FROM messages WHERE id = ? AND conversation_id = ?)) \
+         ORDER BY position ASC",
+    )
+    .bind(source_id)
+    .bind(through_message_id)
+    .bind(through_message_id)
+    .bind(source_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for message in messages {
+        let message_id = uuid::Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO messages (id, conversation_id, role, content, \
+             tool_calls, tool_call_id, token_count, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&message_id)
+        .bind(target_id)
+        .bind(&message.role)
+        .bind(&message.content)
+        .bind(&message.tool_calls)
+        .bind(&message.tool_call_id)
+        .bind(message.token_count)
+        .bind(&message.created_at)
+        .execute(&mut *conn)
+        .await?;
+
+        let copied_v2 = sqlx::query(
+            "INSERT INTO messages_v2 (id, conversation_id, role, provider, model, token_usage_json, meta_json, created_at) \
+             SELECT ?, ?, role, provider, model, token_usage_json, meta_json, created_at \
+             FROM messages_v2 WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(&message_id)
+        .bind(target_id)
+        .bind(&message.id)
+        .execute(&mut *conn)
+        .await?;
+        if copied_v2.rows_affected() == 0 {
+            continue;
+        }
+
+        let parts = sqlx::query_as::<_, crate::db::messages_v2::MessagePart>(
+            "SELECT id, message_id, seq, part_type, text, json_payload, tool_call_id, created_at \
+             FROM message_parts WHERE message_id = ? ORDER BY seq ASC",
+        )
+        .bind(&message.id)
+        .fetch_all(&mut *conn)
+        .await?;
+        for part in parts {
+            sqlx::query(
+                "INSERT INTO message_parts (id, message_id, seq, part_type, text, json_payload, tool_call_id, created_at) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(&message_id)
+            .bind(part.seq)
+            .bind(&part.part_type)
+            .bind(&part.text)
+            .bind(&part.json_payload)
+            .bind(&part.tool_call_id)
+            .bind(&part.created_at)
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy conversation `id` into a new "Fork of ..." conversation holding its
+/// messages up to and including `message_id`. Returns `None` when the
+/// conversation isn't owned by `user_id` or the message isn't a live message
+/// in it.
+pub async fn fork_conversation_at_message(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+    message_id: &str,
+) -> Result<Option<Conversation>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let title = sqlx::query_scalar::<_, String>(
+        "SELECT c.title FROM conversations c \
+         JOIN messages m ON m.conversation_id = c.id \
+         WHERE c.id = ? AND c.user_id = ? AND m.id = ? AND m.deleted_at IS NULL",
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(message_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(title) = title else {
+        return Ok(None);
+    };
+
+    let Some(fork) =
+        insert_conversation_copy(&mut tx, id, user_id, &format!("Fork of {title}")).await?
+    else {
+        return Ok(None);
+    };
+    copy_messages(&mut tx, id, &fork.id, Some(message_id)).await?;
+
+    tx.commit().await?;
+    Ok(Some(fork))
+}
+
+/// Copy conversation `id` and all of its messages into a new
+/// "... (copy)" conversation. Returns `None` when it isn't owned by `user_id`.
+pub async fn duplicate_conversation(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+) -> Result<Option<Conversation>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let title = sqlx::query_scalar::<_, String>(
+        "SELECT title FROM conversations WHERE id = ? AND user_id = ?",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(title) = title else {
+        return Ok(None);
+    };
+
+    let Some(copy) =
+        insert_conversation_copy(&mut tx, id, user_id, &format!("{title} (copy)")).await?
+    else {
+        return Ok(None);
+    };
+    copy_messages(&mut tx, id, &copy.id, None).await?;
+
+    tx.commit().await?;
+    Ok(Some(copy))
+}
+
+pub async fn delete_conversation(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM conversations WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Tag a conversation. Returns `false` if it already had the tag.
+pub async fn add_tag(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    tag: &str,
+) -> Result<bool, sqlx::Error> {
+    let result =
+        sqlx::query("INSERT OR IGNORE INTO conversation_tags (conversation_id, tag) VALUES (?, ?)")
+            .bind(conversation_id)
+            .bind(tag)
+            .execute(pool)
+            .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Untag a conversation. Returns `false` if it didn't have the tag.
+pub async fn remove_tag(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    tag: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM conversation_tags WHERE conversation_id = ? AND tag = ?")
+        .bind(conversation_id)
+        .bind(tag)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// A conversation's tags in alphabetical order.
+pub async fn list_tags(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT tag FROM conversation_tags WHERE conversation_id = ? ORDER BY tag ASC",
+    )
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Tags for several conversations at once, keyed by conversation ID.
+/// Conversations without tags are absent from the map.
+pub async fn list_tags_for_conversations(
+    pool: &SqlitePool,
+    conversation_ids: &[String],
+) -> Result<HashMap<String, Vec<String>>, sqlx::Error> {
+    if conversation_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT conversation_id, tag FROM conversation_tags WHERE conversation_id IN (",
+    );
+    {
+        let mut separated = query.separated(", ");
+        for id in conversation_ids {
+            separated.push_bind(id);
+        }
+    }
+    query.push(") ORDER BY conversation_id ASC, tag ASC");
+
+    let rows = query
+        .build_query_as::<(String, String)>()
+        .fetch_all(pool)
+        .await?;
+    let mut grouped: HashMap<String, Vec<String>> = HashMap::new();
+    for (conversation_id, tag) in rows {
+        grouped.entry(conversation_id).or_default().push(tag);
+    }
+    Ok(grouped)
+}
+
+/// The subset of `ids` that are conversations owned by `user_id`.
+pub async fn owned_conversation_ids(
+    pool: &SqlitePool,
+    user_id: &str,
+    ids: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT id FROM conversations WHERE user_id = ");
+    query.push_bind(user_id).push(" AND id IN (");
+    {
+        let mut separated = query.separated(", ");
+        for id in ids {
+            separated.push_bind(id);
+        }
+    }
+    query.push(")");
+    query.build_query_scalar::<String>().fetch_all(pool).await
+}
+
+/// Delete the conversations in `ids` owned by `user_id` in one statement.
+/// Returns how many were deleted.
+pub async fn delete_conversations(
+    pool: &SqlitePool,
+    user_id: &str,
+    ids: &[String],
+) -> Result<u64, sqlx::Error> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let mut query = QueryBuilder::<Sqlite>::new("DELETE FROM conversations WHERE user_id = ");
+    query.push_bind(user_id).push(" AND id IN (");
+    {
+        let mut separated = query.separated(", ");
+        for id in ids {
+            separated.push_bind(id);
+        }
+    }
+    query.push(")");
+    Ok(query.build().execute(pool).await?.rows_affected())
+}
+
+pub async fn set_share_token(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+    share_token: &str,
+) -> Result<Option<Conversation>, sqlx::Error> {
+    // Only set if share_token is currently NULL to avoid race conditions
+    sqlx::query_as::<_, Conversation>(
+        "UPDATE conversations
+         SET share_token = ?, updated_at = datetime('now')
+         WHERE id = ? AND user_id = ? AND share_token IS NULL
+         RETURNING id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
+                   system_prompt_override, deep_thinking, created_at, updated_at,
+                   image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget, pinned,
+                   last_container_error, archived_at, container_idle_timeout_secs",
+    )
+    .bind(share_token)
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn remove_share_token(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE conversations SET share_token = NULL, updated_at = datetime('now')
+         WHERE id = ? AND user_id = ? AND share_token IS NOT NULL",
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_conversation_by_share_token(
+    pool: &SqlitePool,
+    share_token: &str,
+) -> Result<Option<Conversation>, sqlx::Error> {
+    sqlx::query_as::<_, Conversation>(
+        "SELECT id, user_id, title, provider_id, model_name, subagent_provider_id, subagent_model,
+                system_prompt_override, deep_thinking, created_at, updated_at,
+                image_provider_id, image_model, share_token, thinking_budget, subagent_thinking_budget, pinned,
+                last_container_error, archived_at, container_idle_timeout_secs
+         FROM conversations
+         WHERE share_token = ?",
+    )
+    .bind(share_token)
+    .fetch_optional(pool)
+    .await
+}
+
+/// What a public share link may reveal about a conversation. Built from a
+/// narrow column list so provider secrets and owner details never load.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SharedConversationView {
+    pub id: String,
+    pub title: String,
+    pub model_name: Option<String>,
+    pub provider_display_name: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub message_count: i64,
+}
+
+pub async fn get_conversation_for_share_link(
+    pool: &SqlitePool,
+    share_token: &str,
+) -> Result<Option<SharedConversationView>, sqlx::Error> {
+    sqlx::query_as::<_, SharedConversationView>(
+        "SELECT c.id, c.title, c.model_name,
+                COALESCE(p.name, p.provider) AS provider_display_name,
+                c.created_at, c.updated_at,
+                (SELECT COUNT(*) FROM messages m
+                 WHERE m.conversation_id = c.id AND m.deleted_at IS NULL) AS message_count
+         FROM conversations c
+         LEFT JOIN user_providers p ON p.id = c.provider_id
+         WHERE c.share_token = ?",
+    )
+    .bind(share_token)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Bytes used by the files under a conversation's workspace directory, or 0
+/// if it does not exist. Walks the tree on a blocking thread; symlinks are
+/// counted as links, not followed.
+pub async fn get_workspace_usage(conversation_id: &str) -> u64 {
+    let root = std::path::PathBuf::from(format!("data/conversations/{conversation_id}"));
+    tokio::task::spawn_blocking(move || dir_size(&root))
+        .await
+        .unwrap_or(0)
+}
+
+fn dir_size(path: &std::path::Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| !entry.file_type().is_dir())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::db::messages::create_message;
+    use crate::db::users::create_user;
+
+    async fn setup() -> (SqlitePool, String) {
+        let pool = init_db("sqlite::memory:").await;
+        let user = create_user(&pool, "testuser", "test@example.com", "hash")
+            .await
+            .unwrap();
+        (pool, user.id)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dir_size_counts_nested_files_without_following_symlinks() {
+        let outside = tempfile::TempDir::new().unwrap();
+        std::fs::write(outside.path().join("big.bin"), vec![0u8; 4096]).unwrap();
+        let root = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(root.path().join("a/b")).unwrap();
+        std::fs::write(root.path().join("top.txt"), "12345").unwrap();
+        std::fs::write(root.path().join("a/b/deep.txt"), "1234567890").unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.path().join("link")).unwrap();
+
+        let size = dir_size(root.path());
+        assert!((15..4096).contains(&size), "size = {size}");
+        assert_eq!(dir_size(&root.path().join("missing")), 0);
+    }
+
+    #[tokio::test]
+    async fn test_fork_conversation_at_message_copies_prefix() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool, &user_id, "Original", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let first = create_message(&pool, &conv.id, "user", "hi", None, None, None)
+            .await
+            .unwrap();
+        crate::db::messages_v2::upsert_message_text_part(&pool, &first.id, &conv.id, "user", "hi")
+            .await
+            .unwrap();
+        let second = create_message(&pool, &conv.id, "assistant", "hello", None, None, None)
+            .await
+            .unwrap();
+        create_message(&pool, &conv.id, "user", "later", None, None, None)
+            .await
+            .unwrap();
+        set_share_token(&pool, &conv.id, &user_id, "tok")
+            .await
+            .unwrap();
+
+        let fork = fork_conversation_at_message(&pool, &conv.id, &user_id, &second.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(fork.id, conv.id);
+        assert_eq!(fork.title, "Fork of Original");
+        assert!(fork.share_token.is_none());
+
+        let copied = crate::db::messages::list_messages(&pool, &fork.id, 10, 0)
+            .await
+            .unwrap();
+        let contents: Vec<_> = copied.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["hi", "hello"]);
+        assert_eq!(copied[0].created_at, first.created_at);
+        let parts =
+            crate::db::messages_v2::list_message_parts_for_messages(&pool, &[copied[0].id.clone()])
+                .await
+                .unwrap();
+        assert_eq!(parts[&copied[0].id][0].text.as_deref(), Some("hi"));
+
+        // Unknown message, foreign message and foreign user all yield None.
+        assert!(
+            fork_conversation_at_message(&pool, &conv.id, &user_id, "missing")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            fork_conversation_at_message(&pool, &fork.id, &user_id, &second.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            fork_conversation_at_message(&pool, &conv.id, "other-user", &second.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let (pool, user_id) = setup().await;
+        let provider = crate::db::providers::upsert_provider(
+            &pool, None, &user_id, "openai", "enc", None, None, true, None, None, None,
+        )
+        .await
+        .unwrap();
+        let conv = create_conversation_with_subagent(
+            &pool,
+            &user_id,
+            "Exported",
+            Some("be brief"),
+            Some(&provider.id),
+            Some("gpt-4o"),
+            Some("someone-elses-provider"),
+            Some("gpt-4o-mini"),
+            true,
+            None,
+            None,
+            Some(4096),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        create_message(&pool, &conv.id, "user", "hi", None, None, None)
+            .await
+            .unwrap();
+        let reply = create_message(&pool, &conv.id, "assistant", "hello", None, None, Some(7))
+            .await
+            .unwrap();
+        crate::db::messages_v2::create_message_with_parts(
+            &pool,
+            Some(&reply.id),
+            &conv.id,
+            "assistant",
+            None,
+            None,
+            None,
+            None,
+            &[
+                crate::db::messages_v2::NewMessagePart {
+                    part_type: "reasoning",
+                    text: Some("thinking"),
+                    json_payload: None,
+                    tool_call_id: None,
+                },
+                crate::db::messages_v2::NewMessagePart {
+                    part_type: "text",
+                    text: Some("hello"),
+                    json_payload: None,
+                    tool_call_id: None,
+                },
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert!(
+            export_conversation(&pool, &conv.id, "someone-else")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let export = export_conversation(&pool, &conv.id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(export.title, "Exported");
+        assert_eq!(export.messages.len(), 2);
+        assert!(export.messages[0].parts.is_empty());
+        assert_eq!(export.messages[1].parts.len(), 2);
+        assert_eq!(export.messages[1].parts[0].part_type, "reasoning");
+
+        let imported = import_conversation(&pool, &user_id, export.clone())
+            .await
+            .unwrap();
+        assert_ne!(imported.id, conv.id);
+        assert_eq!(imported.title, "Exported");
+        assert_eq!(imported.system_prompt_override.as_deref(), Some("be brief"));
+        assert_eq!(imported.provider_id.as_deref(), Some(provider.id.as_str()));
+        assert_eq!(imported.model_name.as_deref(), Some("gpt-4o"));
+        assert!(imported.subagent_provider_id.is_none());
+        assert!(imported.subagent_model.is_none());
+        assert!(imported.deep_thinking);
+        assert_eq!(imported.thinking_budget, Some(4096));
+
+        let reexported = export_conversation(&pool, &imported.id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reexported.messages.len(), 2);
+        assert_eq!(reexported.messages[0].content, "hi");
+        assert_eq!(reexported.messages[1].token_count, Some(7));
+        assert_eq!(
+            reexported.messages[1].created_at,
+            export.messages[1].created_at
+        );
+        let texts: Vec<_> = reexported.messages[1]
+            .parts
+            .iter()
+            .map(|p| p.text.as_deref())
+            .collect();
+        assert_eq!(texts, vec![Some("thinking"), Some("hello")]);
+    }
+
+    #[tokio::test]
+    async fn test_conversation_stats() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool, &user_id, "Stats", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+
+        let empty = conversation_stats(&pool, &conv.id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(empty.message_count, 0);
+        assert_eq!(empty.total_tokens, 0);
+        assert_eq!(empty.created_at, conv.created_at);
+
+        let first = create_message(&pool, &conv.id, "user", "hi", None, None, Some(3))
+            .await
+            .unwrap();
+        create_message(&pool, &conv.id, "assistant", "hello", None, None, Some(10))
+            .await
+            .unwrap();
+        create_message(&pool, &conv.id, "tool", "{}", None, Some("t1"), None)
+            .await
+            .unwrap();
+        let stats = conversation_stats(&pool, &conv.id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stats.message_count, 3);
+        assert_eq!(stats.user_message_count, 1);
+        assert_eq!(stats.assistant_message_count, 1);
+        assert_eq!(stats.total_tokens, 13);
+
+        // Soft-deleted messages are not counted.
+        crate::db::messages::delete_messages_after(&pool, &conv.id, &first.id)
+            .await
+            .unwrap();
+        let stats = conversation_stats(&pool, &conv.id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stats.message_count, 1);
+        assert_eq!(stats.total_tokens, 3);
+
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        assert!(
+            conversation_stats(&pool, &conv.id, &other.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_conversation() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool, &user_id, "My Chat", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(conv.user_id, user_id);
+        assert_eq!(conv.title, "My Chat");
+        assert!(conv.provider_id.is_none());
+        assert!(conv.model_name.is_none());
+        assert!(conv.system_prompt_override.is_none());
+        assert!(!conv.deep_thinking);
+        assert!(!conv.id.is_empty());
+        assert!(conv.image_provider_id.is_none());
+        assert!(conv.image_model.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_create_conversation_with_system_prompt() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool,
+            &user_id,
+            "Prompted Chat",
+            Some("You are a pirate."),
+            None,
+            None,
+            false,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            conv.system_prompt_override.as_deref(),
+            Some("You are a pirate.")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_conversation_with_provider_and_model() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool,
+            &user_id,
+            "Provider Chat",
+            None,
+            Some("openai"),
+            Some("gpt-4o"),
+            false,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(conv.provider_id.as_deref(), Some("openai"));
+        assert_eq!(conv.model_name.as_deref(), Some("gpt-4o"));
+        assert!(conv.system_prompt_override.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_create_conversation_with_all_fields() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool,
+            &user_id,
+            "Full Chat",
+            Some("Be helpful."),
+            Some("anthropic"),
+            Some("claude-3"),
+            true,
+            Some("My Google"),
+            Some("gemini-3-pro-image-preview"),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(conv.title, "Full Chat");
+        assert_eq!(conv.system_prompt_override.as_deref(), Some("Be helpful."));
+        assert_eq!(conv.provider_id.as_deref(), Some("anthropic"));
+        assert_eq!(conv.model_name.as_deref(), Some("claude-3"));
+        assert!(conv.deep_thinking);
+        assert_eq!(conv.image_provider_id.as_deref(), Some("My Google"));
+        assert_eq!(
+            conv.image_model.as_deref(),
+            Some("gemini-3-pro-image-preview")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_with_preview_no_messages() {
+        let (pool, user_id) = setup().await;
+        create_conversation(
+            &pool, &user_id, "Empty", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+
+        let convs =
+            list_conversations_with_preview(&pool, &user_id, &ConversationFilter::default())
+                .await
+                .unwrap();
+        assert_eq!(convs.len(), 1);
+        assert_eq!(convs[0].conversation.title, "Empty");
+        assert!(convs[0].last_message_preview.is_none());
+        assert!(convs[0].last_message_at.is_none());
+        assert_eq!(convs[0].message_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_list_with_preview_one_message() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool, &user_id, "Single", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let msg = create_message(&pool, &conv.id, "user", "Hello there", None, None, None)
+            .await
+            .unwrap();
+
+        let convs =
+            list_conversations_with_preview(&pool, &user_id, &ConversationFilter::default())
+                .await
+                .unwrap();
+        assert_eq!(convs.len(), 1);
+        assert_eq!(
+            convs[0].last_message_preview.as_deref(),
+            Some("Hello there")
+        );
+        assert_eq!(
+            convs[0].last_message_at.as_deref(),
+            Some(msg.created_at.as_str())
+        );
+        assert_eq!(convs[0].message_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_with_preview_many_messages() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool, &user_id, "Busy", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let other = create_conversation(
+            &pool, &user_id, "Other", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        for i in 0..4 {
+            create_message(
+                &pool,
+                &conv.id,
+                "user",
+                &format!("msg {i}"),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+        let long = "x".repeat(300);
+        create_message(&pool, &conv.id, "assistant", &long, None, None, None)
+            .await
+            .unwrap();
+        create_message(&pool, &other.id, "user", "unrelated", None, None, None)
+            .await
+            .unwrap();
+
+        let convs =
+            list_conversations_with_preview(&pool, &user_id, &ConversationFilter::default())
+                .await
+                .unwrap();
+        let busy = convs.iter().find(|c| c.conversation.id == conv.id).unwrap();
+        assert_eq!(busy.message_count, 5);
+        assert_eq!(
+            busy.last_message_preview.as_deref(),
+            Some("x".repeat(MESSAGE_PREVIEW_CHARS as usize).as_str())
+        );
+
+        let other = convs
+            .iter()
+            .find(|c| c.conversation.id == other.id)
+            .unwrap();
+        assert_eq!(other.message_count, 1);
+        assert_eq!(other.last_message_preview.as_deref(), Some("unrelated"));
+    }
+
+    #[tokio::test]
+    async fn test_list_with_preview_excludes_other_users() {
+        let (pool, user_id) = setup().await;
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        create_conversation(
+            &pool, &other.id, "Theirs", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+
+        let convs =
+            list_conversations_with_preview(&pool, &user_id, &ConversationFilter::default())
+                .await
+                .unwrap();
+        assert!(convs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_conversations_by_provider_matches_each_role() {
+        let (pool, user_id) = setup().await;
+        let chat = create_conversation_with_subagent(
+            &pool,
+            &user_id,
+            "Chat role",
+            None,
+            Some("target"),
+            Some("m"),
+            Some("other"),
+            Some("m"),
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let subagent = create_conversation_with_subagent(
+            &pool,
+            &user_id,
+            "Subagent role",
+            None,
+            Some("other"),
+            Some("m"),
+            Some("target"),
+            Some("m"),
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let image = create_conversation_with_subagent(
+            &pool,
+            &user_id,
+            "Image role",
+            None,
+            Some("other"),
+            Some("m"),
+            Some("other"),
+            Some("m"),
+            false,
+            Some("target"),
+            Some("img"),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        create_conversation_with_subagent(
+            &pool,
+            &user_id,
+            "Unrelated",
+            None,
+            Some("other"),
+            Some("m"),
+            Some("other"),
+            Some("m"),
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let found = list_conversations_by_provider(&pool, &user_id, "target")
+            .await
+            .unwrap();
+        let mut ids: Vec<&str> = found.iter().map(|c| c.id.as_str()).collect();
+        ids.sort();
+        let mut expected = vec![chat.id.as_str(), subagent.id.as_str(), image.id.as_str()];
+        expected.sort();
+        assert_eq!(ids, expected);
     }
 
     #[tokio::test]
-    async fn test_create_conversation_with_all_fields() {
+    async fn test_list_conversations_by_provider_scoped_to_user() {
         let (pool, user_id) = setup().await;
-        let conv = create_conversation(
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        create_conversation(
             &pool,
-            &user_id,
-            "Full Chat",
-            Some("Be helpful."),
-            Some("anthropic"),
-            Some("claude-3"),
-            true,
-            Some("My Google"),
-            Some("gemini-3-pro-image-preview"),
+            &other.id,
+            "Theirs",
+            None,
+            Some("target"),
+            Some("m"),
+            false,
+            None,
+            None,
             None,
         )
         .await
         .unwrap();
-        assert_eq!(conv.title, "Full Chat");
-        assert_eq!(conv.system_prompt_override.as_deref(), Some("Be helpful."));
-        assert_eq!(conv.provider_id.as_deref(), Some("anthropic"));
-        assert_eq!(conv.model_name.as_deref(), Some("claude-3"));
-        assert!(conv.deep_thinking);
-        assert_eq!(conv.image_provider_id.as_deref(), Some("My Google"));
-        assert_eq!(
-            conv.image_model.as_deref(),
-            Some("gemini-3-pro-image-preview")
-        );
+
+        let found = list_conversations_by_provider(&pool, &user_id, "target")
+            .await
+            .unwrap();
+        assert!(found.is_empty());
     }
 
     #[tokio::test]
@@ -419,7 +1806,7 @@ mod tests {
         )
         .await
         .unwrap();
-        let convs = list_conversations(&pool, &user_id).await.unwrap();
+        let convs = list_conversations(&pool, &user_id, false).await.unwrap();
         assert_eq!(convs.len(), 2);
     }
 
@@ -664,6 +2051,95 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[tokio::test]
+    async fn test_get_conversation_for_share_link() {
+        let (pool, user_id) = setup().await;
+        let provider = crate::db::providers::upsert_provider(
+            &pool,
+            None,
+            &user_id,
+            "openai",
+            "super-secret-ciphertext",
+            None,
+            None,
+            false,
+            None,
+            Some("Work OpenAI"),
+            None,
+        )
+        .await
+        .unwrap();
+        let conv = create_conversation(
+            &pool,
+            &user_id,
+            "Shared",
+            None,
+            Some(&provider.id),
+            Some("gpt-4o"),
+            false,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        create_message(&pool, &conv.id, "user", "hi", None, None, None)
+            .await
+            .unwrap();
+        set_share_token(&pool, &conv.id, &user_id, "tok")
+            .await
+            .unwrap();
+
+        let view = get_conversation_for_share_link(&pool, "tok")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(view.title, "Shared");
+        assert_eq!(view.model_name.as_deref(), Some("gpt-4o"));
+        assert_eq!(view.provider_display_name.as_deref(), Some("Work OpenAI"));
+        assert_eq!(view.message_count, 1);
+
+        let json = serde_json::to_value(&view).unwrap();
+        let keys: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        for forbidden in ["api_key_encrypted", "provider_id", "user_id", "share_token"] {
+            assert!(!keys.contains(&forbidden), "{forbidden} leaked");
+        }
+        assert!(!json.to_string().contains("super-secret-ciphertext"));
+        assert!(!json.to_string().contains(&provider.id));
+
+        assert!(
+            get_conversation_for_share_link(&pool, "other")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_conversation_for_share_link_without_provider() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool, &user_id, "Bare", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        set_share_token(&pool, &conv.id, &user_id, "tok")
+            .await
+            .unwrap();
+
+        let view = get_conversation_for_share_link(&pool, "tok")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(view.provider_display_name.is_none());
+        assert_eq!(view.message_count, 0);
+    }
+
     #[tokio::test]
     async fn test_create_conversation_with_thinking_budget() {
         let (pool, user_id) = setup().await;
@@ -779,7 +2255,7 @@ mod tests {
             .unwrap();
         assert!(touched);
 
-        let convs = list_conversations(&pool, &user_id).await.unwrap();
+        let convs = list_conversations(&pool, &user_id, false).await.unwrap();
         assert_eq!(convs.len(), 2);
         assert_eq!(convs[0].id, conv1.id);
     }
@@ -812,12 +2288,88 @@ mod tests {
         .await
         .unwrap();
 
-        let convs = list_conversations(&pool, &user_id).await.unwrap();
+        let convs = list_conversations(&pool, &user_id, false).await.unwrap();
         assert!(convs.len() >= 2);
         assert_eq!(convs[0].id, "zzz");
         assert_eq!(convs[1].id, "aaa");
     }
 
+    async fn set_updated_at(pool: &SqlitePool, id: &str, updated_at: &str) {
+        sqlx::query("UPDATE conversations SET updated_at = ? WHERE id = ?")
+            .bind(updated_at)
+            .bind(id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn archived_at(pool: &SqlitePool, id: &str) -> Option<String> {
+        sqlx::query_scalar("SELECT archived_at FROM conversations WHERE id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_archive_all_inactive_only_archives_stale_conversations() {
+        let (pool, user_id) = setup().await;
+        let stale = create_conversation(
+            &pool, &user_id, "Stale", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let fresh = create_conversation(
+            &pool, &user_id, "Fresh", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        set_updated_at(&pool, &stale.id, "2000-01-01 00:00:00").await;
+
+        let archived = archive_all_inactive(&pool, Some(&user_id), 90)
+            .await
+            .unwrap();
+        assert_eq!(archived, 1);
+        assert!(archived_at(&pool, &stale.id).await.is_some());
+        assert!(archived_at(&pool, &fresh.id).await.is_none());
+
+        // Already-archived conversations are not counted again
+        let again = archive_all_inactive(&pool, Some(&user_id), 90)
+            .await
+            .unwrap();
+        assert_eq!(again, 0);
+    }
+
+    #[tokio::test]
+    async fn test_archive_all_inactive_scopes_by_user() {
+        let (pool, user_id) = setup().await;
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        let mine = create_conversation(
+            &pool, &user_id, "Mine", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let theirs = create_conversation(
+            &pool, &other.id, "Theirs", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        set_updated_at(&pool, &mine.id, "2000-01-01 00:00:00").await;
+        set_updated_at(&pool, &theirs.id, "2000-01-01 00:00:00").await;
+
+        let archived = archive_all_inactive(&pool, Some(&user_id), 30)
+            .await
+            .unwrap();
+        assert_eq!(archived, 1);
+        assert!(archived_at(&pool, &theirs.id).await.is_none());
+
+        let archived = archive_all_inactive(&pool, None, 30).await.unwrap();
+        assert_eq!(archived, 1);
+        assert!(archived_at(&pool, &theirs.id).await.is_some());
+    }
+
     #[tokio::test]
     async fn test_touch_conversation_activity_wrong_user_returns_false() {
         let (pool, user_id) = setup().await;
@@ -844,4 +2396,261 @@ mod tests {
             .unwrap();
         assert!(!touched);
     }
+
+    #[tokio::test]
+    async fn test_pinned_conversations_list_first() {
+        let (pool, user_id) = setup().await;
+        let old = create_conversation(
+            &pool, &user_id, "Old", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let recent = create_conversation(
+            &pool, &user_id, "Recent", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        sqlx::query("UPDATE conversations SET updated_at = '2000-01-01 00:00:00' WHERE id = ?")
+            .bind(&old.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let pinned = set_pinned(&pool, &old.id, &user_id, true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(pinned.pinned);
+        assert_eq!(pinned.updated_at, "2000-01-01 00:00:00");
+
+        let convs =
+            list_conversations_with_preview(&pool, &user_id, &ConversationFilter::default())
+                .await
+                .unwrap();
+        let ids: Vec<_> = convs.iter().map(|c| c.conversation.id.as_str()).collect();
+        assert_eq!(ids, vec![old.id.as_str(), recent.id.as_str()]);
+
+        let only_pinned = list_conversations_with_preview(
+            &pool,
+            &user_id,
+            &ConversationFilter {
+                pinned: Some(true),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(only_pinned.len(), 1);
+        assert_eq!(only_pinned[0].conversation.id, old.id);
+    }
+
+    #[tokio::test]
+    async fn test_archive_round_trip_and_list_filtering() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool, &user_id, "Old", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        assert!(
+            archive_conversation(&pool, &conv.id, &other.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let archived = archive_conversation(&pool, &conv.id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(archived.archived_at.is_some());
+        assert!(
+            list_conversations_with_preview(&pool, &user_id, &ConversationFilter::default())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            list_conversations_with_preview(
+                &pool,
+                &user_id,
+                &ConversationFilter {
+                    include_archived: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap()
+            .len(),
+            1
+        );
+        assert!(
+            get_conversation(&pool, &conv.id, &user_id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        let restored = unarchive_conversation(&pool, &conv.id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(restored.archived_at.is_none());
+        assert_eq!(
+            list_conversations(&pool, &user_id, false)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bulk_delete_only_touches_owned_conversations() {
+        let (pool, user_id) = setup().await;
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        let mine = create_conversation(
+            &pool, &user_id, "Mine", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let theirs = create_conversation(
+            &pool, &other.id, "Theirs", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let ids = vec![mine.id.clone(), theirs.id.clone(), "missing".to_string()];
+
+        assert_eq!(
+            owned_conversation_ids(&pool, &user_id, &ids).await.unwrap(),
+            vec![mine.id.clone()]
+        );
+        assert_eq!(
+            delete_conversations(&pool, &user_id, &ids).await.unwrap(),
+            1
+        );
+        assert!(
+            get_conversation(&pool, &theirs.id, &other.id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(delete_conversations(&pool, &user_id, &[]).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_tags_round_trip_and_filter() {
+        let (pool, user_id) = setup().await;
+        let work = create_conversation(
+            &pool, &user_id, "Work", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let home = create_conversation(
+            &pool, &user_id, "Home", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+
+        assert!(add_tag(&pool, &work.id, "work").await.unwrap());
+        assert!(!add_tag(&pool, &work.id, "work").await.unwrap());
+        assert!(add_tag(&pool, &work.id, "research").await.unwrap());
+        assert!(add_tag(&pool, &home.id, "personal").await.unwrap());
+        assert_eq!(
+            list_tags(&pool, &work.id).await.unwrap(),
+            vec!["research", "work"]
+        );
+
+        let by_id = list_tags_for_conversations(&pool, &[work.id.clone(), home.id.clone()])
+            .await
+            .unwrap();
+        assert_eq!(by_id[&home.id], vec!["personal"]);
+
+        let tagged = list_conversations_with_preview(
+            &pool,
+            &user_id,
+            &ConversationFilter {
+                tag: Some("work".into()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].conversation.id, work.id);
+
+        assert!(remove_tag(&pool, &work.id, "work").await.unwrap());
+        assert!(!remove_tag(&pool, &work.id, "work").await.unwrap());
+        assert_eq!(list_tags(&pool, &work.id).await.unwrap(), vec!["research"]);
+    }
+
+    #[tokio::test]
+    async fn test_set_pinned_round_trip_and_ownership() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool, &user_id, "Chat", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        assert!(!conv.pinned);
+
+        assert!(
+            set_pinned(&pool, &conv.id, &user_id, true)
+                .await
+                .unwrap()
+                .unwrap()
+                .pinned
+        );
+        assert!(
+            !set_pinned(&pool, &conv.id, &user_id, false)
+                .await
+                .unwrap()
+                .unwrap()
+                .pinned
+        );
+        assert!(
+            set_pinned(&pool, &conv.id, "someone-else", true)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_and_clear_last_container_error() {
+        let (pool, user_id) = setup().await;
+        let conv = create_conversation(
+            &pool, &user_id, "Chat", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        assert!(conv.last_container_error.is_none());
+
+        set_last_container_error(&pool, &conv.id, Some("model config invalid"))
+            .await
+            .unwrap();
+        let fetched = get_conversation(&pool, &conv.id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            fetched.last_container_error.as_deref(),
+            Some("model config invalid")
+        );
+        assert_eq!(fetched.updated_at, conv.updated_at);
+
+        set_last_container_error(&pool, &conv.id, None)
+            .await
+            .unwrap();
+        let fetched = get_conversation(&pool, &conv.id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(fetched.last_container_error.is_none());
+    }
 }
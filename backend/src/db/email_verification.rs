@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct EmailVerificationToken {
+    pub token_hash: String,
+    pub user_id: String,
+    pub expires_at: String,
+    pub used: bool,
+    pub created_at: String,
+}
+
+pub async fn create_verification_token_in_tx(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: &str,
+    token_hash: &str,
+    expires_at: &str,
+) -> Result<EmailVerificationToken, sqlx::Error> {
+    sqlx::query_as::<_, EmailVerificationToken>(
+        "INSERT INTO email_verification_tokens (token_hash, user_id, expires_at) \
+         VALUES (?, ?, ?) \
+         RETURNING token_hash, user_id, expires_at, used, created_at",
+    )
+    .bind(token_hash)
+    .bind(user_id)
+    .bind(expires_at)
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Mark an unused token as used and return it. Returns `None` if the token
+/// does not exist or was already consumed.
+pub async fn consume_verification_token(
+    pool: &SqlitePool,
+    token_hash: &str,
+) -> Result<Option<EmailVerificationToken>, sqlx::Error> {
+    sqlx::query_as::<_, EmailVerificationToken>(
+        "UPDATE email_verification_tokens SET used = 1 \
+         WHERE token_hash = ? AND used = 0 \
+         RETURNING token_hash, user_id, expires_at, used, created_at",
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::db::users::create_user;
+
+    #[tokio::test]
+    async fn test_consume_verification_token_once() {
+        let pool = init_db("sqlite::memory:").await;
+        let user = create_user(&pool, "verify", "verify@example.com", "hash")
+            .await
+            .unwrap();
+
+        let mut tx = pool.begin().await.unwrap();
+        create_verification_token_in_tx(&mut tx, &user.id, "hash-1", "2099-01-01T00:00:00+00:00")
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let consumed = consume_verification_token(&pool, "hash-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(consumed.user_id, user.id);
+        assert!(consumed.used);
+
+        assert!(
+            consume_verification_token(&pool, "hash-1")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_consume_unknown_verification_token() {
+        let pool = init_db("sqlite::memory:").await;
+        assert!(
+            consume_verification_token(&pool, "missing")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// When the `(ip, username)` pair is locked out, return the time the lock
+/// ends. Expired locks return `None`.
+pub async fn locked_until(
+    pool: &SqlitePool,
+    ip: &str,
+    username: &str,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let locked_until: Option<Option<String>> =
+        sqlx::query_scalar("SELECT locked_until FROM login_attempts WHERE ip = ? AND username = ?")
+            .bind(ip)
+            .bind(username)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(locked_until
+        .flatten()
+        .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+        .map(|t| t.with_timezone(&Utc))
+        .filter(|t| *t > Utc::now()))
+}
+
+/// Count a failed login. Once `max_attempts` consecutive failures are
+/// reached the pair is locked for `lockout_secs` and the counter starts
+/// over. Returns the lock end time if this failure triggered a lockout.
+pub async fn record_failure(
+    pool: &SqlitePool,
+    ip: &str,
+    username: &str,
+    max_attempts: u32,
+    lockout_secs: u64,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let now = Utc::now();
+    let count: i64 = sqlx::query_scalar(
+        "INSERT INTO login_attempts (ip, username, attempt_count, last_attempt_at) \
+         VALUES (?, ?, 1, ?) \
+         ON CONFLICT(ip, username) DO UPDATE SET \
+         attempt_count = attempt_count + 1, last_attempt_at = excluded.last_attempt_at \
+         RETURNING attempt_count",
+    )
+    .bind(ip)
+    .bind(username)
+    .bind(now.to_rfc3339())
+    .fetch_one(pool)
+    .await?;
+
+    if max_attempts == 0 || count < i64::from(max_attempts) {
+        return Ok(None);
+    }
+
+    let until = now + chrono::Duration::seconds(lockout_secs as i64);
+    sqlx::query(
+        "UPDATE login_attempts SET attempt_count = 0, locked_until = ? \
+         WHERE ip = ? AND username = ?",
+    )
+    .bind(until.to_rfc3339())
+    .bind(ip)
+    .bind(username)
+    .execute(pool)
+    .await?;
+    Ok(Some(until))
+}
+
+/// Forget failed attempts after a successful login.
+pub async fn reset(pool: &SqlitePool, ip: &str, username: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM login_attempts WHERE ip = ? AND username = ?")
+        .bind(ip)
+        .bind(username)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Delete rows whose last failure is older than `before` and that are not
+/// currently locked. Returns the number of rows removed.
+pub async fn delete_stale(pool: &SqlitePool, before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM login_attempts WHERE last_attempt_at < ? \
+         AND (locked_until IS NULL OR locked_until < ?)",
+    )
+    .bind(before.to_rfc3339())
+    .bind(Utc::now().to_rfc3339())
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Periodically drop stale rows so the table doesn't grow with every IP and
+/// username ever tried.
+pub fn spawn_stale_cleanup(pool: SqlitePool, interval_secs: u64, max_age_secs: i64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            let before = Utc::now() - chrono::Duration::seconds(max_age_secs);
+            match delete_stale(&pool, before).await {
+                Ok(n) if n > 0 => tracing::info!("Removed {n} stale login attempt record(s)"),
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to clean up login attempts: {e}"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+
+    #[tokio::test]
+    async fn test_lockout_after_max_attempts() {
+        let pool = init_db("sqlite::memory:").await;
+        for _ in 0..2 {
+            assert!(
+                record_failure(&pool, "1.2.3.4", "alice", 3, 60)
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        }
+        assert!(
+            locked_until(&pool, "1.2.3.4", "alice")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let until = record_failure(&pool, "1.2.3.4", "alice", 3, 60)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(until > Utc::now());
+        assert!(
+            locked_until(&pool, "1.2.3.4", "alice")
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        // Other IPs and usernames are tracked separately.
+        assert!(
+            locked_until(&pool, "5.6.7.8", "alice")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            locked_until(&pool, "1.2.3.4", "bob")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_lock_is_ignored() {
+        let pool = init_db("sqlite::memory:").await;
+        record_failure(&pool, "ip", "alice", 1, 0).await.unwrap();
+        assert!(locked_until(&pool, "ip", "alice").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delete_stale_keeps_recent_and_locked_rows() {
+        let pool = init_db("sqlite::memory:").await;
+        record_failure(&pool, "ip", "stale", 5, 60).await.unwrap();
+        record_failure(&pool, "ip", "locked", 1, 60).await.unwrap();
+        let later = Utc::now() + chrono::Duration::seconds(1);
+
+        assert_eq!(delete_stale(&pool, later).await.unwrap(), 1);
+        assert!(locked_until(&pool, "ip", "locked").await.unwrap().is_some());
+        assert_eq!(
+            delete_stale(&pool, Utc::now() - chrono::Duration::hours(1))
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reset_clears_counter() {
+        let pool = init_db("sqlite::memory:").await;
+        record_failure(&pool, "ip", "alice", 2, 60).await.unwrap();
+        reset(&pool, "ip", "alice").await.unwrap();
+        assert!(
+            record_failure(&pool, "ip", "alice", 2, 60)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_zero_max_attempts_never_locks() {
+        let pool = init_db("sqlite::memory:").await;
+        for _ in 0..5 {
+            assert!(
+                record_failure(&pool, "ip", "alice", 0, 60)
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        }
+    }
+}
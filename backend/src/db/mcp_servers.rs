@@ -17,6 +17,17 @@ pub struct McpServer {
     pub created_at: String,
 }
 
+/// A user's override of a global MCP server. `None` fields inherit the
+/// server's own setting.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserMcpServerOverride {
+    pub user_id: String,
+    pub mcp_server_id: String,
+    pub is_enabled: Option<bool>,
+    pub read_only_overrides: Option<String>,
+    pub updated_at: String,
+}
+
 #[allow(clippy::too_many_arguments)]
 #[allow(dead_code)]
 pub async fn create_mcp_server(
@@ -91,6 +102,7 @@ pub async fn list_mcp_servers(pool: &SqlitePool) -> Result<Vec<McpServer>, sqlx:
     .await
 }
 
+#[allow(dead_code)]
 pub async fn list_enabled_mcp_servers(pool: &SqlitePool) -> Result<Vec<McpServer>, sqlx::Error> {
     sqlx::query_as::<_, McpServer>(
         "SELECT id, name, description, transport, \
@@ -102,6 +114,28 @@ pub async fn list_enabled_mcp_servers(pool: &SqlitePool) -> Result<Vec<McpServer
     .await
 }
 
+/// Servers available to `user_id`: globally enabled ones plus any the user
+/// has re-enabled, minus any the user has disabled. Each row carries the
+/// user's effective `is_enabled` and `read_only_overrides`.
+pub async fn list_enabled_mcp_servers_for_user(
+    pool: &SqlitePool,
+    user_id: &str,
+) -> Result<Vec<McpServer>, sqlx::Error> {
+    sqlx::query_as::<_, McpServer>(
+        "SELECT s.id, s.name, s.description, s.transport, s.command, s.args, s.url, s.env_vars, \
+         COALESCE(o.read_only_overrides, s.read_only_overrides) AS read_only_overrides, \
+         COALESCE(o.is_enabled, s.is_enabled) AS is_enabled, s.created_at \
+         FROM mcp_servers s \
+         LEFT JOIN user_mcp_server_overrides o \
+         ON o.mcp_server_id = s.id AND o.user_id = ? \
+         WHERE COALESCE(o.is_enabled, s.is_enabled) = 1 \
+         ORDER BY s.name ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn get_mcp_server(pool: &SqlitePool, id: &str) -> Result<Option<McpServer>, sqlx::Error> {
     sqlx::query_as::<_, McpServer>(
         "SELECT id, name, description, transport, \
@@ -179,6 +213,32 @@ pub async fn update_mcp_server_with_overrides(
     .await
 }
 
+/// Copy an existing server under a new id and `new_name`. The copy starts
+/// disabled so it can be reviewed before use. Returns `None` if the source
+/// does not exist.
+pub async fn clone_mcp_server(
+    pool: &SqlitePool,
+    source_id: &str,
+    new_name: &str,
+) -> Result<Option<McpServer>, sqlx::Error> {
+    let id = uuid::Uuid::new_v4().to_string();
+
+    sqlx::query_as::<_, McpServer>(
+        "INSERT INTO mcp_servers (id, name, description, transport, \
+         command, args, url, env_vars, read_only_overrides, is_enabled) \
+         SELECT ?, ?, description, transport, \
+         command, args, url, env_vars, read_only_overrides, 0 \
+         FROM mcp_servers WHERE id = ? \
+         RETURNING id, name, description, transport, \
+         command, args, url, env_vars, read_only_overrides, is_enabled, created_at",
+    )
+    .bind(&id)
+    .bind(new_name)
+    .bind(source_id)
+    .fetch_optional(pool)
+    .await
+}
+
 pub async fn delete_mcp_server(pool: &SqlitePool, id: &str) -> Result<bool, sqlx::Error> {
     let result = sqlx::query("DELETE FROM mcp_servers WHERE id = ?")
         .bind(id)
@@ -217,16 +277,22 @@ pub async fn set_conversation_mcp_servers(
     Ok(())
 }
 
+/// Servers attached to a conversation, with the conversation owner's
+/// overrides applied to `is_enabled` and `read_only_overrides`.
 pub async fn get_conversation_mcp_servers(
     pool: &SqlitePool,
     conversation_id: &str,
 ) -> Result<Vec<McpServer>, sqlx::Error> {
     sqlx::query_as::<_, McpServer>(
-        "SELECT s.id, s.name, s.description, s.transport, \
-         s.command, s.args, s.url, s.env_vars, s.read_only_overrides, s.is_enabled, s.created_at \
+        "SELECT s.id, s.name, s.description, s.transport, s.command, s.args, s.url, s.env_vars, \
+         COALESCE(o.read_only_overrides, s.read_only_overrides) AS read_only_overrides, \
+         COALESCE(o.is_enabled, s.is_enabled) AS is_enabled, s.created_at \
          FROM mcp_servers s \
          INNER JOIN conversation_mcp_servers cms \
          ON s.id = cms.mcp_server_id \
+         INNER JOIN conversations c ON c.id = cms.conversation_id \
+         LEFT JOIN user_mcp_server_overrides o \
+         ON o.mcp_server_id = s.id AND o.user_id = c.user_id \
          WHERE cms.conversation_id = ? \
          ORDER BY s.name ASC",
     )
@@ -235,6 +301,46 @@ pub async fn get_conversation_mcp_servers(
     .await
 }
 
+pub async fn get_user_mcp_server_override(
+    pool: &SqlitePool,
+    user_id: &str,
+    mcp_server_id: &str,
+) -> Result<Option<UserMcpServerOverride>, sqlx::Error> {
+    sqlx::query_as::<_, UserMcpServerOverride>(
+        "SELECT user_id, mcp_server_id, is_enabled, read_only_overrides, updated_at \
+         FROM user_mcp_server_overrides WHERE user_id = ? AND mcp_server_id = ?",
+    )
+    .bind(user_id)
+    .bind(mcp_server_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Create or replace the user's override for a server.
+pub async fn upsert_user_mcp_server_override(
+    pool: &SqlitePool,
+    user_id: &str,
+    mcp_server_id: &str,
+    is_enabled: Option<bool>,
+    read_only_overrides: Option<&str>,
+) -> Result<UserMcpServerOverride, sqlx::Error> {
+    sqlx::query_as::<_, UserMcpServerOverride>(
+        "INSERT INTO user_mcp_server_overrides \
+         (user_id, mcp_server_id, is_enabled, read_only_overrides) VALUES (?, ?, ?, ?) \
+         ON CONFLICT (user_id, mcp_server_id) DO UPDATE SET \
+         is_enabled = excluded.is_enabled, \
+         read_only_overrides = excluded.read_only_overrides, \
+         updated_at = datetime('now') \
+         RETURNING user_id, mcp_server_id, is_enabled, read_only_overrides, updated_at",
+    )
+    .bind(user_id)
+    .bind(mcp_server_id)
+    .bind(is_enabled)
+    .bind(read_only_overrides)
+    .fetch_one(pool)
+    .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -452,4 +558,138 @@ mod tests {
         let servers = get_conversation_mcp_servers(&pool, &conv.id).await.unwrap();
         assert_eq!(servers.len(), 0);
     }
+
+    #[tokio::test]
+    async fn test_clone_mcp_server_copies_config_disabled() {
+        let pool = setup().await;
+        let source = create_mcp_server_with_overrides(
+            &pool,
+            "github-org-a",
+            Some("GitHub tools"),
+            "stdio",
+            Some("npx"),
+            Some("[\"@mcp/github\"]"),
+            None,
+            Some("{\"GITHUB_ORG\":\"a\"}"),
+            Some("{\"list_repos\":true}"),
+            true,
+        )
+        .await
+        .unwrap();
+
+        let clone = clone_mcp_server(&pool, &source.id, "github-org-b")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(clone.id, source.id);
+        assert_eq!(clone.name, "github-org-b");
+        assert!(!clone.is_enabled);
+        assert_eq!(clone.description, source.description);
+        assert_eq!(clone.transport, source.transport);
+        assert_eq!(clone.command, source.command);
+        assert_eq!(clone.args, source.args);
+        assert_eq!(clone.url, source.url);
+        assert_eq!(clone.env_vars, source.env_vars);
+        assert_eq!(clone.read_only_overrides, source.read_only_overrides);
+
+        let source_after = get_mcp_server(&pool, &source.id).await.unwrap().unwrap();
+        assert!(source_after.is_enabled);
+    }
+
+    #[tokio::test]
+    async fn test_clone_mcp_server_missing_source() {
+        let pool = setup().await;
+        let clone = clone_mcp_server(&pool, "nonexistent", "copy")
+            .await
+            .unwrap();
+        assert!(clone.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_user_override_merges_into_conversation_servers() {
+        let pool = setup().await;
+        let user = create_user(&pool, "override_user", "override@example.com", "hash")
+            .await
+            .unwrap();
+        let other = create_user(&pool, "other_user", "other@example.com", "hash")
+            .await
+            .unwrap();
+        let conv = create_conversation(
+            &pool, &user.id, "Test", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let server = create_mcp_server_with_overrides(
+            &pool,
+            "fs",
+            None,
+            "stdio",
+            Some("npx"),
+            None,
+            None,
+            None,
+            Some(r#"{"read_file":true}"#),
+            false,
+        )
+        .await
+        .unwrap();
+        set_conversation_mcp_servers(&pool, &conv.id, std::slice::from_ref(&server.id))
+            .await
+            .unwrap();
+
+        let servers = get_conversation_mcp_servers(&pool, &conv.id).await.unwrap();
+        assert!(!servers[0].is_enabled);
+        assert!(
+            list_enabled_mcp_servers_for_user(&pool, &user.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // Only is_enabled is overridden; read-only flags are inherited.
+        upsert_user_mcp_server_override(&pool, &user.id, &server.id, Some(true), None)
+            .await
+            .unwrap();
+        let servers = get_conversation_mcp_servers(&pool, &conv.id).await.unwrap();
+        assert!(servers[0].is_enabled);
+        assert_eq!(
+            servers[0].read_only_overrides.as_deref(),
+            Some(r#"{"read_file":true}"#)
+        );
+        assert_eq!(
+            list_enabled_mcp_servers_for_user(&pool, &user.id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(
+            list_enabled_mcp_servers_for_user(&pool, &other.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let saved = upsert_user_mcp_server_override(
+            &pool,
+            &user.id,
+            &server.id,
+            None,
+            Some(r#"{"read_file":false}"#),
+        )
+        .await
+        .unwrap();
+        assert_eq!(saved.is_enabled, None);
+        let servers = get_conversation_mcp_servers(&pool, &conv.id).await.unwrap();
+        assert!(!servers[0].is_enabled);
+        assert_eq!(
+            servers[0].read_only_overrides.as_deref(),
+            Some(r#"{"read_file":false}"#)
+        );
+        let fetched = get_user_mcp_server_override(&pool, &user.id, &server.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(fetched.read_only_overrides, saved.read_only_overrides);
+    }
 }
@@ -1,6 +1,12 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
 use sqlx::prelude::FromRow;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+
+/// Maximum number of messages accepted by [`create_message_batch`].
+pub const MAX_MESSAGE_BATCH: usize = 500;
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Message {
@@ -24,7 +30,42 @@ pub async fn create_message(
     token_count: Option<i64>,
 ) -> Result<Message, sqlx::Error> {
     let id = uuid::Uuid::new_v4().to_string();
+    insert_message(
+        pool,
+        &id,
+        conversation_id,
+        role,
+        content,
+        tool_calls,
+        tool_call_id,
+        token_count,
+    )
+    .await
+}
+
+/// Like [`create_message`], but with a caller-chosen ID. Fails with a unique
+/// violation if `id` is already taken.
+pub async fn create_message_with_id(
+    pool: &SqlitePool,
+    id: &str,
+    conversation_id: &str,
+    role: &str,
+    content: &str,
+) -> Result<Message, sqlx::Error> {
+    insert_message(pool, id, conversation_id, role, content, None, None, None).await
+}
 
+#[allow(clippy::too_many_arguments)]
+async fn insert_message(
+    pool: &SqlitePool,
+    id: &str,
+    conversation_id: &str,
+    role: &str,
+    content: &str,
+    tool_calls: Option<&str>,
+    tool_call_id: Option<&str>,
+    token_count: Option<i64>,
+) -> Result<Message, sqlx::Error> {
     sqlx::query_as::<_, Message>(
         "INSERT INTO messages (id, conversation_id, role, content, \
          tool_calls, tool_call_id, token_count) \
@@ -32,7 +73,7 @@ pub async fn create_message(
          RETURNING id, conversation_id, role, content, \
          tool_calls, tool_call_id, token_count, created_at",
     )
-    .bind(&id)
+    .bind(id)
     .bind(conversation_id)
     .bind(role)
     .bind(content)
@@ -43,6 +84,68 @@ pub async fn create_message(
     .await
 }
 
+/// A message to insert via [`create_message_batch`].
+#[derive(Debug, Clone)]
+pub struct NewMessage<'a> {
+    pub role: &'a str,
+    pub content: &'a str,
+    pub tool_calls: Option<&'a str>,
+    pub tool_call_id: Option<&'a str>,
+    pub token_count: Option<i64>,
+}
+
+/// Insert `messages` into a conversation in one transaction, preserving their
+/// order. Callers must keep batches within [`MAX_MESSAGE_BATCH`].
+pub async fn create_message_batch(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    messages: &[NewMessage<'_>],
+) -> Result<Vec<Message>, sqlx::Error> {
+    if messages.is_empty() {
+        return Ok(Vec::new());
+    }
+    let ids: Vec<String> = messages
+        .iter()
+        .map(|_| uuid::Uuid::new_v4().to_string())
+        .collect();
+
+    let mut tx = pool.begin().await?;
+    let mut insert = QueryBuilder::<Sqlite>::new(
+        "INSERT INTO messages (id, conversation_id, role, content, \
+         tool_calls, tool_call_id, token_count) ",
+    );
+    insert.push_values(ids.iter().zip(messages), |mut row, (id, m)| {
+        row.push_bind(id)
+            .push_bind(conversation_id)
+            .push_bind(m.role)
+            .push_bind(m.content)
+            .push_bind(m.tool_calls)
+            .push_bind(m.tool_call_id)
+            .push_bind(m.token_count);
+    });
+    insert.build().execute(&mut *tx).await?;
+
+    // RETURNING row order is unspecified in SQLite, so read back by position.
+    let mut select = QueryBuilder::<Sqlite>::new(
+        "SELECT id, conversation_id, role, content, \
+         tool_calls, tool_call_id, token_count, created_at \
+         FROM messages WHERE id IN (",
+    );
+    {
+        let mut separated = select.separated(", ");
+        for id in &ids {
+            separated.push_bind(id);
+        }
+    }
+    select.push(") ORDER BY position ASC");
+    let created = select
+        .build_query_as::<Message>()
+        .fetch_all(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(created)
+}
+
 pub async fn list_messages(
     pool: &SqlitePool,
     conversation_id: &str,
@@ -53,8 +156,8 @@ pub async fn list_messages(
         "SELECT id, conversation_id, role, content, \
          tool_calls, tool_call_id, token_count, created_at \
          FROM messages \
-         WHERE conversation_id = ? \
-         ORDER BY rowid ASC \
+         WHERE conversation_id = ? AND deleted_at IS NULL \
+         ORDER BY position ASC \
          LIMIT ? OFFSET ?",
     )
     .bind(conversation_id)
@@ -64,6 +167,32 @@ pub async fn list_messages(
     .await
 }
 
+/// Keyset-paginated variant of [`list_messages`]: up to `limit` messages
+/// that follow `after_message_id`. Returns nothing if that message is not
+/// in the conversation.
+pub async fn list_messages_after(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    after_message_id: &str,
+    limit: i64,
+) -> Result<Vec<Message>, sqlx::Error> {
+    sqlx::query_as::<_, Message>(
+        "SELECT id, conversation_id, role, content, \
+         tool_calls, tool_call_id, token_count, created_at \
+         FROM messages \
+         WHERE conversation_id = ? AND deleted_at IS NULL \
+         AND position > (SELECT position FROM messages WHERE id = ? AND conversation_id = ?) \
+         ORDER BY position ASC \
+         LIMIT ?",
+    )
+    .bind(conversation_id)
+    .bind(after_message_id)
+    .bind(conversation_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
 #[derive(Debug, Clone, FromRow)]
 struct CountRow {
     count: i64,
@@ -73,7 +202,7 @@ pub async fn get_message(pool: &SqlitePool, id: &str) -> Result<Option<Message>,
     sqlx::query_as::<_, Message>(
         "SELECT id, conversation_id, role, content, \
          tool_calls, tool_call_id, token_count, created_at \
-         FROM messages WHERE id = ?",
+         FROM messages WHERE id = ? AND deleted_at IS NULL",
     )
     .bind(id)
     .fetch_optional(pool)
@@ -93,14 +222,18 @@ pub async fn update_message_content(
     Ok(result.rows_affected() > 0)
 }
 
+/// Soft-delete every message after `after_message_id`. The rows stay until
+/// [`hard_purge_deleted_messages`] removes them and can be restored with
+/// [`undelete_message`].
 pub async fn delete_messages_after(
     pool: &SqlitePool,
     conversation_id: &str,
     after_message_id: &str,
 ) -> Result<u64, sqlx::Error> {
     let result = sqlx::query(
-        "DELETE FROM messages WHERE conversation_id = ? \
-         AND rowid > (SELECT rowid FROM messages WHERE id = ?)",
+        "UPDATE messages SET deleted_at = datetime('now') \
+         WHERE conversation_id = ? AND deleted_at IS NULL \
+         AND position > (SELECT position FROM messages WHERE id = ?)",
     )
     .bind(conversation_id)
     .bind(after_message_id)
@@ -109,9 +242,244 @@ pub async fn delete_messages_after(
     Ok(result.rows_affected())
 }
 
+/// Restore a soft-deleted message of `conversation_id`, including its
+/// `messages_v2` row. Returns `false` if there is no such deleted message.
+pub async fn undelete_message(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    message_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
+        "UPDATE messages SET deleted_at = NULL \
+         WHERE id = ? AND conversation_id = ? AND deleted_at IS NOT NULL",
+    )
+    .bind(message_id)
+    .bind(conversation_id)
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    sqlx::query("UPDATE messages_v2 SET deleted_at = NULL WHERE id = ?")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Permanently remove messages (and their `messages_v2` rows) that were
+/// soft-deleted before `before`. Returns the number of messages removed.
+pub async fn hard_purge_deleted_messages(
+    pool: &SqlitePool,
+    before: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    // `deleted_at` is written by SQLite's datetime('now'), so compare in that format.
+    let cutoff = before.format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM messages_v2 WHERE deleted_at IS NOT NULL AND deleted_at < ?")
+        .bind(&cutoff)
+        .execute(&mut *tx)
+        .await?;
+    let result =
+        sqlx::query("DELETE FROM messages WHERE deleted_at IS NOT NULL AND deleted_at < ?")
+            .bind(&cutoff)
+            .execute(&mut *tx)
+            .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected())
+}
+
+/// Spawn a background task that purges messages soft-deleted more than
+/// `retention_days` ago, once per `interval_secs`.
+pub fn spawn_deleted_message_purge(pool: SqlitePool, interval_secs: u64, retention_days: i64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        // The first tick completes immediately; skip it so the purge doesn't run at startup.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let before = Utc::now() - chrono::Duration::days(retention_days);
+            match hard_purge_deleted_messages(&pool, before).await {
+                Ok(n) if n > 0 => tracing::info!("Purged {n} deleted message(s)"),
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to purge deleted messages: {e}"),
+            }
+        }
+    });
+}
+
+/// Insert a message that sorts before every other message of the
+/// conversation. The position is read and used in one transaction so a
+/// concurrent insert cannot take it.
+pub async fn create_message_at_start(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    role: &str,
+    content: &str,
+) -> Result<Message, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let position = sqlx::query_scalar::<_, i64>(
+        "SELECT COALESCE(MIN(position), 1) - 1 FROM messages WHERE conversation_id = ?",
+    )
+    .bind(conversation_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    let message = sqlx::query_as::<_, Message>(
+        "INSERT INTO messages (id, conversation_id, role, content, position) \
+         VALUES (?, ?, ?, ?, ?) \
+         RETURNING id, conversation_id, role, content, \
+         tool_calls, tool_call_id, token_count, created_at",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(conversation_id)
+    .bind(role)
+    .bind(content)
+    .bind(position)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(message)
+}
+
+/// Fetch the anchor message plus up to `before` messages preceding it and up
+/// to `after` messages following it, in conversation order. Returns an empty
+/// list if the anchor does not belong to the conversation.
+pub async fn get_messages_around(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    anchor_id: &str,
+    before: usize,
+    after: usize,
+) -> Result<Vec<Message>, sqlx::Error> {
+    sqlx::query_as::<_, Message>(
+        "WITH anchor AS ( \
+             SELECT position AS pos FROM messages \
+             WHERE id = ? AND conversation_id = ? AND deleted_at IS NULL \
+         ) \
+         SELECT id, conversation_id, role, content, \
+         tool_calls, tool_call_id, token_count, created_at \
+         FROM ( \
+             SELECT * FROM ( \
+                 SELECT position AS pos, id, conversation_id, role, content, \
+                 tool_calls, tool_call_id, token_count, created_at \
+                 FROM messages \
+                 WHERE conversation_id = ? AND deleted_at IS NULL \
+                 AND position <= (SELECT pos FROM anchor) \
+                 ORDER BY position DESC \
+                 LIMIT ? \
+             ) \
+             UNION \
+             SELECT * FROM ( \
+                 SELECT position AS pos, id, conversation_id, role, content, \
+                 tool_calls, tool_call_id, token_count, created_at \
+                 FROM messages \
+                 WHERE conversation_id = ? AND deleted_at IS NULL \
+                 AND position > (SELECT pos FROM anchor) \
+                 ORDER BY position ASC \
+                 LIMIT ? \
+             ) \
+         ) \
+         ORDER BY pos ASC",
+    )
+    .bind(anchor_id)
+    .bind(conversation_id)
+    .bind(conversation_id)
+    .bind(before as i64 + 1)
+    .bind(conversation_id)
+    .bind(after as i64)
+    .fetch_all(pool)
+    .await
+}
+
+/// Last message in a conversation owned by `user_id`.
+pub async fn get_last_message(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    user_id: &str,
+) -> Result<Option<Message>, sqlx::Error> {
+    sqlx::query_as::<_, Message>(
+        "SELECT m.id, m.conversation_id, m.role, m.content, \
+         m.tool_calls, m.tool_call_id, m.token_count, m.created_at \
+         FROM messages m \
+         JOIN conversations c ON c.id = m.conversation_id \
+         WHERE m.conversation_id = ? AND c.user_id = ? AND m.deleted_at IS NULL \
+         ORDER BY m.position DESC \
+         LIMIT 1",
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Turn free text into an FTS5 query matching messages that contain every
+/// term, so user input never hits FTS5 query syntax.
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Full-text search over the messages of a conversation owned by `user_id`,
+/// best matches first.
+pub async fn search_messages(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    user_id: &str,
+    query: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Message>, sqlx::Error> {
+    sqlx::query_as::<_, Message>(
+        "SELECT m.id, m.conversation_id, m.role, m.content, \
+         m.tool_calls, m.tool_call_id, m.token_count, m.created_at \
+         FROM messages_fts f \
+         JOIN messages m ON m.id = f.message_id \
+         JOIN conversations c ON c.id = m.conversation_id \
+         WHERE messages_fts MATCH ? AND m.conversation_id = ? AND c.user_id = ? \
+         AND m.deleted_at IS NULL \
+         ORDER BY f.rank, m.position \
+         LIMIT ? OFFSET ?",
+    )
+    .bind(fts_query(query))
+    .bind(conversation_id)
+    .bind(user_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+/// Number of results [`search_messages`] would return without paging.
+pub async fn count_search_messages(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    user_id: &str,
+    query: &str,
+) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query_as::<_, CountRow>(
+        "SELECT COUNT(*) as count \
+         FROM messages_fts f \
+         JOIN messages m ON m.id = f.message_id \
+         JOIN conversations c ON c.id = m.conversation_id \
+         WHERE messages_fts MATCH ? AND m.conversation_id = ? AND c.user_id = ? \
+         AND m.deleted_at IS NULL",
+    )
+    .bind(fts_query(query))
+    .bind(conversation_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.count)
+}
+
 pub async fn count_messages(pool: &SqlitePool, conversation_id: &str) -> Result<i64, sqlx::Error> {
     let row = sqlx::query_as::<_, CountRow>(
-        "SELECT COUNT(*) as count FROM messages WHERE conversation_id = ?",
+        "SELECT COUNT(*) as count FROM messages WHERE conversation_id = ? AND deleted_at IS NULL",
     )
     .bind(conversation_id)
     .fetch_one(pool)
@@ -120,6 +488,232 @@ pub async fn count_messages(pool: &SqlitePool, conversation_id: &str) -> Result<
     Ok(row.count)
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MessageReaction {
+    pub id: String,
+    pub message_id: String,
+    pub user_id: String,
+    pub emoji: String,
+    pub created_at: String,
+}
+
+/// React to a message. Returns `false` if the user already reacted with
+/// this emoji.
+pub async fn add_reaction(
+    pool: &SqlitePool,
+    message_id: &str,
+    user_id: &str,
+    emoji: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT OR IGNORE INTO message_reactions (id, message_id, user_id, emoji) \
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(message_id)
+    .bind(user_id)
+    .bind(emoji)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Remove a user's reaction. Returns `false` if there was none.
+pub async fn remove_reaction(
+    pool: &SqlitePool,
+    message_id: &str,
+    user_id: &str,
+    emoji: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM message_reactions WHERE message_id = ? AND user_id = ? AND emoji = ?",
+    )
+    .bind(message_id)
+    .bind(user_id)
+    .bind(emoji)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn list_reactions_for_message(
+    pool: &SqlitePool,
+    message_id: &str,
+) -> Result<Vec<MessageReaction>, sqlx::Error> {
+    sqlx::query_as::<_, MessageReaction>(
+        "SELECT id, message_id, user_id, emoji, created_at \
+         FROM message_reactions WHERE message_id = ? \
+         ORDER BY created_at ASC, rowid ASC",
+    )
+    .bind(message_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Reaction counts per emoji for several messages, keyed by message ID.
+/// Messages without reactions are absent from the map.
+pub async fn count_reactions_for_messages(
+    pool: &SqlitePool,
+    message_ids: &[String],
+) -> Result<HashMap<String, BTreeMap<String, i64>>, sqlx::Error> {
+    if message_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT message_id, emoji, COUNT(*) FROM message_reactions WHERE message_id IN (",
+    );
+    {
+        let mut separated = query.separated(", ");
+        for id in message_ids {
+            separated.push_bind(id);
+        }
+    }
+    query.push(") GROUP BY message_id, emoji");
+
+    let rows = query
+        .build_query_as::<(String, String, i64)>()
+        .fetch_all(pool)
+        .await?;
+    let mut counts: HashMap<String, BTreeMap<String, i64>> = HashMap::new();
+    for (message_id, emoji, count) in rows {
+        counts.entry(message_id).or_default().insert(emoji, count);
+    }
+    Ok(counts)
+}
+
+/// Token totals over the live messages of a conversation that recorded a
+/// token count.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TokenUsageSummary {
+    pub total_tokens: i64,
+    pub message_count_with_usage: i64,
+    pub average_tokens_per_message: f64,
+}
+
+pub async fn sum_token_usage(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<TokenUsageSummary, sqlx::Error> {
+    sqlx::query_as::<_, TokenUsageSummary>(
+        "SELECT COALESCE(SUM(token_count), 0) AS total_tokens, \
+         COUNT(token_count) AS message_count_with_usage, \
+         COALESCE(AVG(token_count), 0.0) AS average_tokens_per_message \
+         FROM messages WHERE conversation_id = ? AND deleted_at IS NULL",
+    )
+    .bind(conversation_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Bucket sizes accepted by [`token_usage_over_time`].
+pub const TOKEN_USAGE_BUCKETS: &[&str] = &["hour", "day", "week", "month"];
+
+/// Tokens used in one time bucket; `bucket` is the formatted bucket start
+/// (e.g. `2026-10-15` for days).
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TokenBucket {
+    pub bucket: String,
+    pub total_tokens: i64,
+    pub message_count: i64,
+}
+
+/// Token totals per `bucket` (one of [`TOKEN_USAGE_BUCKETS`]), oldest first.
+/// Only buckets containing messages with a token count are returned; an
+/// unknown bucket size is treated as `day`.
+pub async fn token_usage_over_time(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    bucket: &str,
+) -> Result<Vec<TokenBucket>, sqlx::Error> {
+    let format = match bucket {
+        "hour" => "%Y-%m-%dT%H:00",
+        "week" => "%Y-W%W",
+        "month" => "%Y-%m",
+        _ => "%Y-%m-%d",
+    };
+    sqlx::query_as::<_, TokenBucket>(
+        "SELECT strftime(?, created_at) AS bucket, \
+         SUM(token_count) AS total_tokens, COUNT(*) AS message_count \
+         FROM messages \
+         WHERE conversation_id = ? AND deleted_at IS NULL AND token_count IS NOT NULL \
+         GROUP BY bucket ORDER BY bucket ASC",
+    )
+    .bind(format)
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// A bookmarked message together with the conversation it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BookmarkedMessage {
+    pub message_id: String,
+    pub conversation_id: String,
+    pub conversation_title: String,
+    pub role: String,
+    pub content: String,
+    pub message_created_at: String,
+    pub bookmarked_at: String,
+}
+
+/// Bookmark a message. Returns `false` if it was already bookmarked.
+pub async fn bookmark_message(
+    pool: &SqlitePool,
+    user_id: &str,
+    message_id: &str,
+    conversation_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT OR IGNORE INTO message_bookmarks (user_id, message_id, conversation_id) \
+         VALUES (?, ?, ?)",
+    )
+    .bind(user_id)
+    .bind(message_id)
+    .bind(conversation_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Remove a bookmark. Returns `false` if the message wasn't bookmarked.
+pub async fn unbookmark_message(
+    pool: &SqlitePool,
+    user_id: &str,
+    message_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM message_bookmarks WHERE user_id = ? AND message_id = ?")
+        .bind(user_id)
+        .bind(message_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// A user's bookmarks, most recent first. Bookmarks on soft-deleted
+/// messages are skipped.
+pub async fn list_bookmarks_for_user(
+    pool: &SqlitePool,
+    user_id: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<BookmarkedMessage>, sqlx::Error> {
+    sqlx::query_as::<_, BookmarkedMessage>(
+        "SELECT m.id AS message_id, c.id AS conversation_id, c.title AS conversation_title, \
+         m.role, m.content, m.created_at AS message_created_at, b.created_at AS bookmarked_at \
+         FROM message_bookmarks b \
+         JOIN messages m ON m.id = b.message_id \
+         JOIN conversations c ON c.id = b.conversation_id AND c.user_id = b.user_id \
+         WHERE b.user_id = ? AND m.deleted_at IS NULL \
+         ORDER BY b.created_at DESC, b.rowid DESC \
+         LIMIT ? OFFSET ?",
+    )
+    .bind(user_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,6 +743,164 @@ mod tests {
         (pool, conv.id)
     }
 
+    #[tokio::test]
+    async fn test_reactions_dedupe_and_count() {
+        let (pool, conv_id) = setup().await;
+        let msg = create_message(&pool, &conv_id, "assistant", "Hi", None, None, None)
+            .await
+            .unwrap();
+        let alice = create_user(&pool, "alice", "alice@example.com", "hash")
+            .await
+            .unwrap();
+        let bob = create_user(&pool, "bob", "bob@example.com", "hash")
+            .await
+            .unwrap();
+
+        assert!(add_reaction(&pool, &msg.id, &alice.id, "👍").await.unwrap());
+        assert!(!add_reaction(&pool, &msg.id, &alice.id, "👍").await.unwrap());
+        assert!(add_reaction(&pool, &msg.id, &bob.id, "👍").await.unwrap());
+        assert!(add_reaction(&pool, &msg.id, &bob.id, "😂").await.unwrap());
+        assert_eq!(
+            list_reactions_for_message(&pool, &msg.id)
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let counts = count_reactions_for_messages(&pool, std::slice::from_ref(&msg.id))
+            .await
+            .unwrap();
+        assert_eq!(
+            counts[&msg.id],
+            BTreeMap::from([("👍".to_string(), 2), ("😂".to_string(), 1)])
+        );
+
+        assert!(
+            remove_reaction(&pool, &msg.id, &bob.id, "😂")
+                .await
+                .unwrap()
+        );
+        assert!(
+            !remove_reaction(&pool, &msg.id, &bob.id, "😂")
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            list_reactions_for_message(&pool, &msg.id)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bookmarks_round_trip() {
+        let (pool, conv_id) = setup().await;
+        let user_id: String = sqlx::query_scalar("SELECT user_id FROM conversations WHERE id = ?")
+            .bind(&conv_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let first = create_message(&pool, &conv_id, "user", "first", None, None, None)
+            .await
+            .unwrap();
+        let second = create_message(&pool, &conv_id, "assistant", "second", None, None, None)
+            .await
+            .unwrap();
+
+        assert!(
+            bookmark_message(&pool, &user_id, &first.id, &conv_id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !bookmark_message(&pool, &user_id, &first.id, &conv_id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            bookmark_message(&pool, &user_id, &second.id, &conv_id)
+                .await
+                .unwrap()
+        );
+
+        let listed = list_bookmarks_for_user(&pool, &user_id, 10, 0)
+            .await
+            .unwrap();
+        let contents: Vec<_> = listed.iter().map(|b| b.content.as_str()).collect();
+        assert_eq!(contents, ["second", "first"]);
+        assert_eq!(listed[0].conversation_title, "Test Conv");
+        assert_eq!(
+            list_bookmarks_for_user(&pool, &user_id, 1, 1)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert!(
+            unbookmark_message(&pool, &user_id, &second.id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !unbookmark_message(&pool, &user_id, &second.id)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            list_bookmarks_for_user(&pool, &user_id, 10, 0)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_token_usage_summary_and_buckets() {
+        let (pool, conv_id) = setup().await;
+        let empty = sum_token_usage(&pool, &conv_id).await.unwrap();
+        assert_eq!(empty.total_tokens, 0);
+        assert_eq!(empty.average_tokens_per_message, 0.0);
+
+        for (tokens, created_at) in [
+            (Some(10), "2026-01-01 09:00:00"),
+            (Some(30), "2026-01-01 17:00:00"),
+            (None, "2026-01-01 18:00:00"),
+            (Some(20), "2026-01-02 08:00:00"),
+        ] {
+            let msg = create_message(&pool, &conv_id, "assistant", "x", None, None, tokens)
+                .await
+                .unwrap();
+            sqlx::query("UPDATE messages SET created_at = ? WHERE id = ?")
+                .bind(created_at)
+                .bind(&msg.id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let summary = sum_token_usage(&pool, &conv_id).await.unwrap();
+        assert_eq!(summary.total_tokens, 60);
+        assert_eq!(summary.message_count_with_usage, 3);
+        assert_eq!(summary.average_tokens_per_message, 20.0);
+
+        let days = token_usage_over_time(&pool, &conv_id, "day").await.unwrap();
+        let days: Vec<_> = days
+            .iter()
+            .map(|b| (b.bucket.as_str(), b.total_tokens, b.message_count))
+            .collect();
+        assert_eq!(days, [("2026-01-01", 40, 2), ("2026-01-02", 20, 1)]);
+        let months = token_usage_over_time(&pool, &conv_id, "month")
+            .await
+            .unwrap();
+        assert_eq!(months.len(), 1);
+        assert_eq!(months[0].bucket, "2026-01");
+    }
+
     #[tokio::test]
     async fn test_create_message() {
         let (pool, conv_id) = setup().await;
@@ -282,4 +1034,406 @@ mod tests {
             .unwrap();
         assert_eq!(deleted, 0);
     }
+
+    #[tokio::test]
+    async fn test_create_message_at_start() {
+        let (pool, conv_id) = setup().await;
+        create_message(&pool, &conv_id, "user", "First", None, None, None)
+            .await
+            .unwrap();
+        create_message(&pool, &conv_id, "assistant", "Second", None, None, None)
+            .await
+            .unwrap();
+
+        create_message_at_start(&pool, &conv_id, "system", "Summary")
+            .await
+            .unwrap();
+        create_message(&pool, &conv_id, "user", "Third", None, None, None)
+            .await
+            .unwrap();
+
+        let contents: Vec<String> = list_messages(&pool, &conv_id, 100, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, ["Summary", "First", "Second", "Third"]);
+    }
+
+    async fn seed_messages(pool: &SqlitePool, conv_id: &str, n: usize) -> Vec<Message> {
+        let mut out = Vec::with_capacity(n);
+        for i in 0..n {
+            out.push(
+                create_message(pool, conv_id, "user", &format!("m{i}"), None, None, None)
+                    .await
+                    .unwrap(),
+            );
+        }
+        out
+    }
+
+    fn contents(messages: &[Message]) -> Vec<&str> {
+        messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_create_message_batch_preserves_order() {
+        let (pool, conv_id) = setup().await;
+        create_message(&pool, &conv_id, "user", "existing", None, None, None)
+            .await
+            .unwrap();
+
+        let inputs: Vec<String> = (0..5).map(|i| format!("imported {i}")).collect();
+        let batch: Vec<NewMessage<'_>> = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, content)| NewMessage {
+                role: if i % 2 == 0 { "user" } else { "assistant" },
+                content,
+                tool_calls: None,
+                tool_call_id: None,
+                token_count: Some(i as i64),
+            })
+            .collect();
+
+        let created = create_message_batch(&pool, &conv_id, &batch).await.unwrap();
+        assert_eq!(created.len(), 5);
+        let ids: std::collections::HashSet<_> = created.iter().map(|m| &m.id).collect();
+        assert_eq!(ids.len(), 5);
+        assert_eq!(
+            contents(&created),
+            vec![
+                "imported 0",
+                "imported 1",
+                "imported 2",
+                "imported 3",
+                "imported 4"
+            ]
+        );
+        assert_eq!(created[1].role, "assistant");
+        assert_eq!(created[4].token_count, Some(4));
+
+        let all = list_messages(&pool, &conv_id, 100, 0).await.unwrap();
+        assert_eq!(all.len(), 6);
+        assert_eq!(all[0].content, "existing");
+        assert_eq!(all[5].id, created[4].id);
+    }
+
+    #[tokio::test]
+    async fn test_create_message_batch_empty() {
+        let (pool, conv_id) = setup().await;
+        assert!(
+            create_message_batch(&pool, &conv_id, &[])
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    async fn owner_of(pool: &SqlitePool, conv_id: &str) -> String {
+        sqlx::query_scalar("SELECT user_id FROM conversations WHERE id = ?")
+            .bind(conv_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_last_message_empty() {
+        let (pool, conv_id) = setup().await;
+        let user_id = owner_of(&pool, &conv_id).await;
+        assert!(
+            get_last_message(&pool, &conv_id, &user_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_last_message_returns_latest() {
+        let (pool, conv_id) = setup().await;
+        let user_id = owner_of(&pool, &conv_id).await;
+        let msgs = seed_messages(&pool, &conv_id, 4).await;
+        let last = get_last_message(&pool, &conv_id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(last.id, msgs[3].id);
+    }
+
+    #[tokio::test]
+    async fn test_get_last_message_wrong_user() {
+        let (pool, conv_id) = setup().await;
+        seed_messages(&pool, &conv_id, 2).await;
+        let other = create_user(&pool, "intruder", "intruder@example.com", "hash")
+            .await
+            .unwrap();
+        assert!(
+            get_last_message(&pool, &conv_id, &other.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_messages_around_middle() {
+        let (pool, conv_id) = setup().await;
+        let msgs = seed_messages(&pool, &conv_id, 10).await;
+        let around = get_messages_around(&pool, &conv_id, &msgs[5].id, 2, 3)
+            .await
+            .unwrap();
+        assert_eq!(contents(&around), vec!["m3", "m4", "m5", "m6", "m7", "m8"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_messages_around_start() {
+        let (pool, conv_id) = setup().await;
+        let msgs = seed_messages(&pool, &conv_id, 10).await;
+        let around = get_messages_around(&pool, &conv_id, &msgs[0].id, 5, 2)
+            .await
+            .unwrap();
+        assert_eq!(contents(&around), vec!["m0", "m1", "m2"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_messages_around_end() {
+        let (pool, conv_id) = setup().await;
+        let msgs = seed_messages(&pool, &conv_id, 10).await;
+        let around = get_messages_around(&pool, &conv_id, &msgs[9].id, 2, 5)
+            .await
+            .unwrap();
+        assert_eq!(contents(&around), vec!["m7", "m8", "m9"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_messages_around_zero_window_returns_anchor() {
+        let (pool, conv_id) = setup().await;
+        let msgs = seed_messages(&pool, &conv_id, 3).await;
+        let around = get_messages_around(&pool, &conv_id, &msgs[1].id, 0, 0)
+            .await
+            .unwrap();
+        assert_eq!(contents(&around), vec!["m1"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_messages_around_unknown_anchor() {
+        let (pool, conv_id) = setup().await;
+        seed_messages(&pool, &conv_id, 3).await;
+        let around = get_messages_around(&pool, &conv_id, "nonexistent-id", 5, 5)
+            .await
+            .unwrap();
+        assert!(around.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_messages_after() {
+        let (pool, conv_id) = setup().await;
+        let msgs = seed_messages(&pool, &conv_id, 5).await;
+
+        let page = list_messages_after(&pool, &conv_id, &msgs[1].id, 2)
+            .await
+            .unwrap();
+        assert_eq!(contents(&page), vec!["m2", "m3"]);
+
+        let tail = list_messages_after(&pool, &conv_id, &msgs[4].id, 10)
+            .await
+            .unwrap();
+        assert!(tail.is_empty());
+
+        let unknown = list_messages_after(&pool, &conv_id, "missing", 10)
+            .await
+            .unwrap();
+        assert!(unknown.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_messages_after_is_soft_and_undoable() {
+        let (pool, conv_id) = setup().await;
+        let msgs = seed_messages(&pool, &conv_id, 3).await;
+
+        assert_eq!(
+            delete_messages_after(&pool, &conv_id, &msgs[0].id)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(count_messages(&pool, &conv_id).await.unwrap(), 1);
+        assert!(get_message(&pool, &msgs[1].id).await.unwrap().is_none());
+        let raw: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM messages")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(raw, 3);
+
+        assert!(
+            undelete_message(&pool, &conv_id, &msgs[1].id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !undelete_message(&pool, &conv_id, &msgs[1].id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !undelete_message(&pool, "other-conv", &msgs[2].id)
+                .await
+                .unwrap()
+        );
+        let listed = list_messages(&pool, &conv_id, 10, 0).await.unwrap();
+        assert_eq!(contents(&listed), vec!["m0", "m1"]);
+    }
+
+    #[tokio::test]
+    async fn test_hard_purge_deleted_messages() {
+        let (pool, conv_id) = setup().await;
+        let msgs = seed_messages(&pool, &conv_id, 3).await;
+        delete_messages_after(&pool, &conv_id, &msgs[0].id)
+            .await
+            .unwrap();
+
+        let past = Utc::now() - chrono::Duration::days(1);
+        assert_eq!(hard_purge_deleted_messages(&pool, past).await.unwrap(), 0);
+
+        let future = Utc::now() + chrono::Duration::minutes(1);
+        assert_eq!(hard_purge_deleted_messages(&pool, future).await.unwrap(), 2);
+        assert!(
+            !undelete_message(&pool, &conv_id, &msgs[1].id)
+                .await
+                .unwrap()
+        );
+        let raw: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM messages")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(raw, 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_messages_matches_all_terms() {
+        let (pool, conv_id) = setup().await;
+        let user_id = owner_of(&pool, &conv_id).await;
+        for content in [
+            "Let's deploy this on Kubernetes",
+            "Kubernetes needs a cluster",
+            "Docker is simpler",
+        ] {
+            create_message(&pool, &conv_id, "user", content, None, None, None)
+                .await
+                .unwrap();
+        }
+
+        let hits = search_messages(&pool, &conv_id, &user_id, "kubernetes", 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(
+            count_search_messages(&pool, &conv_id, &user_id, "kubernetes")
+                .await
+                .unwrap(),
+            2
+        );
+
+        let hits = search_messages(&pool, &conv_id, &user_id, "kubernetes cluster", 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(contents(&hits), vec!["Kubernetes needs a cluster"]);
+
+        let paged = search_messages(&pool, &conv_id, &user_id, "kubernetes", 1, 1)
+            .await
+            .unwrap();
+        assert_eq!(paged.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_messages_tracks_updates_and_deletes() {
+        let (pool, conv_id) = setup().await;
+        let user_id = owner_of(&pool, &conv_id).await;
+        let msgs = seed_messages(&pool, &conv_id, 3).await;
+
+        update_message_content(&pool, &msgs[0].id, "renamed content")
+            .await
+            .unwrap();
+        let hits = search_messages(&pool, &conv_id, &user_id, "renamed", 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(
+            search_messages(&pool, &conv_id, &user_id, "m0", 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        delete_messages_after(&pool, &conv_id, &msgs[0].id)
+            .await
+            .unwrap();
+        assert!(
+            search_messages(&pool, &conv_id, &user_id, "m2", 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_messages_survives_vacuum() {
+        let (pool, conv_id) = setup().await;
+        let user_id = owner_of(&pool, &conv_id).await;
+        let msgs = seed_messages(&pool, &conv_id, 3).await;
+
+        // Removing a row and vacuuming lets SQLite renumber the implicit rowids.
+        sqlx::query("DELETE FROM messages WHERE id = ?")
+            .bind(&msgs[0].id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("VACUUM").execute(&pool).await.unwrap();
+
+        let hits = search_messages(&pool, &conv_id, &user_id, "m2", 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, msgs[2].id);
+        assert!(
+            search_messages(&pool, &conv_id, &user_id, "m0", 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_messages_handles_syntax_characters_and_other_users() {
+        let (pool, conv_id) = setup().await;
+        create_message(
+            &pool,
+            &conv_id,
+            "user",
+            "say \"hi\" (now)",
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let user_id = owner_of(&pool, &conv_id).await;
+        let hits = search_messages(&pool, &conv_id, &user_id, "\"hi\" (now", 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+
+        let other = create_user(&pool, "intruder", "intruder@example.com", "hash")
+            .await
+            .unwrap();
+        assert!(
+            search_messages(&pool, &conv_id, &other.id, "hi", 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
 }
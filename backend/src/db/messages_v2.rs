@@ -310,7 +310,7 @@ pub async fn get_message_v2(
     sqlx::query_as::<_, MessageV2>(
         "SELECT id, conversation_id, role, provider, model, token_usage_json, meta_json, created_at \
          FROM messages_v2 \
-         WHERE id = ?",
+         WHERE id = ? AND deleted_at IS NULL",
     )
     .bind(message_id)
     .fetch_optional(pool)
@@ -327,7 +327,7 @@ pub async fn list_messages_v2(
     sqlx::query_as::<_, MessageV2>(
         "SELECT id, conversation_id, role, provider, model, token_usage_json, meta_json, created_at \
          FROM messages_v2 \
-         WHERE conversation_id = ? \
+         WHERE conversation_id = ? AND deleted_at IS NULL \
          ORDER BY rowid ASC \
          LIMIT ? OFFSET ?",
     )
@@ -406,13 +406,16 @@ pub async fn list_message_parts_for_messages(
     Ok(grouped)
 }
 
+/// Soft-delete every `messages_v2` row after `after_message_id`, mirroring
+/// [`crate::db::messages::delete_messages_after`].
 pub async fn delete_messages_v2_after(
     pool: &SqlitePool,
     conversation_id: &str,
     after_message_id: &str,
 ) -> Result<u64, sqlx::Error> {
     let result = sqlx::query(
-        "DELETE FROM messages_v2 WHERE conversation_id = ? \
+        "UPDATE messages_v2 SET deleted_at = datetime('now') \
+         WHERE conversation_id = ? AND deleted_at IS NULL \
          AND rowid > (SELECT rowid FROM messages_v2 WHERE id = ?)",
     )
     .bind(conversation_id)
@@ -422,6 +425,146 @@ pub async fn delete_messages_v2_after(
     Ok(result.rows_affected())
 }
 
+/// Delete `message_parts` rows whose parent `messages_v2` row no longer exists.
+pub async fn repair_orphaned_parts(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM message_parts WHERE message_id NOT IN (SELECT id FROM messages_v2)",
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Delete `messages_v2` rows whose legacy `messages` row no longer exists.
+/// Their parts are removed by the `ON DELETE CASCADE` foreign key.
+pub async fn repair_orphaned_messages_v2(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM messages_v2 WHERE id NOT IN (SELECT id FROM messages)")
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Copy `completion` token counts from `messages_v2.token_usage_json` into
+/// legacy `messages.token_count` where the legacy value is missing.
+/// `conversation_id = None` backfills every conversation.
+pub async fn backfill_token_usage(
+    pool: &SqlitePool,
+    conversation_id: Option<&str>,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE messages
+         SET token_count = (
+             SELECT json_extract(v2.token_usage_json, '$.completion')
+             FROM messages_v2 v2
+             WHERE v2.id = messages.id
+         )
+         WHERE (? IS NULL OR conversation_id = ?)
+           AND token_count IS NULL
+           AND EXISTS (
+               SELECT 1 FROM messages_v2 v2
+               WHERE v2.id = messages.id
+                 AND json_extract(v2.token_usage_json, '$.completion') IS NOT NULL
+           )",
+    )
+    .bind(conversation_id)
+    .bind(conversation_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Collapse each run of consecutive `text` parts of a message into a single
+/// part at the run's lowest `seq`. Returns the number of parts removed.
+pub async fn merge_consecutive_text_parts(
+    pool: &SqlitePool,
+    message_id: &str,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let parts = sqlx::query_as::<_, MessagePart>(
+        "SELECT id, message_id, seq, part_type, text, json_payload, tool_call_id, created_at \
+         FROM message_parts \
+         WHERE message_id = ? \
+         ORDER BY seq ASC",
+    )
+    .bind(message_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut removed = 0u64;
+    for run in parts.chunk_by(|a, b| a.part_type == "text" && b.part_type == "text") {
+        if run.len() < 2 {
+            continue;
+        }
+        let merged: String = run.iter().filter_map(|p| p.text.as_deref()).collect();
+        let mut delete =
+            QueryBuilder::<Sqlite>::new("DELETE FROM message_parts WHERE message_id = ");
+        delete.push_bind(message_id).push(" AND id IN (");
+        let mut ids = delete.separated(", ");
+        for part in run {
+            ids.push_bind(&part.id);
+        }
+        ids.push_unseparated(")");
+        delete.build().execute(&mut *tx).await?;
+
+        sqlx::query(
+            "INSERT INTO message_parts (id, message_id, seq, part_type, text, json_payload, tool_call_id) \
+             VALUES (?, ?, ?, 'text', ?, NULL, NULL)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(message_id)
+        .bind(run[0].seq)
+        .bind(merged)
+        .execute(&mut *tx)
+        .await?;
+        removed += run.len() as u64 - 1;
+    }
+
+    tx.commit().await?;
+    Ok(removed)
+}
+
+/// Run [`merge_consecutive_text_parts`] over every message of a conversation.
+pub async fn merge_consecutive_text_parts_for_conversation(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<u64, sqlx::Error> {
+    let message_ids = sqlx::query_scalar::<_, String>(
+        "SELECT id FROM messages_v2 WHERE conversation_id = ? ORDER BY rowid ASC",
+    )
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut removed = 0;
+    for message_id in &message_ids {
+        removed += merge_consecutive_text_parts(pool, message_id).await?;
+    }
+    Ok(removed)
+}
+
+/// Spawn a background task that runs the orphan repair functions once per
+/// `interval_secs` (daily in production).
+pub fn spawn_orphan_repair(pool: SqlitePool, interval_secs: u64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        // The first tick completes immediately; skip it so repairs don't run at startup.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match repair_orphaned_messages_v2(&pool).await {
+                Ok(n) if n > 0 => tracing::info!("Removed {n} orphaned messages_v2 row(s)"),
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to repair orphaned messages_v2 rows: {e}"),
+            }
+            match repair_orphaned_parts(&pool).await {
+                Ok(n) if n > 0 => tracing::info!("Removed {n} orphaned message part(s)"),
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to repair orphaned message parts: {e}"),
+            }
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -503,7 +646,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_delete_messages_after_cascades_parts() {
+    async fn test_delete_messages_after_keeps_parts_until_purge() {
         let (pool, conv_id) = setup().await;
         let (m1, _) = create_message_with_parts(
             &pool,
@@ -552,6 +695,14 @@ mod tests {
         assert_eq!(msgs.len(), 1);
         assert_eq!(msgs[0].id, m1.id);
 
+        // Parts stay until the soft-deleted message is purged so it can be restored.
+        let m2_parts = list_message_parts(&pool, &m2.id).await.unwrap();
+        assert_eq!(m2_parts.len(), 1);
+
+        let future = chrono::Utc::now() + chrono::Duration::minutes(1);
+        crate::db::messages::hard_purge_deleted_messages(&pool, future)
+            .await
+            .unwrap();
         let m2_parts = list_message_parts(&pool, &m2.id).await.unwrap();
         assert_eq!(m2_parts.len(), 0);
     }
@@ -751,4 +902,291 @@ mod tests {
         assert_eq!(grouped.get(&m1.id).map(Vec::len), Some(1));
         assert_eq!(grouped.get(&m2.id).map(Vec::len), Some(1));
     }
+
+    async fn insert_without_foreign_keys(pool: &SqlitePool, sql: &str, binds: &[&str]) {
+        let mut conn = pool.acquire().await.unwrap();
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        let mut query = sqlx::query(sql);
+        for b in binds {
+            query = query.bind(*b);
+        }
+        query.execute(&mut *conn).await.unwrap();
+        sqlx::query("PRAGMA foreign_keys = ON")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+    }
+
+    async fn create_with_usage(
+        pool: &SqlitePool,
+        conv_id: &str,
+        token_count: Option<i64>,
+        token_usage_json: Option<&str>,
+    ) -> Message {
+        let legacy = create_message(pool, conv_id, "assistant", "reply", None, None, token_count)
+            .await
+            .unwrap();
+        create_message_with_parts(
+            pool,
+            Some(&legacy.id),
+            conv_id,
+            "assistant",
+            None,
+            None,
+            token_usage_json,
+            None,
+            &[NewMessagePart {
+                part_type: "text",
+                text: Some("reply"),
+                json_payload: None,
+                tool_call_id: None,
+            }],
+        )
+        .await
+        .unwrap();
+        legacy
+    }
+
+    #[tokio::test]
+    async fn test_backfill_token_usage_fills_missing_counts() {
+        let (pool, conv_id) = setup().await;
+        let missing = create_with_usage(&pool, &conv_id, None, Some(r#"{"completion":42}"#)).await;
+        let present =
+            create_with_usage(&pool, &conv_id, Some(7), Some(r#"{"completion":99}"#)).await;
+        let no_usage = create_with_usage(&pool, &conv_id, None, None).await;
+
+        let updated = backfill_token_usage(&pool, Some(&conv_id)).await.unwrap();
+        assert_eq!(updated, 1);
+
+        let token_count = |id: String| {
+            let pool = pool.clone();
+            async move { get_message(&pool, &id).await.unwrap().unwrap().token_count }
+        };
+        assert_eq!(token_count(missing.id).await, Some(42));
+        assert_eq!(token_count(present.id).await, Some(7));
+        assert_eq!(token_count(no_usage.id).await, None);
+
+        assert_eq!(
+            backfill_token_usage(&pool, Some(&conv_id)).await.unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backfill_token_usage_scopes_by_conversation() {
+        let (pool, conv_id) = setup().await;
+        let user_id: String = sqlx::query_scalar("SELECT user_id FROM conversations WHERE id = ?")
+            .bind(&conv_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let other = create_conversation(
+            &pool, &user_id, "Other", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        create_with_usage(&pool, &conv_id, None, Some(r#"{"completion":1}"#)).await;
+        create_with_usage(&pool, &other.id, None, Some(r#"{"completion":2}"#)).await;
+
+        assert_eq!(
+            backfill_token_usage(&pool, Some(&other.id)).await.unwrap(),
+            1
+        );
+        assert_eq!(backfill_token_usage(&pool, None).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_repair_orphaned_parts_removes_only_orphans() {
+        let (pool, conv_id) = setup().await;
+        let legacy = create_message(&pool, &conv_id, "user", "kept", None, None, None)
+            .await
+            .unwrap();
+        create_message_with_parts(
+            &pool,
+            Some(&legacy.id),
+            &conv_id,
+            "user",
+            None,
+            None,
+            None,
+            None,
+            &[NewMessagePart {
+                part_type: "text",
+                text: Some("kept"),
+                json_payload: None,
+                tool_call_id: None,
+            }],
+        )
+        .await
+        .unwrap();
+
+        insert_without_foreign_keys(
+            &pool,
+            "INSERT INTO message_parts (id, message_id, seq, part_type, text) \
+             VALUES (?, ?, 0, 'text', 'orphan')",
+            &["orphan-part", "missing-message"],
+        )
+        .await;
+
+        let deleted = repair_orphaned_parts(&pool).await.unwrap();
+        assert_eq!(deleted, 1);
+        assert_eq!(
+            list_message_parts(&pool, &legacy.id).await.unwrap().len(),
+            1
+        );
+        assert!(
+            list_message_parts(&pool, "missing-message")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // Nothing left to repair
+        assert_eq!(repair_orphaned_parts(&pool).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_repair_orphaned_messages_v2_removes_rows_without_legacy_parent() {
+        let (pool, conv_id) = setup().await;
+        let legacy = create_message(&pool, &conv_id, "user", "kept", None, None, None)
+            .await
+            .unwrap();
+        let part = NewMessagePart {
+            part_type: "text",
+            text: Some("x"),
+            json_payload: None,
+            tool_call_id: None,
+        };
+        create_message_with_parts(
+            &pool,
+            Some(&legacy.id),
+            &conv_id,
+            "user",
+            None,
+            None,
+            None,
+            None,
+            std::slice::from_ref(&part),
+        )
+        .await
+        .unwrap();
+        let (orphan, _) = create_message_with_parts(
+            &pool,
+            None,
+            &conv_id,
+            "assistant",
+            None,
+            None,
+            None,
+            None,
+            std::slice::from_ref(&part),
+        )
+        .await
+        .unwrap();
+
+        let deleted = repair_orphaned_messages_v2(&pool).await.unwrap();
+        assert_eq!(deleted, 1);
+        assert!(get_message_v2(&pool, &legacy.id).await.unwrap().is_some());
+        assert!(get_message_v2(&pool, &orphan.id).await.unwrap().is_none());
+        // Cascade removes the orphan's parts too
+        assert!(
+            list_message_parts(&pool, &orphan.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(repair_orphaned_parts(&pool).await.unwrap(), 0);
+    }
+
+    fn text_part(text: &str) -> NewMessagePart<'_> {
+        NewMessagePart {
+            part_type: "text",
+            text: Some(text),
+            json_payload: None,
+            tool_call_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_merge_consecutive_text_parts_only_merges_adjacent_runs() {
+        let (pool, conv_id) = setup().await;
+        let tool = NewMessagePart {
+            part_type: "tool_call",
+            text: None,
+            json_payload: Some(r#"{"name":"bash"}"#),
+            tool_call_id: Some("tc-1"),
+        };
+        let (msg, _) = create_message_with_parts(
+            &pool,
+            None,
+            &conv_id,
+            "assistant",
+            None,
+            None,
+            None,
+            None,
+            &[
+                text_part("Hel"),
+                text_part("lo "),
+                text_part("there"),
+                tool,
+                text_part("done"),
+                text_part("!"),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let removed = merge_consecutive_text_parts(&pool, &msg.id).await.unwrap();
+        assert_eq!(removed, 3);
+
+        let parts = list_message_parts(&pool, &msg.id).await.unwrap();
+        let summary: Vec<_> = parts
+            .iter()
+            .map(|p| (p.seq, p.part_type.as_str(), p.text.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, "text", Some("Hello there")),
+                (3, "tool_call", None),
+                (4, "text", Some("done!")),
+            ]
+        );
+        assert_eq!(parts[1].tool_call_id.as_deref(), Some("tc-1"));
+
+        // Already merged: nothing left to do.
+        assert_eq!(
+            merge_consecutive_text_parts(&pool, &msg.id).await.unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_consecutive_text_parts_for_conversation() {
+        let (pool, conv_id) = setup().await;
+        for _ in 0..2 {
+            create_message_with_parts(
+                &pool,
+                None,
+                &conv_id,
+                "assistant",
+                None,
+                None,
+                None,
+                None,
+                &[text_part("a"), text_part("b")],
+            )
+            .await
+            .unwrap();
+        }
+
+        let removed = merge_consecutive_text_parts_for_conversation(&pool, &conv_id)
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+    }
 }
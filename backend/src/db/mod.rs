@@ -1,11 +1,20 @@
+pub mod api_keys;
+pub mod audit_log;
 pub mod conversations;
+pub mod email_verification;
+pub mod login_attempts;
 pub mod mcp_servers;
 pub mod messages;
 pub mod messages_v2;
 pub mod model_defaults;
+pub mod oauth_states;
+pub mod password_reset;
 pub mod presets;
+pub mod pricing;
 pub mod providers;
 pub mod refresh_tokens;
+pub mod system_stats;
+pub mod user_quotas;
 pub mod users;
 
 use sqlx::SqlitePool;
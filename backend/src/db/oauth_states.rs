@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use sqlx::prelude::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OAuthState {
+    pub state: String,
+    pub code_verifier: String,
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+pub async fn create_oauth_state(
+    pool: &SqlitePool,
+    state: &str,
+    code_verifier: &str,
+    expires_at: &str,
+) -> Result<OAuthState, sqlx::Error> {
+    sqlx::query_as::<_, OAuthState>(
+        "INSERT INTO oauth_states (state, code_verifier, expires_at) \
+         VALUES (?, ?, ?) \
+         RETURNING state, code_verifier, created_at, expires_at",
+    )
+    .bind(state)
+    .bind(code_verifier)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await
+}
+
+/// Delete and return a pending state so it can only be used once.
+pub async fn consume_oauth_state(
+    pool: &SqlitePool,
+    state: &str,
+) -> Result<Option<OAuthState>, sqlx::Error> {
+    sqlx::query_as::<_, OAuthState>(
+        "DELETE FROM oauth_states WHERE state = ? \
+         RETURNING state, code_verifier, created_at, expires_at",
+    )
+    .bind(state)
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+
+    #[tokio::test]
+    async fn test_create_and_consume_state() {
+        let pool = init_db("sqlite::memory:").await;
+        let created = create_oauth_state(&pool, "st-1", "verifier", "2099-01-01T00:00:00+00:00")
+            .await
+            .unwrap();
+        assert_eq!(created.state, "st-1");
+
+        let consumed = consume_oauth_state(&pool, "st-1").await.unwrap().unwrap();
+        assert_eq!(consumed.code_verifier, "verifier");
+        assert_eq!(consumed.expires_at, "2099-01-01T00:00:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn test_consume_state_only_once() {
+        let pool = init_db("sqlite::memory:").await;
+        create_oauth_state(&pool, "st-1", "verifier", "2099-01-01T00:00:00+00:00")
+            .await
+            .unwrap();
+
+        assert!(consume_oauth_state(&pool, "st-1").await.unwrap().is_some());
+        assert!(consume_oauth_state(&pool, "st-1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_consume_unknown_state_returns_none() {
+        let pool = init_db("sqlite::memory:").await;
+        assert!(
+            consume_oauth_state(&pool, "missing")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use sqlx::prelude::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PasswordResetToken {
+    pub token_hash: String,
+    pub user_id: String,
+    pub expires_at: String,
+    pub used: bool,
+    pub created_at: String,
+}
+
+pub async fn create_reset_token(
+    pool: &SqlitePool,
+    user_id: &str,
+    token_hash: &str,
+    expires_at: &str,
+) -> Result<PasswordResetToken, sqlx::Error> {
+    sqlx::query_as::<_, PasswordResetToken>(
+        "INSERT INTO password_reset_tokens (token_hash, user_id, expires_at) \
+         VALUES (?, ?, ?) \
+         RETURNING token_hash, user_id, expires_at, used, created_at",
+    )
+    .bind(token_hash)
+    .bind(user_id)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await
+}
+
+/// Mark an unused token as used and return it. Returns `None` if the token
+/// does not exist or was already consumed.
+pub async fn consume_reset_token(
+    pool: &SqlitePool,
+    token_hash: &str,
+) -> Result<Option<PasswordResetToken>, sqlx::Error> {
+    sqlx::query_as::<_, PasswordResetToken>(
+        "UPDATE password_reset_tokens SET used = 1 \
+         WHERE token_hash = ? AND used = 0 \
+         RETURNING token_hash, user_id, expires_at, used, created_at",
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::db::users::create_user;
+
+    #[tokio::test]
+    async fn test_consume_reset_token_once() {
+        let pool = init_db("sqlite::memory:").await;
+        let user = create_user(&pool, "reset", "reset@example.com", "hash")
+            .await
+            .unwrap();
+
+        create_reset_token(&pool, &user.id, "hash-1", "2099-01-01T00:00:00+00:00")
+            .await
+            .unwrap();
+
+        let consumed = consume_reset_token(&pool, "hash-1").await.unwrap().unwrap();
+        assert_eq!(consumed.user_id, user.id);
+        assert!(consumed.used);
+
+        assert!(
+            consume_reset_token(&pool, "hash-1")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            consume_reset_token(&pool, "missing")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
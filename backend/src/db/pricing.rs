@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use sqlx::prelude::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProviderPricing {
+    pub provider_type: String,
+    pub model: String,
+    pub input_cost_per_1k: f64,
+    pub output_cost_per_1k: f64,
+    pub updated_at: String,
+}
+
+impl ProviderPricing {
+    /// Cost in USD of a message with the given token counts.
+    pub fn cost_usd(&self, input_tokens: i64, output_tokens: i64) -> f64 {
+        (input_tokens as f64 * self.input_cost_per_1k
+            + output_tokens as f64 * self.output_cost_per_1k)
+            / 1000.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MessageCost {
+    pub message_id: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: Option<f64>,
+    pub created_at: String,
+}
+
+/// Totals over the live messages of a conversation that recorded a cost.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ConversationCost {
+    pub total_cost_usd: f64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub priced_message_count: i64,
+    /// Messages with usage but no pricing for their model.
+    pub unpriced_message_count: i64,
+}
+
+pub async fn upsert_pricing(
+    pool: &SqlitePool,
+    provider_type: &str,
+    model: &str,
+    input_cost_per_1k: f64,
+    output_cost_per_1k: f64,
+) -> Result<ProviderPricing, sqlx::Error> {
+    sqlx::query_as::<_, ProviderPricing>(
+        "INSERT INTO provider_pricing \
+         (provider_type, model, input_cost_per_1k, output_cost_per_1k) VALUES (?, ?, ?, ?) \
+         ON CONFLICT (provider_type, model) DO UPDATE SET \
+         input_cost_per_1k = excluded.input_cost_per_1k, \
+         output_cost_per_1k = excluded.output_cost_per_1k, \
+         updated_at = datetime('now') \
+         RETURNING provider_type, model, input_cost_per_1k, output_cost_per_1k, updated_at",
+    )
+    .bind(provider_type)
+    .bind(model)
+    .bind(input_cost_per_1k)
+    .bind(output_cost_per_1k)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn list_pricing(pool: &SqlitePool) -> Result<Vec<ProviderPricing>, sqlx::Error> {
+    sqlx::query_as::<_, ProviderPricing>(
+        "SELECT provider_type, model, input_cost_per_1k, output_cost_per_1k, updated_at \
+         FROM provider_pricing ORDER BY provider_type ASC, model ASC",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn delete_pricing(
+    pool: &SqlitePool,
+    provider_type: &str,
+    model: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM provider_pricing WHERE provider_type = ? AND model = ?")
+        .bind(provider_type)
+        .bind(model)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Pricing for the conversation's current chat provider type and model.
+pub async fn get_conversation_pricing(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<Option<ProviderPricing>, sqlx::Error> {
+    sqlx::query_as::<_, ProviderPricing>(
+        "SELECT pp.provider_type, pp.model, pp.input_cost_per_1k, pp.output_cost_per_1k, \
+         pp.updated_at \
+         FROM conversations c \
+         INNER JOIN user_providers up ON up.id = c.provider_id \
+         INNER JOIN provider_pricing pp \
+         ON pp.provider_type = up.provider AND pp.model = c.model_name \
+         WHERE c.id = ?",
+    )
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Store a message's token usage, priced with the conversation's current
+/// model. The cost is `None` if that model has no pricing.
+pub async fn record_message_cost(
+    pool: &SqlitePool,
+    message_id: &str,
+    conversation_id: &str,
+    input_tokens: i64,
+    output_tokens: i64,
+) -> Result<MessageCost, sqlx::Error> {
+    let cost_usd = get_conversation_pricing(pool, conversation_id)
+        .await?
+        .map(|p| p.cost_usd(input_tokens, output_tokens));
+    sqlx::query_as::<_, MessageCost>(
+        "INSERT INTO message_costs (message_id, input_tokens, output_tokens, cost_usd) \
+         VALUES (?, ?, ?, ?) \
+         ON CONFLICT (message_id) DO UPDATE SET \
+         input_tokens = excluded.input_tokens, \
+         output_tokens = excluded.output_tokens, \
+         cost_usd = excluded.cost_usd \
+         RETURNING message_id, input_tokens, output_tokens, cost_usd, created_at",
+    )
+    .bind(message_id)
+    .bind(input_tokens)
+    .bind(output_tokens)
+    .bind(cost_usd)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_conversation_cost(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<ConversationCost, sqlx::Error> {
+    sqlx::query_as::<_, ConversationCost>(
+        "SELECT COALESCE(SUM(mc.cost_usd), 0.0) AS total_cost_usd, \
+         COALESCE(SUM(mc.input_tokens), 0) AS input_tokens, \
+         COALESCE(SUM(mc.output_tokens), 0) AS output_tokens, \
+         COUNT(mc.cost_usd) AS priced_message_count, \
+         COUNT(mc.message_id) - COUNT(mc.cost_usd) AS unpriced_message_count \
+         FROM message_costs mc \
+         INNER JOIN messages m ON m.id = mc.message_id \
+         WHERE m.conversation_id = ? AND m.deleted_at IS NULL",
+    )
+    .bind(conversation_id)
+    .fetch_one(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+
+    async fn setup_conversation(pool: &SqlitePool, model: &str) -> String {
+        let user = crate::db::users::create_user(pool, "u", "u@example.com", "hash")
+            .await
+            .unwrap();
+        let provider = crate::db::providers::upsert_provider(
+            pool,
+            None,
+            &user.id,
+            "openai",
+            "enc",
+            None,
+            Some(model),
+            true,
+            None,
+            Some("OpenAI"),
+            None,
+        )
+        .await
+        .unwrap();
+        crate::db::conversations::create_conversation(
+            pool,
+            &user.id,
+            "t",
+            None,
+            Some(&provider.id),
+            Some(model),
+            false,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .id
+    }
+
+    async fn assistant_message(pool: &SqlitePool, conv_id: &str) -> String {
+        crate::db::messages::create_message(pool, conv_id, "assistant", "hi", None, None, None)
+            .await
+            .unwrap()
+            .id
+    }
+
+    #[test]
+    fn cost_uses_per_thousand_rates() {
+        let pricing = ProviderPricing {
+            provider_type: "openai".into(),
+            model: "gpt-4o".into(),
+            input_cost_per_1k: 0.005,
+            output_cost_per_1k: 0.015,
+            updated_at: String::new(),
+        };
+        assert!((pricing.cost_usd(2000, 1000) - 0.025).abs() < 1e-12);
+        assert_eq!(pricing.cost_usd(0, 0), 0.0);
+    }
+
+    #[tokio::test]
+    async fn records_and_sums_costs_with_current_pricing() {
+        let pool = init_db("sqlite::memory:").await;
+        let conv_id = setup_conversation(&pool, "gpt-4o").await;
+
+        // No pricing yet: usage is kept but unpriced.
+        let unpriced = assistant_message(&pool, &conv_id).await;
+        let cost = record_message_cost(&pool, &unpriced, &conv_id, 100, 50)
+            .await
+            .unwrap();
+        assert_eq!(cost.cost_usd, None);
+
+        upsert_pricing(&pool, "openai", "gpt-4o", 0.01, 0.03)
+            .await
+            .unwrap();
+        let priced = assistant_message(&pool, &conv_id).await;
+        let cost = record_message_cost(&pool, &priced, &conv_id, 1000, 500)
+            .await
+            .unwrap();
+        assert!((cost.cost_usd.unwrap() - 0.025).abs() < 1e-12);
+
+        let total = get_conversation_cost(&pool, &conv_id).await.unwrap();
+        assert!((total.total_cost_usd - 0.025).abs() < 1e-12);
+        assert_eq!(total.input_tokens, 1100);
+        assert_eq!(total.output_tokens, 550);
+        assert_eq!(total.priced_message_count, 1);
+        assert_eq!(total.unpriced_message_count, 1);
+
+        assert_eq!(list_pricing(&pool).await.unwrap().len(), 1);
+        assert!(delete_pricing(&pool, "openai", "gpt-4o").await.unwrap());
+        assert!(!delete_pricing(&pool, "openai", "gpt-4o").await.unwrap());
+    }
+}
@@ -15,6 +15,30 @@ pub struct UserProvider {
     pub models: Option<String>,
     pub name: Option<String>,
     pub image_models: Option<String>,
+    pub fallback_provider_id: Option<String>,
+}
+
+/// Check that a provider `endpoint_url` is an absolute `http`/`https` URL.
+/// An empty value is accepted and means "use the provider's default URL".
+pub fn validate_endpoint_url(url: &str) -> Result<(), String> {
+    let trimmed = url.trim();
+    if trimmed.is_empty() {
+        return Ok(());
+    }
+    let parsed =
+        url::Url::parse(trimmed).map_err(|e| format!("Invalid endpoint_url '{trimmed}': {e}"))?;
+    match parsed.scheme() {
+        "http" | "https" => {}
+        other => {
+            return Err(format!(
+                "endpoint_url must use http or https, got '{other}'"
+            ));
+        }
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err("endpoint_url must include a host".to_string());
+    }
+    Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -51,8 +75,9 @@ pub async fn upsert_provider(
 
     sqlx::query_as::<_, UserProvider>(
         "INSERT INTO user_providers (id, user_id, provider, api_key_encrypted, \
-         endpoint_url, model_name, is_default, models, name, image_models) \
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
+         endpoint_url, model_name, is_default, models, name, image_models, display_order) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, \
+         (SELECT COALESCE(MAX(display_order), -1) + 1 FROM user_providers WHERE user_id = ?)) \
          ON CONFLICT(id) DO UPDATE SET \
          user_id = excluded.user_id, \
          provider = excluded.provider, \
@@ -65,7 +90,8 @@ pub async fn upsert_provider(
          image_models = excluded.image_models \
          WHERE user_providers.user_id = excluded.user_id \
          RETURNING id, user_id, provider, api_key_encrypted, \
-         endpoint_url, model_name, is_default, created_at, models, name, image_models",
+         endpoint_url, model_name, is_default, created_at, models, name, image_models, \
+         fallback_provider_id",
     )
     .bind(&actual_id)
     .bind(user_id)
@@ -77,6 +103,7 @@ pub async fn upsert_provider(
     .bind(models)
     .bind(actual_name)
     .bind(image_models)
+    .bind(user_id)
     .fetch_one(pool)
     .await
 }
@@ -87,15 +114,99 @@ pub async fn list_providers(
 ) -> Result<Vec<UserProvider>, sqlx::Error> {
     sqlx::query_as::<_, UserProvider>(
         "SELECT id, user_id, provider, api_key_encrypted, \
-         endpoint_url, model_name, is_default, created_at, models, name, image_models \
+         endpoint_url, model_name, is_default, created_at, models, name, image_models, \
+         fallback_provider_id \
          FROM user_providers WHERE user_id = ? \
-         ORDER BY created_at ASC",
+         ORDER BY display_order ASC, created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// A provider plus the number of the owner's conversations that reference
+/// it in any role (chat, subagent, or image).
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProviderWithUsage {
+    #[sqlx(flatten)]
+    pub provider: UserProvider,
+    pub conversation_count: i64,
+}
+
+pub async fn list_providers_with_usage(
+    pool: &SqlitePool,
+    user_id: &str,
+) -> Result<Vec<ProviderWithUsage>, sqlx::Error> {
+    sqlx::query_as::<_, ProviderWithUsage>(
+        "SELECT p.id, p.user_id, p.provider, p.api_key_encrypted, \
+         p.endpoint_url, p.model_name, p.is_default, p.created_at, p.models, p.name, p.image_models, \
+         p.fallback_provider_id, \
+         COUNT(c.id) AS conversation_count \
+         FROM user_providers p \
+         LEFT JOIN conversations c ON c.user_id = p.user_id \
+         AND (c.provider_id = p.id OR c.subagent_provider_id = p.id OR c.image_provider_id = p.id) \
+         WHERE p.user_id = ? \
+         GROUP BY p.id \
+         ORDER BY p.display_order ASC, p.created_at ASC",
     )
     .bind(user_id)
     .fetch_all(pool)
     .await
 }
 
+/// Set the display order of a user's providers to the order of `ordered_ids`.
+///
+/// `ordered_ids` must list every one of the user's providers exactly once;
+/// otherwise nothing is changed and `false` is returned.
+pub async fn reorder_providers(
+    pool: &SqlitePool,
+    user_id: &str,
+    ordered_ids: &[String],
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut existing: Vec<String> =
+        sqlx::query_scalar("SELECT id FROM user_providers WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await?;
+    let mut requested = ordered_ids.to_vec();
+    existing.sort();
+    requested.sort();
+    if existing != requested {
+        return Ok(false);
+    }
+
+    for (position, id) in ordered_ids.iter().enumerate() {
+        sqlx::query("UPDATE user_providers SET display_order = ? WHERE id = ? AND user_id = ?")
+            .bind(position as i64)
+            .bind(id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// List a user's providers of a single type (e.g. `"openai"`).
+pub async fn list_providers_by_type(
+    pool: &SqlitePool,
+    user_id: &str,
+    provider_type: &str,
+) -> Result<Vec<UserProvider>, sqlx::Error> {
+    sqlx::query_as::<_, UserProvider>(
+        "SELECT id, user_id, provider, api_key_encrypted, \
+         endpoint_url, model_name, is_default, created_at, models, name, image_models, \
+         fallback_provider_id \
+         FROM user_providers WHERE user_id = ? AND provider = ? \
+         ORDER BY display_order ASC, created_at ASC",
+    )
+    .bind(user_id)
+    .bind(provider_type)
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn get_provider_by_id(
     pool: &SqlitePool,
     user_id: &str,
@@ -103,7 +214,8 @@ pub async fn get_provider_by_id(
 ) -> Result<Option<UserProvider>, sqlx::Error> {
     sqlx::query_as::<_, UserProvider>(
         "SELECT id, user_id, provider, api_key_encrypted, \
-         endpoint_url, model_name, is_default, created_at, models, name, image_models \
+         endpoint_url, model_name, is_default, created_at, models, name, image_models, \
+         fallback_provider_id \
          FROM user_providers \
          WHERE user_id = ? AND id = ?",
     )
@@ -121,7 +233,8 @@ pub async fn get_provider_by_name(
 ) -> Result<Option<UserProvider>, sqlx::Error> {
     sqlx::query_as::<_, UserProvider>(
         "SELECT id, user_id, provider, api_key_encrypted, \
-         endpoint_url, model_name, is_default, created_at, models, name, image_models \
+         endpoint_url, model_name, is_default, created_at, models, name, image_models, \
+         fallback_provider_id \
          FROM user_providers \
          WHERE user_id = ? AND name = ?\n         ORDER BY created_at DESC\n         LIMIT 1",
     )
@@ -137,7 +250,8 @@ pub async fn get_default_provider(
 ) -> Result<Option<UserProvider>, sqlx::Error> {
     sqlx::query_as::<_, UserProvider>(
         "SELECT id, user_id, provider, api_key_encrypted, \
-         endpoint_url, model_name, is_default, created_at, models, name, image_models \
+         endpoint_url, model_name, is_default, created_at, models, name, image_models, \
+         fallback_provider_id \
          FROM user_providers \
          WHERE user_id = ? AND is_default = 1",
     )
@@ -146,6 +260,47 @@ pub async fn get_default_provider(
     .await
 }
 
+/// Set (or clear, with `None`) the provider a conversation fails over to
+/// when this one can't initialize. Returns `false` if the provider doesn't
+/// exist for the user.
+pub async fn set_provider_fallback(
+    pool: &SqlitePool,
+    user_id: &str,
+    id: &str,
+    fallback_provider_id: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE user_providers SET fallback_provider_id = ? WHERE user_id = ? AND id = ?",
+    )
+    .bind(fallback_provider_id)
+    .bind(user_id)
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// The fallback configured for `provider_id`, if it is set and still
+/// belongs to the same user.
+pub async fn get_provider_fallback(
+    pool: &SqlitePool,
+    provider_id: &str,
+    user_id: &str,
+) -> Result<Option<UserProvider>, sqlx::Error> {
+    sqlx::query_as::<_, UserProvider>(
+        "SELECT f.id, f.user_id, f.provider, f.api_key_encrypted, \
+         f.endpoint_url, f.model_name, f.is_default, f.created_at, f.models, f.name, \
+         f.image_models, f.fallback_provider_id \
+         FROM user_providers p \
+         INNER JOIN user_providers f ON f.id = p.fallback_provider_id AND f.user_id = p.user_id \
+         WHERE p.id = ? AND p.user_id = ?",
+    )
+    .bind(provider_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
 pub async fn delete_provider_by_id(
     pool: &SqlitePool,
     user_id: &str,
@@ -163,6 +318,39 @@ pub async fn delete_provider_by_id(
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn validate_endpoint_url_accepts_https() {
+        assert!(validate_endpoint_url("https://api.example.com/v1").is_ok());
+    }
+
+    #[test]
+    fn validate_endpoint_url_accepts_http() {
+        assert!(validate_endpoint_url("http://localhost:11434").is_ok());
+    }
+
+    #[test]
+    fn validate_endpoint_url_accepts_empty() {
+        assert!(validate_endpoint_url("").is_ok());
+        assert!(validate_endpoint_url("   ").is_ok());
+    }
+
+    #[test]
+    fn validate_endpoint_url_rejects_missing_scheme() {
+        assert!(validate_endpoint_url("api.example.com/v1").is_err());
+    }
+
+    #[test]
+    fn validate_endpoint_url_rejects_other_schemes() {
+        let err = validate_endpoint_url("ftp://example.com").unwrap_err();
+        assert!(err.contains("http or https"));
+    }
+
+    #[test]
+    fn validate_endpoint_url_rejects_spaces() {
+        assert!(validate_endpoint_url("https://exa mple.com").is_err());
+    }
+
     use crate::db::init_db;
     use crate::db::users::create_user;
 
@@ -425,4 +613,279 @@ mod tests {
         .unwrap();
         assert!(cleared.image_models.is_none());
     }
+
+    async fn seed(pool: &SqlitePool, user_id: &str, provider: &str, name: &str) -> UserProvider {
+        upsert_provider(
+            pool,
+            None,
+            user_id,
+            provider,
+            "enc",
+            None,
+            None,
+            false,
+            None,
+            Some(name),
+            None,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_list_providers_by_type_filters() {
+        let (pool, user_id) = setup().await;
+        let first = seed(&pool, &user_id, "openai", "OpenAI 1").await;
+        seed(&pool, &user_id, "anthropic", "Claude").await;
+        let second = seed(&pool, &user_id, "openai", "OpenAI 2").await;
+
+        let openai = list_providers_by_type(&pool, &user_id, "openai")
+            .await
+            .unwrap();
+        let ids: Vec<_> = openai.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec![first.id.as_str(), second.id.as_str()]);
+        assert!(openai.iter().all(|p| p.provider == "openai"));
+    }
+
+    #[tokio::test]
+    async fn test_provider_fallback_set_get_and_clear_on_delete() {
+        let (pool, user_id) = setup().await;
+        let primary = seed(&pool, &user_id, "openai", "Primary").await;
+        let backup = seed(&pool, &user_id, "anthropic", "Backup").await;
+        assert!(
+            get_provider_fallback(&pool, &primary.id, &user_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        assert!(
+            set_provider_fallback(&pool, &user_id, &primary.id, Some(&backup.id))
+                .await
+                .unwrap()
+        );
+        let fallback = get_provider_fallback(&pool, &primary.id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(fallback.id, backup.id);
+
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        assert!(
+            get_provider_fallback(&pool, &primary.id, &other.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        delete_provider_by_id(&pool, &user_id, &backup.id)
+            .await
+            .unwrap();
+        let primary = get_provider_by_id(&pool, &user_id, &primary.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(primary.fallback_provider_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_providers_by_type_empty() {
+        let (pool, user_id) = setup().await;
+        seed(&pool, &user_id, "openai", "OpenAI").await;
+
+        let google = list_providers_by_type(&pool, &user_id, "google")
+            .await
+            .unwrap();
+        assert!(google.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_providers_by_type_excludes_other_users() {
+        let (pool, user_id) = setup().await;
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        seed(&pool, &other.id, "openai", "Theirs").await;
+
+        let openai = list_providers_by_type(&pool, &user_id, "openai")
+            .await
+            .unwrap();
+        assert!(openai.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_providers_with_usage_counts_all_roles() {
+        use crate::db::conversations::create_conversation_with_subagent;
+
+        let (pool, user_id) = setup().await;
+        let chat = seed(&pool, &user_id, "openai", "Chat").await;
+        let image = seed(&pool, &user_id, "google", "Image").await;
+        let unused = seed(&pool, &user_id, "anthropic", "Unused").await;
+
+        // chat provider for both roles, plus the image provider
+        create_conversation_with_subagent(
+            &pool,
+            &user_id,
+            "One",
+            None,
+            Some(&chat.id),
+            Some("gpt-4o"),
+            Some(&chat.id),
+            Some("gpt-4o"),
+            false,
+            Some(&image.id),
+            Some("img"),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        // chat provider only as subagent
+        create_conversation_with_subagent(
+            &pool,
+            &user_id,
+            "Two",
+            None,
+            Some(&image.id),
+            Some("gemini"),
+            Some(&chat.id),
+            Some("gpt-4o"),
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let usage = list_providers_with_usage(&pool, &user_id).await.unwrap();
+        let count = |id: &str| {
+            usage
+                .iter()
+                .find(|u| u.provider.id == id)
+                .unwrap()
+                .conversation_count
+        };
+        assert_eq!(usage.len(), 3);
+        assert_eq!(count(&chat.id), 2);
+        assert_eq!(count(&image.id), 2);
+        assert_eq!(count(&unused.id), 0);
+    }
+
+    #[tokio::test]
+    async fn test_list_providers_with_usage_ignores_other_users_conversations() {
+        use crate::db::conversations::create_conversation;
+
+        let (pool, user_id) = setup().await;
+        let mine = seed(&pool, &user_id, "openai", "Mine").await;
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        create_conversation(
+            &pool,
+            &other.id,
+            "Theirs",
+            None,
+            Some(&mine.id),
+            Some("gpt-4o"),
+            false,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let usage = list_providers_with_usage(&pool, &user_id).await.unwrap();
+        assert_eq!(usage[0].conversation_count, 0);
+    }
+
+    fn names(providers: &[UserProvider]) -> Vec<&str> {
+        providers
+            .iter()
+            .map(|p| p.name.as_deref().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_reorder_providers() {
+        let (pool, user_id) = setup().await;
+        let a = seed(&pool, &user_id, "openai", "A").await;
+        let b = seed(&pool, &user_id, "openai", "B").await;
+        let c = seed(&pool, &user_id, "openai", "C").await;
+
+        let order = vec![c.id.clone(), a.id.clone(), b.id.clone()];
+        assert!(reorder_providers(&pool, &user_id, &order).await.unwrap());
+
+        let listed = list_providers(&pool, &user_id).await.unwrap();
+        assert_eq!(names(&listed), ["C", "A", "B"]);
+        let usage = list_providers_with_usage(&pool, &user_id).await.unwrap();
+        let usage_ids: Vec<&str> = usage.iter().map(|u| u.provider.id.as_str()).collect();
+        assert_eq!(usage_ids, order);
+    }
+
+    #[tokio::test]
+    async fn test_new_provider_is_appended_after_reorder() {
+        let (pool, user_id) = setup().await;
+        let a = seed(&pool, &user_id, "openai", "A").await;
+        let b = seed(&pool, &user_id, "openai", "B").await;
+        assert!(
+            reorder_providers(&pool, &user_id, &[b.id.clone(), a.id.clone()])
+                .await
+                .unwrap()
+        );
+
+        seed(&pool, &user_id, "openai", "New").await;
+        let listed = list_providers(&pool, &user_id).await.unwrap();
+        assert_eq!(names(&listed), ["B", "A", "New"]);
+
+        // Updating an existing provider keeps its position.
+        upsert_provider(
+            &pool,
+            Some(&b.id),
+            &user_id,
+            "openai",
+            "enc2",
+            None,
+            None,
+            false,
+            None,
+            Some("B"),
+            None,
+        )
+        .await
+        .unwrap();
+        let listed = list_providers(&pool, &user_id).await.unwrap();
+        assert_eq!(names(&listed), ["B", "A", "New"]);
+    }
+
+    #[tokio::test]
+    async fn test_reorder_providers_rejects_incomplete_or_foreign_ids() {
+        let (pool, user_id) = setup().await;
+        let a = seed(&pool, &user_id, "openai", "A").await;
+        let b = seed(&pool, &user_id, "openai", "B").await;
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        let foreign = seed(&pool, &other.id, "openai", "Foreign").await;
+
+        for order in [
+            vec![b.id.clone()],
+            vec![b.id.clone(), a.id.clone(), foreign.id.clone()],
+            vec![b.id.clone(), foreign.id.clone()],
+            vec![b.id.clone(), b.id.clone()],
+        ] {
+            assert!(!reorder_providers(&pool, &user_id, &order).await.unwrap());
+        }
+
+        let listed = list_providers(&pool, &user_id).await.unwrap();
+        assert_eq!(names(&listed), ["A", "B"]);
+        let others = list_providers(&pool, &other.id).await.unwrap();
+        assert_eq!(names(&others), ["Foreign"]);
+    }
 }
@@ -9,6 +9,23 @@ pub struct RefreshToken {
     pub token_hash: String,
     pub expires_at: String,
     pub created_at: String,
+    pub used: bool,
+    /// Shared by every token rotated from the same login.
+    pub family_id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// Client details recorded alongside a refresh token.
+#[derive(Debug, Clone, Default)]
+pub struct SessionClient {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// Start a new token family, i.e. a fresh login session.
+fn new_family_id() -> String {
+    uuid::Uuid::new_v4().to_string()
 }
 
 pub async fn create_refresh_token(
@@ -16,18 +33,24 @@ pub async fn create_refresh_token(
     user_id: &str,
     token_hash: &str,
     expires_at: &str,
+    client: &SessionClient,
 ) -> Result<RefreshToken, sqlx::Error> {
     let id = uuid::Uuid::new_v4().to_string();
 
     sqlx::query_as::<_, RefreshToken>(
-        "INSERT INTO refresh_tokens (id, user_id, token_hash, expires_at) \
-         VALUES (?, ?, ?, ?) \
-         RETURNING id, user_id, token_hash, expires_at, created_at",
+        "INSERT INTO refresh_tokens \
+         (id, user_id, token_hash, expires_at, family_id, user_agent, ip_address) \
+         VALUES (?, ?, ?, ?, ?, ?, ?) \
+         RETURNING id, user_id, token_hash, expires_at, created_at, used, family_id, \
+         user_agent, ip_address",
     )
     .bind(&id)
     .bind(user_id)
     .bind(token_hash)
     .bind(expires_at)
+    .bind(new_family_id())
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
     .fetch_one(pool)
     .await
 }
@@ -37,29 +60,35 @@ pub async fn create_refresh_token_in_tx(
     user_id: &str,
     token_hash: &str,
     expires_at: &str,
+    client: &SessionClient,
 ) -> Result<RefreshToken, sqlx::Error> {
     let id = uuid::Uuid::new_v4().to_string();
 
     sqlx::query_as::<_, RefreshToken>(
-        "INSERT INTO refresh_tokens (id, user_id, token_hash, expires_at) \
-         VALUES (?, ?, ?, ?) \
-         RETURNING id, user_id, token_hash, expires_at, created_at",
+        "INSERT INTO refresh_tokens \
+         (id, user_id, token_hash, expires_at, family_id, user_agent, ip_address) \
+         VALUES (?, ?, ?, ?, ?, ?, ?) \
+         RETURNING id, user_id, token_hash, expires_at, created_at, used, family_id, \
+         user_agent, ip_address",
     )
     .bind(&id)
     .bind(user_id)
     .bind(token_hash)
     .bind(expires_at)
+    .bind(new_family_id())
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
     .fetch_one(&mut **tx)
     .await
 }
 
-#[cfg(test)]
 pub async fn get_refresh_token_by_hash(
     pool: &SqlitePool,
     token_hash: &str,
 ) -> Result<Option<RefreshToken>, sqlx::Error> {
     sqlx::query_as::<_, RefreshToken>(
-        "SELECT id, user_id, token_hash, expires_at, created_at \
+        "SELECT id, user_id, token_hash, expires_at, created_at, used, family_id, \
+         user_agent, ip_address \
          FROM refresh_tokens WHERE token_hash = ?",
     )
     .bind(token_hash)
@@ -90,18 +119,99 @@ pub async fn delete_user_refresh_tokens(
     Ok(())
 }
 
-pub async fn delete_refresh_token_by_hash(
+/// Delete every token in the family of the token with `token_hash`, ending
+/// that login session. Returns the number of rows deleted.
+pub async fn delete_family_by_hash(
     pool: &SqlitePool,
     token_hash: &str,
-) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM refresh_tokens WHERE token_hash = ?")
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM refresh_tokens \
+         WHERE family_id = (SELECT family_id FROM refresh_tokens WHERE token_hash = ?)",
+    )
+    .bind(token_hash)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Look up the family of a token that has already been used. Returns `None`
+/// for unknown hashes and for tokens that are still valid.
+pub async fn used_token_family_in_tx(
+    tx: &mut Transaction<'_, Sqlite>,
+    token_hash: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT family_id FROM refresh_tokens WHERE token_hash = ? AND used = 1")
         .bind(token_hash)
-        .execute(pool)
+        .fetch_optional(&mut **tx)
+        .await
+}
+
+/// Delete every token in `family_id`. Returns the number of rows deleted.
+pub async fn delete_family_in_tx(
+    tx: &mut Transaction<'_, Sqlite>,
+    family_id: &str,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM refresh_tokens WHERE family_id = ?")
+        .bind(family_id)
+        .execute(&mut **tx)
         .await?;
 
+    Ok(result.rows_affected())
+}
+
+/// The user's active sessions: one unused, unexpired token per login,
+/// newest first.
+pub async fn list_sessions_for_user(
+    pool: &SqlitePool,
+    user_id: &str,
+) -> Result<Vec<RefreshToken>, sqlx::Error> {
+    sqlx::query_as::<_, RefreshToken>(
+        "SELECT id, user_id, token_hash, expires_at, created_at, used, family_id, \
+         user_agent, ip_address \
+         FROM refresh_tokens \
+         WHERE user_id = ? AND used = 0 AND expires_at > ? \
+         ORDER BY created_at DESC, rowid DESC",
+    )
+    .bind(user_id)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .fetch_all(pool)
+    .await
+}
+
+/// End the session that token `session_id` belongs to, if it is owned by
+/// `user_id`. Returns `false` when there is no such session.
+pub async fn delete_session(
+    pool: &SqlitePool,
+    user_id: &str,
+    session_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM refresh_tokens \
+         WHERE user_id = ? \
+         AND family_id = (SELECT family_id FROM refresh_tokens WHERE id = ? AND user_id = ?)",
+    )
+    .bind(user_id)
+    .bind(session_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
     Ok(result.rows_affected() > 0)
 }
 
+/// Mark every outstanding refresh token of a user as used so none of them can
+/// be exchanged again. Returns the number of tokens invalidated.
+pub async fn invalidate_all_for_user(pool: &SqlitePool, user_id: &str) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("UPDATE refresh_tokens SET used = 1 WHERE user_id = ? AND used = 0")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -119,9 +229,15 @@ mod tests {
     #[tokio::test]
     async fn test_create_refresh_token() {
         let (pool, user_id) = setup().await;
-        let token = create_refresh_token(&pool, &user_id, "hash_abc", "2099-12-31T23:59:59")
-            .await
-            .unwrap();
+        let token = create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_abc",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(token.user_id, user_id);
         assert_eq!(token.token_hash, "hash_abc");
         assert_eq!(token.expires_at, "2099-12-31T23:59:59");
@@ -132,9 +248,15 @@ mod tests {
     #[tokio::test]
     async fn test_get_refresh_token_by_hash() {
         let (pool, user_id) = setup().await;
-        create_refresh_token(&pool, &user_id, "hash_xyz", "2099-12-31T23:59:59")
-            .await
-            .unwrap();
+        create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_xyz",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
         let fetched = get_refresh_token_by_hash(&pool, "hash_xyz").await.unwrap();
         assert!(fetched.is_some());
         assert_eq!(fetched.unwrap().token_hash, "hash_xyz");
@@ -148,9 +270,15 @@ mod tests {
     #[tokio::test]
     async fn test_delete_refresh_token() {
         let (pool, user_id) = setup().await;
-        let token = create_refresh_token(&pool, &user_id, "hash_del", "2099-12-31T23:59:59")
-            .await
-            .unwrap();
+        let token = create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_del",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
         let deleted = delete_refresh_token(&pool, &token.id).await.unwrap();
         assert!(deleted);
         // Should be gone
@@ -161,41 +289,40 @@ mod tests {
         assert!(!deleted_again);
     }
 
-    #[tokio::test]
-    async fn test_delete_refresh_token_by_hash() {
-        let (pool, user_id) = setup().await;
-        create_refresh_token(&pool, &user_id, "hash_bh", "2099-12-31T23:59:59")
-            .await
-            .unwrap();
-        let deleted = delete_refresh_token_by_hash(&pool, "hash_bh")
-            .await
-            .unwrap();
-        assert!(deleted);
-        let fetched = get_refresh_token_by_hash(&pool, "hash_bh").await.unwrap();
-        assert!(fetched.is_none());
-        // Deleting again should return false
-        let deleted_again = delete_refresh_token_by_hash(&pool, "hash_bh")
-            .await
-            .unwrap();
-        assert!(!deleted_again);
-    }
-
     #[tokio::test]
     async fn test_delete_user_refresh_tokens() {
         let (pool, user_id) = setup().await;
-        create_refresh_token(&pool, &user_id, "hash_1", "2099-12-31T23:59:59")
-            .await
-            .unwrap();
-        create_refresh_token(&pool, &user_id, "hash_2", "2099-12-31T23:59:59")
-            .await
-            .unwrap();
+        create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_1",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
+        create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_2",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
         // Create a token for a different user to ensure it is not deleted
         let other = create_user(&pool, "other", "other@example.com", "hash")
             .await
             .unwrap();
-        create_refresh_token(&pool, &other.id, "hash_other", "2099-12-31T23:59:59")
-            .await
-            .unwrap();
+        create_refresh_token(
+            &pool,
+            &other.id,
+            "hash_other",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
 
         delete_user_refresh_tokens(&pool, &user_id).await.unwrap();
 
@@ -219,4 +346,224 @@ mod tests {
                 .is_some()
         );
     }
+
+    #[tokio::test]
+    async fn test_invalidate_all_for_user() {
+        let (pool, user_id) = setup().await;
+        create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_a",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
+        create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_b",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        create_refresh_token(
+            &pool,
+            &other.id,
+            "hash_other",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
+
+        let invalidated = invalidate_all_for_user(&pool, &user_id).await.unwrap();
+        assert_eq!(invalidated, 2);
+
+        for hash in ["hash_a", "hash_b"] {
+            let token = get_refresh_token_by_hash(&pool, hash)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(token.used);
+        }
+        let other_token = get_refresh_token_by_hash(&pool, "hash_other")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!other_token.used);
+
+        // Already-used tokens are not counted again
+        let again = invalidate_all_for_user(&pool, &user_id).await.unwrap();
+        assert_eq!(again, 0);
+    }
+
+    #[tokio::test]
+    async fn test_tokens_from_separate_logins_get_separate_families() {
+        let (pool, user_id) = setup().await;
+        let a = create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_fa",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
+        let b = create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_fb",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
+        assert!(!a.family_id.is_empty());
+        assert_ne!(a.family_id, b.family_id);
+    }
+
+    #[tokio::test]
+    async fn test_delete_family_by_hash() {
+        let (pool, user_id) = setup().await;
+        let first = create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_f1",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
+        create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_other_login",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO refresh_tokens (id, user_id, token_hash, expires_at, family_id) \
+             VALUES ('rotated', ?, 'hash_f2', '2099-12-31T23:59:59', ?)",
+        )
+        .bind(&user_id)
+        .bind(&first.family_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(delete_family_by_hash(&pool, "hash_f2").await.unwrap(), 2);
+        assert!(
+            get_refresh_token_by_hash(&pool, "hash_f1")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            get_refresh_token_by_hash(&pool, "hash_other_login")
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(delete_family_by_hash(&pool, "hash_f2").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_used_token_family_in_tx() {
+        let (pool, user_id) = setup().await;
+        let token = create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_u",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
+
+        let mut tx = pool.begin().await.unwrap();
+        assert!(
+            used_token_family_in_tx(&mut tx, "hash_u")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            used_token_family_in_tx(&mut tx, "missing")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        sqlx::query("UPDATE refresh_tokens SET used = 1 WHERE token_hash = 'hash_u'")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        assert_eq!(
+            used_token_family_in_tx(&mut tx, "hash_u").await.unwrap(),
+            Some(token.family_id.clone())
+        );
+        assert_eq!(
+            delete_family_in_tx(&mut tx, &token.family_id)
+                .await
+                .unwrap(),
+            1
+        );
+        tx.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_for_user_skips_used_and_expired() {
+        let (pool, user_id) = setup().await;
+        let client = SessionClient {
+            user_agent: Some("curl/8.0".into()),
+            ip_address: Some("10.0.0.1".into()),
+        };
+        let active =
+            create_refresh_token(&pool, &user_id, "hash_s1", "2099-12-31T23:59:59", &client)
+                .await
+                .unwrap();
+        create_refresh_token(&pool, &user_id, "hash_s2", "2000-01-01T00:00:00", &client)
+            .await
+            .unwrap();
+        create_refresh_token(&pool, &user_id, "hash_s3", "2099-12-31T23:59:59", &client)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE refresh_tokens SET used = 1 WHERE token_hash = 'hash_s3'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let sessions = list_sessions_for_user(&pool, &user_id).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, active.id);
+        assert_eq!(sessions[0].user_agent.as_deref(), Some("curl/8.0"));
+        assert_eq!(sessions[0].ip_address.as_deref(), Some("10.0.0.1"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_session_checks_owner() {
+        let (pool, user_id) = setup().await;
+        let token = create_refresh_token(
+            &pool,
+            &user_id,
+            "hash_own",
+            "2099-12-31T23:59:59",
+            &SessionClient::default(),
+        )
+        .await
+        .unwrap();
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+
+        assert!(!delete_session(&pool, &other.id, &token.id).await.unwrap());
+        assert!(delete_session(&pool, &user_id, &token.id).await.unwrap());
+        assert!(!delete_session(&pool, &user_id, &token.id).await.unwrap());
+    }
 }
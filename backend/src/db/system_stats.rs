@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use sqlx::prelude::FromRow;
+
+/// Database-wide totals for the admin dashboard. "Today" is the current UTC
+/// day; deleted messages are not counted.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SystemStats {
+    pub users_total: i64,
+    /// Users who signed in or used a conversation in the last 7 days.
+    pub users_active_7d: i64,
+    pub conversations_total: i64,
+    pub conversations_active_today: i64,
+    pub messages_total: i64,
+    pub messages_today: i64,
+    pub db_size_bytes: i64,
+}
+
+pub async fn system_stats(pool: &SqlitePool) -> Result<SystemStats, sqlx::Error> {
+    sqlx::query_as::<_, SystemStats>(
+        "SELECT (SELECT COUNT(*) FROM users) AS users_total,
+                (SELECT COUNT(*) FROM users u
+                 WHERE u.last_login_at >= datetime('now', '-7 days')
+                    OR EXISTS (SELECT 1 FROM conversations c
+                               WHERE c.user_id = u.id
+                                 AND c.updated_at >= datetime('now', '-7 days'))
+                ) AS users_active_7d,
+                (SELECT COUNT(*) FROM conversations) AS conversations_total,
+                (SELECT COUNT(*) FROM conversations WHERE updated_at >= date('now'))
+                    AS conversations_active_today,
+                (SELECT COUNT(*) FROM messages WHERE deleted_at IS NULL) AS messages_total,
+                (SELECT COUNT(*) FROM messages
+                 WHERE deleted_at IS NULL AND created_at >= date('now')) AS messages_today,
+                (SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size())
+                    AS db_size_bytes",
+    )
+    .fetch_one(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+
+    #[tokio::test]
+    async fn counts_users_conversations_and_messages() {
+        let pool = init_db("sqlite::memory:").await;
+        let active = crate::db::users::create_user(&pool, "a", "a@example.com", "hash")
+            .await
+            .unwrap();
+        crate::db::users::create_user(&pool, "b", "b@example.com", "hash")
+            .await
+            .unwrap();
+        let conv = crate::db::conversations::create_conversation(
+            &pool, &active.id, "t", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        crate::db::messages::create_message(&pool, &conv.id, "user", "hi", None, None, None)
+            .await
+            .unwrap();
+
+        let stats = system_stats(&pool).await.unwrap();
+        assert_eq!(stats.users_total, 2);
+        assert_eq!(stats.users_active_7d, 1);
+        assert_eq!(stats.conversations_total, 1);
+        assert_eq!(stats.conversations_active_today, 1);
+        assert_eq!(stats.messages_total, 1);
+        assert_eq!(stats.messages_today, 1);
+        assert!(stats.db_size_bytes > 0);
+    }
+}
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use sqlx::prelude::FromRow;
+
+use crate::config::Config;
+
+/// Messages a single send adds to a conversation: the user message and the
+/// assistant reply it triggers.
+pub const MESSAGES_PER_TURN: usize = 2;
+
+/// A user's own limits; `None` means the config default applies.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserQuota {
+    pub user_id: String,
+    pub max_conversations: Option<i64>,
+    pub max_messages_per_conversation: Option<i64>,
+    pub max_workspace_bytes: Option<i64>,
+    pub updated_at: String,
+}
+
+/// The limits in force for a user, after falling back to config defaults.
+/// `None` means unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EffectiveQuota {
+    pub max_conversations: Option<u64>,
+    pub max_messages_per_conversation: Option<u64>,
+    pub max_workspace_bytes: u64,
+}
+
+impl EffectiveQuota {
+    pub fn resolve(quota: Option<&UserQuota>, config: &Config) -> Self {
+        let own = |f: fn(&UserQuota) -> Option<i64>| quota.and_then(f).map(|v| v.max(0) as u64);
+        Self {
+            max_conversations: own(|q| q.max_conversations).or(config.max_conversations_per_user),
+            max_messages_per_conversation: own(|q| q.max_messages_per_conversation)
+                .or(config.max_messages_per_conversation),
+            max_workspace_bytes: own(|q| q.max_workspace_bytes)
+                .unwrap_or(config.workspace_max_bytes),
+        }
+    }
+
+    /// Whether a user who already has `existing` conversations may create one more.
+    pub fn allows_conversation(&self, existing: i64) -> bool {
+        self.max_conversations
+            .is_none_or(|max| (existing.max(0) as u64) < max)
+    }
+
+    /// Whether `adding` messages fit in a conversation holding `existing`.
+    pub fn allows_messages(&self, existing: i64, adding: usize) -> bool {
+        self.max_messages_per_conversation
+            .is_none_or(|max| (existing.max(0) as u64).saturating_add(adding as u64) <= max)
+    }
+}
+
+pub async fn get_user_quota(
+    pool: &SqlitePool,
+    user_id: &str,
+) -> Result<Option<UserQuota>, sqlx::Error> {
+    sqlx::query_as::<_, UserQuota>(
+        "SELECT user_id, max_conversations, max_messages_per_conversation, \
+         max_workspace_bytes, updated_at \
+         FROM user_quotas WHERE user_id = ?",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Replace a user's limits.
+pub async fn set_user_quota(
+    pool: &SqlitePool,
+    user_id: &str,
+    max_conversations: Option<i64>,
+    max_messages_per_conversation: Option<i64>,
+    max_workspace_bytes: Option<i64>,
+) -> Result<UserQuota, sqlx::Error> {
+    sqlx::query_as::<_, UserQuota>(
+        "INSERT INTO user_quotas \
+         (user_id, max_conversations, max_messages_per_conversation, max_workspace_bytes) \
+         VALUES (?, ?, ?, ?) \
+         ON CONFLICT(user_id) DO UPDATE SET \
+         max_conversations = excluded.max_conversations, \
+         max_messages_per_conversation = excluded.max_messages_per_conversation, \
+         max_workspace_bytes = excluded.max_workspace_bytes, \
+         updated_at = datetime('now') \
+         RETURNING user_id, max_conversations, max_messages_per_conversation, \
+         max_workspace_bytes, updated_at",
+    )
+    .bind(user_id)
+    .bind(max_conversations)
+    .bind(max_messages_per_conversation)
+    .bind(max_workspace_bytes)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn effective_quota(
+    pool: &SqlitePool,
+    config: &Config,
+    user_id: &str,
+) -> Result<EffectiveQuota, sqlx::Error> {
+    let quota = get_user_quota(pool, user_id).await?;
+    Ok(EffectiveQuota::resolve(quota.as_ref(), config))
+}
+
+/// The user's conversation limit, if they have already reached it.
+pub async fn conversation_limit_reached(
+    pool: &SqlitePool,
+    config: &Config,
+    user_id: &str,
+) -> Result<Option<u64>, sqlx::Error> {
+    let quota = effective_quota(pool, config, user_id).await?;
+    if quota.max_conversations.is_none() {
+        return Ok(None);
+    }
+    let existing = super::conversations::count_user_conversations(pool, user_id).await?;
+    Ok(quota
+        .max_conversations
+        .filter(|_| !quota.allows_conversation(existing)))
+}
+
+/// The user's per-conversation message limit, if adding `adding` messages
+/// to `conversation_id` would exceed it.
+pub async fn message_limit_reached(
+    pool: &SqlitePool,
+    config: &Config,
+    user_id: &str,
+    conversation_id: &str,
+    adding: usize,
+) -> Result<Option<u64>, sqlx::Error> {
+    let quota = effective_quota(pool, config, user_id).await?;
+    if quota.max_messages_per_conversation.is_none() {
+        return Ok(None);
+    }
+    let existing = super::messages::count_messages(pool, conversation_id).await?;
+    Ok(quota
+        .max_messages_per_conversation
+        .filter(|_| !quota.allows_messages(existing, adding)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+
+    #[tokio::test]
+    async fn set_quota_replaces_limits_and_checks_counts() {
+        let pool = init_db("sqlite::memory:").await;
+        let user = crate::db::users::create_user(&pool, "u", "u@example.com", "hash")
+            .await
+            .unwrap();
+        assert!(get_user_quota(&pool, &user.id).await.unwrap().is_none());
+
+        set_user_quota(&pool, &user.id, Some(5), None, Some(1024))
+            .await
+            .unwrap();
+        let quota = set_user_quota(&pool, &user.id, Some(3), None, None)
+            .await
+            .unwrap();
+        assert_eq!(quota.max_conversations, Some(3));
+        assert_eq!(quota.max_workspace_bytes, None);
+
+        let resolved = EffectiveQuota {
+            max_conversations: Some(3),
+            max_messages_per_conversation: Some(1000),
+            max_workspace_bytes: 4096,
+        };
+        assert!(resolved.allows_conversation(2));
+        assert!(!resolved.allows_conversation(3));
+        assert!(resolved.allows_messages(999, 1));
+        assert!(!resolved.allows_messages(999, 2));
+    }
+}
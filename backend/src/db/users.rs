@@ -9,10 +9,13 @@ pub struct User {
     pub email: String,
     pub password_hash: String,
     pub is_admin: bool,
+    pub email_verified: bool,
+    pub is_active: bool,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Usernames are stored lower-case; see also [`get_user_by_username`].
 #[cfg_attr(not(test), allow(dead_code))]
 pub async fn create_user(
     pool: &SqlitePool,
@@ -25,10 +28,10 @@ pub async fn create_user(
     sqlx::query_as::<_, User>(
         "INSERT INTO users (id, username, email, password_hash)
          VALUES (?, ?, ?, ?)
-         RETURNING id, username, email, password_hash, is_admin, created_at, updated_at",
+         RETURNING id, username, email, password_hash, is_admin, email_verified, is_active, created_at, updated_at",
     )
     .bind(&id)
-    .bind(username)
+    .bind(username.to_lowercase())
     .bind(email)
     .bind(password_hash)
     .fetch_one(pool)
@@ -46,19 +49,44 @@ pub async fn create_user_in_tx(
     sqlx::query_as::<_, User>(
         "INSERT INTO users (id, username, email, password_hash)
          VALUES (?, ?, ?, ?)
-         RETURNING id, username, email, password_hash, is_admin, created_at, updated_at",
+         RETURNING id, username, email, password_hash, is_admin, email_verified, is_active, created_at, updated_at",
     )
     .bind(&id)
-    .bind(username)
+    .bind(username.to_lowercase())
     .bind(email)
     .bind(password_hash)
     .fetch_one(&mut **tx)
     .await
 }
 
+/// Create an account on an admin's behalf: the email counts as verified and
+/// the admin flag is set in the same insert.
+pub async fn admin_create_user_in_tx(
+    tx: &mut Transaction<'_, Sqlite>,
+    username: &str,
+    email: &str,
+    password_hash: &str,
+    is_admin: bool,
+) -> Result<User, sqlx::Error> {
+    let id = uuid::Uuid::new_v4().to_string();
+
+    sqlx::query_as::<_, User>(
+        "INSERT INTO users (id, username, email, password_hash, is_admin, email_verified)
+         VALUES (?, ?, ?, ?, ?, 1)
+         RETURNING id, username, email, password_hash, is_admin, email_verified, is_active, created_at, updated_at",
+    )
+    .bind(&id)
+    .bind(username.to_lowercase())
+    .bind(email)
+    .bind(password_hash)
+    .bind(is_admin)
+    .fetch_one(&mut **tx)
+    .await
+}
+
 pub async fn get_user_by_id(pool: &SqlitePool, id: &str) -> Result<Option<User>, sqlx::Error> {
     sqlx::query_as::<_, User>(
-        "SELECT id, username, email, password_hash, is_admin, created_at, updated_at
+        "SELECT id, username, email, password_hash, is_admin, email_verified, is_active, created_at, updated_at
          FROM users WHERE id = ?",
     )
     .bind(id)
@@ -66,13 +94,14 @@ pub async fn get_user_by_id(pool: &SqlitePool, id: &str) -> Result<Option<User>,
     .await
 }
 
+/// Look up a user by username, ignoring case.
 pub async fn get_user_by_username(
     pool: &SqlitePool,
     username: &str,
 ) -> Result<Option<User>, sqlx::Error> {
     sqlx::query_as::<_, User>(
-        "SELECT id, username, email, password_hash, is_admin, created_at, updated_at
-         FROM users WHERE username = ?",
+        "SELECT id, username, email, password_hash, is_admin, email_verified, is_active, created_at, updated_at
+         FROM users WHERE username = ? COLLATE NOCASE",
     )
     .bind(username)
     .fetch_optional(pool)
@@ -84,7 +113,7 @@ pub async fn get_user_by_email(
     email: &str,
 ) -> Result<Option<User>, sqlx::Error> {
     sqlx::query_as::<_, User>(
-        "SELECT id, username, email, password_hash, is_admin, created_at, updated_at
+        "SELECT id, username, email, password_hash, is_admin, email_verified, is_active, created_at, updated_at
          FROM users WHERE email = ?",
     )
     .bind(email)
@@ -92,6 +121,175 @@ pub async fn get_user_by_email(
     .await
 }
 
+pub async fn update_password_hash(
+    pool: &SqlitePool,
+    id: &str,
+    password_hash: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE users SET password_hash = ?, updated_at = datetime('now') WHERE id = ?",
+    )
+    .bind(password_hash)
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn mark_email_verified(pool: &SqlitePool, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE users SET email_verified = 1, updated_at = datetime('now') WHERE id = ?",
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn set_user_active(
+    pool: &SqlitePool,
+    id: &str,
+    is_active: bool,
+) -> Result<bool, sqlx::Error> {
+    let result =
+        sqlx::query("UPDATE users SET is_active = ?, updated_at = datetime('now') WHERE id = ?")
+            .bind(is_active)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Totals across every conversation of a user.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserStats {
+    pub conversation_count: i64,
+    pub message_count: i64,
+    pub user_message_count: i64,
+    pub assistant_message_count: i64,
+    pub total_tokens: i64,
+}
+
+/// Conversation and message totals for `user_id`, ignoring deleted messages.
+pub async fn user_stats(pool: &SqlitePool, user_id: &str) -> Result<UserStats, sqlx::Error> {
+    sqlx::query_as::<_, UserStats>(
+        "SELECT (SELECT COUNT(*) FROM conversations WHERE user_id = ?) AS conversation_count,
+                COUNT(m.id) AS message_count,
+                COALESCE(SUM(m.role = 'user'), 0) AS user_message_count,
+                COALESCE(SUM(m.role = 'assistant'), 0) AS assistant_message_count,
+                COALESCE(SUM(m.token_count), 0) AS total_tokens
+         FROM messages m
+         JOIN conversations c ON c.id = m.conversation_id
+         WHERE c.user_id = ? AND m.deleted_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn record_login(pool: &SqlitePool, id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET last_login_at = datetime('now') WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// A user as listed to admins.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AdminUserSummary {
+    pub id: String,
+    pub username: String,
+    pub email: String,
+    pub is_admin: bool,
+    pub email_verified: bool,
+    pub is_active: bool,
+    pub created_at: String,
+    pub last_login_at: Option<String>,
+    pub conversation_count: i64,
+}
+
+/// A page of users, oldest first, plus the total number of users.
+pub async fn list_users(
+    pool: &SqlitePool,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<AdminUserSummary>, i64), sqlx::Error> {
+    let users = sqlx::query_as::<_, AdminUserSummary>(
+        "SELECT u.id, u.username, u.email, u.is_admin, u.email_verified, u.is_active,
+                u.created_at, u.last_login_at,
+                (SELECT COUNT(*) FROM conversations c WHERE c.user_id = u.id) AS conversation_count
+         FROM users u
+         ORDER BY u.created_at ASC, u.rowid ASC
+         LIMIT ? OFFSET ?",
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        .fetch_one(pool)
+        .await?;
+    Ok((users, total))
+}
+
+/// Update the fields an admin can change; `None` leaves a field as is.
+pub async fn admin_update_user(
+    pool: &SqlitePool,
+    id: &str,
+    email: Option<&str>,
+    is_admin: Option<bool>,
+    is_active: Option<bool>,
+) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        "UPDATE users
+         SET email = COALESCE(?, email),
+             is_admin = COALESCE(?, is_admin),
+             is_active = COALESCE(?, is_active),
+             updated_at = datetime('now')
+         WHERE id = ?
+         RETURNING id, username, email, password_hash, is_admin, email_verified, is_active, created_at, updated_at",
+    )
+    .bind(email)
+    .bind(is_admin)
+    .bind(is_active)
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Delete a user together with their conversations (and, through the
+/// cascades, messages and everything else they own) in one transaction.
+/// Returns the deleted conversation ids, or `None` if there was no such user.
+pub async fn admin_delete_user(
+    pool: &SqlitePool,
+    id: &str,
+) -> Result<Option<Vec<String>>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let conversation_ids: Vec<String> =
+        sqlx::query_scalar("SELECT id FROM conversations WHERE user_id = ?")
+            .bind(id)
+            .fetch_all(&mut *tx)
+            .await?;
+    sqlx::query("DELETE FROM conversations WHERE user_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    let result = sqlx::query("DELETE FROM users WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+    tx.commit().await?;
+    Ok(Some(conversation_ids))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,11 +309,90 @@ mod tests {
         assert_eq!(user.email, "alice@example.com");
         assert_eq!(user.password_hash, "hash123");
         assert!(!user.is_admin);
+        assert!(!user.email_verified);
+        assert!(user.is_active);
         assert!(!user.id.is_empty());
         assert!(!user.created_at.is_empty());
         assert!(!user.updated_at.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_mark_email_verified() {
+        let pool = setup().await;
+        let user = create_user(&pool, "erin", "erin@example.com", "hash")
+            .await
+            .unwrap();
+        assert!(mark_email_verified(&pool, &user.id).await.unwrap());
+        let fetched = get_user_by_id(&pool, &user.id).await.unwrap().unwrap();
+        assert!(fetched.email_verified);
+        assert!(!mark_email_verified(&pool, "missing").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_set_user_active() {
+        let pool = setup().await;
+        let user = create_user(&pool, "frank", "frank@example.com", "hash")
+            .await
+            .unwrap();
+        assert!(set_user_active(&pool, &user.id, false).await.unwrap());
+        let fetched = get_user_by_id(&pool, &user.id).await.unwrap().unwrap();
+        assert!(!fetched.is_active);
+        assert!(!set_user_active(&pool, "missing", false).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_admin_update_user_changes_only_given_fields() {
+        let pool = setup().await;
+        let user = create_user(&pool, "gina", "gina@example.com", "hash")
+            .await
+            .unwrap();
+        let updated = admin_update_user(&pool, &user.id, None, Some(true), None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(updated.is_admin);
+        assert!(updated.is_active);
+        assert_eq!(updated.email, "gina@example.com");
+
+        let updated = admin_update_user(&pool, &user.id, Some("g@example.com"), None, Some(false))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(updated.is_admin);
+        assert!(!updated.is_active);
+        assert_eq!(updated.email, "g@example.com");
+
+        assert!(
+            admin_update_user(&pool, "missing", None, Some(true), None)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_users_pages_and_records_logins() {
+        let pool = setup().await;
+        let first = create_user(&pool, "hank", "hank@example.com", "hash")
+            .await
+            .unwrap();
+        create_user(&pool, "ivy", "ivy@example.com", "hash")
+            .await
+            .unwrap();
+        record_login(&pool, &first.id).await.unwrap();
+
+        let (page, total) = list_users(&pool, 1, 0).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, first.id);
+        assert!(page[0].last_login_at.is_some());
+        assert_eq!(page[0].conversation_count, 0);
+
+        let (page, _) = list_users(&pool, 1, 1).await.unwrap();
+        assert_eq!(page[0].username, "ivy");
+        assert!(page[0].last_login_at.is_none());
+    }
+
     #[tokio::test]
     async fn test_get_user_by_id() {
         let pool = setup().await;
@@ -141,6 +418,24 @@ mod tests {
         assert_eq!(fetched.unwrap().username, "charlie");
     }
 
+    #[tokio::test]
+    async fn test_username_is_stored_lowercase_and_matched_case_insensitively() {
+        let pool = setup().await;
+        let user = create_user(&pool, "Frank", "frank@example.com", "hash")
+            .await
+            .unwrap();
+        assert_eq!(user.username, "frank");
+        for name in ["frank", "FRANK", "fRaNk"] {
+            let fetched = get_user_by_username(&pool, name).await.unwrap().unwrap();
+            assert_eq!(fetched.id, user.id);
+        }
+        assert!(
+            create_user(&pool, "FRANK", "other@example.com", "hash")
+                .await
+                .is_err()
+        );
+    }
+
     #[tokio::test]
     async fn test_get_user_by_email() {
         let pool = setup().await;
@@ -174,4 +469,65 @@ mod tests {
                 .is_none()
         );
     }
+
+    #[tokio::test]
+    async fn test_update_password_hash() {
+        let pool = setup().await;
+        let user = create_user(&pool, "dave", "dave@example.com", "old_hash")
+            .await
+            .unwrap();
+        assert!(
+            update_password_hash(&pool, &user.id, "new_hash")
+                .await
+                .unwrap()
+        );
+        let fetched = get_user_by_id(&pool, &user.id).await.unwrap().unwrap();
+        assert_eq!(fetched.password_hash, "new_hash");
+
+        assert!(!update_password_hash(&pool, "missing", "x").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_user_stats() {
+        use crate::db::conversations::create_conversation;
+        use crate::db::messages::create_message;
+
+        let pool = setup().await;
+        let user = create_user(&pool, "stats", "stats@example.com", "hash")
+            .await
+            .unwrap();
+        let empty = user_stats(&pool, &user.id).await.unwrap();
+        assert_eq!(empty.conversation_count, 0);
+        assert_eq!(empty.message_count, 0);
+        assert_eq!(empty.total_tokens, 0);
+
+        for title in ["One", "Two"] {
+            let conv = create_conversation(
+                &pool, &user.id, title, None, None, None, false, None, None, None,
+            )
+            .await
+            .unwrap();
+            create_message(&pool, &conv.id, "user", "q", None, None, Some(2))
+                .await
+                .unwrap();
+            create_message(&pool, &conv.id, "assistant", "a", None, None, Some(5))
+                .await
+                .unwrap();
+        }
+        let other = create_user(&pool, "other", "other@example.com", "hash")
+            .await
+            .unwrap();
+        create_conversation(
+            &pool, &other.id, "Theirs", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+
+        let stats = user_stats(&pool, &user.id).await.unwrap();
+        assert_eq!(stats.conversation_count, 2);
+        assert_eq!(stats.message_count, 4);
+        assert_eq!(stats.user_message_count, 2);
+        assert_eq!(stats.assistant_message_count, 2);
+        assert_eq!(stats.total_tokens, 14);
+    }
 }
@@ -0,0 +1,65 @@
+//! In-memory tar archives used to push files into containers; Docker's
+//! archive endpoint only accepts tar streams.
+
+/// Build a tar archive containing one regular file at `path` (relative,
+/// `/`-separated) with the given contents.
+///
+/// Fails if the path cannot be stored in a tar header.
+pub fn single_file_tar(path: &str, contents: &[u8], mtime: u64) -> std::io::Result<Vec<u8>> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_entry_type(tar::EntryType::Regular);
+
+    let mut builder = tar::Builder::new(Vec::new());
+    builder.append_data(&mut header, path, contents)?;
+    builder.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn entries(tar: &[u8]) -> Vec<(String, u64, Vec<u8>)> {
+        tar::Archive::new(tar)
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let path = entry.path().unwrap().to_string_lossy().into_owned();
+                let mtime = entry.header().mtime().unwrap();
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents).unwrap();
+                (path, mtime, contents)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_single_file_tar_round_trips() {
+        let tar = single_file_tar("dir/hello.txt", b"hello world", 1_700_000_000).unwrap();
+        assert_eq!(
+            entries(&tar),
+            [(
+                "dir/hello.txt".to_string(),
+                1_700_000_000,
+                b"hello world".to_vec()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_single_file_tar_long_path() {
+        let path = format!("{}/file.txt", "d".repeat(200));
+        let tar = single_file_tar(&path, b"x", 0).unwrap();
+        assert_eq!(entries(&tar)[0].0, path);
+    }
+
+    #[test]
+    fn test_single_file_tar_rejects_unrepresentable_paths() {
+        assert!(single_file_tar("", b"x", 0).is_err());
+        assert!(single_file_tar("../escape", b"x", 0).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use bollard::Docker;
+use bollard::container::{
+    Config, CreateContainerOptions, LogOutput, LogsOptions, RemoveContainerOptions,
+    RenameContainerOptions, StartContainerOptions, StopContainerOptions, UploadToContainerOptions,
+};
+use bollard::errors::Error;
+use bollard::exec::{CreateExecOptions, StartExecResults};
+use futures_util::future::BoxFuture;
+use futures_util::stream::BoxStream;
+
+/// Demultiplexed stdout/stderr chunks from a container or exec.
+pub type OutputStream = BoxStream<'static, Result<LogOutput, Error>>;
+
+/// The subset of the Docker API used by [`super::manager::DockerManager`].
+///
+/// Implemented for [`bollard::Docker`]; tests substitute a mock so container
+/// lifecycle logic can run without a daemon.
+pub trait DockerClient: Send + Sync {
+    /// Create a container named `name` and return its ID.
+    fn create_container<'a>(
+        &'a self,
+        name: &'a str,
+        config: Config<String>,
+    ) -> BoxFuture<'a, Result<String, Error>>;
+
+    fn start_container<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<(), Error>>;
+
+    fn stop_container<'a>(
+        &'a self,
+        id: &'a str,
+        timeout_secs: i64,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Force-remove a container by ID or name.
+    fn remove_container<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<(), Error>>;
+
+    fn rename_container<'a>(
+        &'a self,
+        id: &'a str,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Extract the tar archive `tar` at `path` inside the container.
+    fn upload_to_container<'a>(
+        &'a self,
+        id: &'a str,
+        path: &'a str,
+        tar: Vec<u8>,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Run `cmd` in the container, returning the exec ID and its attached
+    /// output.
+    fn exec<'a>(
+        &'a self,
+        id: &'a str,
+        cmd: Vec<String>,
+    ) -> BoxFuture<'a, Result<(String, OutputStream), Error>>;
+
+    fn exec_exit_code<'a>(&'a self, exec_id: &'a str) -> BoxFuture<'a, Result<Option<i64>, Error>>;
+
+    /// Follow the container's stdout and stderr from the start of its log.
+    /// The stream ends when the container exits.
+    fn logs(&self, id: &str) -> OutputStream;
+}
+
+impl DockerClient for Docker {
+    fn create_container<'a>(
+        &'a self,
+        name: &'a str,
+        config: Config<String>,
+    ) -> BoxFuture<'a, Result<String, Error>> {
+        Box::pin(async move {
+            let options = CreateContainerOptions {
+                name,
+                platform: None,
+            };
+            Ok(Docker::create_container(self, Some(options), config)
+                .await?
+                .id)
+        })
+    }
+
+    fn start_container<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(Docker::start_container(
+            self,
+            id,
+            None::<StartContainerOptions<String>>,
+        ))
+    }
+
+    fn stop_container<'a>(
+        &'a self,
+        id: &'a str,
+        timeout_secs: i64,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(Docker::stop_container(
+            self,
+            id,
+            Some(StopContainerOptions { t: timeout_secs }),
+        ))
+    }
+
+    fn remove_container<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(Docker::remove_container(
+            self,
+            id,
+            Some(RemoveContainerOptions {
+                force: true,
+                ..Default::default()
+            }),
+        ))
+    }
+
+    fn rename_container<'a>(
+        &'a self,
+        id: &'a str,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(Docker::rename_container(
+            self,
+            id,
+            RenameContainerOptions { name },
+        ))
+    }
+
+    fn upload_to_container<'a>(
+        &'a self,
+        id: &'a str,
+        path: &'a str,
+        tar: Vec<u8>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(Docker::upload_to_container(
+            self,
+            id,
+            Some(UploadToContainerOptions {
+                path,
+                no_overwrite_dir_non_dir: "true",
+            }),
+            tar.into(),
+        ))
+    }
+
+    fn exec<'a>(
+        &'a self,
+        id: &'a str,
+        cmd: Vec<String>,
+    ) -> BoxFuture<'a, Result<(String, OutputStream), Error>> {
+        Box::pin(async move {
+            let exec = self
+                .create_exec(
+                    id,
+                    CreateExecOptions {
+                        cmd: Some(cmd),
+                        attach_stdout: Some(true),
+                        attach_stderr: Some(true),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            let output: OutputStream = match self.start_exec(&exec.id, None).await? {
+                StartExecResults::Attached { output, .. } => output,
+                StartExecResults::Detached => Box::pin(futures_util::stream::empty()),
+            };
+            Ok((exec.id, output))
+        })
+    }
+
+    fn exec_exit_code<'a>(&'a self, exec_id: &'a str) -> BoxFuture<'a, Result<Option<i64>, Error>> {
+        Box::pin(async move { Ok(self.inspect_exec(exec_id).await?.exit_code) })
+    }
+
+    fn logs(&self, id: &str) -> OutputStream {
+        Box::pin(Docker::logs(
+            self,
+            id,
+            Some(LogsOptions::<String> {
+                follow: true,
+                stdout: true,
+                stderr: true,
+                ..Default::default()
+            }),
+        ))
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Records container configs and answers every call successfully,
+    /// except for the first `create_failures` calls to `create_container`.
+    /// `logs` replays `log_lines` as stdout.
+    #[derive(Default)]
+    pub struct MockDockerClient {
+        pub created: Mutex<Vec<(String, Config<String>)>>,
+        pub removed: Mutex<Vec<String>>,
+        /// `(id, new name)` per rename.
+        pub renamed: Mutex<Vec<(String, String)>>,
+        /// `(id, path)` per upload.
+        pub uploaded: Mutex<Vec<(String, String)>>,
+        pub log_lines: Vec<String>,
+        pub create_failures: Mutex<u32>,
+    }
+
+    impl MockDockerClient {
+        pub fn failing(times: u32) -> Self {
+            Self {
+                create_failures: Mutex::new(times),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl DockerClient for MockDockerClient {
+        fn create_container<'a>(
+            &'a self,
+            name: &'a str,
+            config: Config<String>,
+        ) -> BoxFuture<'a, Result<String, Error>> {
+            Box::pin(async move {
+                {
+                    let mut failures = self.create_failures.lock().unwrap();
+                    if *failures > 0 {
+                        *failures -= 1;
+                        return Err(Error::DockerResponseServerError {
+                            status_code: 503,
+                            message: "daemon unavailable".into(),
+                        });
+                    }
+                }
+                let mut created = self.created.lock().unwrap();
+                created.push((name.to_string(), config));
+                Ok(format!("mock-{}", created.len()))
+            })
+        }
+
+        fn start_container<'a>(&'a self, _id: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn stop_container<'a>(
+            &'a self,
+            _id: &'a str,
+            _timeout_secs: i64,
+        ) -> BoxFuture<'a, Result<(), Error>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn remove_container<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+            Box::pin(async move {
+                self.removed.lock().unwrap().push(id.to_string());
+                Ok(())
+            })
+        }
+
+        fn rename_container<'a>(
+            &'a self,
+            id: &'a str,
+            name: &'a str,
+        ) -> BoxFuture<'a, Result<(), Error>> {
+            Box::pin(async move {
+                self.renamed
+                    .lock()
+                    .unwrap()
+                    .push((id.to_string(), name.to_string()));
+                Ok(())
+            })
+        }
+
+        fn upload_to_container<'a>(
+            &'a self,
+            id: &'a str,
+            path: &'a str,
+            _tar: Vec<u8>,
+        ) -> BoxFuture<'a, Result<(), Error>> {
+            Box::pin(async move {
+                self.uploaded
+                    .lock()
+                    .unwrap()
+                    .push((id.to_string(), path.to_string()));
+                Ok(())
+            })
+        }
+
+        fn exec<'a>(
+            &'a self,
+            _id: &'a str,
+            _cmd: Vec<String>,
+        ) -> BoxFuture<'a, Result<(String, OutputStream), Error>> {
+            Box::pin(async {
+                let output: OutputStream = Box::pin(futures_util::stream::empty());
+                Ok(("exec".to_string(), output))
+            })
+        }
+
+        fn exec_exit_code<'a>(
+            &'a self,
+            _exec_id: &'a str,
+        ) -> BoxFuture<'a, Result<Option<i64>, Error>> {
+            Box::pin(async { Ok(Some(0)) })
+        }
+
+        fn logs(&self, _id: &str) -> OutputStream {
+            let lines: Vec<_> = self
+                .log_lines
+                .iter()
+                .map(|line| {
+                    Ok(LogOutput::StdOut {
+                        message: line.clone().into(),
+                    })
+                })
+                .collect();
+            Box::pin(futures_util::stream::iter(lines))
+        }
+    }
+}
@@ -1,18 +1,23 @@
 use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use bollard::Docker;
-use bollard::container::{
-    Config, CreateContainerOptions, NetworkingConfig, RemoveContainerOptions,
-    StartContainerOptions, StopContainerOptions,
-};
+use bollard::container::{Config, NetworkingConfig};
 use bollard::models::{EndpointSettings, HostConfig};
 use dashmap::DashMap;
+use futures_util::{Stream, StreamExt};
+use sqlx::SqlitePool;
 use tokio::sync::Mutex;
 
+use super::archive;
+use super::client::DockerClient;
+use super::pool::{ContainerPool, WarmContainer};
 use super::registry::ContainerRegistry;
 use crate::auth;
 use crate::config;
+use crate::db;
 use crate::ws::WsState;
 
 #[derive(Debug, thiserror::Error)]
@@ -21,26 +26,126 @@ pub enum DockerError {
     TokenCreation(#[from] jsonwebtoken::errors::Error),
     #[error("Docker API error: {0}")]
     Bollard(#[from] bollard::errors::Error),
+    #[error("command timed out after {0}s")]
+    Timeout(u64),
+    #[error("container is not running")]
+    NotRunning,
     #[error("{0}")]
     Other(String),
 }
 
+/// Memory limit used when `container_memory_bytes` is not configured.
+const DEFAULT_MEMORY_BYTES: i64 = 512 * 1024 * 1024;
+/// CPU limit used when `container_cpu_quota` is not configured (1 CPU).
+const DEFAULT_NANO_CPUS: i64 = 1_000_000_000;
+/// CFS period that `container_cpu_quota` is measured against.
+const CPU_PERIOD_MICROS: i64 = 100_000;
+
+/// Output kept per stream from [`DockerManager::exec_in_container`].
+const MAX_EXEC_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// A running conversation container, as listed by
+/// [`DockerManager::list_active_containers`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ActiveContainer {
+    pub conversation_id: String,
+    pub container_id: String,
+    /// RFC 3339 timestamp of when the container was registered.
+    pub started_at: String,
+    pub user_id: String,
+}
+
+/// Outcome of a command run with [`DockerManager::exec_in_container`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ExecResult {
+    pub exit_code: i64,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Whether `command` may be run in a container under `allowlist`.
+///
+/// Only the program is checked, and it must match an entry exactly, so
+/// `/bin/df` or `sh -c df` are rejected even when `df` is allowed.
+pub fn exec_command_allowed(command: &[String], allowlist: &[String]) -> bool {
+    command
+        .first()
+        .is_some_and(|program| allowlist.iter().any(|allowed| allowed == program))
+}
+
+/// Directory inside warm containers where the claim file is written.
+const CLAIM_DIR: &str = "/tmp";
+/// Claim file a warm container's agent polls for its container token.
+const CLAIM_FILE_NAME: &str = "claim-token";
+
+/// Whether `path` is missing or an empty directory.
+async fn workspace_is_empty(path: &str) -> bool {
+    match tokio::fs::read_dir(path).await {
+        Ok(mut entries) => matches!(entries.next_entry().await, Ok(None)),
+        Err(e) => e.kind() == std::io::ErrorKind::NotFound,
+    }
+}
+
+/// Mount point of the conversation workspace inside every container.
+pub const CONTAINER_WORKSPACE: &str = "/workspace";
+
+/// Normalize a destination path inside the container workspace.
+///
+/// Accepts either an absolute path under `/workspace` or a path relative to
+/// it, and returns the path relative to `/workspace`. Returns `None` for
+/// paths that escape the workspace or do not name a file.
+pub fn workspace_relative_path(container_path: &str) -> Option<String> {
+    let path = Path::new(container_path);
+    let relative = if path.is_absolute() {
+        path.strip_prefix(CONTAINER_WORKSPACE).ok()?
+    } else {
+        path
+    };
+
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
 pub struct DockerManager {
-    docker: Docker,
+    docker: Arc<dyn DockerClient>,
     registry: Arc<ContainerRegistry>,
     config: config::Config,
     /// Per-conversation lock to prevent TOCTOU races in start_container.
     start_locks: DashMap<String, Arc<Mutex<()>>>,
+    /// Wait before the first start retry; doubles on each further retry.
+    start_retry_delay: Duration,
+    pool: ContainerPool,
 }
 
 impl DockerManager {
     pub fn new(config: config::Config, registry: Arc<ContainerRegistry>) -> Self {
         let docker = Docker::connect_with_local_defaults().expect("Failed to connect to Docker");
+        Self::with_client(Arc::new(docker), config, registry)
+    }
+
+    /// Create a DockerManager backed by an arbitrary [`DockerClient`].
+    pub fn with_client(
+        docker: Arc<dyn DockerClient>,
+        config: config::Config,
+        registry: Arc<ContainerRegistry>,
+    ) -> Self {
         Self {
             docker,
             registry,
-            config,
             start_locks: DashMap::new(),
+            start_retry_delay: Duration::from_secs(1),
+            pool: ContainerPool::new(config.container_pool_size),
+            config,
         }
     }
 
@@ -50,11 +155,189 @@ impl DockerManager {
         let docker = Docker::connect_with_local_defaults()
             .or_else(|_| Docker::connect_with_defaults())
             .expect("Failed to create Docker client for test");
-        Self {
-            docker,
-            registry,
-            config,
-            start_locks: DashMap::new(),
+        Self::with_client(Arc::new(docker), config, registry)
+    }
+
+    /// Host settings for a new container. A configured CPU quota replaces
+    /// the default `nano_cpus` limit, since Docker rejects both together.
+    fn host_config(&self, workspace_host_path: &str) -> HostConfig {
+        let (cpu_period, cpu_quota, nano_cpus) = match self.config.container_cpu_quota {
+            Some(quota) => (Some(CPU_PERIOD_MICROS), Some(quota), None),
+            None => (None, None, Some(DEFAULT_NANO_CPUS)),
+        };
+        HostConfig {
+            binds: Some(vec![format!("{workspace_host_path}:/workspace")]),
+            extra_hosts: if self.config.docker_network.is_none() {
+                Some(vec!["host.docker.internal:host-gateway".to_string()])
+            } else {
+                None
+            },
+            memory: Some(
+                self.config
+                    .container_memory_bytes
+                    .unwrap_or(DEFAULT_MEMORY_BYTES),
+            ),
+            cpu_period,
+            cpu_quota,
+            nano_cpus,
+            ..Default::default()
+        }
+    }
+
+    fn backend_ws_url(&self) -> String {
+        if self.config.docker_network.is_some() {
+            format!("ws://backend:{}/internal/ws", self.config.internal_ws_port)
+        } else {
+            format!(
+                "ws://host.docker.internal:{}/internal/ws",
+                self.config.internal_ws_port
+            )
+        }
+    }
+
+    /// Path of the local data directory `local_path` as seen by the Docker
+    /// host; `data_relative` is the same path relative to `data/`.
+    async fn workspace_host_path(&self, local_path: &str, data_relative: &str) -> String {
+        if let Some(ref host_dir) = self.config.host_data_dir {
+            format!("{host_dir}/{data_relative}")
+        } else {
+            tokio::fs::canonicalize(local_path)
+                .await
+                .unwrap_or_else(|_| PathBuf::from(local_path))
+                .to_string_lossy()
+                .to_string()
+        }
+    }
+
+    /// Agent container config with `workspace_host_path` mounted at
+    /// `/workspace` and `env` added to the backend URL.
+    fn container_config(&self, workspace_host_path: &str, env: Vec<String>) -> Config<String> {
+        let mut full_env = vec![format!("BACKEND_WS_URL={}", self.backend_ws_url())];
+        full_env.extend(env);
+        Config {
+            image: Some(self.config.container_image.clone()),
+            env: Some(full_env),
+            host_config: Some(self.host_config(workspace_host_path)),
+            networking_config: self.config.docker_network.as_ref().map(|network| {
+                NetworkingConfig {
+                    endpoints_config: HashMap::from([(
+                        network.clone(),
+                        EndpointSettings::default(),
+                    )]),
+                }
+            }),
+            working_dir: Some("/workspace".to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn pool(&self) -> &ContainerPool {
+        &self.pool
+    }
+
+    /// Create and start warm containers until the pool is full. Stops at the
+    /// first failure; the refill task tries again later.
+    pub async fn fill_pool(&self) {
+        while self.pool.idle_count().await < self.pool.pre_warm_count() {
+            match self.create_warm_container().await {
+                Ok(warm) => self.pool.put(warm).await,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to create warm container");
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn create_warm_container(&self) -> Result<WarmContainer, DockerError> {
+        let pool_id = uuid::Uuid::new_v4().to_string();
+        let workspace_dir = format!("data/pool/{pool_id}");
+        tokio::fs::create_dir_all(&workspace_dir)
+            .await
+            .map_err(|e| DockerError::Other(format!("failed to create {workspace_dir}: {e}")))?;
+        let workspace_host_path = self
+            .workspace_host_path(&workspace_dir, &format!("pool/{pool_id}"))
+            .await;
+
+        let name = format!("claude-chat-agent-pool-{}", &pool_id[..8]);
+        let container_config = self.container_config(
+            &workspace_host_path,
+            vec![format!(
+                "CONTAINER_CLAIM_FILE={CLAIM_DIR}/{CLAIM_FILE_NAME}"
+            )],
+        );
+        match self.create_and_start(&name, container_config).await {
+            Ok(container_id) => Ok(WarmContainer {
+                container_id,
+                workspace_dir: PathBuf::from(workspace_dir),
+            }),
+            Err(e) => {
+                let _ = tokio::fs::remove_dir_all(&workspace_dir).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Hand a warm container to a conversation whose workspace is still
+    /// empty: move the warm workspace into place, rename the container, and
+    /// write the container token to its claim file. Returns `None` (after
+    /// discarding the warm container on failure) when the caller should fall
+    /// back to a cold start.
+    async fn claim_warm_container(
+        &self,
+        container_name: &str,
+        container_token: &str,
+        data_path: &str,
+    ) -> Option<String> {
+        if !workspace_is_empty(data_path).await {
+            return None;
+        }
+        let warm = self.pool.take().await?;
+
+        let claim = async {
+            let _ = tokio::fs::remove_dir(data_path).await;
+            if let Some(parent) = Path::new(data_path).parent() {
+                tokio::fs::create_dir_all(parent).await.ok();
+            }
+            tokio::fs::rename(&warm.workspace_dir, data_path)
+                .await
+                .map_err(|e| DockerError::Other(format!("failed to move workspace: {e}")))?;
+
+            let _ = self.docker.remove_container(container_name).await;
+            self.docker
+                .rename_container(&warm.container_id, container_name)
+                .await?;
+
+            let mtime = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            // The agent waits for the trailing newline, so it never reads a
+            // partially extracted token.
+            let tar = archive::single_file_tar(
+                CLAIM_FILE_NAME,
+                format!("{container_token}\n").as_bytes(),
+                mtime,
+            )
+            .map_err(|e| DockerError::Other(format!("failed to build claim archive: {e}")))?;
+            self.docker
+                .upload_to_container(&warm.container_id, CLAIM_DIR, tar)
+                .await?;
+            Ok::<_, DockerError>(())
+        };
+
+        match claim.await {
+            Ok(()) => Some(warm.container_id),
+            Err(e) => {
+                tracing::warn!(
+                    container_id = %warm.container_id,
+                    error = %e,
+                    "Failed to claim warm container, starting a new one"
+                );
+                let _ = self.docker.remove_container(&warm.container_id).await;
+                let _ = tokio::fs::remove_dir_all(&warm.workspace_dir).await;
+                None
+            }
         }
     }
 
@@ -88,94 +371,70 @@ impl DockerManager {
             self.config.container_token_ttl_secs,
         )?;
 
-        let backend_ws_url = if self.config.docker_network.is_some() {
-            format!("ws://backend:{}/internal/ws", self.config.internal_ws_port)
-        } else {
-            format!(
-                "ws://host.docker.internal:{}/internal/ws",
-                self.config.internal_ws_port
-            )
-        };
-
         let container_data_path = format!("data/conversations/{conversation_id}");
-        tokio::fs::create_dir_all(&container_data_path).await.ok();
-
-        let workspace_host_path = if let Some(ref host_dir) = self.config.host_data_dir {
-            format!("{}/conversations/{}", host_dir, conversation_id)
-        } else {
-            tokio::fs::canonicalize(&container_data_path)
-                .await
-                .unwrap_or_else(|_| std::path::PathBuf::from(&container_data_path))
-                .to_string_lossy()
-                .to_string()
-        };
-
         let container_name = format!(
             "claude-chat-agent-{}",
             conversation_id.get(..8).unwrap_or(conversation_id)
         );
 
-        // Remove any existing container with the same name (e.g. from a previous crash)
-        let _ = self
-            .docker
-            .remove_container(
-                &container_name,
-                Some(RemoveContainerOptions {
-                    force: true,
-                    ..Default::default()
-                }),
+        if let Some(container_id) = self
+            .claim_warm_container(&container_name, &container_token, &container_data_path)
+            .await
+        {
+            self.registry
+                .register(conversation_id, &container_id, user_id)
+                .await;
+            tracing::info!(
+                "Claimed warm container {} for conversation {}",
+                container_id,
+                conversation_id
+            );
+            return Ok(container_id);
+        }
+
+        tokio::fs::create_dir_all(&container_data_path).await.ok();
+        let workspace_host_path = self
+            .workspace_host_path(
+                &container_data_path,
+                &format!("conversations/{conversation_id}"),
             )
             .await;
 
-        let container_config = Config {
-            image: Some(self.config.container_image.clone()),
-            env: Some(vec![
-                format!("BACKEND_WS_URL={backend_ws_url}"),
+        let container_config = self.container_config(
+            &workspace_host_path,
+            vec![
                 format!("CONTAINER_TOKEN={container_token}"),
                 format!("CONVERSATION_ID={conversation_id}"),
-            ]),
-            host_config: Some(HostConfig {
-                binds: Some(vec![format!("{}:/workspace", workspace_host_path)]),
-                extra_hosts: if self.config.docker_network.is_none() {
-                    Some(vec!["host.docker.internal:host-gateway".to_string()])
-                } else {
-                    None
-                },
-                memory: Some(512 * 1024 * 1024), // 512MB
-                nano_cpus: Some(1_000_000_000),  // 1 CPU
-                ..Default::default()
-            }),
-            networking_config: self.config.docker_network.as_ref().map(|network| {
-                NetworkingConfig {
-                    endpoints_config: HashMap::from([(
-                        network.clone(),
-                        EndpointSettings::default(),
-                    )]),
+            ],
+        );
+
+        // The daemon may be briefly unavailable (restart, slow image pull), so
+        // retry with exponential backoff before giving up.
+        let max_retries = self.config.container_start_max_retries;
+        let mut delay = self.start_retry_delay;
+        let mut attempt = 0;
+        let container_id = loop {
+            attempt += 1;
+            match self
+                .create_and_start(&container_name, container_config.clone())
+                .await
+            {
+                Ok(id) => break id,
+                Err(e) if attempt <= max_retries => {
+                    tracing::warn!(
+                        conversation_id = %conversation_id,
+                        attempt,
+                        error = %e,
+                        "Container start failed, retrying in {:?}",
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
                 }
-            }),
-            working_dir: Some("/workspace".to_string()),
-            ..Default::default()
+                Err(e) => return Err(e),
+            }
         };
 
-        // Create container
-        let create_result = self
-            .docker
-            .create_container(
-                Some(CreateContainerOptions {
-                    name: &container_name,
-                    platform: None,
-                }),
-                container_config,
-            )
-            .await?;
-
-        let container_id = create_result.id;
-
-        // Start container
-        self.docker
-            .start_container(&container_id, None::<StartContainerOptions<String>>)
-            .await?;
-
         // Register in registry
         self.registry
             .register(conversation_id, &container_id, user_id)
@@ -190,31 +449,34 @@ impl DockerManager {
         Ok(container_id)
     }
 
+    /// One attempt at creating and starting a container named `name`.
+    async fn create_and_start(
+        &self,
+        name: &str,
+        container_config: Config<String>,
+    ) -> Result<String, DockerError> {
+        // Remove any existing container with the same name (e.g. from a
+        // previous crash or a failed attempt)
+        let _ = self.docker.remove_container(name).await;
+
+        let container_id = self.docker.create_container(name, container_config).await?;
+        self.docker.start_container(&container_id).await?;
+        Ok(container_id)
+    }
+
     /// Stop and remove a container for a conversation.
     pub async fn stop_container(&self, conversation_id: &str) -> Result<(), DockerError> {
         let info = self
             .registry
             .unregister(conversation_id)
             .await
-            .ok_or_else(|| DockerError::Other("Container not found in registry".into()))?;
+            .ok_or(DockerError::NotRunning)?;
 
         // Stop container
-        let _ = self
-            .docker
-            .stop_container(&info.container_id, Some(StopContainerOptions { t: 10 }))
-            .await;
+        let _ = self.docker.stop_container(&info.container_id, 10).await;
 
         // Remove container
-        let _ = self
-            .docker
-            .remove_container(
-                &info.container_id,
-                Some(RemoveContainerOptions {
-                    force: true,
-                    ..Default::default()
-                }),
-            )
-            .await;
+        let _ = self.docker.remove_container(&info.container_id).await;
 
         tracing::info!(
             "Stopped container {} for conversation {}",
@@ -225,17 +487,174 @@ impl DockerManager {
         Ok(())
     }
 
+    /// Write `contents` to a file in the running container for a conversation.
+    ///
+    /// `container_path` must resolve inside `/workspace`; missing parent
+    /// directories are created by Docker when the archive is extracted.
+    pub async fn copy_to_container(
+        &self,
+        conversation_id: &str,
+        contents: &[u8],
+        container_path: &str,
+    ) -> Result<(), DockerError> {
+        let relative = workspace_relative_path(container_path).ok_or_else(|| {
+            DockerError::Other(format!("invalid container path: {container_path}"))
+        })?;
+        let info = self
+            .registry
+            .get(conversation_id)
+            .await
+            .ok_or(DockerError::NotRunning)?;
+
+        let mtime = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let tar = archive::single_file_tar(&relative, contents, mtime)
+            .map_err(|e| DockerError::Other(format!("invalid container path: {e}")))?;
+
+        self.docker
+            .upload_to_container(&info.container_id, CONTAINER_WORKSPACE, tar)
+            .await?;
+        self.registry.touch(conversation_id).await;
+        Ok(())
+    }
+
+    /// Run a command in the running container for a conversation and collect
+    /// its output. The allowlist is the caller's responsibility; see
+    /// [`exec_command_allowed`].
+    pub async fn exec_in_container(
+        &self,
+        conversation_id: &str,
+        command: &[&str],
+        timeout_secs: u64,
+    ) -> Result<ExecResult, DockerError> {
+        let info = self
+            .registry
+            .get(conversation_id)
+            .await
+            .ok_or(DockerError::NotRunning)?;
+
+        let cmd = command.iter().map(|s| s.to_string()).collect();
+        let (exec_id, mut output) = self.docker.exec(&info.container_id, cmd).await?;
+
+        let collect = async {
+            let mut stdout = Vec::new();
+            let mut stderr = Vec::new();
+            while let Some(chunk) = output.next().await {
+                let (buf, message) = match chunk? {
+                    bollard::container::LogOutput::StdOut { message } => (&mut stdout, message),
+                    bollard::container::LogOutput::StdErr { message } => (&mut stderr, message),
+                    _ => continue,
+                };
+                let room = MAX_EXEC_OUTPUT_BYTES.saturating_sub(buf.len());
+                buf.extend_from_slice(&message[..message.len().min(room)]);
+            }
+            Ok::<_, DockerError>((stdout, stderr))
+        };
+        let (stdout, stderr) =
+            tokio::time::timeout(std::time::Duration::from_secs(timeout_secs), collect)
+                .await
+                .map_err(|_| DockerError::Timeout(timeout_secs))??;
+
+        let exit_code = self.docker.exec_exit_code(&exec_id).await?.unwrap_or(-1);
+        self.registry.touch(conversation_id).await;
+        Ok(ExecResult {
+            exit_code,
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        })
+    }
+
+    /// Follow the stdout and stderr of a conversation's running container as
+    /// text chunks. The stream ends when the container exits.
+    pub async fn stream_container_logs(
+        &self,
+        conversation_id: &str,
+    ) -> Result<impl Stream<Item = Result<String, DockerError>> + Send + use<>, DockerError> {
+        let info = self
+            .registry
+            .get(conversation_id)
+            .await
+            .ok_or(DockerError::NotRunning)?;
+
+        Ok(self.docker.logs(&info.container_id).map(|chunk| {
+            chunk
+                .map(|output| String::from_utf8_lossy(&output.into_bytes()).into_owned())
+                .map_err(DockerError::from)
+        }))
+    }
+
+    /// Registry entry for a conversation's container, if one has been
+    /// started and not yet stopped.
+    pub async fn inspect_container(
+        &self,
+        conversation_id: &str,
+    ) -> Option<super::registry::ContainerInfo> {
+        self.registry.get(conversation_id).await
+    }
+
     /// Refresh the last-activity timestamp for a conversation's container.
     pub async fn touch_activity(&self, conversation_id: &str) {
         self.registry.touch(conversation_id).await;
     }
 
-    /// Stop idle containers that have exceeded the timeout.
-    pub async fn cleanup_idle_containers(&self, ws_state: &WsState) {
-        let idle = self
-            .registry
-            .get_idle_containers(self.config.container_idle_timeout_secs)
-            .await;
+    /// Push updated configuration values (e.g. rotated API keys) to a running
+    /// container without restarting it.
+    ///
+    /// Docker cannot change the environment of a running container, so the
+    /// values are delivered as an `update_config` message over the container's
+    /// WebSocket connection.
+    pub async fn update_container_env(
+        &self,
+        ws_state: &WsState,
+        conversation_id: &str,
+        env_vars: HashMap<String, String>,
+    ) -> Result<(), DockerError> {
+        let mut msg: serde_json::Map<String, serde_json::Value> = env_vars
+            .into_iter()
+            .map(|(k, v)| (k, serde_json::Value::String(v)))
+            .collect();
+        msg.insert("type".into(), "update_config".into());
+
+        if !ws_state
+            .send_to_container(conversation_id, &serde_json::Value::Object(msg).to_string())
+            .await
+        {
+            return Err(DockerError::NotRunning);
+        }
+        self.registry.touch(conversation_id).await;
+        Ok(())
+    }
+
+    /// Stop idle containers that have exceeded their conversation's idle
+    /// timeout, or the global one when the conversation has no override.
+    pub async fn cleanup_idle_containers(&self, ws_state: &WsState, pool: &SqlitePool) {
+        let mut idle = Vec::new();
+        for info in self.registry.list_all().await {
+            let override_secs = match db::conversations::get_conversation(
+                pool,
+                &info.conversation_id,
+                &info.user_id,
+            )
+            .await
+            {
+                Ok(conv) => conv.and_then(|c| c.container_idle_timeout_secs),
+                Err(e) => {
+                    tracing::warn!(
+                        conversation_id = %info.conversation_id,
+                        error = %e,
+                        "Failed to load idle timeout override"
+                    );
+                    None
+                }
+            };
+            let timeout =
+                effective_idle_timeout(override_secs, self.config.container_idle_timeout_secs);
+            if timeout.is_some_and(|t| info.last_activity.elapsed() > t) {
+                idle.push(info);
+            }
+        }
 
         if idle.is_empty() {
             return;
@@ -258,31 +677,58 @@ impl DockerManager {
         let futs = idle.into_iter().map(|info| {
             let docker = self.docker.clone();
             async move {
-                let _ = docker
-                    .stop_container(&info.container_id, Some(StopContainerOptions { t: 10 }))
-                    .await;
-                let _ = docker
-                    .remove_container(
-                        &info.container_id,
-                        Some(RemoveContainerOptions {
-                            force: true,
-                            ..Default::default()
-                        }),
-                    )
-                    .await;
+                let _ = docker.stop_container(&info.container_id, 10).await;
+                let _ = docker.remove_container(&info.container_id).await;
             }
         });
         futures_util::future::join_all(futs).await;
     }
 
-    /// List all running containers.
-    #[allow(dead_code)]
-    pub async fn list_containers(&self) -> Vec<super::registry::ContainerInfo> {
-        self.registry.list_all().await
+    /// List all running containers, oldest first.
+    pub async fn list_active_containers(&self) -> Vec<ActiveContainer> {
+        let mut containers = self.registry.list_all().await;
+        containers.sort_by(|a, b| {
+            a.started_at
+                .cmp(&b.started_at)
+                .then_with(|| a.conversation_id.cmp(&b.conversation_id))
+        });
+        let now = chrono::Utc::now();
+        containers
+            .into_iter()
+            .map(|info| {
+                let uptime = chrono::Duration::from_std(info.started_at.elapsed())
+                    .unwrap_or_else(|_| chrono::Duration::zero());
+                ActiveContainer {
+                    conversation_id: info.conversation_id,
+                    container_id: info.container_id,
+                    started_at: (now - uptime).to_rfc3339(),
+                    user_id: info.user_id,
+                }
+            })
+            .collect()
+    }
+
+    /// Stop a conversation's container regardless of activity, dropping its
+    /// WebSocket connection first so nothing is sent to it while it stops.
+    pub async fn force_stop(
+        &self,
+        ws_state: &WsState,
+        conversation_id: &str,
+    ) -> Result<(), DockerError> {
+        if self.registry.get(conversation_id).await.is_none() {
+            return Err(DockerError::NotRunning);
+        }
+        ws_state.remove_container(conversation_id).await;
+        self.stop_container(conversation_id).await
     }
 
     /// Stop and remove all running containers (used during graceful shutdown).
     pub async fn shutdown(&self) {
+        for warm in self.pool.drain().await {
+            let _ = self.docker.remove_container(&warm.container_id).await;
+            let _ = tokio::fs::remove_dir_all(&warm.workspace_dir).await;
+        }
+
         let containers = self.registry.list_all().await;
         if containers.is_empty() {
             return;
@@ -295,13 +741,28 @@ impl DockerManager {
     }
 }
 
+/// Idle timeout for a conversation's container: its override when set,
+/// otherwise `default_secs`. `None` means it is never stopped for being idle.
+pub fn effective_idle_timeout(override_secs: Option<i64>, default_secs: u64) -> Option<Duration> {
+    match override_secs {
+        Some(0) => None,
+        Some(secs) if secs > 0 => Some(Duration::from_secs(secs as u64)),
+        _ => Some(Duration::from_secs(default_secs)),
+    }
+}
+
 /// Spawn a background task that periodically cleans up idle containers.
-pub fn spawn_idle_cleanup(manager: Arc<DockerManager>, ws_state: Arc<WsState>, interval_secs: u64) {
+pub fn spawn_idle_cleanup(
+    manager: Arc<DockerManager>,
+    ws_state: Arc<WsState>,
+    pool: SqlitePool,
+    interval_secs: u64,
+) {
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
         loop {
             interval.tick().await;
-            manager.cleanup_idle_containers(&ws_state).await;
+            manager.cleanup_idle_containers(&ws_state, &pool).await;
         }
     });
 }
@@ -309,6 +770,277 @@ pub fn spawn_idle_cleanup(manager: Arc<DockerManager>, ws_state: Arc<WsState>, i
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::docker::client::mock::MockDockerClient;
+
+    #[test]
+    fn test_workspace_relative_path_accepts_workspace_paths() {
+        assert_eq!(
+            workspace_relative_path("/workspace/data/in.csv").as_deref(),
+            Some("data/in.csv")
+        );
+        assert_eq!(
+            workspace_relative_path("notes/./a.txt").as_deref(),
+            Some("notes/a.txt")
+        );
+    }
+
+    #[test]
+    fn test_workspace_relative_path_rejects_escapes() {
+        assert!(workspace_relative_path("/etc/passwd").is_none());
+        assert!(workspace_relative_path("/workspace/../etc/passwd").is_none());
+        assert!(workspace_relative_path("../secret").is_none());
+        assert!(workspace_relative_path("/workspace-other/file").is_none());
+        assert!(workspace_relative_path("/workspace").is_none());
+        assert!(workspace_relative_path("").is_none());
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_exec_command_allowed_matches_program() {
+        let allowlist = strings(&["df", "ls"]);
+        assert!(exec_command_allowed(&strings(&["df", "-h"]), &allowlist));
+        assert!(exec_command_allowed(&strings(&["ls"]), &allowlist));
+    }
+
+    #[test]
+    fn test_exec_command_allowed_rejects_others() {
+        let allowlist = strings(&["df", "ls"]);
+        assert!(!exec_command_allowed(&[], &allowlist));
+        assert!(!exec_command_allowed(
+            &strings(&["rm", "-rf", "/"]),
+            &allowlist
+        ));
+        assert!(!exec_command_allowed(&strings(&["/bin/df"]), &allowlist));
+        assert!(!exec_command_allowed(
+            &strings(&["sh", "-c", "df"]),
+            &allowlist
+        ));
+        assert!(!exec_command_allowed(&strings(&["DF"]), &allowlist));
+        assert!(!exec_command_allowed(&strings(&["df"]), &[]));
+    }
+
+    fn mock_manager(config: config::Config) -> (Arc<MockDockerClient>, DockerManager) {
+        let docker = Arc::new(MockDockerClient::default());
+        let manager = DockerManager::with_client(docker.clone(), config, ContainerRegistry::new());
+        (docker, manager)
+    }
+
+    fn created_host_config(docker: &MockDockerClient) -> HostConfig {
+        let created = docker.created.lock().unwrap();
+        created.last().unwrap().1.host_config.clone().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_start_container_uses_default_limits() {
+        let mut config = config::Config::from_env();
+        config.container_cpu_quota = None;
+        config.container_memory_bytes = None;
+        let (docker, manager) = mock_manager(config);
+
+        let id = manager
+            .start_container("conv-limits-1", "user1")
+            .await
+            .unwrap();
+        assert_eq!(id, "mock-1");
+        let host = created_host_config(&docker);
+        assert_eq!(host.memory, Some(DEFAULT_MEMORY_BYTES));
+        assert_eq!(host.nano_cpus, Some(DEFAULT_NANO_CPUS));
+        assert!(host.cpu_quota.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_start_container_claims_warm_container() {
+        let mut config = config::Config::from_env();
+        config.container_pool_size = 1;
+        let (docker, manager) = mock_manager(config);
+
+        manager.fill_pool().await;
+        assert_eq!(manager.pool().idle_count().await, 1);
+        let (_, warm_config) = docker.created.lock().unwrap()[0].clone();
+        let warm_env = warm_config.env.unwrap();
+        assert!(
+            warm_env
+                .iter()
+                .any(|e| e.starts_with("CONTAINER_CLAIM_FILE="))
+        );
+        assert!(!warm_env.iter().any(|e| e.starts_with("CONTAINER_TOKEN=")));
+
+        let conv_id = uuid::Uuid::new_v4().to_string();
+        let id = manager.start_container(&conv_id, "user1").await.unwrap();
+        assert_eq!(id, "mock-1");
+        assert_eq!(docker.created.lock().unwrap().len(), 1);
+        assert_eq!(
+            docker.renamed.lock().unwrap()[0],
+            (
+                "mock-1".to_string(),
+                format!("claude-chat-agent-{}", &conv_id[..8])
+            )
+        );
+        assert_eq!(
+            docker.uploaded.lock().unwrap()[0],
+            ("mock-1".to_string(), CLAIM_DIR.to_string())
+        );
+        assert!(Path::new(&format!("data/conversations/{conv_id}")).is_dir());
+        assert_eq!(
+            manager
+                .inspect_container(&conv_id)
+                .await
+                .unwrap()
+                .container_id,
+            "mock-1"
+        );
+
+        manager.fill_pool().await;
+        assert_eq!(manager.pool().idle_count().await, 1);
+        assert_eq!(docker.created.lock().unwrap().len(), 2);
+        manager.shutdown().await;
+        assert_eq!(manager.pool().idle_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_start_container_skips_pool_for_used_workspace() {
+        let mut config = config::Config::from_env();
+        config.container_pool_size = 1;
+        let (docker, manager) = mock_manager(config);
+        manager.fill_pool().await;
+
+        let conv_id = uuid::Uuid::new_v4().to_string();
+        let workspace = format!("data/conversations/{conv_id}");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::write(format!("{workspace}/notes.txt"), "keep me").unwrap();
+
+        let id = manager.start_container(&conv_id, "user1").await.unwrap();
+        assert_eq!(id, "mock-2");
+        assert!(docker.renamed.lock().unwrap().is_empty());
+        assert_eq!(manager.pool().idle_count().await, 1);
+        manager.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_start_container_retries_until_success() {
+        let mut config = config::Config::from_env();
+        config.container_start_max_retries = 3;
+        let docker = Arc::new(MockDockerClient::failing(2));
+        let mut manager =
+            DockerManager::with_client(docker.clone(), config, ContainerRegistry::new());
+        manager.start_retry_delay = Duration::from_millis(1);
+
+        let id = manager
+            .start_container("conv-retry-1", "user1")
+            .await
+            .unwrap();
+        assert_eq!(id, "mock-1");
+        assert_eq!(*docker.create_failures.lock().unwrap(), 0);
+        // The stale name is removed before each of the three attempts.
+        assert_eq!(docker.removed.lock().unwrap().len(), 3);
+        assert!(manager.inspect_container("conv-retry-1").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_start_container_gives_up_after_max_retries() {
+        let mut config = config::Config::from_env();
+        config.container_start_max_retries = 1;
+        let docker = Arc::new(MockDockerClient::failing(2));
+        let mut manager =
+            DockerManager::with_client(docker.clone(), config, ContainerRegistry::new());
+        manager.start_retry_delay = Duration::from_millis(1);
+
+        let err = manager
+            .start_container("conv-retry-2", "user1")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DockerError::Bollard(_)));
+        assert!(docker.created.lock().unwrap().is_empty());
+        assert!(manager.inspect_container("conv-retry-2").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_start_container_applies_configured_limits() {
+        let mut config = config::Config::from_env();
+        config.container_cpu_quota = Some(50_000);
+        config.container_memory_bytes = Some(256 * 1024 * 1024);
+        let (docker, manager) = mock_manager(config);
+
+        manager
+            .start_container("conv-limits-2", "user1")
+            .await
+            .unwrap();
+        let host = created_host_config(&docker);
+        assert_eq!(host.memory, Some(256 * 1024 * 1024));
+        assert_eq!(host.cpu_quota, Some(50_000));
+        assert_eq!(host.cpu_period, Some(CPU_PERIOD_MICROS));
+        assert!(host.nano_cpus.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_container_logs_yields_lines() {
+        let docker = Arc::new(MockDockerClient {
+            log_lines: strings(&["starting\n", "ready\n"]),
+            ..Default::default()
+        });
+        let registry = ContainerRegistry::new();
+        let manager =
+            DockerManager::with_client(docker, config::Config::from_env(), registry.clone());
+
+        assert!(matches!(
+            manager.stream_container_logs("conv1").await.err(),
+            Some(DockerError::NotRunning)
+        ));
+
+        registry.register("conv1", "c1", "user1").await;
+        let lines: Vec<String> = manager
+            .stream_container_logs("conv1")
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(lines, strings(&["starting\n", "ready\n"]));
+    }
+
+    #[tokio::test]
+    async fn test_exec_in_container_requires_running_container() {
+        let registry = ContainerRegistry::new();
+        let config = config::Config::from_env();
+        let manager = DockerManager::new_for_test(config, registry);
+
+        let err = manager
+            .exec_in_container("conv1", &["df", "-h"], 5)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DockerError::NotRunning));
+    }
+
+    #[tokio::test]
+    async fn test_copy_to_container_requires_running_container() {
+        let registry = ContainerRegistry::new();
+        let config = config::Config::from_env();
+        let manager = DockerManager::new_for_test(config, registry);
+
+        let err = manager
+            .copy_to_container("conv1", b"data", "/workspace/a.txt")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DockerError::NotRunning));
+    }
+
+    #[tokio::test]
+    async fn test_copy_to_container_uploads_into_workspace() {
+        let (docker, manager) = mock_manager(config::Config::from_env());
+        manager.registry.register("conv1", "c1", "user1").await;
+
+        manager
+            .copy_to_container("conv1", b"data", "/workspace/dir/a.txt")
+            .await
+            .unwrap();
+        assert_eq!(
+            docker.uploaded.lock().unwrap()[0],
+            ("c1".to_string(), CONTAINER_WORKSPACE.to_string())
+        );
+    }
 
     #[tokio::test]
     async fn test_touch_activity_updates_registry() {
@@ -326,6 +1058,21 @@ mod tests {
         assert!(after > before);
     }
 
+    #[tokio::test]
+    async fn test_inspect_container_reads_registry() {
+        let registry = ContainerRegistry::new();
+        let manager = DockerManager::new_for_test(config::Config::from_env(), registry.clone());
+        assert!(manager.inspect_container("conv1").await.is_none());
+
+        registry.register("conv1", "c1", "user1").await;
+        let info = manager.inspect_container("conv1").await.unwrap();
+        assert_eq!(info.container_id, "c1");
+        assert!(info.started_at <= info.last_activity);
+
+        manager.stop_container("conv1").await.unwrap();
+        assert!(manager.inspect_container("conv1").await.is_none());
+    }
+
     #[tokio::test]
     async fn test_touch_activity_nonexistent_is_noop() {
         let registry = ContainerRegistry::new();
@@ -348,14 +1095,69 @@ mod tests {
         let mut config = config::Config::from_env();
         config.container_idle_timeout_secs = 0;
         let manager = DockerManager::new_for_test(config, registry.clone());
+        let pool = crate::db::init_db("sqlite::memory:").await;
 
-        manager.cleanup_idle_containers(&ws_state).await;
+        manager.cleanup_idle_containers(&ws_state, &pool).await;
 
         // Both registry and WsState should be cleaned up
         assert!(registry.get("conv1").await.is_none());
         assert!(!ws_state.send_to_container("conv1", "ping").await);
     }
 
+    #[tokio::test]
+    async fn test_update_container_env_sends_update_config() {
+        let registry = ContainerRegistry::new();
+        let ws_state = WsState::new();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(crate::ws::WS_CHANNEL_CAPACITY);
+        ws_state.add_container("conv1", tx).await;
+
+        let config = config::Config::from_env();
+        let manager = DockerManager::new_for_test(config, registry);
+
+        let env = HashMap::from([("api_key".to_string(), "sk-new".to_string())]);
+        manager
+            .update_container_env(&ws_state, "conv1", env)
+            .await
+            .unwrap();
+
+        let msg: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+        assert_eq!(msg["type"], "update_config");
+        assert_eq!(msg["api_key"], "sk-new");
+    }
+
+    #[tokio::test]
+    async fn test_update_container_env_cannot_override_type() {
+        let registry = ContainerRegistry::new();
+        let ws_state = WsState::new();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(crate::ws::WS_CHANNEL_CAPACITY);
+        ws_state.add_container("conv1", tx).await;
+
+        let config = config::Config::from_env();
+        let manager = DockerManager::new_for_test(config, registry);
+
+        let env = HashMap::from([("type".to_string(), "init".to_string())]);
+        manager
+            .update_container_env(&ws_state, "conv1", env)
+            .await
+            .unwrap();
+
+        let msg: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+        assert_eq!(msg["type"], "update_config");
+    }
+
+    #[tokio::test]
+    async fn test_update_container_env_without_connection_errors() {
+        let registry = ContainerRegistry::new();
+        let ws_state = WsState::new();
+        let config = config::Config::from_env();
+        let manager = DockerManager::new_for_test(config, registry);
+
+        let result = manager
+            .update_container_env(&ws_state, "missing", HashMap::new())
+            .await;
+        assert!(matches!(result, Err(DockerError::NotRunning)));
+    }
+
     #[tokio::test]
     async fn test_touch_activity_prevents_idle_cleanup() {
         let registry = ContainerRegistry::new();
@@ -369,11 +1171,65 @@ mod tests {
         config.container_idle_timeout_secs = 999999;
         let manager = DockerManager::new_for_test(config, registry.clone());
 
+        let pool = crate::db::init_db("sqlite::memory:").await;
+
         manager.touch_activity("conv1").await;
-        manager.cleanup_idle_containers(&ws_state).await;
+        manager.cleanup_idle_containers(&ws_state, &pool).await;
 
         // Container should still be registered (not idle)
         assert!(registry.get("conv1").await.is_some());
         assert!(ws_state.send_to_container("conv1", "ping").await);
     }
+
+    #[test]
+    fn test_effective_idle_timeout() {
+        assert_eq!(
+            effective_idle_timeout(None, 600),
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(
+            effective_idle_timeout(Some(60), 600),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(effective_idle_timeout(Some(0), 600), None);
+        assert_eq!(
+            effective_idle_timeout(Some(-5), 600),
+            Some(Duration::from_secs(600))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_idle_respects_conversation_override() {
+        let pool = crate::db::init_db("sqlite::memory:").await;
+        let user = crate::db::users::create_user(&pool, "idle", "idle@example.com", "hash")
+            .await
+            .unwrap();
+        let mut conv_ids = Vec::new();
+        for timeout in [None, Some(0), Some(999_999)] {
+            let conv = crate::db::conversations::create_conversation_with_subagent(
+                &pool, &user.id, "Chat", None, None, None, None, None, false, None, None, None,
+                None, timeout,
+            )
+            .await
+            .unwrap();
+            conv_ids.push(conv.id);
+        }
+
+        let registry = ContainerRegistry::new();
+        let ws_state = WsState::new();
+        for (i, conv_id) in conv_ids.iter().enumerate() {
+            registry.register(conv_id, &format!("c{i}"), &user.id).await;
+        }
+
+        // Global timeout of 0 makes everything without an override idle.
+        let mut config = config::Config::from_env();
+        config.container_idle_timeout_secs = 0;
+        let manager = DockerManager::new_for_test(config, registry.clone());
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        manager.cleanup_idle_containers(&ws_state, &pool).await;
+
+        assert!(registry.get(&conv_ids[0]).await.is_none());
+        assert!(registry.get(&conv_ids[1]).await.is_some());
+        assert!(registry.get(&conv_ids[2]).await.is_some());
+    }
 }
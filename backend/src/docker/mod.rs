@@ -1,2 +1,5 @@
+pub mod archive;
+pub mod client;
 pub mod manager;
+pub mod pool;
 pub mod registry;
@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Mutex, Notify};
+
+use super::manager::DockerManager;
+
+/// A started agent container that is not yet bound to a conversation.
+///
+/// The agent waits for a container token in its claim file; its workspace
+/// is an empty directory that is moved into place when it is claimed.
+#[derive(Debug, Clone)]
+pub struct WarmContainer {
+    pub container_id: String,
+    /// Backend-side path of the bind-mounted workspace directory.
+    pub workspace_dir: PathBuf,
+}
+
+/// Pre-warmed containers handed to new conversations to skip cold starts.
+pub struct ContainerPool {
+    pre_warm_count: usize,
+    idle: Mutex<VecDeque<WarmContainer>>,
+    refill: Notify,
+}
+
+impl ContainerPool {
+    pub fn new(pre_warm_count: usize) -> Self {
+        Self {
+            pre_warm_count,
+            idle: Mutex::new(VecDeque::new()),
+            refill: Notify::new(),
+        }
+    }
+
+    pub fn pre_warm_count(&self) -> usize {
+        self.pre_warm_count
+    }
+
+    /// Number of warm containers waiting to be claimed.
+    pub async fn idle_count(&self) -> usize {
+        self.idle.lock().await.len()
+    }
+
+    /// Take the oldest warm container and ask the refill task to replace it.
+    pub async fn take(&self) -> Option<WarmContainer> {
+        let warm = self.idle.lock().await.pop_front();
+        if warm.is_some() {
+            self.refill.notify_one();
+        }
+        warm
+    }
+
+    pub async fn put(&self, warm: WarmContainer) {
+        self.idle.lock().await.push_back(warm);
+    }
+
+    /// Remove and return every warm container (used during shutdown).
+    pub async fn drain(&self) -> Vec<WarmContainer> {
+        self.idle.lock().await.drain(..).collect()
+    }
+
+    async fn refill_requested(&self) {
+        self.refill.notified().await;
+    }
+}
+
+/// Spawn a background task that keeps the pool topped up: immediately, after
+/// every claim, and every `interval_secs` to recover from failed creates.
+pub fn spawn_pool_refill(manager: Arc<DockerManager>, interval_secs: u64) {
+    if manager.pool().pre_warm_count() == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            manager.fill_pool().await;
+            tokio::select! {
+                _ = manager.pool().refill_requested() => {}
+                _ = tokio::time::sleep(Duration::from_secs(interval_secs)) => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warm(id: &str) -> WarmContainer {
+        WarmContainer {
+            container_id: id.to_string(),
+            workspace_dir: PathBuf::from(format!("data/pool/{id}")),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_take_is_fifo_and_requests_refill() {
+        let pool = ContainerPool::new(2);
+        assert!(pool.take().await.is_none());
+
+        pool.put(warm("a")).await;
+        pool.put(warm("b")).await;
+        assert_eq!(pool.idle_count().await, 2);
+        assert_eq!(pool.take().await.unwrap().container_id, "a");
+
+        // The permit stored by `take` wakes the refill task right away.
+        tokio::time::timeout(Duration::from_secs(1), pool.refill_requested())
+            .await
+            .unwrap();
+
+        assert_eq!(pool.drain().await.len(), 1);
+        assert_eq!(pool.idle_count().await, 0);
+    }
+}
@@ -13,8 +13,8 @@ pub struct ContainerRegistry {
 pub struct ContainerInfo {
     pub container_id: String,
     pub conversation_id: String,
-    #[allow(dead_code)]
     pub user_id: String,
+    pub started_at: std::time::Instant,
     pub last_activity: std::time::Instant,
 }
 
@@ -31,6 +31,7 @@ impl ContainerRegistry {
                 container_id: container_id.to_string(),
                 conversation_id: conversation_id.to_string(),
                 user_id: user_id.to_string(),
+                started_at: std::time::Instant::now(),
                 last_activity: std::time::Instant::now(),
             },
         );
@@ -53,16 +54,6 @@ impl ContainerRegistry {
         containers.get(conversation_id).cloned()
     }
 
-    pub async fn get_idle_containers(&self, timeout_secs: u64) -> Vec<ContainerInfo> {
-        let containers = self.containers.read().await;
-        let threshold = std::time::Duration::from_secs(timeout_secs);
-        containers
-            .values()
-            .filter(|info| info.last_activity.elapsed() > threshold)
-            .cloned()
-            .collect()
-    }
-
     pub async fn list_all(&self) -> Vec<ContainerInfo> {
         let containers = self.containers.read().await;
         containers.values().cloned().collect()
@@ -116,20 +107,6 @@ mod tests {
         assert!(after > before);
     }
 
-    #[tokio::test]
-    async fn test_get_idle_containers() {
-        let registry = ContainerRegistry::new();
-        registry.register("conv1", "c1", "user1").await;
-
-        // With a 0-second timeout, everything is idle
-        let idle = registry.get_idle_containers(0).await;
-        assert_eq!(idle.len(), 1);
-
-        // With a very large timeout, nothing is idle
-        let idle = registry.get_idle_containers(999999).await;
-        assert_eq!(idle.len(), 0);
-    }
-
     #[tokio::test]
     async fn test_list_all() {
         let registry = ContainerRegistry::new();
@@ -1,5 +1,5 @@
 use axum::Json;
-use axum::http::StatusCode;
+use axum::http::{StatusCode, header};
 use axum::response::{IntoResponse, Response};
 
 #[derive(Debug, thiserror::Error)]
@@ -16,15 +16,41 @@ pub enum AppError {
     #[error("Forbidden: {0}")]
     Forbidden(String),
 
+    #[error("Email address not verified")]
+    EmailNotVerified,
+
     #[error("Conflict: {0}")]
     Conflict(String),
 
     #[error("Payload too large: {0}")]
     PayloadTooLarge(String),
 
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+
+    /// Request was well-formed but failed validation; each entry describes
+    /// one problem.
+    #[error("Validation failed: {}", .0.join("; "))]
+    UnprocessableEntity(Vec<String>),
+
+    #[error("Too many requests; retry in {retry_after_secs} seconds")]
+    TooManyRequests { retry_after_secs: u64 },
+
+    /// The user has reached one of their resource quotas.
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
     #[error("Not implemented")]
     NotImplemented,
 
+    /// The endpoint or option belongs to a feature switched off on this server.
+    #[error("{0} is disabled on this server")]
+    FeatureDisabled(&'static str),
+
+    /// An upstream service (e.g. a provider API) failed or was unreachable.
+    #[error("Bad gateway: {0}")]
+    BadGateway(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 
@@ -34,14 +60,50 @@ pub enum AppError {
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        if let AppError::EmailNotVerified = self {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({
+                    "code": "email_not_verified",
+                    "message": self.to_string(),
+                })),
+            )
+                .into_response();
+        }
         let (status, message) = match &self {
             AppError::NotFound => (StatusCode::NOT_FOUND, self.to_string()),
             AppError::BadRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
-            AppError::Forbidden(_) => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::Forbidden(_) | AppError::EmailNotVerified => {
+                (StatusCode::FORBIDDEN, self.to_string())
+            }
             AppError::Conflict(_) => (StatusCode::CONFLICT, self.to_string()),
             AppError::PayloadTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, self.to_string()),
+            AppError::UnsupportedMediaType(_) => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, self.to_string())
+            }
+            AppError::UnprocessableEntity(errors) => {
+                return (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(serde_json::json!({
+                        "message": self.to_string(),
+                        "errors": errors,
+                    })),
+                )
+                    .into_response();
+            }
+            AppError::TooManyRequests { retry_after_secs } => {
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry_after_secs.to_string())],
+                    Json(serde_json::json!({ "message": self.to_string() })),
+                )
+                    .into_response();
+            }
+            AppError::QuotaExceeded(_) => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
             AppError::NotImplemented => (StatusCode::NOT_IMPLEMENTED, self.to_string()),
+            AppError::FeatureDisabled(_) => (StatusCode::SERVICE_UNAVAILABLE, self.to_string()),
+            AppError::BadGateway(_) => (StatusCode::BAD_GATEWAY, self.to_string()),
             AppError::Internal(msg) => {
                 tracing::error!("Internal error: {msg}");
                 (
@@ -101,6 +163,13 @@ mod tests {
         assert_eq!(status, StatusCode::FORBIDDEN);
     }
 
+    #[tokio::test]
+    async fn email_not_verified_returns_403_with_code() {
+        let (status, body) = extract_status_and_body(AppError::EmailNotVerified).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], "email_not_verified");
+    }
+
     #[tokio::test]
     async fn conflict_returns_409() {
         let (status, _) = extract_status_and_body(AppError::Conflict("duplicate".into())).await;
@@ -121,6 +190,67 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn unsupported_media_type_returns_415() {
+        let (status, body) = extract_status_and_body(AppError::UnsupportedMediaType(
+            "application/x-executable".into(),
+        ))
+        .await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .contains("application/x-executable")
+        );
+    }
+
+    #[tokio::test]
+    async fn unprocessable_entity_returns_422_with_error_list() {
+        let (status, body) = extract_status_and_body(AppError::UnprocessableEntity(vec![
+            "command is required".into(),
+            "url is invalid".into(),
+        ]))
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body["errors"],
+            serde_json::json!(["command is required", "url is invalid"])
+        );
+        assert!(body["message"].as_str().unwrap().contains("url is invalid"));
+    }
+
+    #[tokio::test]
+    async fn bad_gateway_returns_502() {
+        let (status, body) =
+            extract_status_and_body(AppError::BadGateway("provider returned 500".into())).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .contains("provider returned 500")
+        );
+    }
+
+    #[tokio::test]
+    async fn too_many_requests_returns_429_with_retry_after() {
+        let response = AppError::TooManyRequests {
+            retry_after_secs: 42,
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "42");
+    }
+
+    #[tokio::test]
+    async fn quota_exceeded_returns_429_without_retry_after() {
+        let response =
+            AppError::QuotaExceeded("limit of 3 conversations reached".into()).into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().get(header::RETRY_AFTER).is_none());
+    }
+
     #[tokio::test]
     async fn internal_returns_500_and_hides_details() {
         let (status, body) =
@@ -6,5 +6,8 @@ pub mod crypto;
 pub mod db;
 pub mod docker;
 pub mod error;
+pub mod mailer;
+pub mod mcp;
 pub mod prompts;
+pub mod provider_api;
 pub mod ws;
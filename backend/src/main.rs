@@ -13,7 +13,10 @@ mod crypto;
 mod db;
 mod docker;
 mod error;
+mod mailer;
+mod mcp;
 mod prompts;
+mod provider_api;
 mod ws;
 
 use auth::middleware::AppState;
@@ -26,6 +29,13 @@ async fn main() {
         .init();
 
     let config = config::Config::from_env();
+    if let Err(errors) = config.validate() {
+        eprintln!("Invalid configuration:");
+        for error in &errors {
+            eprintln!("  - {error}");
+        }
+        std::process::exit(1);
+    }
     let pool = db::init_db(&config.database_url).await;
 
     let ws_state = WsState::new();
@@ -38,13 +48,41 @@ async fn main() {
     ));
 
     // Spawn idle container cleanup task (check every 30 seconds)
-    docker::manager::spawn_idle_cleanup(docker_manager.clone(), ws_state.clone(), 30);
+    docker::manager::spawn_idle_cleanup(docker_manager.clone(), ws_state.clone(), pool.clone(), 30);
+
+    // Keep the pre-warmed container pool topped up (re-checked every minute)
+    docker::pool::spawn_pool_refill(docker_manager.clone(), 60);
+
+    // Remove orphaned messages_v2 rows and message parts once a day
+    db::messages_v2::spawn_orphan_repair(pool.clone(), 24 * 60 * 60);
+
+    // Purge messages soft-deleted more than 30 days ago once a day
+    db::messages::spawn_deleted_message_purge(pool.clone(), 24 * 60 * 60, 30);
+
+    // Forget failed logins older than a day (unless still locked) every hour
+    db::login_attempts::spawn_stale_cleanup(pool.clone(), 60 * 60, 24 * 60 * 60);
+
+    let jwt_keys = auth::JwtKeys::from_config(&config)
+        .unwrap_or_else(|e| panic!("Invalid JWT configuration: {e}"));
+    let mailer = match (&config.smtp_url, &config.mail_from) {
+        (Some(smtp_url), Some(from)) => Some(Arc::new(
+            mailer::SmtpMailer::new(smtp_url, from).unwrap_or_else(|e| panic!("{e}")),
+        ) as Arc<dyn mailer::Mailer>),
+        _ => None,
+    };
 
     let state = Arc::new(AppState {
         db: pool,
         config: config.clone(),
+        jwt_keys,
         ws_state: ws_state.clone(),
+        sse_state: ws::sse::SseState::new(),
+        pending_uploads: Default::default(),
         docker_manager: docker_manager.clone(),
+        provider_api: Arc::new(provider_api::HttpProviderApiClient::default()),
+        mailer,
+        system_stats_cache: Default::default(),
+        features: config::FeatureFlags::from_env(),
     });
 
     let cors = if let Some(ref origins) = config.cors_allowed_origins {
@@ -75,7 +113,7 @@ async fn main() {
             api::files::router().layer(DefaultBodyLimit::max(50 * 1024 * 1024)),
         )
         .nest("/api/admin", api::admin::router())
-        .nest("/api/mcp-servers", mcp_servers_public_router())
+        .nest("/api/mcp-servers", api::mcp_servers::router())
         .nest("/api/presets", api::presets::router())
         .nest(
             "/api/conversations",
@@ -138,26 +176,3 @@ async fn main() {
 async fn health() -> &'static str {
     "ok"
 }
-
-fn mcp_servers_public_router() -> Router<Arc<AppState>> {
-    Router::new().route("/", get(list_available_mcp_servers))
-}
-
-async fn list_available_mcp_servers(
-    axum::extract::State(state): axum::extract::State<Arc<AppState>>,
-    _auth: auth::middleware::AuthUser,
-) -> Result<axum::Json<Vec<api::conversations::McpServerResponse>>, error::AppError> {
-    let servers = db::mcp_servers::list_enabled_mcp_servers(&state.db).await?;
-    Ok(axum::Json(
-        servers
-            .into_iter()
-            .map(|s| api::conversations::McpServerResponse {
-                id: s.id,
-                name: s.name,
-                description: s.description,
-                transport: s.transport,
-                is_enabled: s.is_enabled,
-            })
-            .collect(),
-    ))
-}
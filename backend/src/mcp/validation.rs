@@ -0,0 +1,171 @@
+/// The parts of an MCP server definition that determine how the agent
+/// launches or connects to it. JSON fields are the raw strings stored in
+/// `mcp_servers`.
+#[derive(Debug, Clone, Copy)]
+pub struct McpServerConfig<'a> {
+    pub transport: &'a str,
+    pub command: Option<&'a str>,
+    pub args: Option<&'a str>,
+    pub url: Option<&'a str>,
+    pub env_vars: Option<&'a str>,
+}
+
+/// Check that `config` describes a usable server, collecting every problem
+/// rather than stopping at the first.
+pub fn validate_mcp_server_config(config: &McpServerConfig) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+
+    match config.transport {
+        "stdio" => {
+            if config.command.is_none_or(|c| c.trim().is_empty()) {
+                errors.push("command is required for stdio servers".to_string());
+            }
+            if let Some(args) = non_blank(config.args) {
+                match serde_json::from_str::<serde_json::Value>(args) {
+                    Ok(serde_json::Value::Array(items)) if items.iter().all(|a| a.is_string()) => {}
+                    _ => errors.push("args must be a JSON array of strings".to_string()),
+                }
+            }
+        }
+        "sse" | "http" => match non_blank(config.url) {
+            None => errors.push(format!("url is required for {} servers", config.transport)),
+            Some(raw) => match url::Url::parse(raw) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => {}
+                Ok(_) => errors.push("url must be an http(s) URL".to_string()),
+                Err(e) => errors.push(format!("url is invalid: {e}")),
+            },
+        },
+        other => errors.push(format!(
+            "transport must be 'stdio', 'sse' or 'http', got '{other}'"
+        )),
+    }
+
+    if let Some(env_vars) = non_blank(config.env_vars) {
+        match serde_json::from_str::<serde_json::Value>(env_vars) {
+            Ok(serde_json::Value::Object(vars)) if vars.values().all(|v| v.is_string()) => {}
+            _ => errors.push("env_vars must be a JSON object of string values".to_string()),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn non_blank(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stdio(command: Option<&'static str>) -> McpServerConfig<'static> {
+        McpServerConfig {
+            transport: "stdio",
+            command,
+            args: None,
+            url: None,
+            env_vars: None,
+        }
+    }
+
+    fn remote(url: Option<&'static str>) -> McpServerConfig<'static> {
+        McpServerConfig {
+            transport: "http",
+            command: None,
+            args: None,
+            url,
+            env_vars: None,
+        }
+    }
+
+    #[test]
+    fn accepts_valid_configs() {
+        let config = McpServerConfig {
+            args: Some(r#"["-y", "@mcp/fs"]"#),
+            env_vars: Some(r#"{"ROOT": "/workspace"}"#),
+            ..stdio(Some("npx"))
+        };
+        assert!(validate_mcp_server_config(&config).is_ok());
+        assert!(validate_mcp_server_config(&remote(Some("https://mcp.example.com/v1"))).is_ok());
+        let sse = McpServerConfig {
+            transport: "sse",
+            ..remote(Some("http://localhost:8080"))
+        };
+        assert!(validate_mcp_server_config(&sse).is_ok());
+    }
+
+    #[test]
+    fn rejects_stdio_without_command() {
+        assert!(validate_mcp_server_config(&stdio(None)).is_err());
+        let errors = validate_mcp_server_config(&stdio(Some("  "))).unwrap_err();
+        assert_eq!(errors, ["command is required for stdio servers"]);
+    }
+
+    #[test]
+    fn rejects_stdio_args_that_are_not_a_string_array() {
+        for args in ["not json", r#"{"a": 1}"#, r#""-y""#, "[1, 2]"] {
+            let config = McpServerConfig {
+                args: Some(args),
+                ..stdio(Some("npx"))
+            };
+            let errors = validate_mcp_server_config(&config).unwrap_err();
+            assert_eq!(errors, ["args must be a JSON array of strings"], "{args}");
+        }
+    }
+
+    #[test]
+    fn rejects_missing_or_invalid_urls() {
+        assert_eq!(
+            validate_mcp_server_config(&remote(None)).unwrap_err(),
+            ["url is required for http servers"]
+        );
+        assert!(
+            validate_mcp_server_config(&remote(Some("not a url"))).unwrap_err()[0]
+                .starts_with("url is invalid")
+        );
+        assert_eq!(
+            validate_mcp_server_config(&remote(Some("ftp://mcp.example.com"))).unwrap_err(),
+            ["url must be an http(s) URL"]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_env_vars() {
+        for env_vars in ["{broken", r#"["A=1"]"#, r#"{"A": 1}"#] {
+            let config = McpServerConfig {
+                env_vars: Some(env_vars),
+                ..stdio(Some("npx"))
+            };
+            let errors = validate_mcp_server_config(&config).unwrap_err();
+            assert_eq!(
+                errors,
+                ["env_vars must be a JSON object of string values"],
+                "{env_vars}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_transport() {
+        let config = McpServerConfig {
+            transport: "grpc",
+            ..stdio(Some("npx"))
+        };
+        let errors = validate_mcp_server_config(&config).unwrap_err();
+        assert!(errors[0].contains("'grpc'"));
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let config = McpServerConfig {
+            args: Some("nope"),
+            env_vars: Some("nope"),
+            ..stdio(None)
+        };
+        assert_eq!(validate_mcp_server_config(&config).unwrap_err().len(), 3);
+    }
+}
@@ -37,6 +37,33 @@ pub fn builtin_presets() -> Vec<SystemPromptPreset> {
     ]
 }
 
+/// Longest excerpt of a single message kept in a conversation summary.
+const SUMMARY_EXCERPT_CHARS: usize = 200;
+
+/// Build the text of a conversation summary message from `(role, content)`
+/// pairs. This is a condensed transcript: one line per message, each cut to
+/// a short excerpt.
+pub fn summary_prompt<'a>(messages: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    let mut out = String::from("Summary of the earlier conversation:");
+    let mut count = 0;
+    for (role, content) in messages {
+        let flat = content.split_whitespace().collect::<Vec<_>>().join(" ");
+        if flat.is_empty() {
+            continue;
+        }
+        let excerpt = match flat.char_indices().nth(SUMMARY_EXCERPT_CHARS) {
+            Some((idx, _)) => format!("{}…", &flat[..idx]),
+            None => flat,
+        };
+        out.push_str(&format!("\n- {role}: {excerpt}"));
+        count += 1;
+    }
+    if count == 0 {
+        out.push_str("\n(no text content)");
+    }
+    out
+}
+
 #[allow(dead_code)]
 pub fn get_preset(id: &str) -> Option<SystemPromptPreset> {
     builtin_presets().into_iter().find(|p| p.id == id)
@@ -81,6 +108,32 @@ mod tests {
         assert!(!preset.content.is_empty());
     }
 
+    #[test]
+    fn test_summary_prompt_lists_messages_in_order() {
+        let summary = summary_prompt([("user", "Hi  there\n"), ("assistant", "Hello!")]);
+        assert_eq!(
+            summary,
+            "Summary of the earlier conversation:\n- user: Hi there\n- assistant: Hello!"
+        );
+    }
+
+    #[test]
+    fn test_summary_prompt_truncates_long_messages() {
+        let long = "é".repeat(SUMMARY_EXCERPT_CHARS + 50);
+        let summary = summary_prompt([("user", long.as_str())]);
+        let line = summary.lines().nth(1).unwrap();
+        assert_eq!(
+            line,
+            format!("- user: {}…", "é".repeat(SUMMARY_EXCERPT_CHARS))
+        );
+    }
+
+    #[test]
+    fn test_summary_prompt_skips_empty_messages() {
+        let summary = summary_prompt([("tool", "   ")]);
+        assert!(summary.ends_with("(no text content)"));
+    }
+
     #[test]
     fn test_get_nonexistent_preset_returns_none() {
         assert!(get_preset("nonexistent").is_none());
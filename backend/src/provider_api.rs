@@ -0,0 +1,288 @@
+//! Direct calls to LLM provider APIs made by the backend itself (the agent
+//! container talks to providers on its own).
+
+use std::time::{Duration, Instant};
+
+use futures_util::future::BoxFuture;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+pub const PROVIDER_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Base URL used when a provider has no `endpoint_url`. OpenAI-compatible
+/// bases include the `/v1` prefix, matching what the agent passes to the
+/// provider SDKs as `base_url`.
+pub fn default_base_url(provider_type: &str) -> Option<&'static str> {
+    match provider_type {
+        "openai" => Some("https://api.openai.com/v1"),
+        "anthropic" => Some("https://api.anthropic.com"),
+        "mistral" => Some("https://api.mistral.ai/v1"),
+        "google" => Some("https://generativelanguage.googleapis.com/v1beta"),
+        _ => None,
+    }
+}
+
+fn base_url(provider_type: &str, endpoint_url: Option<&str>) -> Option<String> {
+    endpoint_url
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .or_else(|| default_base_url(provider_type))
+        .map(|url| url.trim_end_matches('/').to_string())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProviderApiError {
+    #[error("{0}")]
+    Unsupported(String),
+
+    #[error("API key was rejected ({0})")]
+    Rejected(StatusCode),
+
+    #[error("Provider returned {0}")]
+    Status(StatusCode),
+
+    #[error("Request to provider failed: {0}")]
+    Request(String),
+
+    #[error("Unexpected response from provider: {0}")]
+    InvalidResponse(String),
+}
+
+impl From<ProviderApiError> for crate::error::AppError {
+    fn from(e: ProviderApiError) -> Self {
+        match e {
+            ProviderApiError::Unsupported(msg) => crate::error::AppError::BadRequest(msg),
+            e => crate::error::AppError::BadGateway(e.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ProviderApiError {
+    fn from(e: reqwest::Error) -> Self {
+        // Strip the URL so a key embedded in a custom endpoint can't leak.
+        ProviderApiError::Request(e.without_url().to_string())
+    }
+}
+
+/// The provider API calls the backend makes on a user's behalf.
+///
+/// Implemented over HTTP by [`HttpProviderApiClient`]; tests substitute a
+/// mock so handlers can run without a provider.
+pub trait ProviderApiClient: Send + Sync {
+    /// IDs of the models available to `api_key`, sorted and deduplicated.
+    fn list_models<'a>(
+        &'a self,
+        provider_type: &'a str,
+        endpoint_url: Option<&'a str>,
+        api_key: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, ProviderApiError>>;
+}
+
+pub struct HttpProviderApiClient {
+    client: reqwest::Client,
+}
+
+impl Default for HttpProviderApiClient {
+    fn default() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(PROVIDER_REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// Parse an OpenAI-style `{"data": [{"id": ...}]}` model list, which
+/// Anthropic and Mistral also use.
+fn parse_model_ids(body: &[u8]) -> Result<Vec<String>, ProviderApiError> {
+    let list: ModelList = serde_json::from_slice(body)
+        .map_err(|e| ProviderApiError::InvalidResponse(e.to_string()))?;
+    let mut ids: Vec<String> = list
+        .data
+        .into_iter()
+        .map(|m| m.id)
+        .filter(|id| !id.trim().is_empty())
+        .collect();
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+impl ProviderApiClient for HttpProviderApiClient {
+    fn list_models<'a>(
+        &'a self,
+        provider_type: &'a str,
+        endpoint_url: Option<&'a str>,
+        api_key: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, ProviderApiError>> {
+        Box::pin(async move {
+            let base = base_url(provider_type, endpoint_url).ok_or_else(|| {
+                ProviderApiError::Unsupported(format!("Unsupported provider type: {provider_type}"))
+            })?;
+            let request = match provider_type {
+                "openai" | "mistral" => self
+                    .client
+                    .get(format!("{base}/models"))
+                    .bearer_auth(api_key),
+                "anthropic" => self
+                    .client
+                    .get(format!("{base}/v1/models"))
+                    .header("x-api-key", api_key)
+                    .header("anthropic-version", ANTHROPIC_VERSION),
+                other => {
+                    return Err(ProviderApiError::Unsupported(format!(
+                        "Listing models is not supported for {other} providers"
+                    )));
+                }
+            };
+
+            let resp = request.send().await?;
+            let status = resp.status();
+            if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+                return Err(ProviderApiError::Rejected(status));
+            }
+            if !status.is_success() {
+                return Err(ProviderApiError::Status(status));
+            }
+            parse_model_ids(&resp.bytes().await?)
+        })
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct KeyValidation {
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl KeyValidation {
+    fn invalid(error: impl Into<String>) -> Self {
+        Self {
+            valid: false,
+            latency_ms: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// Check `api_key` with the cheapest authenticated request the provider
+/// offers: listing models, or a one-token message for Anthropic (`model` is
+/// required there). Errors never include the key.
+pub async fn validate_api_key(
+    provider_type: &str,
+    endpoint_url: Option<&str>,
+    api_key: &str,
+    model: Option<&str>,
+) -> KeyValidation {
+    let Some(base) = base_url(provider_type, endpoint_url) else {
+        return KeyValidation::invalid(format!("Unsupported provider type: {provider_type}"));
+    };
+    let client = match reqwest::Client::builder()
+        .timeout(PROVIDER_REQUEST_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => return KeyValidation::invalid(e.to_string()),
+    };
+
+    let request = match provider_type {
+        "anthropic" => {
+            let Some(model) = model else {
+                return KeyValidation::invalid("Provider has no model to test with");
+            };
+            client
+                .post(format!("{base}/v1/messages"))
+                .header("x-api-key", api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .json(&serde_json::json!({
+                    "model": model,
+                    "max_tokens": 1,
+                    "messages": [{"role": "user", "content": "ping"}],
+                }))
+        }
+        "google" => client
+            .get(format!("{base}/models"))
+            .header("x-goog-api-key", api_key),
+        _ => client.get(format!("{base}/models")).bearer_auth(api_key),
+    };
+
+    let started = Instant::now();
+    match request.send().await {
+        Ok(resp) if resp.status().is_success() => KeyValidation {
+            valid: true,
+            latency_ms: Some(started.elapsed().as_millis() as u64),
+            error: None,
+        },
+        Ok(resp) => {
+            let status = resp.status();
+            let error = if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+                ProviderApiError::Rejected(status)
+            } else {
+                ProviderApiError::Status(status)
+            };
+            KeyValidation::invalid(error.to_string())
+        }
+        Err(e) => KeyValidation::invalid(ProviderApiError::from(e).to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_url_prefers_custom_endpoint() {
+        assert_eq!(
+            base_url("openai", Some("http://localhost:11434/v1/")).as_deref(),
+            Some("http://localhost:11434/v1")
+        );
+        assert_eq!(
+            base_url("anthropic", Some("  ")).as_deref(),
+            Some("https://api.anthropic.com")
+        );
+        assert_eq!(base_url("unknown", None), None);
+    }
+
+    #[test]
+    fn parse_model_ids_sorts_and_dedupes() {
+        let body = br#"{"object":"list","data":[{"id":"gpt-4o"},{"id":"gpt-4o-mini"},{"id":"gpt-4o"},{"id":" "}]}"#;
+        assert_eq!(parse_model_ids(body).unwrap(), ["gpt-4o", "gpt-4o-mini"]);
+        assert!(matches!(
+            parse_model_ids(br#"{"models":[]}"#),
+            Err(ProviderApiError::InvalidResponse(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn list_models_rejects_unsupported_providers() {
+        let client = HttpProviderApiClient::default();
+        let err = client.list_models("google", None, "k").await.unwrap_err();
+        assert!(matches!(err, ProviderApiError::Unsupported(_)));
+    }
+
+    #[tokio::test]
+    async fn anthropic_validation_requires_a_model() {
+        let result = validate_api_key("anthropic", Some("http://127.0.0.1:1"), "k", None).await;
+        assert!(!result.valid);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Provider has no model to test with")
+        );
+    }
+}
@@ -13,7 +13,7 @@ use tokio::sync::mpsc;
 use super::WsState;
 use super::messages::ClientMessage;
 use crate::auth;
-use crate::auth::middleware::AppState;
+use crate::auth::middleware::{AppState, load_active_user};
 use crate::db;
 use crate::docker::manager::DockerManager;
 
@@ -96,6 +96,96 @@ fn validate_question_answer_payload(
     Ok(())
 }
 
+/// A switch is only valid when the client names the conversation it is
+/// actually in (or none, when it hasn't joined one yet).
+fn switch_source_matches(current: Option<&str>, from: Option<&str>) -> bool {
+    current == from
+}
+
+/// Persist a user message (with its v2 text part), bump the conversation's
+/// activity, and title the conversation after its first message. Uses
+/// `message_id` as the message ID when given.
+///
+/// Returns the saved message and the conversation as it was before titling.
+pub(crate) async fn save_user_message(
+    pool: &sqlx::SqlitePool,
+    conv_id: &str,
+    user_id: &str,
+    message_id: Option<&str>,
+    content: &str,
+) -> Result<
+    (
+        db::messages::Message,
+        Option<db::conversations::Conversation>,
+    ),
+    sqlx::Error,
+> {
+    let msg = match message_id {
+        Some(id) => {
+            db::messages::create_message_with_id(pool, id, conv_id, "user", content).await?
+        }
+        None => {
+            db::messages::create_message(pool, conv_id, "user", content, None, None, None).await?
+        }
+    };
+    if let Err(e) =
+        db::messages_v2::upsert_message_text_part(pool, &msg.id, conv_id, "user", content).await
+    {
+        tracing::error!(
+            conversation_id = %conv_id,
+            message_id = %msg.id,
+            error = %e,
+            "Failed to persist user message to messages_v2"
+        );
+    }
+    if let Err(e) = db::conversations::touch_conversation_activity(pool, conv_id, user_id).await {
+        tracing::error!(
+            conversation_id = %conv_id,
+            error = %e,
+            "Failed to touch conversation activity after user message"
+        );
+    }
+
+    let conv = db::conversations::get_conversation(pool, conv_id, user_id)
+        .await
+        .ok()
+        .flatten();
+
+    // Auto-generate conversation title from first message
+    let msg_count = db::messages::count_messages(pool, conv_id)
+        .await
+        .unwrap_or(0);
+    if msg_count == 1 {
+        let title: String = if content.chars().count() > 50 {
+            format!("{}...", content.chars().take(50).collect::<String>())
+        } else {
+            content.to_string()
+        };
+        if let Some(c) = &conv {
+            let _ = db::conversations::update_conversation_with_subagent(
+                pool,
+                conv_id,
+                user_id,
+                &title,
+                c.provider_id.as_deref(),
+                c.model_name.as_deref(),
+                c.subagent_provider_id.as_deref(),
+                c.subagent_model.as_deref(),
+                c.system_prompt_override.as_deref(),
+                c.deep_thinking,
+                c.image_provider_id.as_deref(),
+                c.image_model.as_deref(),
+                c.thinking_budget,
+                c.subagent_thinking_budget,
+                c.container_idle_timeout_secs,
+            )
+            .await;
+        }
+    }
+
+    Ok((msg, conv))
+}
+
 async fn send_to_container_or_start(
     ws_state: &Arc<WsState>,
     docker_manager: &Arc<DockerManager>,
@@ -165,10 +255,13 @@ pub async fn ws_handler(
         None => return axum::http::StatusCode::UNAUTHORIZED.into_response(),
     };
 
-    let claims = match auth::verify_access_token(&token, &state.config.jwt_secret) {
+    let claims = match auth::verify_access_token(&token, &state.jwt_keys) {
         Ok(c) => c,
         Err(_) => return axum::http::StatusCode::UNAUTHORIZED.into_response(),
     };
+    if let Err(e) = load_active_user(&state, &claims.sub).await {
+        return e.into_response();
+    }
 
     let ws_state = state.ws_state.clone();
     let docker_manager = state.docker_manager.clone();
@@ -190,12 +283,18 @@ async fn handle_client_ws(
 
     let send_task = tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
+            let disable = msg == super::ACCOUNT_DISABLED_MESSAGE;
             if ws_sink.send(Message::Text(msg.into())).await.is_err() {
                 break;
             }
+            if disable {
+                let _ = ws_sink.send(Message::Close(None)).await;
+                break;
+            }
         }
     });
 
+    let connection_id = ws_state.next_client_connection_id();
     let mut current_conversation_id: Option<String> = None;
 
     while let Some(Ok(msg)) = ws_stream.next().await {
@@ -205,6 +304,12 @@ async fn handle_client_ws(
             _ => continue,
         };
 
+        if let Some(ref conv_id) = current_conversation_id {
+            ws_state
+                .touch_client_activity(&user_id, conv_id, connection_id)
+                .await;
+        }
+
         let client_msg: ClientMessage = match serde_json::from_str(&text) {
             Ok(m) => m,
             Err(_) => continue,
@@ -234,11 +339,15 @@ async fn handle_client_ws(
                 }
 
                 if let Some(ref old_id) = current_conversation_id {
-                    ws_state.remove_client(&user_id, old_id).await;
+                    ws_state
+                        .remove_client(&user_id, old_id, connection_id)
+                        .await;
                 }
 
                 current_conversation_id = Some(conv_id.to_string());
-                ws_state.add_client(&user_id, &conv_id, tx.clone()).await;
+                ws_state
+                    .add_client(&user_id, &conv_id, connection_id, tx.clone())
+                    .await;
 
                 let _ = tx.try_send(
                     serde_json::json!({
@@ -248,6 +357,64 @@ async fn handle_client_ws(
                     .to_string(),
                 );
             }
+            ClientMessage::SwitchConversation {
+                from_conversation_id,
+                to_conversation_id: to_id,
+            } => {
+                if to_id.is_empty() {
+                    continue;
+                }
+
+                if !switch_source_matches(
+                    current_conversation_id.as_deref(),
+                    from_conversation_id.as_deref(),
+                ) {
+                    let _ = tx.try_send(
+                        serde_json::json!({
+                            "type": "error",
+                            "code": "conversation_mismatch",
+                            "message": "from_conversation_id does not match the joined conversation"
+                        })
+                        .to_string(),
+                    );
+                    continue;
+                }
+
+                match db::conversations::get_conversation(&state.db, &to_id, &user_id).await {
+                    Ok(None) | Err(_) => {
+                        let _ = tx.try_send(
+                            serde_json::json!({
+                                "type": "error",
+                                "code": "not_found",
+                                "message": "Conversation not found"
+                            })
+                            .to_string(),
+                        );
+                        continue;
+                    }
+                    Ok(Some(_)) => {}
+                }
+
+                ws_state
+                    .switch_client(
+                        &user_id,
+                        connection_id,
+                        current_conversation_id.as_deref(),
+                        &to_id,
+                        tx.clone(),
+                    )
+                    .await;
+                let from_id = current_conversation_id.replace(to_id.clone());
+
+                let _ = tx.try_send(
+                    serde_json::json!({
+                        "type": "conversation_switched",
+                        "from": from_id,
+                        "to": to_id,
+                    })
+                    .to_string(),
+                );
+            }
             ClientMessage::UserMessage {
                 content,
                 attachments,
@@ -270,81 +437,47 @@ async fn handle_client_ws(
                 if content.is_empty() {
                     continue;
                 }
-
-                let msg = match db::messages::create_message(
-                    &state.db, &conv_id, "user", &content, None, None, None,
+                match db::user_quotas::message_limit_reached(
+                    &state.db,
+                    &state.config,
+                    &user_id,
+                    &conv_id,
+                    db::user_quotas::MESSAGES_PER_TURN,
                 )
                 .await
                 {
-                    Ok(m) => m,
+                    Ok(None) => {}
+                    Ok(Some(max)) => {
+                        let _ = tx.try_send(
+                            serde_json::json!({
+                                "type": "error",
+                                "code": "quota_exceeded",
+                                "message": format!("Limit of {max} messages per conversation reached"),
+                            })
+                            .to_string(),
+                        );
+                        continue;
+                    }
                     Err(e) => {
-                        tracing::error!("Failed to create message: {e}");
+                        tracing::error!("Failed to check message quota: {e}");
                         continue;
                     }
-                };
-                if let Err(e) = db::messages_v2::upsert_message_text_part(
-                    &state.db, &msg.id, &conv_id, "user", &content,
-                )
-                .await
-                {
-                    tracing::error!(
-                        conversation_id = %conv_id,
-                        message_id = %msg.id,
-                        error = %e,
-                        "Failed to persist user message to messages_v2"
-                    );
-                }
-                if let Err(e) =
-                    db::conversations::touch_conversation_activity(&state.db, &conv_id, &user_id)
-                        .await
-                {
-                    tracing::error!(
-                        conversation_id = %conv_id,
-                        error = %e,
-                        "Failed to touch conversation activity after user message"
-                    );
                 }
 
-                let conv = db::conversations::get_conversation(&state.db, &conv_id, &user_id)
-                    .await
-                    .ok()
-                    .flatten();
-                let deep_thinking = conv.as_ref().map(|c| c.deep_thinking).unwrap_or(false);
+                let (msg, conv) =
+                    match save_user_message(&state.db, &conv_id, &user_id, None, &content).await {
+                        Ok(saved) => saved,
+                        Err(e) => {
+                            tracing::error!("Failed to create message: {e}");
+                            continue;
+                        }
+                    };
+                let deep_thinking = state.features.enable_deep_thinking
+                    && conv.as_ref().is_some_and(|c| c.deep_thinking);
                 let thinking_budget = conv.as_ref().and_then(|c| c.thinking_budget);
                 let subagent_thinking_budget =
                     conv.as_ref().and_then(|c| c.subagent_thinking_budget);
 
-                // Auto-generate conversation title from first message
-                let msg_count = db::messages::count_messages(&state.db, &conv_id)
-                    .await
-                    .unwrap_or(0);
-                if msg_count == 1 {
-                    let title: String = if content.chars().count() > 50 {
-                        format!("{}...", content.chars().take(50).collect::<String>())
-                    } else {
-                        content.clone()
-                    };
-                    if let Some(c) = &conv {
-                        let _ = db::conversations::update_conversation_with_subagent(
-                            &state.db,
-                            &conv_id,
-                            &user_id,
-                            &title,
-                            c.provider_id.as_deref(),
-                            c.model_name.as_deref(),
-                            c.subagent_provider_id.as_deref(),
-                            c.subagent_model.as_deref(),
-                            c.system_prompt_override.as_deref(),
-                            c.deep_thinking,
-                            c.image_provider_id.as_deref(),
-                            c.image_model.as_deref(),
-                            c.thinking_budget,
-                            c.subagent_thinking_budget,
-                        )
-                        .await;
-                    }
-                }
-
                 let _ = tx.try_send(
                     serde_json::json!({
                         "type": "message_saved",
@@ -608,7 +741,8 @@ async fn handle_client_ws(
                     .await
                     .ok()
                     .flatten();
-                let deep_thinking = edit_conv.as_ref().map(|c| c.deep_thinking).unwrap_or(false);
+                let deep_thinking = state.features.enable_deep_thinking
+                    && edit_conv.as_ref().is_some_and(|c| c.deep_thinking);
                 let thinking_budget = edit_conv.as_ref().and_then(|c| c.thinking_budget);
                 let subagent_thinking_budget =
                     edit_conv.as_ref().and_then(|c| c.subagent_thinking_budget);
@@ -787,10 +921,8 @@ async fn handle_client_ws(
                     .await
                     .ok()
                     .flatten();
-                let deep_thinking = regen_conv
-                    .as_ref()
-                    .map(|c| c.deep_thinking)
-                    .unwrap_or(false);
+                let deep_thinking = state.features.enable_deep_thinking
+                    && regen_conv.as_ref().is_some_and(|c| c.deep_thinking);
                 let thinking_budget = regen_conv.as_ref().and_then(|c| c.thinking_budget);
                 let subagent_thinking_budget =
                     regen_conv.as_ref().and_then(|c| c.subagent_thinking_budget);
@@ -842,7 +974,9 @@ async fn handle_client_ws(
     }
 
     if let Some(ref conv_id) = current_conversation_id {
-        ws_state.remove_client(&user_id, conv_id).await;
+        ws_state
+            .remove_client(&user_id, conv_id, connection_id)
+            .await;
     }
     send_task.abort();
 }
@@ -851,7 +985,8 @@ async fn handle_client_ws(
 mod tests {
     use super::{
         extract_ws_access_token, should_touch_after_edit, should_touch_after_regenerate,
-        should_update_message_content, validate_question_answer_payload, ws_origin_allowed,
+        should_update_message_content, switch_source_matches, validate_question_answer_payload,
+        ws_origin_allowed,
     };
     use axum::http::{HeaderMap, HeaderValue, header};
 
@@ -970,4 +1105,17 @@ mod tests {
         let answers = serde_json::json!([{"id":"q1","selected_options":["A"]}]);
         assert!(validate_question_answer_payload("qq-1", &answers).is_ok());
     }
+
+    #[test]
+    fn switch_source_matches_current_conversation() {
+        assert!(switch_source_matches(Some("conv-1"), Some("conv-1")));
+        assert!(switch_source_matches(None, None));
+    }
+
+    #[test]
+    fn switch_source_rejects_mismatch() {
+        assert!(!switch_source_matches(Some("conv-1"), Some("conv-2")));
+        assert!(!switch_source_matches(Some("conv-1"), None));
+        assert!(!switch_source_matches(None, Some("conv-1")));
+    }
 }
@@ -6,13 +6,18 @@ use axum::{
     response::IntoResponse,
 };
 use futures_util::{SinkExt, StreamExt};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::Instrument;
 
 use super::WsState;
 use super::messages::ContainerMessage;
 use crate::auth;
 use crate::auth::middleware::AppState;
+use crate::config::FeatureFlags;
 use crate::db;
 
 const INIT_PAYLOAD_WARN_BYTES: usize = 1_000_000;
@@ -35,13 +40,13 @@ fn parse_models_json(value: Option<&str>) -> Vec<String> {
 }
 
 #[derive(Debug)]
-struct ResolvedConversationProviders {
-    chat_provider: db::providers::UserProvider,
-    chat_model: String,
-    subagent_provider: db::providers::UserProvider,
-    subagent_model: String,
-    image_provider: Option<db::providers::UserProvider>,
-    image_model: Option<String>,
+pub(crate) struct ResolvedConversationProviders {
+    pub(crate) chat_provider: db::providers::UserProvider,
+    pub(crate) chat_model: String,
+    pub(crate) subagent_provider: db::providers::UserProvider,
+    pub(crate) subagent_model: String,
+    pub(crate) image_provider: Option<db::providers::UserProvider>,
+    pub(crate) image_model: Option<String>,
 }
 
 fn resolve_provider<'a>(
@@ -78,7 +83,7 @@ fn ensure_provider_has_model(
     ))
 }
 
-fn resolve_conversation_providers(
+pub(crate) fn resolve_conversation_providers(
     conv: &db::conversations::Conversation,
     providers: &[db::providers::UserProvider],
 ) -> Result<ResolvedConversationProviders, String> {
@@ -125,6 +130,27 @@ fn resolve_conversation_providers(
     })
 }
 
+/// `conv` as the agent should see it under the current feature flags. With
+/// deep thinking off the subagent mirrors the main model, and with image
+/// generation off no image model is configured. Stored settings are left
+/// untouched so they apply again once the feature is switched back on.
+pub(crate) fn with_feature_flags(
+    mut conv: db::conversations::Conversation,
+    features: &FeatureFlags,
+) -> db::conversations::Conversation {
+    if !features.enable_deep_thinking {
+        conv.deep_thinking = false;
+        conv.subagent_provider_id = conv.provider_id.clone();
+        conv.subagent_model = conv.model_name.clone();
+        conv.subagent_thinking_budget = None;
+    }
+    if !features.enable_image_generation {
+        conv.image_provider_id = None;
+        conv.image_model = None;
+    }
+    conv
+}
+
 async fn fail_container_init(
     state: &Arc<AppState>,
     ws_state: &Arc<WsState>,
@@ -147,6 +173,16 @@ async fn fail_container_init(
         )
         .await;
 
+    if let Err(e) =
+        db::conversations::set_last_container_error(&state.db, conversation_id, Some(message)).await
+    {
+        tracing::warn!(
+            "Failed to record container error for {}: {}",
+            conversation_id,
+            e
+        );
+    }
+
     let _ = ws_state.take_pending_message(conversation_id).await;
     let _ = state.docker_manager.stop_container(conversation_id).await;
     ws_state.remove_container(conversation_id).await;
@@ -160,20 +196,133 @@ async fn fail_container_init(
     } else {
         "Container initialization failed. Please retry."
     };
+    let status = serde_json::json!({
+        "type": "container_status",
+        "conversation_id": conversation_id,
+        "status": "disconnected",
+        "reason": reason,
+        "message": status_message
+    })
+    .to_string();
+    ws_state
+        .send_to_client(user_id, conversation_id, &status)
+        .await;
+    state.sse_state.close(conversation_id, &status).await;
+}
+
+/// Why a container could not be initialized; reported through
+/// [`fail_container_init`].
+#[derive(Debug)]
+struct InitFailure {
+    code: &'static str,
+    message: String,
+}
+
+impl InitFailure {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Provider failures that switching to the fallback provider may fix.
+    fn allows_failover(&self) -> bool {
+        matches!(self.code, "decrypt_failed" | "container_init_failed")
+    }
+}
+
+/// Switch the conversation to its chat provider's fallback, keeping the
+/// model if the fallback offers it, and tell the client. Returns whether the
+/// conversation was switched.
+async fn fail_over_conversation_provider(
+    state: &Arc<AppState>,
+    ws_state: &Arc<WsState>,
+    user_id: &str,
+    conversation_id: &str,
+) -> bool {
+    let Some(conv) = db::conversations::get_conversation(&state.db, conversation_id, user_id)
+        .await
+        .ok()
+        .flatten()
+    else {
+        return false;
+    };
+    let Some(provider_id) = non_empty_str(conv.provider_id.as_deref()) else {
+        return false;
+    };
+    let fallback = match db::providers::get_provider_fallback(&state.db, provider_id, user_id).await
+    {
+        Ok(Some(fallback)) => fallback,
+        Ok(None) => return false,
+        Err(e) => {
+            tracing::warn!(
+                conversation_id = %conversation_id,
+                error = %e,
+                "Failed to load fallback provider"
+            );
+            return false;
+        }
+    };
+
+    let models = parse_models_json(fallback.models.as_deref());
+    let offered = |m: &&str| models.iter().any(|model| model == m);
+    let Some(model_name) = non_empty_str(conv.model_name.as_deref())
+        .filter(offered)
+        .or_else(|| fallback.model_name.as_deref().filter(offered))
+        .or_else(|| models.first().map(String::as_str))
+    else {
+        tracing::warn!(
+            conversation_id = %conversation_id,
+            provider_id = %fallback.id,
+            "Fallback provider has no chat models"
+        );
+        return false;
+    };
+
+    match db::conversations::fail_over_provider(
+        &state.db,
+        conversation_id,
+        user_id,
+        provider_id,
+        &fallback.id,
+        model_name,
+    )
+    .await
+    {
+        Ok(true) => {}
+        Ok(false) => return false,
+        Err(e) => {
+            tracing::warn!(
+                conversation_id = %conversation_id,
+                error = %e,
+                "Failed to switch conversation to fallback provider"
+            );
+            return false;
+        }
+    }
+
+    tracing::info!(
+        conversation_id = %conversation_id,
+        from_provider_id = %provider_id,
+        provider_id = %fallback.id,
+        "Failed over to fallback provider"
+    );
     ws_state
         .send_to_client(
             user_id,
             conversation_id,
             &serde_json::json!({
-                "type": "container_status",
+                "type": "provider_failover",
                 "conversation_id": conversation_id,
-                "status": "disconnected",
-                "reason": reason,
-                "message": status_message
+                "from_provider_id": provider_id,
+                "provider_id": fallback.id,
+                "model_name": model_name,
             })
             .to_string(),
         )
         .await;
+    true
 }
 
 #[derive(serde::Deserialize)]
@@ -181,6 +330,64 @@ pub struct ContainerWsQuery {
     pub token: String,
 }
 
+/// In-progress tool calls for one container session, keyed by tool call id.
+struct ToolCallTracker {
+    timeout: Duration,
+    started: HashMap<String, Instant>,
+}
+
+impl ToolCallTracker {
+    fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            started: HashMap::new(),
+        }
+    }
+
+    fn start(&mut self, tool_call_id: String, now: Instant) {
+        self.started.insert(tool_call_id, now);
+    }
+
+    fn finish(&mut self, tool_call_id: &str) {
+        self.started.remove(tool_call_id);
+    }
+
+    fn clear(&mut self) {
+        self.started.clear();
+    }
+
+    /// The earliest instant at which an in-progress tool call times out.
+    fn next_deadline(&self) -> Option<Instant> {
+        self.started.values().min().map(|t| *t + self.timeout)
+    }
+
+    /// Remove and return every tool call that has exceeded the timeout.
+    fn take_expired(&mut self, now: Instant) -> Vec<String> {
+        let mut expired: Vec<String> = self
+            .started
+            .iter()
+            .filter(|(_, started)| now.duration_since(**started) >= self.timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+        expired.sort();
+        for id in &expired {
+            self.started.remove(id);
+        }
+        expired
+    }
+}
+
+fn tool_call_timeout_message(conversation_id: &str, tool_call_id: &str) -> String {
+    serde_json::json!({
+        "type": "error",
+        "code": "tool_call_timeout",
+        "conversation_id": conversation_id,
+        "tool_call_id": tool_call_id,
+        "message": "Tool call timed out",
+    })
+    .to_string()
+}
+
 pub async fn container_ws_handler(
     ws: WebSocketUpgrade,
     Query(query): Query<ContainerWsQuery>,
@@ -191,6 +398,15 @@ pub async fn container_ws_handler(
         Err(_) => return axum::http::StatusCode::UNAUTHORIZED.into_response(),
     };
 
+    let retention = Duration::from_secs(state.config.container_token_ttl_secs.saturating_mul(2));
+    if !state.ws_state.claim_jti(&claims.jti, retention).await {
+        tracing::warn!(
+            "Rejected replayed container token for conversation {}",
+            claims.sub
+        );
+        return axum::http::StatusCode::UNAUTHORIZED.into_response();
+    }
+
     let ws_state = state.ws_state.clone();
 
     ws.on_upgrade(move |socket| {
@@ -198,12 +414,43 @@ pub async fn container_ws_handler(
     })
 }
 
+/// Parent span for everything that happens on one container connection.
+fn container_session_span(conversation_id: &str, user_id: &str) -> tracing::Span {
+    tracing::info_span!(
+        "container_session",
+        conversation_id = %conversation_id,
+        user_id = %user_id
+    )
+}
+
+fn record_container_ready(conversation_id: &str) {
+    tracing::info!(
+        name: "container.ready",
+        conversation_id = %conversation_id,
+        "Container ready for conversation {}",
+        conversation_id
+    );
+}
+
 async fn handle_container_ws(
     socket: WebSocket,
     conversation_id: String,
     user_id: String,
     state: Arc<AppState>,
     ws_state: Arc<WsState>,
+) {
+    let span = container_session_span(&conversation_id, &user_id);
+    run_container_session(socket, conversation_id, user_id, state, ws_state)
+        .instrument(span)
+        .await
+}
+
+async fn run_container_session(
+    socket: WebSocket,
+    conversation_id: String,
+    user_id: String,
+    state: Arc<AppState>,
+    ws_state: Arc<WsState>,
 ) {
     let (mut ws_sink, mut ws_stream) = socket.split();
     let (tx, mut rx) = mpsc::channel::<String>(super::WS_CHANNEL_CAPACITY);
@@ -232,8 +479,61 @@ async fn handle_container_ws(
             .to_string(),
         )
         .await;
+    if let Err(e) =
+        db::conversations::set_last_container_error(&state.db, &conversation_id, None).await
+    {
+        tracing::warn!(
+            "Failed to clear container error for {}: {}",
+            conversation_id,
+            e
+        );
+    }
+
+    let mut tool_calls =
+        ToolCallTracker::new(Duration::from_secs(state.config.tool_call_timeout_secs));
+
+    loop {
+        let next = match tool_calls.next_deadline() {
+            Some(deadline) => tokio::select! {
+                msg = ws_stream.next() => Some(msg),
+                _ = tokio::time::sleep_until(deadline) => None,
+            },
+            None => Some(ws_stream.next().await),
+        };
+
+        let msg = match next {
+            Some(Some(Ok(msg))) => msg,
+            Some(_) => break,
+            None => {
+                let expired = tool_calls.take_expired(Instant::now());
+                if expired.is_empty() {
+                    continue;
+                }
+                for tool_call_id in &expired {
+                    tracing::warn!(
+                        conversation_id = %conversation_id,
+                        tool_call_id = %tool_call_id,
+                        "Tool call timed out; stopping container"
+                    );
+                    ws_state
+                        .send_to_client(
+                            &user_id,
+                            &conversation_id,
+                            &tool_call_timeout_message(&conversation_id, tool_call_id),
+                        )
+                        .await;
+                }
+                if let Err(e) = state.docker_manager.stop_container(&conversation_id).await {
+                    tracing::error!(
+                        conversation_id = %conversation_id,
+                        error = %e,
+                        "Failed to stop container after tool call timeout"
+                    );
+                }
+                break;
+            }
+        };
 
-    while let Some(Ok(msg)) = ws_stream.next().await {
         let text = match msg {
             Message::Text(t) => t.to_string(),
             Message::Close(_) => break,
@@ -256,278 +556,86 @@ async fn handle_container_ws(
         // Refresh activity timestamp so the idle-cleanup task doesn't kill active containers.
         state.docker_manager.touch_activity(&conversation_id).await;
 
+        match &container_msg {
+            ContainerMessage::ToolCall {
+                tool_call_id: Some(id),
+            } => tool_calls.start(id.clone(), Instant::now()),
+            ContainerMessage::ToolResult {
+                tool_call_id: Some(id),
+            } => tool_calls.finish(id),
+            ContainerMessage::Complete { .. } | ContainerMessage::Error => tool_calls.clear(),
+            _ => {}
+        }
+
         match container_msg {
             ContainerMessage::Ready => {
-                tracing::info!("Container ready for conversation {}", conversation_id);
-                if let Some(conv) =
-                    db::conversations::get_conversation(&state.db, &conversation_id, &user_id)
-                        .await
-                        .ok()
-                        .flatten()
-                {
-                    let providers = match db::providers::list_providers(&state.db, &user_id).await {
-                        Ok(providers) => providers,
-                        Err(e) => {
-                            tracing::error!(
-                                conversation_id = %conversation_id,
-                                error = %e,
-                                "Failed to load providers for container init"
-                            );
-                            fail_container_init(
-                                &state,
-                                &ws_state,
-                                &user_id,
-                                &conversation_id,
-                                "container_init_failed",
-                                "Failed to load providers. Please retry.",
-                            )
-                            .await;
-                            break;
-                        }
-                    };
-                    let resolved = match resolve_conversation_providers(&conv, &providers) {
-                        Ok(resolved) => resolved,
-                        Err(message) => {
-                            tracing::warn!(
-                                conversation_id = %conversation_id,
-                                message = %message,
-                                "Conversation model config is invalid for container init"
-                            );
-                            fail_container_init(
-                                &state,
-                                &ws_state,
-                                &user_id,
-                                &conversation_id,
-                                "conversation_model_config_invalid",
-                                &message,
-                            )
-                            .await;
-                            break;
-                        }
-                    };
-
-                    let messages = db::messages::list_messages(
-                        &state.db,
+                record_container_ready(&conversation_id);
+                let mut result =
+                    send_container_init(&state, &ws_state, &tx, &user_id, &conversation_id).await;
+                if let Err(failure) = &result
+                    && failure.allows_failover()
+                    && fail_over_conversation_provider(
+                        &state,
+                        &ws_state,
+                        &user_id,
                         &conversation_id,
-                        super::CONTAINER_INIT_HISTORY_LIMIT,
-                        0,
                     )
                     .await
-                    .unwrap_or_default();
-
-                    // Check if last message is from user — it will be resent separately
-                    let needs_resend = messages.last().is_some_and(|m| m.role == "user");
-                    let history_messages = if needs_resend {
-                        &messages[..messages.len() - 1]
-                    } else {
-                        &messages[..]
-                    };
-
-                    let history: Vec<serde_json::Value> = history_messages
-                        .iter()
-                        .map(|m| {
-                            let mut entry = serde_json::json!({
-                                "role": m.role,
-                                "content": m.content,
-                            });
-                            if let Some(ref tc) = m.tool_calls
-                                && let Ok(parsed) = serde_json::from_str::<serde_json::Value>(tc)
-                                && parsed.is_array()
-                            {
-                                entry["tool_calls"] = parsed;
-                            }
-                            entry
-                        })
-                        .collect();
-                    let history_parts =
-                        build_history_parts_for_init(&state.db, history_messages).await;
-
-                    let mcp_servers =
-                        db::mcp_servers::get_conversation_mcp_servers(&state.db, &conversation_id)
-                            .await
-                            .unwrap_or_default();
-                    let mcp_configs: Vec<serde_json::Value> = mcp_servers
-                        .iter()
-                        .map(|s| {
-                            serde_json::json!({
-                                "name": s.name,
-                                "transport": s.transport,
-                                "command": s.command,
-                                "args": s.args,
-                                "url": s.url,
-                                "env_vars": s.env_vars,
-                                "read_only_overrides": s.read_only_overrides,
-                            })
-                        })
-                        .collect();
-
-                    let api_key = match crate::crypto::decrypt(
-                        &resolved.chat_provider.api_key_encrypted,
-                        &state.config.encryption_key,
-                    ) {
-                        Ok(key) => key,
-                        Err(e) => {
-                            tracing::error!(
-                                conversation_id = %conversation_id,
-                                provider_id = %resolved.chat_provider.id,
-                                error = %e,
-                                "Failed to decrypt chat provider API key"
-                            );
-                            fail_container_init(
-                                &state,
-                                &ws_state,
-                                &user_id,
-                                &conversation_id,
-                                "decrypt_failed",
-                                "Failed to decrypt chat provider API key. Please re-save provider settings.",
-                            )
-                            .await;
-                            break;
-                        }
-                    };
-
-                    let subagent_api_key = match crate::crypto::decrypt(
-                        &resolved.subagent_provider.api_key_encrypted,
-                        &state.config.encryption_key,
-                    ) {
-                        Ok(key) => key,
-                        Err(e) => {
-                            tracing::error!(
-                                conversation_id = %conversation_id,
-                                provider_id = %resolved.subagent_provider.id,
-                                error = %e,
-                                "Failed to decrypt subagent provider API key"
-                            );
-                            fail_container_init(
-                                &state,
-                                &ws_state,
-                                &user_id,
-                                &conversation_id,
-                                "decrypt_failed",
-                                "Failed to decrypt subagent provider API key. Please re-save provider settings.",
-                            )
+                {
+                    result =
+                        send_container_init(&state, &ws_state, &tx, &user_id, &conversation_id)
                             .await;
-                            break;
-                        }
-                    };
-
-                    let image_api_key = if let Some(image_provider) =
-                        resolved.image_provider.as_ref()
-                    {
-                        match crate::crypto::decrypt(
-                            &image_provider.api_key_encrypted,
-                            &state.config.encryption_key,
-                        ) {
-                            Ok(key) => key,
-                            Err(e) => {
-                                tracing::error!(
-                                    conversation_id = %conversation_id,
-                                    provider_id = %image_provider.id,
-                                    error = %e,
-                                    "Failed to decrypt image provider API key"
-                                );
-                                fail_container_init(
-                                    &state,
-                                    &ws_state,
-                                    &user_id,
-                                    &conversation_id,
-                                    "decrypt_failed",
-                                    "Failed to decrypt image provider API key. Please re-save provider settings.",
-                                )
-                                .await;
-                                break;
-                            }
-                        }
-                    } else {
-                        String::new()
-                    };
-
-                    let image_provider_type = resolved
-                        .image_provider
-                        .as_ref()
-                        .map(|p| p.provider.clone())
-                        .unwrap_or_default();
-                    let image_endpoint_url = resolved
-                        .image_provider
-                        .as_ref()
-                        .and_then(|p| p.endpoint_url.clone());
-                    let chat_provider_type = resolved.chat_provider.provider.clone();
-                    let chat_endpoint_url = resolved.chat_provider.endpoint_url.clone();
-                    let subagent_provider_type = resolved.subagent_provider.provider.clone();
-                    let subagent_endpoint_url = resolved.subagent_provider.endpoint_url.clone();
-                    let chat_model = resolved.chat_model.clone();
-                    let subagent_model = resolved.subagent_model.clone();
-                    let image_model = resolved.image_model.clone();
-
-                    let init_msg = serde_json::json!({
-                        "type": "init",
-                        "conversation_id": conversation_id,
-                        "provider": chat_provider_type,
-                        "model": chat_model,
-                        "api_key": api_key,
-                        "endpoint_url": chat_endpoint_url,
-                        "subagent_provider": subagent_provider_type,
-                        "subagent_model": subagent_model,
-                        "subagent_thinking_budget": conv.subagent_thinking_budget,
-                        "subagent_api_key": subagent_api_key,
-                        "subagent_endpoint_url": subagent_endpoint_url,
-                        "system_prompt": conv.system_prompt_override,
-                        "thinking_budget": conv.thinking_budget,
-                        "tools_enabled": true,
-                        "mcp_servers": mcp_configs,
-                        "history": history,
-                        "history_parts": history_parts,
-                        "image_provider": image_provider_type,
-                        "image_model": image_model,
-                        "image_api_key": image_api_key,
-                        "image_endpoint_url": image_endpoint_url,
-                    });
-
-                    let init_msg_text = init_msg.to_string();
-                    if init_msg_text.len() > INIT_PAYLOAD_WARN_BYTES {
-                        tracing::warn!(
-                            conversation_id = %conversation_id,
-                            payload_bytes = init_msg_text.len(),
-                            "Container init payload is large; this may cause agent reconnect loops"
-                        );
-                    }
-                    let _ = tx.try_send(init_msg_text);
-
-                    // If there's a pending message (queued while container was starting),
-                    // send it as-is (preserves deep_thinking and other fields).
-                    // Otherwise fall back to re-sending the last user message from history.
-                    if let Some(pending) = ws_state.take_pending_message(&conversation_id).await {
-                        let _ = tx.try_send(pending);
-                    } else if let Some(last) = messages.last()
-                        && last.role == "user"
-                    {
-                        let resend = serde_json::json!({
-                            "type": "user_message",
-                            "message_id": &last.id,
-                            "content": &last.content,
-                            "deep_thinking": conv.deep_thinking,
-                            "thinking_budget": conv.thinking_budget,
-                            "subagent_thinking_budget": conv.subagent_thinking_budget,
-                        });
-                        let _ = tx.try_send(resend.to_string());
-                    }
+                }
+                if let Err(failure) = result {
+                    fail_container_init(
+                        &state,
+                        &ws_state,
+                        &user_id,
+                        &conversation_id,
+                        failure.code,
+                        &failure.message,
+                    )
+                    .await;
+                    break;
                 }
             }
-            ContainerMessage::Forward => {
-                tracing::debug!("Forwarding to client for {}", conversation_id);
+            ContainerMessage::Forward
+            | ContainerMessage::ToolCall { .. }
+            | ContainerMessage::ToolResult { .. } => {
+                tracing::debug!(
+                    name: "container.forward",
+                    "Forwarding to client for {}",
+                    conversation_id
+                );
                 if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(&text) {
-                    let forwarded = with_conversation_id(&parsed, &conversation_id);
+                    let forwarded = with_conversation_id(&parsed, &conversation_id).to_string();
                     ws_state
-                        .send_to_client(&user_id, &conversation_id, &forwarded.to_string())
+                        .send_to_client(&user_id, &conversation_id, &forwarded)
                         .await;
+                    state.sse_state.publish(&conversation_id, &forwarded).await;
                 }
             }
+            ContainerMessage::TokenUsageUpdate {
+                total_tokens,
+                completion_tokens,
+            } => {
+                let event =
+                    token_usage_update_event(&conversation_id, total_tokens, completion_tokens);
+                ws_state
+                    .send_to_client(&user_id, &conversation_id, &event.to_string())
+                    .await;
+            }
             ContainerMessage::Complete {
                 content,
                 tool_calls,
                 token_usage,
             } => {
                 let content_str = content.as_deref().unwrap_or("");
+                tracing::info!(
+                    name: "container.complete",
+                    content_length = content_str.len(),
+                    "Container completed a turn"
+                );
                 let token_count = token_usage
                     .as_ref()
                     .and_then(|u| u.get("completion"))
@@ -555,6 +663,23 @@ async fn handle_container_ws(
                         continue;
                     }
                 };
+                if let Some((input_tokens, output_tokens)) =
+                    token_usage.as_ref().and_then(usage_token_counts)
+                    && let Err(e) = db::pricing::record_message_cost(
+                        &state.db,
+                        &saved_msg.id,
+                        &conversation_id,
+                        input_tokens,
+                        output_tokens,
+                    )
+                    .await
+                {
+                    tracing::error!(
+                        conversation_id = %conversation_id,
+                        error = %e,
+                        "Failed to record assistant message cost"
+                    );
+                }
                 if let Err(e) = db::conversations::touch_conversation_activity(
                     &state.db,
                     &conversation_id,
@@ -618,21 +743,27 @@ async fn handle_container_ws(
                         serde_json::Value::String(saved_msg.id),
                     );
                 }
+                let forwarded = forwarded.to_string();
                 ws_state
-                    .send_to_client(&user_id, &conversation_id, &forwarded.to_string())
+                    .send_to_client(&user_id, &conversation_id, &forwarded)
                     .await;
+                state.sse_state.publish(&conversation_id, &forwarded).await;
             }
             ContainerMessage::Error => {
+                tracing::warn!(name: "container.error", "Container reported an error");
                 if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(&text) {
-                    let forwarded = with_conversation_id(&parsed, &conversation_id);
+                    let forwarded = with_conversation_id(&parsed, &conversation_id).to_string();
                     ws_state
-                        .send_to_client(&user_id, &conversation_id, &forwarded.to_string())
+                        .send_to_client(&user_id, &conversation_id, &forwarded)
                         .await;
+                    state.sse_state.publish(&conversation_id, &forwarded).await;
                 }
             }
         }
     }
 
+    tracing::info!(name: "container.disconnected", "Container connection closed");
+
     // Only clean up if this is still the active container for this conversation.
     // A newer container may have already replaced us (e.g. after a model switch).
     let removed = ws_state
@@ -640,25 +771,262 @@ async fn handle_container_ws(
         .await;
 
     if removed {
+        let status = serde_json::json!({
+            "type": "container_status",
+            "conversation_id": conversation_id,
+            "status": "disconnected",
+            "reason": "unexpected_disconnect",
+            "message": "Container disconnected"
+        })
+        .to_string();
         ws_state
-            .send_to_client(
-                &user_id,
-                &conversation_id,
-                &serde_json::json!({
-                    "type": "container_status",
-                    "conversation_id": conversation_id,
-                    "status": "disconnected",
-                    "reason": "unexpected_disconnect",
-                    "message": "Container disconnected"
-                })
-                .to_string(),
-            )
+            .send_to_client(&user_id, &conversation_id, &status)
             .await;
+        state.sse_state.close(&conversation_id, &status).await;
     }
 
     send_task.abort();
 }
 
+/// Send the `init` message (and any message waiting for the container) for
+/// a conversation whose container just reported ready.
+async fn send_container_init(
+    state: &Arc<AppState>,
+    ws_state: &Arc<WsState>,
+    tx: &mpsc::Sender<String>,
+    user_id: &str,
+    conversation_id: &str,
+) -> Result<(), InitFailure> {
+    let Some(conv) = db::conversations::get_conversation(&state.db, conversation_id, user_id)
+        .await
+        .ok()
+        .flatten()
+    else {
+        return Ok(());
+    };
+    let conv = with_feature_flags(conv, &state.features);
+    let providers = match db::providers::list_providers(&state.db, user_id).await {
+        Ok(providers) => providers,
+        Err(e) => {
+            tracing::error!(
+                conversation_id = %conversation_id,
+                error = %e,
+                "Failed to load providers for container init"
+            );
+            return Err(InitFailure::new(
+                "container_init_failed",
+                "Failed to load providers. Please retry.",
+            ));
+        }
+    };
+    let resolved = match resolve_conversation_providers(&conv, &providers) {
+        Ok(resolved) => resolved,
+        Err(message) => {
+            tracing::warn!(
+                conversation_id = %conversation_id,
+                message = %message,
+                "Conversation model config is invalid for container init"
+            );
+            return Err(InitFailure::new(
+                "conversation_model_config_invalid",
+                message,
+            ));
+        }
+    };
+
+    let messages = db::messages::list_messages(
+        &state.db,
+        conversation_id,
+        super::CONTAINER_INIT_HISTORY_LIMIT,
+        0,
+    )
+    .await
+    .unwrap_or_default();
+
+    // Check if last message is from user — it will be resent separately
+    let needs_resend = messages.last().is_some_and(|m| m.role == "user");
+    let history_messages = if needs_resend {
+        &messages[..messages.len() - 1]
+    } else {
+        &messages[..]
+    };
+
+    let history: Vec<serde_json::Value> = history_messages
+        .iter()
+        .map(|m| {
+            let mut entry = serde_json::json!({
+                "role": m.role,
+                "content": m.content,
+            });
+            if let Some(ref tc) = m.tool_calls
+                && let Ok(parsed) = serde_json::from_str::<serde_json::Value>(tc)
+                && parsed.is_array()
+            {
+                entry["tool_calls"] = parsed;
+            }
+            entry
+        })
+        .collect();
+    let history_parts = build_history_parts_for_init(&state.db, history_messages).await;
+
+    let mcp_servers = if state.features.enable_mcp {
+        db::mcp_servers::get_conversation_mcp_servers(&state.db, conversation_id)
+            .await
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let mcp_configs: Vec<serde_json::Value> = mcp_servers
+        .iter()
+        .filter(|s| s.is_enabled)
+        .map(|s| {
+            serde_json::json!({
+                "name": s.name,
+                "transport": s.transport,
+                "command": s.command,
+                "args": s.args,
+                "url": s.url,
+                "env_vars": s.env_vars,
+                "read_only_overrides": s.read_only_overrides,
+            })
+        })
+        .collect();
+
+    let api_key = match crate::crypto::decrypt(
+        &resolved.chat_provider.api_key_encrypted,
+        &state.config.encryption_key,
+    ) {
+        Ok(key) => key,
+        Err(e) => {
+            tracing::error!(
+                conversation_id = %conversation_id,
+                provider_id = %resolved.chat_provider.id,
+                error = %e,
+                "Failed to decrypt chat provider API key"
+            );
+            return Err(InitFailure::new(
+                "decrypt_failed",
+                "Failed to decrypt chat provider API key. Please re-save provider settings.",
+            ));
+        }
+    };
+
+    let subagent_api_key = match crate::crypto::decrypt(
+        &resolved.subagent_provider.api_key_encrypted,
+        &state.config.encryption_key,
+    ) {
+        Ok(key) => key,
+        Err(e) => {
+            tracing::error!(
+                conversation_id = %conversation_id,
+                provider_id = %resolved.subagent_provider.id,
+                error = %e,
+                "Failed to decrypt subagent provider API key"
+            );
+            return Err(InitFailure::new(
+                "decrypt_failed",
+                "Failed to decrypt subagent provider API key. Please re-save provider settings.",
+            ));
+        }
+    };
+
+    let image_api_key = if let Some(image_provider) = resolved.image_provider.as_ref() {
+        match crate::crypto::decrypt(
+            &image_provider.api_key_encrypted,
+            &state.config.encryption_key,
+        ) {
+            Ok(key) => key,
+            Err(e) => {
+                tracing::error!(
+                    conversation_id = %conversation_id,
+                    provider_id = %image_provider.id,
+                    error = %e,
+                    "Failed to decrypt image provider API key"
+                );
+                return Err(InitFailure::new(
+                    "decrypt_failed",
+                    "Failed to decrypt image provider API key. Please re-save provider settings.",
+                ));
+            }
+        }
+    } else {
+        String::new()
+    };
+
+    let image_provider_type = resolved
+        .image_provider
+        .as_ref()
+        .map(|p| p.provider.clone())
+        .unwrap_or_default();
+    let image_endpoint_url = resolved
+        .image_provider
+        .as_ref()
+        .and_then(|p| p.endpoint_url.clone());
+    let chat_provider_type = resolved.chat_provider.provider.clone();
+    let chat_endpoint_url = resolved.chat_provider.endpoint_url.clone();
+    let subagent_provider_type = resolved.subagent_provider.provider.clone();
+    let subagent_endpoint_url = resolved.subagent_provider.endpoint_url.clone();
+    let chat_model = resolved.chat_model.clone();
+    let subagent_model = resolved.subagent_model.clone();
+    let image_model = resolved.image_model.clone();
+
+    let init_msg = serde_json::json!({
+        "type": "init",
+        "conversation_id": conversation_id,
+        "provider": chat_provider_type,
+        "model": chat_model,
+        "api_key": api_key,
+        "endpoint_url": chat_endpoint_url,
+        "subagent_provider": subagent_provider_type,
+        "subagent_model": subagent_model,
+        "subagent_thinking_budget": conv.subagent_thinking_budget,
+        "subagent_api_key": subagent_api_key,
+        "subagent_endpoint_url": subagent_endpoint_url,
+        "system_prompt": conv.system_prompt_override,
+        "thinking_budget": conv.thinking_budget,
+        "tools_enabled": true,
+        "mcp_servers": mcp_configs,
+        "history": history,
+        "history_parts": history_parts,
+        "image_provider": image_provider_type,
+        "image_model": image_model,
+        "image_api_key": image_api_key,
+        "image_endpoint_url": image_endpoint_url,
+        "token_usage_update_interval_tokens":
+            state.config.token_usage_update_interval_tokens,
+    });
+
+    let init_msg_text = init_msg.to_string();
+    if init_msg_text.len() > INIT_PAYLOAD_WARN_BYTES {
+        tracing::warn!(
+            conversation_id = %conversation_id,
+            payload_bytes = init_msg_text.len(),
+            "Container init payload is large; this may cause agent reconnect loops"
+        );
+    }
+    let _ = tx.try_send(init_msg_text);
+
+    // If there's a pending message (queued while container was starting),
+    // send it as-is (preserves deep_thinking and other fields).
+    // Otherwise fall back to re-sending the last user message from history.
+    if let Some(pending) = ws_state.take_pending_message(conversation_id).await {
+        let _ = tx.try_send(pending);
+    } else if let Some(last) = messages.last()
+        && last.role == "user"
+    {
+        let resend = serde_json::json!({
+            "type": "user_message",
+            "message_id": &last.id,
+            "content": &last.content,
+            "deep_thinking": conv.deep_thinking,
+            "thinking_budget": conv.thinking_budget,
+            "subagent_thinking_budget": conv.subagent_thinking_budget,
+        });
+        let _ = tx.try_send(resend.to_string());
+    }
+    Ok(())
+}
+
 async fn build_history_parts_for_init(
     pool: &sqlx::SqlitePool,
     history_messages: &[db::messages::Message],
@@ -751,14 +1119,50 @@ fn with_conversation_id(parsed: &serde_json::Value, conversation_id: &str) -> se
     forwarded
 }
 
+/// Client payload for a mid-turn token usage update. Only the known fields
+/// are forwarded; usage is persisted once the turn completes.
+/// Input and output token counts from a `complete` frame's `token_usage`,
+/// which may use OpenAI (`prompt`/`completion`) or LangChain
+/// (`input_tokens`/`output_tokens`) key names.
+fn usage_token_counts(token_usage: &serde_json::Value) -> Option<(i64, i64)> {
+    let count = |keys: &[&str]| keys.iter().find_map(|k| token_usage.get(*k)?.as_i64());
+    let input = count(&["prompt", "input_tokens"]);
+    let output = count(&["completion", "output_tokens"]);
+    if input.is_none() && output.is_none() {
+        return None;
+    }
+    Some((input.unwrap_or(0), output.unwrap_or(0)))
+}
+
+fn token_usage_update_event(
+    conversation_id: &str,
+    total_tokens: i64,
+    completion_tokens: i64,
+) -> serde_json::Value {
+    serde_json::json!({
+        "type": "token_usage_update",
+        "total_tokens": total_tokens,
+        "completion_tokens": completion_tokens,
+        "conversation_id": conversation_id,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        build_parts_from_complete, legacy_parts_for_init, resolve_conversation_providers,
-        with_conversation_id,
+        AppState, ToolCallTracker, WebSocket, WebSocketUpgrade, WsState, build_parts_from_complete,
+        db, handle_container_ws, legacy_parts_for_init, resolve_conversation_providers,
+        token_usage_update_event, tool_call_timeout_message, usage_token_counts,
+        with_conversation_id, with_feature_flags,
     };
+    use crate::config::FeatureFlags;
     use crate::db::{conversations::Conversation, messages::Message, providers::UserProvider};
+    use futures_util::SinkExt;
+    use std::sync::Arc;
+    use std::time::Duration;
     use tokio::sync::mpsc;
+    use tokio::time::Instant;
+    use tracing_test::traced_test;
 
     fn mk_provider(
         id: &str,
@@ -778,6 +1182,7 @@ mod tests {
             models: Some(serde_json::to_string(models).unwrap()),
             name: Some(id.to_string()),
             image_models: Some(serde_json::to_string(image_models).unwrap()),
+            fallback_provider_id: None,
         }
     }
 
@@ -799,9 +1204,113 @@ mod tests {
             share_token: None,
             thinking_budget: Some(128000),
             subagent_thinking_budget: Some(128000),
+            pinned: false,
+            last_container_error: None,
+            archived_at: None,
+            container_idle_timeout_secs: None,
         }
     }
 
+    #[test]
+    fn with_feature_flags_hides_disabled_models() {
+        let mut conv = mk_conversation();
+        conv.image_provider_id = Some("img".to_string());
+        conv.image_model = Some("img-1".to_string());
+
+        let unchanged = with_feature_flags(conv.clone(), &FeatureFlags::default());
+        assert_eq!(unchanged.subagent_provider_id.as_deref(), Some("sub"));
+        assert_eq!(unchanged.image_model.as_deref(), Some("img-1"));
+
+        let features = FeatureFlags {
+            enable_deep_thinking: false,
+            enable_image_generation: false,
+            ..FeatureFlags::default()
+        };
+        let gated = with_feature_flags(conv, &features);
+        assert!(!gated.deep_thinking);
+        assert_eq!(gated.subagent_provider_id.as_deref(), Some("chat"));
+        assert_eq!(gated.subagent_model.as_deref(), Some("gpt-4o"));
+        assert_eq!(gated.subagent_thinking_budget, None);
+        assert_eq!(gated.image_provider_id, None);
+        assert_eq!(gated.image_model, None);
+    }
+
+    #[test]
+    fn tool_call_tracker_expires_unfinished_calls() {
+        let mut tracker = ToolCallTracker::new(Duration::from_secs(300));
+        let start = Instant::now();
+        tracker.start("tc-1".into(), start);
+        tracker.start("tc-2".into(), start + Duration::from_secs(10));
+
+        assert_eq!(
+            tracker.next_deadline(),
+            Some(start + Duration::from_secs(300))
+        );
+        assert!(
+            tracker
+                .take_expired(start + Duration::from_secs(299))
+                .is_empty()
+        );
+        assert_eq!(
+            tracker.take_expired(start + Duration::from_secs(300)),
+            vec!["tc-1".to_string()]
+        );
+        assert_eq!(
+            tracker.next_deadline(),
+            Some(start + Duration::from_secs(310))
+        );
+    }
+
+    #[test]
+    fn tool_call_tracker_finished_calls_do_not_expire() {
+        let mut tracker = ToolCallTracker::new(Duration::from_secs(300));
+        let start = Instant::now();
+        tracker.start("tc-1".into(), start);
+        tracker.start("tc-2".into(), start);
+
+        tracker.finish("tc-1");
+        assert_eq!(
+            tracker.take_expired(start + Duration::from_secs(600)),
+            vec!["tc-2".to_string()]
+        );
+
+        tracker.start("tc-3".into(), start);
+        tracker.clear();
+        assert!(tracker.next_deadline().is_none());
+        assert!(
+            tracker
+                .take_expired(start + Duration::from_secs(600))
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn tool_call_deadline_fires_before_next_message() {
+        let mut tracker = ToolCallTracker::new(Duration::from_millis(20));
+        tracker.start("tc-1".into(), Instant::now());
+        let (_tx, mut rx) = mpsc::channel::<String>(1);
+
+        let timed_out = tokio::select! {
+            _ = rx.recv() => false,
+            _ = tokio::time::sleep_until(tracker.next_deadline().unwrap()) => true,
+        };
+        assert!(timed_out);
+        assert_eq!(
+            tracker.take_expired(Instant::now()),
+            vec!["tc-1".to_string()]
+        );
+    }
+
+    #[test]
+    fn tool_call_timeout_message_shape() {
+        let msg: serde_json::Value =
+            serde_json::from_str(&tool_call_timeout_message("conv-1", "tc-1")).unwrap();
+        assert_eq!(msg["type"], "error");
+        assert_eq!(msg["code"], "tool_call_timeout");
+        assert_eq!(msg["tool_call_id"], "tc-1");
+        assert_eq!(msg["conversation_id"], "conv-1");
+    }
+
     #[test]
     fn resolve_conversation_providers_requires_explicit_models() {
         let mut conv = mk_conversation();
@@ -965,7 +1474,7 @@ mod tests {
     async fn forward_task_trace_delta_roundtrip_preserves_payload_over_ws_state() {
         let ws_state = crate::ws::WsState::new();
         let (tx, mut rx) = mpsc::channel(crate::ws::WS_CHANNEL_CAPACITY);
-        ws_state.add_client("user-1", "conv-123", tx).await;
+        ws_state.add_client("user-1", "conv-123", 1, tx).await;
 
         let event = serde_json::json!({
             "type": "task_trace_delta",
@@ -1043,7 +1552,7 @@ mod tests {
     async fn forward_subagent_trace_delta_roundtrip_preserves_payload_over_ws_state() {
         let ws_state = crate::ws::WsState::new();
         let (tx, mut rx) = mpsc::channel(crate::ws::WS_CHANNEL_CAPACITY);
-        ws_state.add_client("user-1", "conv-123", tx).await;
+        ws_state.add_client("user-1", "conv-123", 1, tx).await;
 
         let event = serde_json::json!({
             "type": "subagent_trace_delta",
@@ -1082,4 +1591,192 @@ mod tests {
         );
         assert_eq!(parsed["conversation_id"], "conv-123");
     }
+
+    async fn test_state() -> Arc<AppState> {
+        use crate::auth::JwtKeys;
+        use crate::docker::{manager::DockerManager, registry::ContainerRegistry};
+
+        let config: crate::config::Config = envy::from_iter([
+            (
+                "JWT_SECRET".to_string(),
+                "test-jwt-secret-that-is-long-enough-for-hmac".to_string(),
+            ),
+            ("ENCRYPTION_KEY".to_string(), "00".repeat(32)),
+        ])
+        .unwrap();
+        Arc::new(AppState {
+            db: db::init_db("sqlite::memory:").await,
+            jwt_keys: JwtKeys::from_config(&config).unwrap(),
+            docker_manager: Arc::new(DockerManager::new_for_test(
+                config.clone(),
+                ContainerRegistry::new(),
+            )),
+            config,
+            ws_state: WsState::new(),
+            sse_state: crate::ws::sse::SseState::new(),
+            pending_uploads: Default::default(),
+            provider_api: Arc::new(crate::provider_api::HttpProviderApiClient::default()),
+            mailer: None,
+            system_stats_cache: Default::default(),
+            features: Default::default(),
+        })
+    }
+
+    /// Accept one WebSocket connection and hand the server side back, so the
+    /// test can drive `handle_container_ws` inside its own span.
+    async fn accept_socket() -> (
+        WebSocket,
+        tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+    ) {
+        let (socket_tx, socket_rx) = tokio::sync::oneshot::channel();
+        let socket_tx = Arc::new(std::sync::Mutex::new(Some(socket_tx)));
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::get(move |ws: WebSocketUpgrade| async move {
+                let tx = socket_tx.lock().unwrap().take().unwrap();
+                ws.on_upgrade(move |socket| async move {
+                    let _ = tx.send(socket);
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let (client, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/"))
+            .await
+            .unwrap();
+        (socket_rx.await.unwrap(), client)
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn container_session_events_are_recorded_in_session_span() {
+        let state = test_state().await;
+        let (socket, mut client) = accept_socket().await;
+        client
+            .send(tokio_tungstenite::tungstenite::Message::Text(
+                r#"{"type":"ready"}"#.into(),
+            ))
+            .await
+            .unwrap();
+        client.close(None).await.unwrap();
+
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            handle_container_ws(
+                socket,
+                "conv-42".to_string(),
+                "user-7".to_string(),
+                state.clone(),
+                state.ws_state.clone(),
+            ),
+        )
+        .await
+        .expect("container session did not end");
+
+        logs_assert(|lines| {
+            lines
+                .iter()
+                .find(|line| line.contains("Container ready for conversation conv-42"))
+                .filter(|line| {
+                    line.contains("container_session{conversation_id=conv-42 user_id=user-7}")
+                })
+                .map(|_| ())
+                .ok_or_else(|| format!("no container.ready event in session span: {lines:?}"))
+        });
+    }
+
+    #[test]
+    fn token_usage_update_event_has_expected_shape() {
+        let event = token_usage_update_event("conv-123", 1500, 1200);
+        assert_eq!(
+            event,
+            serde_json::json!({
+                "type": "token_usage_update",
+                "total_tokens": 1500,
+                "completion_tokens": 1200,
+                "conversation_id": "conv-123",
+            })
+        );
+    }
+
+    #[test]
+    fn usage_token_counts_accepts_both_key_styles() {
+        assert_eq!(
+            usage_token_counts(&serde_json::json!({"prompt": 120, "completion": 30})),
+            Some((120, 30))
+        );
+        assert_eq!(
+            usage_token_counts(&serde_json::json!({"input_tokens": 5, "output_tokens": 9})),
+            Some((5, 9))
+        );
+        assert_eq!(
+            usage_token_counts(&serde_json::json!({"completion": 7})),
+            Some((0, 7))
+        );
+        assert_eq!(usage_token_counts(&serde_json::json!({})), None);
+        assert_eq!(usage_token_counts(&serde_json::Value::Null), None);
+    }
+
+    #[tokio::test]
+    async fn token_usage_update_is_forwarded_without_db_writes() {
+        let state = test_state().await;
+        let user = db::users::create_user(&state.db, "u", "u@example.com", "hash")
+            .await
+            .unwrap();
+        let conv = db::conversations::create_conversation(
+            &state.db, &user.id, "t", None, None, None, false, None, None, None,
+        )
+        .await
+        .unwrap();
+        let (tx, mut rx) = mpsc::channel(crate::ws::WS_CHANNEL_CAPACITY);
+        state.ws_state.add_client(&user.id, &conv.id, 1, tx).await;
+
+        let (socket, mut container) = accept_socket().await;
+        container
+            .send(tokio_tungstenite::tungstenite::Message::Text(
+                r#"{"type":"token_usage_update","total_tokens":42,"completion_tokens":7}"#.into(),
+            ))
+            .await
+            .unwrap();
+        container.close(None).await.unwrap();
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            handle_container_ws(
+                socket,
+                conv.id.clone(),
+                user.id.clone(),
+                state.clone(),
+                state.ws_state.clone(),
+            ),
+        )
+        .await
+        .expect("container session did not end");
+
+        let mut received = Vec::new();
+        while let Ok(raw) = rx.try_recv() {
+            received.push(serde_json::from_str::<serde_json::Value>(&raw).unwrap());
+        }
+        let update = received
+            .iter()
+            .find(|msg| msg["type"] == "token_usage_update")
+            .expect("token_usage_update forwarded to the client");
+        assert_eq!(update["total_tokens"], 42);
+        assert_eq!(update["completion_tokens"], 7);
+        assert_eq!(update["conversation_id"], conv.id.as_str());
+
+        assert_eq!(
+            db::messages::count_messages(&state.db, &conv.id)
+                .await
+                .unwrap(),
+            0
+        );
+        let after = db::conversations::get_conversation(&state.db, &conv.id, &user.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(after.updated_at, conv.updated_at);
+    }
 }
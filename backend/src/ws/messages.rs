@@ -7,6 +7,12 @@ pub enum ClientMessage {
     JoinConversation {
         conversation_id: String,
     },
+    /// Leave `from_conversation_id` and join `to_conversation_id` in one step.
+    SwitchConversation {
+        #[serde(default)]
+        from_conversation_id: Option<String>,
+        to_conversation_id: String,
+    },
     UserMessage {
         content: String,
         #[serde(default)]
@@ -38,9 +44,24 @@ pub enum ContainerMessage {
         token_usage: Option<serde_json::Value>,
     },
     Error,
-    /// Forwarded types: assistant_delta, thinking_delta, tool_call, tool_result,
-    /// subagent_trace_delta, task_trace_delta (legacy), and other streaming
-    /// passthrough events.
+    /// A tool invocation has started. Forwarded to the client as-is.
+    ToolCall {
+        #[serde(default)]
+        tool_call_id: Option<String>,
+    },
+    /// A tool invocation has finished. Forwarded to the client as-is.
+    ToolResult {
+        #[serde(default)]
+        tool_call_id: Option<String>,
+    },
+    /// Running token totals for the turn in progress. Forwarded to the
+    /// client only; the final counts are persisted from `Complete`.
+    TokenUsageUpdate {
+        total_tokens: i64,
+        completion_tokens: i64,
+    },
+    /// Forwarded types: assistant_delta, thinking_delta, subagent_trace_delta,
+    /// task_trace_delta (legacy), and other streaming passthrough events.
     /// These are handled as raw JSON to preserve all fields during forwarding.
     #[serde(other)]
     Forward,
@@ -59,6 +80,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn deserialize_switch_conversation() {
+        let json = r#"{"type": "switch_conversation", "from_conversation_id": "conv-1", "to_conversation_id": "conv-2"}"#;
+        let msg: ClientMessage = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            msg,
+            ClientMessage::SwitchConversation {
+                from_conversation_id: Some(from),
+                to_conversation_id,
+            } if from == "conv-1" && to_conversation_id == "conv-2"
+        ));
+    }
+
+    #[test]
+    fn deserialize_switch_conversation_without_from() {
+        let json = r#"{"type": "switch_conversation", "to_conversation_id": "conv-2"}"#;
+        let msg: ClientMessage = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            msg,
+            ClientMessage::SwitchConversation {
+                from_conversation_id: None,
+                to_conversation_id,
+            } if to_conversation_id == "conv-2"
+        ));
+    }
+
     #[test]
     fn deserialize_user_message() {
         let json = r#"{"type": "user_message", "content": "hello"}"#;
@@ -142,6 +189,45 @@ mod tests {
         assert!(matches!(msg, ContainerMessage::Error));
     }
 
+    #[test]
+    fn deserialize_container_token_usage_update() {
+        let json =
+            r#"{"type": "token_usage_update", "total_tokens": 1500, "completion_tokens": 1200}"#;
+        let msg: ContainerMessage = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            msg,
+            ContainerMessage::TokenUsageUpdate {
+                total_tokens: 1500,
+                completion_tokens: 1200,
+            }
+        ));
+    }
+
+    #[test]
+    fn deserialize_container_tool_call_and_result() {
+        let json = r#"{"type": "tool_call", "tool_call_id": "tc-1", "tool_name": "bash"}"#;
+        let msg: ContainerMessage = serde_json::from_str(json).unwrap();
+        assert!(
+            matches!(msg, ContainerMessage::ToolCall { tool_call_id: Some(id) } if id == "tc-1")
+        );
+
+        let json = r#"{"type": "tool_result", "tool_call_id": "tc-1", "result": "ok"}"#;
+        let msg: ContainerMessage = serde_json::from_str(json).unwrap();
+        assert!(
+            matches!(msg, ContainerMessage::ToolResult { tool_call_id: Some(id) } if id == "tc-1")
+        );
+    }
+
+    #[test]
+    fn deserialize_tool_call_without_id() {
+        let json = r#"{"type": "tool_call", "tool_name": "bash"}"#;
+        let msg: ContainerMessage = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            msg,
+            ContainerMessage::ToolCall { tool_call_id: None }
+        ));
+    }
+
     #[test]
     fn deserialize_unknown_type_as_forward() {
         let json = r#"{"type": "assistant_delta", "content": "hi"}"#;
@@ -1,10 +1,12 @@
 pub mod client;
 pub mod container;
 pub mod messages;
+pub mod sse;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, hash_map::Entry};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::{RwLock, mpsc};
 
 /// Maximum number of messages to fetch for WS history operations.
@@ -18,14 +20,73 @@ pub type WsSender = mpsc::Sender<String>;
 /// this many messages the channel will apply backpressure.
 pub const WS_CHANNEL_CAPACITY: usize = 1024;
 
+/// Sent to a user's clients when their account is deactivated. Client send
+/// tasks close the socket after delivering it.
+pub const ACCOUNT_DISABLED_MESSAGE: &str =
+    r#"{"type":"error","code":"account_disabled","message":"Your account has been disabled."}"#;
+
+/// Point-in-time connection counts for operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct WsMetrics {
+    /// Open client sockets across all users and conversations.
+    pub active_client_connections: usize,
+    pub active_container_connections: usize,
+    /// Conversations with a message waiting for their container to start.
+    pub pending_messages: usize,
+}
+
+/// A client socket joined to one conversation.
+#[derive(Debug, Clone)]
+pub struct ClientConn {
+    pub sender: WsSender,
+    pub connected_at: Instant,
+    /// Unix time in milliseconds of the last message received from the client.
+    pub last_activity_at: Arc<AtomicU64>,
+}
+
+impl ClientConn {
+    pub fn new(sender: WsSender) -> Self {
+        Self {
+            sender,
+            connected_at: Instant::now(),
+            last_activity_at: Arc::new(AtomicU64::new(unix_millis())),
+        }
+    }
+}
+
+/// Every socket a user has open on one conversation, keyed by connection ID.
+pub type ClientTabs = HashMap<u64, ClientConn>;
+
+/// Serializable snapshot of a [`ClientConn`] for admin diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ClientConnInfo {
+    pub user_id: String,
+    pub conversation_id: String,
+    pub connection_id: u64,
+    pub connected_secs: u64,
+    pub idle_secs: u64,
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
 #[derive(Default)]
 pub struct WsState {
-    pub client_connections: RwLock<HashMap<String, HashMap<String, WsSender>>>,
+    /// Client sockets keyed by user, then conversation.
+    pub client_connections: RwLock<HashMap<String, HashMap<String, ClientTabs>>>,
     pub container_connections: RwLock<HashMap<String, (WsSender, u64)>>,
     /// Messages queued while a container was starting (keyed by conversation_id).
     pub pending_messages: RwLock<HashMap<String, String>>,
+    /// Container token IDs that have already been used to connect, with the
+    /// time they were first seen.
+    pub used_jtis: RwLock<HashMap<String, Instant>>,
     /// Monotonically increasing generation counter for container connections.
     container_gen: AtomicU64,
+    /// Source of client connection IDs.
+    client_conn_gen: AtomicU64,
 }
 
 impl WsState {
@@ -33,36 +94,216 @@ impl WsState {
         Arc::new(Self::default())
     }
 
-    pub async fn add_client(&self, user_id: &str, conversation_id: &str, sender: WsSender) {
+    /// Allocate an ID for a new client socket. A user may have the same
+    /// conversation open in several tabs, each with its own ID.
+    pub fn next_client_connection_id(&self) -> u64 {
+        self.client_conn_gen.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub async fn add_client(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        connection_id: u64,
+        sender: WsSender,
+    ) {
         let mut conns = self.client_connections.write().await;
         conns
             .entry(user_id.to_string())
             .or_default()
-            .insert(conversation_id.to_string(), sender);
+            .entry(conversation_id.to_string())
+            .or_default()
+            .insert(connection_id, ClientConn::new(sender));
     }
 
-    pub async fn remove_client(&self, user_id: &str, conversation_id: &str) {
+    /// Remove one socket from a conversation, leaving the user's other tabs
+    /// in place.
+    pub async fn remove_client(&self, user_id: &str, conversation_id: &str, connection_id: u64) {
         let mut conns = self.client_connections.write().await;
         if let Some(user_conns) = conns.get_mut(user_id) {
-            user_conns.remove(conversation_id);
+            if let Some(tabs) = user_conns.get_mut(conversation_id) {
+                tabs.remove(&connection_id);
+                if tabs.is_empty() {
+                    user_conns.remove(conversation_id);
+                }
+            }
             if user_conns.is_empty() {
                 conns.remove(user_id);
             }
         }
     }
 
-    pub async fn send_to_client(&self, user_id: &str, conversation_id: &str, msg: &str) {
+    /// Move a client from one conversation to another under a single write
+    /// lock, so no message can be routed to the old conversation after the
+    /// new one is registered. The connection keeps its original timestamps.
+    pub async fn switch_client(
+        &self,
+        user_id: &str,
+        connection_id: u64,
+        from_conversation_id: Option<&str>,
+        to_conversation_id: &str,
+        sender: WsSender,
+    ) {
+        let mut conns = self.client_connections.write().await;
+        let user_conns = conns.entry(user_id.to_string()).or_default();
+        let old = from_conversation_id.and_then(|from| {
+            let tabs = user_conns.get_mut(from)?;
+            let old = tabs.remove(&connection_id);
+            if tabs.is_empty() {
+                user_conns.remove(from);
+            }
+            old
+        });
+        let conn = match old {
+            Some(old) => ClientConn { sender, ..old },
+            None => ClientConn::new(sender),
+        };
+        user_conns
+            .entry(to_conversation_id.to_string())
+            .or_default()
+            .insert(connection_id, conn);
+    }
+
+    /// Record that a client socket just sent a message.
+    pub async fn touch_client_activity(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        connection_id: u64,
+    ) {
         let conns = self.client_connections.read().await;
-        if let Some(user_conns) = conns.get(user_id)
-            && let Some(sender) = user_conns.get(conversation_id)
-            && sender.try_send(msg.to_string()).is_err()
+        if let Some(conn) = conns
+            .get(user_id)
+            .and_then(|user_conns| user_conns.get(conversation_id))
+            .and_then(|tabs| tabs.get(&connection_id))
         {
-            tracing::warn!(
-                user_id = %user_id,
-                conversation_id = %conversation_id,
-                "Client WS channel full or closed; dropping message"
-            );
+            conn.last_activity_at
+                .store(unix_millis(), Ordering::Relaxed);
+        }
+    }
+
+    /// Snapshot every open client connection, sorted by user, conversation
+    /// and connection ID.
+    pub async fn list_connections(&self) -> Vec<ClientConnInfo> {
+        let conns = self.client_connections.read().await;
+        let now = unix_millis();
+        let mut list: Vec<ClientConnInfo> = conns
+            .iter()
+            .flat_map(|(user_id, user_conns)| {
+                user_conns.iter().flat_map(move |(conversation_id, tabs)| {
+                    tabs.iter()
+                        .map(move |(connection_id, conn)| ClientConnInfo {
+                            user_id: user_id.clone(),
+                            conversation_id: conversation_id.clone(),
+                            connection_id: *connection_id,
+                            connected_secs: conn.connected_at.elapsed().as_secs(),
+                            idle_secs: now
+                                .saturating_sub(conn.last_activity_at.load(Ordering::Relaxed))
+                                / 1000,
+                        })
+                })
+            })
+            .collect();
+        list.sort_by(|a, b| {
+            (&a.user_id, &a.conversation_id, a.connection_id).cmp(&(
+                &b.user_id,
+                &b.conversation_id,
+                b.connection_id,
+            ))
+        });
+        list
+    }
+
+    /// Remove every client connection for `user_id` and return their senders.
+    pub async fn remove_all_clients_for_user(&self, user_id: &str) -> Vec<WsSender> {
+        let mut conns = self.client_connections.write().await;
+        conns
+            .remove(user_id)
+            .map(|user_conns| {
+                user_conns
+                    .into_values()
+                    .flat_map(HashMap::into_values)
+                    .map(|conn| conn.sender)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Queue `msg` for every tab the user has open on `conversation_id`.
+    /// Returns `true` if at least one connection accepted it. A full channel
+    /// means that tab is not keeping up, so its copy is dropped with a warning.
+    pub async fn send_to_client(&self, user_id: &str, conversation_id: &str, msg: &str) -> bool {
+        let conns = self.client_connections.read().await;
+        let Some(tabs) = conns
+            .get(user_id)
+            .and_then(|user_conns| user_conns.get(conversation_id))
+        else {
+            return false;
+        };
+        let mut delivered = false;
+        for (connection_id, conn) in tabs {
+            match conn.sender.try_send(msg.to_string()) {
+                Ok(()) => delivered = true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    tracing::warn!(
+                        user_id = %user_id,
+                        conversation_id = %conversation_id,
+                        connection_id,
+                        "Client WS channel full; dropping message"
+                    );
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {}
+            }
+        }
+        delivered
+    }
+
+    /// Send `msg` to every connected client. Returns the number of
+    /// connections that accepted the message; closed or full channels are
+    /// skipped.
+    pub async fn broadcast_to_all_clients(&self, msg: &str) -> usize {
+        let conns = self.client_connections.read().await;
+        let mut sent = 0;
+        for (user_id, user_conns) in conns.iter() {
+            for (conversation_id, tabs) in user_conns {
+                for conn in tabs.values() {
+                    if conn.sender.try_send(msg.to_string()).is_ok() {
+                        sent += 1;
+                    } else {
+                        tracing::warn!(
+                            user_id = %user_id,
+                            conversation_id = %conversation_id,
+                            "Client WS channel full or closed; skipping broadcast"
+                        );
+                    }
+                }
+            }
         }
+        sent
+    }
+
+    /// Send `msg` to every connection `user_id` currently has open. Returns
+    /// the number of connections that accepted the message.
+    pub async fn broadcast_to_user(&self, user_id: &str, msg: &str) -> usize {
+        let conns = self.client_connections.read().await;
+        let Some(user_conns) = conns.get(user_id) else {
+            return 0;
+        };
+        let mut sent = 0;
+        for (conversation_id, tabs) in user_conns {
+            for conn in tabs.values() {
+                if conn.sender.try_send(msg.to_string()).is_ok() {
+                    sent += 1;
+                } else {
+                    tracing::warn!(
+                        user_id = %user_id,
+                        conversation_id = %conversation_id,
+                        "Client WS channel full or closed; skipping user broadcast"
+                    );
+                }
+            }
+        }
+        sent
     }
 
     pub async fn add_container(&self, conversation_id: &str, sender: WsSender) -> u64 {
@@ -109,15 +350,61 @@ impl WsState {
         }
     }
 
+    pub async fn is_container_connected(&self, conversation_id: &str) -> bool {
+        let conns = self.container_connections.read().await;
+        conns
+            .get(conversation_id)
+            .is_some_and(|(sender, _)| !sender.is_closed())
+    }
+
     pub async fn set_pending_message(&self, conversation_id: &str, msg: String) {
         let mut pending = self.pending_messages.write().await;
         pending.insert(conversation_id.to_string(), msg);
     }
 
+    pub async fn has_pending_message(&self, conversation_id: &str) -> bool {
+        let pending = self.pending_messages.read().await;
+        pending.contains_key(conversation_id)
+    }
+
     pub async fn take_pending_message(&self, conversation_id: &str) -> Option<String> {
         let mut pending = self.pending_messages.write().await;
         pending.remove(conversation_id)
     }
+
+    pub async fn metrics(&self) -> WsMetrics {
+        let clients = self.client_connections.read().await;
+        let containers = self.container_connections.read().await;
+        let pending = self.pending_messages.read().await;
+        WsMetrics {
+            active_client_connections: clients
+                .values()
+                .flat_map(HashMap::values)
+                .map(HashMap::len)
+                .sum(),
+            active_container_connections: containers.len(),
+            pending_messages: pending.len(),
+        }
+    }
+
+    /// Record the first use of a container token ID. Returns `false` if the
+    /// ID has been seen before, so each token connects at most once.
+    ///
+    /// Entries older than `retention` are dropped on every call; callers pick
+    /// a retention longer than the token TTL so an expired entry can never
+    /// belong to a still-valid token.
+    pub async fn claim_jti(&self, jti: &str, retention: Duration) -> bool {
+        let mut used = self.used_jtis.write().await;
+        let now = Instant::now();
+        used.retain(|_, seen| now.duration_since(*seen) < retention);
+        match used.entry(jti.to_string()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+                true
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -128,19 +415,104 @@ mod tests {
         mpsc::channel(WS_CHANNEL_CAPACITY)
     }
 
+    #[tokio::test]
+    async fn test_metrics_track_connections() {
+        let state = WsState::new();
+        let empty = WsMetrics {
+            active_client_connections: 0,
+            active_container_connections: 0,
+            pending_messages: 0,
+        };
+        assert_eq!(state.metrics().await, empty);
+
+        let (tx1, _rx1) = test_channel();
+        let (tx2, _rx2) = test_channel();
+        let (tx3, _rx3) = test_channel();
+        let (ctx, _crx) = test_channel();
+        state.add_client("user1", "conv1", 1, tx1).await;
+        state.add_client("user1", "conv2", 1, tx2).await;
+        state.add_client("user2", "conv3", 1, tx3).await;
+        state.add_container("conv1", ctx).await;
+        state.set_pending_message("conv2", "queued".into()).await;
+
+        assert_eq!(
+            state.metrics().await,
+            WsMetrics {
+                active_client_connections: 3,
+                active_container_connections: 1,
+                pending_messages: 1,
+            }
+        );
+
+        state.remove_client("user1", "conv1", 1).await;
+        state.remove_container("conv1").await;
+        state.take_pending_message("conv2").await;
+        let metrics = state.metrics().await;
+        assert_eq!(metrics.active_client_connections, 2);
+        assert_eq!(metrics.active_container_connections, 0);
+        assert_eq!(metrics.pending_messages, 0);
+
+        state.remove_all_clients_for_user("user1").await;
+        state.remove_all_clients_for_user("user2").await;
+        assert_eq!(state.metrics().await, empty);
+    }
+
+    #[tokio::test]
+    async fn test_claim_jti_first_use_succeeds() {
+        let state = WsState::new();
+        assert!(state.claim_jti("jti-1", Duration::from_secs(60)).await);
+        assert!(state.claim_jti("jti-2", Duration::from_secs(60)).await);
+    }
+
+    #[tokio::test]
+    async fn test_claim_jti_rejects_replay_even_after_disconnect() {
+        let state = WsState::new();
+        let retention = Duration::from_secs(60);
+        assert!(state.claim_jti("jti-1", retention).await);
+        assert!(!state.claim_jti("jti-1", retention).await);
+
+        let (tx, _rx) = test_channel();
+        state.add_container("conv1", tx).await;
+        state.remove_container("conv1").await;
+        assert!(!state.claim_jti("jti-1", retention).await);
+    }
+
+    #[tokio::test]
+    async fn test_claim_jti_concurrent_claims_admit_one() {
+        let state = WsState::new();
+        let retention = Duration::from_secs(60);
+        let (a, b) = tokio::join!(
+            state.claim_jti("jti-1", retention),
+            state.claim_jti("jti-1", retention)
+        );
+        assert!(a ^ b);
+    }
+
+    #[tokio::test]
+    async fn test_claim_jti_drops_expired_entries() {
+        let state = WsState::new();
+        let retention = Duration::from_millis(20);
+        assert!(state.claim_jti("jti-1", retention).await);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        assert!(state.claim_jti("jti-2", retention).await);
+        assert!(!state.used_jtis.read().await.contains_key("jti-1"));
+        assert!(state.claim_jti("jti-1", retention).await);
+    }
+
     #[tokio::test]
     async fn test_add_and_remove_client() {
         let state = WsState::new();
         let (tx, _rx) = test_channel();
 
-        state.add_client("user1", "conv1", tx).await;
+        state.add_client("user1", "conv1", 1, tx).await;
 
         {
             let conns = state.client_connections.read().await;
             assert!(conns.get("user1").unwrap().contains_key("conv1"));
         }
 
-        state.remove_client("user1", "conv1").await;
+        state.remove_client("user1", "conv1", 1).await;
 
         {
             let conns = state.client_connections.read().await;
@@ -149,22 +521,310 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_send_to_client() {
+    async fn test_switch_client_moves_mapping() {
         let state = WsState::new();
         let (tx, mut rx) = test_channel();
 
-        state.add_client("user1", "conv1", tx).await;
+        state.add_client("user1", "conv1", 1, tx.clone()).await;
+        state
+            .switch_client("user1", 1, Some("conv1"), "conv2", tx)
+            .await;
+
+        {
+            let conns = state.client_connections.read().await;
+            let user_conns = conns.get("user1").unwrap();
+            assert!(!user_conns.contains_key("conv1"));
+            assert!(user_conns.contains_key("conv2"));
+        }
+
+        state.send_to_client("user1", "conv1", "old").await;
+        state.send_to_client("user1", "conv2", "new").await;
+        assert_eq!(rx.recv().await.unwrap(), "new");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_switch_client_without_previous_conversation() {
+        let state = WsState::new();
+        let (tx, mut rx) = test_channel();
+
+        state.switch_client("user1", 1, None, "conv1", tx).await;
         state.send_to_client("user1", "conv1", "hello").await;
+        assert_eq!(rx.recv().await.unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_switch_client_keeps_other_tabs() {
+        let state = WsState::new();
+        let (tx1, _rx1) = test_channel();
+        let (tx2, _rx2) = test_channel();
+
+        state.add_client("user1", "conv1", 1, tx1.clone()).await;
+        state.add_client("user1", "other", 2, tx2).await;
+        state
+            .switch_client("user1", 1, Some("conv1"), "conv2", tx1)
+            .await;
+
+        let conns = state.client_connections.read().await;
+        let user_conns = conns.get("user1").unwrap();
+        assert!(user_conns.contains_key("other"));
+        assert!(user_conns.contains_key("conv2"));
+        assert!(!user_conns.contains_key("conv1"));
+    }
+
+    #[tokio::test]
+    async fn test_switch_client_preserves_connection_timestamps() {
+        let state = WsState::new();
+        let (tx, _rx) = test_channel();
+
+        state.add_client("user1", "conv1", 1, tx.clone()).await;
+        let connected_at = state.client_connections.read().await["user1"]["conv1"][&1].connected_at;
+        state
+            .switch_client("user1", 1, Some("conv1"), "conv2", tx)
+            .await;
+
+        let conns = state.client_connections.read().await;
+        assert_eq!(conns["user1"]["conv2"][&1].connected_at, connected_at);
+    }
+
+    #[tokio::test]
+    async fn test_touch_client_activity_updates_timestamp() {
+        let state = WsState::new();
+        let (tx, _rx) = test_channel();
+        state.add_client("user1", "conv1", 1, tx).await;
+
+        let activity = state.client_connections.read().await["user1"]["conv1"][&1]
+            .last_activity_at
+            .clone();
+        activity.store(0, Ordering::Relaxed);
+        state.touch_client_activity("user1", "conv1", 1).await;
+        assert!(activity.load(Ordering::Relaxed) > 0);
+
+        // Unknown connections are ignored.
+        state.touch_client_activity("user1", "missing", 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_list_connections_reports_idle_time() {
+        let state = WsState::new();
+        assert!(state.list_connections().await.is_empty());
+
+        let (tx1, _rx1) = test_channel();
+        let (tx2, _rx2) = test_channel();
+        state.add_client("user2", "conv2", 1, tx1).await;
+        state.add_client("user1", "conv1", 1, tx2).await;
+        state.client_connections.read().await["user2"]["conv2"][&1]
+            .last_activity_at
+            .fetch_sub(90_000, Ordering::Relaxed);
+
+        let list = state.list_connections().await;
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].user_id, "user1");
+        assert_eq!(list[0].idle_secs, 0);
+        assert_eq!(list[1].conversation_id, "conv2");
+        assert_eq!(list[1].idle_secs, 90);
+    }
+
+    #[tokio::test]
+    async fn test_send_to_client_fans_out_to_all_tabs() {
+        let state = WsState::new();
+        let (tx1, mut rx1) = test_channel();
+        let (tx2, mut rx2) = test_channel();
+        let id1 = state.next_client_connection_id();
+        let id2 = state.next_client_connection_id();
+        assert_ne!(id1, id2);
+
+        state.add_client("user1", "conv1", id1, tx1).await;
+        state.add_client("user1", "conv1", id2, tx2).await;
+        assert!(state.send_to_client("user1", "conv1", "hello").await);
+        assert_eq!(rx1.recv().await.unwrap(), "hello");
+        assert_eq!(rx2.recv().await.unwrap(), "hello");
+        assert_eq!(state.metrics().await.active_client_connections, 2);
+    }
+
+    #[tokio::test]
+    async fn test_remove_client_keeps_other_tabs_on_same_conversation() {
+        let state = WsState::new();
+        let (tx1, _rx1) = test_channel();
+        let (tx2, mut rx2) = test_channel();
+
+        state.add_client("user1", "conv1", 1, tx1).await;
+        state.add_client("user1", "conv1", 2, tx2).await;
+        state.remove_client("user1", "conv1", 1).await;
+
+        assert!(state.send_to_client("user1", "conv1", "still here").await);
+        assert_eq!(rx2.recv().await.unwrap(), "still here");
+
+        state.remove_client("user1", "conv1", 2).await;
+        assert!(state.client_connections.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_to_client_succeeds_if_any_tab_accepts() {
+        let state = WsState::new();
+        let (tx1, rx1) = test_channel();
+        let (tx2, mut rx2) = test_channel();
+
+        state.add_client("user1", "conv1", 1, tx1).await;
+        state.add_client("user1", "conv1", 2, tx2).await;
+        drop(rx1);
+        assert!(state.send_to_client("user1", "conv1", "hello").await);
+        assert_eq!(rx2.recv().await.unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_switch_client_leaves_other_tab_on_old_conversation() {
+        let state = WsState::new();
+        let (tx1, mut rx1) = test_channel();
+        let (tx2, mut rx2) = test_channel();
+
+        state.add_client("user1", "conv1", 1, tx1.clone()).await;
+        state.add_client("user1", "conv1", 2, tx2).await;
+        state
+            .switch_client("user1", 1, Some("conv1"), "conv2", tx1)
+            .await;
+
+        state.send_to_client("user1", "conv1", "old").await;
+        state.send_to_client("user1", "conv2", "new").await;
+        assert_eq!(rx1.recv().await.unwrap(), "new");
+        assert_eq!(rx2.recv().await.unwrap(), "old");
+        assert!(rx1.try_recv().is_err());
+        assert!(rx2.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_to_client() {
+        let state = WsState::new();
+        let (tx, mut rx) = test_channel();
+
+        state.add_client("user1", "conv1", 1, tx).await;
+        assert!(state.send_to_client("user1", "conv1", "hello").await);
 
         let msg = rx.recv().await.unwrap();
         assert_eq!(msg, "hello");
     }
 
+    #[tokio::test]
+    async fn test_send_to_client_returns_false_when_receiver_dropped() {
+        let state = WsState::new();
+        let (tx, rx) = test_channel();
+
+        state.add_client("user1", "conv1", 1, tx).await;
+        drop(rx);
+        assert!(!state.send_to_client("user1", "conv1", "hello").await);
+    }
+
+    #[tokio::test]
+    async fn test_send_to_client_returns_false_when_buffer_full() {
+        let state = WsState::new();
+        let (tx, mut rx) = mpsc::channel(2);
+
+        state.add_client("user1", "conv1", 1, tx).await;
+        assert!(state.send_to_client("user1", "conv1", "a").await);
+        assert!(state.send_to_client("user1", "conv1", "b").await);
+        assert!(!state.send_to_client("user1", "conv1", "c").await);
+
+        assert_eq!(rx.recv().await.unwrap(), "a");
+        assert!(state.send_to_client("user1", "conv1", "d").await);
+    }
+
     #[tokio::test]
     async fn test_send_to_nonexistent_client() {
         let state = WsState::new();
-        // Should not panic
-        state.send_to_client("nobody", "noconv", "hello").await;
+        assert!(!state.send_to_client("nobody", "noconv", "hello").await);
+    }
+
+    #[tokio::test]
+    async fn test_remove_all_clients_for_user() {
+        let state = WsState::new();
+        let (tx1, _rx1) = test_channel();
+        let (tx2, _rx2) = test_channel();
+        let (tx3, _rx3) = test_channel();
+        state.add_client("user1", "conv1", 1, tx1).await;
+        state.add_client("user1", "conv2", 1, tx2).await;
+        state.add_client("user2", "conv3", 1, tx3).await;
+
+        let removed = state.remove_all_clients_for_user("user1").await;
+        assert_eq!(removed.len(), 2);
+
+        let conns = state.client_connections.read().await;
+        assert!(!conns.contains_key("user1"));
+        assert!(conns.contains_key("user2"));
+        drop(conns);
+
+        assert!(state.remove_all_clients_for_user("user1").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_reaches_all_clients() {
+        let state = WsState::new();
+        let (tx1, mut rx1) = test_channel();
+        let (tx2, mut rx2) = test_channel();
+        let (tx3, mut rx3) = test_channel();
+
+        state.add_client("user1", "conv1", 1, tx1).await;
+        state.add_client("user1", "conv2", 1, tx2).await;
+        state.add_client("user2", "conv3", 1, tx3).await;
+
+        let sent = state.broadcast_to_all_clients("notice").await;
+        assert_eq!(sent, 3);
+        assert_eq!(rx1.recv().await.unwrap(), "notice");
+        assert_eq!(rx2.recv().await.unwrap(), "notice");
+        assert_eq!(rx3.recv().await.unwrap(), "notice");
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_skips_dropped_clients() {
+        let state = WsState::new();
+        let (tx1, mut rx1) = test_channel();
+        let (tx2, rx2) = test_channel();
+
+        state.add_client("user1", "conv1", 1, tx1).await;
+        state.add_client("user2", "conv2", 1, tx2).await;
+        drop(rx2);
+
+        let sent = state.broadcast_to_all_clients("notice").await;
+        assert_eq!(sent, 1);
+        assert_eq!(rx1.recv().await.unwrap(), "notice");
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_to_user_reaches_only_that_user() {
+        let state = WsState::new();
+        let (tx1, mut rx1) = test_channel();
+        let (tx2, mut rx2) = test_channel();
+        let (tx3, mut rx3) = test_channel();
+
+        state.add_client("user1", "conv1", 1, tx1).await;
+        state.add_client("user1", "conv2", 1, tx2).await;
+        state.add_client("user2", "conv3", 1, tx3).await;
+
+        let sent = state.broadcast_to_user("user1", "rotate").await;
+        assert_eq!(sent, 2);
+        assert_eq!(rx1.recv().await.unwrap(), "rotate");
+        assert_eq!(rx2.recv().await.unwrap(), "rotate");
+        assert!(rx3.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_to_user_skips_dropped_and_unknown() {
+        let state = WsState::new();
+        let (tx1, mut rx1) = test_channel();
+        let (tx2, rx2) = test_channel();
+
+        state.add_client("user1", "conv1", 1, tx1).await;
+        state.add_client("user1", "conv2", 1, tx2).await;
+        drop(rx2);
+
+        assert_eq!(state.broadcast_to_user("user1", "rotate").await, 1);
+        assert_eq!(rx1.recv().await.unwrap(), "rotate");
+        assert_eq!(state.broadcast_to_user("nobody", "rotate").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_with_no_clients() {
+        let state = WsState::new();
+        assert_eq!(state.broadcast_to_all_clients("notice").await, 0);
     }
 
     #[tokio::test]
@@ -213,8 +873,8 @@ mod tests {
         let (tx1, mut rx1) = test_channel();
         let (tx2, mut rx2) = test_channel();
 
-        state.add_client("user1", "conv1", tx1).await;
-        state.add_client("user1", "conv2", tx2).await;
+        state.add_client("user1", "conv1", 1, tx1).await;
+        state.add_client("user1", "conv2", 1, tx2).await;
 
         state.send_to_client("user1", "conv1", "msg1").await;
         state.send_to_client("user1", "conv2", "msg2").await;
@@ -223,11 +883,35 @@ mod tests {
         assert_eq!(rx2.recv().await.unwrap(), "msg2");
 
         // Remove one, other should still work
-        state.remove_client("user1", "conv1").await;
+        state.remove_client("user1", "conv1", 1).await;
         state.send_to_client("user1", "conv2", "msg3").await;
         assert_eq!(rx2.recv().await.unwrap(), "msg3");
     }
 
+    #[tokio::test]
+    async fn test_is_container_connected() {
+        let state = WsState::new();
+        assert!(!state.is_container_connected("conv1").await);
+
+        let (tx, rx) = test_channel();
+        state.add_container("conv1", tx).await;
+        assert!(state.is_container_connected("conv1").await);
+
+        // A sender whose receiver is gone counts as disconnected
+        drop(rx);
+        assert!(!state.is_container_connected("conv1").await);
+    }
+
+    #[tokio::test]
+    async fn test_has_pending_message() {
+        let state = WsState::new();
+        assert!(!state.has_pending_message("conv1").await);
+        state.set_pending_message("conv1", "msg".into()).await;
+        assert!(state.has_pending_message("conv1").await);
+        state.take_pending_message("conv1").await;
+        assert!(!state.has_pending_message("conv1").await);
+    }
+
     #[tokio::test]
     async fn test_set_and_take_pending_message() {
         let state = WsState::new();
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use axum::response::sse::Event;
+use futures_util::{Stream, StreamExt};
+use tokio::sync::{RwLock, broadcast};
+
+/// Capacity of each SSE stream's broadcast channel. A subscriber that falls
+/// further behind than this skips the oldest events.
+pub const SSE_CHANNEL_CAPACITY: usize = 1024;
+
+/// How long an assistant stream waits for the next container frame before
+/// giving up with an error event.
+pub const ASSISTANT_STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+const IDLE_TIMEOUT_FRAME: &str =
+    r#"{"type":"error","code":"idle_timeout","message":"No response from the container"}"#;
+
+/// Server-sent event streams waiting on container output, keyed by
+/// conversation ID and then by stream ID.
+///
+/// Each `POST /messages/stream` request registers its own sender; the
+/// container handler publishes every relayed frame to all of them.
+pub struct SseState {
+    streams: RwLock<HashMap<String, HashMap<u64, broadcast::Sender<String>>>>,
+    next_stream_id: AtomicU64,
+}
+
+impl SseState {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            streams: RwLock::new(HashMap::new()),
+            next_stream_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Register a new stream for `conversation_id` and return its receiver.
+    /// The stream is dropped from the registry once its receiver is gone.
+    pub async fn subscribe(&self, conversation_id: &str) -> broadcast::Receiver<String> {
+        let (tx, rx) = broadcast::channel(SSE_CHANNEL_CAPACITY);
+        let stream_id = self.next_stream_id.fetch_add(1, Ordering::Relaxed);
+        let mut streams = self.streams.write().await;
+        let senders = streams.entry(conversation_id.to_string()).or_default();
+        senders.retain(|_, sender| sender.receiver_count() > 0);
+        senders.insert(stream_id, tx);
+        rx
+    }
+
+    /// Send `msg` to every live stream on `conversation_id`, pruning streams
+    /// whose receivers have been dropped. Returns the number of streams that
+    /// received it.
+    pub async fn publish(&self, conversation_id: &str, msg: &str) -> usize {
+        if !self.streams.read().await.contains_key(conversation_id) {
+            return 0;
+        }
+        let mut streams = self.streams.write().await;
+        let Some(senders) = streams.get_mut(conversation_id) else {
+            return 0;
+        };
+        senders.retain(|_, sender| sender.send(msg.to_string()).is_ok());
+        let delivered = senders.len();
+        if senders.is_empty() {
+            streams.remove(conversation_id);
+        }
+        delivered
+    }
+
+    /// End every stream on `conversation_id` with `final_frame`, e.g. when its
+    /// container stops or disconnects and no more frames will arrive.
+    pub async fn close(&self, conversation_id: &str, final_frame: &str) {
+        let Some(senders) = self.streams.write().await.remove(conversation_id) else {
+            return;
+        };
+        for sender in senders.values() {
+            let _ = sender.send(final_frame.to_string());
+        }
+    }
+}
+
+enum AssistantRelay {
+    Open(broadcast::Receiver<String>),
+    Done,
+    Closed,
+}
+
+/// Turn relayed container frames into SSE events, appending `[DONE]` after
+/// `complete` and stopping after `error`, once the stream is closed, or when
+/// no frame arrives within `idle_timeout`.
+pub fn assistant_events(
+    rx: broadcast::Receiver<String>,
+    idle_timeout: Duration,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    use broadcast::error::RecvError;
+
+    futures_util::stream::unfold(AssistantRelay::Open(rx), move |relay| async move {
+        let mut rx = match relay {
+            AssistantRelay::Open(rx) => rx,
+            AssistantRelay::Done => {
+                return Some((Event::default().data("[DONE]"), AssistantRelay::Closed));
+            }
+            AssistantRelay::Closed => return None,
+        };
+        loop {
+            let Ok(received) = tokio::time::timeout(idle_timeout, rx.recv()).await else {
+                return Some((
+                    Event::default().data(IDLE_TIMEOUT_FRAME),
+                    AssistantRelay::Closed,
+                ));
+            };
+            match received {
+                Ok(frame) => {
+                    let frame_type = serde_json::from_str::<serde_json::Value>(&frame)
+                        .ok()
+                        .and_then(|v| v.get("type")?.as_str().map(str::to_owned));
+                    let next = match frame_type.as_deref() {
+                        Some("complete") => AssistantRelay::Done,
+                        Some("error") => AssistantRelay::Closed,
+                        _ => AssistantRelay::Open(rx),
+                    };
+                    return Some((Event::default().data(frame), next));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "Assistant event stream fell behind");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .map(Ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn publish_reaches_every_stream_and_prunes_dropped_ones() {
+        let sse = SseState::new();
+        let mut first = sse.subscribe("conv-1").await;
+        let second = sse.subscribe("conv-1").await;
+        let mut other = sse.subscribe("conv-2").await;
+
+        assert_eq!(sse.publish("conv-1", "hello").await, 2);
+        assert_eq!(first.recv().await.unwrap(), "hello");
+        assert!(other.try_recv().is_err());
+
+        drop(second);
+        assert_eq!(sse.publish("conv-1", "again").await, 1);
+
+        drop(first);
+        assert_eq!(sse.publish("conv-1", "gone").await, 0);
+        assert!(!sse.streams.read().await.contains_key("conv-1"));
+        assert_eq!(sse.publish("missing", "x").await, 0);
+    }
+
+    async fn collect_data(
+        stream: impl Stream<Item = Result<Event, Infallible>> + Send + 'static,
+    ) -> Vec<String> {
+        use axum::response::IntoResponse;
+        use http_body_util::BodyExt;
+
+        let body = axum::response::Sse::new(stream)
+            .into_response()
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: ").map(str::to_owned))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn close_ends_streams_with_final_frame() {
+        let sse = SseState::new();
+        let events = assistant_events(sse.subscribe("conv-1").await, Duration::from_secs(60));
+        let delta = r#"{"type":"assistant_delta","delta":"Hi"}"#;
+        sse.publish("conv-1", delta).await;
+        sse.close("conv-1", "stopped").await;
+
+        assert_eq!(collect_data(events).await, [delta, "stopped"]);
+        assert_eq!(sse.publish("conv-1", "late").await, 0);
+    }
+
+    #[tokio::test]
+    async fn assistant_events_end_after_idle_timeout() {
+        let sse = SseState::new();
+        let rx = sse.subscribe("conv-1").await;
+
+        let data = collect_data(assistant_events(rx, Duration::from_millis(50))).await;
+        assert_eq!(data, [IDLE_TIMEOUT_FRAME]);
+    }
+}
@@ -0,0 +1,1802 @@
+use axum::{
+    Router,
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use claude_chat_backend::docker::client::{DockerClient, OutputStream};
+use claude_chat_backend::{
+    api, auth,
+    auth::{JwtKeys, middleware::AppState},
+    config::Config,
+    db,
+    docker::{manager::DockerManager, registry::ContainerRegistry},
+    provider_api::HttpProviderApiClient,
+    ws::{WS_CHANNEL_CAPACITY, WsState, sse::SseState},
+};
+use futures_util::future::BoxFuture;
+use http_body_util::BodyExt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+fn test_config() -> Config {
+    Config {
+        database_url: "sqlite::memory:".into(),
+        jwt_secret: "test-jwt-secret-that-is-long-enough-for-hmac".into(),
+        jwt_algorithm: Default::default(),
+        jwt_private_key_path: None,
+        jwt_public_key_path: None,
+        encryption_key: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".into(),
+        host: "127.0.0.1".into(),
+        port: 0,
+        container_image: "test:latest".into(),
+        container_idle_timeout_secs: 600,
+        container_cpu_quota: None,
+        container_memory_bytes: None,
+        container_start_max_retries: 0,
+        container_pool_size: 0,
+        internal_ws_port: 0,
+        docker_network: None,
+        host_data_dir: None,
+        fileserver_url: None,
+        cors_allowed_origins: None,
+        access_token_ttl_secs: 7200,
+        container_token_ttl_secs: 3600,
+        refresh_token_ttl_days: 30,
+        cookie_secure: false,
+        tool_call_timeout_secs: 300,
+        max_file_size_bytes: 50 * 1024 * 1024,
+        workspace_max_bytes: 1024 * 1024 * 1024,
+        allowed_upload_mime_types: None,
+        token_usage_update_interval_tokens: 500,
+        container_exec_allowlist: vec!["df".into(), "du".into(), "ls".into(), "ps".into()],
+        max_login_attempts: 10,
+        login_lockout_secs: 900,
+        trusted_proxies: vec![],
+        require_email_verification: false,
+        smtp_url: None,
+        mail_from: None,
+        oauth_client_id: None,
+        oauth_client_secret: None,
+        oauth_auth_url: None,
+        oauth_token_url: None,
+        max_conversations_per_user: None,
+        max_messages_per_conversation: None,
+    }
+}
+
+async fn test_state() -> Arc<AppState> {
+    let registry = ContainerRegistry::new();
+    let docker_manager = Arc::new(DockerManager::new(test_config(), registry));
+    test_state_with_manager(docker_manager).await
+}
+
+async fn test_state_with_manager(docker_manager: Arc<DockerManager>) -> Arc<AppState> {
+    let config = test_config();
+    let pool = db::init_db("sqlite::memory:").await;
+    let ws_state = WsState::new();
+    Arc::new(AppState {
+        db: pool,
+        jwt_keys: JwtKeys::from_config(&config).unwrap(),
+        config,
+        ws_state,
+        sse_state: SseState::new(),
+        pending_uploads: Default::default(),
+        docker_manager,
+        provider_api: Arc::new(HttpProviderApiClient::default()),
+        mailer: None,
+        system_stats_cache: Default::default(),
+        features: Default::default(),
+    })
+}
+
+fn app(state: Arc<AppState>) -> Router {
+    Router::new()
+        .nest("/api/admin", api::admin::router())
+        .with_state(state)
+}
+
+async fn json_body(response: axum::response::Response) -> serde_json::Value {
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&body).unwrap()
+}
+
+fn post_with_auth(uri: &str, body: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn put_with_auth(uri: &str, body: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("PUT")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn get_with_auth(uri: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("GET")
+        .uri(uri)
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap()
+}
+
+async fn token_for(state: &Arc<AppState>, username: &str, is_admin: bool) -> String {
+    let user = db::users::create_user(
+        &state.db,
+        username,
+        &format!("{username}@example.com"),
+        "hash",
+    )
+    .await
+    .unwrap();
+    db::users::admin_update_user(&state.db, &user.id, None, Some(is_admin), None)
+        .await
+        .unwrap();
+    auth::create_access_token(
+        &user.id,
+        &user.username,
+        is_admin,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn broadcast_delivers_envelope_to_all_clients() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let (tx1, mut rx1) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    let (tx2, mut rx2) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    state.ws_state.add_client("u1", "c1", 1, tx1).await;
+    state.ws_state.add_client("u2", "c2", 1, tx2).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/broadcast",
+            r#"{"message":"Maintenance at 22:00","type":"announcement"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["sent_to"], 2);
+
+    for rx in [&mut rx1, &mut rx2] {
+        let msg: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+        assert_eq!(msg["type"], "broadcast");
+        assert_eq!(msg["broadcast_type"], "announcement");
+        assert_eq!(msg["message"], "Maintenance at 22:00");
+    }
+}
+
+#[tokio::test]
+async fn broadcast_with_user_id_targets_that_user_only() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let (tx1, mut rx1) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    let (tx2, mut rx2) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    let (tx3, mut rx3) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    state.ws_state.add_client("u1", "c1", 1, tx1).await;
+    state.ws_state.add_client("u1", "c2", 1, tx2).await;
+    state.ws_state.add_client("u2", "c3", 1, tx3).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/broadcast",
+            r#"{"message":"Your key was rotated","type":"notice","user_id":"u1"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["sent_to"], 2);
+
+    for rx in [&mut rx1, &mut rx2] {
+        let msg: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+        assert_eq!(msg["broadcast_type"], "notice");
+        assert_eq!(msg["message"], "Your key was rotated");
+    }
+    assert!(rx3.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn broadcast_skips_dropped_connections() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let (tx1, _rx1) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    let (tx2, rx2) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    state.ws_state.add_client("u1", "c1", 1, tx1).await;
+    state.ws_state.add_client("u2", "c2", 1, tx2).await;
+    drop(rx2);
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/broadcast",
+            r#"{"message":"hello"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["sent_to"], 1);
+}
+
+#[tokio::test]
+async fn broadcast_rejects_empty_message() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/broadcast",
+            r#"{"message":""}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn broadcast_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/broadcast",
+            r#"{"message":"hello"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn announce_reaches_every_conversation_of_every_user() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let (tx1, mut rx1) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    let (tx2, mut rx2) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    let (tx3, mut rx3) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    state.ws_state.add_client("u1", "c1", 1, tx1).await;
+    state.ws_state.add_client("u1", "c2", 1, tx2).await;
+    state.ws_state.add_client("u2", "c3", 1, tx3).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/announce",
+            r#"{"message":"Down for maintenance at 22:00 UTC","severity":"warning"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["sent_to"], 3);
+
+    for rx in [&mut rx1, &mut rx2, &mut rx3] {
+        let msg: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+        assert_eq!(msg["type"], "announcement");
+        assert_eq!(msg["message"], "Down for maintenance at 22:00 UTC");
+        assert_eq!(msg["severity"], "warning");
+    }
+}
+
+#[tokio::test]
+async fn announce_defaults_to_info_and_rejects_unknown_severity() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let (tx, mut rx) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    state.ws_state.add_client("u1", "c1", 1, tx).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/announce",
+            r#"{"message":"All clear"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let msg: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert_eq!(msg["severity"], "info");
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/announce",
+            r#"{"message":"hi","severity":"critical"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/announce",
+            r#"{"message":""}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn announce_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/announce",
+            r#"{"message":"hello"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn repair_orphaned_parts_returns_deleted_count() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let mut conn = state.db.acquire().await.unwrap();
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    sqlx::query(
+        "INSERT INTO message_parts (id, message_id, seq, part_type, text) \
+         VALUES ('orphan-part', 'missing-message', 0, 'text', 'orphan')",
+    )
+    .execute(&mut *conn)
+    .await
+    .unwrap();
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    drop(conn);
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/maintenance/repair-orphaned-parts",
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["deleted_parts"], 1);
+}
+
+#[tokio::test]
+async fn repair_orphaned_parts_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/maintenance/repair-orphaned-parts",
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn auto_archive_archives_only_stale_conversations() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let owner = db::users::create_user(&state.db, "owner", "owner@example.com", "hash")
+        .await
+        .unwrap();
+    let stale = db::conversations::create_conversation(
+        &state.db, &owner.id, "Stale", None, None, None, false, None, None, None,
+    )
+    .await
+    .unwrap();
+    let fresh = db::conversations::create_conversation(
+        &state.db, &owner.id, "Fresh", None, None, None, false, None, None, None,
+    )
+    .await
+    .unwrap();
+    sqlx::query("UPDATE conversations SET updated_at = datetime('now', '-120 days') WHERE id = ?")
+        .bind(&stale.id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/conversations/auto-archive",
+            r#"{"inactive_days": 90, "user_id": null}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["archived"], 1);
+
+    for (id, expect_archived) in [(&stale.id, true), (&fresh.id, false)] {
+        let archived_at: Option<String> =
+            sqlx::query_scalar("SELECT archived_at FROM conversations WHERE id = ?")
+                .bind(id)
+                .fetch_one(&state.db)
+                .await
+                .unwrap();
+        assert_eq!(archived_at.is_some(), expect_archived);
+    }
+}
+
+#[tokio::test]
+async fn auto_archive_rejects_non_positive_threshold() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/conversations/auto-archive",
+            r#"{"inactive_days": 0}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn auto_archive_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/conversations/auto-archive",
+            r#"{"inactive_days": 90}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+async fn seed_message_with_usage(state: &Arc<AppState>, conv_id: &str, completion: i64) -> String {
+    let legacy =
+        db::messages::create_message(&state.db, conv_id, "assistant", "reply", None, None, None)
+            .await
+            .unwrap();
+    db::messages_v2::create_message_with_parts(
+        &state.db,
+        Some(&legacy.id),
+        conv_id,
+        "assistant",
+        None,
+        None,
+        Some(&format!(r#"{{"completion":{completion}}}"#)),
+        None,
+        &[db::messages_v2::NewMessagePart {
+            part_type: "text",
+            text: Some("reply"),
+            json_payload: None,
+            tool_call_id: None,
+        }],
+    )
+    .await
+    .unwrap();
+    legacy.id
+}
+
+#[tokio::test]
+async fn backfill_token_usage_updates_only_requested_conversation() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let owner = db::users::create_user(&state.db, "owner", "owner@example.com", "hash")
+        .await
+        .unwrap();
+    let conv_a = db::conversations::create_conversation(
+        &state.db, &owner.id, "A", None, None, None, false, None, None, None,
+    )
+    .await
+    .unwrap();
+    let conv_b = db::conversations::create_conversation(
+        &state.db, &owner.id, "B", None, None, None, false, None, None, None,
+    )
+    .await
+    .unwrap();
+    let msg_a = seed_message_with_usage(&state, &conv_a.id, 12).await;
+    let msg_b = seed_message_with_usage(&state, &conv_b.id, 34).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            &format!(
+                "/api/admin/maintenance/backfill-token-usage?conversation_id={}",
+                conv_a.id
+            ),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["updated"], 1);
+
+    let a = db::messages::get_message(&state.db, &msg_a)
+        .await
+        .unwrap()
+        .unwrap();
+    let b = db::messages::get_message(&state.db, &msg_b)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(a.token_count, Some(12));
+    assert_eq!(b.token_count, None);
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/maintenance/backfill-token-usage",
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["updated"], 1);
+}
+
+#[tokio::test]
+async fn backfill_token_usage_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/maintenance/backfill-token-usage",
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn merge_text_parts_merges_fragments_in_conversation() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let owner = db::users::create_user(&state.db, "owner", "owner@example.com", "hash")
+        .await
+        .unwrap();
+    let conv = db::conversations::create_conversation(
+        &state.db, &owner.id, "A", None, None, None, false, None, None, None,
+    )
+    .await
+    .unwrap();
+    let text = |t| db::messages_v2::NewMessagePart {
+        part_type: "text",
+        text: Some(t),
+        json_payload: None,
+        tool_call_id: None,
+    };
+    let (msg, _) = db::messages_v2::create_message_with_parts(
+        &state.db,
+        None,
+        &conv.id,
+        "assistant",
+        None,
+        None,
+        None,
+        None,
+        &[text("one "), text("two")],
+    )
+    .await
+    .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            &format!(
+                "/api/admin/maintenance/merge-text-parts?conversation_id={}",
+                conv.id
+            ),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["removed_parts"], 1);
+
+    let parts = db::messages_v2::list_message_parts(&state.db, &msg.id)
+        .await
+        .unwrap();
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0].text.as_deref(), Some("one two"));
+}
+
+#[tokio::test]
+async fn merge_text_parts_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/maintenance/merge-text-parts?conversation_id=c1",
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn deactivate_user_notifies_and_removes_all_connections() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let target = db::users::create_user(&state.db, "target", "target@example.com", "hash")
+        .await
+        .unwrap();
+
+    let (tx1, mut rx1) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    let (tx2, mut rx2) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    let (tx3, mut rx3) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    state.ws_state.add_client(&target.id, "c1", 1, tx1).await;
+    state.ws_state.add_client(&target.id, "c2", 1, tx2).await;
+    state.ws_state.add_client("bystander", "c3", 1, tx3).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            &format!("/api/admin/users/{}/deactivate", target.id),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["closed_connections"], 2);
+
+    for rx in [&mut rx1, &mut rx2] {
+        let msg: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+        assert_eq!(msg["type"], "error");
+        assert_eq!(msg["code"], "account_disabled");
+        assert_eq!(msg["message"], "Your account has been disabled.");
+        // The state's sender was dropped after the notification
+        assert!(rx.recv().await.is_none());
+    }
+    assert!(rx3.try_recv().is_err());
+
+    let conns = state.ws_state.client_connections.read().await;
+    assert!(!conns.contains_key(&target.id));
+    assert!(conns.contains_key("bystander"));
+    drop(conns);
+
+    let user = db::users::get_user_by_id(&state.db, &target.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!user.is_active);
+}
+
+#[tokio::test]
+async fn deactivated_user_token_is_rejected() {
+    let state = test_state().await;
+    let token = token_for(&state, "former-admin", true).await;
+    let user = db::users::get_user_by_username(&state.db, "former-admin")
+        .await
+        .unwrap()
+        .unwrap();
+    db::users::set_user_active(&state.db, &user.id, false)
+        .await
+        .unwrap();
+
+    let resp = app(state)
+        .oneshot(get_with_auth("/api/admin/users", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn deactivate_unknown_user_returns_404() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/users/missing/deactivate",
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn deactivate_user_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/users/anyone/deactivate",
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn mcp_server_crud_lifecycle() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/mcp-servers",
+            r#"{"name":"fs","transport":"stdio","command":"npx","args":"[\"@mcp/fs\"]","env_vars":"{\"ROOT\":\"/w\"}","read_only_overrides":"{\"read_file\":true}","is_enabled":false}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created = json_body(resp).await;
+    let id = created["id"].as_str().unwrap().to_string();
+    assert_eq!(created["command"], "npx");
+    assert_eq!(created["is_enabled"], false);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/mcp-servers", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let list = json_body(resp).await;
+    assert!(list.as_array().unwrap().iter().any(|s| s["id"] == id));
+
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(
+            &format!("/api/admin/mcp-servers/{id}"),
+            r#"{"transport":"sse","url":"http://mcp.internal","is_enabled":true}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let updated = json_body(resp).await;
+    assert_eq!(updated["transport"], "sse");
+    assert_eq!(updated["url"], "http://mcp.internal");
+    assert_eq!(updated["is_enabled"], true);
+    assert_eq!(updated["name"], "fs");
+
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth(
+            &format!("/api/admin/mcp-servers/{id}"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/admin/mcp-servers/{id}"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn mcp_server_requires_command_or_url_for_its_transport() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/mcp-servers",
+            r#"{"name":"remote","transport":"sse","command":"npx"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = json_body(resp).await;
+    assert_eq!(
+        body["errors"],
+        serde_json::json!(["url is required for sse servers"])
+    );
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/mcp-servers",
+            r#"{"name":"local","transport":"stdio","command":"npx"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let id = json_body(resp).await["id"].as_str().unwrap().to_string();
+
+    // Switching to sse without a url leaves the server unreachable.
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(
+            &format!("/api/admin/mcp-servers/{id}"),
+            r#"{"transport":"sse"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn provider_pricing_can_be_upserted_listed_and_deleted() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    for rate in ["0.005", "0.0025"] {
+        let resp = app(state.clone())
+            .oneshot(post_with_auth(
+                "/api/admin/provider-pricing",
+                &format!(
+                    r#"{{"provider_type":"openai","model":"gpt-4o","input_cost_per_1k":{rate},"output_cost_per_1k":0.01}}"#
+                ),
+                &token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/provider-pricing", &token))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["input_cost_per_1k"], 0.0025);
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/provider-pricing",
+            r#"{"provider_type":"openai","model":"gpt-4o","input_cost_per_1k":-1,"output_cost_per_1k":0}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth(
+            "/api/admin/provider-pricing/openai/gpt-4o",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert!(
+        db::pricing::list_pricing(&state.db)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let user_token = token_for(&state, "regular", false).await;
+    let resp = app(state)
+        .oneshot(get_with_auth("/api/admin/provider-pricing", &user_token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn clone_mcp_server_returns_disabled_copy() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let source = db::mcp_servers::create_mcp_server(
+        &state.db,
+        "github-a",
+        Some("GitHub"),
+        "stdio",
+        Some("npx"),
+        Some(r#"["@mcp/github"]"#),
+        None,
+        Some(r#"{"ORG":"a"}"#),
+        true,
+    )
+    .await
+    .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            &format!("/api/admin/mcp-servers/{}/clone", source.id),
+            r#"{"name":"github-b"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body = json_body(resp).await;
+    assert_ne!(body["id"], source.id);
+    assert_eq!(body["name"], "github-b");
+    assert_eq!(body["is_enabled"], false);
+    assert_eq!(body["command"], "npx");
+    assert_eq!(body["env_vars"], r#"{"ORG":"a"}"#);
+
+    // Cloning onto an existing name conflicts.
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            &format!("/api/admin/mcp-servers/{}/clone", source.id),
+            r#"{"name":"github-a"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn clone_unknown_mcp_server_returns_404() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/mcp-servers/nonexistent/clone",
+            r#"{"name":"copy"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn clone_mcp_server_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/mcp-servers/any/clone",
+            r#"{"name":"copy"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn exec_in_container_rejects_disallowed_command() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/containers/conv-1/exec",
+            r#"{"command":["rm","-rf","/workspace"]}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/containers/conv-1/exec",
+            r#"{"command":[]}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn exec_in_container_without_running_container_returns_409() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/containers/conv-1/exec",
+            r#"{"command":["df","-h"],"timeout_secs":5}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn exec_in_container_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/containers/conv-1/exec",
+            r#"{"command":["df","-h"]}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn ws_metrics_reports_connection_counts() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let (tx1, _rx1) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    let (tx2, _rx2) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    state.ws_state.add_client("u1", "c1", 1, tx1).await;
+    state.ws_state.add_container("c1", tx2).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/ws-metrics", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(resp).await,
+        serde_json::json!({
+            "active_client_connections": 1,
+            "active_container_connections": 1,
+            "pending_messages": 0,
+        })
+    );
+}
+
+#[tokio::test]
+async fn ws_metrics_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/ws-metrics", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn ws_connections_lists_open_clients() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let (tx1, _rx1) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    let (tx2, _rx2) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    state.ws_state.add_client("u2", "c2", 1, tx1).await;
+    state.ws_state.add_client("u1", "c1", 1, tx2).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/ws-connections", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(resp).await,
+        serde_json::json!([
+            {"user_id": "u1", "conversation_id": "c1", "connection_id": 1, "connected_secs": 0, "idle_secs": 0},
+            {"user_id": "u2", "conversation_id": "c2", "connection_id": 1, "connected_secs": 0, "idle_secs": 0},
+        ])
+    );
+}
+
+#[tokio::test]
+async fn ws_connections_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/ws-connections", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+/// Docker client that records stopped and removed containers. Only
+/// `stop_container` and `remove_container` are expected to be called.
+#[derive(Default)]
+struct RecordingDocker {
+    stopped: Mutex<Vec<String>>,
+    removed: Mutex<Vec<String>>,
+}
+
+impl DockerClient for RecordingDocker {
+    fn create_container<'a>(
+        &'a self,
+        _name: &'a str,
+        _config: bollard::container::Config<String>,
+    ) -> BoxFuture<'a, Result<String, bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn start_container<'a>(
+        &'a self,
+        _id: &'a str,
+    ) -> BoxFuture<'a, Result<(), bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn stop_container<'a>(
+        &'a self,
+        id: &'a str,
+        _timeout_secs: i64,
+    ) -> BoxFuture<'a, Result<(), bollard::errors::Error>> {
+        self.stopped.lock().unwrap().push(id.to_string());
+        Box::pin(async { Ok(()) })
+    }
+
+    fn remove_container<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> BoxFuture<'a, Result<(), bollard::errors::Error>> {
+        self.removed.lock().unwrap().push(id.to_string());
+        Box::pin(async { Ok(()) })
+    }
+
+    fn rename_container<'a>(
+        &'a self,
+        _id: &'a str,
+        _name: &'a str,
+    ) -> BoxFuture<'a, Result<(), bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn upload_to_container<'a>(
+        &'a self,
+        _id: &'a str,
+        _path: &'a str,
+        _tar: Vec<u8>,
+    ) -> BoxFuture<'a, Result<(), bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn exec<'a>(
+        &'a self,
+        _id: &'a str,
+        _cmd: Vec<String>,
+    ) -> BoxFuture<'a, Result<(String, OutputStream), bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn exec_exit_code<'a>(
+        &'a self,
+        _exec_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<i64>, bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn logs(&self, _id: &str) -> OutputStream {
+        unimplemented!()
+    }
+}
+
+fn delete_with_auth(uri: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("DELETE")
+        .uri(uri)
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn list_and_force_stop_containers() {
+    let docker = Arc::new(RecordingDocker::default());
+    let registry = ContainerRegistry::new();
+    let docker_manager = Arc::new(DockerManager::with_client(
+        docker.clone(),
+        test_config(),
+        registry.clone(),
+    ));
+    let state = test_state_with_manager(docker_manager).await;
+    let token = token_for(&state, "admin", true).await;
+
+    registry.register("conv-1", "container-1", "u1").await;
+    registry.register("conv-2", "container-2", "u2").await;
+    let (tx, _rx) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    state.ws_state.add_container("conv-1", tx).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/containers", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    let listed = body.as_array().unwrap();
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0]["conversation_id"], "conv-1");
+    assert_eq!(listed[0]["container_id"], "container-1");
+    assert_eq!(listed[0]["user_id"], "u1");
+    assert!(
+        chrono::DateTime::parse_from_rfc3339(listed[0]["started_at"].as_str().unwrap()).is_ok()
+    );
+
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth("/api/admin/containers/conv-1", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(*docker.stopped.lock().unwrap(), vec!["container-1"]);
+    assert_eq!(*docker.removed.lock().unwrap(), vec!["container-1"]);
+    assert!(!state.ws_state.send_to_container("conv-1", "ping").await);
+    assert!(registry.get("conv-1").await.is_none());
+    assert!(registry.get("conv-2").await.is_some());
+
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth("/api/admin/containers/conv-1", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn container_admin_endpoints_require_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/containers", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth("/api/admin/containers/conv-1", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn admin_users_can_be_created_listed_and_updated() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/users",
+            r#"{"username":"Newbie","email":"newbie@example.com","password":"password123","is_admin":true}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created = json_body(resp).await;
+    let id = created["id"].as_str().unwrap().to_string();
+    assert_eq!(created["username"], "newbie");
+    assert_eq!(created["is_admin"], true);
+    assert_eq!(created["email_verified"], true);
+    assert!(created.get("password_hash").is_none());
+
+    let duplicate = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/admin/users",
+            r#"{"username":"newbie","email":"other@example.com","password":"password123"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+
+    db::conversations::create_conversation(
+        &state.db, &id, "t", None, None, None, false, None, None, None,
+    )
+    .await
+    .unwrap();
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/users?limit=1&offset=1", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let page = json_body(resp).await;
+    assert_eq!(page["total"], 2);
+    assert_eq!(page["users"].as_array().unwrap().len(), 1);
+    assert_eq!(page["users"][0]["id"], id.as_str());
+    assert_eq!(page["users"][0]["conversation_count"], 1);
+    assert!(page["users"][0]["last_login_at"].is_null());
+
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(
+            &format!("/api/admin/users/{id}"),
+            r#"{"email":"renamed@example.com","is_admin":false,"is_active":false}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let updated = json_body(resp).await;
+    assert_eq!(updated["email"], "renamed@example.com");
+    assert_eq!(updated["is_admin"], false);
+    assert_eq!(updated["is_active"], false);
+
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(
+            &format!("/api/admin/users/{id}"),
+            r#"{"email":"admin@example.com"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn demoted_admin_loses_access_before_token_expires() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let other_token = token_for(&state, "other", true).await;
+    let other_id = db::users::get_user_by_username(&state.db, "other")
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(
+            &format!("/api/admin/users/{other_id}"),
+            r#"{"is_admin":false}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/users", &other_token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn admin_cannot_demote_suspend_or_delete_themselves() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let admin_id = db::users::get_user_by_username(&state.db, "admin")
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(
+            &format!("/api/admin/users/{admin_id}"),
+            r#"{"is_active":false}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth(
+            &format!("/api/admin/users/{admin_id}"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert!(
+        db::users::get_user_by_id(&state.db, &admin_id)
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn delete_user_removes_conversations_messages_and_workspaces() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let target = db::users::create_user(&state.db, "target", "target@example.com", "hash")
+        .await
+        .unwrap();
+    let conv = db::conversations::create_conversation(
+        &state.db, &target.id, "t", None, None, None, false, None, None, None,
+    )
+    .await
+    .unwrap();
+    db::messages::create_message(&state.db, &conv.id, "user", "hi", None, None, None)
+        .await
+        .unwrap();
+    let workspace = format!("data/conversations/{}", conv.id);
+    tokio::fs::create_dir_all(&workspace).await.unwrap();
+    tokio::fs::write(format!("{workspace}/notes.txt"), b"hi")
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth(
+            &format!("/api/admin/users/{}", target.id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    assert!(
+        db::users::get_user_by_id(&state.db, &target.id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let conversations: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM conversations WHERE user_id = ?")
+            .bind(&target.id)
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+    assert_eq!(conversations, 0);
+    let messages: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM messages WHERE conversation_id = ?")
+            .bind(&conv.id)
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+    assert_eq!(messages, 0);
+    assert!(!std::path::Path::new(&workspace).exists());
+
+    let again = app(state.clone())
+        .oneshot(delete_with_auth(
+            &format!("/api/admin/users/{}", target.id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(again.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn user_management_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let requests = [
+        get_with_auth("/api/admin/users", &token),
+        post_with_auth(
+            "/api/admin/users",
+            r#"{"username":"x","email":"x@example.com","password":"password123"}"#,
+            &token,
+        ),
+        put_with_auth("/api/admin/users/anyone", r#"{"is_admin":true}"#, &token),
+        delete_with_auth("/api/admin/users/anyone", &token),
+    ];
+    for req in requests {
+        let resp = app(state.clone()).oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+}
+
+#[tokio::test]
+async fn system_stats_reports_counts_and_caches_them() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let user = db::users::create_user(&state.db, "member", "member@example.com", "hash")
+        .await
+        .unwrap();
+    let conv = db::conversations::create_conversation(
+        &state.db, &user.id, "t", None, None, None, false, None, None, None,
+    )
+    .await
+    .unwrap();
+    db::messages::create_message(&state.db, &conv.id, "user", "hi", None, None, None)
+        .await
+        .unwrap();
+    let (tx, _rx) = mpsc::channel(WS_CHANNEL_CAPACITY);
+    state.ws_state.add_container(&conv.id, tx).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/stats", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let stats = json_body(resp).await;
+    assert_eq!(stats["users"]["total"], 2);
+    assert_eq!(stats["users"]["active_7d"], 1);
+    assert_eq!(stats["conversations"]["total"], 1);
+    assert_eq!(stats["conversations"]["active_today"], 1);
+    assert_eq!(stats["messages"]["total"], 1);
+    assert_eq!(stats["messages"]["today"], 1);
+    assert_eq!(stats["containers"]["active"], 1);
+    assert!(stats["db_size_bytes"].as_i64().unwrap() > 0);
+
+    // A new user isn't counted until the cached result expires.
+    db::users::create_user(&state.db, "late", "late@example.com", "hash")
+        .await
+        .unwrap();
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/stats", &token))
+        .await
+        .unwrap();
+    assert_eq!(json_body(resp).await["users"]["total"], 2);
+}
+
+#[tokio::test]
+async fn system_stats_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/stats", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+fn post_json(uri: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .header("user-agent", "audit-test")
+        .extension(ConnectInfo(SocketAddr::from(([203, 0, 113, 7], 443))))
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn audit_log_records_login_and_provider_save() {
+    let state = test_state().await;
+    let admin_token = token_for(&state, "admin", true).await;
+    let full_app = || {
+        Router::new()
+            .nest("/api/auth", api::auth::router())
+            .nest("/api/users", api::users::router())
+            .nest("/api/admin", api::admin::router())
+            .with_state(state.clone())
+    };
+
+    let resp = full_app()
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"auditee","email":"auditee@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = full_app()
+        .oneshot(post_json(
+            "/api/auth/login",
+            r#"{"username":"auditee","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let token = json_body(resp).await["access_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let resp = full_app()
+        .oneshot(post_with_auth(
+            "/api/users/me/providers",
+            r#"{"name":"Mine","provider_type":"openai","api_key":"sk-secret","models":["gpt-4o"],"is_default":true}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let provider_id = json_body(resp).await["id"].as_str().unwrap().to_string();
+    let user_id = db::users::get_user_by_username(&state.db, "auditee")
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+
+    let resp = full_app()
+        .oneshot(get_with_auth(
+            &format!("/api/admin/audit-log?user_id={user_id}"),
+            &admin_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    let entries = body["entries"].as_array().unwrap();
+    let actions: Vec<&str> = entries
+        .iter()
+        .map(|e| e["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["provider_key_save", "login", "register"]);
+    assert_eq!(entries[0]["target_type"], "provider");
+    assert_eq!(entries[0]["target_id"], provider_id);
+    assert!(
+        !entries[0]["details_json"]
+            .as_str()
+            .unwrap()
+            .contains("sk-secret")
+    );
+    assert_eq!(entries[1]["ip"], "203.0.113.7");
+    assert_eq!(entries[1]["user_agent"], "audit-test");
+    assert!(body.get("next_cursor").is_none());
+
+    // Filter by action and page through with the cursor.
+    let resp = full_app()
+        .oneshot(get_with_auth(
+            "/api/admin/audit-log?action=login&limit=1",
+            &admin_token,
+        ))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body["entries"].as_array().unwrap().len(), 1);
+    assert_eq!(body["entries"][0]["user_id"], user_id);
+    assert!(body.get("next_cursor").is_none());
+
+    let resp = full_app()
+        .oneshot(get_with_auth("/api/admin/audit-log?limit=2", &admin_token))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    let cursor = body["next_cursor"].as_str().unwrap().to_string();
+    let resp = full_app()
+        .oneshot(get_with_auth(
+            &format!("/api/admin/audit-log?limit=2&after={cursor}"),
+            &admin_token,
+        ))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body["entries"][0]["action"], "register");
+}
+
+#[tokio::test]
+async fn audit_log_records_admin_user_changes() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let member = db::users::create_user(&state.db, "member", "member@example.com", "hash")
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(
+            &format!("/api/admin/users/{}", member.id),
+            r#"{"is_admin":true}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            "/api/admin/audit-log?action=admin_user_update",
+            &token,
+        ))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    let entry = &body["entries"][0];
+    assert_eq!(entry["target_id"], member.id);
+    let details: serde_json::Value =
+        serde_json::from_str(entry["details_json"].as_str().unwrap()).unwrap();
+    assert_eq!(details["is_admin"], true);
+}
+
+#[tokio::test]
+async fn audit_log_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/audit-log", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn audit_log_rejects_unknown_cursor() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            "/api/admin/audit-log?after=no-such-entry",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn user_quota_can_be_read_and_replaced() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let member = db::users::create_user(&state.db, "member", "member@example.com", "hash")
+        .await
+        .unwrap();
+    let uri = format!("/api/admin/users/{}/quota", member.id);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert!(body["max_conversations"].is_null());
+    assert!(body["effective"]["max_conversations"].is_null());
+    assert_eq!(
+        body["effective"]["max_workspace_bytes"],
+        state.config.workspace_max_bytes
+    );
+
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(
+            &uri,
+            r#"{"max_conversations":3,"max_workspace_bytes":1024}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["max_conversations"], 3);
+    assert!(body["max_messages_per_conversation"].is_null());
+    assert_eq!(body["effective"]["max_workspace_bytes"], 1024);
+
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(&uri, r#"{"max_conversations":-1}"#, &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/admin/users/missing/quota", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn user_quota_requires_admin() {
+    let state = test_state().await;
+    let token = token_for(&state, "regular", false).await;
+    let uri = "/api/admin/users/someone/quota";
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(uri, &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(uri, "{}", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
@@ -1,18 +1,23 @@
 use axum::{
     Router,
     body::Body,
+    extract::ConnectInfo,
     http::{Request, StatusCode},
 };
 use claude_chat_backend::{
     api,
-    auth::middleware::AppState,
+    auth::{JwtKeys, middleware::AppState},
     config::Config,
     db,
     docker::{manager::DockerManager, registry::ContainerRegistry},
-    ws::WsState,
+    mailer::{Mailer, MailerError},
+    provider_api::HttpProviderApiClient,
+    ws::{WsState, sse::SseState},
 };
+use futures_util::future::BoxFuture;
 use http_body_util::BodyExt;
-use std::sync::Arc;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 use tower::ServiceExt;
 
 /// Build a test Config with required fields.
@@ -20,11 +25,18 @@ fn test_config() -> Config {
     Config {
         database_url: "sqlite::memory:".into(),
         jwt_secret: "test-jwt-secret-that-is-long-enough-for-hmac".into(),
+        jwt_algorithm: Default::default(),
+        jwt_private_key_path: None,
+        jwt_public_key_path: None,
         encryption_key: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".into(),
         host: "127.0.0.1".into(),
         port: 0,
         container_image: "test:latest".into(),
         container_idle_timeout_secs: 600,
+        container_cpu_quota: None,
+        container_memory_bytes: None,
+        container_start_max_retries: 0,
+        container_pool_size: 0,
         internal_ws_port: 0,
         docker_network: None,
         host_data_dir: None,
@@ -34,6 +46,24 @@ fn test_config() -> Config {
         container_token_ttl_secs: 3600,
         refresh_token_ttl_days: 30,
         cookie_secure: false,
+        tool_call_timeout_secs: 300,
+        max_file_size_bytes: 50 * 1024 * 1024,
+        workspace_max_bytes: 1024 * 1024 * 1024,
+        allowed_upload_mime_types: None,
+        token_usage_update_interval_tokens: 500,
+        container_exec_allowlist: vec!["df".into(), "du".into(), "ls".into(), "ps".into()],
+        max_login_attempts: 10,
+        login_lockout_secs: 900,
+        trusted_proxies: vec!["10.0.0.1/32".parse().unwrap()],
+        require_email_verification: false,
+        smtp_url: None,
+        mail_from: None,
+        oauth_client_id: None,
+        oauth_client_secret: None,
+        oauth_auth_url: None,
+        oauth_token_url: None,
+        max_conversations_per_user: None,
+        max_messages_per_conversation: None,
     }
 }
 
@@ -43,16 +73,67 @@ async fn test_state() -> Arc<AppState> {
 }
 
 async fn test_state_with_config(config: Config) -> Arc<AppState> {
+    test_state_with_mailer(config).await.0
+}
+
+/// Records every email as `(to, body)` instead of sending it.
+#[derive(Default)]
+struct RecordingMailer {
+    sent: Mutex<Vec<(String, String)>>,
+}
+
+impl Mailer for RecordingMailer {
+    fn send<'a>(
+        &'a self,
+        to: &'a str,
+        _subject: &'a str,
+        body: &'a str,
+    ) -> BoxFuture<'a, Result<(), MailerError>> {
+        self.sent
+            .lock()
+            .unwrap()
+            .push((to.to_string(), body.to_string()));
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl RecordingMailer {
+    /// The token in the latest email sent to `to`.
+    fn token_sent_to(&self, to: &str) -> String {
+        let sent = self.sent.lock().unwrap();
+        let (_, body) = sent
+            .iter()
+            .rev()
+            .find(|(recipient, _)| recipient == to)
+            .unwrap_or_else(|| panic!("no email sent to {to}"));
+        body.lines()
+            .map(str::trim)
+            .find(|line| line.len() == 64 && line.chars().all(|c| c.is_ascii_hexdigit()))
+            .expect("email contains a token")
+            .to_string()
+    }
+}
+
+async fn test_state_with_mailer(config: Config) -> (Arc<AppState>, Arc<RecordingMailer>) {
+    let mailer = Arc::new(RecordingMailer::default());
     let pool = db::init_db("sqlite::memory:").await;
     let ws_state = WsState::new();
     let registry = ContainerRegistry::new();
     let docker_manager = Arc::new(DockerManager::new(config.clone(), registry));
-    Arc::new(AppState {
+    let state = Arc::new(AppState {
         db: pool,
+        jwt_keys: JwtKeys::from_config(&config).unwrap(),
         config,
         ws_state,
+        sse_state: SseState::new(),
+        pending_uploads: Default::default(),
         docker_manager,
-    })
+        provider_api: Arc::new(HttpProviderApiClient::default()),
+        mailer: Some(mailer.clone()),
+        system_stats_cache: Default::default(),
+        features: Default::default(),
+    });
+    (state, mailer)
 }
 
 /// Build the auth router with test state.
@@ -272,6 +353,29 @@ async fn register_duplicate_username_rejected() {
     assert_eq!(resp.status(), StatusCode::CONFLICT);
 }
 
+#[tokio::test]
+async fn register_username_differing_only_in_case_rejected() {
+    let state = test_state().await;
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"Alice","email":"alice@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["user"]["username"], "alice");
+
+    let resp = auth_app(state)
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"alice","email":"alice2@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
 #[tokio::test]
 async fn concurrent_register_same_username_one_conflict() {
     let state = test_state().await;
@@ -469,6 +573,62 @@ async fn login_success() {
     assert_eq!(body["user"]["username"], "bob");
 }
 
+#[tokio::test]
+async fn login_succeeds_when_audit_log_insert_fails() {
+    let state = test_state().await;
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"audit_fail","email":"audit_fail@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    sqlx::query(
+        "CREATE TRIGGER fail_audit_insert
+         BEFORE INSERT ON audit_log
+         BEGIN
+             SELECT RAISE(FAIL, 'forced audit insert failure');
+         END;",
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+
+    let resp = auth_app(state)
+        .oneshot(post_json(
+            "/api/auth/login",
+            r#"{"username":"audit_fail","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(json_body(resp).await["access_token"].is_string());
+}
+
+#[tokio::test]
+async fn login_username_is_case_insensitive() {
+    let state = test_state().await;
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"Alice","email":"alice@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    for username in ["alice", "ALICE"] {
+        let body = format!(r#"{{"username":"{username}","password":"password123"}}"#);
+        let resp = auth_app(state.clone())
+            .oneshot(post_json("/api/auth/login", &body))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
+
 #[tokio::test]
 async fn login_wrong_password() {
     let state = test_state().await;
@@ -544,6 +704,152 @@ async fn login_sets_auth_cookies_with_expected_attributes() {
     assert!(refresh.contains("Max-Age=2592000"));
 }
 
+/// A login sent directly (not through a proxy) from `ip`.
+fn login_from(ip: &str, username: &str, password: &str) -> Request<Body> {
+    login_with_forwarded_for(ip, ip, username, password)
+}
+
+fn login_with_forwarded_for(
+    peer: &str,
+    forwarded_for: &str,
+    username: &str,
+    password: &str,
+) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/api/auth/login")
+        .header("content-type", "application/json")
+        .header("x-forwarded-for", forwarded_for)
+        .extension(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 443)))
+        .body(Body::from(format!(
+            r#"{{"username":"{username}","password":"{password}"}}"#
+        )))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn login_locks_out_after_repeated_failures() {
+    let mut config = test_config();
+    config.max_login_attempts = 3;
+    config.login_lockout_secs = 60;
+    let state = test_state_with_config(config).await;
+
+    auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"locked","email":"locked@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        let resp = auth_app(state.clone())
+            .oneshot(login_from("198.51.100.1", "locked", "wrong-password"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+    let resp = auth_app(state.clone())
+        .oneshot(login_from("198.51.100.1", "locked", "wrong-password"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The correct password is refused while the lock is active.
+    let resp = auth_app(state.clone())
+        .oneshot(login_from("198.51.100.1", "locked", "password123"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = resp.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after));
+
+    // A different client address is not affected.
+    let resp = auth_app(state)
+        .oneshot(login_from("198.51.100.2", "locked", "password123"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn login_lockout_ignores_forwarded_for_from_untrusted_peer() {
+    let mut config = test_config();
+    config.max_login_attempts = 2;
+    let state = test_state_with_config(config).await;
+
+    auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"spoofed","email":"spoofed@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+
+    // Rotating X-Forwarded-For does not give an untrusted peer fresh attempts.
+    for (forwarded_for, expected) in [
+        ("203.0.113.1", StatusCode::UNAUTHORIZED),
+        ("203.0.113.2", StatusCode::TOO_MANY_REQUESTS),
+        ("203.0.113.3", StatusCode::TOO_MANY_REQUESTS),
+    ] {
+        let resp = auth_app(state.clone())
+            .oneshot(login_with_forwarded_for(
+                "198.51.100.9",
+                forwarded_for,
+                "spoofed",
+                "wrong-password",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), expected, "{forwarded_for}");
+    }
+}
+
+#[tokio::test]
+async fn login_success_resets_failed_attempts() {
+    let mut config = test_config();
+    config.max_login_attempts = 2;
+    let state = test_state_with_config(config).await;
+
+    auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"resets","email":"resets@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+
+    for password in ["wrong-password", "password123", "wrong-password"] {
+        auth_app(state.clone())
+            .oneshot(login_from("198.51.100.3", "resets", password))
+            .await
+            .unwrap();
+    }
+    // Only one failure since the last success, so the account is not locked.
+    let resp = auth_app(state)
+        .oneshot(login_from("198.51.100.3", "resets", "password123"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn login_lockout_applies_to_unknown_usernames() {
+    let mut config = test_config();
+    config.max_login_attempts = 1;
+    let state = test_state_with_config(config).await;
+
+    let resp = auth_app(state.clone())
+        .oneshot(login_from("198.51.100.4", "ghost", "whatever"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
 // ── Refresh ──
 
 #[tokio::test]
@@ -740,6 +1046,100 @@ async fn refresh_token_concurrent_replay_only_one_succeeds() {
     );
 }
 
+async fn refresh_with(state: &Arc<AppState>, refresh_token: &str) -> axum::response::Response {
+    auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/refresh",
+            &format!(r#"{{"refresh_token":"{}"}}"#, refresh_token),
+        ))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn refresh_token_replay_revokes_whole_family() {
+    let state = test_state().await;
+
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"victim","email":"victim@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    let stolen = json_body(resp).await["refresh_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // The legitimate client rotates the token as usual.
+    let resp = refresh_with(&state, &stolen).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let current = json_body(resp).await["refresh_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Later, an attacker replays the copy they captured.
+    let resp = refresh_with(&state, &stolen).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // The replay revoked the rotated token too, forcing a fresh login.
+    let resp = refresh_with(&state, &current).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let remaining = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM refresh_tokens
+         WHERE user_id IN (SELECT id FROM users WHERE username = 'victim')",
+    )
+    .fetch_one(&state.db)
+    .await
+    .unwrap();
+    assert_eq!(remaining, 0);
+}
+
+#[tokio::test]
+async fn refresh_token_replay_leaves_other_sessions_alone() {
+    let state = test_state().await;
+
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"twodevices","email":"twodevices@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    let stolen = json_body(resp).await["refresh_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/login",
+            r#"{"username":"twodevices","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let other_device = json_body(resp).await["refresh_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    assert_eq!(refresh_with(&state, &stolen).await.status(), StatusCode::OK);
+    assert_eq!(
+        refresh_with(&state, &stolen).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // The other login is a separate family and keeps working.
+    assert_eq!(
+        refresh_with(&state, &other_device).await.status(),
+        StatusCode::OK
+    );
+}
+
 // ── Logout ──
 
 #[tokio::test]
@@ -836,83 +1236,246 @@ async fn logout_with_refresh_cookie_clears_auth_cookies() {
     assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
 }
 
-// ── Auth Middleware ──
-
-#[tokio::test]
-async fn authenticated_endpoint_without_token_returns_401() {
-    let state = test_state().await;
-    let app = Router::new()
-        .nest("/api/conversations", api::conversations::router())
-        .with_state(state);
+// ── Sessions ──
 
-    let req = Request::builder()
-        .method("GET")
-        .uri("/api/conversations")
+/// Build an authenticated request that passes the rate limiter, sent from a
+/// recognisable client through the trusted proxy.
+fn session_request(method: &str, uri: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("authorization", format!("Bearer {}", token))
+        .header("x-forwarded-for", "203.0.113.7, 10.0.0.1")
+        .header("user-agent", "session-test/1.0")
+        .extension(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 443))))
         .body(Body::empty())
+        .unwrap()
+}
+
+async fn register_for_sessions(state: &Arc<AppState>, username: &str) -> serde_json::Value {
+    let resp = auth_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/auth/register")
+                .header("content-type", "application/json")
+                .header("x-forwarded-for", "203.0.113.7, 10.0.0.1")
+                .header("user-agent", "session-test/1.0")
+                .extension(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 443))))
+                .body(Body::from(format!(
+                    r#"{{"username":"{username}","email":"{username}@example.com","password":"password123"}}"#
+                )))
+                .unwrap(),
+        )
+        .await
         .unwrap();
-    let resp = app.oneshot(req).await.unwrap();
-    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(resp.status(), StatusCode::OK);
+    json_body(resp).await
 }
 
 #[tokio::test]
-async fn authenticated_endpoint_with_valid_token_succeeds() {
+async fn list_sessions_returns_active_logins_without_token_hash() {
     let state = test_state().await;
+    let body = register_for_sessions(&state, "sessions_list").await;
+    let access_token = body["access_token"].as_str().unwrap();
 
-    // Register to get a token
-    let app = auth_app(state.clone());
-    let resp = app
+    let resp = auth_app(state.clone())
         .oneshot(post_json(
-            "/api/auth/register",
-            r#"{"username":"frank","email":"frank@example.com","password":"password123"}"#,
+            "/api/auth/login",
+            r#"{"username":"sessions_list","password":"password123"}"#,
         ))
         .await
         .unwrap();
-    let body = json_body(resp).await;
-    let access_token = body["access_token"].as_str().unwrap().to_string();
+    assert_eq!(resp.status(), StatusCode::OK);
 
-    // Use token to access protected endpoint
-    let app = Router::new()
-        .nest("/api/conversations", api::conversations::router())
-        .with_state(state);
-    let resp = app
-        .oneshot(get_with_auth("/api/conversations", &access_token))
+    let resp = auth_app(state.clone())
+        .oneshot(session_request("GET", "/api/auth/sessions", access_token))
         .await
         .unwrap();
     assert_eq!(resp.status(), StatusCode::OK);
+    let sessions = json_body(resp).await;
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    for session in sessions {
+        assert!(session.get("token_hash").is_none());
+        assert!(session["id"].is_string());
+        assert!(session["expires_at"].is_string());
+    }
+    let registered = sessions
+        .iter()
+        .find(|s| s["user_agent"] == "session-test/1.0")
+        .expect("registration session missing");
+    assert_eq!(registered["ip_address"], "203.0.113.7");
 }
 
 #[tokio::test]
-async fn authenticated_endpoint_with_invalid_token_returns_401() {
+async fn list_sessions_requires_auth() {
     let state = test_state().await;
-    let app = Router::new()
-        .nest("/api/conversations", api::conversations::router())
-        .with_state(state);
-
-    let resp = app
-        .oneshot(get_with_auth("/api/conversations", "invalid-token-here"))
+    let resp = auth_app(state)
+        .oneshot(session_request(
+            "GET",
+            "/api/auth/sessions",
+            "invalid-token",
+        ))
         .await
         .unwrap();
     assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
 }
 
 #[tokio::test]
-async fn wrong_auth_scheme_returns_401() {
+async fn delete_session_revokes_its_refresh_token() {
     let state = test_state().await;
-    let app = Router::new()
-        .nest("/api/conversations", api::conversations::router())
-        .with_state(state);
+    let body = register_for_sessions(&state, "sessions_delete").await;
+    let access_token = body["access_token"].as_str().unwrap().to_string();
+    let refresh_token = body["refresh_token"].as_str().unwrap().to_string();
 
-    let req = Request::builder()
-        .method("GET")
-        .uri("/api/conversations")
-        .header("authorization", "Basic dXNlcjpwYXNz")
-        .body(Body::empty())
+    let resp = auth_app(state.clone())
+        .oneshot(session_request("GET", "/api/auth/sessions", &access_token))
+        .await
         .unwrap();
-    let resp = app.oneshot(req).await.unwrap();
-    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
-}
-
-// ── Cookie Auth ──
+    let sessions = json_body(resp).await;
+    let session_id = sessions[0]["id"].as_str().unwrap().to_string();
+
+    let resp = auth_app(state.clone())
+        .oneshot(session_request(
+            "DELETE",
+            &format!("/api/auth/sessions/{session_id}"),
+            &access_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/refresh",
+            &format!(r#"{{"refresh_token":"{}"}}"#, refresh_token),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = auth_app(state)
+        .oneshot(session_request("GET", "/api/auth/sessions", &access_token))
+        .await
+        .unwrap();
+    assert_eq!(json_body(resp).await, serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn delete_session_of_another_user_is_not_found() {
+    let state = test_state().await;
+    let owner = register_for_sessions(&state, "sessions_owner").await;
+    let intruder = register_for_sessions(&state, "sessions_intruder").await;
+    let owner_token = owner["access_token"].as_str().unwrap();
+    let intruder_token = intruder["access_token"].as_str().unwrap();
+
+    let resp = auth_app(state.clone())
+        .oneshot(session_request("GET", "/api/auth/sessions", owner_token))
+        .await
+        .unwrap();
+    let sessions = json_body(resp).await;
+    let session_id = sessions[0]["id"].as_str().unwrap().to_string();
+
+    let resp = auth_app(state.clone())
+        .oneshot(session_request(
+            "DELETE",
+            &format!("/api/auth/sessions/{session_id}"),
+            intruder_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // The owner's session is untouched.
+    let owner_refresh = owner["refresh_token"].as_str().unwrap();
+    let resp = auth_app(state)
+        .oneshot(post_json(
+            "/api/auth/refresh",
+            &format!(r#"{{"refresh_token":"{}"}}"#, owner_refresh),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+// ── Auth Middleware ──
+
+#[tokio::test]
+async fn authenticated_endpoint_without_token_returns_401() {
+    let state = test_state().await;
+    let app = Router::new()
+        .nest("/api/conversations", api::conversations::router())
+        .with_state(state);
+
+    let req = Request::builder()
+        .method("GET")
+        .uri("/api/conversations")
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn authenticated_endpoint_with_valid_token_succeeds() {
+    let state = test_state().await;
+
+    // Register to get a token
+    let app = auth_app(state.clone());
+    let resp = app
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"frank","email":"frank@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    let access_token = body["access_token"].as_str().unwrap().to_string();
+
+    // Use token to access protected endpoint
+    let app = Router::new()
+        .nest("/api/conversations", api::conversations::router())
+        .with_state(state);
+    let resp = app
+        .oneshot(get_with_auth("/api/conversations", &access_token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn authenticated_endpoint_with_invalid_token_returns_401() {
+    let state = test_state().await;
+    let app = Router::new()
+        .nest("/api/conversations", api::conversations::router())
+        .with_state(state);
+
+    let resp = app
+        .oneshot(get_with_auth("/api/conversations", "invalid-token-here"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn wrong_auth_scheme_returns_401() {
+    let state = test_state().await;
+    let app = Router::new()
+        .nest("/api/conversations", api::conversations::router())
+        .with_state(state);
+
+    let req = Request::builder()
+        .method("GET")
+        .uri("/api/conversations")
+        .header("authorization", "Basic dXNlcjpwYXNz")
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+// ── Cookie Auth ──
 
 #[tokio::test]
 async fn cookie_authenticates_successfully() {
@@ -1137,3 +1700,453 @@ async fn missing_auth_for_file_view_returns_401() {
     let resp = app.oneshot(req).await.unwrap();
     assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
 }
+
+fn get_with_ip(uri: &str) -> Request<Body> {
+    Request::builder()
+        .method("GET")
+        .uri(uri)
+        .header("x-forwarded-for", "127.0.0.1")
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn oauth_init_returns_state_without_provider() {
+    let state = test_state().await;
+    let resp = auth_app(state.clone())
+        .oneshot(post_json("/api/auth/oauth/init", "{}"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert!(body["auth_url"].is_null());
+    let oauth_state = body["state"].as_str().unwrap();
+    let verifier = body["code_verifier"].as_str().unwrap();
+    assert!(!oauth_state.is_empty());
+    assert_eq!(verifier.len(), 43);
+
+    let stored: String =
+        sqlx::query_scalar("SELECT code_verifier FROM oauth_states WHERE state = ?")
+            .bind(oauth_state)
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+    assert_eq!(stored, verifier);
+}
+
+#[tokio::test]
+async fn oauth_init_builds_auth_url_when_provider_configured() {
+    let mut config = test_config();
+    config.oauth_client_id = Some("client-1".into());
+    config.oauth_client_secret = Some("secret".into());
+    config.oauth_auth_url = Some("https://idp.example.com/authorize".into());
+    config.oauth_token_url = Some("https://idp.example.com/token".into());
+    let state = test_state_with_config(config).await;
+
+    let resp = auth_app(state)
+        .oneshot(post_json("/api/auth/oauth/init", "{}"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    let auth_url = body["auth_url"].as_str().unwrap();
+    assert!(auth_url.starts_with("https://idp.example.com/authorize?"));
+    assert!(auth_url.contains("client_id=client-1"));
+    assert!(auth_url.contains(&format!("state={}", body["state"].as_str().unwrap())));
+    assert!(auth_url.contains(&format!(
+        "code_challenge={}",
+        body["code_challenge"].as_str().unwrap()
+    )));
+    assert!(auth_url.contains("code_challenge_method=S256"));
+}
+
+#[tokio::test]
+async fn oauth_callback_rejects_unknown_state() {
+    let state = test_state().await;
+    let resp = auth_app(state)
+        .oneshot(get_with_ip(
+            "/api/auth/oauth/callback?code=abc&state=unknown",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn oauth_callback_rejects_expired_state() {
+    let state = test_state().await;
+    let expired = (chrono::Utc::now() - chrono::Duration::seconds(1)).to_rfc3339();
+    db::oauth_states::create_oauth_state(&state.db, "stale-state", "verifier", &expired)
+        .await
+        .unwrap();
+
+    let resp = auth_app(state)
+        .oneshot(get_with_ip(
+            "/api/auth/oauth/callback?code=abc&state=stale-state",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let body = json_body(resp).await;
+    assert_eq!(body["message"], "Unauthorized: OAuth state expired");
+}
+
+#[tokio::test]
+async fn oauth_callback_state_cannot_be_replayed() {
+    let state = test_state().await;
+    let resp = auth_app(state.clone())
+        .oneshot(post_json("/api/auth/oauth/init", "{}"))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    let uri = format!(
+        "/api/auth/oauth/callback?code=abc&state={}",
+        body["state"].as_str().unwrap()
+    );
+
+    // The state is valid, but no provider is configured to exchange the code.
+    let first = auth_app(state.clone())
+        .oneshot(get_with_ip(&uri))
+        .await
+        .unwrap();
+    assert_eq!(first.status(), StatusCode::NOT_IMPLEMENTED);
+
+    let second = auth_app(state).oneshot(get_with_ip(&uri)).await.unwrap();
+    assert_eq!(second.status(), StatusCode::UNAUTHORIZED);
+}
+
+// ── Email verification ──
+
+async fn register_for_verification(
+    state: &Arc<AppState>,
+    mailer: &RecordingMailer,
+    username: &str,
+) -> (String, String) {
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            &format!(
+                r#"{{"username":"{username}","email":"{username}@example.com","password":"password123"}}"#
+            ),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["user"]["email_verified"], false);
+    (
+        body["access_token"].as_str().unwrap().to_string(),
+        mailer.token_sent_to(&format!("{username}@example.com")),
+    )
+}
+
+#[tokio::test]
+async fn verify_email_marks_user_verified() {
+    let (state, mailer) = test_state_with_mailer(test_config()).await;
+    let (_, verification_token) = register_for_verification(&state, &mailer, "verifyme").await;
+
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/verify-email",
+            &format!(r#"{{"token":"{verification_token}"}}"#),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let user = db::users::get_user_by_username(&state.db, "verifyme")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(user.email_verified);
+}
+
+#[tokio::test]
+async fn verify_email_token_is_single_use() {
+    let (state, mailer) = test_state_with_mailer(test_config()).await;
+    let (_, verification_token) = register_for_verification(&state, &mailer, "onceonly").await;
+    let body = format!(r#"{{"token":"{verification_token}"}}"#);
+
+    let first = auth_app(state.clone())
+        .oneshot(post_json("/api/auth/verify-email", &body))
+        .await
+        .unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+
+    let second = auth_app(state)
+        .oneshot(post_json("/api/auth/verify-email", &body))
+        .await
+        .unwrap();
+    assert_eq!(second.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn verify_email_rejects_unknown_token() {
+    let state = test_state().await;
+    let resp = auth_app(state)
+        .oneshot(post_json("/api/auth/verify-email", r#"{"token":"bogus"}"#))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn unverified_user_is_rejected_when_verification_required() {
+    let mut config = test_config();
+    config.require_email_verification = true;
+    let (state, mailer) = test_state_with_mailer(config).await;
+    let (access_token, verification_token) =
+        register_for_verification(&state, &mailer, "gated").await;
+
+    let conversations = || {
+        Router::new()
+            .nest("/api/conversations", api::conversations::router())
+            .with_state(state.clone())
+    };
+
+    let resp = conversations()
+        .oneshot(get_with_auth("/api/conversations", &access_token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let body = json_body(resp).await;
+    assert_eq!(body["code"], "email_not_verified");
+
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/verify-email",
+            &format!(r#"{{"token":"{verification_token}"}}"#),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = conversations()
+        .oneshot(get_with_auth("/api/conversations", &access_token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unverified_user_is_allowed_when_verification_not_required() {
+    let (state, mailer) = test_state_with_mailer(test_config()).await;
+    let (access_token, _) = register_for_verification(&state, &mailer, "ungated").await;
+
+    let resp = Router::new()
+        .nest("/api/conversations", api::conversations::router())
+        .with_state(state)
+        .oneshot(get_with_auth("/api/conversations", &access_token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn login_rejects_deactivated_user() {
+    let state = test_state().await;
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"inactive","email":"inactive@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    let user_id = body["user"]["id"].as_str().unwrap().to_string();
+    let refresh_token = body["refresh_token"].as_str().unwrap().to_string();
+
+    db::users::set_user_active(&state.db, &user_id, false)
+        .await
+        .unwrap();
+
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/login",
+            r#"{"username":"inactive","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = auth_app(state)
+        .oneshot(post_json(
+            "/api/auth/refresh",
+            &format!(r#"{{"refresh_token":"{refresh_token}"}}"#),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+// ── Password reset ──
+
+#[tokio::test]
+async fn password_reset_full_flow() {
+    let (state, mailer) = test_state_with_mailer(test_config()).await;
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"forgetful","email":"forgetful@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    let old_refresh = json_body(resp).await["refresh_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/password-reset/request",
+            r#"{"email":"forgetful@example.com"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let reset_token = mailer.token_sent_to("forgetful@example.com");
+    assert_eq!(reset_token.len(), 64);
+
+    let confirm = format!(r#"{{"token":"{reset_token}","new_password":"brand-new-pass"}}"#);
+    let resp = auth_app(state.clone())
+        .oneshot(post_json("/api/auth/password-reset/confirm", &confirm))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // The token cannot be used twice.
+    let resp = auth_app(state.clone())
+        .oneshot(post_json("/api/auth/password-reset/confirm", &confirm))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // Sessions from before the reset are revoked.
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/refresh",
+            &format!(r#"{{"refresh_token":"{old_refresh}"}}"#),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/login",
+            r#"{"username":"forgetful","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = auth_app(state)
+        .oneshot(post_json(
+            "/api/auth/login",
+            r#"{"username":"forgetful","password":"brand-new-pass"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn password_reset_request_for_unknown_email_looks_the_same() {
+    let (state, mailer) = test_state_with_mailer(test_config()).await;
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/password-reset/request",
+            r#"{"email":"nobody@example.com"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert!(mailer.sent.lock().unwrap().is_empty());
+    assert_eq!(
+        body["message"],
+        "If that email is registered, a reset link has been sent"
+    );
+
+    let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM password_reset_tokens")
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn password_reset_confirm_rejects_bad_input() {
+    let state = test_state().await;
+
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/password-reset/confirm",
+            r#"{"token":"bogus","new_password":"long-enough-pass"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = auth_app(state)
+        .oneshot(post_json(
+            "/api/auth/password-reset/confirm",
+            r#"{"token":"bogus","new_password":"short"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn password_reset_confirm_rejects_expired_token() {
+    let state = test_state().await;
+    let user = db::users::create_user(&state.db, "expired", "expired@example.com", "hash")
+        .await
+        .unwrap();
+    let token_hash = {
+        use sha2::{Digest, Sha256};
+        hex::encode(Sha256::digest(b"expired-token"))
+    };
+    db::password_reset::create_reset_token(
+        &state.db,
+        &user.id,
+        &token_hash,
+        "2000-01-01T00:00:00+00:00",
+    )
+    .await
+    .unwrap();
+
+    let resp = auth_app(state)
+        .oneshot(post_json(
+            "/api/auth/password-reset/confirm",
+            r#"{"token":"expired-token","new_password":"long-enough-pass"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn register_returns_503_when_registration_disabled() {
+    let mut state = test_state().await;
+    Arc::get_mut(&mut state)
+        .unwrap()
+        .features
+        .enable_registration = false;
+
+    let resp = auth_app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"alice","email":"alice@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(
+        db::users::get_user_by_username(&state.db, "alice")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
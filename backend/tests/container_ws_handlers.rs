@@ -0,0 +1,266 @@
+use axum::{Router, routing::get};
+use claude_chat_backend::{
+    auth::{self, JwtKeys, middleware::AppState},
+    config::Config,
+    crypto, db,
+    docker::{manager::DockerManager, registry::ContainerRegistry},
+    provider_api::HttpProviderApiClient,
+    ws::{WsState, client::ws_handler, container::container_ws_handler, sse::SseState},
+};
+use futures_util::{SinkExt, StreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+fn test_config() -> Config {
+    Config {
+        database_url: "sqlite::memory:".into(),
+        jwt_secret: "test-jwt-secret-that-is-long-enough-for-hmac".into(),
+        jwt_algorithm: Default::default(),
+        jwt_private_key_path: None,
+        jwt_public_key_path: None,
+        encryption_key: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".into(),
+        host: "127.0.0.1".into(),
+        port: 0,
+        container_image: "test:latest".into(),
+        container_idle_timeout_secs: 600,
+        container_cpu_quota: None,
+        container_memory_bytes: None,
+        container_start_max_retries: 0,
+        container_pool_size: 0,
+        internal_ws_port: 0,
+        docker_network: None,
+        host_data_dir: None,
+        fileserver_url: None,
+        cors_allowed_origins: None,
+        access_token_ttl_secs: 7200,
+        container_token_ttl_secs: 3600,
+        refresh_token_ttl_days: 30,
+        cookie_secure: false,
+        tool_call_timeout_secs: 300,
+        max_file_size_bytes: 50 * 1024 * 1024,
+        workspace_max_bytes: 1024 * 1024 * 1024,
+        allowed_upload_mime_types: None,
+        token_usage_update_interval_tokens: 500,
+        container_exec_allowlist: vec!["df".into(), "du".into(), "ls".into(), "ps".into()],
+        max_login_attempts: 10,
+        login_lockout_secs: 900,
+        trusted_proxies: vec![],
+        require_email_verification: false,
+        smtp_url: None,
+        mail_from: None,
+        oauth_client_id: None,
+        oauth_client_secret: None,
+        oauth_auth_url: None,
+        oauth_token_url: None,
+        max_conversations_per_user: None,
+        max_messages_per_conversation: None,
+    }
+}
+
+async fn test_state() -> Arc<AppState> {
+    let config = test_config();
+    let pool = db::init_db("sqlite::memory:").await;
+    let ws_state = WsState::new();
+    let registry = ContainerRegistry::new();
+    let docker_manager = Arc::new(DockerManager::new(config.clone(), registry));
+    Arc::new(AppState {
+        db: pool,
+        jwt_keys: JwtKeys::from_config(&config).unwrap(),
+        config,
+        ws_state,
+        sse_state: SseState::new(),
+        pending_uploads: Default::default(),
+        docker_manager,
+        provider_api: Arc::new(HttpProviderApiClient::default()),
+        mailer: None,
+        system_stats_cache: Default::default(),
+        features: Default::default(),
+    })
+}
+
+/// Serve the internal container and client WebSockets on a random local port.
+async fn serve(state: Arc<AppState>) -> std::net::SocketAddr {
+    let app = Router::new()
+        .route("/internal/ws", get(container_ws_handler))
+        .route("/ws", get(ws_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+/// A user with a chat provider whose stored key can't be decrypted, and a
+/// conversation using it. Returns `(user_id, failing_provider_id, conversation_id)`.
+async fn conversation_with_failing_provider(state: &Arc<AppState>) -> (String, String, String) {
+    let user = db::users::create_user(&state.db, "u", "u@example.com", "hash")
+        .await
+        .unwrap();
+    let failing = db::providers::upsert_provider(
+        &state.db,
+        None,
+        &user.id,
+        "openai",
+        "not-a-valid-ciphertext",
+        None,
+        Some("gpt-4o"),
+        true,
+        Some(r#"["gpt-4o"]"#),
+        Some("Broken"),
+        None,
+    )
+    .await
+    .unwrap();
+    let conv = db::conversations::create_conversation(
+        &state.db,
+        &user.id,
+        "t",
+        None,
+        Some(&failing.id),
+        Some("gpt-4o"),
+        false,
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    (user.id, failing.id, conv.id)
+}
+
+/// Connect as the conversation's container and report ready. Returns the
+/// first message the backend sends, or `None` if it closes the socket.
+async fn connect_and_ready(
+    state: &Arc<AppState>,
+    conversation_id: &str,
+    user_id: &str,
+) -> Option<serde_json::Value> {
+    let addr = serve(state.clone()).await;
+    let token = auth::create_container_token(
+        conversation_id,
+        user_id,
+        &state.config.jwt_secret,
+        state.config.container_token_ttl_secs,
+    )
+    .unwrap();
+    let (mut socket, _) =
+        tokio_tungstenite::connect_async(format!("ws://{addr}/internal/ws?token={token}"))
+            .await
+            .unwrap();
+    socket
+        .send(Message::Text(r#"{"type":"ready"}"#.into()))
+        .await
+        .unwrap();
+
+    loop {
+        let next = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("timed out waiting for the backend");
+        match next {
+            Some(Ok(Message::Text(text))) => return Some(serde_json::from_str(&text).unwrap()),
+            Some(Ok(Message::Close(_))) | None | Some(Err(_)) => return None,
+            Some(Ok(_)) => continue,
+        }
+    }
+}
+
+#[tokio::test]
+async fn ready_fails_over_to_fallback_provider_when_init_fails() {
+    let state = test_state().await;
+    let (user_id, failing_id, conv_id) = conversation_with_failing_provider(&state).await;
+    let backup_key = crypto::encrypt("sk-backup", &state.config.encryption_key).unwrap();
+    let backup = db::providers::upsert_provider(
+        &state.db,
+        None,
+        &user_id,
+        "anthropic",
+        &backup_key,
+        None,
+        Some("claude-x"),
+        false,
+        Some(r#"["claude-x"]"#),
+        Some("Backup"),
+        None,
+    )
+    .await
+    .unwrap();
+    db::providers::set_provider_fallback(&state.db, &user_id, &failing_id, Some(&backup.id))
+        .await
+        .unwrap();
+
+    let init = connect_and_ready(&state, &conv_id, &user_id)
+        .await
+        .expect("expected an init message");
+    assert_eq!(init["type"], "init");
+    assert_eq!(init["provider"], "anthropic");
+    assert_eq!(init["model"], "claude-x");
+    assert_eq!(init["api_key"], "sk-backup");
+    assert_eq!(init["subagent_api_key"], "sk-backup");
+
+    let conv = db::conversations::get_conversation(&state.db, &conv_id, &user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(conv.provider_id.as_deref(), Some(backup.id.as_str()));
+    assert_eq!(conv.model_name.as_deref(), Some("claude-x"));
+    assert_eq!(
+        conv.subagent_provider_id.as_deref(),
+        Some(backup.id.as_str())
+    );
+    assert!(conv.last_container_error.is_none());
+}
+
+#[tokio::test]
+async fn ready_without_fallback_fails_init() {
+    let state = test_state().await;
+    let (user_id, failing_id, conv_id) = conversation_with_failing_provider(&state).await;
+
+    assert!(
+        connect_and_ready(&state, &conv_id, &user_id)
+            .await
+            .is_none()
+    );
+
+    let conv = db::conversations::get_conversation(&state.db, &conv_id, &user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(conv.provider_id.as_deref(), Some(failing_id.as_str()));
+    assert!(
+        conv.last_container_error
+            .unwrap()
+            .contains("Failed to decrypt chat provider API key")
+    );
+}
+
+#[tokio::test]
+async fn client_ws_rejects_disabled_account() {
+    use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest};
+
+    let state = test_state().await;
+    let user = db::users::create_user(&state.db, "u", "u@example.com", "hash")
+        .await
+        .unwrap();
+    let token = auth::create_access_token(
+        &user.id,
+        &user.username,
+        false,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+    db::users::set_user_active(&state.db, &user.id, false)
+        .await
+        .unwrap();
+    let addr = serve(state).await;
+
+    let mut request = format!("ws://{addr}/ws").into_client_request().unwrap();
+    request
+        .headers_mut()
+        .insert("authorization", format!("Bearer {token}").parse().unwrap());
+    match tokio_tungstenite::connect_async(request).await {
+        Err(tungstenite::Error::Http(resp)) => assert_eq!(resp.status(), 403),
+        other => panic!("expected a 403 handshake rejection, got {other:?}"),
+    }
+}
@@ -3,14 +3,17 @@ use axum::{
     body::Body,
     http::{Request, StatusCode},
 };
+use claude_chat_backend::docker::client::OutputStream;
 use claude_chat_backend::{
     api,
-    auth::middleware::AppState,
+    auth::{JwtKeys, middleware::AppState},
     config::Config,
     db,
-    docker::{manager::DockerManager, registry::ContainerRegistry},
-    ws::WsState,
+    docker::{client::DockerClient, manager::DockerManager, registry::ContainerRegistry},
+    provider_api::HttpProviderApiClient,
+    ws::{WsState, sse::SseState},
 };
+use futures_util::future::BoxFuture;
 use http_body_util::BodyExt;
 use std::{path::PathBuf, sync::Arc};
 use tokio::sync::mpsc;
@@ -24,11 +27,18 @@ fn test_config() -> Config {
     Config {
         database_url: "sqlite::memory:".into(),
         jwt_secret: "test-jwt-secret-that-is-long-enough-for-hmac".into(),
+        jwt_algorithm: Default::default(),
+        jwt_private_key_path: None,
+        jwt_public_key_path: None,
         encryption_key: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".into(),
         host: "127.0.0.1".into(),
         port: 0,
         container_image: "test:latest".into(),
         container_idle_timeout_secs: 600,
+        container_cpu_quota: None,
+        container_memory_bytes: None,
+        container_start_max_retries: 0,
+        container_pool_size: 0,
         internal_ws_port: 0,
         docker_network: None,
         host_data_dir: None,
@@ -38,20 +48,50 @@ fn test_config() -> Config {
         container_token_ttl_secs: 3600,
         refresh_token_ttl_days: 30,
         cookie_secure: false,
+        tool_call_timeout_secs: 300,
+        max_file_size_bytes: 50 * 1024 * 1024,
+        workspace_max_bytes: 1024 * 1024 * 1024,
+        allowed_upload_mime_types: None,
+        token_usage_update_interval_tokens: 500,
+        container_exec_allowlist: vec!["df".into(), "du".into(), "ls".into(), "ps".into()],
+        max_login_attempts: 10,
+        login_lockout_secs: 900,
+        trusted_proxies: vec![],
+        require_email_verification: false,
+        smtp_url: None,
+        mail_from: None,
+        oauth_client_id: None,
+        oauth_client_secret: None,
+        oauth_auth_url: None,
+        oauth_token_url: None,
+        max_conversations_per_user: None,
+        max_messages_per_conversation: None,
     }
 }
 
 async fn test_state() -> Arc<AppState> {
     let config = test_config();
-    let pool = db::init_db("sqlite::memory:").await;
-    let ws_state = WsState::new();
     let registry = ContainerRegistry::new();
     let docker_manager = Arc::new(DockerManager::new(config.clone(), registry));
+    test_state_with_manager(docker_manager).await
+}
+
+async fn test_state_with_manager(docker_manager: Arc<DockerManager>) -> Arc<AppState> {
+    let config = test_config();
+    let pool = db::init_db("sqlite::memory:").await;
+    let ws_state = WsState::new();
     Arc::new(AppState {
         db: pool,
+        jwt_keys: JwtKeys::from_config(&config).unwrap(),
         config,
         ws_state,
+        sse_state: SseState::new(),
+        pending_uploads: Default::default(),
         docker_manager,
+        provider_api: Arc::new(HttpProviderApiClient::default()),
+        mailer: None,
+        system_stats_cache: Default::default(),
+        features: Default::default(),
     })
 }
 
@@ -59,6 +99,7 @@ fn app(state: Arc<AppState>) -> Router {
     Router::new()
         .nest("/api/auth", api::auth::router())
         .nest("/api/conversations", api::conversations::router())
+        .nest("/api/users", api::users::router())
         .with_state(state)
 }
 
@@ -77,6 +118,16 @@ fn post_json(uri: &str, body: &str) -> Request<Body> {
         .unwrap()
 }
 
+fn patch_json(uri: &str, body: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("PATCH")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
 fn put_json(uri: &str, body: &str, token: &str) -> Request<Body> {
     Request::builder()
         .method("PUT")
@@ -123,7 +174,7 @@ async fn register_user(state: &Arc<AppState>) -> String {
 }
 
 fn token_user_id(state: &Arc<AppState>, token: &str) -> String {
-    claude_chat_backend::auth::verify_access_token(token, &state.config.jwt_secret)
+    claude_chat_backend::auth::verify_access_token(token, &state.jwt_keys)
         .unwrap()
         .sub
 }
@@ -874,6 +925,62 @@ async fn delete_conversation_removes_workspace_and_record() {
     assert_eq!(get_resp.status(), StatusCode::NOT_FOUND);
 }
 
+#[tokio::test]
+async fn bulk_delete_removes_owned_and_reports_foreign_ids() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let mine = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let workspace_dir = workspace_dir_for(&mine);
+    tokio::fs::create_dir_all(&workspace_dir).await.unwrap();
+
+    let other = db::users::create_user(&state.db, "other", "other@example.com", "hash")
+        .await
+        .unwrap();
+    let theirs = db::conversations::create_conversation(
+        &state.db, &other.id, "Theirs", None, None, None, false, None, None, None,
+    )
+    .await
+    .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/api/conversations")
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::from(
+                    serde_json::json!({ "ids": [mine, theirs.id] }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(resp).await,
+        serde_json::json!({
+            "deleted": 1,
+            "failed": [{"id": theirs.id, "reason": "not found"}],
+        })
+    );
+    assert!(!workspace_dir.exists());
+
+    let user_id = token_user_id(&state, &token);
+    assert!(
+        db::conversations::get_conversation(&state.db, &mine, &user_id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        db::conversations::get_conversation(&state.db, &theirs.id, &other.id)
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
+
 #[tokio::test]
 async fn delete_conversation_keeps_record_when_workspace_delete_fails() {
     let state = test_state().await;
@@ -1125,3 +1232,2832 @@ async fn list_conversations_uses_stable_tiebreakers_when_updated_at_matches() {
     assert_eq!(convs[0]["id"], conv_new);
     assert_eq!(convs[1]["id"], conv_old);
 }
+
+#[tokio::test]
+async fn list_conversations_includes_last_message_preview() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    db::messages::create_message(&state.db, &conv_id, "user", "first", None, None, None)
+        .await
+        .unwrap();
+    db::messages::create_message(&state.db, &conv_id, "assistant", "latest", None, None, None)
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/conversations", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let body = json_body(resp).await;
+    let conv = &body.as_array().unwrap()[0];
+    assert_eq!(conv["id"], conv_id);
+    assert_eq!(conv["last_message_preview"], "latest");
+    assert!(conv["last_message_at"].is_string());
+    assert_eq!(conv["message_count"], 2);
+}
+
+#[tokio::test]
+async fn list_conversations_search_matches_title_and_message_content() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let by_title = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let by_content = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let resp = app(state.clone())
+        .oneshot(put_json(
+            &format!("/api/conversations/{by_title}"),
+            r#"{"title":"Rust lifetimes"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    db::messages::create_message(
+        &state.db,
+        &by_content,
+        "user",
+        "How do I roll back a Kubernetes deployment?",
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    let search = |q: String| {
+        let state = state.clone();
+        let token = token.clone();
+        async move {
+            let resp = app(state)
+                .oneshot(get_with_auth(&format!("/api/conversations?q={q}"), &token))
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            json_body(resp)
+                .await
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|c| c["id"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(search("rust".into()).await, vec![by_title.clone()]);
+    assert_eq!(search("KUBERNETES".into()).await, vec![by_content.clone()]);
+    assert!(search("terraform".into()).await.is_empty());
+    // LIKE wildcards in the query are matched literally.
+    assert!(search("%25".into()).await.is_empty());
+    assert_eq!(search("".into()).await.len(), 2);
+
+    let resp = app(state)
+        .oneshot(get_with_auth(
+            &format!("/api/conversations?q={}", "a".repeat(257)),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn conversation_tags_crud_and_list_filter() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let work = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let personal = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let tags_uri = format!("/api/conversations/{work}/tags");
+
+    for tag in ["work", " research ", "work"] {
+        let resp = app(state.clone())
+            .oneshot(post_json_with_auth(
+                &tags_uri,
+                &serde_json::json!({ "tag": tag }).to_string(),
+                &token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{personal}/tags"),
+            r#"{"tag":"personal"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        json_body(resp).await,
+        serde_json::json!({ "tags": ["personal"] })
+    );
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(&tags_uri, r#"{"tag":"  "}"#, &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&format!("/api/conversations/{work}"), &token))
+        .await
+        .unwrap();
+    assert_eq!(
+        json_body(resp).await["tags"],
+        serde_json::json!(["research", "work"])
+    );
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/conversations?tag=work", &token))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    let listed = body.as_array().unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["id"], work);
+    assert_eq!(listed[0]["tags"], serde_json::json!(["research", "work"]));
+
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth(&format!("{tags_uri}/work"), &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth(&format!("{tags_uri}/work"), &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/conversations?tag=work", &token))
+        .await
+        .unwrap();
+    assert!(json_body(resp).await.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn message_count_tracks_new_messages_in_list_and_detail() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let counts = || {
+        let state = state.clone();
+        let token = token.clone();
+        let conv_id = conv_id.clone();
+        async move {
+            let resp = app(state.clone())
+                .oneshot(get_with_auth("/api/conversations", &token))
+                .await
+                .unwrap();
+            let listed = json_body(resp).await[0]["message_count"].clone();
+            let resp = app(state)
+                .oneshot(get_with_auth(
+                    &format!("/api/conversations/{conv_id}"),
+                    &token,
+                ))
+                .await
+                .unwrap();
+            let detail = json_body(resp).await["message_count"].clone();
+            (listed, detail)
+        }
+    };
+    assert_eq!(counts().await, (0.into(), 0.into()));
+
+    for content in ["one", "two", "three"] {
+        db::messages::create_message(&state.db, &conv_id, "user", content, None, None, None)
+            .await
+            .unwrap();
+    }
+    assert_eq!(counts().await, (3.into(), 3.into()));
+}
+
+#[tokio::test]
+async fn message_reactions_add_dedupe_and_remove() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let msg =
+        db::messages::create_message(&state.db, &conv_id, "assistant", "hi", None, None, None)
+            .await
+            .unwrap();
+    let reactions_uri = format!("/api/conversations/{conv_id}/messages/{}/reactions", msg.id);
+
+    for emoji in ["👍", "👍", "😂"] {
+        let resp = app(state.clone())
+            .oneshot(post_json_with_auth(
+                &reactions_uri,
+                &serde_json::json!({ "emoji": emoji }).to_string(),
+                &token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &reactions_uri,
+            r#"{"emoji":""}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let messages = || {
+        let state = state.clone();
+        let token = token.clone();
+        let conv_id = conv_id.clone();
+        async move {
+            let resp = app(state)
+                .oneshot(get_with_auth(
+                    &format!("/api/conversations/{conv_id}/messages"),
+                    &token,
+                ))
+                .await
+                .unwrap();
+            json_body(resp).await["messages"][0]["reactions"].clone()
+        }
+    };
+    assert_eq!(messages().await, serde_json::json!({ "👍": 1, "😂": 1 }));
+
+    // Emoji in the path are percent-encoded.
+    let encoded: String = "😂".bytes().map(|b| format!("%{b:02X}")).collect();
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth(
+            &format!("{reactions_uri}/{encoded}"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth(
+            &format!("{reactions_uri}/{encoded}"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(messages().await, serde_json::json!({ "👍": 1 }));
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{conv_id}/messages/missing/reactions"),
+            r#"{"emoji":"👍"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn bookmarks_crud_and_listing() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let mut ids = Vec::new();
+    for content in ["keep this", "and this"] {
+        let msg = db::messages::create_message(
+            &state.db,
+            &conv_id,
+            "assistant",
+            content,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        ids.push(msg.id);
+    }
+    let bookmark_uri =
+        |msg_id: &str| format!("/api/conversations/{conv_id}/messages/{msg_id}/bookmark");
+
+    for msg_id in [&ids[0], &ids[1], &ids[1]] {
+        let resp = app(state.clone())
+            .oneshot(post_json_with_auth(&bookmark_uri(msg_id), "", &token))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/users/me/bookmarks", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    let listed = body.as_array().unwrap();
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0]["message_id"], ids[1]);
+    assert_eq!(listed[0]["content"], "and this");
+    assert_eq!(listed[0]["conversation_id"], conv_id);
+    assert_eq!(listed[0]["conversation_title"], "New Conversation");
+    assert_eq!(listed[1]["message_id"], ids[0]);
+
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth(&bookmark_uri(&ids[1]), &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth(&bookmark_uri(&ids[1]), &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/users/me/bookmarks?limit=10", &token))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["message_id"], ids[0]);
+
+    let resp = app(state)
+        .oneshot(post_json_with_auth(&bookmark_uri("missing"), "", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+fn post_with_auth(uri: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn update_container_key_sends_update_config_to_container() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let (tx, mut rx) = mpsc::channel(claude_chat_backend::ws::WS_CHANNEL_CAPACITY);
+    state.ws_state.add_container(&conv_id, tx).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            &format!("/api/conversations/{}/container/update-key", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let msg: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert_eq!(msg["type"], "update_config");
+    assert_eq!(msg["api_key"], "seed-key");
+    assert_eq!(msg["subagent_api_key"], "seed-key");
+    assert!(msg.get("image_api_key").is_none());
+}
+
+#[tokio::test]
+async fn update_container_key_without_running_container_returns_conflict() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            &format!("/api/conversations/{}/container/update-key", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn update_container_key_for_other_users_conversation_returns_404() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let other = db::users::create_user(&state.db, "intruder", "intruder@example.com", "hash")
+        .await
+        .unwrap();
+    let other_token = claude_chat_backend::auth::create_access_token(
+        &other.id,
+        &other.username,
+        false,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            &format!("/api/conversations/{}/container/update-key", conv_id),
+            &other_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn list_messages_around_returns_context_window() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let mut ids = Vec::new();
+    for i in 0..8 {
+        let msg = db::messages::create_message(
+            &state.db,
+            &conv_id,
+            "user",
+            &format!("m{i}"),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        ids.push(msg.id);
+    }
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!(
+                "/api/conversations/{}/messages/around?anchor_id={}&before=1&after=2",
+                conv_id, ids[4]
+            ),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    let contents: Vec<&str> = body["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(contents, vec!["m3", "m4", "m5", "m6"]);
+    assert_eq!(body["total"], 8);
+}
+
+#[tokio::test]
+async fn list_messages_around_unknown_anchor_returns_404() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!(
+                "/api/conversations/{}/messages/around?anchor_id=missing",
+                conv_id
+            ),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn container_status_reports_disconnected_without_container() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{}/container-status", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["status"], "disconnected");
+    assert_eq!(body["pending_message"], false);
+}
+
+#[tokio::test]
+async fn container_status_reports_connected_container() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let (tx, _rx) = mpsc::channel(claude_chat_backend::ws::WS_CHANNEL_CAPACITY);
+    state.ws_state.add_container(&conv_id, tx).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{}/container-status", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["status"], "connected");
+    assert_eq!(body["pending_message"], false);
+}
+
+#[tokio::test]
+async fn container_status_reports_pending_message() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    state
+        .ws_state
+        .set_pending_message(&conv_id, r#"{"type":"user_message"}"#.into())
+        .await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{}/container-status", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["status"], "disconnected");
+    assert_eq!(body["pending_message"], true);
+}
+
+#[tokio::test]
+async fn stream_message_relays_container_frames_until_done() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let (tx, mut container_rx) = mpsc::channel(8);
+    state.ws_state.add_container(&conv_id, tx).await;
+
+    let message_id = uuid::Uuid::new_v4().to_string();
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/messages/stream", conv_id),
+            &serde_json::json!({"content": "Hello", "message_id": message_id}).to_string(),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "text/event-stream");
+
+    let sent: serde_json::Value =
+        serde_json::from_str(&container_rx.recv().await.unwrap()).unwrap();
+    assert_eq!(sent["type"], "user_message");
+    assert_eq!(sent["message_id"], message_id.as_str());
+    assert_eq!(sent["content"], "Hello");
+    let saved = db::messages::get_message(&state.db, &message_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(saved.role, "user");
+
+    let delta = r#"{"type":"assistant_delta","delta":"Hi"}"#;
+    let complete = r#"{"type":"complete","content":"Hi"}"#;
+    assert_eq!(state.sse_state.publish(&conv_id, delta).await, 1);
+    assert_eq!(state.sse_state.publish(&conv_id, complete).await, 1);
+
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    let data: Vec<&str> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .collect();
+    assert_eq!(data, vec![delta, complete, "[DONE]"]);
+}
+
+#[tokio::test]
+async fn stream_message_queues_message_and_stops_on_error() {
+    let registry = ContainerRegistry::new();
+    let docker_manager = Arc::new(DockerManager::new(test_config(), registry.clone()));
+    let state = test_state_with_manager(docker_manager).await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    // Already starting: the handler must not create a second container.
+    registry
+        .register(&conv_id, "container-1", &token_user_id(&state, &token))
+        .await;
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/messages/stream", conv_id),
+            r#"{"content":"Hello"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(state.ws_state.has_pending_message(&conv_id).await);
+
+    let error = r#"{"type":"error","message":"boom"}"#;
+    state.sse_state.publish(&conv_id, error).await;
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains(&format!("data: {error}")));
+    assert!(!body.contains("[DONE]"));
+}
+
+#[tokio::test]
+async fn stream_message_ends_when_conversation_container_stops() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let (tx, _container_rx) = mpsc::channel(8);
+    state.ws_state.add_container(&conv_id, tx).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/messages/stream", conv_id),
+            r#"{"content":"Hello"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let deleted = app(state.clone())
+        .oneshot(delete_with_auth(
+            &format!("/api/conversations/{}", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+
+    let body = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        resp.into_body().collect(),
+    )
+    .await
+    .expect("stream should end once the container stops")
+    .unwrap()
+    .to_bytes();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains(r#""status":"stopped""#), "{body}");
+    assert!(!body.contains("[DONE]"));
+}
+
+#[tokio::test]
+async fn stream_message_rejects_invalid_requests() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let (tx, _container_rx) = mpsc::channel(8);
+    state.ws_state.add_container(&conv_id, tx).await;
+    let uri = format!("/api/conversations/{}/messages/stream", conv_id);
+
+    for body in [
+        r#"{"content":""}"#,
+        r#"{"content":"Hi","message_id":"not-a-uuid"}"#,
+    ] {
+        let resp = app(state.clone())
+            .oneshot(post_json_with_auth(&uri, body, &token))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{body}");
+    }
+
+    let existing =
+        db::messages::create_message(&state.db, &conv_id, "user", "Hi", None, None, None)
+            .await
+            .unwrap();
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &uri,
+            &serde_json::json!({"content": "Again", "message_id": existing.id}).to_string(),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            "/api/conversations/missing/messages/stream",
+            r#"{"content":"Hi"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn container_status_for_other_users_conversation_returns_404() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let other = db::users::create_user(&state.db, "outsider", "outsider@example.com", "hash")
+        .await
+        .unwrap();
+    let other_token = claude_chat_backend::auth::create_access_token(
+        &other.id,
+        &other.username,
+        false,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{}/container-status", conv_id),
+            &other_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn last_message_without_messages_returns_204() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{}/messages/last", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn last_message_returns_single_message_with_parts() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let legacy = db::messages::create_message(
+        &state.db,
+        &conv_id,
+        "assistant",
+        "legacy content",
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    db::messages_v2::create_message_with_parts(
+        &state.db,
+        Some(legacy.id.as_str()),
+        &conv_id,
+        "assistant",
+        None,
+        None,
+        None,
+        None,
+        &[db::messages_v2::NewMessagePart {
+            part_type: "text",
+            text: Some("structured answer"),
+            json_payload: None,
+            tool_call_id: None,
+        }],
+    )
+    .await
+    .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{}/messages/last", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["id"], legacy.id);
+    let parts = body["parts"].as_array().unwrap();
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0]["text"], "structured answer");
+}
+
+#[tokio::test]
+async fn last_message_tracks_most_recent_insert() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let uri = format!("/api/conversations/{}/messages/last", conv_id);
+
+    for (role, content) in [("user", "question"), ("assistant", "answer")] {
+        db::messages::create_message(&state.db, &conv_id, role, content, None, None, None)
+            .await
+            .unwrap();
+    }
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &token))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body["role"], "assistant");
+    assert_eq!(body["content"], "answer");
+
+    db::messages::create_message(&state.db, &conv_id, "user", "follow-up", None, None, None)
+        .await
+        .unwrap();
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &token))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body["content"], "follow-up");
+}
+
+fn post_json_with_auth(uri: &str, body: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn import_messages_inserts_batch_in_order() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let body = serde_json::json!([
+        {"role": "system", "content": "be brief"},
+        {"role": "user", "content": "hi"},
+        {"role": "assistant", "content": "hello", "token_count": 3},
+        {"role": "user", "content": "weather?"},
+        {"role": "assistant", "content": "sunny"},
+    ]);
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/messages/import", conv_id),
+            &body.to_string(),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body = json_body(resp).await;
+    assert_eq!(body["total"], 5);
+    let msgs = body["messages"].as_array().unwrap();
+    let contents: Vec<_> = msgs
+        .iter()
+        .map(|m| m["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        contents,
+        vec!["be brief", "hi", "hello", "weather?", "sunny"]
+    );
+    let ids: std::collections::HashSet<_> =
+        msgs.iter().map(|m| m["id"].as_str().unwrap()).collect();
+    assert_eq!(ids.len(), 5);
+    assert_eq!(msgs[2]["token_count"], 3);
+
+    let stored = db::messages::list_messages(&state.db, &conv_id, 10, 0)
+        .await
+        .unwrap();
+    let stored_ids: Vec<_> = stored.iter().map(|m| m.id.as_str()).collect();
+    let returned_ids: Vec<_> = msgs.iter().map(|m| m["id"].as_str().unwrap()).collect();
+    assert_eq!(stored_ids, returned_ids);
+}
+
+#[tokio::test]
+async fn import_messages_rejects_invalid_role() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/messages/import", conv_id),
+            r#"[{"role":"user","content":"ok"},{"role":"robot","content":"beep"}]"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        db::messages::count_messages(&state.db, &conv_id)
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn import_messages_rejects_oversized_batch() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let batch: Vec<_> = (0..=db::messages::MAX_MESSAGE_BATCH)
+        .map(|i| serde_json::json!({"role": "user", "content": format!("m{i}")}))
+        .collect();
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/messages/import", conv_id),
+            &serde_json::Value::Array(batch).to_string(),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+fn create_conv_request(token: &str) -> Request<Body> {
+    post_json_with_auth(
+        "/api/conversations",
+        r#"{"provider_id":"openai","model_name":"gpt-4o","subagent_provider_id":"openai","subagent_model":"gpt-4o"}"#,
+        token,
+    )
+}
+
+#[tokio::test]
+async fn create_conversation_enforces_user_quota() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let user_id = token_user_id(&state, &token);
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    db::user_quotas::set_user_quota(&state.db, &user_id, Some(2), None, None)
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(create_conv_request(&token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let resp = app(state.clone())
+        .oneshot(create_conv_request(&token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    let body = json_body(resp).await;
+    assert!(
+        body["message"]
+            .as_str()
+            .unwrap()
+            .contains("limit of 2 conversations")
+    );
+
+    // Copies count against the same quota.
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{conv_id}/duplicate"),
+            "{}",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(
+        db::conversations::count_user_conversations(&state.db, &user_id)
+            .await
+            .unwrap(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn create_conversation_falls_back_to_configured_quota() {
+    let mut config = test_config();
+    config.max_conversations_per_user = Some(1);
+    let docker_manager = Arc::new(DockerManager::new(config.clone(), ContainerRegistry::new()));
+    let state = Arc::new(AppState {
+        db: db::init_db("sqlite::memory:").await,
+        jwt_keys: JwtKeys::from_config(&config).unwrap(),
+        config,
+        ws_state: WsState::new(),
+        sse_state: SseState::new(),
+        pending_uploads: Default::default(),
+        docker_manager,
+        provider_api: Arc::new(HttpProviderApiClient::default()),
+        mailer: None,
+        system_stats_cache: Default::default(),
+        features: Default::default(),
+    });
+    let token = register_user(&state).await;
+    create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let resp = app(state.clone())
+        .oneshot(create_conv_request(&token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // A per-user quota overrides the configured default.
+    let user_id = token_user_id(&state, &token);
+    db::user_quotas::set_user_quota(&state.db, &user_id, Some(5), None, None)
+        .await
+        .unwrap();
+    let resp = app(state.clone())
+        .oneshot(create_conv_request(&token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn import_messages_enforces_message_quota() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let user_id = token_user_id(&state, &token);
+    db::user_quotas::set_user_quota(&state.db, &user_id, None, Some(2), None)
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/messages/import", conv_id),
+            r#"[{"role":"user","content":"a"},{"role":"assistant","content":"b"},{"role":"user","content":"c"}]"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(
+        db::messages::count_messages(&state.db, &conv_id)
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn stream_message_reserves_room_for_the_reply() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let user_id = token_user_id(&state, &token);
+    db::user_quotas::set_user_quota(&state.db, &user_id, None, Some(3), None)
+        .await
+        .unwrap();
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/messages/import", conv_id),
+            r#"[{"role":"user","content":"a"},{"role":"assistant","content":"b"}]"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    // One slot is left, but the message and its reply need two.
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/messages/stream", conv_id),
+            r#"{"content":"c"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(
+        db::messages::count_messages(&state.db, &conv_id)
+            .await
+            .unwrap(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn create_conversation_rejects_subagent_fields_when_deep_thinking_disabled() {
+    let mut state = test_state().await;
+    Arc::get_mut(&mut state)
+        .unwrap()
+        .features
+        .enable_deep_thinking = false;
+    let token = register_user(&state).await;
+    seed_standard_providers(&state, &token).await;
+
+    let resp = app(state.clone())
+        .oneshot(create_conv_request(&token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = json_body(resp).await;
+    assert!(
+        body["message"]
+            .as_str()
+            .unwrap()
+            .contains("Deep thinking is disabled")
+    );
+
+    // Without subagent fields the subagent mirrors the main model.
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            "/api/conversations",
+            r#"{"provider_id":"openai","model_name":"gpt-4o"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let conv = json_body(resp).await;
+    assert_eq!(conv["subagent_provider_id"], "openai");
+    assert_eq!(conv["subagent_model"], "gpt-4o");
+    assert_eq!(conv["deep_thinking"], false);
+
+    let resp = app(state.clone())
+        .oneshot(put_json(
+            &format!("/api/conversations/{}", conv["id"].as_str().unwrap()),
+            r#"{"deep_thinking":true}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn update_keeps_stored_subagent_when_deep_thinking_disabled() {
+    let mut state = test_state().await;
+    let token = register_user(&state).await;
+    seed_standard_providers(&state, &token).await;
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            "/api/conversations",
+            r#"{"provider_id":"openai","model_name":"gpt-4o","subagent_provider_id":"anthropic","subagent_model":"claude-3"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let conv_id = json_body(resp).await["id"].as_str().unwrap().to_string();
+
+    Arc::get_mut(&mut state)
+        .unwrap()
+        .features
+        .enable_deep_thinking = false;
+    let resp = app(state.clone())
+        .oneshot(put_json(
+            &format!("/api/conversations/{conv_id}"),
+            r#"{"title":"Renamed","model_name":"gpt-4.1-mini"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let conv = json_body(resp).await;
+    assert_eq!(conv["model_name"], "gpt-4.1-mini");
+    assert_eq!(conv["subagent_provider_id"], "anthropic");
+    assert_eq!(conv["subagent_model"], "claude-3");
+}
+
+#[tokio::test]
+async fn image_provider_is_rejected_when_image_generation_disabled() {
+    let mut state = test_state().await;
+    Arc::get_mut(&mut state)
+        .unwrap()
+        .features
+        .enable_image_generation = false;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let resp = app(state.clone())
+        .oneshot(put_json(
+            &format!("/api/conversations/{conv_id}"),
+            r#"{"image_provider_id":"My Google","image_model":"gemini-img"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // Clearing the image model is still allowed.
+    let resp = app(state.clone())
+        .oneshot(put_json(
+            &format!("/api/conversations/{conv_id}"),
+            r#"{"image_provider_id":"","image_model":""}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn conversation_mcp_servers_return_503_when_mcp_disabled() {
+    let mut state = test_state().await;
+    Arc::get_mut(&mut state).unwrap().features.enable_mcp = false;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/mcp-servers"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+fn multipart_with_auth(uri: &str, file_name: &str, contents: &str, token: &str) -> Request<Body> {
+    let boundary = "test-boundary";
+    let body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n{contents}\r\n--{boundary}--\r\n"
+    );
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={boundary}"),
+        )
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn copy_file_rejects_dest_outside_workspace() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    for dest in ["/etc/passwd", "/workspace/../etc/passwd", "/workspace"] {
+        let resp = app(state.clone())
+            .oneshot(multipart_with_auth(
+                &format!(
+                    "/api/conversations/{}/container/copy-file?dest_path={}",
+                    conv_id, dest
+                ),
+                "a.txt",
+                "hello",
+                &token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "dest_path {dest}");
+    }
+}
+
+#[tokio::test]
+async fn copy_file_requires_running_container() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let resp = app(state.clone())
+        .oneshot(multipart_with_auth(
+            &format!(
+                "/api/conversations/{}/container/copy-file?dest_path=/workspace/data/a.txt",
+                conv_id
+            ),
+            "a.txt",
+            "hello",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn copy_file_rejects_non_owner() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let other = db::users::create_user(&state.db, "intruder", "intruder@example.com", "hash")
+        .await
+        .unwrap();
+    let other_token = claude_chat_backend::auth::create_access_token(
+        &other.id,
+        &other.username,
+        false,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(multipart_with_auth(
+            &format!(
+                "/api/conversations/{}/container/copy-file?dest_path=/workspace/a.txt",
+                conv_id
+            ),
+            "a.txt",
+            "hello",
+            &other_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn pinned_conversations_list_before_recently_updated_ones() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_old = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let conv_new = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    sqlx::query("UPDATE conversations SET updated_at = ? WHERE id = ?")
+        .bind("2000-01-01 00:00:00")
+        .bind(&conv_old)
+        .execute(&state.db)
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(patch_json(
+            &format!("/api/conversations/{}/pin", conv_old),
+            r#"{"pinned":true}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["pinned"], true);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/conversations", &token))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    let convs = body.as_array().unwrap();
+    assert_eq!(convs[0]["id"], conv_old);
+    assert_eq!(convs[1]["id"], conv_new);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/conversations?pinned=true", &token))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    let convs = body.as_array().unwrap();
+    assert_eq!(convs.len(), 1);
+    assert_eq!(convs[0]["id"], conv_old);
+}
+
+#[tokio::test]
+async fn pin_conversation_round_trips() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let uri = format!("/api/conversations/{}/pin", conv_id);
+
+    for pinned in [true, false] {
+        let resp = app(state.clone())
+            .oneshot(patch_json(
+                &uri,
+                &serde_json::json!({ "pinned": pinned }).to_string(),
+                &token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = app(state.clone())
+            .oneshot(get_with_auth(
+                &format!("/api/conversations/{}", conv_id),
+                &token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(json_body(resp).await["pinned"], pinned);
+    }
+}
+
+#[tokio::test]
+async fn put_pin_and_unpin_reorder_the_list() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_old = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let conv_new = create_conv(&state, &token, "openai", "gpt-4o").await;
+    sqlx::query("UPDATE conversations SET updated_at = ? WHERE id = ?")
+        .bind("2000-01-01 00:00:00")
+        .bind(&conv_old)
+        .execute(&state.db)
+        .await
+        .unwrap();
+
+    let list_ids = || {
+        let state = state.clone();
+        let token = token.clone();
+        async move {
+            let resp = app(state)
+                .oneshot(get_with_auth("/api/conversations", &token))
+                .await
+                .unwrap();
+            json_body(resp)
+                .await
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|c| c["id"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(list_ids().await, vec![conv_new.clone(), conv_old.clone()]);
+
+    let resp = app(state.clone())
+        .oneshot(put_json(
+            &format!("/api/conversations/{conv_old}/pin"),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["pinned"], true);
+    assert_eq!(list_ids().await, vec![conv_old.clone(), conv_new.clone()]);
+
+    let resp = app(state.clone())
+        .oneshot(put_json(
+            &format!("/api/conversations/{conv_old}/unpin"),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["pinned"], false);
+    assert_eq!(list_ids().await, vec![conv_new, conv_old]);
+}
+
+#[tokio::test]
+async fn pin_conversation_not_found_for_other_user() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let other = db::users::create_user(&state.db, "intruder", "intruder@example.com", "hash")
+        .await
+        .unwrap();
+    let other_token = claude_chat_backend::auth::create_access_token(
+        &other.id,
+        &other.username,
+        false,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(patch_json(
+            &format!("/api/conversations/{}/pin", conv_id),
+            r#"{"pinned":true}"#,
+            &other_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn create_conversation_rejects_image_provider_of_unsupported_type() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    seed_standard_providers(&state, &token).await;
+    let user_id = token_user_id(&state, &token);
+    seed_provider(
+        &state,
+        &user_id,
+        "mistral",
+        "mistral",
+        &["mistral-large"],
+        &["mistral-img"],
+    )
+    .await;
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            "/api/conversations",
+            r#"{"provider_id":"openai","model_name":"gpt-4o","subagent_provider_id":"openai","subagent_model":"gpt-4o","image_provider_id":"mistral","image_model":"mistral-img"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(resp).await;
+    assert!(
+        body["message"]
+            .as_str()
+            .unwrap()
+            .contains("does not support image generation")
+    );
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            "/api/conversations",
+            r#"{"provider_id":"openai","model_name":"gpt-4o","subagent_provider_id":"openai","subagent_model":"gpt-4o","image_provider_id":"My Google","image_model":"gemini-img"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn get_conversation_exposes_last_container_error() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let uri = format!("/api/conversations/{}", conv_id);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &token))
+        .await
+        .unwrap();
+    assert!(json_body(resp).await["last_container_error"].is_null());
+
+    db::conversations::set_last_container_error(&state.db, &conv_id, Some("init failed"))
+        .await
+        .unwrap();
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["last_container_error"], "init failed");
+}
+
+#[tokio::test]
+async fn summarize_conversation_prepends_system_message() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    for (role, content) in [("user", "hi"), ("assistant", "hello"), ("user", "bye")] {
+        db::messages::create_message(&state.db, &conv_id, role, content, None, None, None)
+            .await
+            .unwrap();
+    }
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/summarize?count=2", conv_id),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body = json_body(resp).await;
+    let summary = body["summary"].as_str().unwrap();
+    assert!(summary.contains("- user: hi"));
+    assert!(summary.contains("- assistant: hello"));
+    assert!(!summary.contains("bye"));
+
+    let stored = db::messages::list_messages(&state.db, &conv_id, 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 4);
+    assert_eq!(stored[0].id, body["message_id"].as_str().unwrap());
+    assert_eq!(stored[0].role, "system");
+    assert_eq!(stored[0].content, summary);
+    assert_eq!(stored[1].content, "hi");
+}
+
+#[tokio::test]
+async fn summarize_conversation_rejects_empty_conversation() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/summarize", conv_id),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn summarize_conversation_requires_ownership() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    db::messages::create_message(&state.db, &conv_id, "user", "hi", None, None, None)
+        .await
+        .unwrap();
+    let other = db::users::create_user(&state.db, "intruder", "intruder@example.com", "hash")
+        .await
+        .unwrap();
+    let other_token = claude_chat_backend::auth::create_access_token(
+        &other.id,
+        &other.username,
+        false,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{}/summarize", conv_id),
+            "",
+            &other_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        db::messages::count_messages(&state.db, &conv_id)
+            .await
+            .unwrap(),
+        1
+    );
+}
+
+async fn seed_numbered_messages(state: &Arc<AppState>, conv_id: &str, n: usize) -> Vec<String> {
+    let mut ids = Vec::with_capacity(n);
+    for i in 0..n {
+        let msg = db::messages::create_message(
+            &state.db,
+            conv_id,
+            "user",
+            &format!("m{i}"),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        ids.push(msg.id);
+    }
+    ids
+}
+
+async fn get_context(
+    state: &Arc<AppState>,
+    token: &str,
+    conv_id: &str,
+    msg_id: &str,
+    n: usize,
+) -> (StatusCode, serde_json::Value) {
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!(
+                "/api/conversations/{}/messages/{}/context?n={}",
+                conv_id, msg_id, n
+            ),
+            token,
+        ))
+        .await
+        .unwrap();
+    let status = resp.status();
+    let body = if status == StatusCode::OK {
+        json_body(resp).await
+    } else {
+        serde_json::Value::Null
+    };
+    (status, body)
+}
+
+fn contents(messages: &serde_json::Value) -> Vec<&str> {
+    messages
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["content"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn message_context_returns_neighbours_at_midpoint() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let ids = seed_numbered_messages(&state, &conv_id, 8).await;
+
+    let (status, body) = get_context(&state, &token, &conv_id, &ids[4], 2).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["anchor"]["id"], ids[4].as_str());
+    assert_eq!(body["anchor"]["content"], "m4");
+    assert_eq!(body["anchor"]["parts"][0]["text"], "m4");
+    assert_eq!(contents(&body["before"]), vec!["m2", "m3"]);
+    assert_eq!(contents(&body["after"]), vec!["m5", "m6"]);
+}
+
+#[tokio::test]
+async fn message_context_at_conversation_boundaries() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let ids = seed_numbered_messages(&state, &conv_id, 4).await;
+
+    let (status, body) = get_context(&state, &token, &conv_id, &ids[0], 2).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["anchor"]["content"], "m0");
+    assert!(contents(&body["before"]).is_empty());
+    assert_eq!(contents(&body["after"]), vec!["m1", "m2"]);
+
+    let (status, body) = get_context(&state, &token, &conv_id, &ids[3], 2).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["anchor"]["content"], "m3");
+    assert_eq!(contents(&body["before"]), vec!["m1", "m2"]);
+    assert!(contents(&body["after"]).is_empty());
+}
+
+#[tokio::test]
+async fn message_context_for_message_in_other_conversation_returns_404() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let other_conv = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let ids = seed_numbered_messages(&state, &other_conv, 1).await;
+
+    let (status, _) = get_context(&state, &token, &conv_id, &ids[0], 2).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = get_context(&state, &token, &conv_id, "missing", 2).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn search_messages_returns_paged_matches() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    for content in [
+        "How do I run Kubernetes locally?",
+        "Try kind or minikube",
+        "Kubernetes on a laptop works fine",
+    ] {
+        db::messages::create_message(&state.db, &conv_id, "user", content, None, None, None)
+            .await
+            .unwrap();
+    }
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!(
+                "/api/conversations/{}/messages/search?q=kubernetes&limit=1",
+                conv_id
+            ),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["total"], 2);
+    let messages = body["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert!(
+        messages[0]["content"]
+            .as_str()
+            .unwrap()
+            .contains("Kubernetes")
+    );
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{}/messages/search?q=%20", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn search_messages_requires_ownership() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    db::messages::create_message(&state.db, &conv_id, "user", "secret", None, None, None)
+        .await
+        .unwrap();
+    let other = db::users::create_user(&state.db, "intruder", "intruder@example.com", "hash")
+        .await
+        .unwrap();
+    let other_token = claude_chat_backend::auth::create_access_token(
+        &other.id,
+        &other.username,
+        false,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+
+    let resp = app(state)
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{}/messages/search?q=secret", conv_id),
+            &other_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn list_messages_pages_with_after_cursor() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let ids = seed_numbered_messages(&state, &conv_id, 5).await;
+
+    let page = |uri: String| {
+        let state = state.clone();
+        let token = token.clone();
+        async move {
+            let resp = app(state)
+                .oneshot(get_with_auth(&uri, &token))
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            json_body(resp).await
+        }
+    };
+    let contents = |body: &serde_json::Value| -> Vec<String> {
+        body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["content"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let first = page(format!("/api/conversations/{conv_id}/messages?limit=2")).await;
+    assert_eq!(contents(&first), vec!["m0", "m1"]);
+    assert_eq!(first["next_cursor"], ids[1].as_str());
+    assert_eq!(first["total"], 5);
+
+    let second = page(format!(
+        "/api/conversations/{conv_id}/messages?limit=2&after={}",
+        ids[1]
+    ))
+    .await;
+    assert_eq!(contents(&second), vec!["m2", "m3"]);
+    assert_eq!(second["next_cursor"], ids[3].as_str());
+
+    let last = page(format!(
+        "/api/conversations/{conv_id}/messages?limit=2&after={}",
+        ids[3]
+    ))
+    .await;
+    assert_eq!(contents(&last), vec!["m4"]);
+    assert!(last.get("next_cursor").is_none());
+
+    // Offset paging still works when `after` is absent.
+    let offset = page(format!(
+        "/api/conversations/{conv_id}/messages?limit=2&offset=3"
+    ))
+    .await;
+    assert_eq!(contents(&offset), vec!["m3", "m4"]);
+    assert!(offset.get("next_cursor").is_none());
+}
+
+#[tokio::test]
+async fn list_messages_rejects_cursor_from_other_conversation() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let other_conv = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let other_ids = seed_numbered_messages(&state, &other_conv, 1).await;
+
+    let resp = app(state)
+        .oneshot(get_with_auth(
+            &format!(
+                "/api/conversations/{conv_id}/messages?after={}",
+                other_ids[0]
+            ),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn undelete_message_restores_soft_deleted_message() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let ids = seed_numbered_messages(&state, &conv_id, 3).await;
+    db::messages::delete_messages_after(&state.db, &conv_id, &ids[0])
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/messages"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(json_body(resp).await["total"], 1);
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{conv_id}/messages/{}/undelete", ids[2]),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/messages"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    let contents: Vec<&str> = body["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(contents, vec!["m0", "m2"]);
+
+    // Messages that are not deleted cannot be undeleted.
+    let resp = app(state)
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{conv_id}/messages/{}/undelete", ids[0]),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn undelete_message_requires_ownership() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let ids = seed_numbered_messages(&state, &conv_id, 2).await;
+    db::messages::delete_messages_after(&state.db, &conv_id, &ids[0])
+        .await
+        .unwrap();
+    let other = db::users::create_user(&state.db, "intruder", "intruder@example.com", "hash")
+        .await
+        .unwrap();
+    let other_token = claude_chat_backend::auth::create_access_token(
+        &other.id,
+        &other.username,
+        false,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{conv_id}/messages/{}/undelete", ids[1]),
+            "",
+            &other_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        db::messages::count_messages(&state.db, &conv_id)
+            .await
+            .unwrap(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn archive_conversation_round_trip_and_list_filtering() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+
+    let list_ids = |uri: &'static str| {
+        let state = state.clone();
+        let token = token.clone();
+        async move {
+            let resp = app(state)
+                .oneshot(get_with_auth(uri, &token))
+                .await
+                .unwrap();
+            json_body(resp)
+                .await
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|c| c["id"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let resp = app(state.clone())
+        .oneshot(put_json(
+            &format!("/api/conversations/{conv_id}/archive"),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(json_body(resp).await["archived_at"].is_string());
+
+    assert!(list_ids("/api/conversations").await.is_empty());
+    assert_eq!(
+        list_ids("/api/conversations?include_archived=true").await,
+        vec![conv_id.clone()]
+    );
+
+    // Direct links keep working while archived.
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = app(state.clone())
+        .oneshot(put_json(
+            &format!("/api/conversations/{conv_id}/unarchive"),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(json_body(resp).await["archived_at"].is_null());
+    assert_eq!(list_ids("/api/conversations").await, vec![conv_id]);
+}
+
+#[tokio::test]
+async fn archive_conversation_not_found_for_other_user() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let other = db::users::create_user(&state.db, "intruder", "intruder@example.com", "hash")
+        .await
+        .unwrap();
+    let other_token = claude_chat_backend::auth::create_access_token(
+        &other.id,
+        &other.username,
+        false,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+
+    let resp = app(state)
+        .oneshot(put_json(
+            &format!("/api/conversations/{conv_id}/archive"),
+            "",
+            &other_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn conversation_stats_counts_messages_and_tokens() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    db::messages::create_message(&state.db, &conv_id, "user", "q", None, None, Some(4))
+        .await
+        .unwrap();
+    db::messages::create_message(&state.db, &conv_id, "assistant", "a", None, None, Some(6))
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/stats"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["message_count"], 2);
+    assert_eq!(body["user_message_count"], 1);
+    assert_eq!(body["assistant_message_count"], 1);
+    assert_eq!(body["total_tokens"], 10);
+    assert_eq!(
+        body["token_usage"],
+        serde_json::json!({
+            "total_tokens": 10,
+            "message_count_with_usage": 2,
+            "average_tokens_per_message": 5.0,
+        })
+    );
+
+    let resp = app(state)
+        .oneshot(get_with_auth("/api/conversations/missing/stats", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn conversation_cost_sums_priced_messages() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let cost_uri = format!("/api/conversations/{conv_id}/cost");
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&cost_uri, &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["total_cost_usd"], 0.0);
+    assert_eq!(body["priced_message_count"], 0);
+
+    // A message completed before pricing existed stays unpriced.
+    let early =
+        db::messages::create_message(&state.db, &conv_id, "assistant", "a", None, None, None)
+            .await
+            .unwrap();
+    db::pricing::record_message_cost(&state.db, &early.id, &conv_id, 400, 100)
+        .await
+        .unwrap();
+
+    db::pricing::upsert_pricing(&state.db, "openai", "gpt-4o", 0.0025, 0.01)
+        .await
+        .unwrap();
+    for (input, output) in [(1000, 200), (3000, 800)] {
+        let msg =
+            db::messages::create_message(&state.db, &conv_id, "assistant", "b", None, None, None)
+                .await
+                .unwrap();
+        db::pricing::record_message_cost(&state.db, &msg.id, &conv_id, input, output)
+            .await
+            .unwrap();
+    }
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&cost_uri, &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["conversation_id"], conv_id.as_str());
+    // (4000 * 0.0025 + 1000 * 0.01) / 1000
+    assert!((body["total_cost_usd"].as_f64().unwrap() - 0.02).abs() < 1e-9);
+    assert_eq!(body["input_tokens"], 4400);
+    assert_eq!(body["output_tokens"], 1100);
+    assert_eq!(body["priced_message_count"], 2);
+    assert_eq!(body["unpriced_message_count"], 1);
+
+    let resp = app(state)
+        .oneshot(get_with_auth("/api/conversations/missing/cost", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn token_usage_summarizes_and_buckets_seeded_messages() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    for (tokens, created_at) in [
+        (Some(100), "2026-03-01 10:00:00"),
+        (Some(50), "2026-03-01 11:30:00"),
+        (Some(30), "2026-03-02 09:00:00"),
+        (None, "2026-03-02 09:05:00"),
+    ] {
+        let msg =
+            db::messages::create_message(&state.db, &conv_id, "assistant", "x", None, None, tokens)
+                .await
+                .unwrap();
+        sqlx::query("UPDATE messages SET created_at = ? WHERE id = ?")
+            .bind(created_at)
+            .bind(&msg.id)
+            .execute(&state.db)
+            .await
+            .unwrap();
+    }
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/token-usage"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(resp).await,
+        serde_json::json!({
+            "total_tokens": 180,
+            "message_count_with_usage": 3,
+            "average_tokens_per_message": 60.0,
+            "bucket": "day",
+            "buckets": [
+                {"bucket": "2026-03-01", "total_tokens": 150, "message_count": 2},
+                {"bucket": "2026-03-02", "total_tokens": 30, "message_count": 1},
+            ],
+        })
+    );
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/token-usage?bucket=hour"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        json_body(resp).await["buckets"].as_array().unwrap().len(),
+        3
+    );
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/token-usage?bucket=year"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = app(state)
+        .oneshot(get_with_auth(
+            "/api/conversations/missing/token-usage",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn export_and_import_conversation_round_trip() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    db::messages::create_message(
+        &state.db,
+        &conv_id,
+        "user",
+        "What is 2+2?",
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    db::messages::create_message(&state.db, &conv_id, "assistant", "4", None, None, Some(1))
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/export"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let export = json_body(resp).await;
+    assert_eq!(export["provider_id"], "openai");
+    assert_eq!(export["messages"].as_array().unwrap().len(), 2);
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            "/api/conversations/import",
+            &export.to_string(),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let imported = json_body(resp).await;
+    let imported_id = imported["id"].as_str().unwrap();
+    assert_ne!(imported_id, conv_id);
+    assert_eq!(imported["provider_id"], "openai");
+    assert_eq!(imported["model_name"], "gpt-4o");
+
+    let resp = app(state)
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{imported_id}/messages"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["messages"][0]["content"], "What is 2+2?");
+    assert_eq!(body["messages"][1]["content"], "4");
+}
+
+#[tokio::test]
+async fn export_conversation_as_markdown() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    db::messages::create_message(&state.db, &conv_id, "user", "ping", None, None, None)
+        .await
+        .unwrap();
+    db::messages::create_message(&state.db, &conv_id, "assistant", "pong", None, None, None)
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/export?format=markdown"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(
+        resp.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/markdown")
+    );
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(
+        text,
+        "# New Conversation\n\n## User\n\nping\n\n## Assistant\n\npong\n"
+    );
+
+    let resp = app(state)
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/export?format=pdf"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn import_conversation_rejects_invalid_role_and_drops_foreign_providers() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            "/api/conversations/import",
+            r#"{"title":"Bad","messages":[{"role":"wizard","content":"x"}]}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = app(state)
+        .oneshot(post_json_with_auth(
+            "/api/conversations/import",
+            r#"{"title":"Foreign","provider_id":"not-mine","model_name":"gpt-4o","messages":[{"role":"user","content":"hi"}]}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body = json_body(resp).await;
+    assert_eq!(body["title"], "Foreign");
+    assert!(body["provider_id"].is_null());
+    assert!(body["model_name"].is_null());
+}
+
+#[tokio::test]
+async fn fork_conversation_copies_messages_through_selected_one() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let mut ids = Vec::new();
+    for (role, content) in [
+        ("user", "first"),
+        ("assistant", "second"),
+        ("user", "third"),
+        ("assistant", "fourth"),
+    ] {
+        let msg =
+            db::messages::create_message(&state.db, &conv_id, role, content, None, None, None)
+                .await
+                .unwrap();
+        ids.push(msg.id);
+    }
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{conv_id}/fork"),
+            &serde_json::json!({ "from_message_id": ids[1] }).to_string(),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let fork = json_body(resp).await;
+    let fork_id = fork["id"].as_str().unwrap();
+    assert_ne!(fork_id, conv_id);
+    assert_eq!(fork["title"], "Fork of New Conversation");
+    assert_eq!(fork["provider_id"], "openai");
+    assert_eq!(fork["model_name"], "gpt-4o");
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{fork_id}/messages"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body["total"], 2);
+    let contents: Vec<_> = body["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["content"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(contents, ["first", "second"]);
+    assert!(
+        body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|m| !ids.contains(&m["id"].as_str().unwrap().to_string()))
+    );
+
+    // The original is untouched.
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/messages"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(json_body(resp).await["total"], 4);
+}
+
+#[tokio::test]
+async fn fork_conversation_rejects_unknown_message_and_other_users() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let msg = db::messages::create_message(&state.db, &conv_id, "user", "hi", None, None, None)
+        .await
+        .unwrap();
+    let uri = format!("/api/conversations/{conv_id}/fork");
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &uri,
+            r#"{"from_message_id":"missing"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let other = db::users::create_user(&state.db, "other", "other@example.com", "hash")
+        .await
+        .unwrap();
+    let other_token = claude_chat_backend::auth::create_access_token(
+        &other.id,
+        &other.username,
+        false,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+    let resp = app(state)
+        .oneshot(post_json_with_auth(
+            &uri,
+            &serde_json::json!({ "from_message_id": msg.id }).to_string(),
+            &other_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn duplicate_conversation_copies_history_without_share_token() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    for (role, content) in [("user", "ping"), ("assistant", "pong")] {
+        db::messages::create_message(&state.db, &conv_id, role, content, None, None, None)
+            .await
+            .unwrap();
+    }
+    let user_id = token_user_id(&state, &token);
+    db::conversations::set_share_token(&state.db, &conv_id, &user_id, "share-me")
+        .await
+        .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            &format!("/api/conversations/{conv_id}/duplicate"),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let copy = json_body(resp).await;
+    let copy_id = copy["id"].as_str().unwrap().to_string();
+    assert_ne!(copy_id, conv_id);
+    assert_eq!(copy["title"], "New Conversation (copy)");
+    assert_eq!(copy["provider_id"], "openai");
+    assert_eq!(copy["model_name"], "gpt-4o");
+    let stored = db::conversations::get_conversation(&state.db, &copy_id, &user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(stored.share_token.is_none());
+
+    let list = |id: String| {
+        let state = state.clone();
+        let token = token.clone();
+        async move {
+            let resp = app(state)
+                .oneshot(get_with_auth(
+                    &format!("/api/conversations/{id}/messages"),
+                    &token,
+                ))
+                .await
+                .unwrap();
+            json_body(resp).await
+        }
+    };
+    let copied = list(copy_id.clone()).await;
+    assert_eq!(copied["total"], 2);
+    assert_eq!(copied["messages"][0]["content"], "ping");
+    assert_eq!(copied["messages"][1]["content"], "pong");
+
+    // New messages in the copy don't show up in the original.
+    db::messages::create_message(
+        &state.db,
+        &copy_id,
+        "user",
+        "only in copy",
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(list(conv_id.clone()).await["total"], 2);
+    assert_eq!(list(copy_id).await["total"], 3);
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            "/api/conversations/missing/duplicate",
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn conversation_idle_timeout_override_create_and_update() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    seed_standard_providers(&state, &token).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_json_with_auth(
+            "/api/conversations",
+            r#"{"provider_id":"openai","model_name":"gpt-4o","subagent_provider_id":"openai","subagent_model":"gpt-4o","container_idle_timeout_secs":0}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body = json_body(resp).await;
+    assert_eq!(body["container_idle_timeout_secs"], 0);
+    let conv_id = body["id"].as_str().unwrap().to_string();
+
+    let resp = app(state.clone())
+        .oneshot(put_json(
+            &format!("/api/conversations/{conv_id}"),
+            r#"{"container_idle_timeout_secs":120}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["container_idle_timeout_secs"], 120);
+
+    // Omitting the field keeps the override.
+    let resp = app(state.clone())
+        .oneshot(put_json(
+            &format!("/api/conversations/{conv_id}"),
+            r#"{"title":"Renamed"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(json_body(resp).await["container_idle_timeout_secs"], 120);
+
+    let resp = app(state)
+        .oneshot(put_json(
+            &format!("/api/conversations/{conv_id}"),
+            r#"{"container_idle_timeout_secs":-1}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+/// Docker client whose containers print a fixed log and exit. Only `logs`
+/// is expected to be called.
+struct FixedLogsDocker(Vec<&'static str>);
+
+impl DockerClient for FixedLogsDocker {
+    fn create_container<'a>(
+        &'a self,
+        _name: &'a str,
+        _config: bollard::container::Config<String>,
+    ) -> BoxFuture<'a, Result<String, bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn start_container<'a>(
+        &'a self,
+        _id: &'a str,
+    ) -> BoxFuture<'a, Result<(), bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn stop_container<'a>(
+        &'a self,
+        _id: &'a str,
+        _timeout_secs: i64,
+    ) -> BoxFuture<'a, Result<(), bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn remove_container<'a>(
+        &'a self,
+        _id: &'a str,
+    ) -> BoxFuture<'a, Result<(), bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn rename_container<'a>(
+        &'a self,
+        _id: &'a str,
+        _name: &'a str,
+    ) -> BoxFuture<'a, Result<(), bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn upload_to_container<'a>(
+        &'a self,
+        _id: &'a str,
+        _path: &'a str,
+        _tar: Vec<u8>,
+    ) -> BoxFuture<'a, Result<(), bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn exec<'a>(
+        &'a self,
+        _id: &'a str,
+        _cmd: Vec<String>,
+    ) -> BoxFuture<'a, Result<(String, OutputStream), bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn exec_exit_code<'a>(
+        &'a self,
+        _exec_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<i64>, bollard::errors::Error>> {
+        unimplemented!()
+    }
+
+    fn logs(&self, _id: &str) -> OutputStream {
+        let lines: Vec<_> = self
+            .0
+            .iter()
+            .map(|line| {
+                Ok(bollard::container::LogOutput::StdErr {
+                    message: line.as_bytes().to_vec().into(),
+                })
+            })
+            .collect();
+        Box::pin(futures_util::stream::iter(lines))
+    }
+}
+
+#[tokio::test]
+async fn container_logs_stream_as_server_sent_events() {
+    let registry = ContainerRegistry::new();
+    let docker_manager = Arc::new(DockerManager::with_client(
+        Arc::new(FixedLogsDocker(vec!["booting\n", "init failed: bad key\n"])),
+        test_config(),
+        registry.clone(),
+    ));
+    let state = test_state_with_manager(docker_manager).await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let uri = format!("/api/conversations/{conv_id}/container/logs");
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    registry
+        .register(&conv_id, "container-1", &token_user_id(&state, &token))
+        .await;
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "text/event-stream");
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        String::from_utf8(body.to_vec()).unwrap(),
+        "data: booting\n\ndata: init failed: bad key\n\n"
+    );
+
+    let other = db::users::create_user(&state.db, "intruder", "intruder@example.com", "hash")
+        .await
+        .unwrap();
+    let other_token = claude_chat_backend::auth::create_access_token(
+        &other.id,
+        &other.username,
+        false,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+    let resp = app(state)
+        .oneshot(get_with_auth(&uri, &other_token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn container_status_reports_lifecycle() {
+    let registry = ContainerRegistry::new();
+    let docker_manager = Arc::new(DockerManager::new(test_config(), registry.clone()));
+    let state = test_state_with_manager(docker_manager).await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token, "openai", "gpt-4o").await;
+    let uri = format!("/api/conversations/{conv_id}/container/status");
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["status"], "stopped");
+    assert!(body["container_id"].is_null());
+    assert!(body["uptime_secs"].is_null());
+
+    registry
+        .register(&conv_id, "container-1", &token_user_id(&state, &token))
+        .await;
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &token))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body["status"], "starting");
+    assert_eq!(body["container_id"], "container-1");
+    assert_eq!(body["uptime_secs"], 0);
+
+    let (tx, _rx) = mpsc::channel(claude_chat_backend::ws::WS_CHANNEL_CAPACITY);
+    state.ws_state.add_container(&conv_id, tx).await;
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &token))
+        .await
+        .unwrap();
+    assert_eq!(json_body(resp).await["status"], "running");
+
+    let resp = app(state)
+        .oneshot(get_with_auth(
+            "/api/conversations/missing/container/status",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
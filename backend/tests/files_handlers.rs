@@ -6,11 +6,12 @@ use axum::{
 };
 use claude_chat_backend::{
     api,
-    auth::middleware::AppState,
+    auth::{JwtKeys, middleware::AppState},
     config::Config,
     db,
     docker::{manager::DockerManager, registry::ContainerRegistry},
-    ws::WsState,
+    provider_api::HttpProviderApiClient,
+    ws::{WsState, sse::SseState},
 };
 use http_body_util::BodyExt;
 use sha2::{Digest, Sha256};
@@ -23,11 +24,18 @@ fn test_config() -> Config {
     Config {
         database_url: "sqlite::memory:".into(),
         jwt_secret: "test-jwt-secret-that-is-long-enough-for-hmac".into(),
+        jwt_algorithm: Default::default(),
+        jwt_private_key_path: None,
+        jwt_public_key_path: None,
         encryption_key: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".into(),
         host: "127.0.0.1".into(),
         port: 0,
         container_image: "test:latest".into(),
         container_idle_timeout_secs: 600,
+        container_cpu_quota: None,
+        container_memory_bytes: None,
+        container_start_max_retries: 0,
+        container_pool_size: 0,
         internal_ws_port: 0,
         docker_network: None,
         host_data_dir: None,
@@ -37,20 +45,48 @@ fn test_config() -> Config {
         container_token_ttl_secs: 3600,
         refresh_token_ttl_days: 30,
         cookie_secure: false,
+        tool_call_timeout_secs: 300,
+        max_file_size_bytes: 50 * 1024 * 1024,
+        workspace_max_bytes: 1024 * 1024 * 1024,
+        allowed_upload_mime_types: None,
+        token_usage_update_interval_tokens: 500,
+        container_exec_allowlist: vec!["df".into(), "du".into(), "ls".into(), "ps".into()],
+        max_login_attempts: 10,
+        login_lockout_secs: 900,
+        trusted_proxies: vec![],
+        require_email_verification: false,
+        smtp_url: None,
+        mail_from: None,
+        oauth_client_id: None,
+        oauth_client_secret: None,
+        oauth_auth_url: None,
+        oauth_token_url: None,
+        max_conversations_per_user: None,
+        max_messages_per_conversation: None,
     }
 }
 
 async fn test_state() -> Arc<AppState> {
-    let config = test_config();
+    test_state_with_config(test_config()).await
+}
+
+async fn test_state_with_config(config: Config) -> Arc<AppState> {
     let pool = db::init_db("sqlite::memory:").await;
     let ws_state = WsState::new();
     let registry = ContainerRegistry::new();
     let docker_manager = Arc::new(DockerManager::new(config.clone(), registry));
     Arc::new(AppState {
         db: pool,
+        jwt_keys: JwtKeys::from_config(&config).unwrap(),
         config,
         ws_state,
+        sse_state: SseState::new(),
+        pending_uploads: Default::default(),
         docker_manager,
+        provider_api: Arc::new(HttpProviderApiClient::default()),
+        mailer: None,
+        system_stats_cache: Default::default(),
+        features: Default::default(),
     })
 }
 
@@ -322,3 +358,992 @@ async fn view_empty_file_with_range_returns_empty_full_response() {
     let body = response.into_body().collect().await.unwrap().to_bytes();
     assert!(body.is_empty());
 }
+
+#[tokio::test]
+async fn list_files_sorts_by_size_desc() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "listsort", "listsort@example.com").await;
+
+    let conv_dir = format!("data/conversations/{conv_id}");
+    tokio::fs::create_dir_all(&conv_dir).await.unwrap();
+    tokio::fs::write(format!("{conv_dir}/small.txt"), b"a")
+        .await
+        .unwrap();
+    tokio::fs::write(format!("{conv_dir}/large.txt"), b"abcdefgh")
+        .await
+        .unwrap();
+
+    let request = Request::builder()
+        .method("GET")
+        .uri(format!(
+            "/api/conversations/{conv_id}/files?sort=size&order=desc"
+        ))
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app(state).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(body["entries"][0]["name"], "large.txt");
+    assert_eq!(body["entries"][1]["name"], "small.txt");
+}
+
+#[tokio::test]
+async fn list_files_rejects_unknown_sort_field() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "listsortbad", "listsortbad@example.com").await;
+
+    tokio::fs::create_dir_all(format!("data/conversations/{conv_id}"))
+        .await
+        .unwrap();
+
+    let request = Request::builder()
+        .method("GET")
+        .uri(format!("/api/conversations/{conv_id}/files?sort=owner"))
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app(state).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+async fn view_file_response(name: &str, content: &[u8]) -> axum::response::Response {
+    let state = test_state().await;
+    let username = format!("view{}", name.replace('.', ""));
+    let (token, conv_id) =
+        register_and_create_conversation(&state, &username, &format!("{username}@example.com"))
+            .await;
+
+    let conv_dir = format!("data/conversations/{conv_id}");
+    tokio::fs::create_dir_all(&conv_dir).await.unwrap();
+    tokio::fs::write(format!("{conv_dir}/{name}"), content)
+        .await
+        .unwrap();
+
+    let request = Request::builder()
+        .method("GET")
+        .uri(format!(
+            "/api/conversations/{conv_id}/files/view?path={name}"
+        ))
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    app(state).oneshot(request).await.unwrap()
+}
+
+fn assert_view_security_headers(response: &axum::response::Response) {
+    assert_eq!(
+        response
+            .headers()
+            .get(header::CONTENT_SECURITY_POLICY)
+            .unwrap(),
+        "default-src 'none'; sandbox"
+    );
+    assert_eq!(
+        response
+            .headers()
+            .get(header::X_CONTENT_TYPE_OPTIONS)
+            .unwrap(),
+        "nosniff"
+    );
+}
+
+#[tokio::test]
+async fn view_html_file_is_served_as_plain_text_with_security_headers() {
+    let response = view_file_response("page.html", b"<script>alert(1)</script>").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_view_security_headers(&response);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/plain; charset=utf-8"
+    );
+}
+
+#[tokio::test]
+async fn view_js_file_has_security_headers() {
+    let response = view_file_response("app.js", b"console.log(1)").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_view_security_headers(&response);
+    assert_ne!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/plain; charset=utf-8"
+    );
+}
+
+#[tokio::test]
+async fn view_txt_file_has_security_headers() {
+    let response = view_file_response("notes.txt", b"hello").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_view_security_headers(&response);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/plain"
+    );
+}
+
+#[tokio::test]
+async fn view_html_range_request_has_security_headers() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "viewrange", "viewrange@example.com").await;
+
+    let conv_dir = format!("data/conversations/{conv_id}");
+    tokio::fs::create_dir_all(&conv_dir).await.unwrap();
+    tokio::fs::write(format!("{conv_dir}/page.html"), b"<p>hello</p>")
+        .await
+        .unwrap();
+
+    let request = Request::builder()
+        .method("GET")
+        .uri(format!(
+            "/api/conversations/{conv_id}/files/view?path=page.html"
+        ))
+        .header("authorization", format!("Bearer {token}"))
+        .header(header::RANGE, "bytes=0-2")
+        .body(Body::empty())
+        .unwrap();
+    let response = app(state).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_view_security_headers(&response);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/plain; charset=utf-8"
+    );
+}
+
+#[tokio::test]
+async fn upload_url_rejects_non_https_schemes() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "uploadurl", "uploadurl@example.com").await;
+
+    for url in [
+        "http://example.com/a.csv",
+        "file:///etc/passwd",
+        "ftp://example.com/a",
+        "not a url",
+    ] {
+        let response = app(state.clone())
+            .oneshot(authed_post_json(
+                &format!("/api/conversations/{conv_id}/files/upload-url"),
+                &token,
+                &serde_json::json!({ "url": url, "filename": "a.csv" }).to_string(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "url {url}");
+    }
+}
+
+#[tokio::test]
+async fn upload_url_rejects_internal_hosts() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "uploadurlssrf", "uploadurlssrf@example.com")
+            .await;
+
+    for url in [
+        "https://127.0.0.1/a.csv",
+        "https://169.254.169.254/latest/meta-data/",
+    ] {
+        let response = app(state.clone())
+            .oneshot(authed_post_json(
+                &format!("/api/conversations/{conv_id}/files/upload-url"),
+                &token,
+                &serde_json::json!({ "url": url, "filename": "a.csv" }).to_string(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "url {url}");
+    }
+    assert!(!std::path::Path::new(&format!("data/conversations/{conv_id}/a.csv")).exists());
+}
+
+#[tokio::test]
+async fn upload_url_rejects_unsafe_filename() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "uploadurlname", "uploadurlname@example.com")
+            .await;
+
+    let response = app(state.clone())
+        .oneshot(authed_post_json(
+            &format!("/api/conversations/{conv_id}/files/upload-url"),
+            &token,
+            r#"{"url":"https://example.com/a.csv","filename":"../escape.csv"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(!std::path::Path::new(&format!("data/conversations/{conv_id}/../escape.csv")).exists());
+}
+
+#[tokio::test]
+async fn search_files_returns_workspace_relative_matches() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "searchfiles", "searchfiles@example.com").await;
+
+    let conv_dir = format!("data/conversations/{conv_id}");
+    tokio::fs::create_dir_all(format!("{conv_dir}/src"))
+        .await
+        .unwrap();
+    tokio::fs::write(format!("{conv_dir}/src/lib.rs"), "needle\nneedle\n")
+        .await
+        .unwrap();
+    tokio::fs::write(format!("{conv_dir}/README.md"), "needle\n")
+        .await
+        .unwrap();
+
+    let request = Request::builder()
+        .method("GET")
+        .uri(format!(
+            "/api/conversations/{conv_id}/files/search?q=needle"
+        ))
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app(state.clone()).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(
+        body["matches"],
+        serde_json::json!([
+            {"file": "README.md", "line": 1, "text": "needle"},
+            {"file": "src/lib.rs", "line": 1, "text": "needle"},
+            {"file": "src/lib.rs", "line": 2, "text": "needle"},
+        ])
+    );
+
+    let request = Request::builder()
+        .method("GET")
+        .uri(format!(
+            "/api/conversations/{conv_id}/files/search?q=needle&path=/src"
+        ))
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app(state.clone()).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(body["matches"].as_array().unwrap().len(), 2);
+    assert_eq!(body["matches"][0]["file"], "src/lib.rs");
+
+    let request = Request::builder()
+        .method("GET")
+        .uri(format!(
+            "/api/conversations/{conv_id}/files/search?q=needle&path=../"
+        ))
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app(state.clone()).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let request = Request::builder()
+        .method("GET")
+        .uri(format!("/api/conversations/{conv_id}/files/search?q="))
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app(state).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+fn authed_delete(uri: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("DELETE")
+        .uri(uri)
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn delete_removes_files_and_directories() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "deletefiles", "deletefiles@example.com").await;
+
+    let conv_dir = format!("data/conversations/{conv_id}");
+    tokio::fs::create_dir_all(format!("{conv_dir}/build/out"))
+        .await
+        .unwrap();
+    tokio::fs::write(format!("{conv_dir}/build/out/a.o"), b"obj")
+        .await
+        .unwrap();
+    tokio::fs::write(format!("{conv_dir}/notes.txt"), b"notes")
+        .await
+        .unwrap();
+
+    let response = app(state.clone())
+        .oneshot(authed_delete(
+            &format!("/api/conversations/{conv_id}/files?path=notes.txt"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!std::path::Path::new(&format!("{conv_dir}/notes.txt")).exists());
+
+    let response = app(state.clone())
+        .oneshot(authed_delete(
+            &format!("/api/conversations/{conv_id}/files?path=/build"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!std::path::Path::new(&format!("{conv_dir}/build")).exists());
+    assert!(std::path::Path::new(&conv_dir).is_dir());
+}
+
+#[tokio::test]
+async fn delete_rejects_workspace_root_and_traversal() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "deleteroot", "deleteroot@example.com").await;
+    let (_, other_conv_id) =
+        register_and_create_conversation(&state, "deletevictim", "deletevictim@example.com").await;
+
+    let conv_dir = format!("data/conversations/{conv_id}");
+    tokio::fs::create_dir_all(&conv_dir).await.unwrap();
+    let victim = format!("data/conversations/{other_conv_id}/keep.txt");
+    tokio::fs::create_dir_all(format!("data/conversations/{other_conv_id}"))
+        .await
+        .unwrap();
+    tokio::fs::write(&victim, b"keep").await.unwrap();
+
+    for path in ["/", "."] {
+        let response = app(state.clone())
+            .oneshot(authed_delete(
+                &format!("/api/conversations/{conv_id}/files?path={path}"),
+                &token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{path}");
+    }
+    assert!(std::path::Path::new(&conv_dir).is_dir());
+
+    let response = app(state)
+        .oneshot(authed_delete(
+            &format!("/api/conversations/{conv_id}/files?path=../{other_conv_id}/keep.txt"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(std::path::Path::new(&victim).exists());
+}
+
+#[tokio::test]
+async fn rename_and_move_paths_within_workspace() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "renamefiles", "renamefiles@example.com").await;
+
+    let conv_dir = format!("data/conversations/{conv_id}");
+    tokio::fs::create_dir_all(&conv_dir).await.unwrap();
+    tokio::fs::write(format!("{conv_dir}/draft.txt"), b"draft")
+        .await
+        .unwrap();
+
+    let response = app(state.clone())
+        .oneshot(authed_post_json(
+            &format!("/api/conversations/{conv_id}/files/rename"),
+            &token,
+            r#"{"from":"draft.txt","to":"final.txt"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(
+        body,
+        serde_json::json!({"path": "/final.txt", "name": "final.txt"})
+    );
+
+    let response = app(state.clone())
+        .oneshot(authed_post_json(
+            &format!("/api/conversations/{conv_id}/files/move"),
+            &token,
+            r#"{"from":"/final.txt","to":"docs/2024/final.txt"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(body["path"], "/docs/2024/final.txt");
+    assert!(!std::path::Path::new(&format!("{conv_dir}/final.txt")).exists());
+    assert_eq!(
+        tokio::fs::read(format!("{conv_dir}/docs/2024/final.txt"))
+            .await
+            .unwrap(),
+        b"draft"
+    );
+}
+
+#[tokio::test]
+async fn rename_rejects_paths_outside_workspace() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "renameescape", "renameescape@example.com").await;
+    let (_, other_conv_id) =
+        register_and_create_conversation(&state, "renameother", "renameother@example.com").await;
+
+    let conv_dir = format!("data/conversations/{conv_id}");
+    let other_dir = format!("data/conversations/{other_conv_id}");
+    tokio::fs::create_dir_all(&conv_dir).await.unwrap();
+    tokio::fs::create_dir_all(&other_dir).await.unwrap();
+    tokio::fs::write(format!("{conv_dir}/mine.txt"), b"mine")
+        .await
+        .unwrap();
+    tokio::fs::write(format!("{other_dir}/theirs.txt"), b"theirs")
+        .await
+        .unwrap();
+
+    for body in [
+        format!(r#"{{"from":"mine.txt","to":"../{other_conv_id}/mine.txt"}}"#),
+        format!(r#"{{"from":"../{other_conv_id}/theirs.txt","to":"theirs.txt"}}"#),
+    ] {
+        let response = app(state.clone())
+            .oneshot(authed_post_json(
+                &format!("/api/conversations/{conv_id}/files/rename"),
+                &token,
+                &body,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{body}");
+    }
+    assert!(std::path::Path::new(&format!("{conv_dir}/mine.txt")).exists());
+    assert!(std::path::Path::new(&format!("{other_dir}/theirs.txt")).exists());
+    assert!(!std::path::Path::new(&format!("{other_dir}/mine.txt")).exists());
+}
+
+#[tokio::test]
+async fn mkdir_creates_nested_directories_once() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "mkdirfiles", "mkdirfiles@example.com").await;
+    let uri = format!("/api/conversations/{conv_id}/files/mkdir");
+
+    let response = app(state.clone())
+        .oneshot(authed_post_json(&uri, &token, r#"{"path":"src/utils"}"#))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(response).await,
+        serde_json::json!({"path": "/src/utils", "created": true})
+    );
+    assert!(std::path::Path::new(&format!("data/conversations/{conv_id}/src/utils")).is_dir());
+
+    let response = app(state.clone())
+        .oneshot(authed_post_json(&uri, &token, r#"{"path":"/src/utils"}"#))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(response).await["created"], false);
+
+    let response = app(state)
+        .oneshot(authed_post_json(&uri, &token, r#"{"path":"../escaped"}"#))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(!std::path::Path::new("data/conversations/escaped").exists());
+}
+
+#[tokio::test]
+async fn upload_over_workspace_quota_returns_413() {
+    let mut config = test_config();
+    config.workspace_max_bytes = 10;
+    let state = test_state_with_config(config).await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "quotafiles", "quotafiles@example.com").await;
+    let upload = |name: &str, content: &[u8]| {
+        let (boundary, body) = multipart_body_single_file(name, content);
+        authed_post_bytes(
+            &format!("/api/conversations/{conv_id}/files/upload"),
+            &token,
+            &format!("multipart/form-data; boundary={boundary}"),
+            body,
+        )
+    };
+
+    let response = app(state.clone())
+        .oneshot(upload("first.txt", b"12345"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app(state.clone())
+        .oneshot(upload("second.txt", b"12345678"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(!std::path::Path::new(&format!("data/conversations/{conv_id}/second.txt")).exists());
+
+    let request = Request::builder()
+        .method("GET")
+        .uri(format!("/api/conversations/{conv_id}/files/disk-usage"))
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app(state).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(response).await,
+        serde_json::json!({"bytes_used": 5, "bytes_limit": 10})
+    );
+}
+
+#[tokio::test]
+async fn upload_enforces_mime_type_allowlist() {
+    let mut config = test_config();
+    config.allowed_upload_mime_types = Some(vec!["image/jpeg".into(), "text/plain".into()]);
+    let state = test_state_with_config(config).await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "mimefiles", "mimefiles@example.com").await;
+    let upload = |name: &str, content: &[u8]| {
+        let (boundary, body) = multipart_body_single_file(name, content);
+        authed_post_bytes(
+            &format!("/api/conversations/{conv_id}/files/upload"),
+            &token,
+            &format!("multipart/form-data; boundary={boundary}"),
+            body,
+        )
+    };
+
+    let mut jpeg = vec![
+        0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0x00,
+    ];
+    jpeg.resize(256, 0);
+    let response = app(state.clone())
+        .oneshot(upload("photo.jpg", &jpeg))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // An ELF binary is rejected even when disguised with an allowed extension.
+    let mut elf = vec![0x7F, b'E', b'L', b'F', 2, 1, 1, 0];
+    elf.resize(64, 0);
+    elf[16] = 2;
+    let response = app(state.clone())
+        .oneshot(upload("tool.txt", &elf))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let body = json_body(response).await;
+    assert!(
+        body["message"]
+            .as_str()
+            .unwrap()
+            .contains("application/x-executable"),
+        "{body}"
+    );
+    assert!(!std::path::Path::new(&format!("data/conversations/{conv_id}/tool.txt")).exists());
+}
+
+#[tokio::test]
+async fn view_file_revalidation_returns_304_when_unchanged() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "viewcache", "viewcache@example.com").await;
+    let conv_dir = format!("data/conversations/{conv_id}");
+    tokio::fs::create_dir_all(&conv_dir).await.unwrap();
+    tokio::fs::write(format!("{conv_dir}/image.png"), b"not really a png")
+        .await
+        .unwrap();
+    let view = |validator: Option<(&str, &str)>| {
+        let mut builder = Request::builder()
+            .method("GET")
+            .uri(format!(
+                "/api/conversations/{conv_id}/files/view?path=image.png"
+            ))
+            .header("authorization", format!("Bearer {token}"));
+        if let Some((name, value)) = validator {
+            builder = builder.header(name, value);
+        }
+        builder.body(Body::empty()).unwrap()
+    };
+
+    let response = app(state.clone()).oneshot(view(None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    let last_modified = response.headers()["last-modified"]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    for validator in [
+        ("if-none-match", etag.as_str()),
+        ("if-modified-since", &last_modified),
+    ] {
+        let response = app(state.clone())
+            .oneshot(view(Some(validator)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{validator:?}");
+        assert_eq!(response.headers()["etag"], etag.as_str());
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+    }
+
+    let response = app(state)
+        .oneshot(view(Some(("if-none-match", "\"stale\""))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"not really a png");
+}
+
+fn authed_patch_bytes(uri: &str, token: &str, body: &[u8]) -> Request<Body> {
+    Request::builder()
+        .method("PATCH")
+        .uri(uri)
+        .header("authorization", format!("Bearer {token}"))
+        .header("content-type", "application/octet-stream")
+        .body(Body::from(body.to_vec()))
+        .unwrap()
+}
+
+async fn init_resumable_upload(
+    state: &Arc<AppState>,
+    token: &str,
+    conv_id: &str,
+    body: serde_json::Value,
+) -> String {
+    let response = app(state.clone())
+        .oneshot(authed_post_json(
+            &format!("/api/conversations/{conv_id}/files/upload/init"),
+            token,
+            &body.to_string(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    json_body(response).await["upload_id"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn resumable_upload_appends_chunks_and_completes() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "resumable", "resumable@example.com").await;
+    let upload_id = init_resumable_upload(
+        &state,
+        &token,
+        &conv_id,
+        serde_json::json!({"filename": "model.bin", "size": 10, "chunk_size": 4}),
+    )
+    .await;
+    let base = format!("/api/conversations/{conv_id}/files/upload/{upload_id}");
+    let chunk = |offset: u64, bytes: &[u8]| {
+        authed_patch_bytes(&format!("{base}?offset={offset}"), &token, bytes)
+    };
+
+    let response = app(state.clone()).oneshot(chunk(0, b"abcd")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(response).await,
+        serde_json::json!({"received": 4, "size": 10})
+    );
+
+    // A retried chunk at a stale offset is rejected, not appended twice.
+    let response = app(state.clone()).oneshot(chunk(0, b"abcd")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = app(state.clone())
+        .oneshot(chunk(4, b"efghi"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = app(state.clone()).oneshot(chunk(4, b"efgh")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let complete = || authed_post_json(&format!("{base}/complete"), &token, "");
+    let response = app(state.clone()).oneshot(complete()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app(state.clone()).oneshot(chunk(8, b"ij")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app(state.clone()).oneshot(complete()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(response).await,
+        serde_json::json!({"name": "model.bin", "size": 10, "path": "/model.bin"})
+    );
+    assert_eq!(
+        tokio::fs::read(format!("data/conversations/{conv_id}/model.bin"))
+            .await
+            .unwrap(),
+        b"abcdefghij"
+    );
+    assert!(!std::path::Path::new(&format!("data/uploads/{upload_id}.part")).exists());
+
+    let response = app(state).oneshot(complete()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn resumable_upload_abort_and_ownership() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "resumeabort", "resumeabort@example.com").await;
+    let (other_token, other_conv_id) =
+        register_and_create_conversation(&state, "resumeother", "resumeother@example.com").await;
+
+    let response = app(state.clone())
+        .oneshot(authed_post_json(
+            &format!("/api/conversations/{conv_id}/files/upload/init"),
+            &token,
+            r#"{"filename":"../x.bin","size":4,"chunk_size":4}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let upload_id = init_resumable_upload(
+        &state,
+        &token,
+        &conv_id,
+        serde_json::json!({"filename": "x.bin", "size": 4, "chunk_size": 4}),
+    )
+    .await;
+
+    let response = app(state.clone())
+        .oneshot(authed_patch_bytes(
+            &format!("/api/conversations/{other_conv_id}/files/upload/{upload_id}?offset=0"),
+            &other_token,
+            b"evil",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let base = format!("/api/conversations/{conv_id}/files/upload/{upload_id}");
+    let response = app(state.clone())
+        .oneshot(authed_patch_bytes(
+            &format!("{base}?offset=0"),
+            &token,
+            b"ab",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(std::path::Path::new(&format!("data/uploads/{upload_id}.part")).exists());
+
+    let response = app(state.clone())
+        .oneshot(authed_delete(&base, &token))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!std::path::Path::new(&format!("data/uploads/{upload_id}.part")).exists());
+
+    let response = app(state)
+        .oneshot(authed_patch_bytes(
+            &format!("{base}?offset=2"),
+            &token,
+            b"cd",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn resumable_upload_reserves_declared_size_against_quota() {
+    let mut config = test_config();
+    config.workspace_max_bytes = 10;
+    let state = test_state_with_config(config).await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "resumequota", "resumequota@example.com").await;
+    let init = |filename: &str| {
+        authed_post_json(
+            &format!("/api/conversations/{conv_id}/files/upload/init"),
+            &token,
+            &serde_json::json!({"filename": filename, "size": 6, "chunk_size": 6}).to_string(),
+        )
+    };
+
+    let first = init_resumable_upload(
+        &state,
+        &token,
+        &conv_id,
+        serde_json::json!({"filename": "a.bin", "size": 6, "chunk_size": 6}),
+    )
+    .await;
+    let response = app(state.clone()).oneshot(init("b.bin")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Aborting releases the reservation.
+    let response = app(state.clone())
+        .oneshot(authed_delete(
+            &format!("/api/conversations/{conv_id}/files/upload/{first}"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = app(state).oneshot(init("b.bin")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+fn build_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    use std::io::Write;
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, content) in entries {
+        zip.start_file(*name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+#[tokio::test]
+async fn extract_zip_expands_nested_files() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "extractzip", "extractzip@example.com").await;
+    let conv_dir = format!("data/conversations/{conv_id}");
+    tokio::fs::create_dir_all(&conv_dir).await.unwrap();
+    let archive = build_zip(&[
+        ("README.md", b"readme"),
+        ("src/main.rs", b"fn main() {}"),
+        ("src/util/mod.rs", b"// util"),
+    ]);
+    tokio::fs::write(format!("{conv_dir}/bundle.zip"), archive)
+        .await
+        .unwrap();
+
+    let response = app(state)
+        .oneshot(authed_post_json(
+            &format!("/api/conversations/{conv_id}/files/extract?path=bundle.zip&dest=extracted/"),
+            &token,
+            "",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(response).await,
+        serde_json::json!({"files_extracted": 3, "dest": "/extracted/"})
+    );
+    assert_eq!(
+        tokio::fs::read(format!("{conv_dir}/extracted/src/util/mod.rs"))
+            .await
+            .unwrap(),
+        b"// util"
+    );
+    assert!(std::path::Path::new(&format!("{conv_dir}/extracted/README.md")).is_file());
+}
+
+#[tokio::test]
+async fn extract_zip_rejects_escaping_entries_and_non_zip_files() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "extractevil", "extractevil@example.com").await;
+    let conv_dir = format!("data/conversations/{conv_id}");
+    tokio::fs::create_dir_all(&conv_dir).await.unwrap();
+    let archive = build_zip(&[("safe.txt", b"safe"), ("../escape.txt", b"evil")]);
+    tokio::fs::write(format!("{conv_dir}/evil.zip"), archive)
+        .await
+        .unwrap();
+    tokio::fs::write(format!("{conv_dir}/notes.txt"), b"not a zip")
+        .await
+        .unwrap();
+
+    let response = app(state.clone())
+        .oneshot(authed_post_json(
+            &format!("/api/conversations/{conv_id}/files/extract?path=evil.zip"),
+            &token,
+            "",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(!std::path::Path::new(&format!("{conv_dir}/safe.txt")).exists());
+    assert!(!std::path::Path::new("data/conversations/escape.txt").exists());
+
+    let response = app(state.clone())
+        .oneshot(authed_post_json(
+            &format!("/api/conversations/{conv_id}/files/extract?path=notes.txt"),
+            &token,
+            "",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app(state)
+        .oneshot(authed_post_json(
+            &format!("/api/conversations/{conv_id}/files/extract?path=evil.zip&dest=../other"),
+            &token,
+            "",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn delete_batch_reports_partial_failures() {
+    let state = test_state().await;
+    let (token, conv_id) =
+        register_and_create_conversation(&state, "deletebatch", "deletebatch@example.com").await;
+    let (_, other_conv_id) =
+        register_and_create_conversation(&state, "deletebatch2", "deletebatch2@example.com").await;
+
+    let conv_dir = format!("data/conversations/{conv_id}");
+    let other_dir = format!("data/conversations/{other_conv_id}");
+    tokio::fs::create_dir_all(format!("{conv_dir}/logs"))
+        .await
+        .unwrap();
+    tokio::fs::create_dir_all(&other_dir).await.unwrap();
+    tokio::fs::write(format!("{conv_dir}/a.txt"), b"a")
+        .await
+        .unwrap();
+    tokio::fs::write(format!("{conv_dir}/logs/run.log"), b"log")
+        .await
+        .unwrap();
+    tokio::fs::write(format!("{other_dir}/keep.txt"), b"keep")
+        .await
+        .unwrap();
+
+    let body = serde_json::json!({
+        "paths": ["a.txt", "logs/", format!("../{other_conv_id}/keep.txt"), "/"]
+    });
+    let response = app(state)
+        .oneshot(authed_post_json(
+            &format!("/api/conversations/{conv_id}/files/delete-batch"),
+            &token,
+            &body.to_string(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(body["deleted"], serde_json::json!(["a.txt", "logs/"]));
+    let failed: Vec<&str> = body["failed"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        failed,
+        vec![format!("../{other_conv_id}/keep.txt").as_str(), "/"]
+    );
+
+    assert!(!std::path::Path::new(&format!("{conv_dir}/a.txt")).exists());
+    assert!(!std::path::Path::new(&format!("{conv_dir}/logs")).exists());
+    assert!(std::path::Path::new(&format!("{other_dir}/keep.txt")).exists());
+    assert!(std::path::Path::new(&conv_dir).is_dir());
+}
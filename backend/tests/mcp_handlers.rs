@@ -0,0 +1,475 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode},
+};
+use claude_chat_backend::{
+    api, auth,
+    auth::{JwtKeys, middleware::AppState},
+    config::Config,
+    db,
+    docker::{manager::DockerManager, registry::ContainerRegistry},
+    provider_api::HttpProviderApiClient,
+    ws::{WsState, sse::SseState},
+};
+use http_body_util::BodyExt;
+use std::sync::Arc;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn test_config() -> Config {
+    Config {
+        database_url: "sqlite::memory:".into(),
+        jwt_secret: "test-jwt-secret-that-is-long-enough-for-hmac".into(),
+        jwt_algorithm: Default::default(),
+        jwt_private_key_path: None,
+        jwt_public_key_path: None,
+        encryption_key: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".into(),
+        host: "127.0.0.1".into(),
+        port: 0,
+        container_image: "test:latest".into(),
+        container_idle_timeout_secs: 600,
+        container_cpu_quota: None,
+        container_memory_bytes: None,
+        container_start_max_retries: 0,
+        container_pool_size: 0,
+        internal_ws_port: 0,
+        docker_network: None,
+        host_data_dir: None,
+        fileserver_url: None,
+        cors_allowed_origins: None,
+        access_token_ttl_secs: 7200,
+        container_token_ttl_secs: 3600,
+        refresh_token_ttl_days: 30,
+        cookie_secure: false,
+        tool_call_timeout_secs: 300,
+        max_file_size_bytes: 50 * 1024 * 1024,
+        workspace_max_bytes: 1024 * 1024 * 1024,
+        allowed_upload_mime_types: None,
+        token_usage_update_interval_tokens: 500,
+        container_exec_allowlist: vec!["df".into(), "du".into(), "ls".into(), "ps".into()],
+        max_login_attempts: 10,
+        login_lockout_secs: 900,
+        trusted_proxies: vec![],
+        require_email_verification: false,
+        smtp_url: None,
+        mail_from: None,
+        oauth_client_id: None,
+        oauth_client_secret: None,
+        oauth_auth_url: None,
+        oauth_token_url: None,
+        max_conversations_per_user: None,
+        max_messages_per_conversation: None,
+    }
+}
+
+async fn test_state() -> Arc<AppState> {
+    let config = test_config();
+    let pool = db::init_db("sqlite::memory:").await;
+    let ws_state = WsState::new();
+    let registry = ContainerRegistry::new();
+    let docker_manager = Arc::new(DockerManager::new(config.clone(), registry));
+    Arc::new(AppState {
+        db: pool,
+        jwt_keys: JwtKeys::from_config(&config).unwrap(),
+        config,
+        ws_state,
+        sse_state: SseState::new(),
+        pending_uploads: Default::default(),
+        docker_manager,
+        provider_api: Arc::new(HttpProviderApiClient::default()),
+        mailer: None,
+        system_stats_cache: Default::default(),
+        features: Default::default(),
+    })
+}
+
+fn app(state: Arc<AppState>) -> Router {
+    Router::new()
+        .nest("/api/mcp-servers", api::mcp_servers::router())
+        .nest("/api/conversations", api::conversations::router())
+        .with_state(state)
+}
+
+fn get_with_auth(uri: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("GET")
+        .uri(uri)
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn put_json_with_auth(uri: &str, body: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("PUT")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+async fn json_body(response: axum::response::Response) -> serde_json::Value {
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn token_for(state: &Arc<AppState>, username: &str, is_admin: bool) -> String {
+    user_with_token(state, username, is_admin).await.1
+}
+
+async fn user_with_token(
+    state: &Arc<AppState>,
+    username: &str,
+    is_admin: bool,
+) -> (String, String) {
+    let user = db::users::create_user(
+        &state.db,
+        username,
+        &format!("{username}@example.com"),
+        "hash",
+    )
+    .await
+    .unwrap();
+    db::users::admin_update_user(&state.db, &user.id, None, Some(is_admin), None)
+        .await
+        .unwrap();
+    let token = auth::create_access_token(
+        &user.id,
+        &user.username,
+        is_admin,
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )
+    .unwrap();
+    (user.id, token)
+}
+
+async fn create_http_server(state: &Arc<AppState>, url: &str, is_enabled: bool) -> String {
+    db::mcp_servers::create_mcp_server(
+        &state.db,
+        "remote",
+        None,
+        "sse",
+        None,
+        None,
+        Some(url),
+        None,
+        is_enabled,
+    )
+    .await
+    .unwrap()
+    .id
+}
+
+#[tokio::test]
+async fn health_reports_reachable_http_server() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/mcp/health"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock)
+        .await;
+    let id = create_http_server(&state, &format!("{}/mcp/", mock.uri()), true).await;
+
+    let resp = app(state)
+        .oneshot(get_with_auth(
+            &format!("/api/mcp-servers/{id}/health"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["checked"], true);
+    assert_eq!(body["reachable"], true);
+    assert!(body["latency_ms"].is_u64());
+    assert!(body.get("error").is_none());
+}
+
+#[tokio::test]
+async fn health_reports_failing_http_server() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/health"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&mock)
+        .await;
+    let id = create_http_server(&state, &mock.uri(), true).await;
+
+    let resp = app(state)
+        .oneshot(get_with_auth(
+            &format!("/api/mcp-servers/{id}/health"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["reachable"], false);
+    assert!(body["error"].as_str().unwrap().contains("503"));
+    assert!(body.get("latency_ms").is_none());
+}
+
+#[tokio::test]
+async fn health_is_admin_only() {
+    let state = test_state().await;
+    let user_token = token_for(&state, "alice", false).await;
+    let admin_token = token_for(&state, "admin", true).await;
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/health"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock)
+        .await;
+    let id = create_http_server(&state, &mock.uri(), false).await;
+    let uri = format!("/api/mcp-servers/{id}/health");
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &user_token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&uri, &admin_token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["reachable"], true);
+
+    let resp = app(state)
+        .oneshot(get_with_auth(
+            "/api/mcp-servers/missing/health",
+            &admin_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn health_does_not_launch_stdio_servers() {
+    let state = test_state().await;
+    let token = token_for(&state, "admin", true).await;
+    let marker = tempfile::TempDir::new().unwrap();
+    let marker_file = marker.path().join("launched");
+    let server = db::mcp_servers::create_mcp_server(
+        &state.db,
+        "local",
+        None,
+        "stdio",
+        Some("touch"),
+        Some(&serde_json::json!([marker_file]).to_string()),
+        None,
+        None,
+        true,
+    )
+    .await
+    .unwrap();
+
+    let resp = app(state)
+        .oneshot(get_with_auth(
+            &format!("/api/mcp-servers/{}/health", server.id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["checked"], false);
+    assert_eq!(body["reachable"], false);
+    assert!(!marker_file.exists());
+}
+
+#[tokio::test]
+async fn health_requires_auth() {
+    let state = test_state().await;
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .uri("/api/mcp-servers/anything/health")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+/// Attach a new stdio server to a fresh conversation owned by `user_id`.
+async fn conversation_with_server(
+    state: &Arc<AppState>,
+    user_id: &str,
+    is_enabled: bool,
+) -> (String, String) {
+    let server = db::mcp_servers::create_mcp_server(
+        &state.db,
+        "fs",
+        None,
+        "stdio",
+        Some("npx"),
+        None,
+        None,
+        None,
+        is_enabled,
+    )
+    .await
+    .unwrap();
+    let conv = db::conversations::create_conversation(
+        &state.db, user_id, "MCP", None, None, None, false, None, None, None,
+    )
+    .await
+    .unwrap();
+    db::mcp_servers::set_conversation_mcp_servers(
+        &state.db,
+        &conv.id,
+        std::slice::from_ref(&server.id),
+    )
+    .await
+    .unwrap();
+    (conv.id, server.id)
+}
+
+async fn conversation_server_enabled(state: &Arc<AppState>, conv_id: &str, token: &str) -> bool {
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/conversations/{conv_id}/mcp-servers"),
+            token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    json_body(resp).await[0]["is_enabled"].as_bool().unwrap()
+}
+
+#[tokio::test]
+async fn user_override_reenables_disabled_server() {
+    let state = test_state().await;
+    let (user_id, token) = user_with_token(&state, "alice", false).await;
+    let (conv_id, server_id) = conversation_with_server(&state, &user_id, false).await;
+    let override_uri = format!("/api/mcp-servers/{server_id}/override");
+
+    assert!(!conversation_server_enabled(&state, &conv_id, &token).await);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(&override_uri, &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert!(body["is_enabled"].is_null());
+
+    let resp = app(state.clone())
+        .oneshot(put_json_with_auth(
+            &override_uri,
+            r#"{"is_enabled":true,"read_only_overrides":"{\"write_file\":false}"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["is_enabled"], true);
+    assert_eq!(body["read_only_overrides"], r#"{"write_file":false}"#);
+
+    assert!(conversation_server_enabled(&state, &conv_id, &token).await);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/mcp-servers", &token))
+        .await
+        .unwrap();
+    let listed = json_body(resp).await;
+    assert_eq!(listed[0]["id"], server_id.as_str());
+
+    // Other users still see the server as disabled.
+    let other_token = token_for(&state, "bob", false).await;
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/mcp-servers", &other_token))
+        .await
+        .unwrap();
+    assert!(json_body(resp).await.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn user_override_disables_enabled_server() {
+    let state = test_state().await;
+    let (user_id, token) = user_with_token(&state, "alice", false).await;
+    let (conv_id, server_id) = conversation_with_server(&state, &user_id, true).await;
+    let override_uri = format!("/api/mcp-servers/{server_id}/override");
+
+    assert!(conversation_server_enabled(&state, &conv_id, &token).await);
+
+    let resp = app(state.clone())
+        .oneshot(put_json_with_auth(
+            &override_uri,
+            r#"{"is_enabled":false}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(!conversation_server_enabled(&state, &conv_id, &token).await);
+
+    // Clearing the override falls back to the global setting.
+    let resp = app(state.clone())
+        .oneshot(put_json_with_auth(&override_uri, "{}", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(conversation_server_enabled(&state, &conv_id, &token).await);
+}
+
+#[tokio::test]
+async fn user_override_validates_input() {
+    let state = test_state().await;
+    let token = token_for(&state, "alice", false).await;
+
+    let resp = app(state.clone())
+        .oneshot(put_json_with_auth(
+            "/api/mcp-servers/missing/override",
+            r#"{"is_enabled":true}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let id = create_http_server(&state, "http://mcp", true).await;
+    let resp = app(state)
+        .oneshot(put_json_with_auth(
+            &format!("/api/mcp-servers/{id}/override"),
+            r#"{"read_only_overrides":"[1]"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn mcp_endpoints_return_503_when_mcp_disabled() {
+    let mut state = test_state().await;
+    Arc::get_mut(&mut state).unwrap().features.enable_mcp = false;
+    let token = token_for(&state, "admin", true).await;
+    let server_id = create_http_server(&state, "http://127.0.0.1:9/mcp", true).await;
+
+    for uri in [
+        "/api/mcp-servers".to_string(),
+        format!("/api/mcp-servers/{server_id}/health"),
+        format!("/api/mcp-servers/{server_id}/override"),
+    ] {
+        let resp = app(state.clone())
+            .oneshot(get_with_auth(&uri, &token))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE, "{uri}");
+    }
+}
@@ -5,11 +5,12 @@ use axum::{
 };
 use claude_chat_backend::{
     api, auth,
-    auth::middleware::AppState,
+    auth::{JwtKeys, middleware::AppState},
     config::Config,
     db,
     docker::{manager::DockerManager, registry::ContainerRegistry},
-    ws::WsState,
+    provider_api::HttpProviderApiClient,
+    ws::{WsState, sse::SseState},
 };
 use http_body_util::BodyExt;
 use std::sync::Arc;
@@ -19,11 +20,18 @@ fn test_config() -> Config {
     Config {
         database_url: "sqlite::memory:".into(),
         jwt_secret: "test-jwt-secret-that-is-long-enough-for-hmac".into(),
+        jwt_algorithm: Default::default(),
+        jwt_private_key_path: None,
+        jwt_public_key_path: None,
         encryption_key: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".into(),
         host: "127.0.0.1".into(),
         port: 0,
         container_image: "test:latest".into(),
         container_idle_timeout_secs: 600,
+        container_cpu_quota: None,
+        container_memory_bytes: None,
+        container_start_max_retries: 0,
+        container_pool_size: 0,
         internal_ws_port: 0,
         docker_network: None,
         host_data_dir: None,
@@ -33,6 +41,24 @@ fn test_config() -> Config {
         container_token_ttl_secs: 3600,
         refresh_token_ttl_days: 30,
         cookie_secure: false,
+        tool_call_timeout_secs: 300,
+        max_file_size_bytes: 50 * 1024 * 1024,
+        workspace_max_bytes: 1024 * 1024 * 1024,
+        allowed_upload_mime_types: None,
+        token_usage_update_interval_tokens: 500,
+        container_exec_allowlist: vec!["df".into(), "du".into(), "ls".into(), "ps".into()],
+        max_login_attempts: 10,
+        login_lockout_secs: 900,
+        trusted_proxies: vec![],
+        require_email_verification: false,
+        smtp_url: None,
+        mail_from: None,
+        oauth_client_id: None,
+        oauth_client_secret: None,
+        oauth_auth_url: None,
+        oauth_token_url: None,
+        max_conversations_per_user: None,
+        max_messages_per_conversation: None,
     }
 }
 
@@ -44,9 +70,16 @@ async fn test_state() -> Arc<AppState> {
     let docker_manager = Arc::new(DockerManager::new(config.clone(), registry));
     Arc::new(AppState {
         db: pool,
+        jwt_keys: JwtKeys::from_config(&config).unwrap(),
         config,
         ws_state,
+        sse_state: SseState::new(),
+        pending_uploads: Default::default(),
         docker_manager,
+        provider_api: Arc::new(HttpProviderApiClient::default()),
+        mailer: None,
+        system_stats_cache: Default::default(),
+        features: Default::default(),
     })
 }
 
@@ -256,7 +289,7 @@ async fn presets_endpoint_does_not_seed_for_non_register_user_creation_path() {
         &user.id,
         &user.username,
         user.is_admin,
-        &state.config.jwt_secret,
+        &state.jwt_keys,
         state.config.access_token_ttl_secs,
     )
     .unwrap();
@@ -5,11 +5,12 @@ use axum::{
 };
 use claude_chat_backend::{
     api,
-    auth::middleware::AppState,
+    auth::{JwtKeys, middleware::AppState},
     config::Config,
     db,
     docker::{manager::DockerManager, registry::ContainerRegistry},
-    ws::WsState,
+    provider_api::HttpProviderApiClient,
+    ws::{WsState, sse::SseState},
 };
 use http_body_util::BodyExt;
 use std::sync::Arc;
@@ -23,11 +24,18 @@ fn test_config() -> Config {
     Config {
         database_url: "sqlite::memory:".into(),
         jwt_secret: "test-jwt-secret-that-is-long-enough-for-hmac".into(),
+        jwt_algorithm: Default::default(),
+        jwt_private_key_path: None,
+        jwt_public_key_path: None,
         encryption_key: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".into(),
         host: "127.0.0.1".into(),
         port: 0,
         container_image: "test:latest".into(),
         container_idle_timeout_secs: 600,
+        container_cpu_quota: None,
+        container_memory_bytes: None,
+        container_start_max_retries: 0,
+        container_pool_size: 0,
         internal_ws_port: 0,
         docker_network: None,
         host_data_dir: None,
@@ -37,6 +45,24 @@ fn test_config() -> Config {
         container_token_ttl_secs: 3600,
         refresh_token_ttl_days: 30,
         cookie_secure: false,
+        tool_call_timeout_secs: 300,
+        max_file_size_bytes: 50 * 1024 * 1024,
+        workspace_max_bytes: 1024 * 1024 * 1024,
+        allowed_upload_mime_types: None,
+        token_usage_update_interval_tokens: 500,
+        container_exec_allowlist: vec!["df".into(), "du".into(), "ls".into(), "ps".into()],
+        max_login_attempts: 10,
+        login_lockout_secs: 900,
+        trusted_proxies: vec![],
+        require_email_verification: false,
+        smtp_url: None,
+        mail_from: None,
+        oauth_client_id: None,
+        oauth_client_secret: None,
+        oauth_auth_url: None,
+        oauth_token_url: None,
+        max_conversations_per_user: None,
+        max_messages_per_conversation: None,
     }
 }
 
@@ -48,9 +74,16 @@ async fn test_state() -> Arc<AppState> {
     let docker_manager = Arc::new(DockerManager::new(config.clone(), registry));
     Arc::new(AppState {
         db: pool,
+        jwt_keys: JwtKeys::from_config(&config).unwrap(),
         config,
         ws_state,
+        sse_state: SseState::new(),
+        pending_uploads: Default::default(),
         docker_manager,
+        provider_api: Arc::new(HttpProviderApiClient::default()),
+        mailer: None,
+        system_stats_cache: Default::default(),
+        features: Default::default(),
     })
 }
 
@@ -133,7 +166,7 @@ async fn register_user2(state: &Arc<AppState>) -> String {
 }
 
 fn token_user_id(state: &Arc<AppState>, token: &str) -> String {
-    claude_chat_backend::auth::verify_access_token(token, &state.config.jwt_secret)
+    claude_chat_backend::auth::verify_access_token(token, &state.jwt_keys)
         .unwrap()
         .sub
 }
@@ -333,6 +366,36 @@ async fn get_shared_conversation_returns_safe_fields() {
     assert!(body.get("provider").is_none());
 }
 
+#[tokio::test]
+async fn get_shared_conversation_includes_model_info_without_secrets() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token).await;
+
+    let resp = app(state.clone())
+        .oneshot(authed_post(
+            &format!("/api/conversations/{}/share", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    let share_body = json_body(resp).await;
+    let share_token = share_body["share_token"].as_str().unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(unauthed_get(&format!("/api/shared/{}", share_token)))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["model_name"], "gpt-4o");
+    assert_eq!(body["provider_display_name"], "openai");
+    assert_eq!(body["message_count"], 0);
+    for field in ["id", "provider_id", "api_key_encrypted", "share_token"] {
+        assert!(body.get(field).is_none(), "{field} leaked");
+    }
+}
+
 #[tokio::test]
 async fn get_shared_conversation_invalid_token_returns_404() {
     let state = test_state().await;
@@ -411,3 +474,47 @@ async fn revoked_share_returns_404() {
         .unwrap();
     assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 }
+
+#[tokio::test]
+async fn sharing_returns_503_when_sharing_disabled() {
+    let mut state = test_state().await;
+    let token = register_user(&state).await;
+    let conv_id = create_conv(&state, &token).await;
+    let resp = app(state.clone())
+        .oneshot(authed_post(
+            &format!("/api/conversations/{}/share", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    let share_token = json_body(resp).await["share_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    Arc::get_mut(&mut state).unwrap().features.enable_sharing = false;
+
+    let resp = app(state.clone())
+        .oneshot(authed_post(
+            &format!("/api/conversations/{}/share", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let resp = app(state.clone())
+        .oneshot(unauthed_get(&format!("/api/shared/{share_token}")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // Existing links can still be revoked while sharing is off.
+    let resp = app(state.clone())
+        .oneshot(authed_delete(
+            &format!("/api/conversations/{}/share", conv_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+}
@@ -5,25 +5,36 @@ use axum::{
 };
 use claude_chat_backend::{
     api,
-    auth::middleware::AppState,
+    auth::{JwtKeys, middleware::AppState},
     config::Config,
     db,
     docker::{manager::DockerManager, registry::ContainerRegistry},
-    ws::WsState,
+    provider_api::{HttpProviderApiClient, ProviderApiClient, ProviderApiError},
+    ws::{WsState, sse::SseState},
 };
+use futures_util::future::BoxFuture;
 use http_body_util::BodyExt;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 fn test_config() -> Config {
     Config {
         database_url: "sqlite::memory:".into(),
         jwt_secret: "test-jwt-secret-that-is-long-enough-for-hmac".into(),
+        jwt_algorithm: Default::default(),
+        jwt_private_key_path: None,
+        jwt_public_key_path: None,
         encryption_key: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".into(),
         host: "127.0.0.1".into(),
         port: 0,
         container_image: "test:latest".into(),
         container_idle_timeout_secs: 600,
+        container_cpu_quota: None,
+        container_memory_bytes: None,
+        container_start_max_retries: 0,
+        container_pool_size: 0,
         internal_ws_port: 0,
         docker_network: None,
         host_data_dir: None,
@@ -33,10 +44,32 @@ fn test_config() -> Config {
         container_token_ttl_secs: 3600,
         refresh_token_ttl_days: 30,
         cookie_secure: false,
+        tool_call_timeout_secs: 300,
+        max_file_size_bytes: 50 * 1024 * 1024,
+        workspace_max_bytes: 1024 * 1024 * 1024,
+        allowed_upload_mime_types: None,
+        token_usage_update_interval_tokens: 500,
+        container_exec_allowlist: vec!["df".into(), "du".into(), "ls".into(), "ps".into()],
+        max_login_attempts: 10,
+        login_lockout_secs: 900,
+        trusted_proxies: vec![],
+        require_email_verification: false,
+        smtp_url: None,
+        mail_from: None,
+        oauth_client_id: None,
+        oauth_client_secret: None,
+        oauth_auth_url: None,
+        oauth_token_url: None,
+        max_conversations_per_user: None,
+        max_messages_per_conversation: None,
     }
 }
 
 async fn test_state() -> Arc<AppState> {
+    test_state_with_provider_api(Arc::new(HttpProviderApiClient::default())).await
+}
+
+async fn test_state_with_provider_api(provider_api: Arc<dyn ProviderApiClient>) -> Arc<AppState> {
     let config = test_config();
     let pool = db::init_db("sqlite::memory:").await;
     let ws_state = WsState::new();
@@ -44,12 +77,45 @@ async fn test_state() -> Arc<AppState> {
     let docker_manager = Arc::new(DockerManager::new(config.clone(), registry));
     Arc::new(AppState {
         db: pool,
+        jwt_keys: JwtKeys::from_config(&config).unwrap(),
         config,
         ws_state,
+        sse_state: SseState::new(),
+        pending_uploads: Default::default(),
         docker_manager,
+        provider_api,
+        mailer: None,
+        system_stats_cache: Default::default(),
+        features: Default::default(),
     })
 }
 
+/// Answers `list_models` with `models`, or with `error` when set, and
+/// records the API key it was called with.
+#[derive(Default)]
+struct MockProviderApi {
+    models: Vec<String>,
+    error: Option<StatusCode>,
+    seen_keys: Mutex<Vec<String>>,
+}
+
+impl ProviderApiClient for MockProviderApi {
+    fn list_models<'a>(
+        &'a self,
+        _provider_type: &'a str,
+        _endpoint_url: Option<&'a str>,
+        api_key: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, ProviderApiError>> {
+        Box::pin(async move {
+            self.seen_keys.lock().unwrap().push(api_key.to_string());
+            match self.error {
+                Some(status) => Err(ProviderApiError::Rejected(status)),
+                None => Ok(self.models.clone()),
+            }
+        })
+    }
+}
+
 fn app(state: Arc<AppState>) -> Router {
     Router::new()
         .nest("/api/auth", api::auth::router())
@@ -122,6 +188,14 @@ async fn register_user(state: &Arc<AppState>) -> String {
     body["access_token"].as_str().unwrap().to_string()
 }
 
+async fn provider_owner(state: &Arc<AppState>) -> String {
+    db::users::get_user_by_username(&state.db, "testuser")
+        .await
+        .unwrap()
+        .unwrap()
+        .id
+}
+
 async fn create_provider(state: &Arc<AppState>, token: &str, body: &str) -> serde_json::Value {
     let resp = app(state.clone())
         .oneshot(post_with_auth("/api/users/me/providers", body, token))
@@ -252,6 +326,94 @@ async fn upsert_provider_keeps_existing_default_when_is_default_not_provided() {
     assert_eq!(body["is_default"], true);
 }
 
+#[tokio::test]
+async fn upsert_provider_sets_keeps_and_clears_fallback() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+
+    let backup = create_provider(
+        &state,
+        &token,
+        r#"{"name":"Backup","provider_type":"anthropic","api_key":"k2","models":["claude-x"]}"#,
+    )
+    .await;
+    let backup_id = backup["id"].as_str().unwrap();
+    let primary = create_provider(
+        &state,
+        &token,
+        &format!(
+            r#"{{"name":"Primary","provider_type":"openai","api_key":"k1",
+                "models":["gpt-4o"],"fallback_provider_id":"{backup_id}"}}"#
+        ),
+    )
+    .await;
+    let primary_id = primary["id"].as_str().unwrap();
+    assert_eq!(primary["fallback_provider_id"], backup_id);
+
+    // Omitting the field keeps the fallback.
+    let kept = create_provider(
+        &state,
+        &token,
+        &format!(
+            r#"{{"id":"{primary_id}","name":"Primary","provider_type":"openai",
+                "api_key":"__KEEP_EXISTING__","models":["gpt-4o"]}}"#
+        ),
+    )
+    .await;
+    assert_eq!(kept["fallback_provider_id"], backup_id);
+
+    let list = json_body(
+        app(state.clone())
+            .oneshot(get_with_auth("/api/users/me/providers", &token))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let listed = list
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|p| p["id"] == primary_id)
+        .unwrap();
+    assert_eq!(listed["fallback_provider_id"], backup_id);
+
+    for (fallback, expected) in [
+        (primary_id, "A provider cannot be its own fallback"),
+        ("missing", "Fallback provider does not exist"),
+    ] {
+        let resp = app(state.clone())
+            .oneshot(post_with_auth(
+                "/api/users/me/providers",
+                &format!(
+                    r#"{{"id":"{primary_id}","name":"Primary","provider_type":"openai",
+                        "api_key":"__KEEP_EXISTING__","models":["gpt-4o"],
+                        "fallback_provider_id":"{fallback}"}}"#
+                ),
+                &token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(
+            json_body(resp).await["message"]
+                .as_str()
+                .unwrap()
+                .contains(expected)
+        );
+    }
+
+    let cleared = create_provider(
+        &state,
+        &token,
+        &format!(
+            r#"{{"id":"{primary_id}","name":"Primary","provider_type":"openai",
+                "api_key":"__KEEP_EXISTING__","models":["gpt-4o"],"fallback_provider_id":""}}"#
+        ),
+    )
+    .await;
+    assert!(cleared["fallback_provider_id"].is_null());
+}
+
 #[tokio::test]
 async fn get_and_update_model_defaults_roundtrip() {
     let state = test_state().await;
@@ -505,3 +667,727 @@ async fn provider_update_prunes_invalid_model_defaults() {
     assert!(body["image_provider_id"].is_null());
     assert!(body["image_model"].is_null());
 }
+
+fn patch_with_auth(uri: &str, body: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("PATCH")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+async fn register_with_refresh(state: &Arc<AppState>) -> (String, String) {
+    let resp = app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"testuser","email":"test@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    (
+        body["access_token"].as_str().unwrap().to_string(),
+        body["refresh_token"].as_str().unwrap().to_string(),
+    )
+}
+
+#[tokio::test]
+async fn change_password_invalidates_refresh_tokens() {
+    let state = test_state().await;
+    let (token, refresh_token) = register_with_refresh(&state).await;
+
+    let resp = app(state.clone())
+        .oneshot(patch_with_auth(
+            "/api/users/me/password",
+            r#"{"current_password":"password123","new_password":"new-password-456"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/refresh",
+            &format!(r#"{{"refresh_token":"{}"}}"#, refresh_token),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // The current access token keeps working until it expires
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/users/me", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // The user must log in again, and only the new password works
+    let resp = app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/login",
+            r#"{"username":"testuser","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/login",
+            r#"{"username":"testuser","password":"new-password-456"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    let new_refresh = body["refresh_token"].as_str().unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/refresh",
+            &format!(r#"{{"refresh_token":"{}"}}"#, new_refresh),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn change_password_rejects_wrong_current_password() {
+    let state = test_state().await;
+    let (token, refresh_token) = register_with_refresh(&state).await;
+
+    let resp = app(state.clone())
+        .oneshot(patch_with_auth(
+            "/api/users/me/password",
+            r#"{"current_password":"wrong-password","new_password":"new-password-456"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // Sessions are untouched when the change is rejected
+    let resp = app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/refresh",
+            &format!(r#"{{"refresh_token":"{}"}}"#, refresh_token),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn change_password_rejects_short_new_password() {
+    let state = test_state().await;
+    let (token, _) = register_with_refresh(&state).await;
+
+    let resp = app(state.clone())
+        .oneshot(patch_with_auth(
+            "/api/users/me/password",
+            r#"{"current_password":"password123","new_password":"short"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn list_provider_conversations_returns_refs_for_all_roles() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let provider = create_provider(
+        &state,
+        &token,
+        r#"{"name":"Target","provider_type":"openai","api_key":"k1","models":["gpt-4o"],"image_models":["img-1"],"is_default":false}"#,
+    )
+    .await;
+    let provider_id = provider["id"].as_str().unwrap();
+    let user_id = claude_chat_backend::auth::verify_access_token(&token, &state.jwt_keys)
+        .unwrap()
+        .sub;
+
+    let mut expected = Vec::new();
+    for (title, chat, subagent, image) in [
+        ("main", Some(provider_id), Some("other"), None),
+        ("subagent", Some("other"), Some(provider_id), None),
+        ("image", Some("other"), Some("other"), Some(provider_id)),
+    ] {
+        let conv = db::conversations::create_conversation_with_subagent(
+            &state.db,
+            &user_id,
+            title,
+            None,
+            chat,
+            Some("m"),
+            subagent,
+            Some("m"),
+            false,
+            image,
+            image.map(|_| "img-1"),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        expected.push(conv.id);
+    }
+    db::conversations::create_conversation(
+        &state.db,
+        &user_id,
+        "unrelated",
+        None,
+        Some("other"),
+        Some("m"),
+        false,
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            &format!("/api/users/me/providers/{}/conversations", provider_id),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    let refs = body.as_array().unwrap();
+    assert_eq!(refs.len(), 3);
+    let mut ids: Vec<String> = refs
+        .iter()
+        .map(|r| r["id"].as_str().unwrap().to_string())
+        .collect();
+    ids.sort();
+    expected.sort();
+    assert_eq!(ids, expected);
+    for r in refs {
+        assert!(r["title"].is_string());
+        assert!(r["updated_at"].is_string());
+        assert!(r.get("system_prompt_override").is_none());
+    }
+}
+
+#[tokio::test]
+async fn list_provider_conversations_unknown_provider_returns_404() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth(
+            "/api/users/me/providers/missing/conversations",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn upsert_provider_rejects_invalid_endpoint_url() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/users/me/providers",
+            r#"{
+                "name":"Bad URL",
+                "provider_type":"openai",
+                "api_key":"k1",
+                "endpoint_url":"api.example.com/v1",
+                "models":["gpt-4o"],
+                "is_default": false
+            }"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(resp).await;
+    assert!(body["message"].as_str().unwrap().contains("endpoint_url"));
+}
+
+#[tokio::test]
+async fn upsert_provider_accepts_https_and_empty_endpoint_url() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+
+    let body = create_provider(
+        &state,
+        &token,
+        r#"{"name":"Custom","provider_type":"openai","api_key":"k1","endpoint_url":"https://proxy.example.com/v1","models":["gpt-4o"],"is_default":false}"#,
+    )
+    .await;
+    assert_eq!(body["endpoint_url"], "https://proxy.example.com/v1");
+
+    create_provider(
+        &state,
+        &token,
+        r#"{"name":"Default URL","provider_type":"openai","api_key":"k1","endpoint_url":"","models":["gpt-4o"],"is_default":false}"#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn list_providers_includes_conversation_counts() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let used = create_provider(
+        &state,
+        &token,
+        r#"{"name":"Used","provider_type":"openai","api_key":"k1","models":["gpt-4o"],"is_default":false}"#,
+    )
+    .await;
+    let unused = create_provider(
+        &state,
+        &token,
+        r#"{"name":"Unused","provider_type":"google","api_key":"k1","models":["gemini"],"is_default":false}"#,
+    )
+    .await;
+    let used_id = used["id"].as_str().unwrap();
+    let user_id = claude_chat_backend::auth::verify_access_token(&token, &state.jwt_keys)
+        .unwrap()
+        .sub;
+
+    for title in ["one", "two"] {
+        db::conversations::create_conversation(
+            &state.db,
+            &user_id,
+            title,
+            None,
+            Some(used_id),
+            Some("gpt-4o"),
+            false,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/users/me/providers", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    let providers = body.as_array().unwrap();
+    assert_eq!(providers.len(), 2);
+    let count = |id: &serde_json::Value| {
+        providers.iter().find(|p| p["id"] == *id).unwrap()["conversation_count"]
+            .as_i64()
+            .unwrap()
+    };
+    assert_eq!(count(&used["id"]), 2);
+    assert_eq!(count(&unused["id"]), 0);
+}
+
+#[tokio::test]
+async fn reorder_providers_changes_list_order() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let mut ids = Vec::new();
+    for name in ["First", "Second", "Third"] {
+        let body = create_provider(
+            &state,
+            &token,
+            &format!(
+                r#"{{"name":"{name}","provider_type":"openai","api_key":"k1","models":["gpt-4o"],"is_default":false}}"#
+            ),
+        )
+        .await;
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+
+    let order = serde_json::json!({"provider_ids": [ids[2], ids[0], ids[1]]});
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(
+            "/api/users/me/providers/order",
+            &order.to_string(),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/users/me/providers", &token))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    let names: Vec<&str> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["Third", "First", "Second"]);
+}
+
+#[tokio::test]
+async fn reorder_providers_rejects_missing_ids() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let body = create_provider(
+        &state,
+        &token,
+        r#"{"name":"Only","provider_type":"openai","api_key":"k1","models":["gpt-4o"],"is_default":false}"#,
+    )
+    .await;
+    create_provider(
+        &state,
+        &token,
+        r#"{"name":"Other","provider_type":"openai","api_key":"k1","models":["gpt-4o"],"is_default":false}"#,
+    )
+    .await;
+
+    let order = serde_json::json!({"provider_ids": [body["id"], "not-mine"]});
+    let resp = app(state.clone())
+        .oneshot(put_with_auth(
+            "/api/users/me/providers/order",
+            &order.to_string(),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+fn get_with_api_key(uri: &str, key: &str) -> Request<Body> {
+    Request::builder()
+        .method("GET")
+        .uri(uri)
+        .header("x-api-key", key)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn api_key_create_use_and_revoke() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            "/api/users/api-keys",
+            r#"{"name":"ci"}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created = json_body(resp).await;
+    let key = created["key"].as_str().unwrap().to_string();
+    let key_id = created["id"].as_str().unwrap().to_string();
+    assert_eq!(created["name"], "ci");
+
+    // The key authenticates on its own, without a JWT.
+    let resp = app(state.clone())
+        .oneshot(get_with_api_key("/api/users/me", &key))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["username"], "testuser");
+
+    // Listing never exposes key values.
+    let resp = app(state.clone())
+        .oneshot(get_with_auth("/api/users/api-keys", &token))
+        .await
+        .unwrap();
+    let listed = json_body(resp).await;
+    let listed = listed.as_array().unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["id"], key_id.as_str());
+    assert!(listed[0].get("key").is_none());
+    assert!(listed[0].get("key_hash").is_none());
+
+    let resp = app(state.clone())
+        .oneshot(delete_with_auth(
+            &format!("/api/users/api-keys/{key_id}"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = app(state.clone())
+        .oneshot(get_with_api_key("/api/users/me", &key))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = app(state)
+        .oneshot(delete_with_auth(
+            &format!("/api/users/api-keys/{key_id}"),
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn api_key_rejects_unknown_key() {
+    let state = test_state().await;
+    let resp = app(state)
+        .oneshot(get_with_api_key("/api/users/me", "not-a-real-key"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn create_api_key_rejects_empty_name() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let resp = app(state)
+        .oneshot(post_with_auth(
+            "/api/users/api-keys",
+            r#"{"name":""}"#,
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn user_stats_for_new_user_are_zero() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let resp = app(state)
+        .oneshot(get_with_auth("/api/users/me/stats", &token))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["conversation_count"], 0);
+    assert_eq!(body["message_count"], 0);
+    assert_eq!(body["total_tokens"], 0);
+}
+
+async fn create_mock_provider(
+    state: &Arc<AppState>,
+    token: &str,
+    provider_type: &str,
+    endpoint_url: &str,
+) -> String {
+    let body = create_provider(
+        state,
+        token,
+        &serde_json::json!({
+            "name": format!("mock {provider_type}"),
+            "provider_type": provider_type,
+            "api_key": "sk-test-key",
+            "endpoint_url": endpoint_url,
+            "models": ["test-model"],
+        })
+        .to_string(),
+    )
+    .await;
+    body["id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn validate_provider_key_accepts_working_key() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .and(header("authorization", "Bearer sk-test-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"data": []})))
+        .expect(1)
+        .mount(&mock)
+        .await;
+    let id = create_mock_provider(&state, &token, "openai", &format!("{}/v1", mock.uri())).await;
+
+    let resp = app(state)
+        .oneshot(post_with_auth(
+            &format!("/api/users/me/providers/{id}/validate"),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["valid"], true);
+    assert!(body["latency_ms"].is_u64());
+}
+
+#[tokio::test]
+async fn validate_provider_key_reports_rejected_key() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(header("x-api-key", "sk-test-key"))
+        .and(body_partial_json(
+            serde_json::json!({"model": "test-model", "max_tokens": 1}),
+        ))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(1)
+        .mount(&mock)
+        .await;
+    let id = create_mock_provider(&state, &token, "anthropic", &mock.uri()).await;
+
+    let resp = app(state)
+        .oneshot(post_with_auth(
+            &format!("/api/users/me/providers/{id}/validate"),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["valid"], false);
+    let error = body["error"].as_str().unwrap();
+    assert!(error.contains("401"));
+    assert!(!error.contains("sk-test-key"));
+}
+
+#[tokio::test]
+async fn validate_provider_key_requires_ownership() {
+    let state = test_state().await;
+    let token = register_user(&state).await;
+    let id = create_mock_provider(&state, &token, "openai", "http://127.0.0.1:1").await;
+
+    let resp = app(state.clone())
+        .oneshot(post_json(
+            "/api/auth/register",
+            r#"{"username":"other","email":"other@example.com","password":"password123"}"#,
+        ))
+        .await
+        .unwrap();
+    let other_token = json_body(resp).await["access_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let resp = app(state)
+        .oneshot(post_with_auth(
+            &format!("/api/users/me/providers/{id}/validate"),
+            "",
+            &other_token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn refresh_models_replaces_provider_model_list() {
+    let mock = Arc::new(MockProviderApi {
+        models: vec!["gpt-4.1".into(), "gpt-4o".into()],
+        ..Default::default()
+    });
+    let state = test_state_with_provider_api(mock.clone()).await;
+    let token = register_user(&state).await;
+    let provider = create_provider(
+        &state,
+        &token,
+        r#"{"name":"OpenAI","provider_type":"openai","api_key":"sk-live","models":["gpt-4o","gpt-3.5-turbo"]}"#,
+    )
+    .await;
+    let id = provider["id"].as_str().unwrap();
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            &format!("/api/users/me/providers/{id}/refresh-models"),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(resp).await,
+        serde_json::json!(["gpt-4.1", "gpt-4o"])
+    );
+    assert_eq!(*mock.seen_keys.lock().unwrap(), ["sk-live"]);
+
+    let stored = db::providers::list_providers(&state.db, &provider_owner(&state).await)
+        .await
+        .unwrap();
+    assert_eq!(stored[0].models.as_deref(), Some(r#"["gpt-4.1","gpt-4o"]"#));
+    // The previous primary model is still offered, so it is kept.
+    assert_eq!(stored[0].model_name.as_deref(), Some("gpt-4o"));
+}
+
+#[tokio::test]
+async fn refresh_models_surfaces_provider_errors() {
+    let state = test_state_with_provider_api(Arc::new(MockProviderApi {
+        error: Some(StatusCode::UNAUTHORIZED),
+        ..Default::default()
+    }))
+    .await;
+    let token = register_user(&state).await;
+    let provider = create_provider(
+        &state,
+        &token,
+        r#"{"name":"OpenAI","provider_type":"openai","api_key":"sk-bad","models":["gpt-4o"]}"#,
+    )
+    .await;
+
+    let resp = app(state.clone())
+        .oneshot(post_with_auth(
+            &format!(
+                "/api/users/me/providers/{}/refresh-models",
+                provider["id"].as_str().unwrap()
+            ),
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+    let stored = db::providers::list_providers(&state.db, &provider_owner(&state).await)
+        .await
+        .unwrap();
+    assert_eq!(stored[0].models.as_deref(), Some(r#"["gpt-4o"]"#));
+
+    let resp = app(state)
+        .oneshot(post_with_auth(
+            "/api/users/me/providers/missing/refresh-models",
+            "",
+            &token,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn http_provider_client_lists_openai_models() {
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .and(header("authorization", "Bearer sk-test-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "object": "list",
+            "data": [{"id": "gpt-4o-mini"}, {"id": "gpt-4o"}],
+        })))
+        .mount(&mock)
+        .await;
+
+    let models = HttpProviderApiClient::default()
+        .list_models("openai", Some(&format!("{}/v1", mock.uri())), "sk-test-key")
+        .await
+        .unwrap();
+    assert_eq!(models, ["gpt-4o", "gpt-4o-mini"]);
+}
@@ -41,6 +41,10 @@ export async function deleteProvider(id: string): Promise<void> {
   await client.delete(`/users/me/providers/${encodeURIComponent(id)}`)
 }
 
+export async function reorderProviders(providerIds: string[]): Promise<void> {
+  await client.put('/users/me/providers/order', { provider_ids: providerIds })
+}
+
 export async function listMcpServers(): Promise<McpServer[]> {
   const { data } = await client.get<McpServer[]>('/mcp-servers')
   return data
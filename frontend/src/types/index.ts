@@ -3,6 +3,7 @@ export interface User {
   username: string
   email: string
   is_admin: boolean
+  email_verified?: boolean
 }
 
 export interface AuthResponse {
@@ -27,11 +28,21 @@ export interface Conversation {
   image_provider_id: string | null
   image_model: string | null
   share_token: string | null
+  pinned?: boolean
+  last_container_error?: string | null
+  archived_at?: string | null
+  container_idle_timeout_secs?: number | null
+  tags?: string[]
+  last_message_preview?: string | null
+  last_message_at?: string | null
+  message_count?: number
 }
 
 export interface SharedConversation {
   title: string
   model_name: string | null
+  provider_display_name?: string | null
+  message_count?: number
   created_at: string
   updated_at: string
 }
@@ -45,6 +56,7 @@ export interface Message {
   tool_call_id: string | null
   token_count: number | null
   created_at: string
+  reactions?: Record<string, number>
 }
 
 export interface MessagePart {
@@ -69,6 +81,7 @@ export interface ProviderConfig {
   image_models: string[]
   is_default: boolean
   has_api_key: boolean
+  conversation_count?: number
 }
 
 export interface ModelDefaults {
@@ -192,6 +205,7 @@ export type WsMessageEvent =
       event_type: 'assistant_delta' | 'thinking_delta' | 'tool_call' | 'tool_result' | 'complete' | 'error' | string
       payload: Record<string, unknown>
     }
+  | { type: 'token_usage_update'; total_tokens: number; completion_tokens: number }
   | { type: 'complete'; message_id: string; content: string; tool_calls?: unknown[] }
   | { type: 'error'; message: string; code?: string }
   | { type: 'container_status'; status: string; reason?: string; message?: string }
@@ -0,0 +1,2 @@
+-- Allow refresh tokens to be revoked in bulk (e.g. on password change) without deleting them.
+ALTER TABLE refresh_tokens ADD COLUMN used INTEGER NOT NULL DEFAULT 0;
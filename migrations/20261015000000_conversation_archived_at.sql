@@ -0,0 +1,2 @@
+-- Soft-archive flag for conversations; NULL means the conversation is active.
+ALTER TABLE conversations ADD COLUMN archived_at TEXT;
@@ -0,0 +1,2 @@
+-- Admins can deactivate accounts; inactive users cannot log in or refresh tokens.
+ALTER TABLE users ADD COLUMN is_active INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+-- Pinned conversations are listed before all others.
+ALTER TABLE conversations ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Most recent container init failure, kept so clients can show it after a reload.
+ALTER TABLE conversations ADD COLUMN last_container_error TEXT;
@@ -0,0 +1,8 @@
+-- User-chosen display order for providers; existing rows keep creation order.
+ALTER TABLE user_providers ADD COLUMN display_order INTEGER NOT NULL DEFAULT 0;
+UPDATE user_providers SET display_order = (
+    SELECT COUNT(*) FROM user_providers AS p
+    WHERE p.user_id = user_providers.user_id
+      AND (p.created_at < user_providers.created_at
+           OR (p.created_at = user_providers.created_at AND p.rowid < user_providers.rowid))
+);
@@ -0,0 +1,4 @@
+-- Group rotated refresh tokens by login so a replayed token can revoke its whole chain.
+ALTER TABLE refresh_tokens ADD COLUMN family_id TEXT NOT NULL DEFAULT '';
+UPDATE refresh_tokens SET family_id = id WHERE family_id = '';
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_family_id ON refresh_tokens(family_id);
@@ -0,0 +1,3 @@
+-- Remember which client a refresh token was issued to so users can review their sessions.
+ALTER TABLE refresh_tokens ADD COLUMN user_agent TEXT;
+ALTER TABLE refresh_tokens ADD COLUMN ip_address TEXT;
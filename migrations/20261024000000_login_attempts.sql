@@ -0,0 +1,9 @@
+-- Failed login counters per (ip, username) for temporary account lockout.
+CREATE TABLE IF NOT EXISTS login_attempts (
+    ip TEXT NOT NULL,
+    username TEXT NOT NULL,
+    attempt_count INTEGER NOT NULL DEFAULT 0,
+    last_attempt_at TEXT NOT NULL,
+    locked_until TEXT,
+    PRIMARY KEY (ip, username)
+);
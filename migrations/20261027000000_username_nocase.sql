@@ -0,0 +1,11 @@
+-- Usernames are now stored lower-case and matched case-insensitively.
+-- Rows whose lower-case form would collide with another user keep their
+-- original spelling so the migration cannot fail on existing data.
+UPDATE users SET username = lower(username)
+WHERE username <> lower(username)
+  AND NOT EXISTS (
+      SELECT 1 FROM users other
+      WHERE other.id <> users.id AND lower(other.username) = lower(users.username)
+  );
+
+CREATE INDEX IF NOT EXISTS idx_users_username_nocase ON users(username COLLATE NOCASE);
@@ -0,0 +1,3 @@
+-- Soft-deleted messages keep their rows until purged so they can be restored.
+ALTER TABLE messages ADD COLUMN deleted_at TEXT;
+ALTER TABLE messages_v2 ADD COLUMN deleted_at TEXT;
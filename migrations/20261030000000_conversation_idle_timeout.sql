@@ -0,0 +1,3 @@
+-- Per-conversation override of the global container idle timeout.
+-- NULL uses the global default; 0 disables idle cleanup for the conversation.
+ALTER TABLE conversations ADD COLUMN container_idle_timeout_secs INTEGER;
@@ -0,0 +1,8 @@
+-- User-defined labels for organizing conversations.
+CREATE TABLE IF NOT EXISTS conversation_tags (
+    conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (conversation_id, tag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_conversation_tags_tag ON conversation_tags(tag);
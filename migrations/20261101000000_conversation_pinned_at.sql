@@ -0,0 +1,4 @@
+-- When a conversation was pinned; NULL while unpinned. Kept in sync with
+-- the `pinned` flag and used to sort pinned conversations first.
+ALTER TABLE conversations ADD COLUMN pinned_at TEXT;
+UPDATE conversations SET pinned_at = updated_at WHERE pinned = 1;
@@ -0,0 +1,10 @@
+-- Per-user overrides of admin-defined MCP servers. NULL columns fall back to
+-- the global server config.
+CREATE TABLE IF NOT EXISTS user_mcp_server_overrides (
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    mcp_server_id TEXT NOT NULL REFERENCES mcp_servers(id) ON DELETE CASCADE,
+    is_enabled INTEGER,
+    read_only_overrides TEXT,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (user_id, mcp_server_id)
+);
@@ -0,0 +1,19 @@
+-- Admin-managed per-model token prices, in USD per 1000 tokens.
+CREATE TABLE IF NOT EXISTS provider_pricing (
+    provider_type TEXT NOT NULL,
+    model TEXT NOT NULL,
+    input_cost_per_1k REAL NOT NULL,
+    output_cost_per_1k REAL NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (provider_type, model)
+);
+
+-- Token usage and cost of each assistant message. cost_usd is NULL when no
+-- pricing was configured for the model at the time.
+CREATE TABLE IF NOT EXISTS message_costs (
+    message_id TEXT PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+    input_tokens INTEGER NOT NULL,
+    output_tokens INTEGER NOT NULL,
+    cost_usd REAL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
@@ -0,0 +1,3 @@
+-- Provider to switch a conversation to when its provider fails to initialize.
+ALTER TABLE user_providers ADD COLUMN fallback_provider_id TEXT
+    REFERENCES user_providers(id) ON DELETE SET NULL;
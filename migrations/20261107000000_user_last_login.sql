@@ -0,0 +1,2 @@
+-- When the user last signed in (password or OAuth); NULL if never.
+ALTER TABLE users ADD COLUMN last_login_at TEXT;
@@ -0,0 +1,9 @@
+-- Per-user resource limits set by admins. A NULL column falls back to the
+-- server-wide default from config.
+CREATE TABLE user_quotas (
+    user_id TEXT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    max_conversations INTEGER,
+    max_messages_per_conversation INTEGER,
+    max_workspace_bytes BIGINT,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
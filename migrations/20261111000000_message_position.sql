@@ -0,0 +1,19 @@
+-- Explicit message order within a conversation. Order used to be the
+-- implicit rowid, which is not stable for a table with a TEXT primary key.
+ALTER TABLE messages ADD COLUMN position INTEGER;
+
+UPDATE messages SET position = rowid;
+
+CREATE UNIQUE INDEX idx_messages_conversation_position
+    ON messages(conversation_id, position);
+
+-- Inserts without an explicit position go after the conversation's last message.
+CREATE TRIGGER messages_position_insert AFTER INSERT ON messages
+WHEN new.position IS NULL BEGIN
+    UPDATE messages
+    SET position = (
+        SELECT COALESCE(MAX(position), 0) + 1 FROM messages
+        WHERE conversation_id = new.conversation_id
+    )
+    WHERE id = new.id;
+END;
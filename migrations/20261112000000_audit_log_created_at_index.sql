@@ -0,0 +1,2 @@
+-- Audit log pages are keyed on (created_at, id), newest first.
+CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log(created_at, id);